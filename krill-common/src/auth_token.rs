@@ -5,10 +5,22 @@ use tai64::Tai64N;
 
 #[cfg(feature = "random")]
 use {
-    crate::{Holder, KrillError, KrillResult, RandomBytes},
+    crate::{Holder, KrillError, KrillResult, RandomBytes, UnversionedHolder},
     core::fmt,
 };
 
+/// The state of the second factor for a session
+#[derive(Debug, Hash, Default, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Encode, Decode)]
+pub enum SecondFactorState {
+    #[default]
+    NotRequired,
+    EnrollmentRequired,
+    VerificationRequired,
+    Verified {
+        timestamp: [u8; Tai64N::BYTE_SIZE],
+    },
+}
+
 #[cfg(feature = "random")]
 #[derive(Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Encode, Decode)]
 pub struct AuthTokenDetails {
@@ -16,6 +28,32 @@ pub struct AuthTokenDetails {
     timestamp: [u8; Tai64N::BYTE_SIZE],
     expiry: Duration,
     retry: Duration,
+    second_factor: SecondFactorState,
+}
+
+/// [AuthTokenDetails] as stored before the second factor,
+/// only used to migrate the values of older storages
+#[cfg(feature = "random")]
+#[derive(Debug, Clone, Encode, Decode)]
+pub struct UnversionedAuthTokenDetails {
+    holder: UnversionedHolder,
+    timestamp: [u8; Tai64N::BYTE_SIZE],
+    expiry: Duration,
+    retry: Duration,
+}
+
+/// No role required a second factor before the TOTP policy existed
+#[cfg(feature = "random")]
+impl From<UnversionedAuthTokenDetails> for AuthTokenDetails {
+    fn from(details: UnversionedAuthTokenDetails) -> Self {
+        Self {
+            holder: details.holder.into(),
+            timestamp: details.timestamp,
+            expiry: details.expiry,
+            retry: details.retry,
+            second_factor: SecondFactorState::NotRequired,
+        }
+    }
 }
 
 #[cfg(feature = "random")]
@@ -34,6 +72,45 @@ impl AuthTokenDetails {
             timestamp: now.to_bytes(),
             expiry: Duration::from_hours(24),
             retry: Duration::from_secs(30),
+            second_factor: SecondFactorState::default(),
+        }
+    }
+
+    pub fn set_second_factor(mut self, second_factor: SecondFactorState) -> Self {
+        self.second_factor = second_factor;
+
+        self
+    }
+
+    pub fn set_second_factor_verified(mut self) -> Self {
+        self.second_factor = SecondFactorState::Verified {
+            timestamp: Tai64N::now().to_bytes(),
+        };
+
+        self
+    }
+
+    pub fn second_factor(&self) -> SecondFactorState {
+        self.second_factor
+    }
+
+    /// Sessions waiting for TOTP enrollment or verification can only access the second factor routes
+    pub fn is_second_factor_satisfied(&self) -> bool {
+        matches!(
+            self.second_factor,
+            SecondFactorState::NotRequired | SecondFactorState::Verified { .. }
+        )
+    }
+
+    /// Used to require a fresh TOTP code before sensitive operations
+    pub fn second_factor_verified_within(&self, duration: Duration) -> bool {
+        match self.second_factor {
+            SecondFactorState::Verified { timestamp } => {
+                let verified_at = Self::to_tai64_timestamp(timestamp);
+
+                verified_at != Tai64N::UNIX_EPOCH && Tai64N::now() <= verified_at + duration
+            }
+            _ => false,
         }
     }
 
@@ -190,6 +267,7 @@ impl fmt::Debug for AuthTokenDetails {
             .field("timestamp", &self.timestamp_formatted())
             .field("expiry", &humantime::format_duration(self.expiry()))
             .field("retry", &humantime::format_duration(self.retry()))
+            .field("second_factor", &self.second_factor)
             .finish()
     }
 }
@@ -202,6 +280,7 @@ impl fmt::Display for AuthTokenDetails {
             .field("timestamp", &self.timestamp_formatted())
             .field("expiry", &humantime::format_duration(self.expiry()))
             .field("retry", &humantime::format_duration(self.retry()))
+            .field("second_factor", &self.second_factor)
             .finish()
     }
 }
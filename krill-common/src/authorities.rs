@@ -0,0 +1,66 @@
+use bitcode::{Decode, Encode};
+
+use crate::{Holder, KrillError, KrillResult};
+
+/// The authorities chosen in the setup wizard and how many of them must sign
+#[derive(Debug, Default, PartialEq, Eq, Clone, Encode, Decode)]
+pub struct AuthoritySetup {
+    authorities: Vec<String>,
+    threshold: u16,
+}
+
+impl AuthoritySetup {
+    /// Email addresses are compared case insensitively, empty entries are ignored
+    pub fn new(authorities: &[String], threshold: u16) -> KrillResult<Self> {
+        let mut normalized = Vec::<String>::new();
+
+        for email in authorities
+            .iter()
+            .map(|email| email.trim().to_lowercase())
+            .filter(|email| !email.is_empty())
+        {
+            Holder::new_with_tld(&email)?;
+
+            if normalized.contains(&email) {
+                return Err(KrillError::InvalidAuthoritySetup(format!(
+                    "`{email}` is listed more than once"
+                )));
+            }
+
+            normalized.push(email);
+        }
+
+        if normalized.is_empty() {
+            return Err(KrillError::InvalidAuthoritySetup(
+                "At least one authority is required".to_string(),
+            ));
+        }
+
+        if threshold == 0 || threshold as usize > normalized.len() {
+            return Err(KrillError::InvalidAuthoritySetup(format!(
+                "The threshold must be between 1 and {}",
+                normalized.len()
+            )));
+        }
+
+        Ok(Self {
+            authorities: normalized,
+            threshold,
+        })
+    }
+
+    pub fn authorities(&self) -> &[String] {
+        self.authorities.as_slice()
+    }
+
+    /// The number of authorities that must verify before initialization completes
+    pub fn threshold(&self) -> u16 {
+        self.threshold
+    }
+
+    pub fn is_authority(&self, email: &str) -> bool {
+        let email = email.trim().to_lowercase();
+
+        self.authorities.contains(&email)
+    }
+}
@@ -216,6 +216,19 @@ impl<'a> BrandColors<'a> {
         self
     }
 
+    pub fn set_background_light(&mut self, hex_color: &'a str) -> &mut Self {
+        self.background_light = hex_color;
+
+        self
+    }
+
+    /// Colors are `#RGB` or `#RRGGBB` hex values
+    pub fn is_hex_color(value: &str) -> bool {
+        value.strip_prefix('#').is_some_and(|digits| {
+            matches!(digits.len(), 3 | 6) && digits.chars().all(|digit| digit.is_ascii_hexdigit())
+        })
+    }
+
     pub fn primary(&self) -> &str {
         self.primary
     }
@@ -0,0 +1,527 @@
+use core::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::KrillError;
+
+/// A stable machine readable code for a [KrillError] variant.
+/// Codes are grouped by subsystem and are never reused once assigned:
+/// `1xxx` server configuration, `2xxx` storage, `3xxx` FROST, `4xxx` authentication,
+/// `5xxx` languages and UI, `6xxx` mail, `7xxx` network, `8xxx` request data and `9xxx` cryptography.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy, Serialize, Deserialize)]
+pub struct KrillErrorCode(pub u16);
+
+impl KrillErrorCode {
+    pub const PREFIX: &str = "KRILL-";
+
+    pub fn parse(value: &str) -> Option<Self> {
+        value
+            .strip_prefix(Self::PREFIX)
+            .filter(|code| code.len() == 4)
+            .and_then(|code| code.parse::<u16>().ok())
+            .map(Self)
+    }
+
+    pub fn translation_key(&self) -> String {
+        format!("error_{}", self.0)
+    }
+}
+
+impl fmt::Display for KrillErrorCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}{:04}", Self::PREFIX, self.0)
+    }
+}
+
+/// The class of an error which determines the HTTP status and
+/// whether the details of the error can be shown to the client
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy, Serialize, Deserialize)]
+pub enum KrillErrorCategory {
+    Validation,
+    Authentication,
+    Authorization,
+    NotFound,
+    Conflict,
+    Upstream,
+    Configuration,
+    Storage,
+    Internal,
+}
+
+impl KrillErrorCategory {
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::Validation => "validation",
+            Self::Authentication => "authentication",
+            Self::Authorization => "authorization",
+            Self::NotFound => "not_found",
+            Self::Conflict => "conflict",
+            Self::Upstream => "upstream",
+            Self::Configuration => "configuration",
+            Self::Storage => "storage",
+            Self::Internal => "internal",
+        }
+    }
+
+    pub fn http_status(&self) -> u16 {
+        match self {
+            Self::Validation => 400,
+            Self::Authentication => 401,
+            Self::Authorization => 403,
+            Self::NotFound => 404,
+            Self::Conflict => 409,
+            Self::Upstream => 502,
+            Self::Configuration | Self::Storage | Self::Internal => 500,
+        }
+    }
+
+    /// Errors in these categories can contain file paths, connection URIs
+    /// or API keys so only the code is sent to the client
+    pub fn is_redacted(&self) -> bool {
+        matches!(
+            self,
+            Self::Upstream | Self::Configuration | Self::Storage | Self::Internal
+        )
+    }
+}
+
+impl KrillError {
+    pub const REDACTED_MESSAGE: &str = "Internal server error";
+
+    pub fn code(&self) -> KrillErrorCode {
+        KrillErrorCode(self.descriptor().0)
+    }
+
+    pub fn category(&self) -> KrillErrorCategory {
+        self.descriptor().1
+    }
+
+    pub fn http_status(&self) -> u16 {
+        self.category().http_status()
+    }
+
+    pub fn is_redacted(&self) -> bool {
+        self.category().is_redacted()
+    }
+
+    /// The message that can be shown to the client
+    pub fn public_message(&self) -> String {
+        if self.is_redacted() {
+            Self::REDACTED_MESSAGE.to_string()
+        } else {
+            self.to_string()
+        }
+    }
+
+    pub fn translation_key(&self) -> String {
+        self.code().translation_key()
+    }
+
+    pub fn to_error_body(&self) -> KrillErrorBody {
+        KrillErrorBody {
+            code: self.code().to_string(),
+            category: self.category(),
+            status: self.http_status(),
+            message: self.public_message(),
+            translation_key: self.translation_key(),
+        }
+    }
+
+    // Every variant must be listed so that adding a variant without a code fails to compile
+    fn descriptor(&self) -> (u16, KrillErrorCategory) {
+        match self {
+            Self::SetOrgInfoStaticAlreadySet => (1001, KrillErrorCategory::Configuration),
+            Self::ServerOrgInfoNotSet => (1002, KrillErrorCategory::Configuration),
+            Self::ServerSecretNotFound => (1003, KrillErrorCategory::Configuration),
+            Self::UnableToSetServerSecret => (1004, KrillErrorCategory::Configuration),
+            Self::InvalidLanguageEntry(_) => (5001, KrillErrorCategory::Validation),
+            Self::LanguageNotValidBcp47Code(_) => (5002, KrillErrorCategory::Validation),
+            Self::LanguageTranslationNotFound(_) => (5003, KrillErrorCategory::NotFound),
+            #[cfg(feature = "home-dir")]
+            Self::UnableToFindHomeDirectory => (1005, KrillErrorCategory::Configuration),
+            #[cfg(feature = "home-dir")]
+            Self::HomeDirPathNotUtf8 => (1006, KrillErrorCategory::Configuration),
+            #[cfg(feature = "storage")]
+            Self::GlobalStorageNotInitialized => (2001, KrillErrorCategory::Storage),
+            #[cfg(feature = "storage")]
+            Self::MinimumSignersGreaterThanMaximumSigners => (3001, KrillErrorCategory::Validation),
+            #[cfg(feature = "storage")]
+            Self::IdentifierDerivationNotSupported => (3003, KrillErrorCategory::Internal),
+            #[cfg(feature = "storage")]
+            Self::IdentifierAlreadyExists => (3004, KrillErrorCategory::Conflict),
+            #[cfg(feature = "storage")]
+            Self::FrostIdentifierNotFound => (3005, KrillErrorCategory::NotFound),
+            #[cfg(feature = "storage")]
+            Self::Round1SecretNotFound => (3006, KrillErrorCategory::NotFound),
+            #[cfg(feature = "storage")]
+            Self::Part1PublicPackageNotFound => (3007, KrillErrorCategory::NotFound),
+            #[cfg(feature = "storage")]
+            Self::Part2SecretNotFound => (3008, KrillErrorCategory::NotFound),
+            #[cfg(feature = "storage")]
+            Self::Round1NoncesNotFound => (3101, KrillErrorCategory::NotFound),
+            #[cfg(feature = "storage")]
+            Self::Round1CommitmentsNotFound => (3102, KrillErrorCategory::NotFound),
+            #[cfg(feature = "storage")]
+            Self::ThereMustBeAtLeast2Signers => (3002, KrillErrorCategory::Validation),
+            #[cfg(feature = "storage")]
+            Self::DkgStateAlreadyFinalized => (3009, KrillErrorCategory::Conflict),
+            #[cfg(feature = "storage")]
+            Self::InvalidDkgState(_) => (3010, KrillErrorCategory::Conflict),
+            #[cfg(feature = "storage")]
+            Self::Part1KeyGenerationError(_) => (3011, KrillErrorCategory::Internal),
+            #[cfg(feature = "storage")]
+            Self::Part1MaximumPartiesReached => (3012, KrillErrorCategory::Conflict),
+            #[cfg(feature = "storage")]
+            Self::Part2MaximumPartiesReached => (3013, KrillErrorCategory::Conflict),
+            #[cfg(feature = "storage")]
+            Self::InvalidFrostDkgState(_) => (3014, KrillErrorCategory::Conflict),
+            #[cfg(feature = "storage")]
+            Self::Part2KeyGenerationError(_) => (3015, KrillErrorCategory::Internal),
+            #[cfg(feature = "storage")]
+            Self::Part3Finalize(_) => (3016, KrillErrorCategory::Internal),
+            #[cfg(feature = "storage")]
+            Self::ToByteArray(_) => (3017, KrillErrorCategory::Internal),
+            #[cfg(feature = "storage")]
+            Self::DeserializePublicPackage(_) => (3018, KrillErrorCategory::Validation),
+            #[cfg(feature = "storage")]
+            Self::MessageToSignNotFound => (3103, KrillErrorCategory::NotFound),
+            #[cfg(feature = "storage")]
+            Self::SigningPackageNotFound => (3104, KrillErrorCategory::NotFound),
+            #[cfg(feature = "storage")]
+            Self::SignatureShareNotFound => (3105, KrillErrorCategory::NotFound),
+            #[cfg(feature = "storage")]
+            Self::AggregateSignatureNotFound => (3106, KrillErrorCategory::NotFound),
+            #[cfg(feature = "storage")]
+            Self::InvalidMessageToSign => (3107, KrillErrorCategory::Validation),
+            #[cfg(feature = "storage")]
+            Self::SigningRound1(_) => (3108, KrillErrorCategory::Internal),
+            #[cfg(feature = "storage")]
+            Self::SigningRound2(_) => (3109, KrillErrorCategory::Internal),
+            #[cfg(feature = "storage")]
+            Self::InvalidParticipants(_) => (3110, KrillErrorCategory::Validation),
+            #[cfg(feature = "storage")]
+            Self::ExpectedRound1SigningState { .. } => (3111, KrillErrorCategory::Conflict),
+            #[cfg(feature = "storage")]
+            Self::ExpectedRound2SigningState { .. } => (3112, KrillErrorCategory::Conflict),
+            #[cfg(feature = "storage")]
+            Self::ExpectedAggregateSigningState { .. } => (3113, KrillErrorCategory::Conflict),
+            #[cfg(feature = "storage")]
+            Self::InvalidParticipant { .. } => (3114, KrillErrorCategory::Authorization),
+            #[cfg(feature = "storage")]
+            Self::SigningRound1NoncesAndCommitmentsNotFound => (3115, KrillErrorCategory::NotFound),
+            #[cfg(feature = "storage")]
+            Self::UnableToAggregateSignature(_) => (3116, KrillErrorCategory::Internal),
+            #[cfg(feature = "storage")]
+            Self::UnableToRemoveValidSignedParticipantMessage => {
+                (2014, KrillErrorCategory::Storage)
+            }
+            #[cfg(feature = "storage")]
+            Self::InvalidAggregateSignature(_) => (3117, KrillErrorCategory::Validation),
+            #[cfg(feature = "storage")]
+            Self::UnableToDeserializeFrostDkgData => (2015, KrillErrorCategory::Storage),
+            #[cfg(any(feature = "storage", feature = "fs"))]
+            Self::Io(_) => (2003, KrillErrorCategory::Storage),
+            #[cfg(feature = "storage")]
+            Self::Store(_) => (2004, KrillErrorCategory::Storage),
+            #[cfg(feature = "storage")]
+            Self::FrostKeypairKeyspaceNotFound(_) => (2013, KrillErrorCategory::Storage),
+            #[cfg(feature = "storage")]
+            Self::FrostKeypairDataNotFound => (3019, KrillErrorCategory::NotFound),
+            #[cfg(feature = "storage")]
+            Self::UnableToDeserializeFrostKeypairData => (2016, KrillErrorCategory::Storage),
+            #[cfg(feature = "storage")]
+            Self::CoordinatorDataNotFound => (3118, KrillErrorCategory::NotFound),
+            #[cfg(feature = "storage")]
+            Self::UnableToDeserializeCoordinatorMessages => (2017, KrillErrorCategory::Storage),
+            #[cfg(feature = "storage")]
+            Self::ParticipantMessagesDataNotFound => (3119, KrillErrorCategory::NotFound),
+            #[cfg(feature = "storage")]
+            Self::UnableToDeserializeParticipantMessages => (2018, KrillErrorCategory::Storage),
+            #[cfg(feature = "storage")]
+            Self::SignedMessagesDataNotFound => (3120, KrillErrorCategory::NotFound),
+            #[cfg(feature = "storage")]
+            Self::UnableToDeserializeSignedMessages => (2019, KrillErrorCategory::Storage),
+            #[cfg(feature = "storage")]
+            Self::UnableToDeserializeFrostIdentifier => (2020, KrillErrorCategory::Storage),
+            #[cfg(feature = "storage")]
+            Self::UnableToSerializeFrostDkgRound1SecretPackage => {
+                (2021, KrillErrorCategory::Storage)
+            }
+            #[cfg(feature = "storage")]
+            Self::UnableToDeserializeFrostDkgRound1SecretPackage => {
+                (2022, KrillErrorCategory::Storage)
+            }
+            #[cfg(feature = "storage")]
+            Self::UnableToSerializeFrostDkgRound1PublicPackage => {
+                (2023, KrillErrorCategory::Storage)
+            }
+            #[cfg(feature = "storage")]
+            Self::UnableToDeserializeFrostDkgRound1PublicPackage => {
+                (2024, KrillErrorCategory::Storage)
+            }
+            #[cfg(feature = "storage")]
+            Self::UnableToSerializeFrostDkgRound2SecretPackage => {
+                (2025, KrillErrorCategory::Storage)
+            }
+            #[cfg(feature = "storage")]
+            Self::UnableToDeserializeFrostDkgRound2SecretPackage => {
+                (2026, KrillErrorCategory::Storage)
+            }
+            #[cfg(feature = "storage")]
+            Self::UnableToSerializeFrostDkgRound2PublicPackage => {
+                (2027, KrillErrorCategory::Storage)
+            }
+            #[cfg(feature = "storage")]
+            Self::UnableToDeserializeFrostDkgRound2PublicPackage => {
+                (2028, KrillErrorCategory::Storage)
+            }
+            #[cfg(feature = "storage")]
+            Self::UnableToSerializeFrostSigningKeyPackage => (2029, KrillErrorCategory::Storage),
+            #[cfg(feature = "storage")]
+            Self::UnableToDeserializeFrostSigningKeyPackage => (2030, KrillErrorCategory::Storage),
+            #[cfg(feature = "storage")]
+            Self::UnableToSerializeFrostSigningPublicKeyPackage => {
+                (2031, KrillErrorCategory::Storage)
+            }
+            #[cfg(feature = "storage")]
+            Self::UnableToDeserializeFrostSigningPublicKeyPackage => {
+                (2032, KrillErrorCategory::Storage)
+            }
+            #[cfg(feature = "storage")]
+            Self::UnableToSerializeFrostSigningNonces => (2033, KrillErrorCategory::Storage),
+            #[cfg(feature = "storage")]
+            Self::UnableToDeserializeFrostSigningNonces => (2034, KrillErrorCategory::Storage),
+            #[cfg(feature = "storage")]
+            Self::UnableToSerializeFrostSigningCommitments => (2035, KrillErrorCategory::Storage),
+            #[cfg(feature = "storage")]
+            Self::UnableToDeserializeFrostSigningCommitments => (2036, KrillErrorCategory::Storage),
+            #[cfg(feature = "storage")]
+            Self::UnableToSerializeFrostSigningPackage => (2037, KrillErrorCategory::Storage),
+            #[cfg(feature = "storage")]
+            Self::UnableToDeserializeFrostSigningPackage => (2038, KrillErrorCategory::Storage),
+            #[cfg(feature = "storage")]
+            Self::UnableToSerializeFrostSignature => (2039, KrillErrorCategory::Storage),
+            #[cfg(feature = "storage")]
+            Self::UnableToDeserializeFrostSignatureShare => (2040, KrillErrorCategory::Storage),
+            #[cfg(feature = "storage")]
+            Self::UnableToDeserializeFrostSignature => (2041, KrillErrorCategory::Storage),
+            #[cfg(feature = "storage")]
+            Self::UnableToDeserializeParticipantMessageData => (2042, KrillErrorCategory::Storage),
+            #[cfg(feature = "storage")]
+            Self::UnableToDeserializeCoordinatorDataNotFound => (2043, KrillErrorCategory::Storage),
+            #[cfg(feature = "storage")]
+            Self::UnableToDeserializeSignedMessagesDataNotFound => {
+                (2044, KrillErrorCategory::Storage)
+            }
+            #[cfg(feature = "storage")]
+            Self::UnableToDeserializeSupportedLanguages => (2007, KrillErrorCategory::Storage),
+            #[cfg(feature = "storage")]
+            Self::GlobalStorageInitializeError => (2002, KrillErrorCategory::Storage),
+            Self::UnableToSetAppState => (1007, KrillErrorCategory::Configuration),
+            Self::AppStateMachineNotInitialized => (1008, KrillErrorCategory::Configuration),
+            Self::Transmit(_) => (7001, KrillErrorCategory::Upstream),
+            #[cfg(feature = "storage")]
+            Self::UnableToGetOrganizationInfo => (2008, KrillErrorCategory::Storage),
+            #[cfg(feature = "storage")]
+            Self::UnableToGetSupportedLanguages => (2009, KrillErrorCategory::Storage),
+            Self::Mailer(_) => (6001, KrillErrorCategory::Configuration),
+            Self::MailDelivery(_) => (6004, KrillErrorCategory::Upstream),
+            Self::EmailTemplate(_) => (6005, KrillErrorCategory::Internal),
+            Self::MailNotQueued(_) => (6006, KrillErrorCategory::NotFound),
+            Self::Dkim(_) => (6007, KrillErrorCategory::Configuration),
+            Self::UnableToParseTranslationFromJson5 => (5004, KrillErrorCategory::Internal),
+            Self::FatalUi(_) => (5006, KrillErrorCategory::Internal),
+            Self::InvalidLanguageTranslationPath(_) => (5005, KrillErrorCategory::Internal),
+            #[cfg(feature = "storage")]
+            Self::SmtpsStaticAlreadyInitialized => (6003, KrillErrorCategory::Configuration),
+            Self::HttpClient(_) => (7002, KrillErrorCategory::Upstream),
+            Self::HttpResponse(_) => (7003, KrillErrorCategory::Upstream),
+            Self::SolanaRpc { .. } => (7005, KrillErrorCategory::Upstream),
+            Self::InvalidRequestData(_) => (8001, KrillErrorCategory::Validation),
+            Self::InvalidEmailAddress(_) => (8002, KrillErrorCategory::Validation),
+            Self::MalformedSolanaTransaction(_) => (8003, KrillErrorCategory::Validation),
+            Self::InvalidSolanaTransactionSignature(_) => (8004, KrillErrorCategory::Validation),
+            Self::InvalidInstructionData(_) => (8005, KrillErrorCategory::Validation),
+            Self::Statics(_) => (1009, KrillErrorCategory::Configuration),
+            Self::Smtps(_) => (6002, KrillErrorCategory::Configuration),
+            Self::InvalidAuthToken => (4001, KrillErrorCategory::Authentication),
+            Self::AuthTokenExpired => (4002, KrillErrorCategory::Authentication),
+            Self::Forbidden => (4003, KrillErrorCategory::Authorization),
+            Self::SiwsMalformedMessage(_) => (4101, KrillErrorCategory::Validation),
+            Self::SiwsInvalidAddress => (4102, KrillErrorCategory::Validation),
+            Self::SiwsInvalidSignature => (4103, KrillErrorCategory::Authentication),
+            Self::SiwsDomainMismatch => (4104, KrillErrorCategory::Authentication),
+            Self::SiwsNonceNotFound => (4105, KrillErrorCategory::Authentication),
+            Self::SiwsMessageExpired => (4106, KrillErrorCategory::Authentication),
+            Self::SiwsMessageNotYetValid => (4107, KrillErrorCategory::Authentication),
+            Self::SiwsWalletNotLinked => (4108, KrillErrorCategory::Authorization),
+            Self::EncryptionFailed => (9001, KrillErrorCategory::Internal),
+            Self::DecryptionFailed => (9002, KrillErrorCategory::Internal),
+            Self::InvalidTotpSecret => (4206, KrillErrorCategory::Internal),
+            Self::InvalidTotpCode => (4201, KrillErrorCategory::Authentication),
+            Self::TotpNotEnrolled => (4202, KrillErrorCategory::Conflict),
+            Self::TotpAlreadyEnrolled => (4203, KrillErrorCategory::Conflict),
+            Self::TotpRequired => (4204, KrillErrorCategory::Authorization),
+            Self::TotpStepUpRequired => (4205, KrillErrorCategory::Authorization),
+            Self::TotpLocked => (4207, KrillErrorCategory::Authentication),
+            Self::MemberNotFound(_) => (4301, KrillErrorCategory::NotFound),
+            Self::MemberAlreadyExists(_) => (4302, KrillErrorCategory::Conflict),
+            Self::InvalidMemberInvitation => (4303, KrillErrorCategory::Authentication),
+            Self::MemberInvitationExpired => (4304, KrillErrorCategory::Authentication),
+            Self::MemberSuspended => (4305, KrillErrorCategory::Authorization),
+            Self::InvalidAuthoritySetup(_) => (4306, KrillErrorCategory::Validation),
+            Self::WalletAlreadyLinked(_) => (4307, KrillErrorCategory::Conflict),
+            Self::MemberNotActive => (4308, KrillErrorCategory::Authorization),
+            Self::InvalidOrgSettings(_) => (1010, KrillErrorCategory::Validation),
+            Self::OrgSettingsRevisionNotFound(_) => (1011, KrillErrorCategory::NotFound),
+            Self::InvalidTenant(_) => (1012, KrillErrorCategory::Validation),
+            Self::TenantAlreadyExists(_) => (1013, KrillErrorCategory::Conflict),
+            Self::TenantNotFound(_) => (1014, KrillErrorCategory::NotFound),
+            Self::InvalidConfigFile(_) => (1015, KrillErrorCategory::Validation),
+            Self::InvalidSmtpConfig(_) => (1016, KrillErrorCategory::Validation),
+            Self::InvalidMailApiConfig(_) => (1017, KrillErrorCategory::Validation),
+            Self::InvalidTokenCategoryPreset(_) => (1018, KrillErrorCategory::Validation),
+            #[cfg(feature = "storage")]
+            Self::StoredValueNotVersioned => (2045, KrillErrorCategory::Storage),
+            #[cfg(feature = "storage")]
+            Self::UnsupportedSchemaVersion { .. } => (2046, KrillErrorCategory::Storage),
+            #[cfg(feature = "storage")]
+            Self::MigrationFailed { .. } => (2047, KrillErrorCategory::Storage),
+            #[cfg(feature = "storage")]
+            Self::StorageLocked => (2048, KrillErrorCategory::Storage),
+            #[cfg(feature = "storage")]
+            Self::InvalidUnlockSecret => (2049, KrillErrorCategory::Authentication),
+            Self::KeyDerivationFailed => (9003, KrillErrorCategory::Internal),
+            #[cfg(feature = "storage")]
+            Self::BackupIntegrity(_) => (2050, KrillErrorCategory::Storage),
+            #[cfg(feature = "storage")]
+            Self::InvalidBackupKey => (2051, KrillErrorCategory::Validation),
+            #[cfg(feature = "storage")]
+            Self::InvalidBackupKeyShares(_) => (2052, KrillErrorCategory::Validation),
+            #[cfg(feature = "storage")]
+            Self::BackupRestoreTargetNotEmpty(_) => (2053, KrillErrorCategory::Conflict),
+            #[cfg(feature = "storage")]
+            Self::BackupNotConfigured => (2054, KrillErrorCategory::NotFound),
+            #[cfg(feature = "storage")]
+            Self::InvalidBackupDestination(_) => (2055, KrillErrorCategory::Validation),
+            #[cfg(feature = "storage")]
+            Self::BackupDestination(_) => (7004, KrillErrorCategory::Upstream),
+            #[cfg(feature = "storage")]
+            Self::AuditLogTampered { .. } => (2056, KrillErrorCategory::Storage),
+            #[cfg(feature = "storage")]
+            Self::TableValueCorrupted(_) => (2057, KrillErrorCategory::Storage),
+            #[cfg(feature = "storage")]
+            Self::UnknownKeyspace(_) => (9004, KrillErrorCategory::Internal),
+            #[cfg(feature = "storage")]
+            Self::InvalidStorageOptions(_) => (2058, KrillErrorCategory::Validation),
+            #[cfg(feature = "storage")]
+            Self::BackupSourceInUse(_) => (2059, KrillErrorCategory::Conflict),
+        }
+    }
+}
+
+/// The JSON body of errors returned by the HTTP API
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+pub struct KrillErrorBody {
+    pub code: String,
+    pub category: KrillErrorCategory,
+    pub status: u16,
+    pub message: String,
+    pub translation_key: String,
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn error_codes() {
+        let error = KrillError::InvalidTotpCode;
+        let code = error.code();
+
+        assert_eq!(code.to_string(), "KRILL-4201");
+        assert_eq!(KrillErrorCode::parse(&code.to_string()), Some(code));
+        assert_eq!(KrillErrorCode::parse("KRILL-42"), None);
+        assert_eq!(KrillErrorCode::parse("4201"), None);
+
+        let body = error.to_error_body();
+        assert_eq!(body.status, 401);
+        assert_eq!(body.message, error.to_string());
+        assert_eq!(body.translation_key, "error_4201");
+
+        let redacted = KrillError::HttpClient("https://rpc.example/?api-key=secret".to_string());
+        assert!(redacted.is_redacted());
+        assert_eq!(redacted.http_status(), 502);
+        assert_eq!(redacted.public_message(), KrillError::REDACTED_MESSAGE);
+        assert!(!redacted.to_error_body().message.contains("secret"));
+    }
+
+    /// The codes assigned in [KrillError::descriptor], read from the source so variants
+    /// behind features are included
+    fn assigned_codes() -> Vec<u16> {
+        let source = include_str!("error_codes.rs");
+        let start = source.find("fn descriptor(").unwrap();
+        let end = start + source[start..].find("\n    }\n}").unwrap();
+
+        // Long arms are formatted as blocks over several lines
+        let mut arms = Vec::<String>::new();
+        for line in source[start..end].lines().map(str::trim) {
+            if line.starts_with("Self::") {
+                arms.push(String::new());
+            }
+            if let Some(arm) = arms.last_mut() {
+                arm.push_str(line);
+            }
+        }
+
+        let codes = arms
+            .iter()
+            .filter_map(|arm| {
+                let code = arm
+                    .split_once("=>")?
+                    .1
+                    .trim_start_matches(['{', '(', ' '])
+                    .split_once(',')?
+                    .0;
+
+                code.parse::<u16>().ok()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(codes.len(), arms.len(), "Every arm must assign a code");
+
+        codes
+    }
+
+    #[test]
+    fn codes_are_unique() {
+        let codes = assigned_codes();
+        assert!(codes.len() > 100);
+
+        let mut unique = std::collections::BTreeSet::new();
+        for code in codes {
+            assert!(
+                (1000..10_000).contains(&code),
+                "KRILL-{code} is not 4 digits"
+            );
+            assert!(unique.insert(code), "KRILL-{code:04} is assigned twice");
+        }
+    }
+
+    /// en-US is the language every other language falls back to
+    #[test]
+    fn codes_are_translated() {
+        let translations =
+            include_str!("../../krill-server/assets/translations/errors/en-US.bcp47");
+
+        let keys = translations
+            .lines()
+            .filter_map(|line| line.split_once('=').map(|(key, _)| key.trim()))
+            .collect::<std::collections::BTreeSet<_>>();
+
+        for code in assigned_codes() {
+            let key = KrillErrorCode(code).translation_key();
+
+            assert!(keys.contains(key.as_str()), "`{key}` is not translated");
+        }
+    }
+}
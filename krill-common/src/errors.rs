@@ -4,8 +4,6 @@ use crate::{KrillUtils, Message32ByteHash};
 
 pub type KrillResult<T> = Result<T, KrillError>;
 
-// TODO add codes for to_string()
-
 #[derive(Debug, PartialEq, Eq, Clone, thiserror::Error)]
 pub enum KrillError {
     #[error("The `SERVER_ORG_INFO` static is already set")]
@@ -266,12 +264,6 @@ pub enum KrillError {
     #[error("Unable to deserialize bytes into SignedMessagesDataNotFound")]
     UnableToDeserializeSignedMessagesDataNotFound,
     #[cfg(feature = "storage")]
-    #[error("Unable to deserialize branding data. It's data is corrupted!")]
-    UnableToDeserializeBrandingData,
-    #[cfg(feature = "storage")]
-    #[error("Unable to deserialize AppState data. It's data is corrupted!")]
-    UnableToDeserializeAppStateData,
-    #[cfg(feature = "storage")]
     #[error("Unable to deserialize Supported Languages bytes. It's data is corrupted!")]
     UnableToDeserializeSupportedLanguages,
     #[error("Unable to initialize the global storage static `KRILL_STORAGE`")]
@@ -289,16 +281,16 @@ pub enum KrillError {
     #[cfg(feature = "storage")]
     #[error("Unable to get the supported languages")]
     UnableToGetSupportedLanguages,
-    #[error("Unable to decode Solana API KEY bytes stored in the database")]
-    UnableToDecodeSolanaApiKey,
-    #[error("Unable to decode the organization's domain name")]
-    UnableToOrgDomainName,
     #[error("Mail service configuration error. Check your SMTP configuration. Error: `{0}`!")]
     Mailer(String),
-    #[error("Unable to decode SMTPs bytes stored in the database")]
-    UnableToDecodeSmtps,
     #[error("Unable to delivery email. Error: `{0}`!")]
     MailDelivery(String),
+    #[error("Unable to render the email template. {0}")]
+    EmailTemplate(String),
+    #[error("No failed email `{0}` is queued for delivery")]
+    MailNotQueued(String),
+    #[error("Invalid DKIM settings. {0}")]
+    Dkim(String),
     #[error("Unable to translate language information from JSON file provided")]
     UnableToParseTranslationFromJson5,
     #[error("{0}")]
@@ -312,16 +304,158 @@ pub enum KrillError {
     HttpClient(String),
     #[error("{0}")]
     HttpResponse(String),
+    #[error("The Solana RPC method `{method}` failed with the error `{code}`. {message}")]
+    SolanaRpc {
+        method: String,
+        code: i64,
+        message: String,
+        /// The `data` of the error as JSON, like the logs and the instruction error
+        /// of a transaction that failed its preflight simulation
+        data: Option<String>,
+    },
     #[error("The request data is invalid. `{0}`")]
     InvalidRequestData(String),
     #[error("Invalid Email Address. Error: `{0}`")]
     InvalidEmailAddress(String),
+    #[error("The Solana transaction is malformed. Error: `{0}`")]
+    MalformedSolanaTransaction(String),
+    #[error("The signature of `{0}` does not match the Solana transaction")]
+    InvalidSolanaTransactionSignature(String),
+    #[error("The instruction data is invalid. Error: `{0}`")]
+    InvalidInstructionData(String),
     #[error("{0}")]
     Statics(&'static str),
     #[error("{0:?}")]
     Smtps(Vec<String>),
     #[error("The auth token is invalid")]
     InvalidAuthToken,
+    #[error("The auth token has expired")]
+    AuthTokenExpired,
+    #[error("The account is not allowed to perform this operation")]
+    Forbidden,
+    #[error("The Sign In With Solana message is malformed. Error: `{0}`")]
+    SiwsMalformedMessage(String),
+    #[error("The Sign In With Solana address is not a valid Ed25519 public key")]
+    SiwsInvalidAddress,
+    #[error("The Sign In With Solana signature is invalid")]
+    SiwsInvalidSignature,
+    #[error("The domain in the Sign In With Solana message does not match the server domain")]
+    SiwsDomainMismatch,
+    #[error("The Sign In With Solana nonce is invalid, expired or already used")]
+    SiwsNonceNotFound,
+    #[error("The Sign In With Solana message has expired")]
+    SiwsMessageExpired,
+    #[error("The Sign In With Solana message is not valid yet")]
+    SiwsMessageNotYetValid,
+    #[error("The wallet is not linked to a member of the organization")]
+    SiwsWalletNotLinked,
+    #[error("Unable to encrypt the data")]
+    EncryptionFailed,
+    #[error("Unable to decrypt the data. The key is invalid or the data is corrupted")]
+    DecryptionFailed,
+    #[error("The TOTP secret is invalid")]
+    InvalidTotpSecret,
+    #[error("The TOTP code or recovery code is invalid")]
+    InvalidTotpCode,
+    #[error("TOTP is not enrolled for this account")]
+    TotpNotEnrolled,
+    #[error("TOTP is already enrolled for this account")]
+    TotpAlreadyEnrolled,
+    #[error("A second factor is required to access this resource")]
+    TotpRequired,
+    #[error("Enter a fresh TOTP code to perform this operation")]
+    TotpStepUpRequired,
+    #[error("Too many invalid TOTP codes, try again later")]
+    TotpLocked,
+    #[error("No member with the email address `{0}` exists")]
+    MemberNotFound(String),
+    #[error("A member with the email address `{0}` already exists")]
+    MemberAlreadyExists(String),
+    #[error("The member invitation is invalid or was already accepted")]
+    InvalidMemberInvitation,
+    #[error("The member invitation has expired")]
+    MemberInvitationExpired,
+    #[error("The member account is suspended")]
+    MemberSuspended,
+    #[error("The authorities are invalid. {0}")]
+    InvalidAuthoritySetup(String),
+    #[error("The wallet `{0}` is already linked to a member")]
+    WalletAlreadyLinked(String),
+    #[error("The member has not accepted the invitation yet")]
+    MemberNotActive,
+    #[error("The organization settings are invalid. {0}")]
+    InvalidOrgSettings(String),
+    #[error("Version `{0}` of the organization settings does not exist")]
+    OrgSettingsRevisionNotFound(u64),
+    #[error("The tenant is invalid. {0}")]
+    InvalidTenant(String),
+    #[error("A tenant with the identifier or domain `{0}` already exists")]
+    TenantAlreadyExists(String),
+    #[error("The tenant `{0}` does not exist")]
+    TenantNotFound(String),
+    #[error("The configuration file is invalid. {0}")]
+    InvalidConfigFile(String),
+    #[error("The SMTP settings are invalid. {0}")]
+    InvalidSmtpConfig(String),
+    #[error("The mail API settings are invalid. {0}")]
+    InvalidMailApiConfig(String),
+    #[error("The token category presets are invalid. {0}")]
+    InvalidTokenCategoryPreset(String),
+    #[cfg(feature = "storage")]
+    #[error("The stored value has no schema version. Run the storage migrations")]
+    StoredValueNotVersioned,
+    #[cfg(feature = "storage")]
+    #[error(
+        "The storage schema version `{found}` is not supported. Supported version is `{supported}`"
+    )]
+    UnsupportedSchemaVersion { found: u16, supported: u16 },
+    #[cfg(feature = "storage")]
+    #[error("Migrating the storage from schema version `{from}` failed. Error: `{reason}`")]
+    MigrationFailed { from: u16, reason: String },
+    #[cfg(feature = "storage")]
+    #[error("The storage is locked. Unlock it with the operator passphrase")]
+    StorageLocked,
+    #[cfg(feature = "storage")]
+    #[error("The passphrase or secret used to unlock the storage is invalid")]
+    InvalidUnlockSecret,
+    #[error("Unable to derive the encryption key from the passphrase")]
+    KeyDerivationFailed,
+    #[cfg(feature = "storage")]
+    #[error("The backup failed the integrity check. Error: `{0}`")]
+    BackupIntegrity(String),
+    #[cfg(feature = "storage")]
+    #[error("The backup key does not match the key the backup was encrypted with")]
+    InvalidBackupKey,
+    #[cfg(feature = "storage")]
+    #[error("The backup key shares are invalid. Error: `{0}`")]
+    InvalidBackupKeyShares(String),
+    #[cfg(feature = "storage")]
+    #[error("Backups can only be restored into an empty data directory. `{0}` is not empty")]
+    BackupRestoreTargetNotEmpty(String),
+    #[cfg(feature = "storage")]
+    #[error("Backups are not configured")]
+    BackupNotConfigured,
+    #[cfg(feature = "storage")]
+    #[error("The backup destination is invalid. Error: `{0}`")]
+    InvalidBackupDestination(String),
+    #[cfg(feature = "storage")]
+    #[error("The backup destination failed. Error: `{0}`")]
+    BackupDestination(String),
+    #[cfg(feature = "storage")]
+    #[error("The audit log was modified or entries were removed at entry `{sequence}`. Error: `{reason}`")]
+    AuditLogTampered { sequence: u64, reason: String },
+    #[cfg(feature = "storage")]
+    #[error("The value stored in `{0}` is corrupted")]
+    TableValueCorrupted(String),
+    #[cfg(feature = "storage")]
+    #[error("The storage has no keyspace named `{0}`")]
+    UnknownKeyspace(String),
+    #[cfg(feature = "storage")]
+    #[error("The storage options are invalid. Error: `{0}`")]
+    InvalidStorageOptions(String),
+    #[cfg(feature = "storage")]
+    #[error("The database at `{0}` is open in another process and cannot be backed up")]
+    BackupSourceInUse(String),
 }
 
 #[cfg(feature = "storage")]
@@ -14,7 +14,7 @@ pub enum UserRole {
 }
 
 impl UserRole {
-    pub fn as_str(&self) -> &str {
+    pub fn as_str(&self) -> &'static str {
         match self {
             Self::Superuser => "superuser",
             Self::Admin => "administrator",
@@ -29,6 +29,29 @@ pub struct Holder {
     email: String,
     role: UserRole,
     frost_id: Vec<u8>,
+    wallet: Option<String>,
+}
+
+/// [Holder] as stored before holders could sign in with a wallet,
+/// only used to migrate the values of older storages
+#[derive(Debug, Clone, Encode, Decode)]
+pub struct UnversionedHolder {
+    user_display: String,
+    email: String,
+    role: UserRole,
+    frost_id: Vec<u8>,
+}
+
+impl From<UnversionedHolder> for Holder {
+    fn from(holder: UnversionedHolder) -> Self {
+        Self {
+            user_display: holder.user_display,
+            email: holder.email,
+            role: holder.role,
+            frost_id: holder.frost_id,
+            wallet: None,
+        }
+    }
 }
 
 impl Holder {
@@ -59,6 +82,15 @@ impl Holder {
         })
     }
 
+    /// A member that authenticated with a Solana wallet and has no email address
+    pub fn new_with_wallet(address: &str) -> Self {
+        Self {
+            user_display: address.to_string(),
+            wallet: Some(address.to_string()),
+            ..Default::default()
+        }
+    }
+
     pub fn set_superuser(mut self) -> Self {
         self.role = UserRole::Superuser;
 
@@ -114,6 +146,23 @@ impl Holder {
         self.role
     }
 
+    pub fn wallet(&self) -> Option<&str> {
+        self.wallet.as_deref()
+    }
+
+    pub fn set_wallet(mut self, address: &str) -> Self {
+        self.wallet.replace(address.to_string());
+
+        self
+    }
+
+    /// The email address or the wallet address for holders that only authenticate with a wallet
+    pub fn identity(&self) -> &str {
+        self.wallet()
+            .filter(|_| self.email.is_empty())
+            .unwrap_or(self.email_address())
+    }
+
     pub fn email_envelope_details(&self) -> String {
         format!("{} <{}>", self.user_display(), self.email_address())
     }
@@ -121,6 +170,6 @@ impl Holder {
 
 impl fmt::Display for Holder {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}-{}", self.email_address(), self.role.as_str())
+        write!(f, "{}-{}", self.identity(), self.role.as_str())
     }
 }
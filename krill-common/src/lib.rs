@@ -1,6 +1,9 @@
 mod errors;
 pub use errors::*;
 
+mod error_codes;
+pub use error_codes::*;
+
 mod utils;
 pub use utils::*;
 
@@ -26,3 +29,23 @@ pub use auth_token::*;
 
 mod holder;
 pub use holder::*;
+
+mod authorities;
+pub use authorities::*;
+
+mod token_categories;
+pub use token_categories::*;
+
+mod smtp;
+pub use smtp::*;
+
+mod mail_provider;
+pub use mail_provider::*;
+
+mod siws;
+pub use siws::*;
+
+#[cfg(feature = "totp")]
+mod totp;
+#[cfg(feature = "totp")]
+pub use totp::*;
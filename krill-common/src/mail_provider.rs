@@ -0,0 +1,268 @@
+use bitcode::{Decode, Encode};
+use serde::{Deserialize, Serialize};
+
+use crate::{KrillError, KrillResult, SmtpConfig};
+
+/// How outgoing emails leave the server. Some hosting providers block the SMTP
+/// ports, so the emails can be posted to the HTTP API of a mail provider instead.
+#[derive(Debug, PartialEq, Eq, Clone, Encode, Decode, Serialize, Deserialize)]
+pub enum MailProvider {
+    Smtp(SmtpConfig),
+    Api(MailApiConfig),
+}
+
+impl Default for MailProvider {
+    fn default() -> Self {
+        Self::Smtp(SmtpConfig::default())
+    }
+}
+
+impl From<SmtpConfig> for MailProvider {
+    fn from(smtp: SmtpConfig) -> Self {
+        Self::Smtp(smtp)
+    }
+}
+
+impl From<MailApiConfig> for MailProvider {
+    fn from(api: MailApiConfig) -> Self {
+        Self::Api(api)
+    }
+}
+
+impl MailProvider {
+    pub const SMTP: &str = "smtp";
+    pub const API: &str = "api";
+
+    /// `smtp` or `api`
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::Smtp(_) => Self::SMTP,
+            Self::Api(_) => Self::API,
+        }
+    }
+
+    pub fn smtp(&self) -> Option<&SmtpConfig> {
+        match self {
+            Self::Smtp(smtp) => Some(smtp),
+            Self::Api(_) => None,
+        }
+    }
+
+    pub fn api(&self) -> Option<&MailApiConfig> {
+        match self {
+            Self::Smtp(_) => None,
+            Self::Api(api) => Some(api),
+        }
+    }
+
+    /// See [SmtpConfig::validate] and [MailApiConfig::validate]
+    pub fn validate(&self, local_transports: bool) -> KrillResult<()> {
+        match self {
+            Self::Smtp(smtp) => smtp.validate(local_transports),
+            Self::Api(api) => api.validate(),
+        }
+    }
+
+    pub fn trimmed(self) -> Self {
+        match self {
+            Self::Smtp(smtp) => Self::Smtp(smtp.trimmed()),
+            Self::Api(api) => Self::Api(api.trimmed()),
+        }
+    }
+
+    /// The settings without the password or the API key, safe to show in the settings
+    pub fn redacted(&self) -> Self {
+        match self {
+            Self::Smtp(smtp) => Self::Smtp(smtp.redacted()),
+            Self::Api(api) => Self::Api(api.redacted()),
+        }
+    }
+
+    /// Keeps the current secret when `self` was edited from [Self::redacted].
+    /// Switching between SMTP and an API requires entering the new secret.
+    pub fn keep_secret(self, current: &Self) -> Self {
+        match (self, current) {
+            (Self::Smtp(smtp), Self::Smtp(current)) => Self::Smtp(smtp.keep_password(current)),
+            (Self::Api(api), Self::Api(current)) => Self::Api(api.keep_api_key(current)),
+            (updated, _) => updated,
+        }
+    }
+
+    /// The SMTP server or the API endpoint emails are sent to
+    pub fn server(&self) -> String {
+        match self {
+            Self::Smtp(smtp) => smtp.server(),
+            Self::Api(api) => api.endpoint.clone(),
+        }
+    }
+}
+
+/// The HTTP API emails are posted to
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy, Encode, Decode, Serialize, Deserialize)]
+pub enum MailApiProvider {
+    /// Posts the email as JSON to any endpoint, with the signed MIME message in `raw`
+    #[default]
+    Webhook,
+    /// The `/email` endpoint of Postmark
+    Postmark,
+    /// The `/v3/mail/send` endpoint of SendGrid
+    Sendgrid,
+}
+
+impl MailApiProvider {
+    pub const ALL: [Self; 3] = [Self::Webhook, Self::Postmark, Self::Sendgrid];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Webhook => "webhook",
+            Self::Postmark => "postmark",
+            Self::Sendgrid => "sendgrid",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|provider| provider.as_str() == value)
+    }
+
+    /// Empty for webhooks, which have no well known endpoint
+    pub fn default_endpoint(&self) -> &'static str {
+        match self {
+            Self::Webhook => "",
+            Self::Postmark => "https://api.postmarkapp.com/email",
+            Self::Sendgrid => "https://api.sendgrid.com/v3/mail/send",
+        }
+    }
+
+    /// Webhooks may be left unauthenticated, for example behind a private network
+    pub fn requires_api_key(&self) -> bool {
+        !matches!(self, Self::Webhook)
+    }
+}
+
+/// The settings of a [MailApiProvider]
+#[derive(PartialEq, Eq, Clone, Encode, Decode, Serialize, Deserialize)]
+pub struct MailApiConfig {
+    pub provider: MailApiProvider,
+    pub endpoint: String,
+    /// The server token of Postmark, the API key of SendGrid or the bearer token of a webhook
+    pub api_key: String,
+    /// Seconds a request may take before it is retried
+    pub timeout_secs: u16,
+}
+
+impl Default for MailApiConfig {
+    fn default() -> Self {
+        Self {
+            provider: MailApiProvider::default(),
+            endpoint: String::default(),
+            api_key: String::default(),
+            timeout_secs: Self::DEFAULT_TIMEOUT_SECS,
+        }
+    }
+}
+
+impl std::fmt::Debug for MailApiConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MailApiConfig")
+            .field("provider", &self.provider)
+            .field("endpoint", &self.endpoint)
+            .field("api_key", &"Redacted")
+            .field("timeout_secs", &self.timeout_secs)
+            .finish()
+    }
+}
+
+impl MailApiConfig {
+    pub const DEFAULT_TIMEOUT_SECS: u16 = 30;
+    pub const MAX_TIMEOUT_SECS: u16 = 300;
+
+    /// Posts to the default endpoint of the provider
+    pub fn new(provider: MailApiProvider, api_key: &str) -> Self {
+        Self {
+            provider,
+            endpoint: provider.default_endpoint().to_string(),
+            api_key: api_key.to_string(),
+            ..Default::default()
+        }
+    }
+
+    pub fn set_endpoint(mut self, endpoint: &str) -> Self {
+        self.endpoint = endpoint.trim().to_string();
+
+        self
+    }
+
+    /// Rejects endpoints that are not `https://`. Plain `http://` is only accepted
+    /// on the loopback interface, for a relay running next to the server.
+    pub fn validate(&self) -> KrillResult<()> {
+        let invalid = |reason: &str| Err(KrillError::InvalidMailApiConfig(reason.to_string()));
+
+        let Ok(endpoint) = url::Url::parse(self.endpoint.trim()) else {
+            return invalid("The endpoint must be a URL like `https://mail.example.com/send`");
+        };
+
+        let is_loopback = match endpoint.host() {
+            Some(url::Host::Domain(domain)) => domain.eq_ignore_ascii_case("localhost"),
+            Some(url::Host::Ipv4(address)) => address.is_loopback(),
+            Some(url::Host::Ipv6(address)) => address.is_loopback(),
+            None => return invalid("The endpoint has no host"),
+        };
+
+        match endpoint.scheme() {
+            "https" => (),
+            "http" if is_loopback => (),
+            "http" => {
+                return invalid("The endpoint must use `https://` unless it is on this server")
+            }
+            _ => return invalid("The endpoint must be an `https://` URL"),
+        }
+
+        if !endpoint.username().is_empty() || endpoint.password().is_some() {
+            return invalid("The credentials belong in the API key, not in the endpoint");
+        }
+
+        if self.provider.requires_api_key() && self.api_key.trim().is_empty() {
+            return invalid("The API key is required");
+        }
+
+        if self.api_key.contains(['\r', '\n']) {
+            return invalid("The API key must be a single line");
+        }
+
+        if self.timeout_secs == 0 || self.timeout_secs > Self::MAX_TIMEOUT_SECS {
+            return invalid(&format!(
+                "The timeout must be between 1 and {} seconds",
+                Self::MAX_TIMEOUT_SECS
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Without the whitespace around the endpoint and the API key
+    pub fn trimmed(mut self) -> Self {
+        self.endpoint = self.endpoint.trim().to_string();
+        self.api_key = self.api_key.trim().to_string();
+
+        self
+    }
+
+    /// The settings without the API key
+    pub fn redacted(&self) -> Self {
+        Self {
+            api_key: String::default(),
+            ..self.clone()
+        }
+    }
+
+    /// Keeps the current API key when `self` was edited from [Self::redacted]
+    pub fn keep_api_key(mut self, current: &Self) -> Self {
+        if self.api_key.is_empty() {
+            self.api_key = current.api_key.clone();
+        }
+
+        self
+    }
+}
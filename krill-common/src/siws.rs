@@ -0,0 +1,543 @@
+use core::fmt;
+use std::time::{Duration, SystemTime};
+
+use bitcode::{Decode, Encode};
+use tai64::Tai64N;
+
+use crate::{KrillError, KrillResult};
+
+/// A Sign In With Solana (SIWS) message as described by the
+/// wallet-standard `solana:signIn` feature. The text produced by
+/// [SiwsMessage::to_string] is the exact text the wallet signs.
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
+pub struct SiwsMessage {
+    pub domain: String,
+    pub address: String,
+    pub statement: Option<String>,
+    pub uri: Option<String>,
+    pub version: Option<String>,
+    pub chain_id: Option<String>,
+    pub nonce: Option<String>,
+    pub issued_at: Option<String>,
+    pub expiration_time: Option<String>,
+    pub not_before: Option<String>,
+    pub request_id: Option<String>,
+    pub resources: Vec<String>,
+}
+
+impl SiwsMessage {
+    pub const HEADER_SUFFIX: &str = " wants you to sign in with your Solana account:";
+    pub const VERSION: &str = "1";
+    pub const MINIMUM_NONCE_LEN: usize = 8;
+
+    const URI: &str = "URI: ";
+    const VERSION_FIELD: &str = "Version: ";
+    const CHAIN_ID: &str = "Chain ID: ";
+    const NONCE: &str = "Nonce: ";
+    const ISSUED_AT: &str = "Issued At: ";
+    const EXPIRATION_TIME: &str = "Expiration Time: ";
+    const NOT_BEFORE: &str = "Not Before: ";
+    const REQUEST_ID: &str = "Request ID: ";
+    const RESOURCES: &str = "Resources:";
+    const RESOURCE_ITEM: &str = "- ";
+
+    pub fn new(domain: &str, address: &str) -> Self {
+        Self {
+            domain: domain.to_string(),
+            address: address.to_string(),
+            version: Some(Self::VERSION.to_string()),
+            ..Default::default()
+        }
+    }
+
+    pub fn set_statement(mut self, statement: &str) -> Self {
+        self.statement.replace(statement.to_string());
+
+        self
+    }
+
+    pub fn set_uri(mut self, uri: &str) -> Self {
+        self.uri.replace(uri.to_string());
+
+        self
+    }
+
+    pub fn set_chain_id(mut self, chain_id: &str) -> Self {
+        self.chain_id.replace(chain_id.to_string());
+
+        self
+    }
+
+    pub fn set_nonce(mut self, nonce: &str) -> Self {
+        self.nonce.replace(nonce.to_string());
+
+        self
+    }
+
+    pub fn set_issued_at(mut self, issued_at: SystemTime) -> Self {
+        self.issued_at
+            .replace(humantime::format_rfc3339_millis(issued_at).to_string());
+
+        self
+    }
+
+    pub fn set_expiration_time(mut self, expiration_time: SystemTime) -> Self {
+        self.expiration_time
+            .replace(humantime::format_rfc3339_millis(expiration_time).to_string());
+
+        self
+    }
+
+    pub fn set_not_before(mut self, not_before: SystemTime) -> Self {
+        self.not_before
+            .replace(humantime::format_rfc3339_millis(not_before).to_string());
+
+        self
+    }
+
+    pub fn set_request_id(mut self, request_id: &str) -> Self {
+        self.request_id.replace(request_id.to_string());
+
+        self
+    }
+
+    pub fn add_resource(mut self, resource: &str) -> Self {
+        self.resources.push(resource.to_string());
+
+        self
+    }
+
+    /// Parses the text of a SIWS message. The message must be in canonical form,
+    /// meaning formatting the parsed message yields the exact same text
+    pub fn parse(message: &str) -> KrillResult<Self> {
+        let malformed = |reason: &str| KrillError::SiwsMalformedMessage(reason.to_string());
+
+        let mut lines = message.split('\n');
+
+        let domain = lines
+            .next()
+            .and_then(|header| header.strip_suffix(Self::HEADER_SUFFIX))
+            .filter(|domain| !domain.is_empty())
+            .ok_or(malformed("invalid header"))?;
+
+        let address = lines
+            .next()
+            .filter(|address| !address.is_empty())
+            .ok_or(malformed("missing address"))?;
+
+        let mut outcome = Self {
+            domain: domain.to_string(),
+            address: address.to_string(),
+            ..Default::default()
+        };
+
+        let remaining = lines.collect::<Vec<&str>>();
+
+        let fields = match remaining.as_slice() {
+            [] => &[][..],
+            ["", first, ..] if Self::is_field(first) => &remaining[1..],
+            ["", statement, rest @ ..] => {
+                outcome.statement.replace(statement.to_string());
+
+                match rest {
+                    [] => &[][..],
+                    ["", fields @ ..] if !fields.is_empty() => fields,
+                    _ => return Err(malformed("invalid statement")),
+                }
+            }
+            _ => return Err(malformed("expected a blank line after the address")),
+        };
+
+        let mut in_resources = false;
+
+        for line in fields {
+            if in_resources {
+                let resource = line
+                    .strip_prefix(Self::RESOURCE_ITEM)
+                    .ok_or(malformed("invalid resource"))?;
+                outcome.resources.push(resource.to_string());
+
+                continue;
+            }
+
+            if *line == Self::RESOURCES {
+                in_resources = true;
+            } else if let Some(value) = line.strip_prefix(Self::URI) {
+                outcome.uri.replace(value.to_string());
+            } else if let Some(value) = line.strip_prefix(Self::VERSION_FIELD) {
+                outcome.version.replace(value.to_string());
+            } else if let Some(value) = line.strip_prefix(Self::CHAIN_ID) {
+                outcome.chain_id.replace(value.to_string());
+            } else if let Some(value) = line.strip_prefix(Self::NONCE) {
+                outcome.nonce.replace(value.to_string());
+            } else if let Some(value) = line.strip_prefix(Self::ISSUED_AT) {
+                outcome.issued_at.replace(value.to_string());
+            } else if let Some(value) = line.strip_prefix(Self::EXPIRATION_TIME) {
+                outcome.expiration_time.replace(value.to_string());
+            } else if let Some(value) = line.strip_prefix(Self::NOT_BEFORE) {
+                outcome.not_before.replace(value.to_string());
+            } else if let Some(value) = line.strip_prefix(Self::REQUEST_ID) {
+                outcome.request_id.replace(value.to_string());
+            } else {
+                return Err(malformed("unknown field"));
+            }
+        }
+
+        if outcome.to_string() != message {
+            return Err(malformed("the message is not in canonical form"));
+        }
+
+        Ok(outcome)
+    }
+
+    fn is_field(line: &str) -> bool {
+        [
+            Self::URI,
+            Self::VERSION_FIELD,
+            Self::CHAIN_ID,
+            Self::NONCE,
+            Self::ISSUED_AT,
+            Self::EXPIRATION_TIME,
+            Self::NOT_BEFORE,
+            Self::REQUEST_ID,
+        ]
+        .iter()
+        .any(|prefix| line.starts_with(prefix))
+            || line == Self::RESOURCES
+    }
+
+    /// Decodes the base58 address into the 32 byte Ed25519 public key
+    pub fn public_key(&self) -> KrillResult<[u8; 32]> {
+        Self::decode_address(&self.address)
+    }
+
+    /// Decodes a base58 wallet address into the 32 byte Ed25519 public key
+    pub fn decode_address(address: &str) -> KrillResult<[u8; 32]> {
+        let mut buffer = [0u8; 32];
+
+        let decoded_len = bs58::decode(address)
+            .onto(&mut buffer)
+            .or(Err(KrillError::SiwsInvalidAddress))?;
+
+        if decoded_len != buffer.len() {
+            return Err(KrillError::SiwsInvalidAddress);
+        }
+
+        Ok(buffer)
+    }
+
+    /// Decodes a base58 encoded Ed25519 signature as returned by Solana wallets
+    pub fn decode_signature(signature: &str) -> KrillResult<[u8; 64]> {
+        let mut buffer = [0u8; 64];
+
+        let decoded_len = bs58::decode(signature.trim())
+            .onto(&mut buffer)
+            .or(Err(KrillError::SiwsInvalidSignature))?;
+
+        if decoded_len != buffer.len() {
+            return Err(KrillError::SiwsInvalidSignature);
+        }
+
+        Ok(buffer)
+    }
+
+    /// Checks the domain, nonce and the `Issued At`, `Expiration Time`
+    /// and `Not Before` fields against the time `now`
+    pub fn validate(&self, domain: &str, nonce: &str, now: SystemTime) -> KrillResult<()> {
+        if self.domain != domain {
+            return Err(KrillError::SiwsDomainMismatch);
+        }
+
+        if self.version.as_deref() != Some(Self::VERSION) {
+            return Err(KrillError::SiwsMalformedMessage(
+                "unsupported version".to_string(),
+            ));
+        }
+
+        match self.nonce.as_deref() {
+            Some(value) if value.len() >= Self::MINIMUM_NONCE_LEN && value == nonce => {}
+            _ => return Err(KrillError::SiwsNonceNotFound),
+        }
+
+        self.public_key()?;
+
+        if let Some(issued_at) = self.issued_at.as_deref() {
+            // Allow small clock drift between the browser and the server
+            if Self::parse_time(issued_at)? > now + Duration::from_secs(60) {
+                return Err(KrillError::SiwsMessageNotYetValid);
+            }
+        }
+
+        if let Some(not_before) = self.not_before.as_deref() {
+            if Self::parse_time(not_before)? > now {
+                return Err(KrillError::SiwsMessageNotYetValid);
+            }
+        }
+
+        if let Some(expiration_time) = self.expiration_time.as_deref() {
+            if Self::parse_time(expiration_time)? <= now {
+                return Err(KrillError::SiwsMessageExpired);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn parse_time(value: &str) -> KrillResult<SystemTime> {
+        humantime::parse_rfc3339(value).or(Err(KrillError::SiwsMalformedMessage(
+            "invalid timestamp".to_string(),
+        )))
+    }
+
+    /// Verifies the Ed25519 `signature` of this message against the public key in the message address
+    #[cfg(feature = "siws")]
+    pub fn verify_signature(&self, signature: &[u8]) -> KrillResult<()> {
+        use ed25519_dalek::{Signature, VerifyingKey};
+
+        let public_key = VerifyingKey::from_bytes(&self.public_key()?)
+            .or(Err(KrillError::SiwsInvalidAddress))?;
+
+        let signature =
+            Signature::from_slice(signature).or(Err(KrillError::SiwsInvalidSignature))?;
+
+        public_key
+            .verify_strict(self.to_string().as_bytes(), &signature)
+            .or(Err(KrillError::SiwsInvalidSignature))
+    }
+}
+
+impl fmt::Display for SiwsMessage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}{}\n{}",
+            self.domain,
+            Self::HEADER_SUFFIX,
+            self.address
+        )?;
+
+        if let Some(statement) = self.statement.as_ref() {
+            write!(f, "\n\n{statement}")?;
+        }
+
+        let mut fields = Vec::<String>::new();
+
+        let optional_fields = [
+            (Self::URI, &self.uri),
+            (Self::VERSION_FIELD, &self.version),
+            (Self::CHAIN_ID, &self.chain_id),
+            (Self::NONCE, &self.nonce),
+            (Self::ISSUED_AT, &self.issued_at),
+            (Self::EXPIRATION_TIME, &self.expiration_time),
+            (Self::NOT_BEFORE, &self.not_before),
+            (Self::REQUEST_ID, &self.request_id),
+        ];
+
+        optional_fields.iter().for_each(|(prefix, value)| {
+            if let Some(value) = value {
+                fields.push(String::from(*prefix) + value);
+            }
+        });
+
+        if !self.resources.is_empty() {
+            fields.push(Self::RESOURCES.to_string());
+
+            self.resources.iter().for_each(|resource| {
+                fields.push(String::from(Self::RESOURCE_ITEM) + resource);
+            });
+        }
+
+        if !fields.is_empty() {
+            write!(f, "\n\n{}", fields.join("\n"))?;
+        }
+
+        Ok(())
+    }
+}
+
+/// A sign in challenge issued by the server and stored until the wallet signs it
+#[derive(Debug, PartialEq, Eq, Clone, Encode, Decode)]
+pub struct SiwsChallenge {
+    pub address: String,
+    pub nonce: String,
+    pub message: String,
+    pub timestamp: [u8; Tai64N::BYTE_SIZE],
+    pub expiry: Duration,
+}
+
+impl SiwsChallenge {
+    pub const DEFAULT_EXPIRY: Duration = Duration::from_secs(5 * 60);
+
+    pub fn new(message: &SiwsMessage, nonce: &str) -> Self {
+        Self {
+            address: message.address.clone(),
+            nonce: nonce.to_string(),
+            message: message.to_string(),
+            timestamp: Tai64N::now().to_bytes(),
+            expiry: Self::DEFAULT_EXPIRY,
+        }
+    }
+
+    pub fn is_expired(&self) -> bool {
+        let issued = match Tai64N::try_from(self.timestamp) {
+            Ok(value) => value,
+            Err(_) => return true,
+        };
+
+        Tai64N::now() > issued + self.expiry
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::UNIX_EPOCH;
+
+    use super::*;
+
+    const ISSUED_AT: u64 = 1_767_225_600;
+
+    fn address() -> String {
+        bs58::encode([7u8; 32]).into_string()
+    }
+
+    fn message(address: &str) -> SiwsMessage {
+        let issued_at = UNIX_EPOCH + Duration::from_secs(ISSUED_AT);
+
+        SiwsMessage::new("krill.example", address)
+            .set_statement("Sign in to Krill")
+            .set_uri("https://krill.example")
+            .set_chain_id("mainnet")
+            .set_nonce("0123456789abcdef")
+            .set_issued_at(issued_at)
+            .set_expiration_time(issued_at + SiwsChallenge::DEFAULT_EXPIRY)
+            .add_resource("https://krill.example/terms")
+    }
+
+    #[test]
+    fn message_text() {
+        let address = address();
+        let message = message(&address);
+
+        assert_eq!(
+            message.to_string(),
+            format!(
+                "krill.example wants you to sign in with your Solana account:\n\
+                {address}\n\
+                \n\
+                Sign in to Krill\n\
+                \n\
+                URI: https://krill.example\n\
+                Version: 1\n\
+                Chain ID: mainnet\n\
+                Nonce: 0123456789abcdef\n\
+                Issued At: 2026-01-01T00:00:00.000Z\n\
+                Expiration Time: 2026-01-01T00:05:00.000Z\n\
+                Resources:\n\
+                - https://krill.example/terms"
+            )
+        );
+        assert_eq!(SiwsMessage::parse(&message.to_string()), Ok(message));
+
+        let minimal = SiwsMessage {
+            version: None,
+            ..SiwsMessage::new("krill.example", &address)
+        };
+        assert_eq!(SiwsMessage::parse(&minimal.to_string()), Ok(minimal));
+
+        let header = format!("krill.example{}\n{address}", SiwsMessage::HEADER_SUFFIX);
+        for malformed in [
+            String::new(),
+            format!("krill.example wants you to sign in:\n{address}"),
+            format!("{header}\nNonce: 0123456789abcdef"),
+            format!("{header}\n\nSign in\n\nUnknown: field"),
+            // Fields out of the canonical order
+            format!("{header}\n\nNonce: 0123456789abcdef\nVersion: 1"),
+        ] {
+            assert!(matches!(
+                SiwsMessage::parse(&malformed),
+                Err(KrillError::SiwsMalformedMessage(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn message_validation() {
+        let message = message(&address());
+        let issued_at = UNIX_EPOCH + Duration::from_secs(ISSUED_AT);
+
+        assert_eq!(
+            message.validate("krill.example", "0123456789abcdef", issued_at),
+            Ok(())
+        );
+        assert_eq!(
+            message.validate("evil.example", "0123456789abcdef", issued_at),
+            Err(KrillError::SiwsDomainMismatch)
+        );
+        assert_eq!(
+            message.validate("krill.example", "fedcba9876543210", issued_at),
+            Err(KrillError::SiwsNonceNotFound)
+        );
+        assert_eq!(
+            message.validate(
+                "krill.example",
+                "0123456789abcdef",
+                issued_at + SiwsChallenge::DEFAULT_EXPIRY
+            ),
+            Err(KrillError::SiwsMessageExpired)
+        );
+        assert_eq!(
+            message.validate(
+                "krill.example",
+                "0123456789abcdef",
+                issued_at - Duration::from_secs(120)
+            ),
+            Err(KrillError::SiwsMessageNotYetValid)
+        );
+
+        let invalid_address = SiwsMessage {
+            address: "not-base58".to_string(),
+            ..message
+        };
+        assert_eq!(
+            invalid_address.validate("krill.example", "0123456789abcdef", issued_at),
+            Err(KrillError::SiwsInvalidAddress)
+        );
+    }
+
+    #[cfg(feature = "siws")]
+    #[test]
+    fn signature_verification() {
+        use ed25519_dalek::{Signer, SigningKey};
+
+        let signing_key = SigningKey::from_bytes(&[9u8; 32]);
+        let address = bs58::encode(signing_key.verifying_key().as_bytes()).into_string();
+        let message = message(&address);
+
+        let signature = signing_key.sign(message.to_string().as_bytes());
+        let encoded = bs58::encode(signature.to_bytes()).into_string();
+
+        let decoded = SiwsMessage::decode_signature(&encoded).unwrap();
+        assert_eq!(message.verify_signature(&decoded), Ok(()));
+
+        // A signature over any other text or from any other key is rejected
+        let other = message.clone().set_request_id("1");
+        assert_eq!(
+            other.verify_signature(&decoded),
+            Err(KrillError::SiwsInvalidSignature)
+        );
+        let other_key = SigningKey::from_bytes(&[8u8; 32]);
+        assert_eq!(
+            message.verify_signature(&other_key.sign(message.to_string().as_bytes()).to_bytes()),
+            Err(KrillError::SiwsInvalidSignature)
+        );
+
+        assert_eq!(
+            SiwsMessage::decode_signature("too-short"),
+            Err(KrillError::SiwsInvalidSignature)
+        );
+        assert_eq!(
+            SiwsMessage::decode_signature(&bs58::encode([1u8; 32]).into_string()),
+            Err(KrillError::SiwsInvalidSignature)
+        );
+    }
+}
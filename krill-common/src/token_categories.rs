@@ -0,0 +1,63 @@
+use bitcode::{Decode, Encode};
+
+use crate::{KrillError, KrillResult};
+
+/// The defaults of the tokens an organization issues in a category,
+/// like its stablecoins, attestations or tokenized assets
+#[derive(Debug, Default, PartialEq, Eq, Clone, Encode, Decode)]
+pub struct TokenCategoryPreset {
+    pub name: String,
+    pub description: String,
+    pub decimals: u8,
+    /// Mints the tokens with the Token-2022 program instead of the SPL Token program
+    pub token_2022: bool,
+    /// Attestations are usually bound to their holder
+    pub transferable: bool,
+}
+
+impl TokenCategoryPreset {
+    pub const MAX_NAME_LEN: usize = 64;
+    /// Like SOL, more decimals leave too few whole units in a `u64` amount
+    pub const MAX_DECIMALS: u8 = 9;
+
+    /// Names are compared case insensitively and must be unique
+    pub fn validate_all(presets: &[Self]) -> KrillResult<()> {
+        let invalid = |reason: String| Err(KrillError::InvalidTokenCategoryPreset(reason));
+        let mut names = Vec::<String>::new();
+
+        for preset in presets {
+            let name = preset.name.trim().to_lowercase();
+
+            if name.is_empty() || name.chars().count() > Self::MAX_NAME_LEN {
+                return invalid(format!(
+                    "Names must have 1 to {} characters",
+                    Self::MAX_NAME_LEN
+                ));
+            }
+
+            if names.contains(&name) {
+                return invalid(format!("`{}` is listed more than once", preset.name));
+            }
+
+            if preset.decimals > Self::MAX_DECIMALS {
+                return invalid(format!(
+                    "`{}` has more than {} decimals",
+                    preset.name,
+                    Self::MAX_DECIMALS
+                ));
+            }
+
+            // The non-transferable extension only exists in Token-2022
+            if !preset.transferable && !preset.token_2022 {
+                return invalid(format!(
+                    "`{}` is not transferable so it requires Token-2022",
+                    preset.name
+                ));
+            }
+
+            names.push(name);
+        }
+
+        Ok(())
+    }
+}
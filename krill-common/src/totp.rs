@@ -0,0 +1,292 @@
+use core::fmt;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use bitcode::{Decode, Encode};
+use hmac::{Hmac, Mac};
+use sha1::Sha1;
+use subtle::ConstantTimeEq;
+use zeroize::Zeroizing;
+
+use crate::{KrillError, KrillResult, RandomBytes, RandomChars, UserRole};
+
+/// Time-based one time passwords as specified by RFC 6238
+/// using HMAC-SHA1, 6 digits and a 30 second period which
+/// is what most authenticator apps support.
+pub struct Totp {
+    secret: Zeroizing<Vec<u8>>,
+}
+
+impl Totp {
+    pub const DIGITS: u32 = 6;
+    pub const PERIOD: u64 = 30;
+    pub const SECRET_LEN: usize = 20;
+    /// Number of time steps before and after the current one that are accepted
+    pub const SKEW: u64 = 1;
+
+    pub fn new(secret: &[u8]) -> Self {
+        Self {
+            secret: Zeroizing::new(secret.to_vec()),
+        }
+    }
+
+    pub fn generate() -> Self {
+        let secret = RandomBytes::<{ Self::SECRET_LEN }>::generate();
+
+        Self::new(secret.expose())
+    }
+
+    pub fn secret(&self) -> &[u8] {
+        self.secret.as_slice()
+    }
+
+    pub fn secret_base32(&self) -> Zeroizing<String> {
+        Zeroizing::new(data_encoding::BASE32_NOPAD.encode(self.secret()))
+    }
+
+    /// The `otpauth://` URI that authenticator apps scan from a QR code
+    pub fn provisioning_uri(&self, issuer: &str, account: &str) -> Zeroizing<String> {
+        let issuer = Self::percent_encode(issuer);
+        let account = Self::percent_encode(account);
+
+        Zeroizing::new(format!(
+            "otpauth://totp/{issuer}:{account}?secret={}&issuer={issuer}&algorithm=SHA1&digits={}&period={}",
+            self.secret_base32().as_str(),
+            Self::DIGITS,
+            Self::PERIOD
+        ))
+    }
+
+    pub fn time_step(now: SystemTime) -> u64 {
+        now.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs() / Self::PERIOD
+    }
+
+    /// The HOTP value (RFC 4226) for the counter `step`
+    pub fn code_at_step(&self, step: u64) -> KrillResult<u32> {
+        let mut mac =
+            Hmac::<Sha1>::new_from_slice(self.secret()).or(Err(KrillError::InvalidTotpSecret))?;
+        mac.update(&step.to_be_bytes());
+        let hash = mac.finalize().into_bytes();
+
+        let offset = (hash[hash.len() - 1] & 0x0f) as usize;
+        let binary = u32::from_be_bytes([
+            hash[offset] & 0x7f,
+            hash[offset + 1],
+            hash[offset + 2],
+            hash[offset + 3],
+        ]);
+
+        Ok(binary % 10u32.pow(Self::DIGITS))
+    }
+
+    pub fn code_at(&self, now: SystemTime) -> KrillResult<Zeroizing<String>> {
+        self.code_at_step(Self::time_step(now))
+            .map(|code| Zeroizing::new(Self::format_code(code)))
+    }
+
+    /// Returns the time step the `code` is valid for. Steps at or before `last_used_step`
+    /// are rejected so that a code cannot be replayed.
+    pub fn verify(&self, code: &str, now: SystemTime, last_used_step: u64) -> KrillResult<u64> {
+        let code = code.trim();
+
+        if code.len() != Self::DIGITS as usize || !code.bytes().all(|byte| byte.is_ascii_digit()) {
+            return Err(KrillError::InvalidTotpCode);
+        }
+
+        let current = Self::time_step(now);
+
+        for step in current.saturating_sub(Self::SKEW)..=current + Self::SKEW {
+            let expected = Zeroizing::new(Self::format_code(self.code_at_step(step)?));
+
+            if bool::from(expected.as_bytes().ct_eq(code.as_bytes())) {
+                if step <= last_used_step {
+                    return Err(KrillError::InvalidTotpCode);
+                }
+
+                return Ok(step);
+            }
+        }
+
+        Err(KrillError::InvalidTotpCode)
+    }
+
+    fn format_code(code: u32) -> String {
+        format!("{:0width$}", code, width = Self::DIGITS as usize)
+    }
+
+    fn percent_encode(value: &str) -> String {
+        value
+            .bytes()
+            .map(|byte| {
+                if byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'.' | b'_' | b'~' | b'@')
+                {
+                    (byte as char).to_string()
+                } else {
+                    format!("%{:02X}", byte)
+                }
+            })
+            .collect()
+    }
+}
+
+impl fmt::Debug for Totp {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Totp(Redacted {} byte secret)", self.secret.len())
+    }
+}
+
+/// Single use codes that can be used in place of a TOTP code
+/// when the authenticator device is lost
+pub struct TotpRecoveryCodes(Vec<Zeroizing<String>>);
+
+impl TotpRecoveryCodes {
+    pub const COUNT: usize = 10;
+
+    pub fn generate() -> Self {
+        let codes = (0..Self::COUNT)
+            .map(|_| {
+                let random = RandomChars::<10>::generate();
+                let chars = random.expose();
+
+                let mut code = Zeroizing::new(String::with_capacity(11));
+                chars.iter().enumerate().for_each(|(index, char)| {
+                    if index == 5 {
+                        code.push('-');
+                    }
+                    code.push(*char);
+                });
+
+                code
+            })
+            .collect();
+
+        Self(codes)
+    }
+
+    pub fn codes(&self) -> &[Zeroizing<String>] {
+        self.0.as_slice()
+    }
+
+    pub fn hashes(&self) -> Vec<[u8; 32]> {
+        self.0.iter().map(|code| Self::hash(code)).collect()
+    }
+
+    /// Recovery codes are compared case insensitively and without the separator
+    pub fn hash(code: &str) -> [u8; 32] {
+        let normalized = Zeroizing::new(
+            code.trim()
+                .chars()
+                .filter(|char| *char != '-')
+                .collect::<String>()
+                .to_uppercase(),
+        );
+
+        *blake3::hash(normalized.as_bytes()).as_bytes()
+    }
+}
+
+impl fmt::Debug for TotpRecoveryCodes {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "TotpRecoveryCodes(Redacted {} codes)", self.0.len())
+    }
+}
+
+/// Which roles must enroll and verify a second factor before their session is usable
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy, Encode, Decode)]
+pub struct TotpPolicy {
+    pub require_for_superuser: bool,
+    pub require_for_admin: bool,
+}
+
+impl TotpPolicy {
+    pub fn is_required(&self, role: UserRole) -> bool {
+        match role {
+            UserRole::Superuser => self.require_for_superuser,
+            UserRole::Admin => self.require_for_admin,
+            UserRole::Member => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use super::*;
+
+    /// The SHA1 test vectors of RFC 6238 Appendix B truncated to 6 digits
+    #[test]
+    fn rfc6238_codes() {
+        let totp = Totp::new(b"12345678901234567890");
+
+        for (unix_secs, code) in [
+            (59, "287082"),
+            (1_111_111_109, "081804"),
+            (1_234_567_890, "005924"),
+            (2_000_000_000, "279037"),
+        ] {
+            let now = UNIX_EPOCH + Duration::from_secs(unix_secs);
+
+            assert_eq!(totp.code_at(now).unwrap().as_str(), code);
+        }
+    }
+
+    #[test]
+    fn verify_codes() {
+        let totp = Totp::generate();
+        assert_eq!(totp.secret().len(), Totp::SECRET_LEN);
+        assert_ne!(Totp::generate().secret(), totp.secret());
+
+        let now = SystemTime::now();
+        let step = Totp::time_step(now);
+        let code = totp.code_at(now).unwrap();
+
+        assert_eq!(
+            totp.verify(&format!(" {} ", code.as_str()), now, 0),
+            Ok(step)
+        );
+        // A used code cannot be replayed
+        assert_eq!(
+            totp.verify(&code, now, step),
+            Err(KrillError::InvalidTotpCode)
+        );
+
+        // The previous and next steps are accepted to allow for clock skew
+        let period = Duration::from_secs(Totp::PERIOD);
+        assert_eq!(totp.verify(&code, now + period, 0), Ok(step));
+        assert_eq!(totp.verify(&code, now - period, 0), Ok(step));
+        assert_eq!(
+            totp.verify(&code, now + period * 2, 0),
+            Err(KrillError::InvalidTotpCode)
+        );
+
+        for invalid in ["", "12345", "1234567", "12a456"] {
+            assert_eq!(
+                totp.verify(invalid, now, 0),
+                Err(KrillError::InvalidTotpCode)
+            );
+        }
+
+        let uri = totp.provisioning_uri("Krill Org", "ada@krill.example");
+        assert!(uri.starts_with("otpauth://totp/Krill%20Org:ada@krill.example?secret="));
+        assert!(uri.contains(totp.secret_base32().as_str()));
+    }
+
+    #[test]
+    fn recovery_codes() {
+        let recovery_codes = TotpRecoveryCodes::generate();
+        assert_eq!(recovery_codes.codes().len(), TotpRecoveryCodes::COUNT);
+
+        let code = &recovery_codes.codes()[0];
+        assert_eq!(code.len(), 11);
+        assert_eq!(code.find('-'), Some(5));
+
+        // Codes match without the separator and in any case
+        let hash = TotpRecoveryCodes::hash(code);
+        assert_eq!(recovery_codes.hashes()[0], hash);
+        assert_eq!(
+            TotpRecoveryCodes::hash(&code.replace('-', "").to_lowercase()),
+            hash
+        );
+        assert!(!format!("{recovery_codes:?}").contains(code.as_str()));
+    }
+}
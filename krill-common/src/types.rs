@@ -17,6 +17,9 @@ pub enum ServerConfigurationState {
     Uninitialized,
     LoginInitialization,
     Initialized,
+    /// The superuser verified the support mail but fewer authorities than the
+    /// threshold accepted their invitations
+    AwaitingAuthorities,
 }
 
 #[cfg(feature = "random")]
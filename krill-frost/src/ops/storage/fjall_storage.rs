@@ -45,7 +45,7 @@ where
 
     async fn init(path: Utf8PathBuf) -> KrillResult<Self> {
         let mut path = path;
-        path.push("KrillFrostStore");
+        path.push(StoreKeyspace::STORE_DIR);
 
         KrillUtils::create_recursive_dir(&path).await?;
 
@@ -46,6 +46,16 @@ pub enum StoreKeyspace {
 }
 
 impl StoreKeyspace {
+    /// The directory of the FROST store inside the Krill directory
+    pub const STORE_DIR: &str = "KrillFrostStore";
+
+    pub const ALL: [Self; 4] = [
+        Self::FrostKeypair,
+        Self::CoordinatorMessages,
+        Self::ParticipantMessages,
+        Self::SignedMessages,
+    ];
+
     pub fn to_str(&self) -> &str {
         match self {
             Self::FrostKeypair => "frost-signing-keypair",
@@ -0,0 +1,426 @@
+use base64ct::{Base64, Encoding};
+use krill_common::{KrillError, KrillResult};
+use krill_store::{DkimAlgorithm, DkimKey};
+use lettre::{
+    message::{
+        dkim::{
+            DkimCanonicalization, DkimCanonicalizationType, DkimConfig, DkimSigningAlgorithm,
+            DkimSigningKey,
+        },
+        header::HeaderName,
+    },
+    Message,
+};
+use rsa::{
+    pkcs1::{DecodeRsaPrivateKey, EncodeRsaPrivateKey, LineEnding},
+    pkcs8::EncodePublicKey,
+    rand_core::{OsRng, RngCore},
+    RsaPrivateKey,
+};
+
+/// Signs outgoing emails with the DKIM key of the organization
+pub struct DkimSigner {
+    key: DkimKey,
+    /// `p=` of the DNS record, the DER public key for RSA and the raw public key for Ed25519
+    public_key: Vec<u8>,
+    config: DkimConfig,
+}
+
+impl DkimSigner {
+    /// The size of generated RSA keys, the size most mail providers expect
+    pub const RSA_BITS: usize = 2048;
+
+    /// The headers covered by the signature
+    const SIGNED_HEADERS: [&str; 6] = ["From", "Reply-To", "To", "Cc", "Subject", "Date"];
+
+    /// Generates a key for the selector and domain, see [Self::dns_txt_record]
+    /// for the record to publish before emails are signed with it
+    pub fn generate(
+        algorithm: DkimAlgorithm,
+        selector: &str,
+        domain: &str,
+    ) -> KrillResult<DkimKey> {
+        Self::generate_key(algorithm, selector, domain, Self::RSA_BITS)
+    }
+
+    pub fn new(key: DkimKey) -> KrillResult<Self> {
+        Self::check_dns_name("selector", &key.selector)?;
+        Self::check_dns_name("domain", &key.domain)?;
+
+        let (public_key, signing_key) = match key.algorithm {
+            DkimAlgorithm::Rsa => {
+                let private_key = RsaPrivateKey::from_pkcs1_pem(&key.private_key)
+                    .map_err(|error| KrillError::Dkim(error.to_string()))?;
+                let public_key = private_key
+                    .to_public_key()
+                    .to_public_key_der()
+                    .map_err(|error| KrillError::Dkim(error.to_string()))?;
+
+                (
+                    public_key.as_bytes().to_vec(),
+                    DkimSigningKey::new(&key.private_key, DkimSigningAlgorithm::Rsa),
+                )
+            }
+            DkimAlgorithm::Ed25519 => {
+                let secret: [u8; 32] = Base64::decode_vec(&key.private_key)
+                    .ok()
+                    .and_then(|secret| secret.try_into().ok())
+                    .ok_or(KrillError::Dkim(
+                        "The Ed25519 key must be 32 base64 encoded bytes".to_string(),
+                    ))?;
+                let public_key = ed25519_dalek::SigningKey::from_bytes(&secret)
+                    .verifying_key()
+                    .to_bytes();
+
+                (
+                    public_key.to_vec(),
+                    DkimSigningKey::new(&key.private_key, DkimSigningAlgorithm::Ed25519),
+                )
+            }
+        };
+
+        let signing_key = signing_key.map_err(|error| KrillError::Dkim(error.to_string()))?;
+
+        // Relaxed header canonicalization survives the headers being folded again on the way
+        let config = DkimConfig::new(
+            key.selector.clone(),
+            key.domain.clone(),
+            signing_key,
+            Self::SIGNED_HEADERS
+                .into_iter()
+                .map(HeaderName::new_from_ascii_str)
+                .collect(),
+            DkimCanonicalization {
+                header: DkimCanonicalizationType::Relaxed,
+                body: DkimCanonicalizationType::Relaxed,
+            },
+        );
+
+        Ok(Self {
+            key,
+            public_key,
+            config,
+        })
+    }
+
+    pub fn key(&self) -> &DkimKey {
+        &self.key
+    }
+
+    /// The name the TXT record is published at, like `krill._domainkey.example.com`
+    pub fn dns_record_name(&self) -> String {
+        format!("{}._domainkey.{}", self.key.selector, self.key.domain)
+    }
+
+    /// The value of the TXT record the receiving servers verify signatures with
+    pub fn dns_txt_record(&self) -> String {
+        format!(
+            "v=DKIM1; k={}; p={}",
+            self.key.algorithm.as_str(),
+            Base64::encode_string(&self.public_key)
+        )
+    }
+
+    /// The record as a zone file entry. TXT strings are limited to 255 bytes so
+    /// the RSA record is split into several strings.
+    pub fn dns_zone_entry(&self) -> String {
+        let record = self.dns_txt_record();
+        let strings = record
+            .as_bytes()
+            .chunks(255)
+            .map(|chunk| format!("\"{}\"", String::from_utf8_lossy(chunk)))
+            .collect::<Vec<String>>()
+            .join(" ");
+
+        format!("{}. IN TXT ( {strings} )", self.dns_record_name())
+    }
+
+    /// Adds the `DKIM-Signature` header, the message must not change afterwards
+    pub fn sign(&self, message: &mut Message) {
+        message.sign(&self.config);
+    }
+
+    fn generate_key(
+        algorithm: DkimAlgorithm,
+        selector: &str,
+        domain: &str,
+        rsa_bits: usize,
+    ) -> KrillResult<DkimKey> {
+        Self::check_dns_name("selector", selector)?;
+        Self::check_dns_name("domain", domain)?;
+
+        let private_key = match algorithm {
+            DkimAlgorithm::Rsa => RsaPrivateKey::new(&mut OsRng, rsa_bits)
+                .map_err(|error| KrillError::Dkim(error.to_string()))?
+                .to_pkcs1_pem(LineEnding::LF)
+                .map_err(|error| KrillError::Dkim(error.to_string()))?
+                .to_string(),
+            DkimAlgorithm::Ed25519 => {
+                let mut secret = [0u8; 32];
+                OsRng.fill_bytes(&mut secret);
+
+                Base64::encode_string(&secret)
+            }
+        };
+
+        Ok(DkimKey {
+            algorithm,
+            selector: selector.to_string(),
+            domain: domain.to_string(),
+            private_key,
+        })
+    }
+
+    /// Selectors and domains are dot separated DNS labels
+    fn check_dns_name(kind: &str, name: &str) -> KrillResult<()> {
+        let valid = !name.is_empty()
+            && name.len() <= 253
+            && name.split('.').all(|label| {
+                !label.is_empty()
+                    && label.len() <= 63
+                    && !label.starts_with('-')
+                    && !label.ends_with('-')
+                    && label
+                        .chars()
+                        .all(|char| char.is_ascii_alphanumeric() || char == '-')
+            });
+
+        if valid {
+            Ok(())
+        } else {
+            Err(KrillError::Dkim(format!("`{name}` is not a valid {kind}")))
+        }
+    }
+}
+
+impl std::fmt::Debug for DkimSigner {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DkimSigner")
+            .field("key", &self.key)
+            .field("public_key", &self.dns_txt_record())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use krill_common::SmtpConfig;
+    use rsa::{
+        pkcs1v15::Pkcs1v15Sign,
+        pkcs8::DecodePublicKey,
+        sha2::{Digest, Sha256},
+        RsaPublicKey,
+    };
+
+    use crate::{EmailEnvelopeDetails, KrillSmtpsBuilder, MemoryMailbox};
+
+    /// Sends an email signed with the key through the memory transport
+    fn signed_message(signer: DkimSigner) -> String {
+        let mailbox = format!("dkim_{}", signer.key().algorithm.as_str());
+
+        let mut mailer = KrillSmtpsBuilder::new();
+        mailer
+            .set_from("Krill <support@krill.example>")
+            .set_local_transports(true)
+            .set_dkim(signer);
+        let mailer = mailer
+            .build(&SmtpConfig::local(&format!("memory://{mailbox}")).into())
+            .unwrap();
+
+        let message = EmailEnvelopeDetails::new()
+            .set_to("Jane Doe <jane@example.com>")
+            .set_subject("Verify your email address")
+            .set_body("<p>Open the link   to sign in</p>\n\n")
+            .set_text("Open the link to sign in");
+
+        tokio::runtime::Runtime::new()
+            .unwrap()
+            .block_on(mailer.send(&message))
+            .unwrap();
+
+        let captured = MemoryMailbox::named(&mailbox).take().remove(0);
+
+        String::from_utf8(captured.raw).unwrap()
+    }
+
+    /// The unfolded header fields and the body of a formatted message
+    fn parse(formatted: &str) -> (Vec<(String, String)>, String) {
+        let (head, body) = formatted.split_once("\r\n\r\n").unwrap();
+
+        let mut headers: Vec<(String, String)> = Vec::new();
+        for line in head.split("\r\n") {
+            match line.strip_prefix([' ', '\t']) {
+                Some(continuation) => {
+                    let (_, value) = headers.last_mut().unwrap();
+                    value.push(' ');
+                    value.push_str(continuation);
+                }
+                None => {
+                    let (name, value) = line.split_once(':').unwrap();
+                    headers.push((name.to_string(), value.to_string()));
+                }
+            }
+        }
+
+        (headers, body.to_string())
+    }
+
+    fn relaxed_header(name: &str, value: &str) -> String {
+        format!(
+            "{}:{}",
+            name.to_ascii_lowercase(),
+            value.split_whitespace().collect::<Vec<&str>>().join(" ")
+        )
+    }
+
+    fn relaxed_body(body: &str) -> String {
+        let mut lines = body
+            .split("\r\n")
+            .map(|line| {
+                let mut relaxed = String::new();
+                for (index, word) in line.split([' ', '\t']).enumerate() {
+                    if index > 0 && !relaxed.ends_with(' ') {
+                        relaxed.push(' ');
+                    }
+                    relaxed.push_str(word);
+                }
+
+                relaxed.trim_end().to_string()
+            })
+            .collect::<Vec<String>>();
+
+        while lines.last().is_some_and(String::is_empty) {
+            lines.pop();
+        }
+
+        lines.join("\r\n") + "\r\n"
+    }
+
+    /// Verifies the signature like a receiving server would with the published record
+    fn verify(formatted: &str, txt_record: &str) -> bool {
+        let (headers, body) = parse(formatted);
+
+        let (_, signature_header) = headers
+            .iter()
+            .find(|(name, _)| name == "DKIM-Signature")
+            .unwrap();
+        let tags = signature_header
+            .split(';')
+            .filter_map(|tag| tag.split_once('='))
+            .map(|(name, value)| {
+                (
+                    name.trim().to_string(),
+                    value.split_whitespace().collect::<String>(),
+                )
+            })
+            .collect::<std::collections::BTreeMap<String, String>>();
+
+        assert_eq!(tags["c"], "relaxed/relaxed");
+        assert_eq!(
+            (tags["d"].as_str(), tags["s"].as_str()),
+            ("krill.example", "krill")
+        );
+        if Base64::decode_vec(&tags["bh"]).unwrap()
+            != Sha256::digest(relaxed_body(&body).as_bytes()).to_vec()
+        {
+            return false;
+        }
+
+        // Listed headers missing from the message are signed as empty
+        let mut hashed = Sha256::new();
+        for name in tags["h"].split(':') {
+            if let Some((name, value)) = headers
+                .iter()
+                .find(|(header, _)| header.eq_ignore_ascii_case(name))
+            {
+                hashed.update(relaxed_header(name, value) + "\r\n");
+            }
+        }
+        let unsigned = &signature_header[..signature_header.find("; b=").unwrap() + 4];
+        hashed.update(relaxed_header("DKIM-Signature", unsigned));
+        let hashed = hashed.finalize();
+
+        let signature = Base64::decode_vec(&tags["b"]).unwrap();
+        let public_key = Base64::decode_vec(txt_record.rsplit_once("p=").unwrap().1).unwrap();
+
+        match tags["a"].as_str() {
+            "rsa-sha256" => RsaPublicKey::from_public_key_der(&public_key)
+                .unwrap()
+                .verify(Pkcs1v15Sign::new::<Sha256>(), &hashed, &signature)
+                .is_ok(),
+            "ed25519-sha256" => {
+                use ed25519_dalek::Verifier;
+
+                ed25519_dalek::VerifyingKey::from_bytes(&public_key.try_into().unwrap())
+                    .unwrap()
+                    .verify(
+                        &hashed,
+                        &ed25519_dalek::Signature::from_slice(&signature).unwrap(),
+                    )
+                    .is_ok()
+            }
+            algorithm => panic!("Unexpected algorithm `{algorithm}`"),
+        }
+    }
+
+    #[test]
+    fn signatures_verify() {
+        for (algorithm, prefix) in [
+            (DkimAlgorithm::Rsa, "v=DKIM1; k=rsa; p="),
+            (DkimAlgorithm::Ed25519, "v=DKIM1; k=ed25519; p="),
+        ] {
+            // A smaller RSA key keeps the test fast
+            let key = DkimSigner::generate_key(algorithm, "krill", "krill.example", 1024).unwrap();
+            let signer = DkimSigner::new(key.clone()).unwrap();
+
+            assert_eq!(signer.dns_record_name(), "krill._domainkey.krill.example");
+            assert!(signer.dns_txt_record().starts_with(prefix));
+            assert!(signer
+                .dns_zone_entry()
+                .starts_with("krill._domainkey.krill.example. IN TXT ( \"v=DKIM1;"));
+            assert!(!format!("{signer:?}").contains(&key.private_key));
+
+            let txt_record = signer.dns_txt_record();
+            let formatted = signed_message(signer);
+            assert!(formatted.contains("DKIM-Signature: v=1;"));
+            assert!(verify(&formatted, &txt_record));
+
+            // A changed subject breaks the signature
+            let tampered = formatted.replace("Verify your email", "Verify your e-mail");
+            assert!(!verify(&tampered, &txt_record));
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_keys() {
+        assert!(
+            DkimSigner::generate(DkimAlgorithm::Ed25519, "krill selector", "krill.example")
+                .is_err()
+        );
+        assert!(DkimSigner::generate(DkimAlgorithm::Ed25519, "krill", "-krill.example").is_err());
+
+        let key =
+            DkimSigner::generate(DkimAlgorithm::Ed25519, "2026.krill", "krill.example").unwrap();
+        assert!(DkimSigner::new(key.clone()).is_ok());
+
+        let truncated = DkimKey {
+            private_key: "c2VjcmV0".to_string(),
+            ..key.clone()
+        };
+        assert!(matches!(
+            DkimSigner::new(truncated),
+            Err(KrillError::Dkim(_))
+        ));
+
+        let mislabeled = DkimKey {
+            algorithm: DkimAlgorithm::Rsa,
+            ..key
+        };
+        assert!(matches!(
+            DkimSigner::new(mislabeled),
+            Err(KrillError::Dkim(_))
+        ));
+    }
+}
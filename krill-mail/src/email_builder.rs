@@ -1,17 +1,75 @@
-#[derive(Default, Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+use bitcode::{Decode, Encode};
+use krill_common::{KrillError, KrillResult};
+use lettre::{
+    message::{
+        header::{ContentType, HeaderName, HeaderValue},
+        Attachment, Mailbox, MultiPart, SinglePart,
+    },
+    Message,
+};
+
+#[derive(Default, Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Encode, Decode)]
 pub struct EmailEnvelopeDetails {
-    pub to: String,
+    pub to: Vec<String>,
+    pub cc: Vec<String>,
+    /// Recipients left out of the headers of the message
+    pub bcc: Vec<String>,
     pub subject: String,
+    /// The HTML body
     pub body: String,
+    /// The plain text alternative of the HTML body
+    pub text: Option<String>,
+    pub headers: Vec<(String, String)>,
+    pub attachments: Vec<EmailAttachment>,
 }
 
 impl EmailEnvelopeDetails {
+    /// The total size of the attachments
+    pub const MAX_ATTACHMENTS_SIZE: usize = 10 * 1024 * 1024;
+
+    /// Headers set from the envelope details or by the mailer
+    const RESERVED_HEADERS: &[&str] = &[
+        "bcc",
+        "cc",
+        "content-disposition",
+        "content-transfer-encoding",
+        "content-type",
+        "date",
+        "dkim-signature",
+        "from",
+        "message-id",
+        "mime-version",
+        "reply-to",
+        "sender",
+        "subject",
+        "to",
+    ];
+
     pub fn new() -> Self {
         Self::default()
     }
 
+    /// Replaces the recipients with `to`
     pub fn set_to(mut self, to: &str) -> Self {
-        self.to = to.to_string();
+        self.to = vec![to.to_string()];
+
+        self
+    }
+
+    pub fn add_to(mut self, to: &str) -> Self {
+        self.to.push(to.to_string());
+
+        self
+    }
+
+    pub fn add_cc(mut self, cc: &str) -> Self {
+        self.cc.push(cc.to_string());
+
+        self
+    }
+
+    pub fn add_bcc(mut self, bcc: &str) -> Self {
+        self.bcc.push(bcc.to_string());
 
         self
     }
@@ -27,4 +85,257 @@ impl EmailEnvelopeDetails {
 
         self
     }
+
+    pub fn set_text(mut self, text: &str) -> Self {
+        self.text.replace(text.to_string());
+
+        self
+    }
+
+    /// Adds a header like `X-Krill-Request`, headers of the message itself are rejected when sending
+    pub fn add_header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+
+        self
+    }
+
+    pub fn add_attachment(mut self, attachment: EmailAttachment) -> Self {
+        self.attachments.push(attachment);
+
+        self
+    }
+
+    /// Builds the MIME message. The HTML body and its plain text alternative are
+    /// sent as `multipart/alternative`, wrapped in `multipart/mixed` with the attachments.
+    pub fn to_message(&self, from: &Mailbox, reply_to: Option<&Mailbox>) -> KrillResult<Message> {
+        if self.to.is_empty() && self.cc.is_empty() && self.bcc.is_empty() {
+            return Err(KrillError::Mailer("The email has no recipient".to_string()));
+        }
+
+        let attachments_size = self
+            .attachments
+            .iter()
+            .map(|attachment| attachment.data.len())
+            .sum::<usize>();
+        if attachments_size > Self::MAX_ATTACHMENTS_SIZE {
+            return Err(KrillError::Mailer(format!(
+                "The attachments exceed {} bytes",
+                Self::MAX_ATTACHMENTS_SIZE
+            )));
+        }
+
+        let mut email = Message::builder()
+            .from(from.clone())
+            .subject(self.subject.as_str());
+
+        if let Some(reply_to) = reply_to {
+            email = email.reply_to(reply_to.clone());
+        }
+
+        for to in &self.to {
+            email = email.to(Self::mailbox(to)?);
+        }
+
+        for cc in &self.cc {
+            email = email.cc(Self::mailbox(cc)?);
+        }
+
+        for bcc in &self.bcc {
+            email = email.bcc(Self::mailbox(bcc)?);
+        }
+
+        for (name, value) in &self.headers {
+            email = email.raw_header(Self::header(name, value)?);
+        }
+
+        let alternative = self
+            .text
+            .as_ref()
+            .filter(|_| !self.body.is_empty())
+            .map(|text| MultiPart::alternative_plain_html(text.clone(), self.body.clone()));
+        let single = match self.text.as_ref() {
+            Some(text) if self.body.is_empty() => SinglePart::plain(text.clone()),
+            _ => SinglePart::html(self.body.clone()),
+        };
+
+        let email = if self.attachments.is_empty() {
+            match alternative {
+                Some(alternative) => email.multipart(alternative),
+                None => email.singlepart(single),
+            }
+        } else {
+            let mut mixed = match alternative {
+                Some(alternative) => MultiPart::mixed().multipart(alternative),
+                None => MultiPart::mixed().singlepart(single),
+            };
+
+            for attachment in &self.attachments {
+                mixed = mixed.singlepart(attachment.to_part()?);
+            }
+
+            email.multipart(mixed)
+        };
+
+        email.map_err(|error| KrillError::Mailer(error.to_string()))
+    }
+
+    fn mailbox(address: &str) -> KrillResult<Mailbox> {
+        address
+            .parse::<Mailbox>()
+            .map_err(|error| KrillError::Mailer(error.to_string()))
+    }
+
+    fn header(name: &str, value: &str) -> KrillResult<HeaderValue> {
+        let is_valid_name = !name.is_empty()
+            && name
+                .chars()
+                .all(|char| char.is_ascii_alphanumeric() || char == '-')
+            && !Self::RESERVED_HEADERS.contains(&name.to_ascii_lowercase().as_str());
+
+        if !is_valid_name || value.contains(['\r', '\n']) {
+            return Err(KrillError::Mailer(format!(
+                "The header `{name}` is not allowed"
+            )));
+        }
+
+        let name = HeaderName::new_from_ascii(name.to_string())
+            .map_err(|error| KrillError::Mailer(error.to_string()))?;
+
+        Ok(HeaderValue::new(name, value.to_string()))
+    }
+}
+
+/// A file attached to an email, like an exported key
+#[derive(Default, Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Encode, Decode)]
+pub struct EmailAttachment {
+    pub filename: String,
+    /// A MIME type like `application/octet-stream`
+    pub content_type: String,
+    pub data: Vec<u8>,
+}
+
+impl EmailAttachment {
+    pub const OCTET_STREAM: &str = "application/octet-stream";
+
+    pub fn new(filename: &str, content_type: &str, data: Vec<u8>) -> Self {
+        Self {
+            filename: filename.to_string(),
+            content_type: content_type.to_string(),
+            data,
+        }
+    }
+
+    fn to_part(&self) -> KrillResult<SinglePart> {
+        let is_valid_filename = !self.filename.trim().is_empty()
+            && !self
+                .filename
+                .chars()
+                .any(|char| char.is_control() || matches!(char, '/' | '\\' | '"'));
+
+        if !is_valid_filename {
+            return Err(KrillError::Mailer(format!(
+                "`{}` is not a valid attachment name",
+                self.filename
+            )));
+        }
+
+        let content_type = ContentType::parse(&self.content_type)
+            .map_err(|error| KrillError::Mailer(error.to_string()))?;
+
+        Ok(Attachment::new(self.filename.clone()).body(self.data.clone(), content_type))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn from() -> Mailbox {
+        "Support <support@example.com>".parse().unwrap()
+    }
+
+    fn formatted(details: &EmailEnvelopeDetails) -> String {
+        String::from_utf8(details.to_message(&from(), None).unwrap().formatted()).unwrap()
+    }
+
+    #[test]
+    fn html_only() {
+        let message = formatted(
+            &EmailEnvelopeDetails::new()
+                .set_to("Foo <foo@example.com>")
+                .set_subject("Html")
+                .set_body("<p>Html</p>"),
+        );
+
+        assert!(message.contains("To: Foo <foo@example.com>"));
+        assert!(message.contains("Content-Type: text/html; charset=utf-8"));
+        assert!(!message.contains("multipart"));
+    }
+
+    #[test]
+    fn multipart_with_attachments() {
+        let message = formatted(
+            &EmailEnvelopeDetails::new()
+                .set_to("foo@example.com")
+                .add_to("bar@example.com")
+                .add_cc("cc@example.com")
+                .add_bcc("hidden@example.com")
+                .set_subject("Keys")
+                .set_body("<p>Your keys</p>")
+                .set_text("Your keys")
+                .add_header("X-Krill-Request", "42")
+                .add_attachment(EmailAttachment::new(
+                    "hmac.key",
+                    EmailAttachment::OCTET_STREAM,
+                    vec![0u8, 159, 146, 150],
+                )),
+        );
+
+        assert!(message.contains("To: foo@example.com, bar@example.com"));
+        assert!(message.contains("Cc: cc@example.com"));
+        assert!(!message.contains("hidden@example.com"));
+        assert!(message.contains("X-Krill-Request: 42"));
+        assert!(message.contains("Content-Type: multipart/mixed"));
+        assert!(message.contains("Content-Type: multipart/alternative"));
+        assert!(message.contains("Content-Type: text/plain; charset=utf-8"));
+        assert!(message.contains("Content-Type: text/html; charset=utf-8"));
+        assert!(message.contains("Content-Disposition: attachment; filename=\"hmac.key\""));
+        assert!(message.contains("Content-Type: application/octet-stream"));
+        assert!(message.contains("AJ+Slg=="));
+    }
+
+    #[test]
+    fn rejects_invalid_details() {
+        let details = EmailEnvelopeDetails::new()
+            .set_to("foo@example.com")
+            .set_body("<p>Html</p>");
+
+        for invalid in [
+            EmailEnvelopeDetails::new().set_body("<p>No recipient</p>"),
+            details.clone().set_to("not an address"),
+            details.clone().add_header("Bcc", "spy@example.com"),
+            details
+                .clone()
+                .add_header("X-Note", "a\r\nBcc: spy@example.com"),
+            details.clone().add_header("X Note", "value"),
+            details.clone().add_attachment(EmailAttachment::new(
+                "../key",
+                EmailAttachment::OCTET_STREAM,
+                vec![1],
+            )),
+            details
+                .clone()
+                .add_attachment(EmailAttachment::new("key", "not a mime type", vec![1])),
+            details.clone().add_attachment(EmailAttachment::new(
+                "key",
+                EmailAttachment::OCTET_STREAM,
+                vec![0; EmailEnvelopeDetails::MAX_ATTACHMENTS_SIZE + 1],
+            )),
+        ] {
+            assert!(matches!(
+                invalid.to_message(&from(), None),
+                Err(KrillError::Mailer(_))
+            ));
+        }
+    }
 }
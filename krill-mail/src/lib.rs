@@ -4,36 +4,111 @@ pub use email_builder::*;
 mod transport_builder;
 pub use transport_builder::*;
 
+mod templates;
+pub use templates::*;
+
+mod dkim;
+pub use dkim::*;
+
+mod transport;
+pub use transport::*;
+
+mod api_transport;
+pub use api_transport::*;
+
 mod mailer;
 pub use mailer::*;
 
+mod queue;
+pub use queue::*;
+
 #[cfg(test)]
-mod test_smtp_service {
+mod test_verification_flow {
+    use krill_common::{AuthTokenDetails, Holder, OrganizationInfo, SmtpConfig};
+    use krill_store::{KrillStorage, MailStatus, QueuedMail, UnlockSecret};
 
     use crate::*;
 
-    // TODO: Finish tests
-    //#[test]
-    fn test_secure_smtp() {
+    /// Sends the verification link like the server does and follows it back to the stored token
+    #[test]
+    fn verification_link_end_to_end() {
+        const LINK_PATH: &str = "/verification-support-mail-link/";
+
         let runtime = tokio::runtime::Runtime::new().unwrap();
 
-        runtime.block_on(async move {
+        runtime.block_on(async {
+            let storage = KrillStorage::init_memory()
+                .unlock(UnlockSecret::secret([4u8; 32]))
+                .await
+                .unwrap();
+
             let mut mailer = KrillSmtpsBuilder::new();
             mailer
-                .set_from("Support <support@domain.tld>")
-                .set_hello_name("domain.tld")
-                .set_reply_to("Support <support@domain.tld>");
+                .set_from("Example <support@example.com>")
+                .set_hello_name("krill.example.com")
+                .set_local_transports(true);
             let mailer = mailer
-                .build("smtps://username@domain.tld:password@smtp.domain-for-smtp-provider.tld:465")
+                .build(&SmtpConfig::local("memory://verification_link_end_to_end").into())
                 .unwrap();
+            let mailbox = MemoryMailbox::named("verification_link_end_to_end");
+
+            let holder = Holder::new_with_tld("support@example.com")
+                .unwrap()
+                .set_superuser();
+            let issued = storage.set_superuser_token(holder.clone()).await.unwrap();
+            let link = String::from("https://krill.example.com")
+                + LINK_PATH
+                + AuthTokenDetails::store_key_bytes_to_hex(issued.token).as_str();
+
+            let message = EmailTemplate::new(EmailTemplateName::VerificationLink)
+                .set_var("expiry", &issued.details.expiry_formatted())
+                .set_var(EmailTemplate::ACTION_URL, &link)
+                .render(
+                    EmailTemplate::FALLBACK_LANGUAGE,
+                    &EmailBranding::new(&OrganizationInfo::default(), "https://krill.example.com"),
+                )
+                .unwrap()
+                .envelope(&holder.email_envelope_details());
+
+            let reference = "verification:support@example.com";
+            MailQueue::enqueue(&storage, reference, &message)
+                .await
+                .unwrap();
+
+            let report = MailQueue::default()
+                .deliver_due(&storage, &mailer, QueuedMail::unix_now())
+                .await
+                .unwrap();
+            assert_eq!(report.sent, 1);
+            assert_eq!(
+                storage
+                    .mail_status(reference)
+                    .await
+                    .unwrap()
+                    .unwrap()
+                    .status,
+                MailStatus::Sent
+            );
+
+            let captured = mailbox.take();
+            assert_eq!(captured.len(), 1);
+            assert_eq!(
+                captured[0].details.to,
+                vec![holder.email_envelope_details()]
+            );
+            assert!(String::from_utf8_lossy(&captured[0].raw).contains("From: Example"));
 
-            let my_mail = EmailEnvelopeDetails::new()
-                .set_to("Foo Bar <foo@example.com>")
-                .set_subject("Mail Completed")
-                .set_body("Has been successful");
-            let outcome = mailer.send(&my_mail).await.unwrap();
+            let body = captured[0].details.body.as_str();
+            let token = body[body.find(LINK_PATH).unwrap() + LINK_PATH.len()..]
+                .chars()
+                .take_while(char::is_ascii_hexdigit)
+                .collect::<String>();
 
-            dbg!(&outcome);
+            let stored = storage.get_superuser_auth_token().await.unwrap().unwrap();
+            assert!(AuthTokenDetails::const_cmp(
+                stored.token,
+                &AuthTokenDetails::decode_token(&token).unwrap()
+            ));
         });
     }
 }
@@ -1,19 +1,19 @@
 use std::borrow::Cow;
 
-use krill_common::{KrillError, KrillResult};
-use lettre::{
-    message::Mailbox, transport::smtp::response::Severity, AsyncSmtpTransport, AsyncTransport,
-    Message, Tokio1Executor,
-};
+use krill_common::KrillResult;
+use lettre::message::Mailbox;
 
-use crate::EmailEnvelopeDetails;
+use crate::{DkimSigner, EmailEnvelopeDetails, KrillMailTransport};
 
 #[derive(Debug)]
 pub struct KrillSmtps<'a> {
     pub(crate) from: Mailbox,
     pub(crate) reply_to: Option<Mailbox>,
     pub(crate) hello_name: Option<Cow<'a, str>>,
-    pub(crate) mailer: AsyncSmtpTransport<Tokio1Executor>,
+    /// The host of the SMTP server or the name of the API provider, emails are rate limited per provider
+    pub(crate) provider: String,
+    pub(crate) transport: KrillMailTransport,
+    pub(crate) dkim: Option<DkimSigner>,
 }
 
 impl<'a> KrillSmtps<'a> {
@@ -29,48 +29,32 @@ impl<'a> KrillSmtps<'a> {
         self.hello_name.as_ref().map(|value| value.as_ref())
     }
 
-    pub fn mailer(&self) -> &AsyncSmtpTransport<Tokio1Executor> {
-        &self.mailer
+    pub fn provider(&self) -> &str {
+        self.provider.as_str()
     }
 
-    pub async fn send(&self, message: &EmailEnvelopeDetails) -> KrillResult<()> {
-        let email = Message::builder().from(self.from().clone());
-
-        let email = if let Some(reply_to) = self.reply_to() {
-            email.reply_to(reply_to.clone())
-        } else {
-            email
-        };
+    pub fn transport(&self) -> &KrillMailTransport {
+        &self.transport
+    }
 
-        let email = email
-            .to(message
-                .to
-                .parse::<Mailbox>()
-                .map_err(|error| KrillError::Mailer(error.to_string()))?)
-            .subject(message.subject.as_str())
-            .header(lettre::message::header::ContentType::TEXT_HTML)
-            .body(message.body.to_string())
-            .map_err(|error| KrillError::Mailer(error.to_string()))?;
+    pub fn dkim(&self) -> Option<&DkimSigner> {
+        self.dkim.as_ref()
+    }
 
-        let response = self
-            .mailer
-            .send(email)
-            .await
-            .map_err(|error| KrillError::MailDelivery(error.to_string()))?;
+    /// Builds the email, signs it when a DKIM key is set and sends it
+    pub async fn send(&self, message: &EmailEnvelopeDetails) -> KrillResult<()> {
+        let mut email = message.to_message(self.from(), self.reply_to())?;
 
-        if response.code().severity == Severity::PositiveCompletion {
-            Ok(())
-        } else {
-            Err(KrillError::Smtps(
-                response.message().map(|msg| msg.to_string()).collect(),
-            ))
+        if let Some(dkim) = self.dkim.as_ref() {
+            dkim.sign(&mut email);
         }
+
+        self.transport
+            .send(message, &self.from, self.reply_to.as_ref(), email)
+            .await
     }
 
     pub async fn test_connection(&self) -> KrillResult<bool> {
-        self.mailer
-            .test_connection()
-            .await
-            .map_err(|error| KrillError::Mailer(error.to_string()))
+        self.transport.test_connection().await
     }
 }
@@ -0,0 +1,254 @@
+use std::{
+    collections::BTreeMap,
+    sync::{Mutex, PoisonError},
+    time::Duration,
+};
+
+use krill_common::{KrillError, KrillResult};
+use krill_store::{KrillStorage, QueuedMail};
+
+use crate::{EmailEnvelopeDetails, KrillSmtps};
+
+/// How often a failed delivery is attempted again
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct MailRetryPolicy {
+    /// Attempts before the email is dead lettered
+    pub max_attempts: u32,
+    /// The delay after the first failed attempt, doubled after every other one
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+}
+
+impl Default for MailRetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 6,
+            base_delay: Duration::from_secs(30),
+            max_delay: Duration::from_secs(60 * 60),
+        }
+    }
+}
+
+impl MailRetryPolicy {
+    /// The delay after `attempts` failed attempts, `None` once the email has to be dead lettered
+    pub fn retry_delay(&self, attempts: u32) -> Option<Duration> {
+        if attempts >= self.max_attempts {
+            return None;
+        }
+
+        let factor = 2u32
+            .checked_pow(attempts.saturating_sub(1))
+            .unwrap_or(u32::MAX);
+
+        Some(self.base_delay.saturating_mul(factor).min(self.max_delay))
+    }
+}
+
+/// Limits the emails sent through each provider in a fixed window
+#[derive(Debug)]
+pub struct MailRateLimiter {
+    max_per_window: u32,
+    window: Duration,
+    /// The start of the current window in seconds since the UNIX epoch and the emails sent in it
+    windows: Mutex<BTreeMap<String, (u64, u32)>>,
+}
+
+impl Default for MailRateLimiter {
+    fn default() -> Self {
+        Self::new(60, Duration::from_secs(60))
+    }
+}
+
+impl MailRateLimiter {
+    pub fn new(max_per_window: u32, window: Duration) -> Self {
+        Self {
+            max_per_window,
+            window,
+            windows: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    /// Counts an email sent through the provider, `false` when the provider is at its limit
+    pub fn try_acquire(&self, provider: &str, now: u64) -> bool {
+        let mut windows = self.windows.lock().unwrap_or_else(PoisonError::into_inner);
+        let (start, sent) = windows.entry(provider.to_string()).or_insert((now, 0));
+
+        if now.saturating_sub(*start) >= self.window.as_secs() {
+            *start = now;
+            *sent = 0;
+        }
+
+        if *sent < self.max_per_window {
+            *sent += 1;
+
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// What [MailQueue::deliver_due] did with the due emails
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub struct MailDeliveryReport {
+    pub sent: usize,
+    pub retried: usize,
+    pub dead_lettered: usize,
+    /// Left for the next run by the rate limit
+    pub deferred: usize,
+}
+
+/// Delivers the emails queued in the storage with retries
+#[derive(Debug, Default)]
+pub struct MailQueue {
+    policy: MailRetryPolicy,
+    limiter: MailRateLimiter,
+}
+
+impl MailQueue {
+    /// The most emails delivered by a run
+    pub const BATCH_SIZE: usize = 50;
+
+    pub fn new(policy: MailRetryPolicy, limiter: MailRateLimiter) -> Self {
+        Self { policy, limiter }
+    }
+
+    pub fn policy(&self) -> &MailRetryPolicy {
+        &self.policy
+    }
+
+    /// Queues the email for the next run, see [QueuedMail::reference]
+    pub async fn enqueue(
+        storage: &KrillStorage,
+        reference: &str,
+        message: &EmailEnvelopeDetails,
+    ) -> KrillResult<QueuedMail> {
+        storage
+            .enqueue_mail(reference, bitcode::encode(message), QueuedMail::unix_now())
+            .await
+    }
+
+    /// Sends the emails due at `now`. Failed deliveries are retried with an
+    /// exponential backoff and dead lettered after the last attempt, emails that
+    /// cannot be built are dead lettered right away.
+    pub async fn deliver_due(
+        &self,
+        storage: &KrillStorage,
+        mailer: &KrillSmtps<'_>,
+        now: u64,
+    ) -> KrillResult<MailDeliveryReport> {
+        let mut report = MailDeliveryReport::default();
+
+        for mut mail in storage.due_mail(now, Self::BATCH_SIZE).await? {
+            if !self.limiter.try_acquire(mailer.provider(), now) {
+                report.deferred += 1;
+
+                continue;
+            }
+
+            let outcome = bitcode::decode::<EmailEnvelopeDetails>(&mail.message)
+                .map_err(|error| KrillError::Mailer(error.to_string()));
+            let outcome = match outcome {
+                Ok(message) => mailer.send(&message).await,
+                Err(error) => Err(error),
+            };
+
+            match outcome {
+                Ok(()) => {
+                    mail.sent(now);
+                    report.sent += 1;
+                }
+                Err(error) => {
+                    let retry_at = match error {
+                        KrillError::Mailer(_) => None,
+                        _ => self
+                            .policy
+                            .retry_delay(mail.attempts + 1)
+                            .map(|delay| now + delay.as_secs()),
+                    };
+
+                    match retry_at {
+                        Some(_) => report.retried += 1,
+                        None => report.dead_lettered += 1,
+                    }
+
+                    mail.failed(&error.to_string(), retry_at, now);
+                }
+            }
+
+            storage.update_queued_mail(&mail).await?;
+        }
+
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use krill_store::{MailStatus, UnlockSecret};
+
+    #[test]
+    fn retry_backoff() {
+        let policy = MailRetryPolicy {
+            max_attempts: 5,
+            base_delay: Duration::from_secs(30),
+            max_delay: Duration::from_secs(100),
+        };
+
+        assert_eq!(policy.retry_delay(1), Some(Duration::from_secs(30)));
+        assert_eq!(policy.retry_delay(2), Some(Duration::from_secs(60)));
+        assert_eq!(policy.retry_delay(3), Some(Duration::from_secs(100)));
+        assert_eq!(policy.retry_delay(4), Some(Duration::from_secs(100)));
+        assert_eq!(policy.retry_delay(5), None);
+
+        let policy = MailRetryPolicy {
+            max_attempts: u32::MAX,
+            ..Default::default()
+        };
+        assert_eq!(policy.retry_delay(64), Some(policy.max_delay));
+    }
+
+    #[test]
+    fn rate_limit_per_provider() {
+        let limiter = MailRateLimiter::new(2, Duration::from_secs(60));
+
+        assert!(limiter.try_acquire("smtp.example.com", 100));
+        assert!(limiter.try_acquire("smtp.example.com", 110));
+        assert!(!limiter.try_acquire("smtp.example.com", 120));
+        assert!(limiter.try_acquire("smtp.other.com", 120));
+        assert!(limiter.try_acquire("smtp.example.com", 160));
+    }
+
+    #[test]
+    fn enqueue_encodes_the_message() {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+
+        runtime.block_on(async {
+            let storage = KrillStorage::init_memory()
+                .unlock(UnlockSecret::secret([4u8; 32]))
+                .await
+                .unwrap();
+
+            let message = EmailEnvelopeDetails::new()
+                .set_to("jane@example.com")
+                .set_subject("Invitation")
+                .set_body("<p>Join us</p>");
+
+            let queued = MailQueue::enqueue(&storage, "invitation:jane@example.com", &message)
+                .await
+                .unwrap();
+
+            assert_eq!(queued.status, MailStatus::Queued);
+            assert_eq!(
+                bitcode::decode::<EmailEnvelopeDetails>(&queued.message).unwrap(),
+                message
+            );
+            assert_eq!(
+                storage.due_mail(QueuedMail::unix_now(), 10).await.unwrap(),
+                vec![queued]
+            );
+        });
+    }
+}
@@ -0,0 +1,473 @@
+use std::collections::BTreeMap;
+
+use krill_common::{KrillError, KrillResult, OrganizationInfo};
+
+use crate::EmailEnvelopeDetails;
+
+/// The transactional emails. Every language catalog has the subject, heading,
+/// body and footer of each template and the label of its button, if any.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, PartialOrd, Ord)]
+pub enum EmailTemplateName {
+    /// `expiry` and `action_url`
+    VerificationLink,
+    /// `invited_by`, `days` and `action_url`
+    Invitation,
+    /// `requested_by`, `description` and `action_url`
+    SigningRequest,
+    /// `amount`, `asset` and `account`
+    DepositReceived,
+    /// `status`, `archive` and `destination`
+    BackupReport,
+}
+
+impl EmailTemplateName {
+    pub const ALL: [Self; 5] = [
+        Self::VerificationLink,
+        Self::Invitation,
+        Self::SigningRequest,
+        Self::DepositReceived,
+        Self::BackupReport,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::VerificationLink => "verification_link",
+            Self::Invitation => "invitation",
+            Self::SigningRequest => "signing_request",
+            Self::DepositReceived => "deposit_received",
+            Self::BackupReport => "backup_report",
+        }
+    }
+}
+
+/// The organization branding of every email
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct EmailBranding {
+    pub org_name: String,
+    /// The `/logo` route of the server since images are not attached
+    pub logo_url: String,
+    pub primary_color: String,
+    pub background_color: String,
+}
+
+impl EmailBranding {
+    /// `origin` is where the server is reached, like `https://krill.example.com`
+    pub fn new(org_info: &OrganizationInfo, origin: &str) -> Self {
+        Self {
+            org_name: org_info.name.clone(),
+            logo_url: origin.trim_end_matches('/').to_string() + "/logo",
+            primary_color: org_info.color_scheme.primary_color().to_string(),
+            background_color: org_info.color_scheme.background_light().to_string(),
+        }
+    }
+}
+
+/// A subject and an HTML body ready to be sent
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
+pub struct RenderedEmail {
+    pub subject: String,
+    pub html: String,
+}
+
+impl RenderedEmail {
+    pub fn envelope(&self, to: &str) -> EmailEnvelopeDetails {
+        EmailEnvelopeDetails::new()
+            .set_to(to)
+            .set_subject(&self.subject)
+            .set_body(&self.html)
+    }
+}
+
+/// A named template and the values of its variables. Values are HTML escaped
+/// when they are rendered.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct EmailTemplate {
+    name: EmailTemplateName,
+    variables: BTreeMap<&'static str, String>,
+}
+
+impl EmailTemplate {
+    /// The language used when the server language has no translations
+    pub const FALLBACK_LANGUAGE: &str = "en-US";
+    /// The link of the button, only `https` and `http` links are rendered
+    pub const ACTION_URL: &str = "action_url";
+
+    /// Catalogs in the `.bcp47` format of the UI translations
+    const CATALOGS: [(&str, &str); 3] = [
+        ("en-US", include_str!("../translations/en-US.bcp47")),
+        ("fr", include_str!("../translations/fr.bcp47")),
+        ("sw", include_str!("../translations/sw.bcp47")),
+    ];
+
+    pub fn new(name: EmailTemplateName) -> Self {
+        Self {
+            name,
+            variables: BTreeMap::new(),
+        }
+    }
+
+    pub fn name(&self) -> EmailTemplateName {
+        self.name
+    }
+
+    pub fn set_var(mut self, name: &'static str, value: &str) -> Self {
+        self.variables.insert(name, value.to_string());
+
+        self
+    }
+
+    /// The languages with translations
+    pub fn languages() -> impl Iterator<Item = &'static str> {
+        Self::CATALOGS.iter().map(|(language, _)| *language)
+    }
+
+    /// Renders the template in `language`, a BCP 47 code like the server language.
+    /// Languages without translations fall back to the language without its region,
+    /// then to [Self::FALLBACK_LANGUAGE]. Missing keys also fall back to it.
+    pub fn render(&self, language: &str, branding: &EmailBranding) -> KrillResult<RenderedEmail> {
+        let language = Self::resolve_language(language);
+        let catalog = Self::catalog(language);
+        let fallback = Self::catalog(Self::FALLBACK_LANGUAGE);
+
+        // The subject is a header, only the body is HTML
+        let text = |part: &str, escape: fn(&str) -> String| -> KrillResult<Option<String>> {
+            let key = format!("{}_{part}", self.name.as_str());
+
+            catalog
+                .get(key.as_str())
+                .or(fallback.get(key.as_str()))
+                .map(|text| self.substitute(text, &branding.org_name, escape))
+                .transpose()
+        };
+        let required = |part: &str, escape: fn(&str) -> String| -> KrillResult<String> {
+            text(part, escape)?.ok_or(KrillError::EmailTemplate(format!(
+                "`{}_{part}` is not translated",
+                self.name.as_str()
+            )))
+        };
+
+        let subject = required("subject", str::to_string)?.replace(['\r', '\n'], " ");
+
+        let action = match (
+            text("action", escape_html)?,
+            self.variables.get(Self::ACTION_URL),
+        ) {
+            (Some(label), Some(url)) => Self::action(
+                &label,
+                &Self::safe_url(url)?,
+                &escape_html(&branding.primary_color),
+            ),
+            (Some(_), None) => {
+                return Err(KrillError::EmailTemplate(format!(
+                    "`{}` requires the `{}` variable",
+                    self.name.as_str(),
+                    Self::ACTION_URL
+                )))
+            }
+            (None, _) => String::default(),
+        };
+
+        let html = format!(
+            r#"<!DOCTYPE html>
+<html lang="{language}">
+<head>
+  <meta charset="UTF-8">
+  <title>{title}</title>
+</head>
+<body style="margin:0; padding:0; background-color:{background_color}; font-family: Arial, sans-serif;">
+  <table width="100%" cellpadding="0" cellspacing="0" border="0" style="background-color:{background_color}; padding:20px 0;">
+    <tr>
+      <td align="center">
+        <table width="400" cellpadding="0" cellspacing="0" border="0" style="background:#ffffff; border-radius:8px; border-top:4px solid {primary_color}; padding:20px; text-align:center;">
+          <tr>
+            <td style="padding-bottom:10px;">
+              <img src="{logo_url}" alt="{org_name}" height="48" style="height:48px;">
+            </td>
+          </tr>
+          <tr>
+            <td style="font-size:20px; font-weight:bold; color:{primary_color};">{heading}</td>
+          </tr>
+          <tr><td style="height:10px;"></td></tr>
+          <tr>
+            <td style="font-size:14px; color:#555;">{body}</td>
+          </tr>
+{action}          <tr><td style="height:20px;"></td></tr>
+          <tr>
+            <td style="font-size:12px; color:#888;">{footer}</td>
+          </tr>
+        </table>
+      </td>
+    </tr>
+  </table>
+</body>
+</html>
+"#,
+            title = escape_html(&subject),
+            background_color = escape_html(&branding.background_color),
+            primary_color = escape_html(&branding.primary_color),
+            logo_url = escape_html(&branding.logo_url),
+            org_name = escape_html(&branding.org_name),
+            heading = required("heading", escape_html)?,
+            body = required("body", escape_html)?,
+            footer = required("footer", escape_html)?,
+        );
+
+        Ok(RenderedEmail { subject, html })
+    }
+
+    fn action(label: &str, url: &str, primary_color: &str) -> String {
+        format!(
+            r#"          <tr><td style="height:20px;"></td></tr>
+          <tr>
+            <td>
+              <a href="{url}" style="display:inline-block; padding:12px 24px; border-radius:6px; background-color:{primary_color}; color:#ffffff; text-decoration:none; font-weight:bold;">{label}</a>
+            </td>
+          </tr>
+          <tr><td style="height:10px;"></td></tr>
+          <tr>
+            <td style="font-size:12px; color:#000; word-break:break-all;">{url}</td>
+          </tr>
+"#
+        )
+    }
+
+    /// Escapes the translation with `escape` and replaces its `{variable}`
+    /// placeholders with the escaped values. `{org_name}` is always available.
+    fn substitute(
+        &self,
+        text: &str,
+        org_name: &str,
+        escape: fn(&str) -> String,
+    ) -> KrillResult<String> {
+        let text = escape(text);
+        let mut output = String::with_capacity(text.len());
+        let mut rest = text.as_str();
+
+        while let Some(start) = rest.find('{') {
+            output.push_str(&rest[..start]);
+            rest = &rest[start + 1..];
+
+            let end = rest.find('}').ok_or(KrillError::EmailTemplate(format!(
+                "Unclosed placeholder in `{}`",
+                self.name.as_str()
+            )))?;
+            let name = &rest[..end];
+            rest = &rest[end + 1..];
+
+            let value = match name {
+                "org_name" => org_name,
+                name => self
+                    .variables
+                    .get(name)
+                    .ok_or(KrillError::EmailTemplate(format!(
+                        "`{}` requires the `{name}` variable",
+                        self.name.as_str()
+                    )))?,
+            };
+            output.push_str(&escape(value));
+        }
+        output.push_str(rest);
+
+        Ok(output)
+    }
+
+    fn safe_url(url: &str) -> KrillResult<String> {
+        let url = url.trim();
+
+        if url.starts_with("https://") || url.starts_with("http://") {
+            Ok(escape_html(url))
+        } else {
+            Err(KrillError::EmailTemplate(
+                "Links must use `https` or `http`".to_string(),
+            ))
+        }
+    }
+
+    fn resolve_language(language: &str) -> &'static str {
+        let primary = language.split('-').next().unwrap_or_default();
+
+        Self::languages()
+            .find(|candidate| candidate.eq_ignore_ascii_case(language))
+            .or(Self::languages().find(|candidate| candidate.eq_ignore_ascii_case(primary)))
+            .unwrap_or(Self::FALLBACK_LANGUAGE)
+    }
+
+    fn catalog(language: &str) -> BTreeMap<&'static str, &'static str> {
+        Self::CATALOGS
+            .iter()
+            .find(|(candidate, _)| *candidate == language)
+            .map(|(_, catalog)| {
+                catalog
+                    .lines()
+                    .map(str::trim)
+                    .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                    .filter_map(|line| line.split_once('='))
+                    .map(|(key, value)| (key.trim(), value.trim()))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+}
+
+/// Escapes text for HTML element content and quoted attribute values
+pub fn escape_html(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+
+    for char in value.chars() {
+        match char {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            char => escaped.push(char),
+        }
+    }
+
+    escaped
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn branding() -> EmailBranding {
+        let org_info = OrganizationInfo {
+            name: "Forex & Co".to_string(),
+            ..Default::default()
+        };
+
+        EmailBranding::new(&org_info, "https://krill.example.com/")
+    }
+
+    fn template(name: EmailTemplateName) -> EmailTemplate {
+        let template = EmailTemplate::new(name);
+
+        match name {
+            EmailTemplateName::VerificationLink => template.set_var("expiry", "24 hours").set_var(
+                EmailTemplate::ACTION_URL,
+                "https://krill.example.com/verify/abc",
+            ),
+            EmailTemplateName::Invitation => template
+                .set_var("invited_by", "Jane <jane@example.com>")
+                .set_var("days", "7")
+                .set_var(
+                    EmailTemplate::ACTION_URL,
+                    "https://krill.example.com/invite?a=1&b=2",
+                ),
+            EmailTemplateName::SigningRequest => template
+                .set_var("requested_by", "treasury@example.com")
+                .set_var("description", "Transfer 10 SOL to the payroll account")
+                .set_var(
+                    EmailTemplate::ACTION_URL,
+                    "https://krill.example.com/sign/1",
+                ),
+            EmailTemplateName::DepositReceived => template
+                .set_var("amount", "1,250.50")
+                .set_var("asset", "USDC")
+                .set_var("account", "4Nd1mBQtrMJVYVfKf2PJy9NZUZdTAsp7D4xWLs4gDB4T"),
+            EmailTemplateName::BackupReport => template
+                .set_var("status", "Succeeded")
+                .set_var("archive", "krill-backup-20260101T000000Z.krill")
+                .set_var("destination", "s3://backups/krill"),
+        }
+    }
+
+    /// Set `KRILL_UPDATE_GOLDEN` to rewrite the golden files after changing a template
+    #[test]
+    fn golden_files() {
+        let golden_dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("golden");
+        let update = std::env::var_os("KRILL_UPDATE_GOLDEN").is_some();
+
+        for name in EmailTemplateName::ALL {
+            for language in EmailTemplate::languages() {
+                let rendered = template(name).render(language, &branding()).unwrap();
+                let output = format!("Subject: {}\n\n{}", rendered.subject, rendered.html);
+
+                let path = golden_dir.join(format!("{}.{language}.html", name.as_str()));
+
+                if update {
+                    std::fs::write(&path, &output).unwrap();
+                }
+
+                assert_eq!(
+                    std::fs::read_to_string(&path).unwrap(),
+                    output,
+                    "`{}` differs from the rendered email",
+                    path.display()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn variables_are_escaped() {
+        let rendered = EmailTemplate::new(EmailTemplateName::SigningRequest)
+            .set_var("requested_by", "<script>alert('x')</script>")
+            .set_var("description", "\"quoted\" & more")
+            .set_var(
+                EmailTemplate::ACTION_URL,
+                "https://krill.example.com/sign?id=\"><b>",
+            )
+            .render("en-US", &branding())
+            .unwrap();
+
+        assert_eq!(
+            rendered.subject,
+            "Signature requested by <script>alert('x')</script>"
+        );
+        assert!(!rendered.html.contains("<script>"));
+        assert!(rendered
+            .html
+            .contains("&lt;script&gt;alert(&#39;x&#39;)&lt;/script&gt;"));
+        assert!(rendered.html.contains("&quot;quoted&quot; &amp; more"));
+        assert!(!rendered.html.contains("\"><b>"));
+        assert!(rendered.html.contains("alt=\"Forex &amp; Co\""));
+
+        assert_eq!(
+            EmailTemplate::new(EmailTemplateName::Invitation)
+                .set_var("invited_by", "Jane")
+                .set_var("days", "7")
+                .set_var(EmailTemplate::ACTION_URL, "javascript:alert(1)")
+                .render("en-US", &branding())
+                .err(),
+            Some(KrillError::EmailTemplate(
+                "Links must use `https` or `http`".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn languages_fall_back() {
+        let english = template(EmailTemplateName::BackupReport)
+            .render("en-US", &branding())
+            .unwrap();
+        let french = template(EmailTemplateName::BackupReport)
+            .render("fr", &branding())
+            .unwrap();
+
+        assert_ne!(english, french);
+        assert_eq!(
+            template(EmailTemplateName::BackupReport)
+                .render("fr-CA", &branding())
+                .unwrap(),
+            french
+        );
+        assert_eq!(
+            template(EmailTemplateName::BackupReport)
+                .render("ja-JP", &branding())
+                .unwrap(),
+            english
+        );
+
+        assert_eq!(
+            EmailTemplate::new(EmailTemplateName::DepositReceived)
+                .set_var("amount", "1")
+                .render("en-US", &branding())
+                .err(),
+            Some(KrillError::EmailTemplate(
+                "`deposit_received` requires the `asset` variable".to_string()
+            ))
+        );
+    }
+}
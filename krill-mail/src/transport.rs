@@ -0,0 +1,377 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    sync::{Arc, LazyLock, Mutex, PoisonError},
+    time::Duration,
+};
+
+use krill_common::{
+    KrillError, KrillResult, MailProvider, SmtpAuthMechanism, SmtpConfig, SmtpSecurity,
+};
+use lettre::{
+    message::Mailbox,
+    transport::smtp::{
+        authentication::{Credentials, Mechanism, DEFAULT_MECHANISMS},
+        client::{Certificate, Tls, TlsParameters},
+        extension::ClientId,
+        response::Severity,
+        PoolConfig,
+    },
+    AsyncFileTransport, AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor,
+};
+
+use crate::{EmailEnvelopeDetails, MailApiTransport};
+
+/// Where the emails are delivered, selected by the [MailProvider]. HTTP APIs
+/// use the [MailApiTransport]. For SMTP, a `file:///var/lib/krill/mail` host
+/// writes every email to an `.eml` file in the directory, `memory://<mailbox>`
+/// captures the emails in the named [MemoryMailbox] and every other host is an SMTP server.
+#[derive(Debug)]
+pub enum KrillMailTransport {
+    Smtp(AsyncSmtpTransport<Tokio1Executor>),
+    Api(MailApiTransport),
+    File {
+        directory: PathBuf,
+        transport: AsyncFileTransport<Tokio1Executor>,
+    },
+    Memory(MemoryMailbox),
+}
+
+impl KrillMailTransport {
+    pub const FILE_SCHEME: &str = "file";
+    pub const MEMORY_SCHEME: &str = "memory";
+
+    pub fn from_config(config: &MailProvider, hello_name: Option<&str>) -> KrillResult<Self> {
+        match config {
+            MailProvider::Smtp(smtp) if smtp.is_local() => Self::local(&smtp.host),
+            MailProvider::Smtp(smtp) => Self::smtp(smtp, hello_name).map(Self::Smtp),
+            MailProvider::Api(api) => MailApiTransport::new(api).map(Self::Api),
+        }
+    }
+
+    fn local(uri: &str) -> KrillResult<Self> {
+        match Self::scheme(uri).as_deref() {
+            Some(Self::FILE_SCHEME) => {
+                let directory = url::Url::parse(uri)
+                    .ok()
+                    .and_then(|uri| uri.to_file_path().ok())
+                    .ok_or(KrillError::Mailer(
+                        "The mail directory must be an absolute path like `file:///var/lib/krill/mail`"
+                            .to_string(),
+                    ))?;
+
+                Ok(Self::File {
+                    transport: AsyncFileTransport::new(&directory),
+                    directory,
+                })
+            }
+            Some(Self::MEMORY_SCHEME) => {
+                let name = uri
+                    .split_once("://")
+                    .map(|(_, name)| name.trim_end_matches('/'))
+                    .unwrap_or_default();
+
+                Ok(Self::Memory(MemoryMailbox::named(name)))
+            }
+            _ => Err(KrillError::Mailer(format!(
+                "`{uri}` is not a local mail transport"
+            ))),
+        }
+    }
+
+    fn smtp(
+        config: &SmtpConfig,
+        hello_name: Option<&str>,
+    ) -> KrillResult<AsyncSmtpTransport<Tokio1Executor>> {
+        let mailer_error =
+            |error: lettre::transport::smtp::Error| KrillError::Mailer(error.to_string());
+
+        let tls_parameters = || {
+            let mut parameters = TlsParameters::builder(config.host.clone());
+
+            if !config.ca_bundle.trim().is_empty() {
+                parameters = parameters.add_root_certificate(
+                    Certificate::from_pem(config.ca_bundle.as_bytes()).map_err(mailer_error)?,
+                );
+            }
+
+            parameters.build().map_err(mailer_error)
+        };
+
+        let tls = match config.security {
+            SmtpSecurity::Tls => Tls::Wrapper(tls_parameters()?),
+            SmtpSecurity::StartTls => Tls::Required(tls_parameters()?),
+            SmtpSecurity::None => Tls::None,
+        };
+
+        let mut transport = AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&config.host)
+            .port(config.port)
+            .tls(tls)
+            .timeout(Some(Duration::from_secs(config.timeout_secs.into())))
+            .pool_config(PoolConfig::new().max_size(config.pool_size.into()));
+
+        let mechanisms = match config.auth {
+            SmtpAuthMechanism::Automatic => Some(DEFAULT_MECHANISMS.to_vec()),
+            SmtpAuthMechanism::Plain => Some(vec![Mechanism::Plain]),
+            SmtpAuthMechanism::Login => Some(vec![Mechanism::Login]),
+            SmtpAuthMechanism::Xoauth2 => Some(vec![Mechanism::Xoauth2]),
+            SmtpAuthMechanism::None => None,
+        };
+
+        if let Some(mechanisms) = mechanisms {
+            transport = transport
+                .credentials(Credentials::new(
+                    config.username.clone(),
+                    config.password.clone(),
+                ))
+                .authentication(mechanisms);
+        }
+
+        if let Some(hello_name) = hello_name {
+            transport = transport.hello_name(ClientId::Domain(hello_name.to_string()));
+        }
+
+        Ok(transport.build())
+    }
+
+    /// The host of the SMTP server, the name of the API provider or the scheme of a local transport
+    pub fn provider(&self, config: &MailProvider) -> String {
+        match self {
+            Self::Smtp(_) => config
+                .smtp()
+                .map(|smtp| smtp.host.clone())
+                .unwrap_or_default(),
+            Self::Api(transport) => transport.provider().as_str().to_string(),
+            Self::File { .. } => Self::FILE_SCHEME.to_string(),
+            Self::Memory(_) => Self::MEMORY_SCHEME.to_string(),
+        }
+    }
+
+    /// Sends the email built from the details, the memory transport keeps both.
+    /// HTTP APIs take the sender and the recipients as separate fields.
+    pub async fn send(
+        &self,
+        details: &EmailEnvelopeDetails,
+        from: &Mailbox,
+        reply_to: Option<&Mailbox>,
+        email: Message,
+    ) -> KrillResult<()> {
+        match self {
+            Self::Smtp(transport) => {
+                let response = transport
+                    .send(email)
+                    .await
+                    .map_err(|error| KrillError::MailDelivery(error.to_string()))?;
+
+                if response.code().severity == Severity::PositiveCompletion {
+                    Ok(())
+                } else {
+                    Err(KrillError::Smtps(
+                        response.message().map(|msg| msg.to_string()).collect(),
+                    ))
+                }
+            }
+            Self::Api(transport) => transport.send(details, from, reply_to, &email).await,
+            Self::File {
+                directory,
+                transport,
+            } => {
+                Self::create_directory(directory)?;
+
+                transport
+                    .send(email)
+                    .await
+                    .map(|_| ())
+                    .map_err(|error| KrillError::MailDelivery(error.to_string()))
+            }
+            Self::Memory(mailbox) => {
+                mailbox.capture(details, &email);
+
+                Ok(())
+            }
+        }
+    }
+
+    pub async fn test_connection(&self) -> KrillResult<bool> {
+        match self {
+            Self::Smtp(transport) => transport
+                .test_connection()
+                .await
+                .map_err(|error| KrillError::Mailer(error.to_string())),
+            Self::Api(transport) => transport.test_connection().await,
+            Self::File { directory, .. } => {
+                Self::create_directory(directory)?;
+
+                Ok(directory.is_dir())
+            }
+            Self::Memory(_) => Ok(true),
+        }
+    }
+
+    fn scheme(uri: &str) -> Option<String> {
+        uri.split_once("://")
+            .map(|(scheme, _)| scheme.to_ascii_lowercase())
+    }
+
+    fn create_directory(directory: &Path) -> KrillResult<()> {
+        std::fs::create_dir_all(directory).map_err(|error| {
+            KrillError::MailDelivery(format!(
+                "Unable to create the mail directory `{}`. {error}",
+                directory.display()
+            ))
+        })
+    }
+}
+
+/// An email captured by a [MemoryMailbox]
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct CapturedMail {
+    /// The details the email was built from, which are easier to assert on than the encoded body
+    pub details: EmailEnvelopeDetails,
+    /// The formatted email with its headers, as it would be sent to the SMTP server
+    pub raw: Vec<u8>,
+}
+
+/// Keeps the emails in memory so the mail flows can be tested without a mail server.
+/// Mailboxes with the same name share their emails, which lets a test read the
+/// emails sent by a mailer built from a `memory://<mailbox>` URI.
+#[derive(Debug, Default, Clone)]
+pub struct MemoryMailbox(Arc<Mutex<Vec<CapturedMail>>>);
+
+static MEMORY_MAILBOXES: LazyLock<Mutex<BTreeMap<String, MemoryMailbox>>> =
+    LazyLock::new(|| Mutex::new(BTreeMap::new()));
+
+impl MemoryMailbox {
+    pub fn named(name: &str) -> Self {
+        MEMORY_MAILBOXES
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .entry(name.to_string())
+            .or_default()
+            .clone()
+    }
+
+    /// The captured emails, oldest first
+    pub fn messages(&self) -> Vec<CapturedMail> {
+        self.0
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    /// Removes the captured emails
+    pub fn take(&self) -> Vec<CapturedMail> {
+        std::mem::take(&mut *self.0.lock().unwrap_or_else(PoisonError::into_inner))
+    }
+
+    fn capture(&self, details: &EmailEnvelopeDetails, email: &Message) {
+        let captured = CapturedMail {
+            details: details.clone(),
+            raw: email.formatted(),
+        };
+
+        self.0
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(captured);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn transport_from_config() {
+        // The SMTP connection pool spawns its tasks on the Tokio runtime
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let _runtime = runtime.enter();
+
+        let smtp = |security: SmtpSecurity, ca_bundle: &str| {
+            MailProvider::Smtp(SmtpConfig {
+                security,
+                port: security.default_port(),
+                ca_bundle: ca_bundle.to_string(),
+                ..SmtpConfig::new("smtp.example.com", "user", "pass")
+            })
+        };
+
+        for security in SmtpSecurity::ALL {
+            assert!(matches!(
+                KrillMailTransport::from_config(&smtp(security, ""), Some("krill.example")),
+                Ok(KrillMailTransport::Smtp(_))
+            ));
+        }
+        assert!(KrillMailTransport::from_config(
+            &smtp(
+                SmtpSecurity::StartTls,
+                "-----BEGIN CERTIFICATE-----\nnot base64\n-----END CERTIFICATE-----\n"
+            ),
+            None
+        )
+        .is_err());
+
+        assert!(matches!(
+            KrillMailTransport::from_config(&SmtpConfig::local("file:///tmp/krill-mail").into(), None),
+            Ok(KrillMailTransport::File { directory, .. }) if directory == Path::new("/tmp/krill-mail")
+        ));
+        assert!(KrillMailTransport::from_config(
+            &SmtpConfig::local("file://relative/mail").into(),
+            None
+        )
+        .is_err());
+        assert!(matches!(
+            KrillMailTransport::from_config(
+                &SmtpConfig::local("memory://transport_from_config").into(),
+                None
+            ),
+            Ok(KrillMailTransport::Memory(_))
+        ));
+
+        assert!(SmtpConfig::local("FILE:///tmp/krill-mail").is_local());
+        assert!(SmtpConfig::local("memory://tests").is_local());
+        assert!(!SmtpConfig::new("smtp.example.com", "user", "pass").is_local());
+        assert!(!SmtpConfig::local("/tmp/krill-mail").is_local());
+    }
+
+    #[test]
+    fn file_transport_writes_eml() {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+
+        runtime.block_on(async {
+            let directory = std::env::temp_dir().join(format!(
+                "krill-mail-{}",
+                krill_store::QueuedMail::unix_now()
+            ));
+            let uri = url::Url::from_directory_path(&directory).unwrap();
+            let transport =
+                KrillMailTransport::from_config(&SmtpConfig::local(uri.as_str()).into(), None)
+                    .unwrap();
+
+            assert!(transport.test_connection().await.unwrap());
+
+            let details = EmailEnvelopeDetails::new()
+                .set_to("jane@example.com")
+                .set_subject("Hello")
+                .set_body("<p>Hi Jane</p>");
+            let from = "Support <support@example.com>".parse().unwrap();
+            let email = details.to_message(&from, None).unwrap();
+            transport.send(&details, &from, None, email).await.unwrap();
+
+            let written = std::fs::read_dir(&directory)
+                .unwrap()
+                .map(|entry| entry.unwrap().path())
+                .collect::<Vec<PathBuf>>();
+            assert_eq!(written.len(), 1);
+            assert_eq!(
+                written[0].extension().and_then(|value| value.to_str()),
+                Some("eml")
+            );
+            assert!(std::fs::read_to_string(&written[0])
+                .unwrap()
+                .contains("Subject: Hello"));
+
+            std::fs::remove_dir_all(&directory).unwrap();
+        });
+    }
+}
@@ -1,15 +1,17 @@
 use std::borrow::Cow;
 
-use krill_common::{KrillError, KrillResult};
-use lettre::{message::Mailbox, AsyncSmtpTransport, Tokio1Executor};
+use krill_common::{KrillError, KrillResult, MailProvider};
+use lettre::message::Mailbox;
 
-use crate::KrillSmtps;
+use crate::{DkimSigner, KrillMailTransport, KrillSmtps};
 
 #[derive(Debug, Default)]
 pub struct KrillSmtpsBuilder<'a> {
     from: Cow<'a, str>,
     reply_to: Option<Cow<'a, str>>,
     hello_name: Option<Cow<'a, str>>,
+    local_transports: bool,
+    dkim: Option<DkimSigner>,
 }
 
 impl<'a> KrillSmtpsBuilder<'a> {
@@ -34,19 +36,24 @@ impl<'a> KrillSmtpsBuilder<'a> {
         self
     }
 
-    pub fn build(self, smtps_uri: &str) -> KrillResult<KrillSmtps<'a>> {
-        let transport = AsyncSmtpTransport::<Tokio1Executor>::from_url(smtps_uri)
-            .map_err(|error| KrillError::Mailer(error.to_string()))?;
+    /// Allows the `file://` and `memory://` SMTP hosts, see [KrillMailTransport]
+    pub fn set_local_transports(&mut self, allowed: bool) -> &mut Self {
+        self.local_transports = allowed;
 
-        let mailer = if let Some(hello_name) = self.hello_name.as_ref() {
-            transport.hello_name(lettre::transport::smtp::extension::ClientId::Domain(
-                hello_name.to_string(),
-            ))
-        } else {
-            transport
-        };
+        self
+    }
+
+    /// Signs every email sent with the key
+    pub fn set_dkim(&mut self, dkim: DkimSigner) -> &mut Self {
+        self.dkim.replace(dkim);
+
+        self
+    }
+
+    pub fn build(self, mail: &MailProvider) -> KrillResult<KrillSmtps<'a>> {
+        mail.validate(self.local_transports)?;
 
-        let mailer = mailer.build();
+        let transport = KrillMailTransport::from_config(mail, self.hello_name.as_deref())?;
 
         let outcome = KrillSmtps {
             from: self
@@ -62,7 +69,9 @@ impl<'a> KrillSmtpsBuilder<'a> {
                 })
                 .transpose()?,
             hello_name: self.hello_name,
-            mailer,
+            provider: transport.provider(mail),
+            transport,
+            dkim: self.dkim,
         };
 
         Ok(outcome)
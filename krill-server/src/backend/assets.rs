@@ -6,7 +6,7 @@ pub async fn send_logo() -> ServerFnResult<Response> {
 
     let logo = crate::backend::SERVER_ORG_INFO
         .get()
-        .map(|info| info.logo.clone())
+        .map(|info| info.logo)
         .unwrap_or_default();
 
     MediaTypeHttp::new_one_day_cache(logo)
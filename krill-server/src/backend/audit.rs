@@ -0,0 +1,27 @@
+use dioxus::prelude::*;
+
+#[cfg(feature = "server")]
+use axum::http::HeaderMap;
+
+#[cfg(feature = "server")]
+use crate::backend::{store, ServerUtils};
+
+/// The audit log as JSON lines, oldest first
+#[post("/api/audit_log_export", headers: HeaderMap)]
+pub async fn audit_log_export() -> ServerFnResult<String> {
+    ServerUtils::sensitive_session(&headers).await?;
+
+    let storage = store().map_err(ServerUtils::error)?;
+
+    storage.export_audit_log().await.map_err(ServerUtils::error)
+}
+
+/// Verifies the hash chain of the audit log and returns the number of entries
+#[post("/api/audit_log_verify", headers: HeaderMap)]
+pub async fn audit_log_verify() -> ServerFnResult<u64> {
+    ServerUtils::admin_session(&headers).await?;
+
+    let storage = store().map_err(ServerUtils::error)?;
+
+    storage.verify_audit_log().await.map_err(ServerUtils::error)
+}
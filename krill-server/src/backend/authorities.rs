@@ -0,0 +1,94 @@
+use dioxus::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "server")]
+use {
+    axum::http::HeaderMap,
+    krill_common::{AuthoritySetup, Holder, KrillResult, ServerConfigurationState, UserRole},
+};
+
+#[cfg(feature = "server")]
+use crate::backend::{send_member_invitation, store, ServerUtils, SERVER_APP_STATE};
+
+/// The authorities that accepted their invitation and those still pending
+#[derive(Debug, Default, PartialEq, Eq, Clone, Serialize, Deserialize)]
+pub struct AuthorityProgressToUi {
+    pub threshold: u16,
+    pub verified: Vec<String>,
+    pub pending: Vec<String>,
+}
+
+#[post("/api/authority_progress", headers: HeaderMap)]
+pub async fn authority_progress() -> ServerFnResult<AuthorityProgressToUi> {
+    ServerUtils::admin_session(&headers).await?;
+
+    let storage = store().map_err(ServerUtils::error)?;
+
+    let progress = storage
+        .authority_progress()
+        .await
+        .map_err(ServerUtils::error)?;
+
+    Ok(AuthorityProgressToUi {
+        threshold: progress.threshold,
+        verified: progress.verified,
+        pending: progress.pending,
+    })
+}
+
+/// Sends a new invitation to the authorities that have not accepted yet
+#[post("/api/authority_resend_invitations", headers: HeaderMap)]
+pub async fn authority_resend_invitations() -> ServerFnResult<Vec<String>> {
+    let session = ServerUtils::sensitive_session(&headers).await?;
+
+    let storage = store().map_err(ServerUtils::error)?;
+
+    let setup = storage
+        .authority_setup()
+        .await
+        .map_err(ServerUtils::error)?
+        .unwrap_or_default();
+
+    invite_authorities(session.holder(), &setup)
+        .await
+        .map_err(ServerUtils::error)
+}
+
+/// Sends the invitation of every authority that has not accepted yet.
+/// Returns the addresses the invitation could not be sent to.
+#[cfg(feature = "server")]
+pub(crate) async fn invite_authorities(
+    superuser: &Holder,
+    setup: &AuthoritySetup,
+) -> KrillResult<Vec<String>> {
+    let storage = store()?;
+    let pending = storage.authority_progress().await?.pending;
+
+    let mut failed = Vec::<String>::new();
+
+    for email in setup
+        .authorities()
+        .iter()
+        .filter(|email| pending.contains(email))
+    {
+        if let Err(error) = send_member_invitation(superuser, email, "", UserRole::Admin).await {
+            tracing::error!("Unable to invite the authority `{email}`. Error: `{error:?}`");
+
+            failed.push(email.clone());
+        }
+    }
+
+    Ok(failed)
+}
+
+/// Completes initialization once enough authorities accepted their invitation
+#[cfg(feature = "server")]
+pub(crate) async fn check_authorities_verified() -> KrillResult<()> {
+    if SERVER_APP_STATE.get() != Some(ServerConfigurationState::AwaitingAuthorities) {
+        return Ok(());
+    }
+
+    SERVER_APP_STATE.set(store()?.complete_initialization().await?);
+
+    Ok(())
+}
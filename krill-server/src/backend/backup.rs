@@ -0,0 +1,155 @@
+use dioxus::prelude::*;
+
+#[cfg(feature = "server")]
+use {
+    axum::http::HeaderMap,
+    krill_common::{KrillError, KrillResult},
+    krill_store::{BackupDestination, SnapshotSource},
+    std::{sync::Once, time::Duration},
+    tai64::Tai64N,
+};
+
+#[cfg(feature = "server")]
+use crate::backend::{current_tenant, store, tenant_scopes, with_tenant, ServerUtils};
+
+/// Configures scheduled backups and returns the shares of the new backup key.
+/// The shares are only shown once, hand them to different administrators.
+#[post("/api/backup_configure", headers: HeaderMap)]
+pub async fn backup_configure(
+    destination: String,
+    bearer_token: Option<String>,
+    interval_hours: u32,
+    retention: u16,
+    threshold: u8,
+    shares: u8,
+) -> ServerFnResult<Vec<String>> {
+    ServerUtils::sensitive_session(&headers).await?;
+
+    backup_configure_processor(
+        &destination,
+        bearer_token,
+        interval_hours,
+        retention,
+        threshold,
+        shares,
+    )
+    .await
+    .map_err(ServerUtils::error)
+}
+
+/// Runs a backup immediately and returns the file name at the destination
+#[post("/api/backup_now", headers: HeaderMap)]
+pub async fn backup_now() -> ServerFnResult<String> {
+    ServerUtils::sensitive_session(&headers).await?;
+
+    run_backup().await.map_err(ServerUtils::error)
+}
+
+/// The backups at the configured destination, oldest first
+#[post("/api/backup_list", headers: HeaderMap)]
+pub async fn backup_list() -> ServerFnResult<Vec<String>> {
+    ServerUtils::admin_session(&headers).await?;
+
+    backup_list_processor().await.map_err(ServerUtils::error)
+}
+
+#[cfg(feature = "server")]
+async fn backup_configure_processor(
+    destination: &str,
+    bearer_token: Option<String>,
+    interval_hours: u32,
+    retention: u16,
+    threshold: u8,
+    shares: u8,
+) -> KrillResult<Vec<String>> {
+    let destination = BackupDestination::parse(destination, bearer_token)?;
+
+    let shares = store()?
+        .configure_backups(
+            destination,
+            interval_hours as u64 * 3600,
+            retention,
+            threshold,
+            shares,
+        )
+        .await?;
+
+    Ok(shares
+        .iter()
+        .map(|share| share.encode().as_str().to_string())
+        .collect())
+}
+
+#[cfg(feature = "server")]
+async fn backup_list_processor() -> KrillResult<Vec<String>> {
+    let config = store()?
+        .backup_config()
+        .await?
+        .ok_or(KrillError::BackupNotConfigured)?;
+
+    config.destination.list().await
+}
+
+#[cfg(feature = "server")]
+async fn run_backup() -> KrillResult<String> {
+    let storage = store()?;
+
+    let extra_sources = match storage.data_dir() {
+        Some(data_dir) => SnapshotSource::frost_store(data_dir)
+            .await?
+            .into_iter()
+            .collect(),
+        None => Vec::default(),
+    };
+
+    storage.run_backup(extra_sources).await
+}
+
+#[cfg(feature = "server")]
+static BACKUP_SCHEDULER: Once = Once::new();
+
+/// Checks every minute whether a backup is due. Started once even when the router is rebuilt.
+#[cfg(feature = "server")]
+pub(crate) fn spawn_backup_scheduler() {
+    const TICK: Duration = Duration::from_secs(60);
+
+    BACKUP_SCHEDULER.call_once(|| {
+        tokio::spawn(async {
+            let mut interval = tokio::time::interval(TICK);
+
+            loop {
+                interval.tick().await;
+
+                run_backup_if_due().await;
+
+                for tenant in tenant_scopes() {
+                    with_tenant(tenant, run_backup_if_due()).await;
+                }
+            }
+        });
+    });
+}
+
+/// Runs a backup of the organization serving the current task when its schedule is due
+#[cfg(feature = "server")]
+async fn run_backup_if_due() {
+    let organization = current_tenant()
+        .map(|tenant| tenant.id)
+        .unwrap_or_else(|| "main".to_string());
+
+    let due = match store() {
+        Ok(storage) => storage.is_backup_due(Tai64N::now()).await,
+        Err(error) => Err(error),
+    };
+
+    match due {
+        Ok(true) => match run_backup().await {
+            Ok(name) => tracing::info!("Backup `{name}` of `{organization}` completed"),
+            Err(error) => tracing::error!("Backup of `{organization}` failed: {error}"),
+        },
+        Ok(false) => {}
+        Err(error) => {
+            tracing::error!("Unable to check the backup schedule of `{organization}`: {error}")
+        }
+    }
+}
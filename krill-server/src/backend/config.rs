@@ -0,0 +1,101 @@
+use dioxus::prelude::*;
+
+#[cfg(feature = "server")]
+use {
+    axum::http::HeaderMap,
+    krill_common::{Holder, KrillResult},
+    krill_store::{AuditAction, AuditOutcome, OrgConfig, OrgSettings, UnlockSecret},
+};
+
+#[cfg(feature = "server")]
+use crate::backend::{apply_org_settings, store, validate_org_settings, ServerUtils};
+
+use crate::OrgSettingsToUi;
+
+/// The audit log target of configuration exports and imports
+#[cfg(feature = "server")]
+const AUDIT_TARGET: &str = "OrgConfig";
+
+/// The configuration of the organization as JSON, see [OrgConfig]. The secrets
+/// are only exported, encrypted, when a passphrase is given.
+#[post("/api/config_export", headers: HeaderMap)]
+pub async fn config_export(passphrase: String) -> ServerFnResult<String> {
+    let session = ServerUtils::sensitive_session(&headers).await?;
+    let actor = session.holder().clone();
+
+    let outcome = export_processor(&passphrase).await;
+
+    ServerUtils::audit(
+        Some(actor),
+        AuditAction::ConfigExported,
+        AUDIT_TARGET,
+        match outcome.as_ref() {
+            Ok(_) => AuditOutcome::Success,
+            Err(error) => AuditOutcome::Failure(error.to_string()),
+        },
+    )
+    .await;
+
+    outcome.map_err(ServerUtils::error)
+}
+
+/// Replaces the organization settings with those of the configuration after
+/// validating them like the settings page does
+#[post("/api/config_import", headers: HeaderMap)]
+pub async fn config_import(json: String, passphrase: String) -> ServerFnResult<OrgSettingsToUi> {
+    let session = ServerUtils::sensitive_session(&headers).await?;
+    let actor = session.holder().clone();
+
+    let outcome = import_processor(&actor, &json, &passphrase).await;
+
+    ServerUtils::audit(
+        Some(actor),
+        AuditAction::ConfigImported,
+        AUDIT_TARGET,
+        match outcome.as_ref() {
+            Ok(_) => AuditOutcome::Success,
+            Err(error) => AuditOutcome::Failure(error.to_string()),
+        },
+    )
+    .await;
+
+    outcome.map(Into::into).map_err(ServerUtils::error)
+}
+
+#[cfg(feature = "server")]
+async fn export_processor(passphrase: &str) -> KrillResult<String> {
+    let passphrase = secrets_passphrase(passphrase)?;
+
+    store()?.export_config(passphrase.as_ref()).await?.to_json()
+}
+
+#[cfg(feature = "server")]
+async fn import_processor(
+    actor: &Holder,
+    json: &str,
+    passphrase: &str,
+) -> KrillResult<OrgSettings> {
+    let storage = store()?;
+
+    let config = OrgConfig::from_json(json)?;
+    let passphrase = secrets_passphrase(passphrase)?;
+
+    let settings = config.settings(Some(&storage.org_settings().await?), passphrase.as_ref())?;
+    validate_org_settings(&settings).await?;
+
+    let settings = storage
+        .import_config(&config, passphrase.as_ref(), actor)
+        .await?;
+    apply_org_settings(&storage, &settings).await?;
+
+    Ok(settings)
+}
+
+/// An empty passphrase leaves the secrets out
+#[cfg(feature = "server")]
+pub(crate) fn secrets_passphrase(passphrase: &str) -> KrillResult<Option<UnlockSecret>> {
+    match passphrase {
+        "" => Ok(None),
+        passphrase => OrgConfig::secrets_passphrase(passphrase).map(Some),
+    }
+}
@@ -1,20 +1,101 @@
-use std::sync::OnceLock;
+use std::{
+    collections::BTreeMap,
+    sync::{OnceLock, PoisonError},
+};
 
 use async_dup::Arc;
 use async_lock::RwLock;
 use krill_common::{
-    AdminConfiguration, KrillError, KrillResult, OrganizationInfo, ServerConfigurationState,
+    AdminConfiguration, Holder, KrillError, KrillResult, OrganizationInfo, ServerConfigurationState,
+};
+use krill_mail::{DkimSigner, KrillSmtps, KrillSmtpsBuilder};
+use krill_store::{
+    BackupKey, BackupKeyShare, KrillStorage, OrgConfig, OrgSettings, StorageBackendKind,
+    StorageOptions, UnlockSecret,
 };
-use krill_mail::{KrillSmtps, KrillSmtpsBuilder};
-use krill_store::KrillStorage;
 use yansi::Paint;
+use zeroize::Zeroizing;
+
+use crate::backend::{current_tenant, load_tenants};
 
-pub static KRILL_STORAGE: OnceLock<KrillStorage> = OnceLock::new();
+pub static KRILL_STORAGE: OnceLock<std::sync::Arc<KrillStorage>> = OnceLock::new();
 pub(crate) static ADMIN_SECRET: OnceLock<Arc<RwLock<AdminConfiguration>>> = OnceLock::new();
-pub(crate) static SERVER_MAIL_CONNECTION: OnceLock<KrillSmtps> = OnceLock::new();
-pub(crate) static SERVER_ORG_INFO: OnceLock<OrganizationInfo> = OnceLock::new();
-pub(crate) static SERVER_API_KEY: OnceLock<String> = OnceLock::new();
-pub(crate) static SERVER_DOMAIN_NAME: OnceLock<String> = OnceLock::new();
+pub(crate) static SERVER_MAIL_CONNECTION: ReloadableStatic<std::sync::Arc<KrillSmtps<'static>>> =
+    ReloadableStatic::new();
+pub(crate) static SERVER_ORG_INFO: ReloadableStatic<OrganizationInfo> = ReloadableStatic::new();
+pub(crate) static SERVER_API_KEY: ReloadableStatic<String> = ReloadableStatic::new();
+pub(crate) static SERVER_DOMAIN_NAME: ReloadableStatic<String> = ReloadableStatic::new();
+
+/// A server static that is replaced when the organization settings change.
+/// Every tenant has its own value, reads and writes use the value of the
+/// organization serving the current request, see [crate::backend::current_tenant].
+/// Readers get a copy of the value so no lock is held across an `.await`.
+pub(crate) struct ReloadableStatic<T>(std::sync::RwLock<BTreeMap<Option<String>, T>>);
+
+impl<T: Clone> ReloadableStatic<T> {
+    pub(crate) const fn new() -> Self {
+        Self(std::sync::RwLock::new(BTreeMap::new()))
+    }
+
+    pub(crate) fn get(&self) -> Option<T> {
+        self.0
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(&Self::organization())
+            .cloned()
+    }
+
+    pub(crate) fn is_set(&self) -> bool {
+        self.0
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .contains_key(&Self::organization())
+    }
+
+    pub(crate) fn set(&self, value: T) {
+        self.0
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(Self::organization(), value);
+    }
+
+    /// The identifier of the tenant, `None` for the main organization
+    fn organization() -> Option<String> {
+        current_tenant().map(|tenant| tenant.id)
+    }
+}
+
+/// Sets the organization statics from the settings, at startup and every time
+/// the settings or the DKIM key change, so the server never has to restart for new settings
+pub(crate) async fn apply_org_settings(
+    storage: &KrillStorage,
+    settings: &OrgSettings,
+) -> KrillResult<()> {
+    let org_info = &settings.org_info;
+
+    let mut mail_info = KrillSmtpsBuilder::new();
+    mail_info
+        .set_from(&format!("{} <{}>", org_info.name, org_info.support_mail))
+        .set_hello_name(&settings.fqdn)
+        .set_reply_to(&format!("{} <{}>", org_info.name, org_info.support_mail))
+        .set_local_transports(local_mail_enabled());
+    if let Some(dkim_key) = storage.get_dkim_key().await? {
+        mail_info.set_dkim(DkimSigner::new(dkim_key)?);
+    }
+    let mailer = mail_info.build(&settings.mail)?;
+
+    SERVER_ORG_INFO.set(org_info.clone());
+    SERVER_DOMAIN_NAME.set(settings.fqdn.clone());
+    SERVER_API_KEY.set(settings.api_key.clone());
+    SERVER_MAIL_CONNECTION.set(std::sync::Arc::new(mailer));
+
+    Ok(())
+}
+
+/// Whether [LOCAL_MAIL_ENV] is set to `1` or `true`
+pub(crate) fn local_mail_enabled() -> bool {
+    std::env::var(LOCAL_MAIL_ENV).is_ok_and(|value| matches!(value.as_str(), "1" | "true"))
+}
 
 pub fn default_langs() -> Vec<String> {
     [
@@ -26,21 +107,56 @@ pub fn default_langs() -> Vec<String> {
     .collect()
 }
 
-pub fn store() -> KrillResult<&'static KrillStorage> {
+/// The storage of the organization serving the current request
+pub fn store() -> KrillResult<std::sync::Arc<KrillStorage>> {
+    match current_tenant() {
+        Some(tenant) => Ok(tenant.storage),
+        None => main_store(),
+    }
+}
+
+/// The storage of the main organization, which also holds the tenant registry
+pub fn main_store() -> KrillResult<std::sync::Arc<KrillStorage>> {
     KRILL_STORAGE
         .get()
+        .cloned()
         .ok_or(KrillError::GlobalStorageNotInitialized)
 }
 
-pub(crate) fn init_server_statics() -> KrillResult<()> {
+/// Whether the server starts once the statics are initialized
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub(crate) enum InitOutcome {
+    Serve,
+    /// A command like [EXPORT_CONFIG_FLAG] ran and the storage was closed
+    Exit,
+}
+
+pub(crate) fn init_server_statics() -> KrillResult<InitOutcome> {
     futures_lite::future::block_on(async {
-        let store_init = KrillStorage::init().await?;
+        let storage_options = StorageOptions::from_env_and_args()?;
+
+        if let Some(archive) = arg_value(RESTORE_FLAG) {
+            restore_backup(&archive, &storage_options).await?;
+        }
+
+        let store_init = unlock_storage(&storage_options).await?;
+
+        // Exported before the storage becomes a static so it is dropped and closed here
+        if let Some(path) = arg_value(EXPORT_CONFIG_FLAG) {
+            export_config_file(&store_init, &path).await?;
+
+            return Ok(InitOutcome::Exit);
+        }
 
         KRILL_STORAGE
-            .set(store_init)
+            .set(std::sync::Arc::new(store_init))
             .or(Err(KrillError::GlobalStorageInitializeError))?;
 
-        let store = store()?;
+        let store = &*main_store()?;
+
+        if let Some(path) = arg_value(IMPORT_CONFIG_FLAG) {
+            import_config_file(store, &path).await?;
+        }
 
         let app_state = crate::backend::state::load_app_state(store).await?;
 
@@ -121,45 +237,245 @@ pub(crate) fn init_server_statics() -> KrillResult<()> {
 
             println!("\n\n\n");
         } else {
-            let org_info = store.get_org_info().await?;
-            let org_name = org_info.name.clone();
-            let support_mail = org_info.support_mail.clone();
-            let domain = store.get_fqdn().await?.ok_or(KrillError::Store(
-                "Domain Name is not set yet the organization is initialized".to_string(),
-            ))?;
-
-            SERVER_ORG_INFO
-                .set(org_info)
-                .or(Err(KrillError::UnableToGetOrganizationInfo))?;
-
-            SERVER_DOMAIN_NAME
-                .set(domain.clone())
-                .or(Err(KrillError::Statics(
-                    "Unable to initialized the domain name static",
-                )))?;
-
-            let smtps_uri = store.get_smtps_uri().await?.ok_or(KrillError::Smtps(vec![
-                "SMTPs not set yet server state is not uninitialized".to_string(),
-            ]))?;
-
-            let mut mail_info = KrillSmtpsBuilder::new();
-            mail_info
-                .set_from(&format!("{} <{}>", org_name, support_mail))
-                .set_hello_name(&domain)
-                .set_reply_to(&format!("{} <{}>", org_name, support_mail));
-            let mailer = mail_info.build(&smtps_uri).unwrap();
-
-            SERVER_MAIL_CONNECTION
-                .set(mailer)
-                .or(Err(KrillError::Statics(
-                    "Unable to set `SERVER_MAIL_CONNECTION`",
-                )))?;
+            apply_org_settings(store, &store.org_settings().await?).await?;
         }
 
-        Ok(())
+        load_tenants(store).await?;
+
+        Ok(InitOutcome::Serve)
     })
 }
 
+/// Read instead of prompting when the server runs without a terminal, like under systemd
+pub const UNLOCK_PASSPHRASE_ENV: &str = "KRILL_UNLOCK_PASSPHRASE";
+/// Replaces the storage key and passphrase after unlocking
+pub const REKEY_FLAG: &str = "--rekey";
+/// `--restore <archive>` restores a backup into an empty data directory before starting
+pub const RESTORE_FLAG: &str = "--restore";
+/// `--export-config <file>` writes the organization configuration and exits
+pub const EXPORT_CONFIG_FLAG: &str = "--export-config";
+/// `--import-config <file>` creates or updates the organization before starting
+pub const IMPORT_CONFIG_FLAG: &str = "--import-config";
+/// Encrypts the secrets of exported configurations and decrypts those of imported ones
+pub const CONFIG_PASSPHRASE_ENV: &str = "KRILL_CONFIG_PASSPHRASE";
+/// Allows the `file://` and `memory://` mail URIs so the mail flows run without a mail server
+pub const LOCAL_MAIL_ENV: &str = "KRILL_LOCAL_MAIL";
+
+const MIN_PASSPHRASE_LEN: usize = 12;
+const MAX_UNLOCK_ATTEMPTS: u8 = 3;
+
+/// Opens the storage and unlocks it with the operator passphrase.
+/// The passphrase is chosen the first time the server starts.
+async fn unlock_storage(options: &StorageOptions) -> KrillResult<KrillStorage> {
+    let passphrase_from_env = std::env::var(UNLOCK_PASSPHRASE_ENV)
+        .ok()
+        .map(Zeroizing::new);
+
+    let mut attempts = 0u8;
+
+    let storage = loop {
+        let storage = KrillStorage::open(options).await?;
+
+        let passphrase = match passphrase_from_env.as_ref() {
+            Some(passphrase) => passphrase.clone(),
+            None if storage.is_key_ring_set().await? => {
+                prompt_passphrase("Enter the passphrase that unlocks the storage: ")?
+            }
+            None => {
+                println!(
+                    "{}",
+                    "Choose the passphrase that encrypts the storage. It is required every time the server starts."
+                        .cyan()
+                );
+
+                new_passphrase()?
+            }
+        };
+
+        match storage.unlock(UnlockSecret::Passphrase(passphrase)).await {
+            Ok(storage) => break storage,
+            Err(KrillError::InvalidUnlockSecret)
+                if passphrase_from_env.is_none() && attempts + 1 < MAX_UNLOCK_ATTEMPTS =>
+            {
+                attempts += 1;
+
+                println!("{}", "Invalid passphrase, try again.".red());
+            }
+            Err(error) => return Err(error),
+        }
+    };
+
+    if std::env::args().any(|arg| arg == REKEY_FLAG) {
+        println!("{}", "Choose the new storage passphrase.".cyan());
+
+        let storage = storage
+            .rekey(UnlockSecret::Passphrase(new_passphrase()?))
+            .await?;
+
+        println!(
+            "{}",
+            "The storage was rekeyed. Update `KRILL_UNLOCK_PASSPHRASE` if it is set.".green()
+        );
+
+        return Ok(storage);
+    }
+
+    Ok(storage)
+}
+
+fn arg_value(flag: &str) -> Option<String> {
+    let mut args = std::env::args().skip_while(|arg| arg != flag);
+    args.next()?;
+
+    args.next()
+}
+
+/// Secrets are only exported when [CONFIG_PASSPHRASE_ENV] is set
+async fn export_config_file(storage: &KrillStorage, path: &str) -> KrillResult<()> {
+    let passphrase = std::env::var(CONFIG_PASSPHRASE_ENV).unwrap_or_default();
+    let passphrase = crate::backend::secrets_passphrase(&passphrase)?;
+
+    let json = storage
+        .export_config(passphrase.as_ref())
+        .await?
+        .to_json()?;
+    async_fs::write(path, json).await?;
+
+    let notice = if passphrase.is_some() {
+        format!("The configuration was exported to `{path}` with its secrets encrypted.")
+    } else {
+        format!("The configuration was exported to `{path}` without its secrets.")
+    };
+    println!("{}", notice.green());
+
+    Ok(())
+}
+
+/// Checked like the settings page checks new settings. The passphrase of the
+/// secrets is read from [CONFIG_PASSPHRASE_ENV] or prompted for.
+async fn import_config_file(storage: &KrillStorage, path: &str) -> KrillResult<()> {
+    let config = OrgConfig::from_json(&async_fs::read_to_string(path).await?)?;
+
+    let passphrase = match (
+        config.secrets.is_some(),
+        std::env::var(CONFIG_PASSPHRASE_ENV),
+    ) {
+        (false, _) => None,
+        (true, Ok(passphrase)) => Some(UnlockSecret::passphrase(&passphrase)),
+        (true, Err(_)) => Some(UnlockSecret::Passphrase(prompt_passphrase(
+            "Enter the passphrase of the configuration secrets: ",
+        )?)),
+    };
+
+    let current = match storage.get_app_state().await? {
+        ServerConfigurationState::Uninitialized => None,
+        _ => Some(storage.org_settings().await?),
+    };
+    let settings = config.settings(current.as_ref(), passphrase.as_ref())?;
+    crate::backend::validate_org_settings(&settings).await?;
+
+    let actor = Holder::new_with_tld(&settings.org_info.support_mail)?.set_superuser();
+    storage
+        .import_config(&config, passphrase.as_ref(), &actor)
+        .await?;
+
+    println!(
+        "{}",
+        format!("The configuration in `{path}` was imported.").green()
+    );
+
+    Ok(())
+}
+
+/// Prompts for the backup key shares until enough were entered and restores the archive
+async fn restore_backup(archive: &str, options: &StorageOptions) -> KrillResult<()> {
+    if options.backend != StorageBackendKind::Fjall {
+        return Err(KrillError::InvalidStorageOptions(
+            "Backups can only be restored into a storage on disk".to_string(),
+        ));
+    }
+
+    let bytes = async_fs::read(archive).await?;
+    let header = krill_store::BackupArchive::header(&bytes)?;
+
+    println!("{}", "Enter the backup key shares, one at a time.".cyan());
+
+    let mut shares = Vec::<BackupKeyShare>::new();
+
+    let key = loop {
+        let share = match prompt_passphrase("Backup key share: ")
+            .map(|share| BackupKeyShare::decode(&share))
+        {
+            Ok(Ok(share)) if share.key_id == header.key_id => share,
+            Ok(Ok(_)) => {
+                println!("{}", "The share belongs to another backup key.".red());
+
+                continue;
+            }
+            Ok(Err(error)) => {
+                println!("{}", error.to_string().red());
+
+                continue;
+            }
+            Err(error) => return Err(error),
+        };
+
+        let threshold = share.threshold as usize;
+        if !shares.iter().any(|existing| existing.index == share.index) {
+            shares.push(share);
+        }
+
+        if shares.len() >= threshold {
+            break BackupKey::combine(&shares)?;
+        }
+
+        println!(
+            "{}",
+            format!("{} of {threshold} shares entered.", shares.len()).cyan()
+        );
+    };
+
+    let data_dir = options.data_dir().await?;
+    KrillStorage::restore_backup(bytes, key, &data_dir).await?;
+
+    println!(
+        "{}",
+        "The backup was restored. Unlock it with the passphrase the storage had when the backup was made."
+            .green()
+    );
+
+    Ok(())
+}
+
+fn prompt_passphrase(prompt: &str) -> KrillResult<Zeroizing<String>> {
+    rpassword::prompt_password(prompt)
+        .map(Zeroizing::new)
+        .map_err(|error| KrillError::Io(error.kind()))
+}
+
+fn new_passphrase() -> KrillResult<Zeroizing<String>> {
+    loop {
+        let passphrase = prompt_passphrase("New passphrase: ")?;
+
+        if passphrase.chars().count() < MIN_PASSPHRASE_LEN {
+            println!(
+                "{}",
+                format!("The passphrase must have at least {MIN_PASSPHRASE_LEN} characters.").red()
+            );
+
+            continue;
+        }
+
+        if prompt_passphrase("Repeat the passphrase: ")? != passphrase {
+            println!("{}", "The passphrases do not match.".red());
+
+            continue;
+        }
+
+        return Ok(passphrase);
+    }
+}
+
 #[derive(Debug, Clone, Copy)]
 pub struct ConfigPrint(usize);
 
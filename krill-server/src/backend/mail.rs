@@ -0,0 +1,322 @@
+use dioxus::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "server")]
+use {
+    axum::http::HeaderMap,
+    krill_common::{Holder, KrillError, KrillResult},
+    krill_mail::{DkimSigner, EmailEnvelopeDetails, MailQueue},
+    krill_store::{AuditAction, AuditOutcome, DkimAlgorithm, QueuedMail},
+    std::{
+        sync::{LazyLock, Once},
+        time::Duration,
+    },
+    tokio::sync::Notify,
+};
+
+#[cfg(feature = "server")]
+use crate::backend::{
+    apply_org_settings, current_tenant, store, tenant_scopes, with_tenant, ServerUtils,
+    SERVER_MAIL_CONNECTION, SERVER_ORG_INFO,
+};
+
+/// The delivery state of the latest email about something, like an invitation
+#[derive(Debug, Default, PartialEq, Eq, Clone, Serialize, Deserialize)]
+pub struct MailStatusToUi {
+    /// What the email is about, used to retry it
+    pub reference: String,
+    /// `queued`, `sent` or `failed`
+    pub status: String,
+    pub attempts: u32,
+    pub last_error: Option<String>,
+}
+
+#[cfg(feature = "server")]
+impl From<QueuedMail> for MailStatusToUi {
+    fn from(mail: QueuedMail) -> Self {
+        Self {
+            status: mail.status.as_str().to_string(),
+            reference: mail.reference,
+            attempts: mail.attempts,
+            last_error: mail.last_error,
+        }
+    }
+}
+
+/// The DNS record of the key outgoing emails are signed with
+#[derive(Debug, Default, PartialEq, Eq, Clone, Serialize, Deserialize)]
+pub struct DkimRecordToUi {
+    /// `rsa` or `ed25519`
+    pub algorithm: String,
+    pub selector: String,
+    pub domain: String,
+    /// Where the TXT record is published, like `krill._domainkey.example.com`
+    pub dns_name: String,
+    pub txt_record: String,
+    /// The record as a zone file entry with the value split into 255 byte strings
+    pub zone_entry: String,
+}
+
+#[cfg(feature = "server")]
+impl From<&DkimSigner> for DkimRecordToUi {
+    fn from(signer: &DkimSigner) -> Self {
+        let key = signer.key();
+
+        Self {
+            algorithm: key.algorithm.as_str().to_string(),
+            selector: key.selector.clone(),
+            domain: key.domain.clone(),
+            dns_name: signer.dns_record_name(),
+            txt_record: signer.dns_txt_record(),
+            zone_entry: signer.dns_zone_entry(),
+        }
+    }
+}
+
+/// The audit log target of DKIM key changes
+#[cfg(feature = "server")]
+const DKIM_AUDIT_TARGET: &str = "DKIM";
+
+/// The references the queued emails are looked up by
+#[cfg(feature = "server")]
+pub(crate) struct MailReference;
+
+#[cfg(feature = "server")]
+impl MailReference {
+    pub(crate) fn invitation(email: &str) -> String {
+        format!("invitation:{email}")
+    }
+
+    pub(crate) fn verification(holder: &Holder) -> String {
+        format!("verification:{}", holder.email_address())
+    }
+}
+
+#[cfg(feature = "server")]
+static MAIL_QUEUE: LazyLock<MailQueue> = LazyLock::new(MailQueue::default);
+
+/// Wakes the worker as soon as an email is queued
+#[cfg(feature = "server")]
+static MAIL_QUEUED: LazyLock<Notify> = LazyLock::new(Notify::new);
+
+#[cfg(feature = "server")]
+static MAIL_WORKER: Once = Once::new();
+
+/// Queues the email in the storage of the current organization. It is sent
+/// by the mail worker so a failing mail server does not fail the request.
+#[cfg(feature = "server")]
+pub(crate) async fn queue_mail(
+    reference: &str,
+    message: &EmailEnvelopeDetails,
+) -> KrillResult<QueuedMail> {
+    let queued = MailQueue::enqueue(&*store()?, reference, message).await?;
+
+    MAIL_QUEUED.notify_one();
+
+    Ok(queued)
+}
+
+/// Delivers the queued emails of every organization. Started once even when the router is rebuilt.
+#[cfg(feature = "server")]
+pub(crate) fn spawn_mail_worker() {
+    const TICK: Duration = Duration::from_secs(15);
+
+    MAIL_WORKER.call_once(|| {
+        tokio::spawn(async {
+            loop {
+                // Woken early when an email is queued, retries are picked up on the tick
+                let _ = tokio::time::timeout(TICK, MAIL_QUEUED.notified()).await;
+
+                deliver_queued_mail().await;
+
+                for tenant in tenant_scopes() {
+                    with_tenant(tenant, deliver_queued_mail()).await;
+                }
+            }
+        });
+    });
+}
+
+#[cfg(feature = "server")]
+async fn deliver_queued_mail() {
+    /// Sent and dead lettered emails are kept a week for the UI
+    const RETENTION_SECS: u64 = 7 * 24 * 60 * 60;
+
+    let organization = current_tenant()
+        .map(|tenant| tenant.id)
+        .unwrap_or_else(|| "main".to_string());
+
+    // The mail server is only known once the organization is configured
+    let Some(mailer) = SERVER_MAIL_CONNECTION.get() else {
+        return;
+    };
+
+    let now = QueuedMail::unix_now();
+
+    let outcome = async {
+        let storage = store()?;
+
+        storage
+            .prune_mail_queue(now.saturating_sub(RETENTION_SECS))
+            .await?;
+
+        MAIL_QUEUE.deliver_due(&storage, &mailer, now).await
+    }
+    .await;
+
+    match outcome {
+        Ok(report) if report.dead_lettered > 0 => tracing::warn!(
+            "{} emails of `{organization}` could not be delivered",
+            report.dead_lettered
+        ),
+        Ok(_) => {}
+        Err(error) => {
+            tracing::error!("Unable to deliver the queued emails of `{organization}`: {error}")
+        }
+    }
+}
+
+#[post("/api/mail_status", headers: HeaderMap)]
+pub async fn mail_status(reference: String) -> ServerFnResult<Option<MailStatusToUi>> {
+    ServerUtils::admin_session(&headers).await?;
+
+    let storage = store().map_err(ServerUtils::error)?;
+
+    storage
+        .mail_status(&reference)
+        .await
+        .map(|mail| mail.map(Into::into))
+        .map_err(ServerUtils::error)
+}
+
+/// Queues a dead lettered email again
+#[post("/api/mail_retry", headers: HeaderMap)]
+pub async fn mail_retry(reference: String) -> ServerFnResult<MailStatusToUi> {
+    ServerUtils::admin_session(&headers).await?;
+
+    let storage = store().map_err(ServerUtils::error)?;
+
+    let mail = storage
+        .retry_mail(&reference, QueuedMail::unix_now())
+        .await
+        .map_err(ServerUtils::error)?;
+
+    MAIL_QUEUED.notify_one();
+
+    Ok(mail.into())
+}
+
+/// The delivery state of the verification link sent to the support email address.
+/// The superuser is not signed in yet so only the state is returned.
+#[server]
+pub async fn superuser_link_mail_status() -> ServerFnResult<String> {
+    let org_info = SERVER_ORG_INFO
+        .get()
+        .ok_or(KrillError::ServerOrgInfoNotSet)
+        .map_err(ServerUtils::error)?;
+    let holder = Holder::new_with_tld(&org_info.support_mail)
+        .map_err(ServerUtils::error)?
+        .set_superuser();
+
+    let storage = store().map_err(ServerUtils::error)?;
+
+    storage
+        .mail_status(&MailReference::verification(&holder))
+        .await
+        .map(|mail| {
+            mail.map(|mail| mail.status.as_str().to_string())
+                .unwrap_or_default()
+        })
+        .map_err(ServerUtils::error)
+}
+
+/// The DNS record of the DKIM key, `None` when emails are not signed
+#[post("/api/dkim", headers: HeaderMap)]
+pub async fn dkim_record() -> ServerFnResult<Option<DkimRecordToUi>> {
+    ServerUtils::admin_session(&headers).await?;
+
+    let storage = store().map_err(ServerUtils::error)?;
+
+    storage
+        .get_dkim_key()
+        .await
+        .and_then(|key| key.map(DkimSigner::new).transpose())
+        .map(|signer| signer.as_ref().map(Into::into))
+        .map_err(ServerUtils::error)
+}
+
+/// Replaces the DKIM key with a new `rsa` or `ed25519` key. Emails are signed with
+/// it right away so the TXT record should be published under a new selector first.
+#[post("/api/dkim_generate", headers: HeaderMap)]
+pub async fn dkim_generate(
+    algorithm: String,
+    selector: String,
+    domain: String,
+) -> ServerFnResult<DkimRecordToUi> {
+    let session = ServerUtils::sensitive_session(&headers).await?;
+
+    let outcome = async {
+        let algorithm = match algorithm.as_str() {
+            "rsa" => DkimAlgorithm::Rsa,
+            "ed25519" => DkimAlgorithm::Ed25519,
+            _ => {
+                return Err(KrillError::Dkim(format!(
+                    "Unsupported algorithm `{algorithm}`"
+                )))
+            }
+        };
+
+        let signer = DkimSigner::new(DkimSigner::generate(
+            algorithm,
+            selector.trim(),
+            domain.trim(),
+        )?)?;
+
+        let storage = store()?;
+        storage.set_dkim_key(signer.key()).await?;
+        apply_org_settings(&storage, &storage.org_settings().await?).await?;
+
+        Ok::<_, KrillError>(DkimRecordToUi::from(&signer))
+    }
+    .await;
+
+    ServerUtils::audit(
+        Some(session.holder().clone()),
+        AuditAction::DkimKeyChanged,
+        DKIM_AUDIT_TARGET,
+        match outcome.as_ref() {
+            Ok(_) => AuditOutcome::Success,
+            Err(error) => AuditOutcome::Failure(error.to_string()),
+        },
+    )
+    .await;
+
+    outcome.map_err(ServerUtils::error)
+}
+
+/// Stops signing outgoing emails
+#[post("/api/dkim_remove", headers: HeaderMap)]
+pub async fn dkim_remove() -> ServerFnResult<()> {
+    let session = ServerUtils::sensitive_session(&headers).await?;
+
+    let outcome = async {
+        let storage = store()?;
+        storage.remove_dkim_key().await?;
+
+        apply_org_settings(&storage, &storage.org_settings().await?).await
+    }
+    .await;
+
+    ServerUtils::audit(
+        Some(session.holder().clone()),
+        AuditAction::DkimKeyChanged,
+        DKIM_AUDIT_TARGET,
+        match outcome.as_ref() {
+            Ok(_) => AuditOutcome::Success,
+            Err(error) => AuditOutcome::Failure(error.to_string()),
+        },
+    )
+    .await;
+
+    outcome.map_err(ServerUtils::error)
+}
@@ -0,0 +1,419 @@
+use dioxus::{fullstack::response::Response, prelude::*};
+use serde::{Deserialize, Serialize};
+
+use crate::MailStatusToUi;
+
+#[cfg(feature = "server")]
+use {
+    axum::http::HeaderMap,
+    krill_common::{AuthTokenDetails, Holder, KrillError, KrillResult, UserRole},
+    krill_mail::{EmailTemplate, EmailTemplateName},
+    krill_store::{
+        AuditAction, AuditOutcome, MemberInvitation, MemberProfile, MemberQuery, MemberStatus,
+    },
+};
+
+#[cfg(feature = "server")]
+use crate::backend::{
+    build_cookie, check_authorities_verified, initial_second_factor, queue_mail,
+    redirect_error_header, redirect_success_header, store, MailReference, ServerUtils,
+    SERVER_DOMAIN_NAME,
+};
+
+/// A member as shown in the member directory
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+pub struct MemberToUi {
+    pub email: String,
+    pub display_name: String,
+    /// `administrator` or `member`
+    pub role: String,
+    pub wallets: Vec<String>,
+    pub has_frost_identifier: bool,
+    /// `invited`, `active` or `suspended`
+    pub status: String,
+    /// The delivery of the invitation email of invited members
+    pub invitation_mail: Option<MailStatusToUi>,
+}
+
+#[derive(Debug, Default, PartialEq, Eq, Clone, Serialize, Deserialize)]
+pub struct MembersPageToUi {
+    pub members: Vec<MemberToUi>,
+    pub total: usize,
+    pub page: usize,
+    pub page_size: usize,
+}
+
+#[cfg(feature = "server")]
+impl From<MemberProfile> for MemberToUi {
+    fn from(member: MemberProfile) -> Self {
+        Self {
+            email: member.email,
+            display_name: member.display_name,
+            role: member.role.as_str().to_string(),
+            wallets: member.wallets,
+            has_frost_identifier: member.frost_identifier.is_some(),
+            status: member.status.as_str().to_string(),
+            invitation_mail: None,
+        }
+    }
+}
+
+/// Searches the member directory. An empty `status` lists members of every status.
+#[post("/api/members_list", headers: HeaderMap)]
+pub async fn members_list(
+    search: String,
+    status: String,
+    page: usize,
+) -> ServerFnResult<MembersPageToUi> {
+    ServerUtils::admin_session(&headers).await?;
+
+    let storage = store().map_err(ServerUtils::error)?;
+
+    let query = MemberQuery {
+        search,
+        status: MemberStatus::parse(status.trim()),
+        page,
+        ..Default::default()
+    };
+
+    let outcome = storage.members(&query).await.map_err(ServerUtils::error)?;
+
+    let mut members = Vec::with_capacity(outcome.members.len());
+    for member in outcome.members {
+        let invitation_mail = match member.status {
+            MemberStatus::Invited => storage
+                .mail_status(&MailReference::invitation(&member.email))
+                .await
+                .map_err(ServerUtils::error)?,
+            _ => None,
+        };
+
+        let mut member = MemberToUi::from(member);
+        member.invitation_mail = invitation_mail.map(Into::into);
+        members.push(member);
+    }
+
+    Ok(MembersPageToUi {
+        members,
+        total: outcome.total,
+        page: query.page,
+        page_size: query.page_size,
+    })
+}
+
+/// Invites a member by email. Inviting a member that has not accepted yet sends a new link.
+#[post("/api/member_invite", headers: HeaderMap)]
+pub async fn member_invite(
+    email: String,
+    display_name: String,
+    role: String,
+) -> ServerFnResult<MemberToUi> {
+    let session = ServerUtils::sensitive_session(&headers).await?;
+    let actor = session.holder().clone();
+
+    let outcome = match parse_role(&role) {
+        Ok(role) => send_member_invitation(&actor, &email, &display_name, role).await,
+        Err(error) => Err(error),
+    };
+
+    ServerUtils::audit(
+        Some(actor),
+        AuditAction::MemberInvited,
+        email.trim(),
+        match outcome.as_ref() {
+            Ok(_) => AuditOutcome::Success,
+            Err(error) => AuditOutcome::Failure(error.to_string()),
+        },
+    )
+    .await;
+
+    outcome.map(Into::into).map_err(ServerUtils::error)
+}
+
+#[post("/api/member_update", headers: HeaderMap)]
+pub async fn member_update(
+    email: String,
+    display_name: String,
+    role: String,
+) -> ServerFnResult<MemberToUi> {
+    let session = ServerUtils::sensitive_session(&headers).await?;
+
+    let outcome = async {
+        let role = parse_role(&role)?;
+
+        store()?.update_member(&email, &display_name, role).await
+    }
+    .await;
+
+    ServerUtils::audit(
+        Some(session.holder().clone()),
+        AuditAction::MemberUpdated,
+        email.trim(),
+        match outcome.as_ref() {
+            Ok(_) => AuditOutcome::Success,
+            Err(error) => AuditOutcome::Failure(error.to_string()),
+        },
+    )
+    .await;
+
+    outcome.map(Into::into).map_err(ServerUtils::error)
+}
+
+/// Suspends or reactivates a member. Suspended members cannot use their sessions.
+#[post("/api/member_set_suspended", headers: HeaderMap)]
+pub async fn member_set_suspended(email: String, suspended: bool) -> ServerFnResult<MemberToUi> {
+    let session = ServerUtils::sensitive_session(&headers).await?;
+
+    let outcome = async {
+        if session.holder().email_address() == email.trim().to_lowercase() {
+            return Err(KrillError::Forbidden);
+        }
+
+        store()?.set_member_suspended(&email, suspended).await
+    }
+    .await;
+
+    ServerUtils::audit(
+        Some(session.holder().clone()),
+        if suspended {
+            AuditAction::MemberSuspended
+        } else {
+            AuditAction::MemberReactivated
+        },
+        email.trim(),
+        match outcome.as_ref() {
+            Ok(_) => AuditOutcome::Success,
+            Err(error) => AuditOutcome::Failure(error.to_string()),
+        },
+    )
+    .await;
+
+    outcome.map(Into::into).map_err(ServerUtils::error)
+}
+
+#[post("/api/member_remove", headers: HeaderMap)]
+pub async fn member_remove(email: String) -> ServerFnResult<()> {
+    let session = ServerUtils::sensitive_session(&headers).await?;
+
+    let outcome = async { store()?.remove_member(&email).await }.await;
+
+    ServerUtils::audit(
+        Some(session.holder().clone()),
+        AuditAction::MemberRemoved,
+        email.trim(),
+        match outcome.as_ref() {
+            Ok(_) => AuditOutcome::Success,
+            Err(error) => AuditOutcome::Failure(error.to_string()),
+        },
+    )
+    .await;
+
+    outcome.map_err(ServerUtils::error)
+}
+
+/// Links a wallet the member signs in with
+#[post("/api/member_link_wallet", headers: HeaderMap)]
+pub async fn member_link_wallet(email: String, address: String) -> ServerFnResult<MemberToUi> {
+    let session = ServerUtils::sensitive_session(&headers).await?;
+
+    let outcome = async { store()?.link_member_wallet(&email, &address).await }.await;
+
+    ServerUtils::audit(
+        Some(session.holder().clone()),
+        AuditAction::MemberWalletLinked,
+        email.trim(),
+        match outcome.as_ref() {
+            Ok(_) => AuditOutcome::Success,
+            Err(error) => AuditOutcome::Failure(error.to_string()),
+        },
+    )
+    .await;
+
+    outcome.map(Into::into).map_err(ServerUtils::error)
+}
+
+#[post("/api/member_unlink_wallet", headers: HeaderMap)]
+pub async fn member_unlink_wallet(email: String, address: String) -> ServerFnResult<MemberToUi> {
+    let session = ServerUtils::sensitive_session(&headers).await?;
+
+    let outcome = async { store()?.unlink_member_wallet(&email, &address).await }.await;
+
+    ServerUtils::audit(
+        Some(session.holder().clone()),
+        AuditAction::MemberWalletUnlinked,
+        email.trim(),
+        match outcome.as_ref() {
+            Ok(_) => AuditOutcome::Success,
+            Err(error) => AuditOutcome::Failure(error.to_string()),
+        },
+    )
+    .await;
+
+    outcome.map(Into::into).map_err(ServerUtils::error)
+}
+
+/// Sets the hex encoded FROST identifier of the member, an empty `identifier` removes it
+#[post("/api/member_set_frost_identifier", headers: HeaderMap)]
+pub async fn member_set_frost_identifier(
+    email: String,
+    identifier: String,
+) -> ServerFnResult<MemberToUi> {
+    let session = ServerUtils::sensitive_session(&headers).await?;
+
+    let outcome = async {
+        let identifier = match identifier.trim() {
+            "" => None,
+            identifier => Some(decode_frost_identifier(identifier)?),
+        };
+
+        store()?
+            .set_member_frost_identifier(&email, identifier)
+            .await
+    }
+    .await;
+
+    ServerUtils::audit(
+        Some(session.holder().clone()),
+        AuditAction::MemberFrostIdentifierChanged,
+        email.trim(),
+        match outcome.as_ref() {
+            Ok(_) => AuditOutcome::Success,
+            Err(error) => AuditOutcome::Failure(error.to_string()),
+        },
+    )
+    .await;
+
+    outcome.map(Into::into).map_err(ServerUtils::error)
+}
+
+/// The link sent in the invitation email. Activates the member and issues the session cookie.
+#[get("/member-invitation/{token}")]
+pub async fn member_accept_invitation(token: String) -> ServerFnResult<Response> {
+    let mut res = Response::new(axum::body::Body::empty());
+
+    let storage = store().map_err(ServerUtils::error)?;
+
+    let accepted = match MemberInvitation::decode_token(&token) {
+        Ok(token) => storage.accept_member_invitation(&token).await,
+        Err(error) => Err(error),
+    };
+
+    let member = match accepted {
+        Ok(member) => member,
+        Err(error) => {
+            ServerUtils::audit(
+                None,
+                AuditAction::MemberInvitationAccepted,
+                "",
+                AuditOutcome::Failure(error.to_string()),
+            )
+            .await;
+
+            redirect_error_header(&mut res, error)?;
+
+            return Ok(res);
+        }
+    };
+
+    check_authorities_verified()
+        .await
+        .map_err(ServerUtils::error)?;
+
+    let holder = member.holder().map_err(ServerUtils::error)?;
+
+    let details = initial_second_factor(&storage, AuthTokenDetails::new(holder.clone()))
+        .await
+        .map_err(ServerUtils::error)?;
+    let store_key = storage
+        .set_auth_token(AuthTokenDetails::generate_token(), details.clone())
+        .await
+        .map_err(ServerUtils::error)?;
+
+    ServerUtils::audit(
+        Some(holder.clone()),
+        AuditAction::MemberInvitationAccepted,
+        holder.identity(),
+        AuditOutcome::Success,
+    )
+    .await;
+    ServerUtils::audit(
+        Some(holder.clone()),
+        AuditAction::SessionIssued,
+        holder.identity(),
+        AuditOutcome::Success,
+    )
+    .await;
+
+    redirect_success_header(&mut res)?;
+    build_cookie(
+        &mut res,
+        &details.auth_token_as_cookie_raw(store_key, "Lax"),
+    )?;
+
+    Ok(res)
+}
+
+#[cfg(feature = "server")]
+fn parse_role(role: &str) -> KrillResult<UserRole> {
+    match role.trim() {
+        "administrator" => Ok(UserRole::Admin),
+        "member" => Ok(UserRole::Member),
+        _ => Err(KrillError::InvalidRequestData(role.to_string())),
+    }
+}
+
+#[cfg(feature = "server")]
+fn decode_frost_identifier(identifier: &str) -> KrillResult<Vec<u8>> {
+    let mut buffer = [0u8; MemberProfile::FROST_IDENTIFIER_LEN];
+
+    if identifier.len() != buffer.len() * 2 {
+        return Err(KrillError::InvalidRequestData(identifier.to_string()));
+    }
+
+    faster_hex::hex_decode(identifier.as_bytes(), &mut buffer)
+        .or(Err(KrillError::InvalidRequestData(identifier.to_string())))?;
+
+    Ok(buffer.to_vec())
+}
+
+/// Invites the member and emails the invitation link
+#[cfg(feature = "server")]
+pub(crate) async fn send_member_invitation(
+    actor: &Holder,
+    email: &str,
+    display_name: &str,
+    role: UserRole,
+) -> KrillResult<MemberProfile> {
+    let domain = SERVER_DOMAIN_NAME
+        .get()
+        .ok_or(KrillError::Statics("`SERVER_DOMAIN_NAME` not set"))?;
+    let storage = store()?;
+
+    let (token, member) = storage
+        .invite_member(email, display_name, role, actor)
+        .await?;
+
+    let uri = ServerUtils::origin(&domain)
+        + "/member-invitation/"
+        + MemberInvitation::encode_token(&token).as_str();
+
+    let invited_by = match actor.user_display() {
+        "" => actor.email_address(),
+        user_display => user_display,
+    };
+
+    let days = MemberInvitation::VALIDITY.as_secs() / (24 * 60 * 60);
+
+    let message = ServerUtils::render_email(
+        EmailTemplate::new(EmailTemplateName::Invitation)
+            .set_var("invited_by", invited_by)
+            .set_var("days", &days.to_string())
+            .set_var(EmailTemplate::ACTION_URL, &uri),
+    )
+    .await?
+    .envelope(&member.holder()?.email_envelope_details());
+
+    queue_mail(&MailReference::invitation(&member.email), &message).await?;
+
+    Ok(member)
+}
@@ -19,6 +19,34 @@ pub use languages::*;
 mod verification;
 pub use verification::*;
 
+mod siws;
+
+mod totp;
+pub use totp::*;
+
+mod backup;
+pub use backup::*;
+
+mod audit;
+
+mod members;
+pub use members::*;
+
+mod authorities;
+pub use authorities::*;
+
+mod org_settings;
+pub use org_settings::*;
+
+mod tenants;
+pub use tenants::*;
+
+mod config;
+pub use config::*;
+
+mod mail;
+pub use mail::*;
+
 #[cfg(feature = "server")]
 mod server_utils;
 #[cfg(feature = "server")]
@@ -0,0 +1,320 @@
+use dioxus::prelude::*;
+use krill_common::MailProvider;
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "server")]
+use {
+    axum::http::HeaderMap,
+    krill_common::{BrandColors, Holder, KrillError, KrillResult},
+    krill_store::{AuditAction, AuditOutcome, OrgSettings, OrgSettingsRevision},
+};
+
+/// The audit log target of settings changes
+#[cfg(feature = "server")]
+const AUDIT_TARGET: &str = "OrgSettings";
+
+#[cfg(feature = "server")]
+use crate::backend::{
+    apply_org_settings, check_api_key, check_mail_provider, current_tenant, store,
+    validate_org_info, ServerUtils,
+};
+
+/// The organization settings as shown on the settings page. The SMTP password,
+/// the mail API key and the RPC API key are never sent back.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Serialize, Deserialize)]
+pub struct OrgSettingsToUi {
+    pub name: String,
+    pub support_mail: String,
+    pub logo: Vec<u8>,
+    pub favicon: Vec<u8>,
+    pub primary_color: String,
+    pub secondary_color: String,
+    pub accent_color: String,
+    pub background_dark: String,
+    pub background_light: String,
+    pub fqdn: String,
+    /// The mail provider settings without the password or the API key
+    pub mail: MailProvider,
+}
+
+/// Changes to the organization settings. Empty images, SMTP password, mail API key
+/// and RPC API key keep the current ones.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Serialize, Deserialize)]
+pub struct OrgSettingsUpdate {
+    pub name: String,
+    pub support_mail: String,
+    pub logo: Vec<u8>,
+    pub favicon: Vec<u8>,
+    pub primary_color: String,
+    pub secondary_color: String,
+    pub accent_color: String,
+    pub background_dark: String,
+    pub background_light: String,
+    pub fqdn: String,
+    pub mail: MailProvider,
+    pub api_key: String,
+}
+
+/// Settings that were replaced, newest first on the settings page
+#[derive(Debug, Default, PartialEq, Eq, Clone, Serialize, Deserialize)]
+pub struct OrgSettingsRevisionToUi {
+    pub version: u64,
+    pub name: String,
+    pub support_mail: String,
+    pub fqdn: String,
+    /// `host:port` of the SMTP server or the endpoint of the mail API
+    pub mail_server: String,
+    pub replaced_by: String,
+    /// UTC date and time the settings were replaced
+    pub replaced: String,
+}
+
+#[cfg(feature = "server")]
+impl From<OrgSettings> for OrgSettingsToUi {
+    fn from(settings: OrgSettings) -> Self {
+        let org_info = settings.org_info;
+        let colors = &org_info.color_scheme;
+
+        Self {
+            primary_color: colors.primary_color().to_string(),
+            secondary_color: colors.secondary_color().to_string(),
+            accent_color: colors.accent_color().to_string(),
+            background_dark: colors.background_dark().to_string(),
+            background_light: colors.background_light().to_string(),
+            name: org_info.name,
+            support_mail: org_info.support_mail,
+            logo: org_info.logo,
+            favicon: org_info.favicon,
+            fqdn: settings.fqdn,
+            mail: settings.mail.redacted(),
+        }
+    }
+}
+
+#[cfg(feature = "server")]
+impl From<OrgSettingsRevision> for OrgSettingsRevisionToUi {
+    fn from(revision: OrgSettingsRevision) -> Self {
+        Self {
+            version: revision.version,
+            replaced: format_utc(revision.unix_timestamp()),
+            name: revision.settings.org_info.name,
+            support_mail: revision.settings.org_info.support_mail,
+            fqdn: revision.settings.fqdn,
+            mail_server: revision.settings.mail.server(),
+            replaced_by: revision.replaced_by,
+        }
+    }
+}
+
+#[cfg(feature = "server")]
+impl OrgSettingsUpdate {
+    fn apply(self, current: OrgSettings) -> OrgSettings {
+        let mut org_info = current.org_info;
+
+        org_info.name = self.name.trim().to_string();
+        org_info.support_mail = self.support_mail.trim().to_string();
+
+        if !self.logo.is_empty() {
+            org_info.logo = self.logo;
+        }
+
+        if !self.favicon.is_empty() {
+            org_info.favicon = self.favicon;
+        }
+
+        let mut colors = BrandColors::new();
+        colors
+            .set_primary(self.primary_color.trim())
+            .set_secondary(self.secondary_color.trim())
+            .set_accent(self.accent_color.trim())
+            .set_background_dark(self.background_dark.trim())
+            .set_background_light(self.background_light.trim());
+        org_info.color_scheme.set_brand_colors(colors);
+
+        let keep_if_empty = |value: String, current: String| match value.trim() {
+            "" => current,
+            value => value.to_string(),
+        };
+
+        OrgSettings {
+            org_info,
+            mail: self.mail.trimmed().keep_secret(&current.mail),
+            api_key: keep_if_empty(self.api_key, current.api_key),
+            fqdn: self.fqdn.trim().to_string(),
+        }
+    }
+}
+
+#[post("/api/org_settings", headers: HeaderMap)]
+pub async fn org_settings() -> ServerFnResult<OrgSettingsToUi> {
+    ServerUtils::admin_session(&headers).await?;
+
+    let storage = store().map_err(ServerUtils::error)?;
+
+    storage
+        .org_settings()
+        .await
+        .map(Into::into)
+        .map_err(ServerUtils::error)
+}
+
+/// Validates the settings like the configuration wizard does, stores them and
+/// reloads them into the server without a restart
+#[post("/api/org_settings_update", headers: HeaderMap)]
+pub async fn org_settings_update(update: OrgSettingsUpdate) -> ServerFnResult<OrgSettingsToUi> {
+    let session = ServerUtils::sensitive_session(&headers).await?;
+    let actor = session.holder().clone();
+
+    let outcome = update_processor(&actor, update).await;
+
+    ServerUtils::audit(
+        Some(actor),
+        AuditAction::OrgSettingsUpdated,
+        AUDIT_TARGET,
+        match outcome.as_ref() {
+            Ok(_) => AuditOutcome::Success,
+            Err(error) => AuditOutcome::Failure(error.to_string()),
+        },
+    )
+    .await;
+
+    outcome.map(Into::into).map_err(ServerUtils::error)
+}
+
+#[post("/api/org_settings_history", headers: HeaderMap)]
+pub async fn org_settings_history() -> ServerFnResult<Vec<OrgSettingsRevisionToUi>> {
+    ServerUtils::admin_session(&headers).await?;
+
+    let storage = store().map_err(ServerUtils::error)?;
+
+    storage
+        .org_settings_history()
+        .await
+        .map(|history| history.into_iter().map(Into::into).collect())
+        .map_err(ServerUtils::error)
+}
+
+/// Restores a previous version. The settings are validated again since
+/// credentials that worked before might have been revoked since.
+#[post("/api/org_settings_rollback", headers: HeaderMap)]
+pub async fn org_settings_rollback(version: u64) -> ServerFnResult<OrgSettingsToUi> {
+    let session = ServerUtils::sensitive_session(&headers).await?;
+    let actor = session.holder().clone();
+
+    let outcome = rollback_processor(&actor, version).await;
+
+    ServerUtils::audit(
+        Some(actor),
+        AuditAction::OrgSettingsRolledBack,
+        &format!("{}:{version}", AUDIT_TARGET),
+        match outcome.as_ref() {
+            Ok(_) => AuditOutcome::Success,
+            Err(error) => AuditOutcome::Failure(error.to_string()),
+        },
+    )
+    .await;
+
+    outcome.map(Into::into).map_err(ServerUtils::error)
+}
+
+#[cfg(feature = "server")]
+async fn update_processor(actor: &Holder, update: OrgSettingsUpdate) -> KrillResult<OrgSettings> {
+    let storage = store()?;
+
+    let settings = update.apply(storage.org_settings().await?);
+    validate_org_settings(&settings).await?;
+
+    storage.update_org_settings(&settings, actor).await?;
+    apply_org_settings(&storage, &settings).await?;
+
+    Ok(settings)
+}
+
+#[cfg(feature = "server")]
+async fn rollback_processor(actor: &Holder, version: u64) -> KrillResult<OrgSettings> {
+    let storage = store()?;
+
+    let revision = storage.org_settings_revision(version).await?;
+    validate_org_settings(&revision.settings).await?;
+
+    let settings = storage.rollback_org_settings(version, actor).await?;
+    apply_org_settings(&storage, &settings).await?;
+
+    Ok(settings)
+}
+
+/// The same checks the configuration wizard runs before creating the organization
+#[cfg(feature = "server")]
+pub(crate) async fn validate_org_settings(settings: &OrgSettings) -> KrillResult<()> {
+    let org_info = &settings.org_info;
+
+    validate_org_info(org_info)?;
+
+    if settings.fqdn.is_empty() || settings.fqdn.contains(['/', ' ', '@']) {
+        return Err(KrillError::InvalidOrgSettings(
+            "Invalid domain name".to_string(),
+        ));
+    }
+
+    // Requests are routed to tenants by their domain name
+    if current_tenant().is_some_and(|tenant| tenant.domain != settings.fqdn) {
+        return Err(KrillError::InvalidOrgSettings(
+            "The domain name of a tenant cannot be changed".to_string(),
+        ));
+    }
+
+    check_mail_provider(
+        &settings.mail,
+        &org_info.name,
+        &org_info.support_mail,
+        &settings.fqdn,
+    )
+    .await?;
+
+    check_api_key(&settings.api_key).await
+}
+
+/// `YYYY-MM-DD HH:MM UTC` without pulling in a date crate
+#[cfg(feature = "server")]
+pub(crate) fn format_utc(unix_timestamp: u64) -> String {
+    let days = (unix_timestamp / 86_400) as i64;
+    let minutes_of_day = (unix_timestamp % 86_400) / 60;
+
+    // Converts days since the UNIX epoch to a civil date
+    let shifted = days + 719_468;
+    let era = shifted.div_euclid(146_097);
+    let day_of_era = shifted.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{year:04}-{month:02}-{day:02} {:02}:{:02} UTC",
+        minutes_of_day / 60,
+        minutes_of_day % 60
+    )
+}
+
+#[cfg(all(test, feature = "server"))]
+mod test {
+    use super::*;
+
+    #[test]
+    fn utc_dates() {
+        assert_eq!(format_utc(0), "1970-01-01 00:00 UTC");
+        assert_eq!(format_utc(951_782_400), "2000-02-29 00:00 UTC");
+        assert_eq!(format_utc(1_709_251_199), "2024-02-29 23:59 UTC");
+        assert_eq!(format_utc(1_735_689_599), "2024-12-31 23:59 UTC");
+        // 2100 is not a leap year
+        assert_eq!(format_utc(4_107_456_000), "2100-02-28 00:00 UTC");
+        assert_eq!(format_utc(4_107_542_400), "2100-03-01 00:00 UTC");
+    }
+}
@@ -1,36 +1,164 @@
+use std::time::Duration;
+
 use axum::body::Body as AxumBody;
-use axum::http::HeaderValue;
+use axum::http::{HeaderMap, HeaderValue};
 use dioxus::{fullstack::response::Response, prelude::*};
-use krill_common::{AuthTokenDetails, KrillError, OrganizationInfo};
+use krill_common::{
+    AuthTokenDetails, Holder, KrillError, KrillResult, OrganizationInfo, SecondFactorState,
+    UserRole,
+};
+use krill_mail::{EmailBranding, EmailTemplate, RenderedEmail};
+use krill_store::{AuditAction, AuditOutcome, AuthTokenType, KrillStorage};
 
-use crate::backend::SERVER_ORG_INFO;
+use crate::backend::{store, SERVER_DOMAIN_NAME, SERVER_ORG_INFO};
 
 pub struct ServerUtils;
 
 impl ServerUtils {
-    pub(crate) fn request_get_org() -> ServerFnResult<&'static OrganizationInfo> {
+    pub const STEP_UP_WINDOW: Duration = Duration::from_secs(5 * 60);
+
+    pub(crate) fn request_get_org() -> ServerFnResult<OrganizationInfo> {
         SERVER_ORG_INFO
             .get()
             .ok_or(KrillError::ServerOrgInfoNotSet)
-            .map_err(|error| {
-                let error_message = "Error-OrgNotFound";
+            .map_err(Self::error)
+    }
+
+    /// Converts the error into a server function error with the HTTP status of the error
+    /// and a [krill_common::KrillErrorBody] as the details. Redacted errors are only logged.
+    pub(crate) fn error(error: KrillError) -> ServerFnError {
+        let body = error.to_error_body();
+
+        if error.is_redacted() {
+            tracing::error!("{}. Error: `{error:?}`", body.code);
+        }
+
+        ServerFnError::ServerError {
+            message: body.code.clone() + ": " + body.message.as_str(),
+            code: body.status,
+            details: serde_json::to_value(&body).ok(),
+        }
+    }
+
+    /// Appends to the audit log. The event already happened so a failure to record it
+    /// is logged instead of failing the request.
+    pub(crate) async fn audit(
+        actor: Option<Holder>,
+        action: AuditAction,
+        target: &str,
+        outcome: AuditOutcome,
+    ) {
+        let outcome = match store() {
+            Ok(storage) => storage
+                .append_audit_entry(actor, action, target, outcome)
+                .await
+                .map(|_| ()),
+            Err(error) => Err(error),
+        };
+
+        if let Err(error) = outcome {
+            tracing::error!(
+                "Unable to record `{}` in the audit log. Error: `{error:?}`",
+                action.as_str()
+            );
+        }
+    }
+
+    /// Renders the email in the server language with the branding of the organization
+    pub(crate) async fn render_email(template: EmailTemplate) -> KrillResult<RenderedEmail> {
+        let domain = SERVER_DOMAIN_NAME
+            .get()
+            .ok_or(KrillError::Statics("`SERVER_DOMAIN_NAME` not set"))?;
+        let org_info = SERVER_ORG_INFO
+            .get()
+            .ok_or(KrillError::ServerOrgInfoNotSet)?;
+        let language = store()?.get_server_language().await?;
+
+        template.render(
+            language.code(),
+            &EmailBranding::new(&org_info, &Self::origin(&domain)),
+        )
+    }
+
+    /// The origin used in links sent to users
+    pub fn origin(domain: &str) -> String {
+        if domain.starts_with("localhost") || domain.starts_with("127.0.0.1") {
+            String::from("http://") + domain + ":8080"
+        } else {
+            String::from("https://") + domain
+        }
+    }
+
+    /// The store key and session of the user making the request even if the second factor
+    /// has not been verified yet. Only the TOTP server functions should use this.
+    pub(crate) async fn pending_session(
+        headers: &HeaderMap,
+    ) -> ServerFnResult<(AuthTokenType, AuthTokenDetails)> {
+        let session = crate::backend::fetch_session(headers)
+            .await
+            .map_err(Self::error)?;
+
+        session.ok_or(Self::error(KrillError::InvalidAuthToken))
+    }
+
+    /// The session of the user making the request
+    pub(crate) async fn session(headers: &HeaderMap) -> ServerFnResult<AuthTokenDetails> {
+        let (_, session) = Self::pending_session(headers).await?;
 
-                tracing::error!("{error_message}. Error: `{error:?}`");
+        if !session.is_second_factor_satisfied() {
+            return Err(Self::error(KrillError::TotpRequired));
+        }
 
-                ServerFnError::ServerError {
-                    message: error_message.to_string() + ": Internal server error",
-                    code: 500,
-                    details: None,
-                }
-            })
+        Ok(session)
+    }
+
+    /// The session of the user making the request which must belong to a superuser or an administrator
+    pub(crate) async fn admin_session(headers: &HeaderMap) -> ServerFnResult<AuthTokenDetails> {
+        let session = Self::session(headers).await?;
+
+        match session.holder().role() {
+            UserRole::Superuser | UserRole::Admin => Ok(session),
+            UserRole::Member => Err(Self::error(KrillError::Forbidden)),
+        }
+    }
+
+    /// An administrator session for sensitive operations. Holders enrolled in TOTP
+    /// must have verified a code within [Self::STEP_UP_WINDOW].
+    pub(crate) async fn sensitive_session(headers: &HeaderMap) -> ServerFnResult<AuthTokenDetails> {
+        let session = Self::admin_session(headers).await?;
+
+        let storage = store().map_err(Self::error)?;
+        Self::check_step_up(&storage, &session)
+            .await
+            .map_err(Self::error)?;
+
+        Ok(session)
+    }
+
+    /// The second factor state of the session is set when it is issued, so the
+    /// enrollment is read again in case the holder enrolled in TOTP since.
+    pub(crate) async fn check_step_up(
+        storage: &KrillStorage,
+        session: &AuthTokenDetails,
+    ) -> KrillResult<()> {
+        if session.second_factor_verified_within(Self::STEP_UP_WINDOW) {
+            return Ok(());
+        }
+
+        match session.second_factor() {
+            SecondFactorState::NotRequired
+                if !storage
+                    .is_totp_enrolled(session.holder().identity())
+                    .await? =>
+            {
+                Ok(())
+            }
+            _ => Err(KrillError::TotpStepUpRequired),
+        }
     }
 
     pub fn parse_token(token: &str) -> ServerFnResult<[u8; AuthTokenDetails::AUTH_TOKEN_LEN]> {
-        AuthTokenDetails::decode_token(token).map_err(|error| ServerFnError::ServerError {
-            message: error.to_string(),
-            code: 400,
-            details: None,
-        })
+        AuthTokenDetails::decode_token(token).map_err(Self::error)
     }
 }
 
@@ -73,3 +201,62 @@ impl MediaTypeHttp {
         self.set_header("Cache-Control", HeaderValue::from_static(value))
     }
 }
+
+#[cfg(test)]
+mod test {
+    use krill_common::{Totp, TotpRecoveryCodes};
+    use krill_store::UnlockSecret;
+
+    use super::*;
+
+    /// Sessions issued before the holder enrolled in TOTP must step up
+    #[tokio::test]
+    async fn step_up_reads_current_enrollment() {
+        let storage = KrillStorage::init_memory()
+            .unlock(UnlockSecret::secret([4u8; 32]))
+            .await
+            .unwrap();
+
+        let holder = Holder::new_with_tld("admin@krill.example")
+            .unwrap()
+            .set_admin();
+        let session =
+            AuthTokenDetails::new(holder.clone()).set_second_factor(SecondFactorState::NotRequired);
+
+        ServerUtils::check_step_up(&storage, &session)
+            .await
+            .unwrap();
+
+        storage
+            .begin_totp_enrollment(
+                holder.identity(),
+                &Totp::generate(),
+                &TotpRecoveryCodes::generate(),
+            )
+            .await
+            .unwrap();
+        // An unconfirmed enrollment does not protect the holder yet
+        ServerUtils::check_step_up(&storage, &session)
+            .await
+            .unwrap();
+
+        let mut enrollment = storage
+            .get_totp_enrollment(holder.identity())
+            .await
+            .unwrap()
+            .unwrap();
+        enrollment.confirmed = true;
+        storage
+            .set_totp_enrollment(holder.identity(), enrollment)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            ServerUtils::check_step_up(&storage, &session).await.err(),
+            Some(KrillError::TotpStepUpRequired)
+        );
+        ServerUtils::check_step_up(&storage, &session.set_second_factor_verified())
+            .await
+            .unwrap();
+    }
+}
@@ -0,0 +1,133 @@
+use dioxus::{fullstack::response::Response, prelude::*};
+
+#[cfg(feature = "server")]
+use {
+    krill_common::{
+        AuthTokenDetails, KrillError, KrillResult, RandomChars, SiwsChallenge, SiwsMessage,
+    },
+    krill_store::{AuditAction, AuditOutcome},
+    std::time::SystemTime,
+};
+
+#[cfg(feature = "server")]
+use crate::backend::{
+    build_cookie, initial_second_factor, store, ServerUtils, SERVER_DOMAIN_NAME, SERVER_ORG_INFO,
+};
+
+/// Issues a Sign In With Solana message for the wallet `address` to sign
+#[server]
+pub async fn siws_challenge(address: String) -> ServerFnResult<String> {
+    siws_challenge_processor(address.trim())
+        .await
+        .map_err(ServerUtils::error)
+}
+
+/// Verifies the signed Sign In With Solana message and issues the session cookie
+#[post("/api/siws_verify")]
+pub async fn siws_verify(message: String, signature: String) -> ServerFnResult<Response> {
+    let (store_key, auth_token_details) = siws_verify_processor(&message, &signature)
+        .await
+        .map_err(ServerUtils::error)?;
+
+    let mut res = Response::new(axum::body::Body::empty());
+    *res.status_mut() = StatusCode::OK;
+
+    build_cookie(
+        &mut res,
+        &auth_token_details.auth_token_as_cookie_raw(store_key, "Strict"),
+    )?;
+
+    Ok(res)
+}
+
+#[cfg(feature = "server")]
+async fn siws_challenge_processor(address: &str) -> KrillResult<String> {
+    let domain = SERVER_DOMAIN_NAME
+        .get()
+        .ok_or(KrillError::Statics("`SERVER_DOMAIN_NAME` not set"))?;
+    let org_name = SERVER_ORG_INFO
+        .get()
+        .map(|org_info| org_info.name)
+        .ok_or(KrillError::ServerOrgInfoNotSet)?;
+
+    let storage = store()?;
+
+    let nonce = RandomChars::<16>::generate().as_string().to_string();
+    let now = SystemTime::now();
+
+    let message = SiwsMessage::new(&domain, address)
+        .set_statement(&format!("Sign in to {org_name}"))
+        .set_uri(&ServerUtils::origin(&domain))
+        .set_chain_id("mainnet")
+        .set_nonce(&nonce)
+        .set_issued_at(now)
+        .set_expiration_time(now + SiwsChallenge::DEFAULT_EXPIRY);
+
+    // Reject addresses that are not Ed25519 public keys before storing anything
+    message.public_key()?;
+
+    // Challenges that are never signed would otherwise stay in the storage
+    storage.prune_siws_challenges().await?;
+
+    let challenge = SiwsChallenge::new(&message, &nonce);
+    storage.set_siws_challenge(challenge).await?;
+
+    Ok(message.to_string())
+}
+
+#[cfg(feature = "server")]
+async fn siws_verify_processor(
+    message: &str,
+    signature: &str,
+) -> KrillResult<([u8; AuthTokenDetails::AUTH_TOKEN_LEN], AuthTokenDetails)> {
+    let domain = SERVER_DOMAIN_NAME
+        .get()
+        .ok_or(KrillError::Statics("`SERVER_DOMAIN_NAME` not set"))?;
+
+    let storage = store()?;
+
+    let parsed = SiwsMessage::parse(message)?;
+    let nonce = parsed
+        .nonce
+        .as_deref()
+        .ok_or(KrillError::SiwsNonceNotFound)?;
+
+    let challenge = storage
+        .take_siws_challenge(nonce)
+        .await?
+        .ok_or(KrillError::SiwsNonceNotFound)?;
+
+    // Only messages issued by this server are accepted
+    if challenge.message != message || challenge.address != parsed.address {
+        return Err(KrillError::SiwsNonceNotFound);
+    }
+
+    parsed.validate(&domain, &challenge.nonce, SystemTime::now())?;
+    parsed.verify_signature(&SiwsMessage::decode_signature(signature)?)?;
+
+    // Only wallets linked to an active member of the organization sign in
+    let holder = storage
+        .wallet_member(&parsed.address)
+        .await?
+        .holder()?
+        .set_wallet(&parsed.address);
+
+    let auth_token_details = initial_second_factor(&storage, AuthTokenDetails::new(holder)).await?;
+    let store_key = storage
+        .set_auth_token(
+            AuthTokenDetails::generate_token(),
+            auth_token_details.clone(),
+        )
+        .await?;
+
+    let holder = auth_token_details.holder();
+    ServerUtils::audit(
+        Some(holder.clone()),
+        AuditAction::SessionIssued,
+        holder.identity(),
+        AuditOutcome::Success,
+    )
+    .await;
+
+    Ok((store_key, auth_token_details))
+}
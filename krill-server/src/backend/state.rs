@@ -1,7 +1,3 @@
-use std::sync::OnceLock;
-
-use async_dup::Arc;
-use async_lock::RwLock;
 use axum::{
     extract::Request,
     http::HeaderMap,
@@ -10,12 +6,15 @@ use axum::{
 };
 use axum_extra::extract::CookieJar;
 use krill_common::{AuthTokenDetails, KrillError, KrillResult, ServerConfigurationState};
-use krill_store::KrillStorage;
+use krill_store::{AuthTokenType, KrillStorage};
 
-use crate::{backend::store, RouteUtils};
+use crate::{
+    backend::{store, ReloadableStatic},
+    RouteUtils,
+};
 
-pub(crate) static SERVER_APP_STATE: OnceLock<Arc<RwLock<ServerConfigurationState>>> =
-    OnceLock::new();
+pub(crate) static SERVER_APP_STATE: ReloadableStatic<ServerConfigurationState> =
+    ReloadableStatic::new();
 
 pub(crate) async fn check_app_state(request: Request, next: Next) -> impl IntoResponse {
     let path = request.uri().path();
@@ -62,9 +61,11 @@ pub(crate) async fn check_app_state(request: Request, next: Next) -> impl IntoRe
     }
 
     if state == ServerConfigurationState::LoginInitialization {
+        // Authorities are invited when the organization is created and may accept before the superuser
         if path.starts_with("/verify-support-mail")
             || path.starts_with("/verification-support-mail-link")
             || path.starts_with("/api/send_superuser_login_auth_link")
+            || path.starts_with("/member-invitation/")
         {
             return next.run(request).await;
         } else {
@@ -72,8 +73,22 @@ pub(crate) async fn check_app_state(request: Request, next: Next) -> impl IntoRe
         }
     }
 
+    if state == ServerConfigurationState::AwaitingAuthorities {
+        if path == RouteUtils::AUTHORITIES_PENDING
+            || path == RouteUtils::TWO_FACTOR
+            || path.starts_with("/api/totp_")
+            || path.starts_with("/api/authority_")
+            || path.starts_with("/member-invitation/")
+        {
+            return next.run(request).await;
+        }
+
+        return Redirect::to(RouteUtils::AUTHORITIES_PENDING).into_response();
+    }
+
     // Paths that should not be run if the server state is initialized
-    if path.starts_with("/api/verification_stream")
+    if path == RouteUtils::AUTHORITIES_PENDING
+        || path.starts_with("/api/verification_stream")
         || path.starts_with("/verification-support-mail-link")
         || path.starts_with("/api/send_superuser_login_auth_link")
         || path.starts_with("/verify-support-mail")
@@ -81,12 +96,29 @@ pub(crate) async fn check_app_state(request: Request, next: Next) -> impl IntoRe
         return Redirect::to(RouteUtils::DASHBOARD).into_response();
     }
 
-    let fetch_cookie_outcome = fetch_cookie(request.headers()).await;
+    // Sign In With Solana and invitation links are how members without a session obtain one
+    if path.starts_with("/api/siws_challenge")
+        || path.starts_with("/api/siws_verify")
+        || path.starts_with("/member-invitation/")
+    {
+        return next.run(request).await;
+    }
+
+    let fetch_session_outcome = fetch_session(request.headers()).await;
 
-    match fetch_cookie_outcome {
-        Ok(Some(_)) => {
+    match fetch_session_outcome {
+        Ok(Some((_, details))) => {
             let path = request.uri().path();
 
+            // Sessions waiting for the second factor can only enroll or verify TOTP
+            if !details.is_second_factor_satisfied() {
+                if path == RouteUtils::TWO_FACTOR || path.starts_with("/api/totp_") {
+                    return next.run(request).await;
+                }
+
+                return Redirect::to(RouteUtils::TWO_FACTOR).into_response();
+            }
+
             if path == RouteUtils::LOGIN {
                 Redirect::to(RouteUtils::DASHBOARD).into_response()
             } else {
@@ -106,38 +138,30 @@ pub(crate) async fn check_app_state(request: Request, next: Next) -> impl IntoRe
 }
 
 fn redirect_to_error(error: KrillError) -> Redirect {
-    let error = match error {
-        KrillError::Transmit(error) => error,
-        KrillError::HttpClient(error) => error,
-        KrillError::HttpResponse(error) => error,
-        _ => {
-            tracing::error!("{:?}", error);
-
-            "Encountered fatal error".to_string()
-        }
-    };
-
-    let error_route = crate::RouteUtils::ERRORS.to_string() + "/" + error.as_str();
+    if error.is_redacted() {
+        tracing::error!("{}. Error: `{:?}`", error.code(), error);
+    }
 
-    Redirect::to(&error_route)
+    Redirect::to(&crate::RouteUtils::error_route(&error))
 }
 
 pub(crate) async fn load_app_state(store: &KrillStorage) -> KrillResult<ServerConfigurationState> {
-    let app_state = store.get_app_state().await?;
+    let mut app_state = store.get_app_state().await?;
 
-    SERVER_APP_STATE
-        .set(Arc::new(RwLock::new(app_state)))
-        .or(Err(KrillError::UnableToSetAppState))?;
+    // Servers restarted before enough authorities accepted must keep waiting for them
+    if app_state == ServerConfigurationState::Initialized {
+        app_state = store.complete_initialization().await?;
+    }
+
+    SERVER_APP_STATE.set(app_state);
 
     Ok(app_state)
 }
 
 pub async fn server_state() -> KrillResult<ServerConfigurationState> {
-    let state = SERVER_APP_STATE
+    SERVER_APP_STATE
         .get()
-        .ok_or(KrillError::AppStateMachineNotInitialized)?;
-
-    Ok(*state.read().await)
+        .ok_or(KrillError::AppStateMachineNotInitialized)
 }
 
 pub(crate) async fn fetch_cookie(
@@ -166,3 +190,27 @@ pub(crate) async fn fetch_cookie(
         .await?
         .map(|_| cookie_hash))
 }
+
+/// Fetches the store key and the details of the session in the auth cookie, if any
+pub(crate) async fn fetch_session(
+    headers: &HeaderMap,
+) -> KrillResult<Option<(AuthTokenType, AuthTokenDetails)>> {
+    let cookie_hash = if let Some(cookie_hash) = fetch_cookie(headers).await? {
+        cookie_hash
+    } else {
+        return Ok(None);
+    };
+
+    let storage = store()?;
+
+    let session = storage
+        .get_auth_token(cookie_hash)
+        .await?
+        .filter(|details| !details.is_expired());
+
+    if let Some(details) = session.as_ref() {
+        storage.check_member_not_suspended(details.holder()).await?;
+    }
+
+    Ok(session.map(|details| (cookie_hash, details)))
+}
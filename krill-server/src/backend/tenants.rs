@@ -0,0 +1,285 @@
+use dioxus::prelude::*;
+use krill_common::MailProvider;
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "server")]
+use {
+    axum::{
+        extract::Request,
+        http::{header::HOST, HeaderMap},
+        middleware::Next,
+        response::IntoResponse,
+    },
+    krill_common::{
+        Holder, KrillError, KrillResult, OrganizationInfo, ServerConfigurationState, UserRole,
+        FAVICON_DEFAULT, LOGO_DEFAULT,
+    },
+    krill_store::{AuditAction, AuditOutcome, KrillStorage, OrgSettings, Tenant},
+    std::{collections::BTreeMap, future::Future, sync::PoisonError},
+};
+
+#[cfg(feature = "server")]
+use crate::backend::{
+    apply_org_settings, format_utc, load_app_state, main_store, validate_org_settings, ServerUtils,
+};
+
+/// The audit log target of tenant changes
+#[cfg(feature = "server")]
+const AUDIT_TARGET: &str = "Tenant";
+
+/// The organization of a new tenant. Its superuser signs in with the support
+/// email address on the domain of the tenant.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Serialize, Deserialize)]
+pub struct TenantSetup {
+    pub id: String,
+    pub name: String,
+    pub support_mail: String,
+    pub fqdn: String,
+    pub mail: MailProvider,
+    pub api_key: String,
+}
+
+#[derive(Debug, Default, PartialEq, Eq, Clone, Serialize, Deserialize)]
+pub struct TenantToUi {
+    pub id: String,
+    pub domain: String,
+    /// UTC date and time the tenant was created
+    pub created: String,
+}
+
+#[cfg(feature = "server")]
+impl From<Tenant> for TenantToUi {
+    fn from(tenant: Tenant) -> Self {
+        Self {
+            created: format_utc(tenant.unix_timestamp()),
+            id: tenant.id,
+            domain: tenant.domain,
+        }
+    }
+}
+
+#[cfg(feature = "server")]
+impl TenantSetup {
+    fn settings(&self) -> OrgSettings {
+        let org_info = OrganizationInfo {
+            name: self.name.trim().to_string(),
+            support_mail: self.support_mail.trim().to_string(),
+            logo: LOGO_DEFAULT.to_vec(),
+            favicon: FAVICON_DEFAULT.to_vec(),
+            ..Default::default()
+        };
+
+        OrgSettings {
+            org_info,
+            mail: self.mail.clone().trimmed(),
+            api_key: self.api_key.trim().to_string(),
+            fqdn: Tenant::normalize_domain(&self.fqdn),
+        }
+    }
+}
+
+/// The tenant serving the current request
+#[cfg(feature = "server")]
+#[derive(Debug, Clone)]
+pub(crate) struct TenantScope {
+    pub(crate) id: String,
+    pub(crate) domain: String,
+    pub(crate) storage: std::sync::Arc<KrillStorage>,
+}
+
+#[cfg(feature = "server")]
+tokio::task_local! {
+    static CURRENT_TENANT: TenantScope;
+}
+
+/// The tenants by their domain name. Tenants are never removed while the server runs.
+#[cfg(feature = "server")]
+static TENANT_DOMAINS: std::sync::RwLock<BTreeMap<String, TenantScope>> =
+    std::sync::RwLock::new(BTreeMap::new());
+
+/// The tenant serving the current request, `None` for the main organization
+#[cfg(feature = "server")]
+pub(crate) fn current_tenant() -> Option<TenantScope> {
+    CURRENT_TENANT.try_with(Clone::clone).ok()
+}
+
+/// Every tenant served, background tasks run for each of them
+#[cfg(feature = "server")]
+pub(crate) fn tenant_scopes() -> Vec<TenantScope> {
+    TENANT_DOMAINS
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .values()
+        .cloned()
+        .collect()
+}
+
+/// Runs `future` as the tenant, the storage and the organization statics resolve to those of the tenant
+#[cfg(feature = "server")]
+pub(crate) async fn with_tenant<F: Future>(tenant: TenantScope, future: F) -> F::Output {
+    CURRENT_TENANT.scope(tenant, future).await
+}
+
+/// Runs `future` as the tenant, or as the main organization for `None`. Spawned tasks
+/// do not inherit the tenant, capture it with [current_tenant] before spawning them.
+#[cfg(feature = "server")]
+pub(crate) async fn in_scope<F: Future>(tenant: Option<TenantScope>, future: F) -> F::Output {
+    match tenant {
+        Some(tenant) => with_tenant(tenant, future).await,
+        None => future.await,
+    }
+}
+
+/// Serves the request as the tenant whose domain is the host of the request.
+/// Any other host is served by the main organization.
+#[cfg(feature = "server")]
+pub(crate) async fn scope_tenant(request: Request, next: Next) -> impl IntoResponse {
+    let tenant = request
+        .headers()
+        .get(HOST)
+        .and_then(|host| host.to_str().ok())
+        .or(request.uri().host())
+        .and_then(|host| {
+            TENANT_DOMAINS
+                .read()
+                .unwrap_or_else(PoisonError::into_inner)
+                .get(&Tenant::normalize_domain(host))
+                .cloned()
+        });
+
+    in_scope(tenant, next.run(request)).await
+}
+
+/// Loads the state and the settings of the tenant like the main organization
+/// at startup and starts routing its domain to it
+#[cfg(feature = "server")]
+pub(crate) async fn serve_tenant(tenant: &Tenant, storage: KrillStorage) -> KrillResult<()> {
+    let scope = TenantScope {
+        id: tenant.id.clone(),
+        domain: tenant.domain.clone(),
+        storage: std::sync::Arc::new(storage),
+    };
+
+    with_tenant(scope.clone(), async {
+        let storage = &scope.storage;
+
+        if load_app_state(storage).await? != ServerConfigurationState::Uninitialized {
+            apply_org_settings(storage, &storage.org_settings().await?).await?;
+        }
+
+        Ok::<_, KrillError>(())
+    })
+    .await?;
+
+    TENANT_DOMAINS
+        .write()
+        .unwrap_or_else(PoisonError::into_inner)
+        .insert(tenant.domain.clone(), scope);
+
+    Ok(())
+}
+
+/// Serves every tenant registered in the main storage
+#[cfg(feature = "server")]
+pub(crate) async fn load_tenants(main: &KrillStorage) -> KrillResult<()> {
+    for tenant in main.tenants().await? {
+        let storage = main.open_tenant(&tenant).await?;
+
+        serve_tenant(&tenant, storage).await?;
+    }
+
+    Ok(())
+}
+
+/// Tenants are managed by the administrators of the main organization
+#[post("/api/tenants", headers: HeaderMap)]
+pub async fn tenants() -> ServerFnResult<Vec<TenantToUi>> {
+    ServerUtils::admin_session(&headers).await?;
+
+    if current_tenant().is_some() {
+        return Err(ServerUtils::error(KrillError::Forbidden));
+    }
+
+    let storage = main_store().map_err(ServerUtils::error)?;
+
+    storage
+        .tenants()
+        .await
+        .map(|tenants| tenants.into_iter().map(Into::into).collect())
+        .map_err(ServerUtils::error)
+}
+
+/// Creates the tenant after checking its settings like the configuration wizard does
+#[post("/api/tenant_create", headers: HeaderMap)]
+pub async fn tenant_create(setup: TenantSetup) -> ServerFnResult<TenantToUi> {
+    let session = ServerUtils::sensitive_session(&headers).await?;
+    let actor = session.holder().clone();
+
+    let outcome = create_processor(&actor, &setup).await;
+
+    ServerUtils::audit(
+        Some(actor),
+        AuditAction::TenantCreated,
+        &format!("{}:{}", AUDIT_TARGET, setup.id),
+        match outcome.as_ref() {
+            Ok(_) => AuditOutcome::Success,
+            Err(error) => AuditOutcome::Failure(error.to_string()),
+        },
+    )
+    .await;
+
+    outcome.map(Into::into).map_err(ServerUtils::error)
+}
+
+#[cfg(feature = "server")]
+async fn create_processor(actor: &Holder, setup: &TenantSetup) -> KrillResult<Tenant> {
+    if current_tenant().is_some() || actor.role() != UserRole::Superuser {
+        return Err(KrillError::Forbidden);
+    }
+
+    let main = main_store()?;
+    let settings = setup.settings();
+
+    if main.org_settings().await?.fqdn == settings.fqdn {
+        return Err(KrillError::TenantAlreadyExists(settings.fqdn));
+    }
+
+    validate_org_settings(&settings).await?;
+
+    let tenant = main.create_tenant(&setup.id, &settings.fqdn).await?;
+
+    // A tenant that cannot be served is removed so its identifier and domain can be used again
+    if let Err(error) = initialize_tenant(&main, &tenant, settings).await {
+        if let Err(rollback_error) = main.remove_tenant(&tenant).await {
+            tracing::error!(
+                "Unable to remove the tenant `{}` after a failed creation: {rollback_error}",
+                tenant.id
+            );
+        }
+
+        return Err(error);
+    }
+
+    Ok(tenant)
+}
+
+#[cfg(feature = "server")]
+async fn initialize_tenant(
+    main: &KrillStorage,
+    tenant: &Tenant,
+    settings: OrgSettings,
+) -> KrillResult<()> {
+    let storage = main.open_tenant(tenant).await?;
+
+    storage
+        .set_all_org_details(
+            settings.org_info,
+            &settings.mail,
+            &settings.api_key,
+            &settings.fqdn,
+        )
+        .await?;
+    storage.set_app_state_login_init().await?;
+
+    serve_tenant(tenant, storage).await
+}
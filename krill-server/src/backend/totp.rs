@@ -0,0 +1,265 @@
+use dioxus::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "server")]
+use {
+    axum::http::HeaderMap,
+    krill_common::{
+        AuthTokenDetails, KrillError, KrillResult, SecondFactorState, Totp, TotpPolicy,
+        TotpRecoveryCodes,
+    },
+    krill_store::{AuditAction, AuditOutcome, AuthTokenType, KrillStorage},
+    std::time::SystemTime,
+};
+
+#[cfg(feature = "server")]
+use crate::backend::{store, ServerUtils, SERVER_ORG_INFO};
+
+/// The step of the second factor flow the session is in
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
+pub enum TwoFactorStep {
+    Enroll,
+    Verify,
+    Done,
+}
+
+/// Shown once when enrolling. The secret and recovery codes cannot be retrieved again.
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+pub struct TotpEnrollmentToUi {
+    pub provisioning_uri: String,
+    pub secret: String,
+    pub recovery_codes: Vec<String>,
+}
+
+#[post("/api/totp_status", headers: HeaderMap)]
+pub async fn totp_status() -> ServerFnResult<TwoFactorStep> {
+    let (_, session) = ServerUtils::pending_session(&headers).await?;
+
+    let storage = store().map_err(ServerUtils::error)?;
+    // The state of the session was set when it was issued, the enrollment may have changed since
+    let enrolled = storage
+        .is_totp_enrolled(session.holder().identity())
+        .await
+        .map_err(ServerUtils::error)?;
+
+    Ok(two_factor_step(session.second_factor(), enrolled))
+}
+
+#[cfg(feature = "server")]
+fn two_factor_step(second_factor: SecondFactorState, enrolled: bool) -> TwoFactorStep {
+    match second_factor {
+        SecondFactorState::Verified { .. } => TwoFactorStep::Done,
+        _ if enrolled => TwoFactorStep::Verify,
+        SecondFactorState::NotRequired => TwoFactorStep::Done,
+        // The holder removed the enrollment the session must verify
+        SecondFactorState::EnrollmentRequired | SecondFactorState::VerificationRequired => {
+            TwoFactorStep::Enroll
+        }
+    }
+}
+
+/// Starts TOTP enrollment for the holder of the session
+#[post("/api/totp_enroll", headers: HeaderMap)]
+pub async fn totp_enroll() -> ServerFnResult<TotpEnrollmentToUi> {
+    let (_, session) = ServerUtils::pending_session(&headers).await?;
+
+    totp_enroll_processor(&session)
+        .await
+        .map_err(ServerUtils::error)
+}
+
+/// Confirms the enrollment with the first code from the authenticator app
+#[post("/api/totp_confirm_enrollment", headers: HeaderMap)]
+pub async fn totp_confirm_enrollment(code: String) -> ServerFnResult<()> {
+    let (store_key, session) = ServerUtils::pending_session(&headers).await?;
+
+    totp_confirm_enrollment_processor(store_key, session, &code)
+        .await
+        .map_err(ServerUtils::error)
+}
+
+/// Verifies a TOTP code or a recovery code. Also used to step up
+/// an existing session before sensitive operations.
+#[post("/api/totp_verify", headers: HeaderMap)]
+pub async fn totp_verify(code: String) -> ServerFnResult<()> {
+    let (store_key, session) = ServerUtils::pending_session(&headers).await?;
+
+    totp_verify_processor(store_key, session, &code)
+        .await
+        .map_err(ServerUtils::error)
+}
+
+#[post("/api/totp_disable", headers: HeaderMap)]
+pub async fn totp_disable() -> ServerFnResult<()> {
+    let session = ServerUtils::sensitive_session(&headers).await?;
+
+    totp_disable_processor(&session)
+        .await
+        .map_err(ServerUtils::error)
+}
+
+/// Sets which roles must use TOTP. Applies to sessions issued after the change.
+#[post("/api/totp_set_policy", headers: HeaderMap)]
+pub async fn totp_set_policy(
+    require_for_superuser: bool,
+    require_for_admin: bool,
+) -> ServerFnResult<()> {
+    ServerUtils::sensitive_session(&headers).await?;
+
+    let storage = store().map_err(ServerUtils::error)?;
+
+    storage
+        .set_totp_policy(TotpPolicy {
+            require_for_superuser,
+            require_for_admin,
+        })
+        .await
+        .map_err(ServerUtils::error)
+}
+
+/// Sets the second factor state of a new session based on the TOTP enrollment and policy
+#[cfg(feature = "server")]
+pub(crate) async fn initial_second_factor(
+    storage: &KrillStorage,
+    details: AuthTokenDetails,
+) -> KrillResult<AuthTokenDetails> {
+    let holder = details.holder();
+
+    let second_factor = if storage.is_totp_enrolled(holder.identity()).await? {
+        SecondFactorState::VerificationRequired
+    } else if storage.totp_policy().await?.is_required(holder.role()) {
+        SecondFactorState::EnrollmentRequired
+    } else {
+        SecondFactorState::NotRequired
+    };
+
+    Ok(details.set_second_factor(second_factor))
+}
+
+#[cfg(feature = "server")]
+async fn totp_enroll_processor(session: &AuthTokenDetails) -> KrillResult<TotpEnrollmentToUi> {
+    let issuer = SERVER_ORG_INFO
+        .get()
+        .map(|org_info| org_info.name)
+        .ok_or(KrillError::ServerOrgInfoNotSet)?;
+
+    let storage = store()?;
+    let identity = session.holder().identity();
+
+    let totp = Totp::generate();
+    let recovery_codes = TotpRecoveryCodes::generate();
+
+    storage
+        .begin_totp_enrollment(identity, &totp, &recovery_codes)
+        .await?;
+
+    Ok(TotpEnrollmentToUi {
+        provisioning_uri: totp.provisioning_uri(&issuer, identity).to_string(),
+        secret: totp.secret_base32().to_string(),
+        recovery_codes: recovery_codes
+            .codes()
+            .iter()
+            .map(|code| code.to_string())
+            .collect(),
+    })
+}
+
+#[cfg(feature = "server")]
+async fn totp_confirm_enrollment_processor(
+    store_key: AuthTokenType,
+    session: AuthTokenDetails,
+    code: &str,
+) -> KrillResult<()> {
+    let storage = store()?;
+    let identity = session.holder().identity();
+
+    let mut enrollment = storage
+        .get_totp_enrollment(identity)
+        .await?
+        .ok_or(KrillError::TotpNotEnrolled)?;
+
+    if enrollment.confirmed {
+        return Err(KrillError::TotpAlreadyEnrolled);
+    }
+
+    let totp = enrollment.totp();
+    let step = totp.verify(code, SystemTime::now(), enrollment.last_used_step)?;
+
+    enrollment.confirmed = true;
+    enrollment.last_used_step = step;
+    storage.set_totp_enrollment(identity, enrollment).await?;
+
+    storage
+        .set_auth_token_with_store_key(store_key, session.set_second_factor_verified())
+        .await?;
+
+    Ok(())
+}
+
+#[cfg(feature = "server")]
+async fn totp_verify_processor(
+    store_key: AuthTokenType,
+    session: AuthTokenDetails,
+    code: &str,
+) -> KrillResult<()> {
+    let storage = store()?;
+    let identity = session.holder().identity();
+
+    let mut enrollment = storage
+        .get_totp_enrollment(identity)
+        .await?
+        .filter(|enrollment| enrollment.confirmed)
+        .ok_or(KrillError::TotpNotEnrolled)?;
+
+    let now = SystemTime::now();
+
+    if enrollment.is_locked(now) {
+        return Err(KrillError::TotpLocked);
+    }
+
+    let totp = enrollment.totp();
+
+    match totp.verify(code, now, enrollment.last_used_step) {
+        Ok(step) => enrollment.last_used_step = step,
+        Err(KrillError::InvalidTotpCode) => {
+            if !enrollment.use_recovery_code(code) {
+                if enrollment.record_failed_attempt(now) {
+                    tracing::warn!("TOTP verification of `{identity}` locked after invalid codes");
+                }
+                storage.set_totp_enrollment(identity, enrollment).await?;
+
+                return Err(KrillError::InvalidTotpCode);
+            }
+
+            ServerUtils::audit(
+                Some(session.holder().clone()),
+                AuditAction::TotpRecoveryCodeUsed,
+                identity,
+                AuditOutcome::Success,
+            )
+            .await;
+        }
+        Err(error) => return Err(error),
+    }
+
+    enrollment.reset_failed_attempts();
+    storage.set_totp_enrollment(identity, enrollment).await?;
+
+    storage
+        .set_auth_token_with_store_key(store_key, session.set_second_factor_verified())
+        .await?;
+
+    Ok(())
+}
+
+#[cfg(feature = "server")]
+async fn totp_disable_processor(session: &AuthTokenDetails) -> KrillResult<()> {
+    let storage = store()?;
+    let holder = session.holder();
+
+    if storage.totp_policy().await?.is_required(holder.role()) {
+        return Err(KrillError::TotpRequired);
+    }
+
+    storage.remove_totp_enrollment(holder.identity()).await
+}
@@ -13,16 +13,22 @@ use krill_common::{FAVICON_DEFAULT, LOGO_DEFAULT};
 #[cfg(feature = "server")]
 use {
     dioxus::fullstack::headers::Header,
-    krill_common::{AuthTokenDetails, Holder, ServerConfigurationState},
-    krill_mail::{EmailEnvelopeDetails, KrillSmtpsBuilder},
+    krill_common::{
+        AuthTokenDetails, AuthoritySetup, BrandColors, Holder, MailProvider,
+        ServerConfigurationState,
+    },
+    krill_mail::{EmailTemplate, EmailTemplateName, KrillSmtpsBuilder},
+    krill_store::{AuditAction, AuditOutcome, OrgSettings},
     solana_tx_parser::{JsonRpcCluster, SolanaTxParserUtils},
 };
 
 #[cfg(feature = "server")]
 use crate::{
     backend::{
-        store, ServerUtils, ADMIN_SECRET, SERVER_API_KEY, SERVER_APP_STATE, SERVER_DOMAIN_NAME,
-        SERVER_MAIL_CONNECTION, SERVER_ORG_INFO,
+        apply_org_settings, current_tenant, in_scope, initial_second_factor, invite_authorities,
+        local_mail_enabled, queue_mail, store, MailReference, ServerUtils, ADMIN_SECRET,
+        SERVER_API_KEY, SERVER_APP_STATE, SERVER_DOMAIN_NAME, SERVER_MAIL_CONNECTION,
+        SERVER_ORG_INFO,
     },
     CacheOrgInfo,
 };
@@ -34,155 +40,197 @@ type SseTxInner = dioxus_fullstack::SseTx<ConfigVerificationOutcome>;
 pub async fn verification_stream(
     data: ProgressStateToUiRecord,
 ) -> dioxus::Result<ServerEvents<ConfigVerificationOutcome>> {
-    Ok(ServerEvents::new(
-        |mut tx: dioxus_fullstack::SseTx<ConfigVerificationOutcome>| async move {
-            if !validate_details(
-                &mut tx,
-                data.smtp_info.as_ref(),
-                data.api_key.as_ref(),
-                data.passcode.as_ref(),
-            )
-            .await
-            {
-                tx.close_channel();
+    // The wizard runs in a spawned task, which does not inherit the tenant of the request
+    let tenant = current_tenant();
 
-                return;
-            }
+    Ok(ServerEvents::new(move |tx: SseTxInner| {
+        in_scope(tenant, run_verification(tx, data))
+    }))
+}
 
-            if !validate_passcode(&mut tx, data.passcode.unwrap().trim()).await {
-                tx.close_channel();
+/// Checks every step of the setup wizard and creates the organization
+#[cfg(feature = "server")]
+async fn run_verification(mut tx: SseTxInner, data: ProgressStateToUiRecord) {
+    if !validate_details(
+        &mut tx,
+        data.mail_info.as_ref(),
+        data.api_key.as_ref(),
+        data.passcode.as_ref(),
+    )
+    .await
+    {
+        tx.close_channel();
 
-                return;
-            }
+        return;
+    }
 
-            let org_info = data.org_info;
-            let (success_parsing, org_info_parsed) = validate_org_details(&mut tx, org_info).await;
-            if !success_parsing {
+    let authorities =
+        match AuthoritySetup::new(&data.authorities, data.threshold.unwrap_or_default()) {
+            Ok(value) => value,
+            Err(error) => {
+                tx.send(ConfigVerificationOutcome::Failure(error.to_string()))
+                    .await
+                    .err();
                 tx.close_channel();
 
                 return;
             }
+        };
 
-            let org_name = org_info_parsed.name.clone();
-            let support_mail = org_info_parsed.support_mail.clone();
-            let domain_name = data.domain_name.as_ref().unwrap().trim().to_string();
-            let smtps_uri = data.smtp_info.as_ref().unwrap().trim();
-            let api_key = data.api_key.as_ref().unwrap().trim();
+    if !validate_passcode(&mut tx, data.passcode.unwrap().trim()).await {
+        tx.close_channel();
 
-            if org_name.is_empty() || org_name.len() < 2 {
-                tx.send(ConfigVerificationOutcome::Failure(
-                    "Invalid organization name".to_string(),
-                ))
-                .await
-                .err();
+        return;
+    }
 
-                return;
-            }
+    let org_info = data.org_info;
+    let (success_parsing, org_info_parsed) = validate_org_details(&mut tx, org_info).await;
+    if !success_parsing {
+        tx.close_channel();
 
-            if !ConfigVerificationOutcome::test_smtp_config(
-                &mut tx,
-                smtps_uri,
-                org_name.clone(),
-                support_mail,
-                domain_name.to_string(),
-            )
-            .await
-            {
-                tx.close_channel();
+        return;
+    }
 
-                return;
-            }
+    let org_name = org_info_parsed.name.clone();
+    let support_mail = org_info_parsed.support_mail.clone();
+    let domain_name = data.domain_name.as_ref().unwrap().trim().to_string();
+    let mail = data.mail_info.as_ref().unwrap();
+    let api_key = data.api_key.as_ref().unwrap().trim();
 
-            if !ConfigVerificationOutcome::test_api_key(&mut tx, api_key).await {
-                tx.close_channel();
+    if org_name.is_empty() || org_name.len() < 2 {
+        tx.send(ConfigVerificationOutcome::Failure(
+            "Invalid organization name".to_string(),
+        ))
+        .await
+        .err();
 
-                return;
-            }
+        return;
+    }
 
-            if !ConfigVerificationOutcome::create_organization(
-                &mut tx,
-                smtps_uri,
-                &domain_name,
-                api_key,
-                org_info_parsed,
-            )
-            .await
-            {
-                tx.close_channel();
-            }
-        },
-    ))
+    if !ConfigVerificationOutcome::test_mail_provider(
+        &mut tx,
+        mail,
+        org_name.clone(),
+        support_mail,
+        domain_name.to_string(),
+    )
+    .await
+    {
+        tx.close_channel();
+
+        return;
+    }
+
+    if !ConfigVerificationOutcome::test_api_key(&mut tx, api_key).await {
+        tx.close_channel();
+
+        return;
+    }
+
+    if !ConfigVerificationOutcome::create_organization(
+        &mut tx,
+        mail,
+        &domain_name,
+        api_key,
+        org_info_parsed,
+        &authorities,
+    )
+    .await
+    {
+        tx.close_channel();
+
+        return;
+    }
+
+    ConfigVerificationOutcome::send_authority_invitations(&mut tx, &authorities).await;
 }
 
 #[get("/verification-support-mail-link/{token}")]
 pub async fn verify_support_mail(token: String) -> ServerFnResult<Response> {
     let mut res = Response::new(axum::body::Body::empty());
 
-    let storage = store().map_err(|error| ServerFnError::ServerError {
-        message: error.to_string(),
-        code: 500,
-        details: None,
-    })?;
+    let storage = store().map_err(ServerUtils::error)?;
 
     let parsed_token = ServerUtils::parse_token(&token)?;
 
-    let login_init_details =
-        storage
-            .get_superuser_auth_token()
-            .await
-            .map_err(|error| ServerFnError::ServerError {
-                message: error.to_string(),
-                code: 500,
-                details: None,
-            })?;
+    let login_init_details = storage
+        .get_superuser_auth_token()
+        .await
+        .map_err(ServerUtils::error)?;
 
     let auth_details = if let Some(auth_details) = login_init_details {
         auth_details
     } else {
-        redirect_error_header(
-            &mut res,
-            "Auth details not found! Possibly already verified",
-        )?;
+        // Not found or possibly already verified
+        redirect_error_header(&mut res, KrillError::InvalidAuthToken)?;
 
         return Ok(res);
     };
 
-    if !AuthTokenDetails::const_cmp(auth_details.token, &parsed_token) {
-        redirect_error_header(&mut res, "Unauthorized user")?;
+    let holder = auth_details.details.holder().clone();
 
-        return Ok(res);
-    }
+    let rejected = if !AuthTokenDetails::const_cmp(auth_details.token, &parsed_token) {
+        Some(KrillError::InvalidAuthToken)
+    } else if auth_details.details.is_expired() {
+        Some(KrillError::AuthTokenExpired)
+    } else if holder.role() != UserRole::Superuser {
+        Some(KrillError::Forbidden)
+    } else {
+        None
+    };
 
-    if auth_details.details.is_expired() {
-        redirect_error_header(&mut res, "The link is already expired!")?;
+    if let Some(error) = rejected {
+        ServerUtils::audit(
+            Some(holder.clone()),
+            AuditAction::SuperuserLinkVerified,
+            holder.identity(),
+            AuditOutcome::Failure(error.to_string()),
+        )
+        .await;
 
-        return Ok(res);
-    }
-
-    if auth_details.details.holder().role() != UserRole::Superuser {
-        redirect_error_header(&mut res, "Unauthorized user")?;
+        redirect_error_header(&mut res, error)?;
 
         return Ok(res);
     }
 
-    let state = SERVER_APP_STATE.get().ok_or(ServerFnError::ServerError {
-        message: "Unable to transition the state of the server `SERVER_APP_STATE`".to_string(),
-        code: 400,
-        details: None,
-    })?;
+    if !SERVER_APP_STATE.is_set() {
+        return Err(ServerUtils::error(
+            KrillError::AppStateMachineNotInitialized,
+        ));
+    }
 
     let auth_token = AuthTokenDetails::generate_token();
 
+    let session_details = initial_second_factor(&storage, auth_details.details.clone())
+        .await
+        .map_err(ServerUtils::error)?;
+
     let store_key = storage
-        .set_auth_token(auth_token, auth_details.details.clone())
+        .set_auth_token(auth_token, session_details)
         .await
-        .map_err(|error| ServerFnError::ServerError {
-            message: error.to_string(),
-            code: 500,
-            details: None,
-        })?;
+        .map_err(ServerUtils::error)?;
+
+    SERVER_APP_STATE.set(
+        storage
+            .complete_initialization()
+            .await
+            .map_err(ServerUtils::error)?,
+    );
 
-    *state.write().await = ServerConfigurationState::Initialized;
+    ServerUtils::audit(
+        Some(holder.clone()),
+        AuditAction::SuperuserLinkVerified,
+        holder.identity(),
+        AuditOutcome::Success,
+    )
+    .await;
+    ServerUtils::audit(
+        Some(holder.clone()),
+        AuditAction::SessionIssued,
+        holder.identity(),
+        AuditOutcome::Success,
+    )
+    .await;
 
     redirect_success_header(&mut res)?;
     build_cookie(
@@ -200,37 +248,13 @@ pub async fn send_superuser_login_auth_link() -> ServerFnResult<Vec<u8>> {
     let org_info = crate::ServerUtils::request_get_org()?;
 
     let holder = Holder::new_with_tld(&org_info.support_mail)
-        .map_err(|error| {
-            let error_message = "Error-HolderNew";
-
-            tracing::error!("{error_message}. Error: `{error:?}`");
-
-            ServerFnError::ServerError {
-                message: error_message.to_string() + ": Internal server error",
-                code: 500,
-                details: None,
-            }
-        })?
+        .map_err(ServerUtils::error)?
         .set_superuser();
 
-    send_auth_email_processor(
-        holder,
-        "Verify that you control this email address",
-        "Click or Tap the link below to verify that you control the support email address",
-    )
-    .await
-    .map(|value| bitcode::encode(&value))
-    .map_err(|error| {
-        let error_message = "Error-Sending Mail";
-
-        tracing::error!("{error_message}. Error: `{error:?}`");
-
-        ServerFnError::ServerError {
-            message: error_message.to_string() + ": Internal server error",
-            code: 500,
-            details: None,
-        }
-    })
+    send_auth_email_processor(holder)
+        .await
+        .map(|value| bitcode::encode(&value))
+        .map_err(ServerUtils::error)
 }
 
 #[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
@@ -245,28 +269,14 @@ pub enum ConfigVerificationOutcome {
     CreatingOrganization,
     OrganizationCreated(Vec<u8>), //Returns the new org info
     OrganizationCreationFailed(String),
+    InvitingAuthorities,
+    AuthoritiesInvited,
+    /// The addresses or the error. The invitations can be resent after signing in.
+    AuthorityInvitationFailed(String),
 }
 
 #[cfg(feature = "server")]
 impl ConfigVerificationOutcome {
-    async fn handler_with_error<T: core::fmt::Debug>(
-        tx: &mut SseTxInner,
-        value: KrillResult<T>,
-    ) -> Option<T> {
-        match value {
-            Ok(value) => Some(value),
-            Err(error) => {
-                tx.send(ConfigVerificationOutcome::Failure(error.to_string()))
-                    .await
-                    .err();
-
-                tx.close_channel();
-
-                None
-            }
-        }
-    }
-
     async fn tx_handler(tx: &mut SseTxInner, outcome: ConfigVerificationOutcome) -> bool {
         if tx.send(outcome).await.is_err() {
             tx.close_channel();
@@ -299,9 +309,9 @@ impl ConfigVerificationOutcome {
         false
     }
 
-    pub async fn test_smtp_config(
+    pub async fn test_mail_provider(
         tx: &mut SseTxInner,
-        smtps_uri: &str,
+        mail: &MailProvider,
         org_name: String,
         support_mail: String,
         hello_name: String,
@@ -315,47 +325,12 @@ impl ConfigVerificationOutcome {
             return false;
         }
 
-        let mut mailer = KrillSmtpsBuilder::new();
-        mailer
-            .set_from(&format!("{} <{}>", org_name, support_mail))
-            .set_hello_name(&hello_name)
-            .set_reply_to(&format!("{} <{}>", org_name, support_mail));
-
-        let mailer = if let Some(value) = Self::handler_with_error(tx, mailer.build(smtps_uri))
-            .await
-            .take()
-        {
-            value
-        } else {
-            tx.close_channel();
-
-            return false;
-        };
-
-        let outcome = mailer.test_connection().await;
-
-        match outcome {
-            Ok(is_success) => {
-                if is_success {
-                    return Self::tx_handler(tx, ConfigVerificationOutcome::TestingSmtpSuccess)
-                        .await;
-                } else {
-                    return Self::tx_error_handler(
-                        tx,
-                        ConfigVerificationOutcome::TestingSmtpFailure(
-                            "Invalid SMTPs settings".to_string(),
-                        ),
-                    )
-                    .await;
-                }
-            }
+        match check_mail_provider(mail, &org_name, &support_mail, &hello_name).await {
+            Ok(()) => Self::tx_handler(tx, ConfigVerificationOutcome::TestingSmtpSuccess).await,
             Err(error) => {
-                return Self::tx_error_handler(
+                Self::tx_error_handler(
                     tx,
-                    ConfigVerificationOutcome::TestingSmtpFailure(format!(
-                        "Unable to test SMTP Settings. {}",
-                        error
-                    )),
+                    ConfigVerificationOutcome::TestingSmtpFailure(error.to_string()),
                 )
                 .await
             }
@@ -372,59 +347,69 @@ impl ConfigVerificationOutcome {
             return false;
         }
 
-        let mut client = SolanaTxParserUtils::new(JsonRpcCluster::Custom(api_key.to_string()));
-
-        let parsed = if let Some(value) = Self::handler_with_error(tx, client.get_version().await)
-            .await
-            .take()
-        {
-            value
-        } else {
-            tx.close_channel();
+        match check_api_key(api_key).await {
+            Ok(()) => Self::tx_handler(tx, ConfigVerificationOutcome::TestingApiKeySuccess).await,
+            Err(error) => {
+                Self::tx_error_handler(
+                    tx,
+                    ConfigVerificationOutcome::TestingApiKeyFailure(error.to_string()),
+                )
+                .await
+            }
+        }
+    }
 
+    /// Runs after the organization is created so a failure only needs the invitations resent
+    async fn send_authority_invitations(tx: &mut SseTxInner, setup: &AuthoritySetup) -> bool {
+        if !Self::tx_handler(tx, ConfigVerificationOutcome::InvitingAuthorities).await {
             return false;
+        }
+
+        let superuser = SERVER_ORG_INFO
+            .get()
+            .ok_or(KrillError::ServerOrgInfoNotSet)
+            .and_then(|org_info| Holder::new_with_tld(&org_info.support_mail))
+            .map(Holder::set_superuser);
+
+        let outcome = match superuser {
+            Ok(superuser) => invite_authorities(&superuser, setup).await,
+            Err(error) => Err(error),
         };
 
-        if parsed.result.is_some() {
-            Self::tx_handler(tx, ConfigVerificationOutcome::TestingApiKeySuccess).await
-        } else {
-            Self::tx_handler(
-                tx,
-                ConfigVerificationOutcome::TestingApiKeyFailure(
-                    parsed
-                        .error
-                        .map(|error| error.message)
-                        .unwrap_or(String::from(
-                            "Encountered error when parsing the `getVersion` method",
-                        )),
-                ),
-            )
-            .await
+        match outcome {
+            Ok(failed) if failed.is_empty() => {
+                Self::tx_handler(tx, ConfigVerificationOutcome::AuthoritiesInvited).await
+            }
+            Ok(failed) => {
+                Self::tx_error_handler(
+                    tx,
+                    ConfigVerificationOutcome::AuthorityInvitationFailed(failed.join(", ")),
+                )
+                .await
+            }
+            Err(error) => {
+                Self::tx_error_handler(
+                    tx,
+                    ConfigVerificationOutcome::AuthorityInvitationFailed(error.to_string()),
+                )
+                .await
+            }
         }
     }
 
     async fn create_organization(
         tx: &mut SseTxInner,
-        smtps_uri: &str,
+        mail: &MailProvider,
         fqdn: &str,
         api_key: &str,
         org_info: OrganizationInfo,
+        authorities: &AuthoritySetup,
     ) -> bool {
-        let media_checker = |bytes: &[u8]| -> bool {
-            wasm_toolkit::WasmToolkitCommon::to_file_format_kind(bytes) == file_format::Kind::Image
-        };
-
-        if !media_checker(&org_info.logo) {
-            return Self::tx_org_failure_handler(tx, "Invalid Logo. Only images are accepted")
-                .await;
-        }
-
-        if !media_checker(&org_info.favicon) {
-            return Self::tx_org_failure_handler(tx, "Invalid Logo. Only images are accepted")
-                .await;
+        if let Err(error) = validate_org_info(&org_info) {
+            return Self::tx_org_failure_handler(tx, error.to_string().as_str()).await;
         }
 
-        if SERVER_MAIL_CONNECTION.get().is_some() {
+        if SERVER_MAIL_CONNECTION.is_set() {
             return Self::tx_org_failure_handler(
                 tx,
                 "`SERVER_MAIL_CONNECTION` already initialized!",
@@ -432,12 +417,12 @@ impl ConfigVerificationOutcome {
             .await;
         }
 
-        if SERVER_DOMAIN_NAME.get().is_some() {
+        if SERVER_DOMAIN_NAME.is_set() {
             return Self::tx_org_failure_handler(tx, "`SERVER_DOMAIN_NAME` already initialized!")
                 .await;
         }
 
-        if SERVER_API_KEY.get().is_some() {
+        if SERVER_API_KEY.is_set() {
             return Self::tx_org_failure_handler(tx, "`SERVER_API_KEY` already initialized!").await;
         }
 
@@ -452,8 +437,8 @@ impl ConfigVerificationOutcome {
             return false;
         }
 
-        if let Some(server_app_state) = SERVER_APP_STATE.get() {
-            *server_app_state.write().await = ServerConfigurationState::LoginInitialization;
+        if SERVER_APP_STATE.is_set() {
+            SERVER_APP_STATE.set(ServerConfigurationState::LoginInitialization);
         } else {
             return Self::tx_org_failure_handler(tx, "`SERVER_APP_STATE` already initialized!")
                 .await;
@@ -478,54 +463,154 @@ impl ConfigVerificationOutcome {
         }
 
         if let Err(error) = storage
-            .set_all_org_details(org_info.clone(), smtps_uri, api_key, fqdn)
+            .set_all_org_details(org_info.clone(), mail, api_key, fqdn)
             .await
         {
+            ServerUtils::audit(
+                None,
+                AuditAction::OrganizationCreated,
+                &org_info.name,
+                AuditOutcome::Failure(error.to_string()),
+            )
+            .await;
+
+            return Self::tx_org_failure_handler(tx, error.to_string().as_str()).await;
+        }
+
+        ServerUtils::audit(
+            None,
+            AuditAction::OrganizationCreated,
+            &org_info.name,
+            AuditOutcome::Success,
+        )
+        .await;
+        if let Err(error) = storage.set_authority_setup(authorities).await {
             return Self::tx_org_failure_handler(tx, error.to_string().as_str()).await;
         }
         if let Err(error) = storage.set_app_state_login_init().await {
             return Self::tx_org_failure_handler(tx, error.to_string().as_str()).await;
         }
 
-        let mut mailer = KrillSmtpsBuilder::new();
-        mailer
-            .set_from(&format!("{} <{}>", org_info.name, org_info.support_mail))
-            .set_hello_name(fqdn)
-            .set_reply_to(&format!("{} <{}>", org_info.name, org_info.support_mail));
-        let mailer = match mailer.build(smtps_uri) {
-            Ok(value) => value,
-            Err(error) => {
-                return Self::tx_org_failure_handler(tx, error.to_string().as_str()).await;
-            }
+        let settings = OrgSettings {
+            org_info,
+            mail: mail.clone(),
+            api_key: api_key.to_string(),
+            fqdn: fqdn.to_string(),
         };
 
-        SERVER_DOMAIN_NAME.get_or_init(|| fqdn.to_string());
-        SERVER_API_KEY.get_or_init(|| api_key.to_string());
-        SERVER_ORG_INFO.get_or_init(|| org_info);
-        SERVER_MAIL_CONNECTION.get_or_init(|| mailer);
-
-        if let Some(info) = SERVER_ORG_INFO.get().cloned() {
-            Self::tx_handler(
-                tx,
-                ConfigVerificationOutcome::OrganizationCreated(bitcode::encode(&info)),
-            )
-            .await
-        } else {
-            return Self::tx_org_failure_handler(tx, "organization was created successfully but server was not able to send the registered org info").await;
+        if let Err(error) = apply_org_settings(&storage, &settings).await {
+            return Self::tx_org_failure_handler(tx, error.to_string().as_str()).await;
         }
+
+        Self::tx_handler(
+            tx,
+            ConfigVerificationOutcome::OrganizationCreated(bitcode::encode(&settings.org_info)),
+        )
+        .await
+    }
+}
+
+/// Checks the organization details the same way when the organization is created
+/// and when its settings change
+#[cfg(feature = "server")]
+pub(crate) fn validate_org_info(org_info: &OrganizationInfo) -> KrillResult<()> {
+    let is_image = |bytes: &[u8]| -> bool {
+        wasm_toolkit::WasmToolkitCommon::to_file_format_kind(bytes) == file_format::Kind::Image
+    };
+    let invalid = |reason: &str| Err(KrillError::InvalidOrgSettings(reason.to_string()));
+
+    if org_info.name.trim().len() < 2 {
+        return invalid("Invalid organization name");
+    }
+
+    if Holder::new_with_tld(&org_info.support_mail).is_err() {
+        return invalid("Invalid support email address");
+    }
+
+    if !is_image(&org_info.logo) {
+        return invalid("Invalid Logo. Only images are accepted");
+    }
+
+    if !is_image(&org_info.favicon) {
+        return invalid("Invalid Favicon. Only images are accepted");
+    }
+
+    let colors = &org_info.color_scheme;
+    if ![
+        colors.primary_color(),
+        colors.secondary_color(),
+        colors.accent_color(),
+        colors.background_dark(),
+        colors.background_light(),
+    ]
+    .into_iter()
+    .all(BrandColors::is_hex_color)
+    {
+        return invalid("Colors must be hex values like `#FF6600`");
+    }
+
+    Ok(())
+}
+
+/// Connects to the SMTP server or calls the mail API with the settings that will
+/// send the organization emails
+#[cfg(feature = "server")]
+pub(crate) async fn check_mail_provider(
+    mail: &MailProvider,
+    org_name: &str,
+    support_mail: &str,
+    hello_name: &str,
+) -> KrillResult<()> {
+    let mut mailer = KrillSmtpsBuilder::new();
+    mailer
+        .set_from(&format!("{} <{}>", org_name, support_mail))
+        .set_hello_name(hello_name)
+        .set_reply_to(&format!("{} <{}>", org_name, support_mail))
+        .set_local_transports(local_mail_enabled());
+
+    match mailer.build(mail)?.test_connection().await {
+        Ok(true) => Ok(()),
+        Ok(false) => Err(KrillError::Mailer(match mail {
+            MailProvider::Smtp(_) => "Invalid SMTPs settings".to_string(),
+            MailProvider::Api(_) => "The mail API rejected the API key".to_string(),
+        })),
+        Err(error) => Err(KrillError::Mailer(format!(
+            "Unable to test the mail settings. {error}"
+        ))),
+    }
+}
+
+/// Calls `getVersion` on the RPC provider with the API key
+#[cfg(feature = "server")]
+pub(crate) async fn check_api_key(api_key: &str) -> KrillResult<()> {
+    let mut client = SolanaTxParserUtils::new(JsonRpcCluster::Custom(api_key.to_string()));
+
+    let parsed = client.get_version().await?;
+
+    if parsed.result.is_some() {
+        Ok(())
+    } else {
+        Err(KrillError::InvalidOrgSettings(
+            parsed
+                .error
+                .map(|error| error.message)
+                .unwrap_or(String::from(
+                    "Encountered error when parsing the `getVersion` method",
+                )),
+        ))
     }
 }
 
 #[cfg(feature = "server")]
 async fn validate_details(
     tx: &mut SseTxInner,
-    smtp_info: Option<&String>,
+    mail_info: Option<&MailProvider>,
     api_key: Option<&String>,
     passcode: Option<&String>,
 ) -> bool {
-    if smtp_info.is_none() {
+    if mail_info.is_none() {
         tx.send(ConfigVerificationOutcome::Failure(
-            "SMTP(s) configuration is missing".to_string(),
+            "The mail provider configuration is missing".to_string(),
         ))
         .await
         .err();
@@ -561,22 +646,28 @@ async fn validate_passcode(tx: &mut SseTxInner, passcode: &str) -> bool {
     if let Some(admin_config) = ADMIN_SECRET.get() {
         let stored_passcode = admin_config.read().await.secret_to_string();
 
-        if admin_config.read().await.is_expired_after_60() {
-            tx.send(ConfigVerificationOutcome::Failure(
-                "Passcode expired".to_string(),
-            ))
-            .await
-            .err();
-
-            return false;
-        }
+        let failure = if admin_config.read().await.is_expired_after_60() {
+            Some("Passcode expired")
+        } else if stored_passcode != passcode {
+            Some("Passcode is invalid")
+        } else {
+            None
+        };
 
-        if stored_passcode != passcode {
-            tx.send(ConfigVerificationOutcome::Failure(
-                "Passcode is invalid".to_string(),
-            ))
-            .await
-            .err();
+        ServerUtils::audit(
+            None,
+            AuditAction::AdminPasscodeUsed,
+            "AdminPasscode",
+            failure
+                .map(|reason| AuditOutcome::Failure(reason.to_string()))
+                .unwrap_or(AuditOutcome::Success),
+        )
+        .await;
+
+        if let Some(reason) = failure {
+            tx.send(ConfigVerificationOutcome::Failure(reason.to_string()))
+                .await
+                .err();
 
             return false;
         }
@@ -644,7 +735,7 @@ async fn validate_org_details(
 }
 
 #[cfg(feature = "server")]
-fn build_cookie(res: &mut Response, auth_token_as_cookie: &str) -> ServerFnResult<()> {
+pub(crate) fn build_cookie(res: &mut Response, auth_token_as_cookie: &str) -> ServerFnResult<()> {
     res.headers_mut().append(
         SetCookie::name(),
         auth_token_as_cookie
@@ -667,16 +758,10 @@ fn build_cookie(res: &mut Response, auth_token_as_cookie: &str) -> ServerFnResul
 }
 
 #[cfg(feature = "server")]
-fn redirect_error_header(res: &mut Response, error: &str) -> ServerFnResult<()> {
+pub(crate) fn redirect_error_header(res: &mut Response, error: KrillError) -> ServerFnResult<()> {
     *res.status_mut() = StatusCode::SEE_OTHER;
 
-    use percent_encoding::{utf8_percent_encode, AsciiSet, CONTROLS};
-
-    /// https://url.spec.whatwg.org/#fragment-percent-encode-set
-    const FRAGMENT: &AsciiSet = &CONTROLS.add(b' ').add(b'"').add(b'<').add(b'>').add(b'`');
-    let encoded = utf8_percent_encode(error, FRAGMENT).to_string();
-
-    let route = (crate::RouteUtils::ERRORS.to_string() + "/" + encoded.as_str())
+    let route = crate::RouteUtils::error_route(&error)
         .parse::<dioxus_fullstack::HeaderValue>()
         .map_err(|inner_error| {
             tracing::error!(
@@ -684,11 +769,7 @@ fn redirect_error_header(res: &mut Response, error: &str) -> ServerFnResult<()>
                 inner_error.to_string()
             );
 
-            ServerFnError::ServerError {
-                message: error.to_string(),
-                code: 500,
-                details: None,
-            }
+            ServerUtils::error(error)
         })?;
 
     res.headers_mut().insert("Location", route);
@@ -697,7 +778,7 @@ fn redirect_error_header(res: &mut Response, error: &str) -> ServerFnResult<()>
 }
 
 #[cfg(feature = "server")]
-fn redirect_success_header(res: &mut Response) -> ServerFnResult<()> {
+pub(crate) fn redirect_success_header(res: &mut Response) -> ServerFnResult<()> {
     *res.status_mut() = StatusCode::TEMPORARY_REDIRECT;
 
     let route = crate::RouteUtils::DASHBOARD
@@ -721,11 +802,7 @@ fn redirect_success_header(res: &mut Response) -> ServerFnResult<()> {
 }
 
 #[cfg(feature = "server")]
-async fn send_auth_email_processor(
-    holder: Holder,
-    subject: &str,
-    body: &str,
-) -> KrillResult<VerifyMailDetailsToUi> {
+async fn send_auth_email_processor(holder: Holder) -> KrillResult<VerifyMailDetailsToUi> {
     let domain = SERVER_DOMAIN_NAME
         .get()
         .ok_or(KrillError::Statics("`SERVER_DOMAIN_NAME` not set"))?;
@@ -750,21 +827,19 @@ async fn send_auth_email_processor(
         let superuser_auth_token = storage.set_superuser_token(holder.clone()).await?;
         auth_token = superuser_auth_token.token;
 
-        let mailer = SERVER_MAIL_CONNECTION
-            .get()
-            .ok_or(KrillError::Statics("`SERVER_MAIL_CONNECTION` not set"))?;
+        let uri = ServerUtils::origin(&domain)
+            + "/verification-support-mail-link/"
+            + AuthTokenDetails::store_key_bytes_to_hex(auth_token).as_str();
 
-        let message = EmailEnvelopeDetails::new()
-            .set_to(&holder.email_envelope_details())
-            .set_subject(subject)
-            .set_body(&html_code_template(
-                domain,
-                &AuthTokenDetails::store_key_bytes_to_hex(auth_token),
-                body,
-                &auth_details.expiry_formatted(),
-            ));
+        let message = ServerUtils::render_email(
+            EmailTemplate::new(EmailTemplateName::VerificationLink)
+                .set_var("expiry", &auth_details.expiry_formatted())
+                .set_var(EmailTemplate::ACTION_URL, &uri),
+        )
+        .await?
+        .envelope(&holder.email_envelope_details());
 
-        mailer.send(&message).await?;
+        queue_mail(&MailReference::verification(&holder), &message).await?;
 
         Ok((auth_token, superuser_auth_token.details).into())
     } else {
@@ -772,74 +847,67 @@ async fn send_auth_email_processor(
     }
 }
 
-fn html_code_template(domain: &str, code: &str, body: &str, expiry: &str) -> String {
-    let query = "/verification-support-mail-link/";
-    let uri = if domain.starts_with("localhost") || domain.starts_with("127.0.0.1") {
-        String::from("http://") + domain + ":8080" + query + code
-    } else {
-        String::from("https://") + domain + query + code
-    };
+#[cfg(all(test, feature = "server"))]
+mod test {
+    use async_dup::Arc;
+    use async_lock::RwLock;
+    use axum::{body::to_bytes, response::IntoResponse};
+    use krill_common::AdminConfiguration;
+    use krill_store::{AuditQuery, KrillStorage, UnlockSecret};
 
-    format!(
-        r#"
-<!DOCTYPE html>
-<html>
-<head>
-  <meta charset="UTF-8">
-  <title>Verification Link</title>
-</head>
-<body style="margin:0; padding:0; background-color:#f4f4f4; font-family: Arial, sans-serif;">
-
-  <table width="100%" cellpadding="0" cellspacing="0" border="0" style="background-color:#f4f4f4; padding:20px 0;">
-    <tr>
-      <td align="center">
-
-        <!-- Container -->
-        <table width="400" cellpadding="0" cellspacing="0" border="0" style="background:#ffffff; border-radius:8px; padding:20px; text-align:center;">
-          
-          <!-- Title -->
-          <tr>
-            <td style="font-size:20px; font-weight:bold; color:#333;">
-              Verification Link
-            </td>
-          </tr>
-
-          <!-- Spacer -->
-          <tr><td style="height:10px;"></td></tr>
-
-          <!-- Message -->
-          <tr>
-            <td style="font-size:14px; color:#555;">
-              {body}. This link will expire in {expiry}.
-            </td>
-          </tr>
-
-          <!-- Spacer -->
-          <tr><td style="height:20px;"></td></tr>
-
-          <!-- Code -->
-          <td style="font-size:12px; color:#000;">
-              <a href="{uri}">{uri}</a>
-        </td>
-
-          <!-- Spacer -->
-          <tr><td style="height:20px;"></td></tr>
-
-          <!-- Footer -->
-          <tr>
-            <td style="font-size:12px; color:#888;">
-              If you did not request this code, you can safely ignore this email.
-            </td>
-          </tr>
-
-        </table>
-
-      </td>
-    </tr>
-  </table>
-
-</body>
-</html>
-"#
-    )
+    use super::*;
+    use crate::backend::{with_tenant, TenantScope, KRILL_STORAGE};
+
+    async fn unlocked_storage() -> KrillStorage {
+        KrillStorage::init_memory()
+            .unlock(UnlockSecret::secret([4u8; 32]))
+            .await
+            .unwrap()
+    }
+
+    /// The wizard of a tenant runs in a spawned task and must still record to the tenant
+    #[tokio::test]
+    async fn wizard_runs_as_tenant() {
+        let main = unlocked_storage().await;
+        let main = KRILL_STORAGE.get_or_init(|| std::sync::Arc::new(main));
+        ADMIN_SECRET.get_or_init(|| Arc::new(RwLock::new(AdminConfiguration::new())));
+
+        let tenant = TenantScope {
+            id: "tenant".to_string(),
+            domain: "tenant.example".to_string(),
+            storage: std::sync::Arc::new(unlocked_storage().await),
+        };
+
+        let data = ProgressStateToUiRecord {
+            mail_info: Some(MailProvider::default()),
+            api_key: Some("api-key".to_string()),
+            passcode: Some("invalid".to_string()),
+            authorities: vec!["authority@example.com".to_string()],
+            threshold: Some(1),
+            ..Default::default()
+        };
+
+        let events = with_tenant(tenant.clone(), verification_stream(data))
+            .await
+            .unwrap();
+        // The stream ends once the wizard rejects the passcode
+        to_bytes(events.into_response().into_body(), usize::MAX)
+            .await
+            .unwrap();
+
+        let query = AuditQuery {
+            action: Some(AuditAction::AdminPasscodeUsed),
+            ..Default::default()
+        };
+        assert_eq!(
+            tenant
+                .storage
+                .audit_entries(query.clone())
+                .await
+                .unwrap()
+                .len(),
+            1
+        );
+        assert!(main.audit_entries(query).await.unwrap().is_empty());
+    }
 }
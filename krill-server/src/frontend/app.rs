@@ -258,7 +258,7 @@ async fn finalize_variable(outcome: WasmToolkitResult<()>) {
 
 #[server]
 pub async fn fetch_org_info() -> ServerFnResult<Vec<u8>> {
-    let info = crate::SERVER_ORG_INFO.get().cloned().unwrap_or_default();
+    let info = crate::SERVER_ORG_INFO.get().unwrap_or_default();
 
     Ok(bitcode::encode(&info))
 }
@@ -6,11 +6,12 @@ use crate::{
     OrgCacheOps, Translations, TranslationsMemInfo, BROWSER_MEASUREMENTS, NOTIFICATION_MANAGER,
 };
 
+/// The navigation of the dashboard pages. `children` is the content of the page.
 #[component]
-pub fn Header() -> Element {
+pub fn Header(#[props(default)] active: ActiveItem, children: Element) -> Element {
     let translations_info = consume_context::<Signal<TranslationsMemInfo>>();
     let mut org_info = use_signal(|| OrganizationInfo::default());
-    let mut active = use_signal(|| ActiveItem::default());
+    let mut active = use_signal(|| active);
 
     use_effect(move || {
         spawn(async move {
@@ -103,6 +104,7 @@ pub fn Header() -> Element {
                             active,
                             move || {
                                 active.set(ActiveItem::Home);
+                                navigator().push(crate::RouteUtils::DASHBOARD);
                             },
                             &translations_info.read().translations,
                             home_icon,
@@ -121,7 +123,10 @@ pub fn Header() -> Element {
                         nav_op_item(
                             ActiveItem::Members,
                             active,
-                            move || { active.set(ActiveItem::Members) },
+                            move || {
+                                active.set(ActiveItem::Members);
+                                navigator().push(crate::RouteUtils::MEMBERS);
+                            },
                             &translations_info.read().translations,
                             members_icon,
                         )
@@ -130,7 +135,10 @@ pub fn Header() -> Element {
                         nav_op_item(
                             ActiveItem::Settings,
                             active,
-                            move || { active.set(ActiveItem::Settings) },
+                            move || {
+                                active.set(ActiveItem::Settings);
+                                navigator().push(crate::RouteUtils::SETTINGS);
+                            },
                             &translations_info.read().translations,
                             settings_icon,
                         )
@@ -140,6 +148,7 @@ pub fn Header() -> Element {
             }
 
             div { class: "flex flex-col {content_min_height} overflow-y-auto",
+                {children}
                 div { class: "{content_margin}" }
             }
         }
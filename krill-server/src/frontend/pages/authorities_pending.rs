@@ -0,0 +1,166 @@
+use dioxus::prelude::*;
+
+use crate::{
+    ButtonInfo, LoadingLanguageTranslation, PrimaryButton, Translations, NOTIFICATION_MANAGER,
+    SELECTED_LANGUAGE,
+};
+
+/// Shown after the superuser signs in until enough authorities accepted their invitation
+#[component]
+pub fn AuthoritiesPending() -> Element {
+    let mut translations = use_signal(|| Translations::default());
+
+    let mut loading_langs = use_signal(|| true);
+    let mut reload = use_signal(|| 0u32);
+    let mut error_watcher = use_signal(|| String::default());
+    let mut notice = use_signal(|| String::default());
+
+    use_effect(move || {
+        spawn(async move {
+            match Translations::get_translation("authorities", SELECTED_LANGUAGE.read().code())
+                .await
+            {
+                Ok(fetched_translations) => {
+                    translations.set(fetched_translations);
+                    loading_langs.set(false);
+                }
+                Err(error) => {
+                    NOTIFICATION_MANAGER
+                        .send_final(wasm_toolkit::NotificationType::Failure(
+                            wasm_toolkit::WasmToolkitError::Op(error.to_string()),
+                        ))
+                        .await
+                }
+            };
+        });
+    });
+
+    let progress = use_resource(move || async move {
+        reload.read();
+
+        crate::authority_progress().await
+    });
+
+    let mut error_handler = move |error: ServerFnError| match error {
+        ServerFnError::ServerError {
+            message,
+            code: _,
+            details: _,
+        } => {
+            error_watcher.set(message.to_string());
+        }
+        _ => {
+            error_watcher.set(error.to_string());
+        }
+    };
+
+    if *loading_langs.read() {
+        return rsx! {
+            LoadingLanguageTranslation {}
+        };
+    }
+
+    let (threshold, verified, pending) = match progress.read().as_ref() {
+        Some(Ok(value)) => (
+            value.threshold,
+            value.verified.clone(),
+            value.pending.clone(),
+        ),
+        _ => (0, Vec::default(), Vec::default()),
+    };
+
+    rsx! {
+        div { class: "krill-bg-dots flex flex-col w-full min-h-screen items-center justify-center p-5 font-[subheadingfont]",
+            div { class: "flex text-center text-4xl font-[headingfont] dark:text-[var(--primary-color)] font-black mb-5",
+                {translations.read().translate("authorities_pending_header")}
+            }
+            div { class: "flex w-[80%] lg:max-w-[60%] text-lg mb-5",
+                {
+                    translations
+                        .read()
+                        .translate("authorities_pending_notice")
+                        .replace("{verified}", &verified.len().to_string())
+                        .replace("{threshold}", &threshold.to_string())
+                }
+            }
+
+            div { class: "flex flex-col w-[80%] lg:max-w-[60%] gap-1 mb-5",
+                for email in verified.iter() {
+                    div { key: "{email}", class: "flex w-full justify-between font-[monospacefont]",
+                        span { {email.as_str()} }
+                        span { class: "text-green-500", {translations.read().translate("accepted")} }
+                    }
+                }
+                for email in pending.iter() {
+                    div { key: "{email}", class: "flex w-full justify-between font-[monospacefont]",
+                        span { {email.as_str()} }
+                        span { class: "dark:text-[var(--primary-color)]", {translations.read().translate("pending")} }
+                    }
+                }
+            }
+
+            div { class: "flex flex-col w-full items-center gap-4",
+                if threshold > 0 && verified.len() >= threshold as usize {
+                    PrimaryButton {
+                        info: ButtonInfo::new_enabled_and_width(
+                            &translations.read().translate("continue"),
+                            "w-[70%] max-w-[400px]",
+                        ),
+                        callback: move |_| {
+                            navigator().push(crate::RouteUtils::DASHBOARD);
+                        },
+                    }
+                }
+                PrimaryButton {
+                    info: ButtonInfo::new_enabled_and_width(
+                        &translations.read().translate("refresh"),
+                        "w-[70%] max-w-[400px]",
+                    ),
+                    callback: move |_| {
+                        error_watcher.set(String::default());
+                        *reload.write() += 1;
+                    },
+                }
+                PrimaryButton {
+                    info: ButtonInfo::new_enabled_and_width(
+                        &translations.read().translate("resend_invitations"),
+                        "w-[70%] max-w-[400px]",
+                    ),
+                    callback: move |_| {
+                        error_watcher.set(String::default());
+
+                        spawn(async move {
+                            match crate::authority_resend_invitations().await {
+                                Ok(failed) if failed.is_empty() => {
+                                    notice.set(translations.read().translate("invitations_resent"));
+                                }
+                                Ok(failed) => {
+                                    error_watcher.set(failed.join(", "));
+                                }
+                                Err(error) => error_handler(error),
+                            }
+                        });
+                    },
+                }
+            }
+
+            if !notice.read().is_empty() {
+                div { class: "flex w-[80%] lg:max-w-[60%] text-lg mt-4 text-green-500",
+                    {notice.read().as_str()}
+                }
+            }
+
+            if !error_watcher.read().is_empty() {
+                div { class: "flex items-center justify-start flex w-[80%] lg:max-w-[60%] text-lg text-wrap flex-wrap px-4 py-2 mb-1 mt-4",
+                    span { class: "flex mr-1 max-w-[20px] min-w-[15px] w-[20%] border border-[var(--primary-color)] rounded-full p-[1px]",
+                        img { src: crate::ERROR_ICON, alt: "error_icon" }
+                    }
+
+                    span { class: "flex px-0.5 font-bold font-black text-lg lg:text-xl dark:text-red-300 light:text-red-500",
+                        {error_watcher.read().as_str()}
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,124 @@
+use dioxus::prelude::*;
+use krill_common::AuthoritySetup;
+
+use crate::{
+    ButtonInfo, ClearButton, PrimaryButton, ProgressStateToUiRecord, Translations,
+    NOTIFICATION_MANAGER,
+};
+
+#[component]
+pub fn Administrators() -> Element {
+    let mut state_data = consume_context::<Signal<ProgressStateToUiRecord>>();
+    let translations = consume_context::<Signal<Translations>>();
+
+    let mut authorities = use_signal(|| {
+        let saved = state_data.read().authorities.clone();
+
+        if saved.is_empty() {
+            vec![String::default()]
+        } else {
+            saved
+        }
+    });
+    let mut threshold = use_signal(|| state_data.read().threshold.unwrap_or(1).to_string());
+    let mut error_watcher = use_signal(|| String::default());
+
+    rsx! {
+        div { class: "flex flex-col w-full max-w-[500px] items-center justify-start transition duration-1000 ease-in-out",
+            div { class: "w-full text-[var(--primary-color)] text-4xl font-[headingfont] font-black mb-2",
+                {translations.read().translate("administrators_heading")}
+            }
+            div { class: "dark:text-white light:text-black font-[subheadingfont] font-thin w-full mb-10",
+                {translations.read().translate("administrators_subheading")}
+            }
+
+            for (index, email) in authorities.read().iter().cloned().enumerate() {
+                div { key: "{index}", class: "flex w-full items-center gap-2 mb-4",
+                    input {
+                        class: "flex-1 font-[monospacefont] dark:text-[var(--primary-color)] rounded-[50px]
+                            border border-[var(--primary-color)] px-4 py-1.5 focus:outline-none",
+                        r#type: "email",
+                        placeholder: translations.read().translate("authority_email_placeholder"),
+                        value: "{email}",
+                        oninput: move |event| {
+                            authorities.write()[index] = event.value();
+                        },
+                    }
+                    if authorities.read().len() > 1 {
+                        span {
+                            class: "flex cursor-pointer px-2 font-[subheadingfont] dark:text-red-300 light:text-red-500",
+                            onclick: move |_| {
+                                authorities.write().remove(index);
+                            },
+                            {translations.read().translate("remove_authority")}
+                        }
+                    }
+                }
+            }
+
+            div { class: "flex w-full justify-start mb-10",
+                ClearButton {
+                    info: ButtonInfo::new_enabled_and_width(
+                        &translations.read().translate("add_authority"),
+                        "w-full max-w-[200px]",
+                    ),
+                    callback: move |_| {
+                        authorities.write().push(String::default());
+                    },
+                }
+            }
+
+            label {
+                class: "w-full flex mb-2.5 text-md font-medium font-[subheadingfont] dark:text-[var(--primary-color)]",
+                r#for: "authority-threshold",
+                {translations.read().translate("threshold_label_text")}
+            }
+            input {
+                id: "authority-threshold",
+                class: "w-full font-[monospacefont] dark:text-[var(--primary-color)] rounded-[50px]
+                    border border-[var(--primary-color)] px-4 py-1.5 mb-10 focus:outline-none",
+                r#type: "number",
+                min: "1",
+                value: "{threshold}",
+                oninput: move |event| threshold.set(event.value()),
+            }
+
+            if !error_watcher.read().is_empty() {
+                div { class: "flex w-full font-[subheadingfont] text-lg mb-5 dark:text-red-300 light:text-red-500",
+                    {error_watcher.read().as_str()}
+                }
+            }
+
+            div { class: "flex w-full mb-10 items-center justify-center",
+                PrimaryButton {
+                    info: ButtonInfo::new_enabled_and_width(
+                        &translations.read().translate("next"),
+                        "w-full max-w-[200px]",
+                    ),
+                    callback: move |_| {
+                        let parsed_threshold = threshold.read().trim().parse::<u16>().unwrap_or_default();
+
+                        let setup = match AuthoritySetup::new(&authorities.read(), parsed_threshold) {
+                            Ok(value) => value,
+                            Err(error) => {
+                                error_watcher.set(error.to_string());
+
+                                return;
+                            }
+                        };
+                        error_watcher.set(String::default());
+
+                        let mut state_writer = state_data.write();
+                        state_writer.set_authorities(setup.authorities().to_vec(), setup.threshold());
+
+                        if let Err(error) = state_writer.transition() {
+                            spawn(async move {
+                                NOTIFICATION_MANAGER.send_final_error(error).await;
+                            });
+                        }
+                    },
+                }
+            }
+        }
+    }
+}
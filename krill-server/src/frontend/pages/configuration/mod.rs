@@ -13,6 +13,9 @@ pub use org_info::*;
 mod secrets;
 pub use secrets::*;
 
+mod administrators;
+pub use administrators::*;
+
 mod verification;
 pub use verification::*;
 
@@ -223,8 +223,9 @@ fn SupportMail(support_mail: Signal<String>) -> Element {
     }
 }
 
+/// Also used on the settings page, which provides the same translation keys
 #[component]
-fn UploadImage(
+pub(crate) fn UploadImage(
     heading: String,
     dropped_files: Signal<Vec<FileUploadInfo>>,
     dropzone_id: String,
@@ -2,12 +2,13 @@ use core::fmt;
 
 use countries_iso3166::BC47LanguageInfo;
 use dioxus::prelude::*;
+use krill_common::MailProvider;
 use serde::{Deserialize, Serialize};
 use wasm_toolkit::WasmToolkitResult;
 
 use crate::{
-    ApiSecrets, LanguageView, Loader, OrgInfo, Passcode, Translations, Verification,
-    NOTIFICATION_MANAGER, WINDOW,
+    Administrators, ApiSecrets, LanguageView, Loader, OrgInfo, Passcode, Translations,
+    Verification, NOTIFICATION_MANAGER, WINDOW,
 };
 
 #[component]
@@ -88,6 +89,9 @@ pub fn Configuration() -> Element {
                         ConfigurationProgress::ApiSecrets => rsx! {
                             ApiSecrets {}
                         },
+                        ConfigurationProgress::Administrators => rsx! {
+                            Administrators {}
+                        },
                         ConfigurationProgress::Passcode => rsx! {
                             Passcode {}
                         },
@@ -106,7 +110,7 @@ fn progress_info(
     translations: Signal<Translations>,
 ) -> Element {
     rsx! {
-        div { class: "flex w-full grid-column-7",
+        div { class: "flex w-full grid-column-9",
             {
                 progress_info_item(
                     progress_state_data,
@@ -131,6 +135,14 @@ fn progress_info(
                 )
             }
             ProgressInfoItemBar {}
+            {
+                progress_info_item(
+                    progress_state_data,
+                    ConfigurationProgress::Administrators,
+                    translations,
+                )
+            }
+            ProgressInfoItemBar {}
             {
                 progress_info_item(
                     progress_state_data,
@@ -198,9 +210,15 @@ pub struct ProgressStateToUiRecord {
     pub language: BC47LanguageInfo,
     pub org_info: CacheOrgInfo,
     pub domain_name: Option<String>,
-    pub smtp_info: Option<String>,
+    pub mail_info: Option<MailProvider>,
     pub api_key: Option<String>,
     pub passcode: Option<String>,
+    /// Email addresses of the authorities to invite
+    #[serde(default)]
+    pub authorities: Vec<String>,
+    /// How many authorities must accept before initialization completes
+    #[serde(default)]
+    pub threshold: Option<u16>,
 }
 
 impl ProgressStateToUiRecord {
@@ -282,8 +300,8 @@ impl ProgressStateToUiRecord {
         self
     }
 
-    pub fn set_smtps(&mut self, smtps: &str) -> &mut Self {
-        self.smtp_info.replace(smtps.to_string());
+    pub fn set_mail(&mut self, mail: MailProvider) -> &mut Self {
+        self.mail_info.replace(mail);
 
         self
     }
@@ -294,6 +312,13 @@ impl ProgressStateToUiRecord {
         self
     }
 
+    pub fn set_authorities(&mut self, authorities: Vec<String>, threshold: u16) -> &mut Self {
+        self.authorities = authorities;
+        self.threshold.replace(threshold);
+
+        self
+    }
+
     pub fn set_progress_state(&mut self, state: ConfigurationProgress) -> &mut Self {
         self.progress_state = state;
 
@@ -304,7 +329,8 @@ impl ProgressStateToUiRecord {
         let state = match self.progress_state {
             ConfigurationProgress::Language => ConfigurationProgress::OrgInfo,
             ConfigurationProgress::OrgInfo => ConfigurationProgress::ApiSecrets,
-            ConfigurationProgress::ApiSecrets => ConfigurationProgress::Passcode,
+            ConfigurationProgress::ApiSecrets => ConfigurationProgress::Administrators,
+            ConfigurationProgress::Administrators => ConfigurationProgress::Passcode,
             ConfigurationProgress::Passcode => ConfigurationProgress::Verification,
             ConfigurationProgress::Verification => ConfigurationProgress::Verification,
         };
@@ -356,6 +382,7 @@ pub(crate) enum ConfigurationProgress {
     Language,
     OrgInfo,
     ApiSecrets,
+    Administrators,
     Passcode,
     Verification,
 }
@@ -366,6 +393,7 @@ impl ConfigurationProgress {
             Self::Language => "language",
             Self::OrgInfo => "branding",
             Self::ApiSecrets => "apisecrets",
+            Self::Administrators => "administrators",
             Self::Passcode => "passcode",
             Self::Verification => "verification",
         }
@@ -376,6 +404,7 @@ impl ConfigurationProgress {
             Self::Language => "Krill Shield Logo",
             Self::OrgInfo => "Branding",
             Self::ApiSecrets => "Api Secrets",
+            Self::Administrators => "Administrators",
             Self::Passcode => "Passcode",
             Self::Verification => "Verification",
         }
@@ -386,6 +415,7 @@ impl ConfigurationProgress {
             Self::Language => asset!("/assets/icons/language.svg"),
             Self::OrgInfo => asset!("/assets/icons/identity.svg"),
             Self::ApiSecrets => asset!("/assets/icons/network.svg"),
+            Self::Administrators => asset!("/assets/icons/members.svg"),
             Self::Passcode => asset!("/assets/icons/krill-shield-logo.svg"),
             Self::Verification => asset!("/assets/icons/krill-shield-logo.svg"),
         }
@@ -1,4 +1,7 @@
 use dioxus::prelude::*;
+use krill_common::{
+    MailApiConfig, MailApiProvider, MailProvider, SmtpAuthMechanism, SmtpConfig, SmtpSecurity,
+};
 use url::Url;
 
 use crate::{PrimaryButton, ProgressStateToUiRecord, Translations, NOTIFICATION_MANAGER};
@@ -14,12 +17,31 @@ pub fn ApiSecrets() -> Element {
     let username = use_signal(|| SmtpsInfo::username(&translations.read()).to_string());
     let password = use_signal(|| SmtpsInfo::password(&translations.read()).to_string());
     let smtp_server = use_signal(|| SmtpsInfo::smtp_server(&translations.read()).to_string());
-    let port = use_signal(|| SmtpsInfo::port());
+    let mut port = use_signal(|| SmtpSecurity::default().default_port().to_string());
+    let mut security = use_signal(|| SmtpSecurity::default().as_str().to_string());
+    let auth = use_signal(|| SmtpAuthMechanism::default().as_str().to_string());
+    let ca_bundle = use_signal(|| String::default());
+    let timeout = use_signal(|| SmtpConfig::DEFAULT_TIMEOUT_SECS.to_string());
+    let pool_size = use_signal(|| SmtpConfig::DEFAULT_POOL_SIZE.to_string());
+
+    let delivery = use_signal(|| MailProvider::SMTP.to_string());
+    let mut api_provider = use_signal(|| MailApiProvider::default().as_str().to_string());
+    let mut api_endpoint = use_signal(|| MailApiProvider::default().default_endpoint().to_string());
+    let mail_api_key = use_signal(String::default);
+    let api_timeout = use_signal(|| MailApiConfig::DEFAULT_TIMEOUT_SECS.to_string());
 
     let username_error_handler = use_signal(|| false);
     let password_error_handler = use_signal(|| false);
     let smtp_server_error_handler = use_signal(|| false);
     let apikey_error_handler = use_signal(|| false);
+    let api_endpoint_error_handler = use_signal(|| false);
+    let mail_api_key_error_handler = use_signal(|| false);
+
+    let uses_api = move || delivery.read().as_str() == MailProvider::API;
+    let selected_api_provider =
+        move || MailApiProvider::parse(&api_provider.read()).unwrap_or_default();
+
+    let logs_in = move || auth.read().as_str() != SmtpAuthMechanism::None.as_str();
 
     // "username@domain.tld:password@smtp.example.com:465"
     let formatter = move |hidden: bool| -> String {
@@ -46,7 +68,120 @@ pub fn ApiSecrets() -> Element {
         } else {
             smtp_server.read().to_string()
         };
-        format!("{}:{}@{}:{}", username, password, smtp_server, port.read())
+
+        if logs_in() {
+            format!("{}:{}@{}:{}", username, password, smtp_server, port.read())
+        } else {
+            format!("{}:{}", smtp_server, port.read())
+        }
+    };
+
+    // Numbers that do not parse are kept as 0, which the validation rejects
+    let smtp_config = move || SmtpConfig {
+        host: smtp_server.read().trim().to_string(),
+        port: port.read().trim().parse().unwrap_or_default(),
+        security: SmtpSecurity::parse(&security.read()).unwrap_or_default(),
+        auth: SmtpAuthMechanism::parse(&auth.read()).unwrap_or_default(),
+        username: if logs_in() {
+            username.read().trim().to_string()
+        } else {
+            String::default()
+        },
+        password: if logs_in() {
+            password.read().clone()
+        } else {
+            String::default()
+        },
+        ca_bundle: ca_bundle.read().trim().to_string(),
+        timeout_secs: timeout.read().trim().parse().unwrap_or_default(),
+        pool_size: pool_size.read().trim().parse().unwrap_or_default(),
+    };
+
+    let mail_provider = move || -> MailProvider {
+        if uses_api() {
+            MailApiConfig {
+                provider: selected_api_provider(),
+                endpoint: api_endpoint.read().trim().to_string(),
+                api_key: mail_api_key.read().trim().to_string(),
+                timeout_secs: api_timeout.read().trim().parse().unwrap_or_default(),
+            }
+            .into()
+        } else {
+            smtp_config().into()
+        }
+    };
+
+    let delivery_options = [MailProvider::SMTP, MailProvider::API]
+        .iter()
+        .map(|option| {
+            (
+                option.to_string(),
+                translations
+                    .read()
+                    .translate(&("mail_delivery_".to_string() + *option)),
+            )
+        })
+        .collect::<Vec<(String, String)>>();
+    let api_provider_options = MailApiProvider::ALL
+        .iter()
+        .map(|option| {
+            (
+                option.as_str().to_string(),
+                translations
+                    .read()
+                    .translate(&("mail_api_provider_".to_string() + option.as_str())),
+            )
+        })
+        .collect::<Vec<(String, String)>>();
+
+    let security_options = SmtpSecurity::ALL
+        .iter()
+        .map(|option| {
+            (
+                option.as_str().to_string(),
+                translations
+                    .read()
+                    .translate(&("smtp_security_".to_string() + option.as_str())),
+            )
+        })
+        .collect::<Vec<(String, String)>>();
+    let auth_options = SmtpAuthMechanism::ALL
+        .iter()
+        .map(|option| {
+            (
+                option.as_str().to_string(),
+                translations
+                    .read()
+                    .translate(&("smtp_auth_".to_string() + option.as_str())),
+            )
+        })
+        .collect::<Vec<(String, String)>>();
+
+    let credentials_entered = !logs_in()
+        || (username.read().as_str() != SmtpsInfo::username(&translations.read())
+            && password.read().as_str() != SmtpsInfo::password(&translations.read())
+            && !username.read().is_empty()
+            && !password.read().is_empty());
+
+    // Webhooks may be left without an API key
+    let mail_entered = if uses_api() {
+        !api_endpoint.read().trim().is_empty()
+            && (!mail_api_key.read().trim().is_empty()
+                || !selected_api_provider().requires_api_key())
+    } else {
+        smtp_server.read().as_str() != SmtpsInfo::smtp_server(&translations.read())
+            && credentials_entered
+            && !smtp_server.read().is_empty()
+            && !port.read().is_empty()
+    };
+
+    let (preview_label, preview) = if uses_api() {
+        (
+            api_provider.read().to_uppercase(),
+            api_endpoint.read().to_string(),
+        )
+    } else {
+        (security.read().to_uppercase(), formatter(true))
     };
 
     rsx! {
@@ -72,66 +207,208 @@ pub fn ApiSecrets() -> Element {
                 div { class: "flex w-full  items-center justify-center",
                     div { class: "flex bg-[var(--primary-color)] rounded-l-[50px] px-2 py-1.5
                         font-[monospacefont] text-md lg:text-lg text-white",
-                        "smtps://"
+                        {preview_label}
                     }
                     div { class: "flex overflow-hidden text-ellipsis whitespace-nowrap font-[monospacefont] rounded-r-[50px] items-center justify-start border border-[var(--primary-color)] px-2 py-1.5",
-                        {formatter(true)}
+                        {preview}
                     }
                 }
             }
 
-            SecretsEntry {
-                input_id: "smtp-username",
-                placeholder: SmtpsInfo::username(&translations.read()),
-                label_text: translations.read().translate("smtp_username_label_text"),
-                input_icon_text: translations.read().translate("smtp_username_icon_text"),
-                error_handler: username_error_handler,
-                error_text: translations.read().translate("username_invalid_error_text"),
-                write_input: username,
-                r#type: "text",
+            SelectEntry {
+                input_id: "mail-delivery",
+                label_text: translations.read().translate("mail_delivery_label_text"),
+                input_icon_text: translations.read().translate("mail_delivery_icon_text"),
+                options: delivery_options,
+                write_input: delivery,
             }
 
-            SecretsEntry {
-                input_id: "smtp-password",
-                placeholder: SmtpsInfo::password(&translations.read()),
-                label_text: translations.read().translate("smtp_password_label_text"),
-                input_icon_text: translations.read().translate("smtp_password_icon_text"),
-                error_handler: password_error_handler,
-                error_text: translations.read().translate("invalid_smtp_password_text"),
-                write_input: password,
-                r#type: "password",
-            }
+            if uses_api() {
+                SelectEntry {
+                    input_id: "mail-api-provider",
+                    label_text: translations.read().translate("mail_api_provider_label_text"),
+                    input_icon_text: translations.read().translate("mail_api_provider_icon_text"),
+                    options: api_provider_options,
+                    write_input: api_provider,
+                    on_change: move |value: String| {
+                        // Follows the provider while the endpoint is a default one
+                        let default_endpoints = MailApiProvider::ALL
+                            .map(|provider| provider.default_endpoint().to_string());
+
+                        if let Some(selected) = MailApiProvider::parse(&value) {
+                            if default_endpoints.contains(&*api_endpoint.read()) {
+                                api_endpoint.set(selected.default_endpoint().to_string());
+                            }
+                        }
 
-            SecretsEntry {
-                input_id: "smtp-server",
-                placeholder: SmtpsInfo::smtp_server(&translations.read()),
-                label_text: translations.read().translate("smtp_server_label_text"),
-                input_icon_text: translations.read().translate("smtp_server_icon_text"),
-                error_handler: smtp_server_error_handler,
-                error_text: translations.read().translate("invalid_smtp_server_error_text"),
-                write_input: smtp_server,
-                r#type: "text",
-            }
+                        api_provider.set(value);
+                    },
+                }
+
+                SecretsEntry {
+                    input_id: "mail-api-endpoint",
+                    placeholder: translations.read().translate("mail_api_endpoint_placeholder"),
+                    label_text: translations.read().translate("mail_api_endpoint_label_text"),
+                    input_icon_text: translations.read().translate("mail_api_endpoint_icon_text"),
+                    error_handler: api_endpoint_error_handler,
+                    error_text: translations.read().translate("invalid_mail_api_endpoint_error_text"),
+                    write_input: api_endpoint,
+                    r#type: "url",
+                }
+
+                SecretsEntry {
+                    input_id: "mail-api-key",
+                    placeholder: String::default(),
+                    label_text: translations.read().translate("mail_api_key_label_text"),
+                    input_icon_text: translations.read().translate("mail_api_key_icon_text"),
+                    error_handler: mail_api_key_error_handler,
+                    error_text: translations.read().translate("invalid_mail_api_key_error_text"),
+                    write_input: mail_api_key,
+                    r#type: "password",
+                }
 
-            PortEntry { write_input: port }
+                NumberEntry {
+                    input_id: "mail-api-timeout",
+                    placeholder: MailApiConfig::DEFAULT_TIMEOUT_SECS.to_string(),
+                    label_text: translations.read().translate("mail_api_timeout_label_text"),
+                    input_icon_text: translations.read().translate("smtp_timeout_icon_text"),
+                    error_text: translations
+                        .read()
+                        .translate("invalid_smtp_timeout_error_text")
+                        .replace("{max}", &MailApiConfig::MAX_TIMEOUT_SECS.to_string()),
+                    max: MailApiConfig::MAX_TIMEOUT_SECS,
+                    write_input: api_timeout,
+                }
+            } else {
+                SecretsEntry {
+                    input_id: "smtp-server",
+                    placeholder: SmtpsInfo::smtp_server(&translations.read()),
+                    label_text: translations.read().translate("smtp_server_label_text"),
+                    input_icon_text: translations.read().translate("smtp_server_icon_text"),
+                    error_handler: smtp_server_error_handler,
+                    error_text: translations.read().translate("invalid_smtp_server_error_text"),
+                    write_input: smtp_server,
+                    r#type: "text",
+                }
+
+                SelectEntry {
+                    input_id: "smtp-security",
+                    label_text: translations.read().translate("smtp_security_label_text"),
+                    input_icon_text: translations.read().translate("smtp_security_icon_text"),
+                    options: security_options,
+                    write_input: security,
+                    on_change: move |value: String| {
+                        // Follows the security mode while the port is a default one
+                        let default_ports = SmtpSecurity::ALL
+                            .map(|security| security.default_port().to_string());
+
+                        if let Some(selected) = SmtpSecurity::parse(&value) {
+                            if port.read().is_empty() || default_ports.contains(&*port.read()) {
+                                port.set(selected.default_port().to_string());
+                            }
+                        }
+
+                        security.set(value);
+                    },
+                }
 
-            if username.read().as_str() != SmtpsInfo::username(&translations.read())
-                && password.read().as_str() != SmtpsInfo::password(&translations.read())
-                && smtp_server.read().as_str() != SmtpsInfo::smtp_server(&translations.read())
-                && apikey.read().as_str() != apikey_defaults && !username.read().is_empty()
-                && !password.read().is_empty() && !smtp_server.read().is_empty()
-                && !port.read().is_empty() && !apikey.read().is_empty()
-            {
+                NumberEntry {
+                    input_id: "port-input-id",
+                    placeholder: translations.read().translate("smtp_port_placeholder"),
+                    label_text: translations.read().translate("smtp_port_label_text"),
+                    input_icon_text: translations.read().translate("smtp_port_icon_text"),
+                    error_text: translations.read().translate("invalid_smtp_port_error_text"),
+                    max: u16::MAX,
+                    write_input: port,
+                }
+
+                SelectEntry {
+                    input_id: "smtp-auth",
+                    label_text: translations.read().translate("smtp_auth_label_text"),
+                    input_icon_text: translations.read().translate("smtp_auth_icon_text"),
+                    options: auth_options,
+                    write_input: auth,
+                }
+
+                if logs_in() {
+                    SecretsEntry {
+                        input_id: "smtp-username",
+                        placeholder: SmtpsInfo::username(&translations.read()),
+                        label_text: translations.read().translate("smtp_username_label_text"),
+                        input_icon_text: translations.read().translate("smtp_username_icon_text"),
+                        error_handler: username_error_handler,
+                        error_text: translations.read().translate("username_invalid_error_text"),
+                        write_input: username,
+                        r#type: "text",
+                    }
+
+                    SecretsEntry {
+                        input_id: "smtp-password",
+                        placeholder: SmtpsInfo::password(&translations.read()),
+                        label_text: translations.read().translate("smtp_password_label_text"),
+                        input_icon_text: translations.read().translate("smtp_password_icon_text"),
+                        error_handler: password_error_handler,
+                        error_text: translations.read().translate("invalid_smtp_password_text"),
+                        write_input: password,
+                        r#type: "password",
+                    }
+                }
+
+                CaBundleEntry { write_input: ca_bundle }
+
+                NumberEntry {
+                    input_id: "smtp-timeout",
+                    placeholder: SmtpConfig::DEFAULT_TIMEOUT_SECS.to_string(),
+                    label_text: translations.read().translate("smtp_timeout_label_text"),
+                    input_icon_text: translations.read().translate("smtp_timeout_icon_text"),
+                    error_text: translations
+                        .read()
+                        .translate("invalid_smtp_timeout_error_text")
+                        .replace("{max}", &SmtpConfig::MAX_TIMEOUT_SECS.to_string()),
+                    max: SmtpConfig::MAX_TIMEOUT_SECS,
+                    write_input: timeout,
+                }
+
+                NumberEntry {
+                    input_id: "smtp-pool-size",
+                    placeholder: SmtpConfig::DEFAULT_POOL_SIZE.to_string(),
+                    label_text: translations.read().translate("smtp_pool_size_label_text"),
+                    input_icon_text: translations.read().translate("smtp_pool_size_icon_text"),
+                    error_text: translations
+                        .read()
+                        .translate("invalid_smtp_pool_size_error_text")
+                        .replace("{max}", &SmtpConfig::MAX_POOL_SIZE.to_string()),
+                    max: SmtpConfig::MAX_POOL_SIZE,
+                    write_input: pool_size,
+                }
+            }
+
+            if apikey.read().as_str() != apikey_defaults && !apikey.read().is_empty() && mail_entered {
                 div { class: "flex w-full mb-10 items-center justify-center",
                     PrimaryButton {
                         info: crate::ButtonInfo::new_enabled_and_width("Next", "w-full max-w-[200px]"),
                         callback: move || {
-                            let smtps = String::from("smtps://") + formatter(false).as_str();
+                            let mail = mail_provider();
+
+                            // The server checks again, local transports may be disabled there
+                            if let Err(error) = mail.validate(true) {
+                                spawn(async move {
+                                    NOTIFICATION_MANAGER
+                                        .send_final(
+                                            wasm_toolkit::NotificationType::Failure(
+                                                wasm_toolkit::WasmToolkitError::Op(error.to_string()),
+                                            ),
+                                        )
+                                        .await;
+                                });
+
+                                return;
+                            }
 
                             let mut state_writer = state_data.write();
 
                             state_writer.set_api_key(apikey.read().as_str());
-                            state_writer.set_smtps(&smtps);
+                            state_writer.set_mail(mail);
                             if let Err(error) = state_writer.transition() {
                                 spawn(async move {
                                     NOTIFICATION_MANAGER.send_final_error(error).await;
@@ -232,43 +509,39 @@ fn SecretsEntry(
 }
 
 #[component]
-fn PortEntry(write_input: Signal<String>) -> Element {
-    let translations = consume_context::<Signal<Translations>>();
-
-    let placeholder = translations.read().translate("smtp_port_placeholder");
-    let port_id = "port-input-id";
-
+fn NumberEntry(
+    input_id: String,
+    placeholder: String,
+    label_text: String,
+    input_icon_text: String,
+    error_text: String,
+    max: u16,
+    write_input: Signal<String>,
+) -> Element {
     let mut error_handler = use_signal(|| bool::default());
 
     rsx! {
         div { class: "flex flex-col w-full max-w-[500px] items-start justify-start mb-10 h-full",
             label {
                 class: "w-full flex block mb-2.5 text-md font-medium text-heading font-[subheadingfont] dark:text-[var(--primary-color)]",
-                r#for: port_id,
-                {translations.read().translate("smtp_port_label_text")}
+                r#for: input_id.as_str(),
+                "{label_text}"
             }
             div { class: "flex w-full border border-[var(--primary-color)] rounded-[50px] border-default-medium ",
                 div { class: "flex min-w-[90px] px-2.5 py-1 font-[monospacefont] items-center justify-center text-center
                 text-white bg-[var(--primary-color)]  rounded-l-[50px]",
-                    {translations.read().translate("smtp_port_icon_text")}
+                    "{input_icon_text}"
                 }
                 input {
                     class: "[appearance:textfield] block font-[monospacefont] dark:text-[var(--primary-color)] w-full ps-9 pe-3  py-1.5
                     text-heading text-md border-transparent focus:border-transparent focus:ring-0 focus:outline-none shadow-none focus:shadow-none placeholder:text-body",
-                    id: port_id,
+                    id: input_id.as_str(),
                     oninput: move |event| {
                         let value: String = event.value();
 
-                        if let Ok(port) = value.parse::<u16>() {
-                            if port < u16::MAX {
-                                error_handler.set(false);
-
-                            } else {
-                                error_handler.set(true);
-                            }
-
-                        } else {
-                            error_handler.set(true);
+                        match value.parse::<u16>() {
+                            Ok(number) if number > 0 && number <= max => error_handler.set(false),
+                            _ => error_handler.set(true),
                         }
 
                         write_input.set(value);
@@ -283,14 +556,77 @@ fn PortEntry(write_input: Signal<String>) -> Element {
             div { class: "flex w-full p-1 mt-2.5 ",
                 if *error_handler.read() {
                     p { class: "text-sm text-red-400 light:text-red-900",
-                        span { class: "font-medium",
-                            {translations.read().translate("invalid_smtp_port_error_text")}
-                        }
+                        span { class: "font-medium", "{error_text}" }
+                    }
+                }
+            }
+        }
+
+    }
+}
+
+/// `on_change` replaces writing the selected value to `write_input`
+#[component]
+fn SelectEntry(
+    input_id: String,
+    label_text: String,
+    input_icon_text: String,
+    options: Vec<(String, String)>,
+    write_input: Signal<String>,
+    on_change: Option<EventHandler<String>>,
+) -> Element {
+    rsx! {
+        div { class: "flex flex-col w-full max-w-[500px] items-start justify-start mb-10 h-full",
+            label {
+                class: "w-full flex block mb-2.5 text-md font-medium text-heading font-[subheadingfont] dark:text-[var(--primary-color)]",
+                r#for: input_id.as_str(),
+                "{label_text}"
+            }
+            div { class: "flex w-full border border-[var(--primary-color)] rounded-[50px] border-default-medium ",
+                div { class: "flex min-w-[90px] px-2.5 py-1 font-[monospacefont] items-center justify-center text-center
+                text-white bg-[var(--primary-color)]  rounded-l-[50px]",
+                    "{input_icon_text}"
+                }
+                select {
+                    class: "block font-[monospacefont] dark:text-[var(--primary-color)] w-full ps-9 pe-3 py-1.5 bg-transparent
+                    text-heading text-md border-transparent focus:border-transparent focus:ring-0 focus:outline-none shadow-none focus:shadow-none",
+                    id: input_id.as_str(),
+                    value: write_input.read().clone(),
+                    onchange: move |event| match on_change.as_ref() {
+                        Some(on_change) => on_change.call(event.value()),
+                        None => write_input.set(event.value()),
+                    },
+                    for (value, text) in options {
+                        option { value, "{text}" }
                     }
                 }
             }
         }
+    }
+}
+
+/// Optional PEM certificates for SMTP servers with a private certificate authority
+#[component]
+fn CaBundleEntry(write_input: Signal<String>) -> Element {
+    let translations = consume_context::<Signal<Translations>>();
+    let input_id = "smtp-ca-bundle";
 
+    rsx! {
+        div { class: "flex flex-col w-full max-w-[500px] items-start justify-start mb-10 h-full",
+            label {
+                class: "w-full flex block mb-2.5 text-md font-medium text-heading font-[subheadingfont] dark:text-[var(--primary-color)]",
+                r#for: input_id,
+                {translations.read().translate("smtp_ca_bundle_label_text")}
+            }
+            textarea {
+                class: "block w-full h-28 font-[monospacefont] text-xs dark:text-[var(--primary-color)] px-4 py-2
+                border border-[var(--primary-color)] rounded-[20px] bg-transparent focus:outline-none",
+                id: input_id,
+                placeholder: "-----BEGIN CERTIFICATE-----",
+                value: write_input.read().clone(),
+                oninput: move |event| write_input.set(event.value()),
+            }
+        }
     }
 }
 
@@ -308,8 +644,4 @@ impl SmtpsInfo {
     fn smtp_server(translations: &Translations) -> String {
         translations.translate("smtp_server_placeholder")
     }
-
-    fn port() -> String {
-        465.to_string()
-    }
 }
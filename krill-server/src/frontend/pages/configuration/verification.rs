@@ -221,6 +221,23 @@ fn event_matcher(
                 &translations.read().translate("create_org_failed")
             )));
         }
+        ConfigVerificationOutcome::InvitingAuthorities => {
+            events.write().push(testing_event(
+                &translations.read().translate("inviting_authorities"),
+            ));
+        }
+        ConfigVerificationOutcome::AuthoritiesInvited => {
+            events.write().push(event_succeeded(
+                &translations.read().translate("authorities_invited"),
+            ));
+        }
+        // The organization exists so signing in stays possible and the invitations can be resent
+        ConfigVerificationOutcome::AuthorityInvitationFailed(error) => {
+            events.write().push(error_event(&format!(
+                "{} `{error}`",
+                &translations.read().translate("authority_invitation_failed")
+            )));
+        }
     }
 }
 
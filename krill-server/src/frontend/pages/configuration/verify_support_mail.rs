@@ -20,6 +20,18 @@ pub fn VerifySupportMail() -> Element {
     let mut error_watcher = use_signal(|| String::default());
     let mut can_resend = use_signal(|| false);
     let mut countdown = use_signal(|| Option::<u64>::default());
+    let mut mail_status = use_signal(|| String::default());
+
+    // Polled every few seconds of the countdown until the email is sent or not delivered
+    let _mail_status_poll = use_resource(move || async move {
+        let tick = countdown.read().unwrap_or_default();
+
+        if tick % 5 == 0 && matches!(mail_status.peek().as_str(), "" | "queued") {
+            if let Ok(status) = crate::superuser_link_mail_status().await {
+                mail_status.set(status);
+            }
+        }
+    });
 
     use_effect(move || {
         spawn(async move {
@@ -150,6 +162,12 @@ pub fn VerifySupportMail() -> Element {
                             " "
                             {details_inner.obsf_mail.as_str()}
 
+                            if !mail_status.read().is_empty() {
+                                div { class: "flex w-full items-center justify-center mt-1 text-sm",
+                                    {translations.read().translate(&("mail_".to_string() + mail_status.read().as_str()))}
+                                }
+                            }
+
                             if let Some(count) = countdown.read().as_ref() {
                                 div { class: "flex w-full items-center justify-center mt-1 font-[monospacefont] text-lg",
 
@@ -172,6 +190,7 @@ pub fn VerifySupportMail() -> Element {
                                         ),
                                         callback: move |_| {
                                             details.set(Option::default());
+                                            mail_status.set(String::default());
                                             error_watcher.set(String::default());
                                             can_resend.set(false);
                                             countdown.write().take();
@@ -1,11 +1,48 @@
 use dioxus::prelude::*;
+use krill_common::KrillErrorCode;
+
+use crate::{LoadingLanguageTranslation, TranslationsMemInfo};
 
 #[component]
 pub fn Errors(message: String) -> Element {
+    let translations_info = use_signal(TranslationsMemInfo::new);
+
+    use_effect(move || {
+        spawn(async move {
+            TranslationsMemInfo::fetch("errors", translations_info).await;
+        });
+    });
+
+    if translations_info.read().loading {
+        return rsx! {
+            LoadingLanguageTranslation {}
+        };
+    }
+
+    let translations = translations_info.read().translations.clone();
+
+    // Errors are identified by their code, older links may still contain a message
+    let (code, description) = match KrillErrorCode::parse(&message) {
+        Some(code) => (
+            Some(code.to_string()),
+            translations.translate(&code.translation_key()),
+        ),
+        None => (None, message.clone()),
+    };
+
     rsx! {
         div { class: "flex flex-col w-full min-h-screen items-center justify-center",
+            div { class: "flex w-[90%] text-center items-center justify-center text-2xl font-[headingfont] font-black mb-2",
+                {translations.translate("errors_header")}
+            }
             div { class: "flex w-[90%] text-center items-center justify-center text-red-500",
-                "Error: {message}"
+                {description}
+            }
+            if let Some(code) = code {
+                div { class: "flex w-[90%] text-center items-center justify-center font-[monospacefont] text-sm mt-2",
+                    {translations.translate("error_code")}
+                    ": {code}"
+                }
             }
         }
     }
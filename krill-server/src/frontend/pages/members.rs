@@ -0,0 +1,436 @@
+use dioxus::prelude::*;
+
+use crate::{
+    ActiveItem, ButtonInfo, ClearButton, Header, LoadingLanguageTranslation, MemberToUi,
+    PrimaryButton, Translations, TranslationsMemInfo, NOTIFICATION_MANAGER, SELECTED_LANGUAGE,
+};
+
+#[component]
+pub fn Members() -> Element {
+    use_context_provider(|| Signal::new(TranslationsMemInfo::new()));
+
+    let translations_info = consume_context::<Signal<TranslationsMemInfo>>();
+    let mut translations = use_signal(|| Translations::default());
+
+    let mut loading_langs = use_signal(|| true);
+    let mut search = use_signal(|| String::default());
+    let mut status = use_signal(|| String::default());
+    let mut page = use_signal(|| 0usize);
+    // Bumped after every change so the directory is fetched again
+    let mut reload = use_signal(|| 0u32);
+
+    let mut invite_email = use_signal(|| String::default());
+    let mut invite_name = use_signal(|| String::default());
+    let mut invite_role = use_signal(|| "member".to_string());
+    let mut keys_email = use_signal(|| String::default());
+    let mut wallet_address = use_signal(|| String::default());
+    let mut frost_identifier = use_signal(|| String::default());
+    let mut error_watcher = use_signal(|| String::default());
+
+    use_effect(move || {
+        spawn(async move {
+            TranslationsMemInfo::fetch("dashboard", translations_info).await;
+
+            match Translations::get_translation("members", SELECTED_LANGUAGE.read().code()).await {
+                Ok(fetched_translations) => {
+                    translations.set(fetched_translations);
+                    loading_langs.set(false);
+                }
+                Err(error) => {
+                    NOTIFICATION_MANAGER
+                        .send_final(wasm_toolkit::NotificationType::Failure(
+                            wasm_toolkit::WasmToolkitError::Op(error.to_string()),
+                        ))
+                        .await
+                }
+            };
+        });
+    });
+
+    let mut error_handler = move |error: ServerFnError| match error {
+        ServerFnError::ServerError {
+            message,
+            code: _,
+            details: _,
+        } => {
+            error_watcher.set(message.to_string());
+        }
+        _ => {
+            error_watcher.set(error.to_string());
+        }
+    };
+
+    let directory = use_resource(move || async move {
+        reload.read();
+
+        crate::members_list(search.read().clone(), status.read().clone(), *page.read()).await
+    });
+
+    let submit_invite = move |_| {
+        let email = invite_email.read().trim().to_string();
+        let display_name = invite_name.read().trim().to_string();
+        let role = invite_role.read().clone();
+        error_watcher.set(String::default());
+
+        spawn(async move {
+            match crate::member_invite(email, display_name, role).await {
+                Ok(_) => {
+                    invite_email.set(String::default());
+                    invite_name.set(String::default());
+                    *reload.write() += 1;
+                }
+                Err(error) => error_handler(error),
+            }
+        });
+    };
+
+    let set_suspended = move |email: String, suspended: bool| {
+        error_watcher.set(String::default());
+
+        spawn(async move {
+            match crate::member_set_suspended(email, suspended).await {
+                Ok(_) => *reload.write() += 1,
+                Err(error) => error_handler(error),
+            }
+        });
+    };
+
+    let retry_mail = move |reference: String| {
+        error_watcher.set(String::default());
+
+        spawn(async move {
+            match crate::mail_retry(reference).await {
+                Ok(_) => *reload.write() += 1,
+                Err(error) => error_handler(error),
+            }
+        });
+    };
+
+    let remove = move |email: String| {
+        error_watcher.set(String::default());
+
+        spawn(async move {
+            match crate::member_remove(email).await {
+                Ok(()) => *reload.write() += 1,
+                Err(error) => error_handler(error),
+            }
+        });
+    };
+
+    // Selects the member the wallet and FROST identifier forms apply to
+    let manage = move |email: String| {
+        keys_email.set(email);
+        wallet_address.set(String::default());
+        frost_identifier.set(String::default());
+    };
+
+    let link_wallet = move |_| {
+        let email = keys_email.read().trim().to_string();
+        let address = wallet_address.read().trim().to_string();
+        error_watcher.set(String::default());
+
+        spawn(async move {
+            match crate::member_link_wallet(email, address).await {
+                Ok(_) => {
+                    wallet_address.set(String::default());
+                    *reload.write() += 1;
+                }
+                Err(error) => error_handler(error),
+            }
+        });
+    };
+
+    let unlink_wallet = move |email: String, address: String| {
+        error_watcher.set(String::default());
+
+        spawn(async move {
+            match crate::member_unlink_wallet(email, address).await {
+                Ok(_) => *reload.write() += 1,
+                Err(error) => error_handler(error),
+            }
+        });
+    };
+
+    let set_frost_identifier = move |_| {
+        let email = keys_email.read().trim().to_string();
+        let identifier = frost_identifier.read().trim().to_string();
+        error_watcher.set(String::default());
+
+        spawn(async move {
+            match crate::member_set_frost_identifier(email, identifier).await {
+                Ok(_) => {
+                    frost_identifier.set(String::default());
+                    *reload.write() += 1;
+                }
+                Err(error) => error_handler(error),
+            }
+        });
+    };
+
+    if *loading_langs.read() {
+        return rsx! {
+            LoadingLanguageTranslation {}
+        };
+    }
+
+    let (members, total, page_size, directory_error) = match directory.read().as_ref() {
+        Some(Ok(outcome)) => (
+            outcome.members.clone(),
+            outcome.total,
+            outcome.page_size,
+            None,
+        ),
+        Some(Err(error)) => (Vec::default(), 0, 1, Some(error.to_string())),
+        None => (Vec::default(), 0, 1, None),
+    };
+    let last_page = total.saturating_sub(1) / page_size.max(1);
+
+    rsx! {
+        div { class: "h-screen w-full flex flex-col justify-start items-center krill-bg-dots ",
+            Header { active: ActiveItem::Members,
+                div { class: "flex flex-col w-full items-center p-5 font-[subheadingfont]",
+                    div { class: "flex text-center text-3xl font-[headingfont] dark:text-[var(--primary-color)] font-black mb-5",
+                        {translations.read().translate("members_header")}
+                    }
+
+                    div { class: "flex flex-col md:flex-row w-full lg:max-w-[90dvh] gap-2 mb-4",
+                        input {
+                            class: "flex-1 rounded-lg px-4 py-2 bg-[var(--primary-color)]",
+                            placeholder: translations.read().translate("search_placeholder"),
+                            value: "{search}",
+                            oninput: move |event| {
+                                search.set(event.value());
+                                page.set(0);
+                            },
+                        }
+                        select {
+                            class: "rounded-lg px-4 py-2 bg-[var(--primary-color)]",
+                            value: "{status}",
+                            onchange: move |event| {
+                                status.set(event.value());
+                                page.set(0);
+                            },
+                            option { value: "", {translations.read().translate("status_all")} }
+                            option { value: "invited", {translations.read().translate("status_invited")} }
+                            option { value: "active", {translations.read().translate("status_active")} }
+                            option { value: "suspended", {translations.read().translate("status_suspended")} }
+                        }
+                    }
+
+                    div { class: "flex flex-col w-full lg:max-w-[90dvh] gap-2 mb-4",
+                        if let Some(directory_error) = directory_error {
+                            div { class: "flex w-full justify-center text-lg dark:text-red-300 light:text-red-500",
+                                {directory_error}
+                            }
+                        } else if members.is_empty() {
+                            div { class: "flex w-full justify-center text-lg",
+                                {translations.read().translate("no_members")}
+                            }
+                        }
+                        for member in members {
+                            {member_row(member, &translations.read(), set_suspended, retry_mail, remove, manage, unlink_wallet)}
+                        }
+                    }
+
+                    div { class: "flex w-full lg:max-w-[90dvh] items-center justify-between mb-6",
+                        ClearButton {
+                            info: ButtonInfo::new_enabled_and_width(&translations.read().translate("previous_page"), "w-[30%]"),
+                            callback: move |_| {
+                                let current = *page.read();
+                                page.set(current.saturating_sub(1));
+                            },
+                        }
+                        span { class: "text-sm", "{*page.read() + 1} / {last_page + 1}" }
+                        ClearButton {
+                            info: ButtonInfo::new_enabled_and_width(&translations.read().translate("next_page"), "w-[30%]"),
+                            callback: move |_| {
+                                let current = *page.read();
+                                page.set((current + 1).min(last_page));
+                            },
+                        }
+                    }
+
+                    div { class: "flex flex-col w-full lg:max-w-[90dvh] gap-2 items-center",
+                        div { class: "flex w-full text-xl font-bold",
+                            {translations.read().translate("invite_header")}
+                        }
+                        input {
+                            class: "w-full rounded-lg px-4 py-2 bg-[var(--primary-color)]",
+                            r#type: "email",
+                            placeholder: translations.read().translate("email_placeholder"),
+                            value: "{invite_email}",
+                            oninput: move |event| invite_email.set(event.value()),
+                        }
+                        input {
+                            class: "w-full rounded-lg px-4 py-2 bg-[var(--primary-color)]",
+                            placeholder: translations.read().translate("display_name_placeholder"),
+                            value: "{invite_name}",
+                            oninput: move |event| invite_name.set(event.value()),
+                        }
+                        select {
+                            class: "w-full rounded-lg px-4 py-2 bg-[var(--primary-color)]",
+                            value: "{invite_role}",
+                            onchange: move |event| invite_role.set(event.value()),
+                            option { value: "member", {translations.read().translate("role_member")} }
+                            option { value: "administrator", {translations.read().translate("role_administrator")} }
+                        }
+                        PrimaryButton {
+                            info: ButtonInfo::new_enabled_and_width(
+                                &translations.read().translate("send_invitation"),
+                                "w-[70%] max-w-[400px]",
+                            ),
+                            callback: submit_invite,
+                        }
+                    }
+
+                    div { class: "flex flex-col w-full lg:max-w-[90dvh] gap-2 items-center mt-6",
+                        div { class: "flex w-full text-xl font-bold",
+                            {translations.read().translate("keys_header")}
+                        }
+                        input {
+                            class: "w-full rounded-lg px-4 py-2 bg-[var(--primary-color)]",
+                            r#type: "email",
+                            placeholder: translations.read().translate("email_placeholder"),
+                            value: "{keys_email}",
+                            oninput: move |event| keys_email.set(event.value()),
+                        }
+                        input {
+                            class: "w-full rounded-lg px-4 py-2 bg-[var(--primary-color)] font-[monospacefont]",
+                            placeholder: translations.read().translate("wallet_placeholder"),
+                            value: "{wallet_address}",
+                            oninput: move |event| wallet_address.set(event.value()),
+                        }
+                        PrimaryButton {
+                            info: ButtonInfo::new_enabled_and_width(
+                                &translations.read().translate("link_wallet"),
+                                "w-[70%] max-w-[400px]",
+                            ),
+                            callback: link_wallet,
+                        }
+                        input {
+                            class: "w-full rounded-lg px-4 py-2 bg-[var(--primary-color)] font-[monospacefont]",
+                            placeholder: translations.read().translate("frost_identifier_placeholder"),
+                            value: "{frost_identifier}",
+                            oninput: move |event| frost_identifier.set(event.value()),
+                        }
+                        PrimaryButton {
+                            info: ButtonInfo::new_enabled_and_width(
+                                &translations.read().translate("set_frost_identifier"),
+                                "w-[70%] max-w-[400px]",
+                            ),
+                            callback: set_frost_identifier,
+                        }
+                    }
+
+                    if !error_watcher.read().is_empty() {
+                        div { class: "flex items-center justify-start flex w-[80%] lg:max-w-[60%] text-lg text-wrap flex-wrap px-4 py-2 mb-1 mt-1",
+                            span { class: "flex mr-1 max-w-[20px] min-w-[15px] w-[20%] border border-[var(--primary-color)] rounded-full p-[1px]",
+                                img { src: crate::ERROR_ICON, alt: "error_icon" }
+                            }
+
+                            span { class: "flex px-0.5 font-bold font-black text-lg lg:text-xl dark:text-red-300 light:text-red-500",
+                                {error_watcher.read().as_str()}
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn member_row(
+    member: MemberToUi,
+    translations: &Translations,
+    mut set_suspended: impl FnMut(String, bool) + Copy + 'static,
+    mut retry_mail: impl FnMut(String) + Copy + 'static,
+    mut remove: impl FnMut(String) + Copy + 'static,
+    mut manage: impl FnMut(String) + Copy + 'static,
+    unlink_wallet: impl FnMut(String, String) + Copy + 'static,
+) -> Element {
+    let email = member.email.clone();
+    let suspend_email = member.email.clone();
+    let manage_email = member.email.clone();
+    let is_suspended = member.status == "suspended";
+    let invitation_mail = member.invitation_mail.clone();
+    let retry_reference = invitation_mail
+        .as_ref()
+        .map(|mail| mail.reference.clone())
+        .unwrap_or_default();
+
+    let toggle_label = if is_suspended {
+        translations.translate("reactivate")
+    } else {
+        translations.translate("suspend")
+    };
+
+    rsx! {
+        div {
+            key: "{member.email}",
+            class: "krill-bg-surface-container krill-backdrop-blur-glass krill-shadow-glass",
+            class: "flex flex-col md:flex-row w-full items-start md:items-center justify-between gap-2 p-3 rounded-lg",
+            div { class: "flex flex-col",
+                span { class: "text-lg font-bold", {member.display_name.as_str()} }
+                span { class: "text-sm font-[monospacefont] break-all", {member.email.as_str()} }
+                for wallet in member.wallets.iter() {
+                    {wallet_entry(&member.email, wallet, translations, unlink_wallet)}
+                }
+                if member.has_frost_identifier {
+                    span { class: "text-xs", {translations.translate("frost_identifier_set")} }
+                }
+            }
+            div { class: "flex gap-2 items-center text-sm",
+                span { {translations.translate(&("role_".to_string() + member.role.as_str()))} }
+                span { class: "dark:text-[var(--primary-color)]",
+                    {translations.translate(&("status_".to_string() + member.status.as_str()))}
+                }
+                if let Some(mail) = invitation_mail.as_ref() {
+                    span {
+                        title: mail.last_error.clone().unwrap_or_default(),
+                        {translations.translate(&("mail_".to_string() + mail.status.as_str()))}
+                    }
+                    if mail.status == "failed" {
+                        ClearButton {
+                            info: ButtonInfo::new_enabled_and_width(&translations.translate("retry_mail"), "w-auto"),
+                            callback: move |_| retry_mail(retry_reference.clone()),
+                        }
+                    }
+                }
+                if member.status != "invited" {
+                    ClearButton {
+                        info: ButtonInfo::new_enabled_and_width(&toggle_label, "w-auto"),
+                        callback: move |_| set_suspended(suspend_email.clone(), !is_suspended),
+                    }
+                }
+                ClearButton {
+                    info: ButtonInfo::new_enabled_and_width(&translations.translate("manage_keys"), "w-auto"),
+                    callback: move |_| manage(manage_email.clone()),
+                }
+                ClearButton {
+                    info: ButtonInfo::new_enabled_and_width(&translations.translate("remove"), "w-auto"),
+                    callback: move |_| remove(email.clone()),
+                }
+            }
+        }
+    }
+}
+
+fn wallet_entry(
+    email: &str,
+    wallet: &str,
+    translations: &Translations,
+    mut unlink_wallet: impl FnMut(String, String) + Copy + 'static,
+) -> Element {
+    let email = email.to_string();
+    let address = wallet.to_string();
+
+    rsx! {
+        div { key: "{wallet}", class: "flex gap-2 items-center",
+            span { class: "text-xs font-[monospacefont] break-all", {wallet} }
+            ClearButton {
+                info: ButtonInfo::new_enabled_and_width(&translations.translate("unlink_wallet"), "w-auto"),
+                callback: move |_| unlink_wallet(email.clone(), address.clone()),
+            }
+        }
+    }
+}
@@ -12,3 +12,15 @@ pub use errors::*;
 
 mod dashboard;
 pub use dashboard::*;
+
+mod two_factor;
+pub use two_factor::*;
+
+mod members;
+pub use members::*;
+
+mod settings;
+pub use settings::*;
+
+mod authorities_pending;
+pub use authorities_pending::*;
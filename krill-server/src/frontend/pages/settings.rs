@@ -0,0 +1,633 @@
+use dioxus::prelude::*;
+use krill_common::{
+    MailApiConfig, MailApiProvider, MailProvider, OrganizationInfo, SmtpAuthMechanism, SmtpConfig,
+    SmtpSecurity,
+};
+use wasm_toolkit::FileUploadInfo;
+
+use crate::{
+    ActiveItem, ButtonInfo, ClearButton, Header, LoadingLanguageTranslation, OrgCacheOps,
+    OrgSettingsRevisionToUi, OrgSettingsUpdate, PrimaryButton, Translations, TranslationsMemInfo,
+    UploadImage, NOTIFICATION_MANAGER, SELECTED_LANGUAGE,
+};
+
+#[component]
+pub fn Settings() -> Element {
+    use_context_provider(|| Signal::new(TranslationsMemInfo::new()));
+    // The image upload component reads the translations from the context
+    let mut translations = use_context_provider(|| Signal::new(Translations::default()));
+
+    let translations_info = consume_context::<Signal<TranslationsMemInfo>>();
+
+    let mut loading_langs = use_signal(|| true);
+    // Bumped after every change so the settings and the history are fetched again
+    let mut reload = use_signal(|| 0u32);
+    let mut saving = use_signal(|| false);
+    let mut error_watcher = use_signal(|| String::default());
+    let mut notice = use_signal(|| String::default());
+
+    let mut name = use_signal(|| String::default());
+    let mut support_mail = use_signal(|| String::default());
+    let mut fqdn = use_signal(|| String::default());
+    let mut primary_color = use_signal(|| String::default());
+    let mut secondary_color = use_signal(|| String::default());
+    let mut accent_color = use_signal(|| String::default());
+    let mut background_dark = use_signal(|| String::default());
+    let mut background_light = use_signal(|| String::default());
+    let mut mail_delivery = use_signal(|| MailProvider::SMTP.to_string());
+    let mut smtp_host = use_signal(|| String::default());
+    let mut smtp_port = use_signal(|| String::default());
+    let mut smtp_security = use_signal(|| String::default());
+    let mut smtp_auth = use_signal(|| String::default());
+    let mut smtp_username = use_signal(|| String::default());
+    let mut smtp_password = use_signal(|| String::default());
+    let mut smtp_ca_bundle = use_signal(|| String::default());
+    let mut smtp_timeout = use_signal(|| String::default());
+    let mut smtp_pool_size = use_signal(|| String::default());
+    let mut mail_api_provider = use_signal(|| String::default());
+    let mut mail_api_endpoint = use_signal(|| String::default());
+    let mut mail_api_key = use_signal(|| String::default());
+    let mut mail_api_timeout = use_signal(|| String::default());
+    let mut api_key = use_signal(|| String::default());
+    let logo = use_signal(|| Vec::<FileUploadInfo>::default());
+    let favicon = use_signal(|| Vec::<FileUploadInfo>::default());
+    let mut dkim_algorithm = use_signal(|| String::from("rsa"));
+    let dkim_selector = use_signal(|| String::from("krill"));
+    let mut dkim_domain = use_signal(|| String::default());
+
+    use_effect(move || {
+        spawn(async move {
+            TranslationsMemInfo::fetch("dashboard", translations_info).await;
+
+            match Translations::get_translation("settings", SELECTED_LANGUAGE.read().code()).await {
+                Ok(fetched_translations) => {
+                    translations.set(fetched_translations);
+                    loading_langs.set(false);
+                }
+                Err(error) => {
+                    NOTIFICATION_MANAGER
+                        .send_final(wasm_toolkit::NotificationType::Failure(
+                            wasm_toolkit::WasmToolkitError::Op(error.to_string()),
+                        ))
+                        .await
+                }
+            };
+        });
+    });
+
+    let current = use_resource(move || async move {
+        reload.read();
+
+        crate::org_settings().await
+    });
+
+    let history = use_resource(move || async move {
+        reload.read();
+
+        crate::org_settings_history().await
+    });
+
+    let dkim = use_resource(move || async move {
+        reload.read();
+
+        crate::dkim_record().await
+    });
+
+    use_effect(move || {
+        if let Some(Ok(settings)) = current.read().as_ref() {
+            name.set(settings.name.clone());
+            support_mail.set(settings.support_mail.clone());
+            fqdn.set(settings.fqdn.clone());
+            primary_color.set(settings.primary_color.clone());
+            secondary_color.set(settings.secondary_color.clone());
+            accent_color.set(settings.accent_color.clone());
+            background_dark.set(settings.background_dark.clone());
+            background_light.set(settings.background_light.clone());
+            // The fields of the provider not in use start from its defaults
+            let smtp = settings.mail.smtp().cloned().unwrap_or_default();
+            let mail_api = settings.mail.api().cloned().unwrap_or_default();
+            mail_delivery.set(settings.mail.kind().to_string());
+            smtp_host.set(smtp.host.clone());
+            smtp_port.set(smtp.port.to_string());
+            smtp_security.set(smtp.security.as_str().to_string());
+            smtp_auth.set(smtp.auth.as_str().to_string());
+            smtp_username.set(smtp.username.clone());
+            smtp_password.set(String::default());
+            smtp_ca_bundle.set(smtp.ca_bundle.clone());
+            smtp_timeout.set(smtp.timeout_secs.to_string());
+            smtp_pool_size.set(smtp.pool_size.to_string());
+            mail_api_provider.set(mail_api.provider.as_str().to_string());
+            mail_api_endpoint.set(mail_api.endpoint.clone());
+            mail_api_key.set(String::default());
+            mail_api_timeout.set(mail_api.timeout_secs.to_string());
+            api_key.set(String::default());
+
+            // Signatures align with the domain of the address the emails are sent from
+            if dkim_domain.peek().is_empty() {
+                if let Some((_, domain)) = settings.support_mail.rsplit_once('@') {
+                    dkim_domain.set(domain.to_string());
+                }
+            }
+        }
+    });
+
+    let mut error_handler = move |error: ServerFnError| match error {
+        ServerFnError::ServerError {
+            message,
+            code: _,
+            details: _,
+        } => {
+            error_watcher.set(message.to_string());
+        }
+        _ => {
+            error_watcher.set(error.to_string());
+        }
+    };
+
+    let save = move |_| {
+        let update = OrgSettingsUpdate {
+            name: name.read().clone(),
+            support_mail: support_mail.read().clone(),
+            logo: logo
+                .read()
+                .first()
+                .map(|info| info.data.clone())
+                .unwrap_or_default(),
+            favicon: favicon
+                .read()
+                .first()
+                .map(|info| info.data.clone())
+                .unwrap_or_default(),
+            primary_color: primary_color.read().clone(),
+            secondary_color: secondary_color.read().clone(),
+            accent_color: accent_color.read().clone(),
+            background_dark: background_dark.read().clone(),
+            background_light: background_light.read().clone(),
+            fqdn: fqdn.read().clone(),
+            // Numbers that do not parse are sent as 0, which the validation rejects
+            mail: if mail_delivery.read().as_str() == MailProvider::API {
+                MailApiConfig {
+                    provider: MailApiProvider::parse(&mail_api_provider.read()).unwrap_or_default(),
+                    endpoint: mail_api_endpoint.read().clone(),
+                    api_key: mail_api_key.read().clone(),
+                    timeout_secs: mail_api_timeout.read().trim().parse().unwrap_or_default(),
+                }
+                .into()
+            } else {
+                SmtpConfig {
+                    host: smtp_host.read().clone(),
+                    port: smtp_port.read().trim().parse().unwrap_or_default(),
+                    security: SmtpSecurity::parse(&smtp_security.read()).unwrap_or_default(),
+                    auth: SmtpAuthMechanism::parse(&smtp_auth.read()).unwrap_or_default(),
+                    username: smtp_username.read().clone(),
+                    password: smtp_password.read().clone(),
+                    ca_bundle: smtp_ca_bundle.read().clone(),
+                    timeout_secs: smtp_timeout.read().trim().parse().unwrap_or_default(),
+                    pool_size: smtp_pool_size.read().trim().parse().unwrap_or_default(),
+                }
+                .into()
+            },
+            api_key: api_key.read().clone(),
+        };
+        error_watcher.set(String::default());
+        notice.set(String::default());
+        saving.set(true);
+
+        spawn(async move {
+            match crate::org_settings_update(update).await {
+                Ok(_) => {
+                    notice.set(translations.read().translate("settings_saved"));
+                    refresh_org_info().await;
+                    *reload.write() += 1;
+                }
+                Err(error) => error_handler(error),
+            }
+
+            saving.set(false);
+        });
+    };
+
+    let rollback = move |version: u64| {
+        error_watcher.set(String::default());
+        notice.set(String::default());
+        saving.set(true);
+
+        spawn(async move {
+            match crate::org_settings_rollback(version).await {
+                Ok(_) => {
+                    notice.set(
+                        translations
+                            .read()
+                            .translate("settings_rolled_back")
+                            .replace("{version}", &version.to_string()),
+                    );
+                    refresh_org_info().await;
+                    *reload.write() += 1;
+                }
+                Err(error) => error_handler(error),
+            }
+
+            saving.set(false);
+        });
+    };
+
+    let generate_dkim = move |_| {
+        let algorithm = dkim_algorithm.read().clone();
+        let selector = dkim_selector.read().clone();
+        let domain = dkim_domain.read().clone();
+        error_watcher.set(String::default());
+        notice.set(String::default());
+        saving.set(true);
+
+        spawn(async move {
+            match crate::dkim_generate(algorithm, selector, domain).await {
+                Ok(_) => {
+                    notice.set(translations.read().translate("dkim_generated"));
+                    *reload.write() += 1;
+                }
+                Err(error) => error_handler(error),
+            }
+
+            saving.set(false);
+        });
+    };
+
+    let remove_dkim = move |_| {
+        error_watcher.set(String::default());
+        notice.set(String::default());
+        saving.set(true);
+
+        spawn(async move {
+            match crate::dkim_remove().await {
+                Ok(_) => {
+                    notice.set(translations.read().translate("dkim_removed"));
+                    *reload.write() += 1;
+                }
+                Err(error) => error_handler(error),
+            }
+
+            saving.set(false);
+        });
+    };
+
+    if *loading_langs.read() {
+        return rsx! {
+            LoadingLanguageTranslation {}
+        };
+    }
+
+    let settings_error = match current.read().as_ref() {
+        Some(Err(error)) => Some(error.to_string()),
+        _ => None,
+    };
+    let revisions = match history.read().as_ref() {
+        Some(Ok(revisions)) => revisions.clone(),
+        _ => Vec::default(),
+    };
+    let dkim_record = match dkim.read().as_ref() {
+        Some(Ok(record)) => record.clone(),
+        _ => None,
+    };
+    let has_dkim = dkim_record.is_some();
+
+    let text_input = |label: String, mut value: Signal<String>, kind: &'static str| {
+        rsx! {
+            label { class: "flex flex-col w-full gap-1",
+                span { class: "text-sm dark:text-[var(--primary-color)]", {label} }
+                input {
+                    class: "w-full rounded-lg px-4 py-2 bg-[var(--primary-color)]",
+                    r#type: kind,
+                    value: "{value}",
+                    oninput: move |event| value.set(event.value()),
+                }
+            }
+        }
+    };
+
+    let color_input = |label: String, mut value: Signal<String>| {
+        rsx! {
+            label { class: "flex items-center justify-between w-full gap-2",
+                span { class: "text-sm", {label} }
+                input {
+                    class: "w-16 h-8 rounded-lg cursor-pointer",
+                    r#type: "color",
+                    value: "{value}",
+                    oninput: move |event| value.set(event.value()),
+                }
+            }
+        }
+    };
+
+    rsx! {
+        div { class: "h-screen w-full flex flex-col justify-start items-center krill-bg-dots ",
+            Header { active: ActiveItem::Settings,
+                div { class: "flex flex-col w-full items-center p-5 font-[subheadingfont]",
+                    div { class: "flex text-center text-3xl font-[headingfont] dark:text-[var(--primary-color)] font-black mb-5",
+                        {translations.read().translate("settings_header")}
+                    }
+
+                    if let Some(settings_error) = settings_error {
+                        div { class: "flex w-full justify-center text-lg dark:text-red-300 light:text-red-500 mb-4",
+                            {settings_error}
+                        }
+                    }
+
+                    div { class: "flex flex-col w-full lg:max-w-[90dvh] gap-3 mb-6",
+                        div { class: "flex w-full text-xl font-bold",
+                            {translations.read().translate("organization_header")}
+                        }
+                        {text_input(translations.read().translate("org_name"), name, "text")}
+                        {text_input(translations.read().translate("support_mail"), support_mail, "email")}
+                        {text_input(translations.read().translate("domain_name"), fqdn, "text")}
+
+                        UploadImage {
+                            heading: translations.read().translate("organization_logo"),
+                            dropped_files: logo,
+                            dropzone_id: "settings-logo-dropzone-file",
+                        }
+                        UploadImage {
+                            heading: translations.read().translate("organization_favicon"),
+                            dropped_files: favicon,
+                            dropzone_id: "settings-favicon-dropzone-file",
+                        }
+
+                        div { class: "flex w-full text-xl font-bold",
+                            {translations.read().translate("colors_header")}
+                        }
+                        {color_input(translations.read().translate("primary_color"), primary_color)}
+                        {color_input(translations.read().translate("secondary_color"), secondary_color)}
+                        {color_input(translations.read().translate("accent_color"), accent_color)}
+                        {color_input(translations.read().translate("background_dark"), background_dark)}
+                        {color_input(translations.read().translate("background_light"), background_light)}
+
+                        div { class: "flex w-full text-xl font-bold mt-2",
+                            {translations.read().translate("credentials_header")}
+                        }
+                        div { class: "flex w-full text-sm",
+                            {translations.read().translate("credentials_notice")}
+                        }
+                        label { class: "flex items-center justify-between w-full gap-2",
+                            span { class: "text-sm", {translations.read().translate("mail_delivery")} }
+                            select {
+                                class: "rounded-lg px-4 py-2 bg-[var(--primary-color)]",
+                                value: "{mail_delivery}",
+                                onchange: move |event| mail_delivery.set(event.value()),
+                                for delivery in [MailProvider::SMTP, MailProvider::API] {
+                                    option { value: delivery,
+                                        {translations.read().translate(&("mail_delivery_".to_string() + delivery))}
+                                    }
+                                }
+                            }
+                        }
+                        if mail_delivery.read().as_str() == MailProvider::API {
+                            label { class: "flex items-center justify-between w-full gap-2",
+                                span { class: "text-sm", {translations.read().translate("mail_api_provider")} }
+                                select {
+                                    class: "rounded-lg px-4 py-2 bg-[var(--primary-color)]",
+                                    value: "{mail_api_provider}",
+                                    onchange: move |event| {
+                                        // Follows the provider while the endpoint is a default one
+                                        let default_endpoints = MailApiProvider::ALL.map(|provider| provider.default_endpoint().to_string());
+                                        if let Some(provider) = MailApiProvider::parse(&event.value()) {
+                                            if default_endpoints.contains(&*mail_api_endpoint.read()) {
+                                                mail_api_endpoint.set(provider.default_endpoint().to_string());
+                                            }
+                                        }
+                                        mail_api_provider.set(event.value());
+                                    },
+                                    for provider in MailApiProvider::ALL {
+                                        option { value: provider.as_str(),
+                                            {translations.read().translate(&("mail_api_provider_".to_string() + provider.as_str()))}
+                                        }
+                                    }
+                                }
+                            }
+                            {text_input(translations.read().translate("mail_api_endpoint"), mail_api_endpoint, "url")}
+                            {text_input(translations.read().translate("mail_api_key"), mail_api_key, "password")}
+                            {text_input(translations.read().translate("mail_api_timeout"), mail_api_timeout, "number")}
+                        } else {
+                            {text_input(translations.read().translate("smtp_host"), smtp_host, "text")}
+                            label { class: "flex items-center justify-between w-full gap-2",
+                                span { class: "text-sm", {translations.read().translate("smtp_security")} }
+                                select {
+                                    class: "rounded-lg px-4 py-2 bg-[var(--primary-color)]",
+                                    value: "{smtp_security}",
+                                    onchange: move |event| {
+                                        // Follows the security mode while the port is a default one
+                                        let default_ports = SmtpSecurity::ALL.map(|security| security.default_port().to_string());
+                                        if let Some(security) = SmtpSecurity::parse(&event.value()) {
+                                            if smtp_port.read().is_empty() || default_ports.contains(&*smtp_port.read()) {
+                                                smtp_port.set(security.default_port().to_string());
+                                            }
+                                        }
+                                        smtp_security.set(event.value());
+                                    },
+                                    for security in SmtpSecurity::ALL {
+                                        option { value: security.as_str(),
+                                            {translations.read().translate(&("smtp_security_".to_string() + security.as_str()))}
+                                        }
+                                    }
+                                }
+                            }
+                            {text_input(translations.read().translate("smtp_port"), smtp_port, "number")}
+                            label { class: "flex items-center justify-between w-full gap-2",
+                                span { class: "text-sm", {translations.read().translate("smtp_auth")} }
+                                select {
+                                    class: "rounded-lg px-4 py-2 bg-[var(--primary-color)]",
+                                    value: "{smtp_auth}",
+                                    onchange: move |event| smtp_auth.set(event.value()),
+                                    for mechanism in SmtpAuthMechanism::ALL {
+                                        option { value: mechanism.as_str(),
+                                            {translations.read().translate(&("smtp_auth_".to_string() + mechanism.as_str()))}
+                                        }
+                                    }
+                                }
+                            }
+                            if smtp_auth.read().as_str() != SmtpAuthMechanism::None.as_str() {
+                                {text_input(translations.read().translate("smtp_username"), smtp_username, "text")}
+                                {text_input(translations.read().translate("smtp_password"), smtp_password, "password")}
+                            }
+                            label { class: "flex flex-col w-full gap-1",
+                                span { class: "text-sm dark:text-[var(--primary-color)]",
+                                    {translations.read().translate("smtp_ca_bundle")}
+                                }
+                                textarea {
+                                    class: "w-full h-24 rounded-lg px-4 py-2 bg-[var(--primary-color)] font-[monospacefont] text-xs",
+                                    placeholder: "-----BEGIN CERTIFICATE-----",
+                                    value: "{smtp_ca_bundle}",
+                                    oninput: move |event| smtp_ca_bundle.set(event.value()),
+                                }
+                            }
+                            {text_input(translations.read().translate("smtp_timeout"), smtp_timeout, "number")}
+                            {text_input(translations.read().translate("smtp_pool_size"), smtp_pool_size, "number")}
+                        }
+                        {text_input(translations.read().translate("api_key"), api_key, "password")}
+
+                        div { class: "flex w-full justify-center mt-4",
+                            if *saving.read() {
+                                span { class: "text-lg dark:text-[var(--primary-color)]",
+                                    {translations.read().translate("testing_settings")}
+                                }
+                            } else {
+                                PrimaryButton {
+                                    info: ButtonInfo::new_enabled_and_width(
+                                        &translations.read().translate("save_settings"),
+                                        "w-[70%] max-w-[400px]",
+                                    ),
+                                    callback: save,
+                                }
+                            }
+                        }
+                    }
+
+                    if !notice.read().is_empty() {
+                        div { class: "flex w-[80%] lg:max-w-[60%] text-lg mb-4 text-green-500",
+                            {notice.read().as_str()}
+                        }
+                    }
+
+                    if !error_watcher.read().is_empty() {
+                        div { class: "flex items-center justify-start flex w-[80%] lg:max-w-[60%] text-lg text-wrap flex-wrap px-4 py-2 mb-4",
+                            span { class: "flex mr-1 max-w-[20px] min-w-[15px] w-[20%] border border-[var(--primary-color)] rounded-full p-[1px]",
+                                img { src: crate::ERROR_ICON, alt: "error_icon" }
+                            }
+
+                            span { class: "flex px-0.5 font-bold font-black text-lg lg:text-xl dark:text-red-300 light:text-red-500",
+                                {error_watcher.read().as_str()}
+                            }
+                        }
+                    }
+
+                    div { class: "flex flex-col w-full lg:max-w-[90dvh] gap-3 mb-6",
+                        div { class: "flex w-full text-xl font-bold",
+                            {translations.read().translate("dkim_header")}
+                        }
+                        div { class: "flex w-full text-sm",
+                            {translations.read().translate("dkim_notice")}
+                        }
+                        if let Some(record) = dkim_record {
+                            div { class: "krill-bg-surface-container krill-backdrop-blur-glass krill-shadow-glass",
+                                class: "flex flex-col w-full gap-1 p-3 rounded-lg",
+                                span { class: "text-sm dark:text-[var(--primary-color)]",
+                                    {translations.read().translate("dkim_record_name")}
+                                }
+                                span { class: "text-sm font-[monospacefont] break-all", {record.dns_name.as_str()} }
+                                span { class: "text-sm dark:text-[var(--primary-color)]",
+                                    {translations.read().translate("dkim_record_value")}
+                                }
+                                span { class: "text-xs font-[monospacefont] break-all", {record.txt_record.as_str()} }
+                            }
+                        } else {
+                            div { class: "flex w-full justify-center text-lg",
+                                {translations.read().translate("dkim_not_set")}
+                            }
+                        }
+                        label { class: "flex items-center justify-between w-full gap-2",
+                            span { class: "text-sm", {translations.read().translate("dkim_algorithm")} }
+                            select {
+                                class: "rounded-lg px-4 py-2 bg-[var(--primary-color)]",
+                                value: "{dkim_algorithm}",
+                                onchange: move |event| dkim_algorithm.set(event.value()),
+                                option { value: "rsa", "RSA" }
+                                option { value: "ed25519", "Ed25519" }
+                            }
+                        }
+                        {text_input(translations.read().translate("dkim_selector"), dkim_selector, "text")}
+                        {text_input(translations.read().translate("dkim_domain"), dkim_domain, "text")}
+                        if !*saving.read() {
+                            div { class: "flex w-full justify-center gap-4",
+                                PrimaryButton {
+                                    info: ButtonInfo::new_enabled_and_width(
+                                        &translations.read().translate("dkim_generate"),
+                                        "w-auto",
+                                    ),
+                                    callback: generate_dkim,
+                                }
+                                if has_dkim {
+                                    ClearButton {
+                                        info: ButtonInfo::new_enabled_and_width(
+                                            &translations.read().translate("dkim_remove"),
+                                            "w-auto",
+                                        ),
+                                        callback: remove_dkim,
+                                    }
+                                }
+                            }
+                        }
+                    }
+
+                    div { class: "flex flex-col w-full lg:max-w-[90dvh] gap-2 mb-10",
+                        div { class: "flex w-full text-xl font-bold",
+                            {translations.read().translate("history_header")}
+                        }
+                        if revisions.is_empty() {
+                            div { class: "flex w-full justify-center text-lg",
+                                {translations.read().translate("no_history")}
+                            }
+                        }
+                        for revision in revisions {
+                            {revision_row(revision, &translations.read(), *saving.read(), rollback)}
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn revision_row(
+    revision: OrgSettingsRevisionToUi,
+    translations: &Translations,
+    disabled: bool,
+    mut rollback: impl FnMut(u64) + Copy + 'static,
+) -> Element {
+    let version = revision.version;
+    let replaced = translations
+        .translate("replaced_by")
+        .replace("{date}", &revision.replaced)
+        .replace("{identity}", &revision.replaced_by);
+
+    rsx! {
+        div {
+            key: "{revision.version}",
+            class: "krill-bg-surface-container krill-backdrop-blur-glass krill-shadow-glass",
+            class: "flex flex-col md:flex-row w-full items-start md:items-center justify-between gap-2 p-3 rounded-lg",
+            div { class: "flex flex-col",
+                span { class: "text-lg font-bold", "v{revision.version} · {revision.name}" }
+                span { class: "text-sm font-[monospacefont] break-all", {revision.support_mail.as_str()} }
+                span { class: "text-sm font-[monospacefont] break-all", {revision.fqdn.as_str()} }
+                span { class: "text-xs font-[monospacefont] break-all", {revision.mail_server.as_str()} }
+                span { class: "text-xs dark:text-[var(--primary-color)]", {replaced} }
+            }
+            if !disabled {
+                ClearButton {
+                    info: ButtonInfo::new_enabled_and_width(&translations.translate("rollback"), "w-auto"),
+                    callback: move |_| rollback(version),
+                }
+            }
+        }
+    }
+}
+
+/// Caches the reloaded organization information and applies the new branding to the page
+async fn refresh_org_info() {
+    let org_info = match crate::fetch_org_info().await {
+        Ok(bytes) => match bitcode::decode::<OrganizationInfo>(&bytes) {
+            Ok(org_info) => org_info,
+            Err(_) => return,
+        },
+        Err(error) => {
+            tracing::error!("Unable to fetch the organization info. Error: `{error}`");
+
+            return;
+        }
+    };
+
+    if let Err(error) = OrgCacheOps::set_org_info(&org_info) {
+        NOTIFICATION_MANAGER.send_final_error(error).await;
+
+        return;
+    }
+
+    crate::bind_org_info_to_page().await;
+}
@@ -0,0 +1,183 @@
+use dioxus::prelude::*;
+
+use crate::{
+    ButtonInfo, LoadingLanguageTranslation, PrimaryButton, TotpEnrollmentToUi, Translations,
+    TwoFactorStep, NOTIFICATION_MANAGER, SELECTED_LANGUAGE,
+};
+
+#[component]
+pub fn TwoFactor() -> Element {
+    let mut translations = use_signal(|| Translations::default());
+
+    let mut loading_langs = use_signal(|| true);
+    let mut step = use_signal(|| Option::<TwoFactorStep>::default());
+    let mut enrollment = use_signal(|| Option::<TotpEnrollmentToUi>::default());
+    let mut code = use_signal(|| String::default());
+    let mut error_watcher = use_signal(|| String::default());
+
+    use_effect(move || {
+        spawn(async move {
+            match Translations::get_translation("two-factor", SELECTED_LANGUAGE.read().code()).await
+            {
+                Ok(fetched_translations) => {
+                    translations.set(fetched_translations);
+                    loading_langs.set(false);
+                }
+                Err(error) => {
+                    NOTIFICATION_MANAGER
+                        .send_final(wasm_toolkit::NotificationType::Failure(
+                            wasm_toolkit::WasmToolkitError::Op(error.to_string()),
+                        ))
+                        .await
+                }
+            };
+        });
+    });
+
+    let mut error_handler = move |error: ServerFnError| match error {
+        ServerFnError::ServerError {
+            message,
+            code: _,
+            details: _,
+        } => {
+            error_watcher.set(message.to_string());
+        }
+        _ => {
+            error_watcher.set(error.to_string());
+        }
+    };
+
+    use_effect(move || {
+        spawn(async move {
+            match crate::totp_status().await {
+                Ok(TwoFactorStep::Done) => {
+                    navigator().push(crate::RouteUtils::DASHBOARD);
+                }
+                Ok(value) => {
+                    step.set(Some(value));
+                }
+                Err(error) => error_handler(error),
+            }
+        });
+    });
+
+    let submit_code = move |_| {
+        let current_code = code.read().trim().to_string();
+        let current_step = *step.read();
+        error_watcher.set(String::default());
+
+        spawn(async move {
+            let outcome = match current_step {
+                Some(TwoFactorStep::Enroll) => crate::totp_confirm_enrollment(current_code).await,
+                _ => crate::totp_verify(current_code).await,
+            };
+
+            match outcome {
+                Ok(()) => {
+                    navigator().push(crate::RouteUtils::DASHBOARD);
+                }
+                Err(error) => error_handler(error),
+            }
+        });
+    };
+
+    rsx! {
+        div { class: "krill-bg-dots flex flex-col w-full min-h-screen items-center justify-center p-5",
+
+            if *loading_langs.read() {
+                LoadingLanguageTranslation {}
+            } else {
+                div { class: "flex text-center text-4xl font-[headingfont] dark:text-[var(--primary-color)] font-black items-center justify-center mb-5",
+                    {translations.read().translate("two_factor_header")}
+                }
+
+                if *step.read() == Some(TwoFactorStep::Enroll) {
+                    if let Some(enrollment_inner) = enrollment.read().as_ref() {
+                        div { class: "flex flex-col w-[80%] lg:max-w-[60%] items-center justify-center font-[subheadingfont]",
+                            div { class: "flex w-full text-lg mb-2",
+                                {translations.read().translate("scan_provisioning_uri")}
+                            }
+                            div { class: "flex w-full font-[monospacefont] text-sm break-all mb-2",
+                                {enrollment_inner.provisioning_uri.as_str()}
+                            }
+                            div { class: "flex w-full text-lg mb-2",
+                                {translations.read().translate("manual_secret")}
+                            }
+                            div { class: "flex w-full font-[monospacefont] text-lg break-all mb-4 dark:text-[var(--primary-color)]",
+                                {enrollment_inner.secret.as_str()}
+                            }
+                            div { class: "flex w-full text-lg mb-2",
+                                {translations.read().translate("recovery_codes_notice")}
+                            }
+                            div { class: "grid grid-cols-2 gap-2 w-full font-[monospacefont] text-lg mb-4",
+                                for recovery_code in enrollment_inner.recovery_codes.iter() {
+                                    span { key: "{recovery_code}", {recovery_code.as_str()} }
+                                }
+                            }
+                        }
+                    } else {
+                        div { class: "flex flex-col w-full items-center justify-center font-[subheadingfont] text-lg mb-5",
+                            {translations.read().translate("enrollment_required")}
+                        }
+
+                        PrimaryButton {
+                            info: ButtonInfo::new_enabled_and_width(
+                                &translations.read().translate("set_up_authenticator"),
+                                "w-[70%] max-w-[400px]",
+                            ),
+                            callback: move |_| {
+                                error_watcher.set(String::default());
+
+                                spawn(async move {
+                                    match crate::totp_enroll().await {
+                                        Ok(value) => {
+                                            enrollment.set(Some(value));
+                                        }
+                                        Err(error) => error_handler(error),
+                                    }
+                                });
+                            },
+                        }
+                    }
+                } else if step.read().is_some() {
+                    div { class: "flex flex-col w-full items-center justify-center font-[subheadingfont] text-lg mb-5",
+                        {translations.read().translate("verification_required")}
+                    }
+                }
+
+                if *step.read() == Some(TwoFactorStep::Verify) || enrollment.read().is_some() {
+                    div { class: "flex flex-col w-full items-center justify-center",
+                        input {
+                            class: "w-[70%] max-w-[400px] font-[monospacefont] rounded-lg bg-[var(--primary-color)]
+                                text-center text-2xl px-4 py-2 mb-4",
+                            autocomplete: "one-time-code",
+                            placeholder: translations.read().translate("code_placeholder"),
+                            value: "{code}",
+                            oninput: move |event| code.set(event.value()),
+                        }
+
+                        PrimaryButton {
+                            info: ButtonInfo::new_enabled_and_width(
+                                &translations.read().translate("verify_code"),
+                                "w-[70%] max-w-[400px]",
+                            ),
+                            callback: submit_code,
+                        }
+                    }
+                }
+
+                if !error_watcher.read().is_empty() {
+                    div { class: "flex items-center justify-start font-[subheadingfont] flex w-[80%] lg:max-w-[60%] text-lg text-wrap flex-wrap px-4 py-2 mb-1 mt-1",
+                        span { class: "flex mr-1 max-w-[20px] min-w-[15px] w-[20%] border border-[var(--primary-color)] rounded-full p-[1px]",
+                            img { src: crate::ERROR_ICON, alt: "error_icon" }
+                        }
+
+                        span { class: "flex px-0.5 font-[subheadingfont] font-bold font-black text-lg lg:text-xl dark:text-red-300 light:text-red-500",
+                            {error_watcher.read().as_str()}
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
@@ -1,6 +1,10 @@
 use dioxus::prelude::*;
+use krill_common::KrillError;
 
-use crate::{Configuration, Dashboard, Errors, Home, Login, NotFound, VerifySupportMail};
+use crate::{
+    AuthoritiesPending, Configuration, Dashboard, Errors, Home, Login, Members, NotFound, Settings,
+    TwoFactor, VerifySupportMail,
+};
 
 #[derive(Clone, Routable, Debug, PartialEq)]
 pub enum Route {
@@ -10,15 +14,27 @@ pub enum Route {
     #[route("/login")]
     Login {},
 
+    #[route("/two-factor")]
+    TwoFactor {},
+
     #[route("/configuration")]
     Configuration {},
 
+    #[route("/authorities-pending")]
+    AuthoritiesPending {},
+
     #[route("/")]
     Home {},
 
     #[route("/dashboard")]
     Dashboard {},
 
+    #[route("/dashboard/members")]
+    Members {},
+
+    #[route("/dashboard/settings")]
+    Settings {},
+
     #[route("/404")]
     NotFound {},
 
@@ -32,8 +48,17 @@ impl RouteUtils {
     pub const CONFIGURATION: &str = "/configuration";
     pub const LOGIN: &str = "/login";
     pub const LOGOUT: &str = "/logout";
+    pub const TWO_FACTOR: &str = "/two-factor";
     pub const VERIFY_SUPPORT_MAIL: &str = "/verify-support-mail";
+    pub const AUTHORITIES_PENDING: &str = "/authorities-pending";
     pub const DASHBOARD: &str = "/dashboard";
+    pub const MEMBERS: &str = "/dashboard/members";
+    pub const SETTINGS: &str = "/dashboard/settings";
     pub const ERRORS: &str = "/errors";
     pub const NOT_FOUND: &str = "/404";
+
+    /// The error page only receives the error code so that no internal details end up in the URL
+    pub fn error_route(error: &KrillError) -> String {
+        Self::ERRORS.to_string() + "/" + error.code().to_string().as_str()
+    }
 }
@@ -72,39 +72,22 @@ impl Translations {
         Self::get_translation(page, &lang).await
     }
 
+    /// Tries every language of [Self::fallback_languages] until a translation file exists
     pub async fn get_translation(page: &str, target: &str) -> KrillResult<Self> {
-        let mut language = target.to_string();
-
         let host_name = WINDOW
             .read()
             .origin()
             .map_err(|error| KrillError::Transmit(error.to_string()))?;
 
-        let lang_path = Self::translation_path(page, &language);
-        let mut url = host_name.clone() + lang_path.as_str();
-
-        let mut response = Self::get_translation_text(&url).await?;
-
-        if response.status() == 404 && language != "en-US" && language.contains('-') {
-            language = language.split('-').next().unwrap_or("en-US").to_string();
-            let lang_path_default = Self::translation_path(page, &language);
-            url = host_name.clone() + lang_path_default.as_str();
+        for language in Self::fallback_languages(target) {
+            let url = host_name.clone() + Self::translation_path(page, &language).as_str();
 
-            response = Self::get_translation_text(&url).await?;
+            let response = Self::get_translation_text(&url).await?;
 
-            if response.status() == 404 && language != "en-US" && language != "en" {
-                let lang_path_default = Self::translation_path_default(page);
-                url = host_name + lang_path_default.as_str();
-
-                response = Self::get_translation_text(&url).await?;
+            if response.status() == 404 {
+                continue;
             }
-        }
 
-        if response.status() == 404 {
-            Err(KrillError::FatalUi(
-                "en-US translation not found yet it is the default".to_string(),
-            ))
-        } else {
             let body = response.text().await.map_err(|error| {
                 KrillError::Transmit(
                     "Unable to parse the body of fetching a translation. Error: `{}`".to_string()
@@ -123,8 +106,29 @@ impl Translations {
                     )
                 })?;
 
-            Ok(Self(translations))
+            return Ok(Self(translations));
         }
+
+        Err(KrillError::FatalUi(
+            "en-US translation not found yet it is the default".to_string(),
+        ))
+    }
+
+    /// The language, then its base language like `pt` for `pt-BR`, then `en-US`
+    /// which every page is translated to
+    pub fn fallback_languages(target: &str) -> Vec<String> {
+        let base = target.split('-').next().unwrap_or(target);
+
+        let mut languages = Vec::<String>::new();
+        for language in [target, base, "en-US"] {
+            let language = if language == "en" { "en-US" } else { language };
+
+            if !language.is_empty() && !languages.iter().any(|existing| existing == language) {
+                languages.push(language.to_string());
+            }
+        }
+
+        languages
     }
 
     pub async fn get_translation_text(url: &str) -> KrillResult<reqwest::Response> {
@@ -146,3 +150,40 @@ impl Translations {
         Self::translation_path(page_name, "en-US")
     }
 }
+
+#[cfg(all(test, feature = "server"))]
+mod test {
+    use super::*;
+
+    #[test]
+    fn fallback_languages() {
+        assert_eq!(
+            Translations::fallback_languages("pt-BR"),
+            vec!["pt-BR", "pt", "en-US"]
+        );
+        assert_eq!(Translations::fallback_languages("sw"), vec!["sw", "en-US"]);
+        assert_eq!(Translations::fallback_languages("en"), vec!["en-US"]);
+        assert_eq!(Translations::fallback_languages("en-US"), vec!["en-US"]);
+    }
+
+    /// Every language offered in the UI must find a translation for every page
+    #[test]
+    fn every_language_is_translated() {
+        let translations_dir =
+            std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("assets/translations");
+
+        for page in std::fs::read_dir(&translations_dir).unwrap() {
+            let page = page.unwrap().path();
+
+            for language in crate::backend::default_langs() {
+                assert!(
+                    Translations::fallback_languages(&language)
+                        .iter()
+                        .any(|language| page.join(language.to_string() + ".bcp47").is_file()),
+                    "No translation of `{}` for `{language}`",
+                    page.display()
+                );
+            }
+        }
+    }
+}
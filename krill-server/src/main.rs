@@ -11,14 +11,24 @@ fn main() {
     dioxus::launch(app);
 
     #[cfg(feature = "server")]
-    crate::init_server_statics().unwrap();
+    if crate::init_server_statics().unwrap() == InitOutcome::Exit {
+        return;
+    }
 
     #[cfg(feature = "server")]
     dioxus::serve(|| async move {
         use axum::{extract::Request, middleware::Next};
 
-        Ok(dioxus::server::router(app).layer(axum::middleware::from_fn(
-            |request: Request, next: Next| async move { check_app_state(request, next).await },
-        )))
+        crate::spawn_backup_scheduler();
+        crate::spawn_mail_worker();
+
+        // The tenant is resolved first so the app state checked is the one of the tenant
+        Ok(dioxus::server::router(app)
+            .layer(axum::middleware::from_fn(
+                |request: Request, next: Next| async move { check_app_state(request, next).await },
+            ))
+            .layer(axum::middleware::from_fn(
+                |request: Request, next: Next| async move { scope_tenant(request, next).await },
+            )))
     });
 }
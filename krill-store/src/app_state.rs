@@ -1,54 +1,65 @@
-use krill_common::{KrillError, KrillResult, ServerConfigurationState};
+use krill_common::{KrillResult, ServerConfigurationState};
 
-use crate::KrillStorage;
+use crate::{KrillStorage, Record};
 
 impl KrillStorage {
     pub const KEYSPACE_APP_STATE: &str = "AppState";
 
+    pub(crate) const APP_STATE: Record<ServerConfigurationState> =
+        Record::new(Self::KEYSPACE_APP_STATE, Self::KEYSPACE_APP_STATE);
+
     pub async fn set_app_state_login_init(&self) -> KrillResult<()> {
-        let keyspace = self.app_state_keyspace();
-
-        self.set(
-            keyspace,
-            Self::KEYSPACE_APP_STATE,
-            ServerConfigurationState::LoginInitialization,
-        )
-        .await
+        Self::APP_STATE
+            .put(self, &ServerConfigurationState::LoginInitialization)
+            .await
     }
 
     pub async fn set_app_state_initialized(&self) -> KrillResult<()> {
-        let keyspace = self.app_state_keyspace();
-
-        self.set(
-            keyspace,
-            Self::KEYSPACE_APP_STATE,
-            ServerConfigurationState::Initialized,
-        )
-        .await
+        Self::APP_STATE
+            .put(self, &ServerConfigurationState::Initialized)
+            .await
     }
 
     pub async fn get_app_state(&self) -> KrillResult<ServerConfigurationState> {
-        let keyspace = self.app_state_keyspace();
-
-        self.get_app_state_inner(keyspace).await
-    }
-
-    async fn get_app_state_inner(
-        &self,
-        keyspace: fjall::SingleWriterTxKeyspace,
-    ) -> KrillResult<ServerConfigurationState> {
-        let value: Option<Vec<u8>> = self.get(keyspace.clone(), Self::KEYSPACE_APP_STATE).await?;
-
-        if let Some(bytes) = value {
-            bitcode::decode::<ServerConfigurationState>(&bytes)
-                .or(Err(KrillError::UnableToDeserializeAppStateData))
+        if let Some(app_state) = Self::APP_STATE.get(self).await? {
+            Ok(app_state)
         } else {
             let app_state = ServerConfigurationState::default();
 
-            self.set(keyspace, Self::KEYSPACE_APP_STATE, app_state)
-                .await?;
+            Self::APP_STATE.put(self, &app_state).await?;
 
             Ok(app_state)
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::test_storages;
+
+    #[test]
+    fn app_state_transitions() {
+        smol::block_on(async {
+            for storage in test_storages("AppState").await {
+                assert_eq!(
+                    storage.get_app_state().await.unwrap(),
+                    ServerConfigurationState::default()
+                );
+
+                storage.set_app_state_login_init().await.unwrap();
+                assert_eq!(
+                    storage.get_app_state().await.unwrap(),
+                    ServerConfigurationState::LoginInitialization
+                );
+
+                storage.set_app_state_initialized().await.unwrap();
+                assert_eq!(
+                    storage.get_app_state().await.unwrap(),
+                    ServerConfigurationState::Initialized
+                );
+            }
+        })
+    }
+}
@@ -0,0 +1,555 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use bitcode::{Decode, Encode};
+use krill_common::{Holder, KrillError, KrillResult};
+use tai64::Tai64N;
+
+use crate::{KrillCipher, KrillStorage, StoredValue};
+
+/// Security relevant actions recorded in the audit log
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Encode, Decode)]
+pub enum AuditAction {
+    AdminPasscodeUsed,
+    SuperuserLinkVerified,
+    OrganizationCreated,
+    SessionIssued,
+    TotpRecoveryCodeUsed,
+    MemberInvited,
+    MemberInvitationAccepted,
+    MemberUpdated,
+    MemberSuspended,
+    MemberReactivated,
+    MemberRemoved,
+    OrgSettingsUpdated,
+    OrgSettingsRolledBack,
+    TenantCreated,
+    ConfigExported,
+    ConfigImported,
+    DkimKeyChanged,
+    MemberWalletLinked,
+    MemberWalletUnlinked,
+    MemberFrostIdentifierChanged,
+}
+
+impl AuditAction {
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::AdminPasscodeUsed => "admin_passcode_used",
+            Self::SuperuserLinkVerified => "superuser_link_verified",
+            Self::OrganizationCreated => "organization_created",
+            Self::SessionIssued => "session_issued",
+            Self::TotpRecoveryCodeUsed => "totp_recovery_code_used",
+            Self::MemberInvited => "member_invited",
+            Self::MemberInvitationAccepted => "member_invitation_accepted",
+            Self::MemberUpdated => "member_updated",
+            Self::MemberSuspended => "member_suspended",
+            Self::MemberReactivated => "member_reactivated",
+            Self::MemberRemoved => "member_removed",
+            Self::OrgSettingsUpdated => "org_settings_updated",
+            Self::OrgSettingsRolledBack => "org_settings_rolled_back",
+            Self::TenantCreated => "tenant_created",
+            Self::ConfigExported => "config_exported",
+            Self::ConfigImported => "config_imported",
+            Self::DkimKeyChanged => "dkim_key_changed",
+            Self::MemberWalletLinked => "member_wallet_linked",
+            Self::MemberWalletUnlinked => "member_wallet_unlinked",
+            Self::MemberFrostIdentifierChanged => "member_frost_identifier_changed",
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Encode, Decode)]
+pub enum AuditOutcome {
+    Success,
+    Failure(String),
+}
+
+/// One entry of the audit log. [AuditEntry::hash] covers every other field
+/// including the hash of the previous entry, so changing or removing an
+/// entry breaks the chain.
+#[derive(Debug, PartialEq, Eq, Clone, Encode, Decode)]
+pub struct AuditEntry {
+    pub sequence: u64,
+    pub timestamp: [u8; Tai64N::BYTE_SIZE],
+    /// `None` when the actor is not authenticated yet, like when the admin passcode is used
+    pub actor: Option<Holder>,
+    pub action: AuditAction,
+    pub target: String,
+    pub outcome: AuditOutcome,
+    pub previous_hash: [u8; 32],
+    pub hash: [u8; 32],
+}
+
+impl AuditEntry {
+    const HASH_CONTEXT: &str = "krill-store 2026-01-01 audit log entry";
+
+    pub fn compute_hash(&self) -> [u8; 32] {
+        let mut hasher = blake3::Hasher::new_derive_key(Self::HASH_CONTEXT);
+
+        hasher.update(&self.sequence.to_le_bytes());
+        hasher.update(&self.timestamp);
+
+        for field in [
+            bitcode::encode(&self.actor),
+            bitcode::encode(&self.action),
+            self.target.as_bytes().to_vec(),
+            bitcode::encode(&self.outcome),
+        ] {
+            // Length prefixed so the boundaries of the fields cannot be shifted
+            hasher.update(&(field.len() as u64).to_le_bytes());
+            hasher.update(&field);
+        }
+
+        hasher.update(&self.previous_hash);
+
+        *hasher.finalize().as_bytes()
+    }
+
+    /// Seconds since the UNIX epoch
+    pub fn unix_timestamp(&self) -> u64 {
+        Tai64N::from_slice(&self.timestamp)
+            .map(|timestamp| timestamp.to_system_time())
+            .unwrap_or(SystemTime::UNIX_EPOCH)
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_secs())
+            .unwrap_or_default()
+    }
+
+    pub fn to_json(&self) -> serde_json::Value {
+        let (outcome, reason) = match &self.outcome {
+            AuditOutcome::Success => ("success", None),
+            AuditOutcome::Failure(reason) => ("failure", Some(reason.as_str())),
+        };
+
+        serde_json::json!({
+            "sequence": self.sequence,
+            "timestamp": self.unix_timestamp(),
+            "actor": self.actor.as_ref().map(|actor| actor.identity()),
+            "role": self.actor.as_ref().map(|actor| actor.role().as_str()),
+            "action": self.action.as_str(),
+            "target": self.target,
+            "outcome": outcome,
+            "reason": reason,
+            "previous_hash": blake3::Hash::from(self.previous_hash).to_hex().as_str(),
+            "hash": blake3::Hash::from(self.hash).to_hex().as_str(),
+        })
+    }
+}
+
+/// Filters for [KrillStorage::audit_entries]. Entries are returned oldest first.
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
+pub struct AuditQuery {
+    pub action: Option<AuditAction>,
+    /// Matches [Holder::identity]
+    pub identity: Option<String>,
+    /// Only entries with a greater sequence
+    pub after: Option<u64>,
+    pub limit: Option<usize>,
+}
+
+impl AuditQuery {
+    fn matches(&self, entry: &AuditEntry) -> bool {
+        self.action.is_none_or(|action| action == entry.action)
+            && self.identity.as_ref().is_none_or(|identity| {
+                entry
+                    .actor
+                    .as_ref()
+                    .is_some_and(|actor| actor.identity() == identity)
+            })
+            && self.after.is_none_or(|after| entry.sequence > after)
+    }
+}
+
+/// The last entry of the chain, detects entries removed from the end
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy, Encode, Decode)]
+struct AuditHead {
+    entries: u64,
+    hash: [u8; 32],
+}
+
+impl KrillStorage {
+    pub(crate) const KEYSPACE_AUDIT_LOG: &str = "AuditLog";
+    const KEY_AUDIT_HEAD: &str = "AuditHead";
+
+    /// Appends an entry chained to the previous one. Entries cannot be changed or removed.
+    pub async fn append_audit_entry(
+        &self,
+        actor: Option<Holder>,
+        action: AuditAction,
+        target: &str,
+        outcome: AuditOutcome,
+    ) -> KrillResult<AuditEntry> {
+        let backend = self.backend();
+        let keyspace = self.audit_log_keyspace();
+        let cipher = KrillCipher::new(*self.cipher()?.expose_key());
+        let target = target.to_string();
+
+        blocking::unblock(move || {
+            let mut appended = None;
+
+            // Updates are exclusive so concurrent appends cannot fork the chain
+            backend.update(&mut |backend| {
+                let head = backend
+                    .get(keyspace, Self::KEY_AUDIT_HEAD.as_bytes())?
+                    .map(|bytes| {
+                        Self::open_audit_value::<AuditHead>(
+                            &cipher,
+                            Self::KEY_AUDIT_HEAD.as_bytes(),
+                            &bytes,
+                        )
+                    })
+                    .transpose()?
+                    .unwrap_or_default();
+
+                let mut entry = AuditEntry {
+                    sequence: head.entries,
+                    timestamp: Tai64N::now().to_bytes(),
+                    actor: actor.clone(),
+                    action,
+                    target: target.clone(),
+                    outcome: outcome.clone(),
+                    previous_hash: head.hash,
+                    hash: [0u8; 32],
+                };
+                entry.hash = entry.compute_hash();
+
+                let head = AuditHead {
+                    entries: head.entries + 1,
+                    hash: entry.hash,
+                };
+
+                let entry_key = entry.sequence.to_be_bytes();
+                let writes = vec![
+                    (
+                        keyspace,
+                        entry_key.to_vec(),
+                        Some(Self::seal_audit_value(&cipher, &entry_key, &entry)?),
+                    ),
+                    (
+                        keyspace,
+                        Self::KEY_AUDIT_HEAD.as_bytes().to_vec(),
+                        Some(Self::seal_audit_value(
+                            &cipher,
+                            Self::KEY_AUDIT_HEAD.as_bytes(),
+                            &head,
+                        )?),
+                    ),
+                ];
+
+                appended.replace(entry);
+
+                Ok(writes)
+            })?;
+
+            appended.ok_or(KrillError::Store(
+                "The audit entry was not appended".to_string(),
+            ))
+        })
+        .await
+    }
+
+    pub async fn audit_entries(&self, query: AuditQuery) -> KrillResult<Vec<AuditEntry>> {
+        let entries = self.read_audit_entries().await?;
+
+        let matching = entries
+            .into_iter()
+            .map(|(_, entry)| entry)
+            .collect::<KrillResult<Vec<_>>>()?
+            .into_iter()
+            .filter(|entry| query.matches(entry));
+
+        Ok(match query.limit {
+            Some(limit) => matching.take(limit).collect(),
+            None => matching.collect(),
+        })
+    }
+
+    /// The whole log as JSON lines, oldest first
+    pub async fn export_audit_log(&self) -> KrillResult<String> {
+        Ok(self
+            .audit_entries(AuditQuery::default())
+            .await?
+            .iter()
+            .map(|entry| entry.to_json().to_string() + "\n")
+            .collect())
+    }
+
+    /// Walks the chain and returns the number of entries.
+    /// Fails at the first entry that was changed, removed or reordered.
+    pub async fn verify_audit_log(&self) -> KrillResult<u64> {
+        let entries = self.read_audit_entries().await?;
+        let head = self.read_audit_head().await?;
+
+        let tampered = |sequence: u64, reason: &str| KrillError::AuditLogTampered {
+            sequence,
+            reason: reason.to_string(),
+        };
+
+        let mut previous_hash = [0u8; 32];
+        let mut expected = 0u64;
+
+        for (key, entry) in entries {
+            let entry = entry.map_err(|_| tampered(expected, "The entry cannot be decrypted"))?;
+
+            if entry.sequence != expected || key != expected {
+                return Err(tampered(expected, "The entry is missing"));
+            }
+
+            if entry.previous_hash != previous_hash {
+                return Err(tampered(
+                    expected,
+                    "The entry is not chained to the previous entry",
+                ));
+            }
+
+            if entry.compute_hash() != entry.hash {
+                return Err(tampered(expected, "The entry was modified"));
+            }
+
+            previous_hash = entry.hash;
+            expected += 1;
+        }
+
+        let head = head.unwrap_or_default();
+
+        if head.entries != expected || head.hash != previous_hash {
+            return Err(tampered(
+                expected,
+                "Entries were removed from the end of the log",
+            ));
+        }
+
+        Ok(expected)
+    }
+
+    pub(crate) fn audit_log_keyspace(&self) -> &'static str {
+        Self::KEYSPACE_AUDIT_LOG
+    }
+
+    /// Entries in the order of their keys, paired with the sequence of the key
+    async fn read_audit_entries(&self) -> KrillResult<Vec<(u64, KrillResult<AuditEntry>)>> {
+        let backend = self.backend();
+        let keyspace = self.audit_log_keyspace();
+        let cipher = KrillCipher::new(*self.cipher()?.expose_key());
+
+        blocking::unblock(move || {
+            backend
+                .entries(keyspace)?
+                .into_iter()
+                .filter(|(key, _)| key.as_slice() != Self::KEY_AUDIT_HEAD.as_bytes())
+                .map(|(key, value)| {
+                    let sequence = <[u8; 8]>::try_from(key.as_slice())
+                        .map(u64::from_be_bytes)
+                        .or(Err(KrillError::AuditLogTampered {
+                            sequence: 0,
+                            reason: "The log contains an unknown key".to_string(),
+                        }))?;
+
+                    Ok((
+                        sequence,
+                        Self::open_audit_value::<AuditEntry>(&cipher, &key, &value),
+                    ))
+                })
+                .collect()
+        })
+        .await
+    }
+
+    async fn read_audit_head(&self) -> KrillResult<Option<AuditHead>> {
+        let backend = self.backend();
+        let keyspace = self.audit_log_keyspace();
+        let cipher = KrillCipher::new(*self.cipher()?.expose_key());
+
+        blocking::unblock(move || {
+            backend
+                .get(keyspace, Self::KEY_AUDIT_HEAD.as_bytes())?
+                .map(|bytes| {
+                    Self::open_audit_value::<AuditHead>(
+                        &cipher,
+                        Self::KEY_AUDIT_HEAD.as_bytes(),
+                        &bytes,
+                    )
+                })
+                .transpose()
+        })
+        .await
+    }
+
+    fn seal_audit_value(
+        cipher: &KrillCipher,
+        key: &[u8],
+        value: &impl Encode,
+    ) -> KrillResult<Vec<u8>> {
+        let ciphertext = Self::seal_value(
+            cipher,
+            Self::KEYSPACE_AUDIT_LOG,
+            key,
+            &bitcode::encode(value),
+        )?;
+
+        Ok(StoredValue::wrap(Self::SCHEMA_VERSION, &ciphertext))
+    }
+
+    fn open_audit_value<T: for<'a> Decode<'a>>(
+        cipher: &KrillCipher,
+        key: &[u8],
+        bytes: &[u8],
+    ) -> KrillResult<T> {
+        let payload = Self::open_value(
+            cipher,
+            Self::KEYSPACE_AUDIT_LOG,
+            key,
+            Self::versioned_payload(bytes)?,
+        )?;
+
+        bitcode::decode::<T>(&payload).or(Err(KrillError::Store(
+            "Unable to deserialize the audit log".to_string(),
+        )))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::test_storages;
+
+    async fn append_three(storage: &KrillStorage) -> Holder {
+        let superuser = Holder::new_with_tld("superuser@example.com")
+            .unwrap()
+            .set_superuser();
+
+        storage
+            .append_audit_entry(
+                None,
+                AuditAction::AdminPasscodeUsed,
+                "AdminPasscode",
+                AuditOutcome::Failure("Passcode is invalid".to_string()),
+            )
+            .await
+            .unwrap();
+        storage
+            .append_audit_entry(
+                None,
+                AuditAction::OrganizationCreated,
+                "Krill Org",
+                AuditOutcome::Success,
+            )
+            .await
+            .unwrap();
+        storage
+            .append_audit_entry(
+                Some(superuser.clone()),
+                AuditAction::SessionIssued,
+                superuser.identity(),
+                AuditOutcome::Success,
+            )
+            .await
+            .unwrap();
+
+        superuser
+    }
+
+    fn write_raw(storage: &KrillStorage, key: &[u8], value: Option<Vec<u8>>) {
+        storage
+            .backend()
+            .commit(vec![(storage.audit_log_keyspace(), key.to_vec(), value)])
+            .unwrap();
+    }
+
+    #[test]
+    fn chain_and_query() {
+        smol::block_on(async {
+            for storage in test_storages("AuditChain").await {
+                assert_eq!(storage.verify_audit_log().await.unwrap(), 0);
+
+                let superuser = append_three(&storage).await;
+                assert_eq!(storage.verify_audit_log().await.unwrap(), 3);
+
+                let entries = storage.audit_entries(AuditQuery::default()).await.unwrap();
+                assert_eq!(entries.len(), 3);
+                assert_eq!(entries[0].previous_hash, [0u8; 32]);
+                assert_eq!(entries[2].previous_hash, entries[1].hash);
+
+                let sessions = storage
+                    .audit_entries(AuditQuery {
+                        identity: Some(superuser.identity().to_string()),
+                        ..Default::default()
+                    })
+                    .await
+                    .unwrap();
+                assert_eq!(sessions.len(), 1);
+                assert_eq!(sessions[0].action, AuditAction::SessionIssued);
+
+                let after_first = storage
+                    .audit_entries(AuditQuery {
+                        after: Some(0),
+                        limit: Some(1),
+                        ..Default::default()
+                    })
+                    .await
+                    .unwrap();
+                assert_eq!(after_first[0].action, AuditAction::OrganizationCreated);
+
+                let export = storage.export_audit_log().await.unwrap();
+                assert_eq!(export.lines().count(), 3);
+                assert!(export.contains("\"action\":\"admin_passcode_used\""));
+            }
+        })
+    }
+
+    #[test]
+    fn detects_tampering() {
+        smol::block_on(async {
+            for storage in test_storages("AuditTampering").await {
+                append_three(&storage).await;
+
+                let mut entries = storage.audit_entries(AuditQuery::default()).await.unwrap();
+                let cipher = KrillCipher::new(*storage.cipher().unwrap().expose_key());
+
+                // Rewriting an entry with a valid encryption still breaks the hash
+                let original = entries[1].clone();
+                entries[1].outcome = AuditOutcome::Failure("Rewritten".to_string());
+                let key = 1u64.to_be_bytes();
+                write_raw(
+                    &storage,
+                    &key,
+                    Some(KrillStorage::seal_audit_value(&cipher, &key, &entries[1]).unwrap()),
+                );
+                assert!(matches!(
+                    storage.verify_audit_log().await,
+                    Err(KrillError::AuditLogTampered { sequence: 1, .. })
+                ));
+
+                write_raw(
+                    &storage,
+                    &key,
+                    Some(KrillStorage::seal_audit_value(&cipher, &key, &original).unwrap()),
+                );
+                assert_eq!(storage.verify_audit_log().await.unwrap(), 3);
+
+                // Moving an entry to another key fails decryption
+                let moved = KrillStorage::seal_audit_value(&cipher, &key, &entries[2]).unwrap();
+                write_raw(&storage, &2u64.to_be_bytes(), Some(moved));
+                assert!(matches!(
+                    storage.verify_audit_log().await,
+                    Err(KrillError::AuditLogTampered { sequence: 2, .. })
+                ));
+
+                // Removing the last entry is detected by the head
+                write_raw(&storage, &2u64.to_be_bytes(), None);
+                assert!(matches!(
+                    storage.verify_audit_log().await,
+                    Err(KrillError::AuditLogTampered { sequence: 2, .. })
+                ));
+
+                // Removing an entry in the middle is detected by the sequence
+                write_raw(&storage, &0u64.to_be_bytes(), None);
+                assert!(matches!(
+                    storage.verify_audit_log().await,
+                    Err(KrillError::AuditLogTampered { sequence: 0, .. })
+                ));
+            }
+        })
+    }
+}
@@ -1,26 +1,28 @@
 use bitcode::{Decode, Encode};
-use krill_common::{AuthTokenDetails, Holder, KrillError, KrillResult, ServerConfigurationState};
+use krill_common::{AuthTokenDetails, Holder, KrillResult, ServerConfigurationState};
 
-use crate::KrillStorage;
+use crate::{KrillStorage, Record, Table, TableTransaction};
 
 pub type AuthTokenType = [u8; AuthTokenDetails::AUTH_TOKEN_LEN];
 
 impl KrillStorage {
     pub(crate) const KEYSPACE_AUTH_TOKENS: &str = "AuthTokens";
-    const KEY_SUPERUSER_AUTH_TOKENS: &str = "SuperuserAuthTokens";
+    pub(crate) const KEY_SUPERUSER_AUTH_TOKENS: &str = "SuperuserAuthTokens";
+
+    /// Keyed by [Self::auth_token_db_key]
+    const AUTH_TOKENS: Table<[u8; 32], AuthTokenDetails> = Table::new(Self::KEYSPACE_AUTH_TOKENS);
+    pub(crate) const SUPERUSER_AUTH_TOKEN: Record<SuperuserAuthToken> =
+        Record::new(Self::KEYSPACE_APP_STATE, Self::KEY_SUPERUSER_AUTH_TOKENS);
 
     pub async fn set_auth_token(
         &self,
         token: [u8; AuthTokenDetails::BYTE_32_LEN],
         details: AuthTokenDetails,
     ) -> KrillResult<[u8; AuthTokenDetails::AUTH_TOKEN_LEN]> {
-        let keyspace = self.auth_tokens_namespace();
-
         let auth_token_key = details.store_key(token);
 
-        self.set(keyspace, auth_token_key, details)
+        self.set_auth_token_with_store_key(auth_token_key, details)
             .await
-            .map(|_| auth_token_key)
     }
 
     pub async fn set_auth_token_with_store_key(
@@ -28,22 +30,35 @@ impl KrillStorage {
         auth_token_key: [u8; AuthTokenDetails::AUTH_TOKEN_LEN],
         details: AuthTokenDetails,
     ) -> KrillResult<[u8; AuthTokenDetails::AUTH_TOKEN_LEN]> {
-        let keyspace = self.auth_tokens_namespace();
-
-        self.set(keyspace, auth_token_key, details)
+        Self::AUTH_TOKENS
+            .put(self, &Self::auth_token_db_key(auth_token_key), &details)
             .await
             .map(|_| auth_token_key)
     }
 
     pub async fn remove_auth_token(&self, token: AuthTokenType) -> KrillResult<()> {
-        let keyspace = self.auth_tokens_namespace();
-        self.remove(keyspace, token).await
+        Self::AUTH_TOKENS
+            .delete(self, &Self::auth_token_db_key(token))
+            .await
     }
 
-    pub async fn remove_superuser_auth_token(&self) -> KrillResult<()> {
-        let keyspace = self.auth_tokens_namespace();
+    /// Adds the removal of every session of the holder with the email address to `tx`
+    pub(crate) async fn revoke_auth_tokens(
+        &self,
+        tx: &mut TableTransaction<'_>,
+        email: &str,
+    ) -> KrillResult<()> {
+        for (key, details) in Self::AUTH_TOKENS.scan(self).await? {
+            if details.holder().email_address().eq_ignore_ascii_case(email) {
+                tx.delete(&Self::AUTH_TOKENS, &key)?;
+            }
+        }
 
-        self.remove(keyspace, Self::KEY_SUPERUSER_AUTH_TOKENS).await
+        Ok(())
+    }
+
+    pub async fn remove_superuser_auth_token(&self) -> KrillResult<()> {
+        Self::SUPERUSER_AUTH_TOKEN.delete(self).await
     }
 
     /// Removes auth token if expired
@@ -51,17 +66,9 @@ impl KrillStorage {
         &self,
         token: AuthTokenType,
     ) -> KrillResult<Option<AuthTokenDetails>> {
-        let keyspace = self.auth_tokens_namespace();
-
-        let auth_token = self
-            .get(keyspace, token)
-            .await?
-            .map(|token_bytes| {
-                bitcode::decode::<AuthTokenDetails>(&token_bytes).or(Err(KrillError::Store(
-                    "Auth Token bytes corrupted".to_string(),
-                )))
-            })
-            .transpose()?;
+        let auth_token = Self::AUTH_TOKENS
+            .get(self, &Self::auth_token_db_key(token))
+            .await?;
 
         if let Some(auth_token_exists) = auth_token.as_ref() {
             if auth_token_exists.is_expired() {
@@ -75,9 +82,6 @@ impl KrillStorage {
     }
 
     pub async fn set_superuser_token(&self, holder: Holder) -> KrillResult<SuperuserAuthToken> {
-        let app_state_keyspace = self.app_state_keyspace();
-        let auth_tokens_keyspace = self.auth_tokens_namespace();
-
         let auth_token_details = AuthTokenDetails::new(holder);
         let auth_token: AuthTokenType =
             auth_token_details.store_key(AuthTokenDetails::generate_token());
@@ -86,36 +90,17 @@ impl KrillStorage {
             token: auth_token,
         };
 
-        self.set_many_with_keyspaces_and_encoded(vec![
-            (
-                app_state_keyspace,
-                Self::KEYSPACE_APP_STATE,
-                bitcode::encode(&ServerConfigurationState::Initialized),
-            ),
-            (
-                auth_tokens_keyspace,
-                Self::KEY_SUPERUSER_AUTH_TOKENS,
-                bitcode::encode(&token_outcome),
-            ),
-        ])
-        .await?;
+        let mut tx = self.transaction();
+        tx.put_record(&Self::APP_STATE, &ServerConfigurationState::Initialized)?
+            .put_record(&Self::SUPERUSER_AUTH_TOKEN, &token_outcome)?;
+        tx.commit().await?;
 
         Ok(token_outcome)
     }
 
     /// Removes auth token if expired
     pub async fn get_superuser_auth_token(&self) -> KrillResult<Option<SuperuserAuthToken>> {
-        let auth_tokens_keyspace = self.auth_tokens_namespace();
-
-        let auth_token = self
-            .get(auth_tokens_keyspace, Self::KEY_SUPERUSER_AUTH_TOKENS)
-            .await?
-            .map(|token_bytes| {
-                bitcode::decode::<SuperuserAuthToken>(&token_bytes).or(Err(KrillError::Store(
-                    "Auth Token bytes corrupted".to_string(),
-                )))
-            })
-            .transpose()?;
+        let auth_token = Self::SUPERUSER_AUTH_TOKEN.get(self).await?;
 
         if let Some(auth_token_exists) = auth_token.as_ref() {
             let checked = if auth_token_exists.details.is_expired() {
@@ -131,6 +116,11 @@ impl KrillStorage {
 
         Ok(None)
     }
+
+    /// Only the hash of the token is used as the key so that tokens never appear on disk
+    pub(crate) fn auth_token_db_key(token: AuthTokenType) -> [u8; 32] {
+        *blake3::hash(&token).as_bytes()
+    }
 }
 
 #[derive(Debug, Clone, Encode, Decode)]
@@ -138,3 +128,61 @@ pub struct SuperuserAuthToken {
     pub details: AuthTokenDetails,
     pub token: AuthTokenType,
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::test_storages;
+
+    #[test]
+    fn auth_tokens() {
+        smol::block_on(async {
+            for storage in test_storages("AuthTokens").await {
+                let holder = Holder::new_with_tld("member@krill.example").unwrap();
+                let details = AuthTokenDetails::new(holder.clone());
+
+                let token = storage
+                    .set_auth_token(AuthTokenDetails::generate_token(), details)
+                    .await
+                    .unwrap();
+
+                assert_eq!(
+                    storage
+                        .get_auth_token(token)
+                        .await
+                        .unwrap()
+                        .map(|details| details.holder().clone()),
+                    Some(holder)
+                );
+
+                storage.remove_auth_token(token).await.unwrap();
+                assert!(storage.get_auth_token(token).await.unwrap().is_none());
+
+                let superuser = Holder::new_with_tld("superuser@krill.example")
+                    .unwrap()
+                    .set_superuser();
+                let superuser_token = storage
+                    .set_superuser_token(superuser.clone())
+                    .await
+                    .unwrap();
+
+                assert_eq!(
+                    storage.get_app_state().await.unwrap(),
+                    ServerConfigurationState::Initialized
+                );
+                assert_eq!(
+                    storage
+                        .get_superuser_auth_token()
+                        .await
+                        .unwrap()
+                        .map(|stored| stored.token),
+                    Some(superuser_token.token)
+                );
+
+                storage.remove_superuser_auth_token().await.unwrap();
+                assert!(storage.get_superuser_auth_token().await.unwrap().is_none());
+            }
+        })
+    }
+}
@@ -0,0 +1,135 @@
+use krill_common::{AuthoritySetup, KrillResult, ServerConfigurationState};
+
+use crate::{KrillStorage, MemberStatus, Record};
+
+/// Which authorities accepted their invitation
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
+pub struct AuthorityProgress {
+    pub threshold: u16,
+    pub verified: Vec<String>,
+    pub pending: Vec<String>,
+}
+
+impl AuthorityProgress {
+    pub fn is_threshold_reached(&self) -> bool {
+        self.verified.len() >= self.threshold as usize
+    }
+}
+
+impl KrillStorage {
+    const AUTHORITY_SETUP: Record<AuthoritySetup> =
+        Record::new(Self::KEYSPACE_APP_STATE, "AuthoritySetup");
+
+    pub async fn set_authority_setup(&self, setup: &AuthoritySetup) -> KrillResult<()> {
+        Self::AUTHORITY_SETUP.put(self, setup).await
+    }
+
+    pub async fn authority_setup(&self) -> KrillResult<Option<AuthoritySetup>> {
+        Self::AUTHORITY_SETUP.get(self).await
+    }
+
+    /// Authorities are members that accepted their invitation. Without a setup,
+    /// like on servers configured before authorities existed, nothing is pending.
+    pub async fn authority_progress(&self) -> KrillResult<AuthorityProgress> {
+        let Some(setup) = self.authority_setup().await? else {
+            return Ok(AuthorityProgress::default());
+        };
+
+        let mut progress = AuthorityProgress {
+            threshold: setup.threshold(),
+            ..Default::default()
+        };
+
+        for email in setup.authorities() {
+            let verified = self
+                .get_member(email)
+                .await?
+                .is_some_and(|member| member.status == MemberStatus::Active);
+
+            if verified {
+                progress.verified.push(email.clone());
+            } else {
+                progress.pending.push(email.clone());
+            }
+        }
+
+        Ok(progress)
+    }
+
+    /// The state once the superuser is verified, [ServerConfigurationState::Initialized]
+    /// only when enough authorities accepted
+    pub async fn complete_initialization(&self) -> KrillResult<ServerConfigurationState> {
+        let state = if self.authority_progress().await?.is_threshold_reached() {
+            ServerConfigurationState::Initialized
+        } else {
+            ServerConfigurationState::AwaitingAuthorities
+        };
+
+        Self::APP_STATE.put(self, &state).await?;
+
+        Ok(state)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use krill_common::{Holder, UserRole};
+
+    use crate::test_storages;
+
+    #[test]
+    fn initialization_waits_for_authorities() {
+        smol::block_on(async {
+            for storage in test_storages("Authorities").await {
+                assert!(storage
+                    .authority_progress()
+                    .await
+                    .unwrap()
+                    .is_threshold_reached());
+
+                let setup = AuthoritySetup::new(
+                    &[
+                        "ada@krill.example".to_string(),
+                        "bob@krill.example".to_string(),
+                        "carol@krill.example".to_string(),
+                    ],
+                    2,
+                )
+                .unwrap();
+                storage.set_authority_setup(&setup).await.unwrap();
+
+                let superuser = Holder::new_with_tld("support@krill.example")
+                    .unwrap()
+                    .set_superuser();
+                let mut tokens = Vec::new();
+                for email in setup.authorities() {
+                    let (token, _) = storage
+                        .invite_member(email, "", UserRole::Admin, &superuser)
+                        .await
+                        .unwrap();
+                    tokens.push(token);
+                }
+
+                storage.accept_member_invitation(&tokens[0]).await.unwrap();
+                assert_eq!(
+                    storage.complete_initialization().await.unwrap(),
+                    ServerConfigurationState::AwaitingAuthorities
+                );
+
+                storage.accept_member_invitation(&tokens[2]).await.unwrap();
+                let progress = storage.authority_progress().await.unwrap();
+                assert_eq!(progress.pending, ["bob@krill.example"]);
+                assert_eq!(
+                    storage.complete_initialization().await.unwrap(),
+                    ServerConfigurationState::Initialized
+                );
+                assert_eq!(
+                    storage.get_app_state().await.unwrap(),
+                    ServerConfigurationState::Initialized
+                );
+            }
+        })
+    }
+}
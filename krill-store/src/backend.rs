@@ -0,0 +1,366 @@
+use std::{
+    collections::BTreeMap,
+    ops::Bound,
+    sync::{Mutex, RwLock},
+};
+
+use camino::Utf8Path;
+use fjall::{
+    KeyspaceCreateOptions, PersistMode, Readable, SingleWriterTxDatabase, SingleWriterTxKeyspace,
+};
+use krill_common::{KrillError, KrillResult};
+
+use crate::SnapshotKeyspace;
+
+/// Writes committed together, `None` removes the key
+pub type StorageWrites = Vec<(&'static str, Vec<u8>, Option<Vec<u8>>)>;
+
+/// The key range of a scan, with the keys already encoded
+pub type KeyRange = (Bound<Vec<u8>>, Bound<Vec<u8>>);
+
+/// The engine behind [crate::KrillStorage]. Backends only see the encrypted values in
+/// their envelope, every method is blocking and called from a blocking thread.
+pub trait StorageBackend: Send + Sync {
+    /// The name shown to operators, like `fjall`
+    fn name(&self) -> &'static str;
+
+    fn get(&self, keyspace: &str, key: &[u8]) -> KrillResult<Option<Vec<u8>>>;
+
+    /// The entries with keys in `range` in key order
+    fn range(&self, keyspace: &str, range: KeyRange) -> KrillResult<Vec<(Vec<u8>, Vec<u8>)>>;
+
+    /// The entries whose key starts with `prefix` in key order
+    fn prefix(&self, keyspace: &str, prefix: &[u8]) -> KrillResult<Vec<(Vec<u8>, Vec<u8>)>>;
+
+    /// The entry with the greatest key, without reading the rest of the keyspace
+    fn last(&self, keyspace: &str) -> KrillResult<Option<(Vec<u8>, Vec<u8>)>>;
+
+    /// Applies all `writes` or none of them and only returns once they are durable
+    fn commit(&self, writes: StorageWrites) -> KrillResult<()>;
+
+    /// Runs `update` and commits the writes it returns while holding the write lock
+    /// so that no other write happens between the reads and the writes
+    fn update(
+        &self,
+        update: &mut dyn FnMut(&dyn StorageBackend) -> KrillResult<StorageWrites>,
+    ) -> KrillResult<()>;
+
+    /// Every keyspace with all of its entries, read at a single point in time
+    fn snapshot(&self) -> KrillResult<Vec<SnapshotKeyspace>>;
+
+    /// Every entry of the keyspace in key order
+    fn entries(&self, keyspace: &str) -> KrillResult<Vec<(Vec<u8>, Vec<u8>)>> {
+        self.range(keyspace, (Bound::Unbounded, Bound::Unbounded))
+    }
+}
+
+/// Stores the keyspaces on disk with fjall
+pub struct FjallBackend {
+    db: SingleWriterTxDatabase,
+    keyspaces: Vec<(String, SingleWriterTxKeyspace)>,
+    write_lock: Mutex<()>,
+}
+
+impl FjallBackend {
+    /// Opens or creates the database at `path` with the keyspaces named `keyspace_names`
+    pub fn open(path: &Utf8Path, keyspace_names: &[&str]) -> KrillResult<Self> {
+        Self::with_db(
+            SingleWriterTxDatabase::builder(path).open()?,
+            keyspace_names,
+        )
+    }
+
+    /// Uses the open database `db` with the keyspaces named `keyspace_names`
+    pub fn with_db(db: SingleWriterTxDatabase, keyspace_names: &[&str]) -> KrillResult<Self> {
+        let keyspaces = keyspace_names
+            .iter()
+            .map(|name| {
+                let keyspace = db.keyspace(name, KeyspaceCreateOptions::default)?;
+
+                Ok((name.to_string(), keyspace))
+            })
+            .collect::<KrillResult<Vec<_>>>()?;
+
+        Ok(Self {
+            db,
+            keyspaces,
+            write_lock: Mutex::default(),
+        })
+    }
+
+    fn keyspace(&self, name: &str) -> KrillResult<&SingleWriterTxKeyspace> {
+        self.keyspaces
+            .iter()
+            .find(|(keyspace_name, _)| keyspace_name == name)
+            .map(|(_, keyspace)| keyspace)
+            .ok_or(KrillError::UnknownKeyspace(name.to_string()))
+    }
+
+    fn apply(&self, writes: StorageWrites) -> KrillResult<()> {
+        let keyspaces = writes
+            .iter()
+            .map(|(keyspace, _, _)| self.keyspace(keyspace).cloned())
+            .collect::<KrillResult<Vec<_>>>()?;
+
+        let mut tx = self.db.write_tx();
+
+        for (keyspace, (_, key, value)) in keyspaces.iter().zip(writes) {
+            match value {
+                Some(value) => tx.insert(keyspace, key, value),
+                None => tx.remove(keyspace, key),
+            }
+        }
+
+        tx.commit()?;
+        self.db.persist(PersistMode::SyncAll)?;
+
+        Ok(())
+    }
+}
+
+impl StorageBackend for FjallBackend {
+    fn name(&self) -> &'static str {
+        "fjall"
+    }
+
+    fn get(&self, keyspace: &str, key: &[u8]) -> KrillResult<Option<Vec<u8>>> {
+        let keyspace = self.keyspace(keyspace)?;
+
+        Ok(self
+            .db
+            .read_tx()
+            .get(keyspace, key)?
+            .map(|value| value.to_vec()))
+    }
+
+    fn range(&self, keyspace: &str, range: KeyRange) -> KrillResult<Vec<(Vec<u8>, Vec<u8>)>> {
+        let keyspace = self.keyspace(keyspace)?;
+
+        self.db
+            .read_tx()
+            .range(keyspace, range)
+            .map(|entry| {
+                entry
+                    .into_inner()
+                    .map(|(key, value)| (key.to_vec(), value.to_vec()))
+                    .map_err(KrillError::from)
+            })
+            .collect()
+    }
+
+    fn prefix(&self, keyspace: &str, prefix: &[u8]) -> KrillResult<Vec<(Vec<u8>, Vec<u8>)>> {
+        let keyspace = self.keyspace(keyspace)?;
+
+        self.db
+            .read_tx()
+            .prefix(keyspace, prefix)
+            .map(|entry| {
+                entry
+                    .into_inner()
+                    .map(|(key, value)| (key.to_vec(), value.to_vec()))
+                    .map_err(KrillError::from)
+            })
+            .collect()
+    }
+
+    fn last(&self, keyspace: &str) -> KrillResult<Option<(Vec<u8>, Vec<u8>)>> {
+        let keyspace = self.keyspace(keyspace)?;
+
+        self.db
+            .read_tx()
+            .last_key_value(keyspace)
+            .map(|entry| {
+                entry
+                    .into_inner()
+                    .map(|(key, value)| (key.to_vec(), value.to_vec()))
+                    .map_err(KrillError::from)
+            })
+            .transpose()
+    }
+
+    fn commit(&self, writes: StorageWrites) -> KrillResult<()> {
+        let _guard = self.write_lock.lock().map_err(lock_poisoned)?;
+
+        self.apply(writes)
+    }
+
+    fn update(
+        &self,
+        update: &mut dyn FnMut(&dyn StorageBackend) -> KrillResult<StorageWrites>,
+    ) -> KrillResult<()> {
+        let _guard = self.write_lock.lock().map_err(lock_poisoned)?;
+
+        let writes = update(self)?;
+
+        self.apply(writes)
+    }
+
+    fn snapshot(&self) -> KrillResult<Vec<SnapshotKeyspace>> {
+        let tx = self.db.read_tx();
+
+        self.keyspaces
+            .iter()
+            .map(|(name, keyspace)| {
+                let entries = tx
+                    .iter(keyspace)
+                    .map(|entry| {
+                        entry
+                            .into_inner()
+                            .map(|(key, value)| (key.to_vec(), value.to_vec()))
+                            .map_err(KrillError::from)
+                    })
+                    .collect::<KrillResult<Vec<_>>>()?;
+
+                Ok(SnapshotKeyspace {
+                    name: name.clone(),
+                    entries,
+                })
+            })
+            .collect()
+    }
+}
+
+fn lock_poisoned<T>(_error: T) -> KrillError {
+    KrillError::Store("The storage lock is poisoned".to_string())
+}
+
+type MemoryKeyspaces = BTreeMap<String, BTreeMap<Vec<u8>, Vec<u8>>>;
+
+/// Keeps the keyspaces in memory, for tests and servers that must not write to disk.
+/// Everything is lost when the storage is dropped.
+pub struct MemoryBackend {
+    keyspaces: RwLock<MemoryKeyspaces>,
+    write_lock: Mutex<()>,
+}
+
+impl MemoryBackend {
+    pub fn new(keyspace_names: &[&str]) -> Self {
+        let keyspaces = keyspace_names
+            .iter()
+            .map(|name| (name.to_string(), BTreeMap::default()))
+            .collect();
+
+        Self {
+            keyspaces: RwLock::new(keyspaces),
+            write_lock: Mutex::default(),
+        }
+    }
+
+    fn read<T>(
+        &self,
+        keyspace: &str,
+        read: impl FnOnce(&BTreeMap<Vec<u8>, Vec<u8>>) -> T,
+    ) -> KrillResult<T> {
+        let keyspaces = self.keyspaces.read().map_err(lock_poisoned)?;
+
+        keyspaces
+            .get(keyspace)
+            .map(read)
+            .ok_or(KrillError::UnknownKeyspace(keyspace.to_string()))
+    }
+
+    fn apply(&self, writes: StorageWrites) -> KrillResult<()> {
+        let mut keyspaces = self.keyspaces.write().map_err(lock_poisoned)?;
+
+        // Checked first so that a failed commit changes nothing
+        if let Some((keyspace, _, _)) = writes
+            .iter()
+            .find(|(keyspace, _, _)| !keyspaces.contains_key(*keyspace))
+        {
+            return Err(KrillError::UnknownKeyspace(keyspace.to_string()));
+        }
+
+        for (keyspace, key, value) in writes {
+            let Some(entries) = keyspaces.get_mut(keyspace) else {
+                continue;
+            };
+
+            match value {
+                Some(value) => entries.insert(key, value),
+                None => entries.remove(&key),
+            };
+        }
+
+        Ok(())
+    }
+
+    fn cloned<'a>(
+        entries: impl Iterator<Item = (&'a Vec<u8>, &'a Vec<u8>)>,
+    ) -> Vec<(Vec<u8>, Vec<u8>)> {
+        entries
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect()
+    }
+}
+
+impl StorageBackend for MemoryBackend {
+    fn name(&self) -> &'static str {
+        "memory"
+    }
+
+    fn get(&self, keyspace: &str, key: &[u8]) -> KrillResult<Option<Vec<u8>>> {
+        self.read(keyspace, |entries| entries.get(key).cloned())
+    }
+
+    fn range(&self, keyspace: &str, range: KeyRange) -> KrillResult<Vec<(Vec<u8>, Vec<u8>)>> {
+        // Unlike fjall a BTreeMap panics on inverted ranges
+        let is_empty = match (&range.0, &range.1) {
+            (Bound::Included(start), Bound::Included(end)) => start > end,
+            (Bound::Included(start) | Bound::Excluded(start), Bound::Excluded(end))
+            | (Bound::Excluded(start), Bound::Included(end)) => start >= end,
+            _ => false,
+        };
+
+        self.read(keyspace, |entries| match is_empty {
+            true => Vec::default(),
+            false => Self::cloned(entries.range(range)),
+        })
+    }
+
+    fn prefix(&self, keyspace: &str, prefix: &[u8]) -> KrillResult<Vec<(Vec<u8>, Vec<u8>)>> {
+        self.read(keyspace, |entries| {
+            Self::cloned(
+                entries
+                    .range(prefix.to_vec()..)
+                    .take_while(|(key, _)| key.starts_with(prefix)),
+            )
+        })
+    }
+
+    fn last(&self, keyspace: &str) -> KrillResult<Option<(Vec<u8>, Vec<u8>)>> {
+        self.read(keyspace, |entries| {
+            entries
+                .last_key_value()
+                .map(|(key, value)| (key.clone(), value.clone()))
+        })
+    }
+
+    fn commit(&self, writes: StorageWrites) -> KrillResult<()> {
+        let _guard = self.write_lock.lock().map_err(lock_poisoned)?;
+
+        self.apply(writes)
+    }
+
+    fn update(
+        &self,
+        update: &mut dyn FnMut(&dyn StorageBackend) -> KrillResult<StorageWrites>,
+    ) -> KrillResult<()> {
+        let _guard = self.write_lock.lock().map_err(lock_poisoned)?;
+
+        let writes = update(self)?;
+
+        self.apply(writes)
+    }
+
+    fn snapshot(&self) -> KrillResult<Vec<SnapshotKeyspace>> {
+        let keyspaces = self.keyspaces.read().map_err(lock_poisoned)?;
+
+        Ok(keyspaces
+            .iter()
+            .map(|(name, entries)| SnapshotKeyspace {
+                name: name.clone(),
+                entries: Self::cloned(entries.iter()),
+            })
+            .collect())
+    }
+}
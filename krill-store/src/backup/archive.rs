@@ -0,0 +1,232 @@
+use core::fmt;
+
+use bitcode::{Decode, Encode};
+use krill_common::{KrillError, KrillResult};
+use tai64::Tai64N;
+use zeroize::Zeroizing;
+
+use crate::{BackupKeyShare, KrillCipher, Shamir, Snapshot};
+
+/// The key backups are encrypted with. It is kept in the encrypted storage
+/// so scheduled backups can run, and handed to the authorities as Shamir shares
+/// so the backups can be restored when the server is lost.
+pub struct BackupKey(KrillCipher);
+
+impl BackupKey {
+    pub const KEY_LEN: usize = KrillCipher::KEY_LEN;
+    pub const ID_LEN: usize = 8;
+
+    const ID_CONTEXT: &str = "krill-store 2026-01-01 backup key identifier";
+
+    pub fn new(key: [u8; Self::KEY_LEN]) -> Self {
+        Self(KrillCipher::new(key))
+    }
+
+    pub fn generate() -> Self {
+        Self(KrillCipher::generate())
+    }
+
+    pub fn expose(&self) -> &[u8; Self::KEY_LEN] {
+        self.0.expose_key()
+    }
+
+    /// Identifies the key without revealing it
+    pub fn id(&self) -> [u8; Self::ID_LEN] {
+        let mut id = [0u8; Self::ID_LEN];
+        id.copy_from_slice(&blake3::derive_key(Self::ID_CONTEXT, self.expose())[..Self::ID_LEN]);
+
+        id
+    }
+
+    /// Splits the key so that any `threshold` of the `shares` reconstruct it
+    pub fn split(&self, threshold: u8, shares: u8) -> KrillResult<Vec<BackupKeyShare>> {
+        let key_id = self.id();
+
+        Ok(Shamir::split(self.expose(), threshold, shares)?
+            .into_iter()
+            .zip(1u8..)
+            .map(|(value, index)| BackupKeyShare {
+                threshold,
+                index,
+                key_id,
+                value,
+            })
+            .collect())
+    }
+
+    pub fn combine(shares: &[BackupKeyShare]) -> KrillResult<Self> {
+        let first = shares.first().ok_or(KrillError::InvalidBackupKeyShares(
+            "No shares were provided".to_string(),
+        ))?;
+
+        let mut indexes = shares.iter().map(|share| share.index).collect::<Vec<_>>();
+        indexes.sort_unstable();
+        indexes.dedup();
+
+        if indexes.len() != shares.len() {
+            return Err(KrillError::InvalidBackupKeyShares(
+                "The same share was provided more than once".to_string(),
+            ));
+        }
+
+        if shares
+            .iter()
+            .any(|share| share.key_id != first.key_id || share.threshold != first.threshold)
+        {
+            return Err(KrillError::InvalidBackupKeyShares(
+                "The shares belong to different backup keys".to_string(),
+            ));
+        }
+
+        if shares.len() < first.threshold as usize {
+            return Err(KrillError::InvalidBackupKeyShares(format!(
+                "{} of {} required shares were provided",
+                shares.len(),
+                first.threshold
+            )));
+        }
+
+        let points = shares
+            .iter()
+            .take(first.threshold as usize)
+            .map(|share| (share.index, &*share.value))
+            .collect::<Vec<_>>();
+
+        let key = Self::new(*Shamir::combine(&points));
+
+        if key.id() != first.key_id {
+            return Err(KrillError::InvalidBackupKeyShares(
+                "The shares do not reconstruct the backup key".to_string(),
+            ));
+        }
+
+        Ok(key)
+    }
+}
+
+impl fmt::Debug for BackupKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "BackupKey(Redacted)")
+    }
+}
+
+/// Readable without the backup key so destinations can be listed and verified
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Encode, Decode)]
+pub struct BackupHeader {
+    pub format_version: u16,
+    pub created: [u8; Tai64N::BYTE_SIZE],
+    pub key_id: [u8; BackupKey::ID_LEN],
+    /// The blake3 hash of the snapshot sources
+    pub digest: [u8; 32],
+}
+
+/// `MAGIC || header length (u32 little endian) || header || ciphertext`.
+/// The header is the additional data of the ciphertext so it cannot be altered.
+pub struct BackupArchive;
+
+impl BackupArchive {
+    pub const MAGIC: [u8; 8] = *b"KRILLBAK";
+    pub const FORMAT_VERSION: u16 = 1;
+    pub const FILE_PREFIX: &str = "krill-backup-";
+    pub const FILE_EXTENSION: &str = ".kbak";
+
+    pub fn seal(snapshot: &Snapshot, key: &BackupKey) -> KrillResult<Vec<u8>> {
+        let header = bitcode::encode(&BackupHeader {
+            format_version: Self::FORMAT_VERSION,
+            created: snapshot.created,
+            key_id: key.id(),
+            digest: snapshot.digest(),
+        });
+
+        let plaintext = Zeroizing::new(bitcode::encode(snapshot));
+        let ciphertext = key.0.encrypt(&plaintext, &header)?;
+
+        let mut outcome =
+            Vec::with_capacity(Self::MAGIC.len() + 4 + header.len() + ciphertext.len());
+        outcome.extend_from_slice(&Self::MAGIC);
+        outcome.extend_from_slice(&(header.len() as u32).to_le_bytes());
+        outcome.extend_from_slice(&header);
+        outcome.extend_from_slice(&ciphertext);
+
+        Ok(outcome)
+    }
+
+    pub fn header(bytes: &[u8]) -> KrillResult<BackupHeader> {
+        Self::split(bytes).map(|(header, _, _)| header)
+    }
+
+    /// Decrypts the archive and verifies the snapshot against the digest in the header
+    pub fn open(bytes: &[u8], key: &BackupKey) -> KrillResult<Snapshot> {
+        let (header, header_bytes, ciphertext) = Self::split(bytes)?;
+
+        if header.key_id != key.id() {
+            return Err(KrillError::InvalidBackupKey);
+        }
+
+        let plaintext =
+            key.0
+                .decrypt(ciphertext, header_bytes)
+                .or(Err(KrillError::BackupIntegrity(
+                    "The backup could not be decrypted".to_string(),
+                )))?;
+
+        let snapshot = bitcode::decode::<Snapshot>(&plaintext).or(Err(
+            KrillError::BackupIntegrity("The snapshot is corrupted".to_string()),
+        ))?;
+
+        if snapshot.created != header.created || snapshot.digest() != header.digest {
+            return Err(KrillError::BackupIntegrity(
+                "The snapshot does not match the backup header".to_string(),
+            ));
+        }
+
+        Ok(snapshot)
+    }
+
+    /// File names sort in the order the backups were created
+    pub fn file_name(header: &BackupHeader) -> String {
+        let created = header
+            .created
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect::<String>();
+
+        Self::FILE_PREFIX.to_string() + created.as_str() + Self::FILE_EXTENSION
+    }
+
+    pub fn is_backup_file(name: &str) -> bool {
+        name.starts_with(Self::FILE_PREFIX) && name.ends_with(Self::FILE_EXTENSION)
+    }
+
+    fn split(bytes: &[u8]) -> KrillResult<(BackupHeader, &[u8], &[u8])> {
+        let corrupted =
+            || KrillError::BackupIntegrity("The backup header is corrupted".to_string());
+
+        let rest = bytes
+            .strip_prefix(Self::MAGIC.as_slice())
+            .ok_or_else(corrupted)?;
+
+        if rest.len() < 4 {
+            return Err(corrupted());
+        }
+
+        let (header_len, rest) = rest.split_at(4);
+        let header_len = u32::from_le_bytes(header_len.try_into().or(Err(corrupted()))?) as usize;
+
+        if rest.len() < header_len {
+            return Err(corrupted());
+        }
+
+        let (header_bytes, ciphertext) = rest.split_at(header_len);
+        let header = bitcode::decode::<BackupHeader>(header_bytes).or(Err(corrupted()))?;
+
+        if header.format_version != Self::FORMAT_VERSION {
+            return Err(KrillError::BackupIntegrity(format!(
+                "Unsupported backup format version `{}`",
+                header.format_version
+            )));
+        }
+
+        Ok((header, header_bytes, ciphertext))
+    }
+}
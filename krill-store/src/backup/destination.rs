@@ -0,0 +1,421 @@
+use std::{
+    io::Write,
+    process::{Command, Stdio},
+};
+
+use bitcode::{Decode, Encode};
+use camino::Utf8PathBuf;
+use krill_common::{KrillError, KrillResult, RandomChars};
+use url::Url;
+
+use crate::BackupArchive;
+
+/// Where backups are written
+#[derive(Debug, PartialEq, Eq, Clone, Encode, Decode)]
+pub enum BackupDestination {
+    /// A directory on the server, usually a mounted volume
+    Local { dir: String },
+    /// Uses the `sftp` command of the system in batch mode so the host key
+    /// must already be trusted by the user running the server
+    Sftp {
+        host: String,
+        port: u16,
+        username: String,
+        dir: String,
+        identity_file: Option<String>,
+    },
+    /// `PUT`, `GET` and `DELETE` on `{url}/{file name}`. A `GET` on `url`
+    /// returns the JSON array of the file names.
+    Http {
+        url: String,
+        bearer_token: Option<String>,
+    },
+}
+
+impl BackupDestination {
+    const SFTP_DEFAULT_PORT: u16 = 22;
+
+    /// Parses `file:///path`, `sftp://user@host:port/path?identity=/path/to/key`
+    /// or an `https://` URL. The bearer token of HTTP destinations is set separately.
+    pub fn parse(uri: &str, bearer_token: Option<String>) -> KrillResult<Self> {
+        let invalid = |reason: &str| KrillError::InvalidBackupDestination(reason.to_string());
+
+        let url = Url::parse(uri.trim()).or(Err(invalid("The destination is not a valid URI")))?;
+
+        match url.scheme() {
+            "file" => {
+                let path = url
+                    .to_file_path()
+                    .or(Err(invalid("The directory must be an absolute path")))?;
+                let dir = Utf8PathBuf::from_path_buf(path)
+                    .or(Err(KrillError::HomeDirPathNotUtf8))?
+                    .to_string();
+
+                Ok(Self::Local { dir })
+            }
+            "sftp" => {
+                let decode = |value: &str| {
+                    percent_encoding::percent_decode_str(value)
+                        .decode_utf8_lossy()
+                        .to_string()
+                };
+
+                let host = url
+                    .host_str()
+                    .ok_or(invalid("The SFTP host is missing"))?
+                    .to_string();
+
+                if url.username().is_empty() {
+                    return Err(invalid("The SFTP username is missing"));
+                }
+
+                if url.password().is_some() {
+                    return Err(invalid(
+                        "SFTP passwords are not supported, use an identity file",
+                    ));
+                }
+
+                let identity_file = url
+                    .query_pairs()
+                    .find(|(name, _)| name == "identity")
+                    .map(|(_, value)| value.to_string());
+
+                // The directory is quoted in the batch commands of `sftp`
+                let dir = decode(url.path());
+                if dir.contains(['"', '\n', '\r']) {
+                    return Err(invalid(
+                        "The SFTP directory cannot contain quotes or line breaks",
+                    ));
+                }
+
+                Ok(Self::Sftp {
+                    host,
+                    port: url.port().unwrap_or(Self::SFTP_DEFAULT_PORT),
+                    username: decode(url.username()),
+                    dir,
+                    identity_file,
+                })
+            }
+            "https" => Ok(Self::Http {
+                url: uri.trim().trim_end_matches('/').to_string(),
+                bearer_token,
+            }),
+            "http"
+                if url.host_str() == Some("localhost") || url.host_str() == Some("127.0.0.1") =>
+            {
+                Ok(Self::Http {
+                    url: uri.trim().trim_end_matches('/').to_string(),
+                    bearer_token,
+                })
+            }
+            _ => Err(invalid(
+                "Only `file`, `sftp` and `https` destinations are supported",
+            )),
+        }
+    }
+
+    pub async fn put(&self, name: &str, bytes: Vec<u8>) -> KrillResult<()> {
+        match self {
+            Self::Local { dir } => {
+                let dir = Utf8PathBuf::from(dir);
+                let name = name.to_string();
+
+                blocking::unblock(move || {
+                    std::fs::create_dir_all(&dir)?;
+
+                    // Written under a temporary name first so a partial backup is never listed
+                    let temporary = dir.join(name.clone() + ".partial");
+                    let mut file = std::fs::File::create(&temporary)?;
+                    file.write_all(&bytes)?;
+                    file.sync_all()?;
+
+                    std::fs::rename(&temporary, dir.join(name))?;
+
+                    Ok(())
+                })
+                .await
+            }
+            Self::Sftp { dir, .. } => {
+                let local = Self::temporary_file();
+                let remote = Self::remote_path(dir, name);
+                let destination = self.clone();
+
+                blocking::unblock(move || {
+                    std::fs::write(&local, &bytes)?;
+
+                    let outcome = destination.sftp(&[
+                        format!("put \"{local}\" \"{remote}.partial\""),
+                        format!("rename \"{remote}.partial\" \"{remote}\""),
+                    ]);
+
+                    std::fs::remove_file(&local).ok();
+
+                    outcome.map(|_| ())
+                })
+                .await
+            }
+            Self::Http { .. } => self
+                .http(reqwest::Method::PUT, Some(name))?
+                .body(bytes)
+                .send()
+                .await
+                .and_then(|response| response.error_for_status())
+                .map(|_| ())
+                .map_err(Self::http_error),
+        }
+    }
+
+    pub async fn get(&self, name: &str) -> KrillResult<Vec<u8>> {
+        match self {
+            Self::Local { dir } => {
+                let path = Utf8PathBuf::from(dir).join(name);
+
+                blocking::unblock(move || Ok(std::fs::read(path)?)).await
+            }
+            Self::Sftp { dir, .. } => {
+                let local = Self::temporary_file();
+                let remote = Self::remote_path(dir, name);
+                let destination = self.clone();
+
+                blocking::unblock(move || {
+                    destination.sftp(&[format!("get \"{remote}\" \"{local}\"")])?;
+
+                    let bytes = std::fs::read(&local);
+                    std::fs::remove_file(&local).ok();
+
+                    Ok(bytes?)
+                })
+                .await
+            }
+            Self::Http { .. } => self
+                .http(reqwest::Method::GET, Some(name))?
+                .send()
+                .await
+                .and_then(|response| response.error_for_status())
+                .map_err(Self::http_error)?
+                .bytes()
+                .await
+                .map(|bytes| bytes.to_vec())
+                .map_err(Self::http_error),
+        }
+    }
+
+    pub async fn delete(&self, name: &str) -> KrillResult<()> {
+        match self {
+            Self::Local { dir } => {
+                let path = Utf8PathBuf::from(dir).join(name);
+
+                blocking::unblock(move || Ok(std::fs::remove_file(path)?)).await
+            }
+            Self::Sftp { dir, .. } => {
+                let remote = Self::remote_path(dir, name);
+                let destination = self.clone();
+
+                blocking::unblock(move || {
+                    destination.sftp(&[format!("rm \"{remote}\"")]).map(|_| ())
+                })
+                .await
+            }
+            Self::Http { .. } => self
+                .http(reqwest::Method::DELETE, Some(name))?
+                .send()
+                .await
+                .and_then(|response| response.error_for_status())
+                .map(|_| ())
+                .map_err(Self::http_error),
+        }
+    }
+
+    /// The backups at the destination, oldest first
+    pub async fn list(&self) -> KrillResult<Vec<String>> {
+        let names = match self {
+            Self::Local { dir } => {
+                let dir = Utf8PathBuf::from(dir);
+
+                blocking::unblock(move || match std::fs::read_dir(&dir) {
+                    Ok(entries) => entries
+                        .map(|entry| Ok(entry?.file_name().to_string_lossy().to_string()))
+                        .collect::<KrillResult<Vec<String>>>(),
+                    Err(error) if error.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+                    Err(error) => Err(error.into()),
+                })
+                .await?
+            }
+            Self::Sftp { dir, .. } => {
+                let destination = self.clone();
+                let dir = dir.clone();
+
+                blocking::unblock(move || destination.sftp(&[format!("ls -1 \"{dir}\"")]))
+                    .await?
+                    .lines()
+                    .filter_map(|line| line.trim().rsplit('/').next().map(|name| name.to_string()))
+                    .collect()
+            }
+            Self::Http { .. } => {
+                let bytes = self
+                    .http(reqwest::Method::GET, None)?
+                    .send()
+                    .await
+                    .and_then(|response| response.error_for_status())
+                    .map_err(Self::http_error)?
+                    .bytes()
+                    .await
+                    .map_err(Self::http_error)?;
+
+                serde_json::from_slice::<Vec<String>>(&bytes).or(Err(
+                    KrillError::BackupDestination(
+                        "The destination did not return a list of backups".to_string(),
+                    ),
+                ))?
+            }
+        };
+
+        let mut names = names
+            .into_iter()
+            .filter(|name| BackupArchive::is_backup_file(name))
+            .collect::<Vec<_>>();
+        names.sort();
+
+        Ok(names)
+    }
+
+    /// Deletes the oldest backups so that at most `keep` remain. Returns the deleted backups.
+    pub async fn enforce_retention(&self, keep: usize) -> KrillResult<Vec<String>> {
+        let names = self.list().await?;
+        let excess = names.len().saturating_sub(keep.max(1));
+
+        let mut deleted = Vec::new();
+
+        for name in names.into_iter().take(excess) {
+            self.delete(&name).await?;
+
+            deleted.push(name);
+        }
+
+        Ok(deleted)
+    }
+
+    /// Runs the commands and returns the standard output
+    fn sftp(&self, commands: &[String]) -> KrillResult<String> {
+        let Self::Sftp {
+            host,
+            port,
+            username,
+            identity_file,
+            ..
+        } = self
+        else {
+            return Err(KrillError::InvalidBackupDestination(
+                "Not an SFTP destination".to_string(),
+            ));
+        };
+
+        let mut command = Command::new("sftp");
+        command
+            .arg("-b")
+            .arg("-")
+            .arg("-P")
+            .arg(port.to_string())
+            .arg("-o")
+            .arg("BatchMode=yes");
+
+        if let Some(identity_file) = identity_file {
+            command.arg("-i").arg(identity_file);
+        }
+
+        let mut child = command
+            .arg(format!("{username}@{host}"))
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|error| KrillError::BackupDestination(error.to_string()))?;
+
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(commands.join("\n").as_bytes())?;
+        }
+
+        let output = child
+            .wait_with_output()
+            .map_err(|error| KrillError::BackupDestination(error.to_string()))?;
+
+        if !output.status.success() {
+            return Err(KrillError::BackupDestination(
+                String::from_utf8_lossy(&output.stderr).trim().to_string(),
+            ));
+        }
+
+        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    }
+
+    fn http(
+        &self,
+        method: reqwest::Method,
+        name: Option<&str>,
+    ) -> KrillResult<reqwest::RequestBuilder> {
+        let Self::Http { url, bearer_token } = self else {
+            return Err(KrillError::InvalidBackupDestination(
+                "Not an HTTP destination".to_string(),
+            ));
+        };
+
+        let url = match name {
+            Some(name) => format!("{url}/{name}"),
+            None => url.to_string(),
+        };
+
+        let request = reqwest::Client::new().request(method, url);
+
+        Ok(match bearer_token {
+            Some(token) => request.bearer_auth(token),
+            None => request,
+        })
+    }
+
+    fn http_error(error: reqwest::Error) -> KrillError {
+        KrillError::BackupDestination(error.without_url().to_string())
+    }
+
+    fn remote_path(dir: &str, name: &str) -> String {
+        dir.trim_end_matches('/').to_string() + "/" + name
+    }
+
+    fn temporary_file() -> Utf8PathBuf {
+        let mut path = Utf8PathBuf::from_path_buf(std::env::temp_dir())
+            .unwrap_or_else(|_| Utf8PathBuf::from("/tmp"));
+        path.push(format!(
+            "krill-backup-{}.partial",
+            RandomChars::<16>::generate().as_string().as_str()
+        ));
+
+        path
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn sftp_destination() {
+        assert_eq!(
+            BackupDestination::parse(
+                "sftp://krill%2Bbackup@backup.example:2222/srv/krill%20backups?identity=/keys/id",
+                None
+            )
+            .unwrap(),
+            BackupDestination::Sftp {
+                host: "backup.example".to_string(),
+                port: 2222,
+                username: "krill+backup".to_string(),
+                dir: "/srv/krill backups".to_string(),
+                identity_file: Some("/keys/id".to_string()),
+            }
+        );
+
+        assert!(matches!(
+            BackupDestination::parse("sftp://krill@backup.example/srv/%22krill%22", None),
+            Err(KrillError::InvalidBackupDestination(_))
+        ));
+    }
+}
@@ -0,0 +1,173 @@
+use core::fmt;
+
+use krill_common::{KrillError, KrillResult, RandomBytes};
+use zeroize::Zeroizing;
+
+use crate::BackupKey;
+
+/// A Shamir share of a [BackupKey] given to one of the authorities.
+/// Any `threshold` shares reconstruct the key.
+#[derive(PartialEq, Eq, Clone)]
+pub struct BackupKeyShare {
+    pub threshold: u8,
+    /// The x coordinate, never zero
+    pub index: u8,
+    /// Detects shares of different keys being combined
+    pub key_id: [u8; BackupKey::ID_LEN],
+    pub value: Zeroizing<[u8; BackupKey::KEY_LEN]>,
+}
+
+impl BackupKeyShare {
+    pub const PREFIX: &str = "krill-share-";
+
+    const ENCODED_LEN: usize = 2 + BackupKey::ID_LEN + BackupKey::KEY_LEN;
+
+    /// `krill-share-` followed by the base58 encoded share
+    pub fn encode(&self) -> Zeroizing<String> {
+        let mut bytes = Zeroizing::new(Vec::with_capacity(Self::ENCODED_LEN));
+        bytes.push(self.threshold);
+        bytes.push(self.index);
+        bytes.extend_from_slice(&self.key_id);
+        bytes.extend_from_slice(self.value.as_ref());
+
+        Zeroizing::new(
+            Self::PREFIX.to_string() + bs58::encode(bytes.as_slice()).into_string().as_str(),
+        )
+    }
+
+    pub fn decode(share: &str) -> KrillResult<Self> {
+        let invalid = || KrillError::InvalidBackupKeyShares("The share is not valid".to_string());
+
+        let bytes = Zeroizing::new(
+            bs58::decode(
+                share
+                    .trim()
+                    .strip_prefix(Self::PREFIX)
+                    .ok_or_else(invalid)?,
+            )
+            .into_vec()
+            .or(Err(invalid()))?,
+        );
+
+        if bytes.len() != Self::ENCODED_LEN || bytes[0] == 0 || bytes[1] == 0 {
+            return Err(invalid());
+        }
+
+        let mut key_id = [0u8; BackupKey::ID_LEN];
+        key_id.copy_from_slice(&bytes[2..2 + BackupKey::ID_LEN]);
+
+        let mut value = Zeroizing::new([0u8; BackupKey::KEY_LEN]);
+        value.copy_from_slice(&bytes[2 + BackupKey::ID_LEN..]);
+
+        Ok(Self {
+            threshold: bytes[0],
+            index: bytes[1],
+            key_id,
+            value,
+        })
+    }
+}
+
+impl fmt::Debug for BackupKeyShare {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BackupKeyShare")
+            .field("threshold", &self.threshold)
+            .field("index", &self.index)
+            .field("value", &"Redacted")
+            .finish()
+    }
+}
+
+/// Shamir secret sharing over GF(2^8) with the AES polynomial, applied to each byte of the key
+pub(crate) struct Shamir;
+
+impl Shamir {
+    pub(crate) fn split(
+        secret: &[u8; BackupKey::KEY_LEN],
+        threshold: u8,
+        shares: u8,
+    ) -> KrillResult<Vec<Zeroizing<[u8; BackupKey::KEY_LEN]>>> {
+        if threshold == 0 || threshold > shares {
+            return Err(KrillError::InvalidBackupKeyShares(
+                "The threshold must be between one and the number of shares".to_string(),
+            ));
+        }
+
+        let mut outcome = (0..shares)
+            .map(|_| Zeroizing::new([0u8; BackupKey::KEY_LEN]))
+            .collect::<Vec<_>>();
+
+        for (position, secret_byte) in secret.iter().enumerate() {
+            // The constant term is the secret, the other coefficients are random
+            let mut coefficients = Zeroizing::new(vec![*secret_byte]);
+            coefficients.extend_from_slice(
+                &RandomBytes::<256>::generate().expose()[..threshold as usize - 1],
+            );
+
+            for (share_position, share) in outcome.iter_mut().enumerate() {
+                share[position] = Self::evaluate(&coefficients, share_position as u8 + 1);
+            }
+        }
+
+        Ok(outcome)
+    }
+
+    pub(crate) fn combine(
+        shares: &[(u8, &[u8; BackupKey::KEY_LEN])],
+    ) -> Zeroizing<[u8; BackupKey::KEY_LEN]> {
+        let mut secret = Zeroizing::new([0u8; BackupKey::KEY_LEN]);
+
+        for (position, secret_byte) in secret.iter_mut().enumerate() {
+            // Lagrange interpolation at x = 0
+            *secret_byte = shares.iter().fold(0u8, |accumulator, (x_i, value)| {
+                let basis = shares
+                    .iter()
+                    .filter(|(x_j, _)| x_j != x_i)
+                    .fold(1u8, |basis, (x_j, _)| {
+                        Self::mul(basis, Self::div(*x_j, x_j ^ x_i))
+                    });
+
+                accumulator ^ Self::mul(value[position], basis)
+            });
+        }
+
+        secret
+    }
+
+    fn evaluate(coefficients: &[u8], x: u8) -> u8 {
+        coefficients.iter().rev().fold(0u8, |outcome, coefficient| {
+            Self::mul(outcome, x) ^ coefficient
+        })
+    }
+
+    fn mul(mut a: u8, mut b: u8) -> u8 {
+        let mut outcome = 0u8;
+
+        while b != 0 {
+            if b & 1 == 1 {
+                outcome ^= a;
+            }
+
+            let carry = a & 0x80;
+            a <<= 1;
+            if carry != 0 {
+                a ^= 0x1b;
+            }
+
+            b >>= 1;
+        }
+
+        outcome
+    }
+
+    /// `b` is never zero since share indexes are distinct
+    fn div(a: u8, b: u8) -> u8 {
+        // b^254 is the inverse of b
+        let mut inverse = 1u8;
+        for _ in 0..254 {
+            inverse = Self::mul(inverse, b);
+        }
+
+        Self::mul(a, inverse)
+    }
+}
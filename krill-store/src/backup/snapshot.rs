@@ -0,0 +1,175 @@
+use bitcode::{Decode, Encode};
+use camino::{Utf8Path, Utf8PathBuf};
+use fjall::{KeyspaceCreateOptions, PersistMode, SingleWriterTxDatabase};
+use krill_common::{KrillError, KrillResult};
+use krill_frost::StoreKeyspace;
+use tai64::Tai64N;
+
+use crate::{FjallBackend, KrillStorage, StorageBackend};
+
+#[derive(Debug, PartialEq, Eq, Clone, Encode, Decode)]
+pub struct SnapshotKeyspace {
+    pub name: String,
+    pub entries: Vec<(Vec<u8>, Vec<u8>)>,
+}
+
+/// All keyspaces of one database, named after its directory
+#[derive(Debug, PartialEq, Eq, Clone, Encode, Decode)]
+pub struct SnapshotSource {
+    pub name: String,
+    pub keyspaces: Vec<SnapshotKeyspace>,
+}
+
+impl SnapshotSource {
+    /// All keyspaces are read at the same point in time so the snapshot is consistent
+    pub fn capture(name: &str, backend: &dyn StorageBackend) -> KrillResult<Self> {
+        Ok(Self {
+            name: name.to_string(),
+            keyspaces: backend.snapshot()?,
+        })
+    }
+
+    /// Captures a database that is not open, like the FROST store. The database is locked
+    /// until the capture is done so it fails when another process has the database open.
+    pub fn capture_path(name: &str, path: &Utf8Path, keyspace_names: &[&str]) -> KrillResult<Self> {
+        let db = SingleWriterTxDatabase::builder(path)
+            .open()
+            .map_err(|error| match error {
+                fjall::Error::Locked => KrillError::BackupSourceInUse(path.to_string()),
+                error => error.into(),
+            })?;
+
+        Self::capture(name, &FjallBackend::with_db(db, keyspace_names)?)
+    }
+
+    /// Writes the snapshot into a new database at `path`
+    pub fn restore(&self, path: &Utf8Path) -> KrillResult<()> {
+        let db = SingleWriterTxDatabase::builder(path).open()?;
+        let mut tx = db.write_tx();
+
+        for snapshot_keyspace in &self.keyspaces {
+            let keyspace = db.keyspace(&snapshot_keyspace.name, KeyspaceCreateOptions::default)?;
+
+            for (key, value) in &snapshot_keyspace.entries {
+                tx.insert(&keyspace, key.as_slice(), value.as_slice());
+            }
+        }
+
+        tx.commit()?;
+        db.persist(PersistMode::SyncAll)?;
+
+        Ok(())
+    }
+
+    /// The FROST store in the data directory, if the server has one
+    pub async fn frost_store(data_dir: &Utf8Path) -> KrillResult<Option<Self>> {
+        let path = data_dir.join(StoreKeyspace::STORE_DIR);
+
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        blocking::unblock(move || {
+            let keyspaces = StoreKeyspace::ALL;
+            let keyspace_names = keyspaces
+                .iter()
+                .map(|keyspace| keyspace.to_str())
+                .collect::<Vec<_>>();
+
+            Self::capture_path(StoreKeyspace::STORE_DIR, &path, &keyspace_names).map(Some)
+        })
+        .await
+    }
+
+    fn keyspace_names(&self) -> Vec<&str> {
+        self.keyspaces
+            .iter()
+            .map(|keyspace| keyspace.name.as_str())
+            .collect()
+    }
+}
+
+/// A consistent copy of every database of the Krill state
+#[derive(Debug, PartialEq, Eq, Clone, Encode, Decode)]
+pub struct Snapshot {
+    pub created: [u8; Tai64N::BYTE_SIZE],
+    pub sources: Vec<SnapshotSource>,
+}
+
+impl Snapshot {
+    pub fn new(sources: Vec<SnapshotSource>) -> Self {
+        Self {
+            created: Tai64N::now().to_bytes(),
+            sources,
+        }
+    }
+
+    pub fn digest(&self) -> [u8; 32] {
+        *blake3::hash(&bitcode::encode(&self.sources)).as_bytes()
+    }
+
+    /// Restores every source into a directory of the same name under `root` and verifies
+    /// the restored databases against the snapshot. The directories must not exist or be empty.
+    pub fn restore(&self, root: &Utf8Path) -> KrillResult<()> {
+        let paths = self
+            .sources
+            .iter()
+            .map(|source| {
+                let path = root.join(&source.name);
+
+                if Self::is_non_empty_dir(&path)? {
+                    return Err(KrillError::BackupRestoreTargetNotEmpty(path.to_string()));
+                }
+
+                Ok(path)
+            })
+            .collect::<KrillResult<Vec<Utf8PathBuf>>>()?;
+
+        for (source, path) in self.sources.iter().zip(paths.iter()) {
+            source.restore(path)?;
+        }
+
+        let restored = self
+            .sources
+            .iter()
+            .zip(paths.iter())
+            .map(|(source, path)| {
+                SnapshotSource::capture_path(&source.name, path, &source.keyspace_names())
+            })
+            .collect::<KrillResult<Vec<_>>>()?;
+
+        let restored = Self {
+            created: self.created,
+            sources: restored,
+        };
+
+        if restored.digest() != self.digest() {
+            return Err(KrillError::BackupIntegrity(
+                "The restored data does not match the backup".to_string(),
+            ));
+        }
+
+        Ok(())
+    }
+
+    fn is_non_empty_dir(path: &Utf8Path) -> KrillResult<bool> {
+        match std::fs::read_dir(path) {
+            Ok(mut entries) => Ok(entries.next().is_some()),
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => Ok(false),
+            Err(error) => Err(KrillError::Io(error.kind())),
+        }
+    }
+}
+
+impl KrillStorage {
+    /// The name of the directory of the application storage
+    pub const STORAGE_DIR: &str = "KrillAppStorage";
+
+    /// Captures every keyspace including the key ring, the values stay encrypted with the storage key
+    pub async fn snapshot_source(&self) -> KrillResult<SnapshotSource> {
+        let backend = self.backend();
+
+        blocking::unblock(move || SnapshotSource::capture(Self::STORAGE_DIR, backend.as_ref()))
+            .await
+    }
+}
@@ -0,0 +1,80 @@
+use chacha20poly1305::{
+    aead::{Aead, Payload},
+    KeyInit, XChaCha20Poly1305, XNonce,
+};
+use core::fmt;
+use krill_common::{KrillError, KrillResult, RandomBytes};
+use zeroize::Zeroizing;
+
+/// Encrypts the values of the storage with XChaCha20-Poly1305.
+/// The ciphertext is prefixed by the random 24 byte nonce.
+pub struct KrillCipher(Zeroizing<[u8; KrillCipher::KEY_LEN]>);
+
+impl KrillCipher {
+    pub const KEY_LEN: usize = 32;
+    pub const NONCE_LEN: usize = 24;
+
+    pub fn new(key: [u8; Self::KEY_LEN]) -> Self {
+        Self(Zeroizing::new(key))
+    }
+
+    pub fn generate() -> Self {
+        Self(RandomBytes::<{ Self::KEY_LEN }>::generate().take())
+    }
+
+    pub(crate) fn expose_key(&self) -> &[u8; Self::KEY_LEN] {
+        &self.0
+    }
+
+    /// `aad` binds the ciphertext to its location, usually the keyspace and key
+    pub fn encrypt(&self, plaintext: &[u8], aad: &[u8]) -> KrillResult<Vec<u8>> {
+        let cipher = XChaCha20Poly1305::new_from_slice(self.0.as_ref())
+            .or(Err(KrillError::EncryptionFailed))?;
+
+        let nonce = RandomBytes::<{ Self::NONCE_LEN }>::generate();
+
+        let ciphertext = cipher
+            .encrypt(
+                XNonce::from_slice(nonce.expose()),
+                Payload {
+                    msg: plaintext,
+                    aad,
+                },
+            )
+            .or(Err(KrillError::EncryptionFailed))?;
+
+        let mut outcome = Vec::with_capacity(Self::NONCE_LEN + ciphertext.len());
+        outcome.extend_from_slice(nonce.expose());
+        outcome.extend_from_slice(&ciphertext);
+
+        Ok(outcome)
+    }
+
+    pub fn decrypt(&self, ciphertext: &[u8], aad: &[u8]) -> KrillResult<Zeroizing<Vec<u8>>> {
+        if ciphertext.len() < Self::NONCE_LEN {
+            return Err(KrillError::DecryptionFailed);
+        }
+
+        let cipher = XChaCha20Poly1305::new_from_slice(self.0.as_ref())
+            .or(Err(KrillError::DecryptionFailed))?;
+
+        let (nonce, ciphertext) = ciphertext.split_at(Self::NONCE_LEN);
+
+        cipher
+            .decrypt(
+                XNonce::from_slice(nonce),
+                Payload {
+                    msg: ciphertext,
+                    aad,
+                },
+            )
+            .map(Zeroizing::new)
+            .or(Err(KrillError::DecryptionFailed))
+    }
+}
+
+impl fmt::Debug for KrillCipher {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "KrillCipher(Redacted)")
+    }
+}
@@ -0,0 +1,664 @@
+use base64ct::{Base64, Encoding};
+use bitcode::{Decode, Encode};
+use countries_iso3166::BC47LanguageInfo;
+use krill_common::{
+    AuthoritySetup, BrandColors, Holder, KrillError, KrillResult, MailProvider, OrganizationInfo,
+    TokenCategoryPreset, FAVICON_DEFAULT, LOGO_DEFAULT,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{KeyDerivation, KeyRing, KrillCipher, KrillStorage, OrgSettings, UnlockSecret};
+
+/// The configuration of an organization as a JSON file that can be diffed and
+/// imported on another server. The mail provider settings and the RPC API key are left out
+/// unless they are encrypted with a passphrase.
+///
+/// ```json
+/// {
+///   "format": "krill-config",
+///   "version": 1,
+///   "organization": {
+///     "name": "Example",
+///     "support_mail": "support@example.com",
+///     "fqdn": "krill.example.com",
+///     "language": "en-US",
+///     "logo": "<base64>",
+///     "favicon": "<base64>",
+///     "colors": {
+///       "primary": "#FF6600",
+///       "secondary": "#1A1A1A",
+///       "accent": "#FFD700",
+///       "background_dark": "#0F0F0F",
+///       "background_light": "#FAFAFA"
+///     }
+///   },
+///   "authorities": { "emails": ["authority@example.com"], "threshold": 1 },
+///   "token_categories": [
+///     {
+///       "name": "Stablecoin",
+///       "description": "Redeemable one to one for USD",
+///       "decimals": 6,
+///       "token_2022": true,
+///       "transferable": true
+///     }
+///   ],
+///   "secrets": null
+/// }
+/// ```
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct OrgConfig {
+    /// Always [OrgConfig::FORMAT]
+    pub format: String,
+    pub version: u16,
+    pub organization: OrgConfigOrganization,
+    /// Only imported into a server without an organization
+    #[serde(default)]
+    pub authorities: Option<OrgConfigAuthorities>,
+    #[serde(default)]
+    pub token_categories: Vec<OrgConfigTokenCategory>,
+    #[serde(default)]
+    pub secrets: Option<OrgConfigSecrets>,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct OrgConfigOrganization {
+    pub name: String,
+    pub support_mail: String,
+    pub fqdn: String,
+    /// A BCP 47 language code
+    pub language: String,
+    /// Base64, empty for the default logo
+    #[serde(default)]
+    pub logo: String,
+    /// Base64, empty for the default favicon
+    #[serde(default)]
+    pub favicon: String,
+    pub colors: OrgConfigColors,
+}
+
+/// Hex values like `#FF6600`
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct OrgConfigColors {
+    pub primary: String,
+    pub secondary: String,
+    pub accent: String,
+    pub background_dark: String,
+    pub background_light: String,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct OrgConfigAuthorities {
+    pub emails: Vec<String>,
+    pub threshold: u16,
+}
+
+/// See [TokenCategoryPreset]
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct OrgConfigTokenCategory {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    pub decimals: u8,
+    pub token_2022: bool,
+    pub transferable: bool,
+}
+
+impl From<TokenCategoryPreset> for OrgConfigTokenCategory {
+    fn from(preset: TokenCategoryPreset) -> Self {
+        Self {
+            name: preset.name,
+            description: preset.description,
+            decimals: preset.decimals,
+            token_2022: preset.token_2022,
+            transferable: preset.transferable,
+        }
+    }
+}
+
+impl From<&OrgConfigTokenCategory> for TokenCategoryPreset {
+    fn from(category: &OrgConfigTokenCategory) -> Self {
+        Self {
+            name: category.name.trim().to_string(),
+            description: category.description.trim().to_string(),
+            decimals: category.decimals,
+            token_2022: category.token_2022,
+            transferable: category.transferable,
+        }
+    }
+}
+
+/// The secrets encrypted with a key wrapped by a key derived from the passphrase
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct OrgConfigSecrets {
+    /// Base64 of the [KeyRing]
+    pub key_ring: String,
+    /// Base64
+    pub ciphertext: String,
+}
+
+#[derive(Encode, Decode)]
+struct ConfigSecrets {
+    mail: MailProvider,
+    api_key: String,
+}
+
+impl OrgConfig {
+    pub const FORMAT: &str = "krill-config";
+    pub const VERSION: u16 = 1;
+    /// Passphrases encrypting the secrets are as long as the storage passphrase
+    pub const MIN_PASSPHRASE_LEN: usize = 12;
+
+    const SECRETS_AAD: &[u8] = b"KrillConfigSecrets";
+
+    /// Parses and validates the configuration
+    pub fn from_json(json: &str) -> KrillResult<Self> {
+        let config = serde_json::from_str::<Self>(json)
+            .map_err(|error| KrillError::InvalidConfigFile(error.to_string()))?;
+
+        config.validate()?;
+
+        Ok(config)
+    }
+
+    /// Pretty printed with a stable field order so exports can be diffed
+    pub fn to_json(&self) -> KrillResult<String> {
+        serde_json::to_string_pretty(self)
+            .map_err(|error| KrillError::InvalidConfigFile(error.to_string()))
+    }
+
+    pub fn validate(&self) -> KrillResult<()> {
+        let invalid = |reason: String| Err(KrillError::InvalidConfigFile(reason));
+
+        if self.format != Self::FORMAT {
+            return invalid(format!("The format must be `{}`", Self::FORMAT));
+        }
+
+        if self.version != Self::VERSION {
+            return invalid(format!(
+                "Version `{}` is not supported, expected `{}`",
+                self.version,
+                Self::VERSION
+            ));
+        }
+
+        let organization = &self.organization;
+
+        if organization.name.trim().len() < 2 {
+            return invalid("The organization name is too short".to_string());
+        }
+
+        if Holder::new_with_tld(&organization.support_mail).is_err() {
+            return invalid(format!(
+                "`{}` is not a valid support email address",
+                organization.support_mail
+            ));
+        }
+
+        if organization.fqdn.trim().is_empty() || organization.fqdn.contains(['/', ' ', '@']) {
+            return invalid(format!(
+                "`{}` is not a valid domain name",
+                organization.fqdn
+            ));
+        }
+
+        if BC47LanguageInfo::from(organization.language.as_str())
+            == BC47LanguageInfo::UnsupportedLanguage
+        {
+            return invalid(format!(
+                "`{}` is not a supported language",
+                organization.language
+            ));
+        }
+
+        let colors = &organization.colors;
+        for color in [
+            &colors.primary,
+            &colors.secondary,
+            &colors.accent,
+            &colors.background_dark,
+            &colors.background_light,
+        ] {
+            if !BrandColors::is_hex_color(color) {
+                return invalid(format!("`{color}` is not a hex color like `#FF6600`"));
+            }
+        }
+
+        Self::decode_image(&organization.logo)?;
+        Self::decode_image(&organization.favicon)?;
+
+        if let Some(authorities) = self.authorities.as_ref() {
+            AuthoritySetup::new(&authorities.emails, authorities.threshold)?;
+        }
+
+        TokenCategoryPreset::validate_all(&self.token_category_presets())?;
+
+        Ok(())
+    }
+
+    /// The settings the configuration results in. Secrets are decrypted with
+    /// `passphrase`, configurations without secrets keep the `current` ones.
+    pub fn settings(
+        &self,
+        current: Option<&OrgSettings>,
+        passphrase: Option<&UnlockSecret>,
+    ) -> KrillResult<OrgSettings> {
+        let secrets = match (self.secrets.as_ref(), passphrase) {
+            (Some(secrets), Some(passphrase)) => Self::decrypt_secrets(secrets, passphrase)?,
+            (Some(_), None) => {
+                return Err(KrillError::InvalidConfigFile(
+                    "The passphrase of the secrets is required".to_string(),
+                ))
+            }
+            (None, _) => match current {
+                Some(current) => ConfigSecrets {
+                    mail: current.mail.clone(),
+                    api_key: current.api_key.clone(),
+                },
+                None => {
+                    return Err(KrillError::InvalidConfigFile(
+                        "Creating the organization requires the secrets".to_string(),
+                    ))
+                }
+            },
+        };
+
+        let organization = &self.organization;
+        let colors = &organization.colors;
+
+        let mut org_info = OrganizationInfo {
+            name: organization.name.trim().to_string(),
+            support_mail: organization.support_mail.trim().to_string(),
+            logo: match Self::decode_image(&organization.logo)? {
+                Some(logo) => logo,
+                None => LOGO_DEFAULT.to_vec(),
+            },
+            favicon: match Self::decode_image(&organization.favicon)? {
+                Some(favicon) => favicon,
+                None => FAVICON_DEFAULT.to_vec(),
+            },
+            ..Default::default()
+        };
+
+        let mut brand_colors = BrandColors::new();
+        brand_colors
+            .set_primary(&colors.primary)
+            .set_secondary(&colors.secondary)
+            .set_accent(&colors.accent)
+            .set_background_dark(&colors.background_dark)
+            .set_background_light(&colors.background_light);
+        org_info.color_scheme.set_brand_colors(brand_colors);
+
+        Ok(OrgSettings {
+            org_info,
+            mail: secrets.mail,
+            api_key: secrets.api_key,
+            fqdn: organization.fqdn.trim().to_string(),
+        })
+    }
+
+    pub fn token_category_presets(&self) -> Vec<TokenCategoryPreset> {
+        self.token_categories.iter().map(Into::into).collect()
+    }
+
+    /// Rejects passphrases that are too short to protect the secrets
+    pub fn secrets_passphrase(passphrase: &str) -> KrillResult<UnlockSecret> {
+        if passphrase.chars().count() < Self::MIN_PASSPHRASE_LEN {
+            return Err(KrillError::InvalidConfigFile(format!(
+                "The passphrase must have at least {} characters",
+                Self::MIN_PASSPHRASE_LEN
+            )));
+        }
+
+        Ok(UnlockSecret::passphrase(passphrase))
+    }
+
+    fn encrypt_secrets(
+        settings: &OrgSettings,
+        passphrase: &UnlockSecret,
+    ) -> KrillResult<OrgConfigSecrets> {
+        let cipher = KrillCipher::generate();
+        let key_ring = KeyRing::wrap(passphrase, KeyDerivation::ARGON2ID_DEFAULT, &cipher)?;
+
+        let ciphertext = cipher.encrypt(
+            &bitcode::encode(&ConfigSecrets {
+                mail: settings.mail.clone(),
+                api_key: settings.api_key.clone(),
+            }),
+            Self::SECRETS_AAD,
+        )?;
+
+        Ok(OrgConfigSecrets {
+            key_ring: Base64::encode_string(&bitcode::encode(&key_ring)),
+            ciphertext: Base64::encode_string(&ciphertext),
+        })
+    }
+
+    fn decrypt_secrets(
+        secrets: &OrgConfigSecrets,
+        passphrase: &UnlockSecret,
+    ) -> KrillResult<ConfigSecrets> {
+        let invalid = || KrillError::InvalidConfigFile("The secrets are corrupted".to_string());
+
+        let key_ring = Base64::decode_vec(&secrets.key_ring)
+            .ok()
+            .and_then(|bytes| bitcode::decode::<KeyRing>(&bytes).ok())
+            .ok_or_else(invalid)?;
+        let ciphertext = Base64::decode_vec(&secrets.ciphertext).or(Err(invalid()))?;
+
+        let plaintext = key_ring
+            .unwrap(passphrase)?
+            .decrypt(&ciphertext, Self::SECRETS_AAD)
+            .or(Err(KrillError::InvalidUnlockSecret))?;
+
+        bitcode::decode(&plaintext).or(Err(invalid()))
+    }
+
+    fn decode_image(base64: &str) -> KrillResult<Option<Vec<u8>>> {
+        if base64.trim().is_empty() {
+            return Ok(None);
+        }
+
+        Base64::decode_vec(base64.trim())
+            .map(Some)
+            .or(Err(KrillError::InvalidConfigFile(
+                "Images must be Base64 encoded".to_string(),
+            )))
+    }
+}
+
+impl KrillStorage {
+    /// The configuration of the organization, with its secrets encrypted by `passphrase` if any
+    pub async fn export_config(&self, passphrase: Option<&UnlockSecret>) -> KrillResult<OrgConfig> {
+        let settings = self.org_settings().await?;
+        let org_info = &settings.org_info;
+        let colors = &org_info.color_scheme;
+
+        Ok(OrgConfig {
+            format: OrgConfig::FORMAT.to_string(),
+            version: OrgConfig::VERSION,
+            organization: OrgConfigOrganization {
+                name: org_info.name.clone(),
+                support_mail: org_info.support_mail.clone(),
+                fqdn: settings.fqdn.clone(),
+                language: self.get_server_language().await?.code().to_string(),
+                logo: Base64::encode_string(&org_info.logo),
+                favicon: Base64::encode_string(&org_info.favicon),
+                colors: OrgConfigColors {
+                    primary: colors.primary_color().to_string(),
+                    secondary: colors.secondary_color().to_string(),
+                    accent: colors.accent_color().to_string(),
+                    background_dark: colors.background_dark().to_string(),
+                    background_light: colors.background_light().to_string(),
+                },
+            },
+            authorities: self
+                .authority_setup()
+                .await?
+                .map(|setup| OrgConfigAuthorities {
+                    emails: setup.authorities().to_vec(),
+                    threshold: setup.threshold(),
+                }),
+            token_categories: self
+                .token_category_presets()
+                .await?
+                .into_iter()
+                .map(Into::into)
+                .collect(),
+            secrets: passphrase
+                .map(|passphrase| OrgConfig::encrypt_secrets(&settings, passphrase))
+                .transpose()?,
+        })
+    }
+
+    /// Creates the organization from the configuration, or replaces the settings of
+    /// the existing organization keeping the previous ones in the history. The
+    /// authorities of an existing organization already accepted their invitation
+    /// so they are only imported with a new organization.
+    pub async fn import_config(
+        &self,
+        config: &OrgConfig,
+        passphrase: Option<&UnlockSecret>,
+        actor: &Holder,
+    ) -> KrillResult<OrgSettings> {
+        config.validate()?;
+
+        let current = match Self::ORG_INFO.get(self).await? {
+            Some(_) => Some(self.org_settings().await?),
+            None => None,
+        };
+
+        let settings = config.settings(current.as_ref(), passphrase)?;
+
+        if current.is_some() {
+            self.update_org_settings(&settings, actor).await?;
+        } else {
+            self.set_all_org_details(
+                settings.org_info.clone(),
+                &settings.mail,
+                &settings.api_key,
+                &settings.fqdn,
+            )
+            .await?;
+
+            if let Some(authorities) = config.authorities.as_ref() {
+                self.set_authority_setup(&AuthoritySetup::new(
+                    &authorities.emails,
+                    authorities.threshold,
+                )?)
+                .await?;
+            }
+
+            self.set_app_state_login_init().await?;
+        }
+
+        self.set_server_language(&config.organization.language)
+            .await?;
+        self.set_token_category_presets(&config.token_category_presets())
+            .await?;
+
+        Ok(settings)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use krill_common::{MailApiConfig, MailApiProvider, ServerConfigurationState};
+
+    use crate::test_storages;
+
+    fn org_settings() -> OrgSettings {
+        let org_info = OrganizationInfo {
+            name: "Forex Bureau".to_string(),
+            support_mail: "support@bureau.example.com".to_string(),
+            ..Default::default()
+        };
+
+        OrgSettings {
+            org_info,
+            mail: MailApiConfig::new(MailApiProvider::Sendgrid, "sendgrid-api-key").into(),
+            api_key: "rpc-api-key".to_string(),
+            fqdn: "bureau.example.com".to_string(),
+        }
+    }
+
+    #[test]
+    fn config_round_trip() {
+        smol::block_on(async {
+            let mut storages = test_storages("ConfigExport").await;
+            let (source, target) = (storages.next().unwrap(), storages.next().unwrap());
+
+            let settings = org_settings();
+            source
+                .set_all_org_details(
+                    settings.org_info.clone(),
+                    &settings.mail,
+                    &settings.api_key,
+                    &settings.fqdn,
+                )
+                .await
+                .unwrap();
+            source
+                .set_authority_setup(
+                    &AuthoritySetup::new(&["authority@bureau.example.com".to_string()], 1).unwrap(),
+                )
+                .await
+                .unwrap();
+            source.set_server_language("fr").await.unwrap();
+            let presets = vec![
+                TokenCategoryPreset {
+                    name: "Stablecoin".to_string(),
+                    description: "Redeemable one to one for USD".to_string(),
+                    decimals: 6,
+                    token_2022: true,
+                    transferable: true,
+                },
+                TokenCategoryPreset {
+                    name: "Attestation".to_string(),
+                    decimals: 0,
+                    token_2022: true,
+                    ..Default::default()
+                },
+            ];
+            source.set_token_category_presets(&presets).await.unwrap();
+
+            let passphrase = UnlockSecret::secret([6u8; 32]);
+
+            let public = source.export_config(None).await.unwrap();
+            assert!(public.secrets.is_none());
+            assert!(!public.to_json().unwrap().contains("password"));
+            assert_eq!(
+                source.export_config(None).await.unwrap().to_json().unwrap(),
+                public.to_json().unwrap()
+            );
+
+            let json = source
+                .export_config(Some(&passphrase))
+                .await
+                .unwrap()
+                .to_json()
+                .unwrap();
+            assert!(!json.contains("password"));
+            assert!(!json.contains("rpc-api-key"));
+
+            let config = OrgConfig::from_json(&json).unwrap();
+            let superuser = Holder::new_with_tld("support@bureau.example.com")
+                .unwrap()
+                .set_superuser();
+
+            assert_eq!(
+                target.import_config(&public, None, &superuser).await.err(),
+                Some(KrillError::InvalidConfigFile(
+                    "Creating the organization requires the secrets".to_string()
+                ))
+            );
+            assert_eq!(
+                target
+                    .import_config(&config, Some(&UnlockSecret::secret([7u8; 32])), &superuser)
+                    .await
+                    .err(),
+                Some(KrillError::InvalidUnlockSecret)
+            );
+
+            assert_eq!(
+                target
+                    .import_config(&config, Some(&passphrase), &superuser)
+                    .await
+                    .unwrap(),
+                settings
+            );
+            assert_eq!(target.org_settings().await.unwrap(), settings);
+            assert_eq!(
+                target.authority_setup().await.unwrap(),
+                source.authority_setup().await.unwrap()
+            );
+            assert_eq!(
+                target.get_server_language().await.unwrap().code(),
+                source.get_server_language().await.unwrap().code()
+            );
+            assert_eq!(target.token_category_presets().await.unwrap(), presets);
+            assert_eq!(
+                target.get_app_state().await.unwrap(),
+                ServerConfigurationState::LoginInitialization
+            );
+
+            // Importing into an existing organization keeps its secrets and history
+            let mut renamed = public.clone();
+            renamed.organization.name = "Forex Bureau Ltd".to_string();
+            let updated = target
+                .import_config(&renamed, None, &superuser)
+                .await
+                .unwrap();
+            assert_eq!(updated.org_info.name, "Forex Bureau Ltd");
+            assert_eq!(target.token_category_presets().await.unwrap(), presets);
+            assert_eq!(updated.mail, settings.mail);
+            assert_eq!(
+                target.org_settings_history().await.unwrap()[0].settings,
+                settings
+            );
+        })
+    }
+
+    #[test]
+    fn config_schema_validation() {
+        let valid = r##"{
+            "format": "krill-config",
+            "version": 1,
+            "organization": {
+                "name": "Forex Bureau",
+                "support_mail": "support@bureau.example.com",
+                "fqdn": "bureau.example.com",
+                "language": "en-US",
+                "colors": {
+                    "primary": "#FF6600",
+                    "secondary": "#1A1A1A",
+                    "accent": "#FFD700",
+                    "background_dark": "#0F0F0F",
+                    "background_light": "#FAFAFA"
+                }
+            }
+        }"##;
+        let config = OrgConfig::from_json(valid).unwrap();
+        assert!(config.authorities.is_none());
+        assert!(config.organization.logo.is_empty());
+
+        for (from, to) in [
+            ("\"version\": 1", "\"version\": 4"),
+            ("\"format\": \"krill-config\"", "\"format\": \"other\""),
+            ("\"#FF6600\"", "\"orange\""),
+            ("\"en-US\"", "\"xx-YY\""),
+            ("support@bureau.example.com", "support"),
+            ("\"fqdn\"", "\"domain\""),
+            (
+                "\"version\": 1",
+                "\"version\": 1, \"smtps_uri\": \"smtps://\"",
+            ),
+        ] {
+            assert!(
+                matches!(
+                    OrgConfig::from_json(&valid.replace(from, to)),
+                    Err(KrillError::InvalidConfigFile(_))
+                ),
+                "`{to}` must be rejected"
+            );
+        }
+
+        let duplicate = r#""token_categories": [
+            { "name": "Stablecoin", "decimals": 6, "token_2022": false, "transferable": true },
+            { "name": "stablecoin", "decimals": 2, "token_2022": false, "transferable": true }
+        ], "organization""#;
+        assert!(matches!(
+            OrgConfig::from_json(&valid.replacen("\"organization\"", duplicate, 1)),
+            Err(KrillError::InvalidTokenCategoryPreset(_))
+        ));
+
+        assert!(OrgConfig::secrets_passphrase("too short").is_err());
+    }
+}
@@ -0,0 +1,485 @@
+use core::fmt;
+
+use argon2::{Algorithm, Argon2, Params, Version};
+use bitcode::{Decode, Encode};
+use krill_common::{KrillError, KrillResult, RandomBytes};
+use zeroize::Zeroizing;
+
+use crate::{KrillCipher, KrillStorage, StorageBackend, StorageWrites, StoredValue};
+
+/// The secret an operator unlocks the storage with
+pub enum UnlockSecret {
+    /// Typed by the operator and stretched with Argon2id
+    Passphrase(Zeroizing<String>),
+    /// A high entropy secret, like the one reconstructed from threshold shares
+    Secret(Zeroizing<[u8; KrillCipher::KEY_LEN]>),
+}
+
+impl UnlockSecret {
+    pub fn passphrase(passphrase: &str) -> Self {
+        Self::Passphrase(Zeroizing::new(passphrase.to_string()))
+    }
+
+    pub fn secret(secret: [u8; KrillCipher::KEY_LEN]) -> Self {
+        Self::Secret(Zeroizing::new(secret))
+    }
+}
+
+impl fmt::Debug for UnlockSecret {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Passphrase(_) => write!(f, "UnlockSecret::Passphrase(Redacted)"),
+            Self::Secret(_) => write!(f, "UnlockSecret::Secret(Redacted)"),
+        }
+    }
+}
+
+/// How the key encryption key is derived from the [UnlockSecret]
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Encode, Decode)]
+pub enum KeyDerivation {
+    Argon2id {
+        memory_kib: u32,
+        iterations: u32,
+        parallelism: u32,
+    },
+    /// Only for secrets that are already uniformly random
+    Blake3,
+}
+
+impl KeyDerivation {
+    /// 64 MiB of memory and 3 passes
+    pub const ARGON2ID_DEFAULT: Self = Self::Argon2id {
+        memory_kib: 64 * 1024,
+        iterations: 3,
+        parallelism: 1,
+    };
+
+    const BLAKE3_CONTEXT: &str = "krill-store 2026-01-01 storage key encryption key";
+
+    fn derive(
+        &self,
+        secret: &UnlockSecret,
+        salt: &[u8; KeyRing::SALT_LEN],
+    ) -> KrillResult<KrillCipher> {
+        let mut key = Zeroizing::new([0u8; KrillCipher::KEY_LEN]);
+
+        match (self, secret) {
+            (
+                Self::Argon2id {
+                    memory_kib,
+                    iterations,
+                    parallelism,
+                },
+                UnlockSecret::Passphrase(passphrase),
+            ) => {
+                let params = Params::new(
+                    *memory_kib,
+                    *iterations,
+                    *parallelism,
+                    Some(KrillCipher::KEY_LEN),
+                )
+                .or(Err(KrillError::KeyDerivationFailed))?;
+
+                Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+                    .hash_password_into(passphrase.as_bytes(), salt, key.as_mut())
+                    .or(Err(KrillError::KeyDerivationFailed))?;
+            }
+            (Self::Blake3, UnlockSecret::Secret(secret)) => {
+                let mut hasher = blake3::Hasher::new_derive_key(Self::BLAKE3_CONTEXT);
+                hasher.update(salt);
+                hasher.update(secret.as_ref());
+
+                key.copy_from_slice(hasher.finalize().as_bytes());
+            }
+            _ => return Err(KrillError::InvalidUnlockSecret),
+        }
+
+        Ok(KrillCipher::new(*key))
+    }
+}
+
+/// The key that encrypts the values of the storage, wrapped by a key derived
+/// from the [UnlockSecret]. Stored in plaintext in the meta keyspace.
+#[derive(Debug, PartialEq, Eq, Clone, Encode, Decode)]
+pub struct KeyRing {
+    pub key_derivation: KeyDerivation,
+    pub salt: [u8; KeyRing::SALT_LEN],
+    pub wrapped_key: Vec<u8>,
+}
+
+impl KeyRing {
+    pub const SALT_LEN: usize = 16;
+
+    const AAD: &[u8] = b"KrillStorageKeyRing";
+
+    /// Wraps `key` with a key derived from `secret` and a new random salt
+    pub fn wrap(
+        secret: &UnlockSecret,
+        key_derivation: KeyDerivation,
+        key: &KrillCipher,
+    ) -> KrillResult<Self> {
+        let key_derivation = match secret {
+            UnlockSecret::Passphrase(_) => key_derivation,
+            UnlockSecret::Secret(_) => KeyDerivation::Blake3,
+        };
+
+        let salt = *RandomBytes::<{ Self::SALT_LEN }>::generate().expose();
+
+        let wrapped_key = key_derivation
+            .derive(secret, &salt)?
+            .encrypt(key.expose_key(), Self::AAD)?;
+
+        Ok(Self {
+            key_derivation,
+            salt,
+            wrapped_key,
+        })
+    }
+
+    pub fn unwrap(&self, secret: &UnlockSecret) -> KrillResult<KrillCipher> {
+        let key = self
+            .key_derivation
+            .derive(secret, &self.salt)?
+            .decrypt(&self.wrapped_key, Self::AAD)
+            .or(Err(KrillError::InvalidUnlockSecret))?;
+
+        let key: [u8; KrillCipher::KEY_LEN] = key
+            .as_slice()
+            .try_into()
+            .or(Err(KrillError::InvalidUnlockSecret))?;
+
+        Ok(KrillCipher::new(key))
+    }
+}
+
+impl KrillStorage {
+    const KEY_KEY_RING: &str = "KeyRing";
+
+    /// The key derivation used when creating the key ring or rekeying with a passphrase
+    pub fn set_key_derivation(&mut self, key_derivation: KeyDerivation) -> &mut Self {
+        self.key_derivation = key_derivation;
+
+        self
+    }
+
+    pub fn is_unlocked(&self) -> bool {
+        self.cipher.is_some()
+    }
+
+    pub async fn is_key_ring_set(&self) -> KrillResult<bool> {
+        let backend = self.backend();
+
+        Ok(
+            blocking::unblock(move || Self::read_key_ring(backend.as_ref()))
+                .await?
+                .is_some(),
+        )
+    }
+
+    /// Unwraps the storage key with `secret` and runs the pending migrations.
+    /// A new key ring protected by `secret` is created the first time the storage is unlocked.
+    pub async fn unlock(mut self, secret: UnlockSecret) -> KrillResult<Self> {
+        blocking::unblock(move || {
+            let backend = self.backend();
+
+            let version = Self::read_schema_version(backend.as_ref())?;
+            if version > Self::SCHEMA_VERSION {
+                return Err(KrillError::UnsupportedSchemaVersion {
+                    found: version,
+                    supported: Self::SCHEMA_VERSION,
+                });
+            }
+
+            let cipher = match Self::read_key_ring(backend.as_ref())? {
+                Some(key_ring) => key_ring.unwrap(&secret)?,
+                None => {
+                    let cipher = KrillCipher::generate();
+                    let key_ring = KeyRing::wrap(&secret, self.key_derivation, &cipher)?;
+
+                    backend.commit(vec![(
+                        Self::KEYSPACE_META,
+                        Self::KEY_KEY_RING.as_bytes().to_vec(),
+                        Some(bitcode::encode(&key_ring)),
+                    )])?;
+
+                    cipher
+                }
+            };
+
+            self.cipher.replace(cipher);
+
+            self.migrate()?;
+
+            Ok(self)
+        })
+        .await
+    }
+
+    /// Replaces the storage key, re-encrypts every value with it and protects it with `new_secret`.
+    /// Run this before the storage is shared, no other writes can happen while rekeying.
+    pub async fn rekey(mut self, new_secret: UnlockSecret) -> KrillResult<Self> {
+        blocking::unblock(move || {
+            let current = self.cipher()?;
+            let cipher = KrillCipher::generate();
+            let key_ring = KeyRing::wrap(&new_secret, self.key_derivation, &cipher)?;
+
+            self.backend().update(&mut |backend| {
+                let mut writes = StorageWrites::new();
+
+                for keyspace in Self::data_keyspaces() {
+                    for (key, value) in backend.entries(keyspace)? {
+                        let payload = Self::open_value(
+                            current,
+                            keyspace,
+                            &key,
+                            Self::versioned_payload(&value)?,
+                        )?;
+                        let ciphertext = Self::seal_value(&cipher, keyspace, &key, &payload)?;
+
+                        writes.push((
+                            keyspace,
+                            key,
+                            Some(StoredValue::wrap(Self::SCHEMA_VERSION, &ciphertext)),
+                        ));
+                    }
+                }
+
+                writes.push((
+                    Self::KEYSPACE_META,
+                    Self::KEY_KEY_RING.as_bytes().to_vec(),
+                    Some(bitcode::encode(&key_ring)),
+                ));
+
+                Ok(writes)
+            })?;
+
+            self.cipher.replace(cipher);
+
+            Ok(self)
+        })
+        .await
+    }
+
+    pub(crate) fn cipher(&self) -> KrillResult<&KrillCipher> {
+        self.cipher.as_ref().ok_or(KrillError::StorageLocked)
+    }
+
+    /// The keyspace and the key of the value are the additional data so a ciphertext
+    /// cannot be moved to another key or keyspace
+    pub(crate) fn seal_value(
+        cipher: &KrillCipher,
+        keyspace: &str,
+        key: &[u8],
+        payload: &[u8],
+    ) -> KrillResult<Vec<u8>> {
+        cipher.encrypt(payload, &Self::value_aad(keyspace, key))
+    }
+
+    pub(crate) fn open_value(
+        cipher: &KrillCipher,
+        keyspace: &str,
+        key: &[u8],
+        ciphertext: &[u8],
+    ) -> KrillResult<Zeroizing<Vec<u8>>> {
+        cipher.decrypt(ciphertext, &Self::value_aad(keyspace, key))
+    }
+
+    /// Keyspace names never contain `:` so the separator is unambiguous
+    fn value_aad(keyspace: &str, key: &[u8]) -> Vec<u8> {
+        [keyspace.as_bytes(), b":", key].concat()
+    }
+
+    fn read_key_ring(backend: &dyn StorageBackend) -> KrillResult<Option<KeyRing>> {
+        backend
+            .get(Self::KEYSPACE_META, Self::KEY_KEY_RING.as_bytes())?
+            .map(|bytes| {
+                bitcode::decode::<KeyRing>(&bytes).or(Err(KrillError::Store(
+                    "The key ring is corrupted".to_string(),
+                )))
+            })
+            .transpose()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use camino::Utf8Path;
+    use krill_common::{AuthTokenDetails, Holder, MailProvider, SmtpConfig};
+
+    use crate::TestDir;
+
+    const SMTP_PASSWORD: &str = "correct-horse-battery-staple";
+    const API_KEY: &str = "9f1c2d3e4b5a69788796a5b4c3d2e1f0";
+
+    // Keeps the tests fast, the parameters are stored in the key ring
+    const TEST_KEY_DERIVATION: KeyDerivation = KeyDerivation::Argon2id {
+        memory_kib: 64,
+        iterations: 1,
+        parallelism: 1,
+    };
+
+    async fn open(path: &Utf8Path, secret: UnlockSecret) -> KrillResult<KrillStorage> {
+        let mut storage = KrillStorage::init_db(path.to_path_buf()).await?;
+        storage.set_key_derivation(TEST_KEY_DERIVATION);
+
+        storage.unlock(secret).await
+    }
+
+    fn files_contain(path: &std::path::Path, needle: &[u8]) -> bool {
+        std::fs::read_dir(path).unwrap().any(|entry| {
+            let path = entry.unwrap().path();
+
+            if path.is_dir() {
+                files_contain(&path, needle)
+            } else {
+                std::fs::read(&path)
+                    .unwrap()
+                    .windows(needle.len())
+                    .any(|window| window == needle)
+            }
+        })
+    }
+
+    #[test]
+    fn no_plaintext_on_disk() {
+        smol::block_on(async {
+            let dir = TestDir::new("KeyRing");
+            let path = dir.storage_path();
+            let storage = open(&path, UnlockSecret::passphrase("operator passphrase"))
+                .await
+                .unwrap();
+
+            let mail = MailProvider::Smtp(SmtpConfig::new("mail.example", "krill", SMTP_PASSWORD));
+            storage.set_mail_provider(&mail).await.unwrap();
+            storage.set_solana_api_key(API_KEY).await.unwrap();
+
+            let token = AuthTokenDetails::generate_token();
+            let details = AuthTokenDetails::new(Holder::new_with_tld("admin@example.com").unwrap());
+            let store_key = storage.set_auth_token(token, details).await.unwrap();
+
+            assert_eq!(storage.get_mail_provider().await.unwrap(), Some(mail));
+            drop(storage);
+
+            assert!(!files_contain(path.as_std_path(), SMTP_PASSWORD.as_bytes()));
+            assert!(!files_contain(path.as_std_path(), API_KEY.as_bytes()));
+            assert!(!files_contain(path.as_std_path(), b"admin@example.com"));
+            assert!(!files_contain(path.as_std_path(), &token));
+
+            let storage = open(&path, UnlockSecret::passphrase("operator passphrase"))
+                .await
+                .unwrap();
+            assert_eq!(
+                storage.get_solana_api_key().await.unwrap().as_deref(),
+                Some(API_KEY)
+            );
+            assert!(storage.get_auth_token(store_key).await.unwrap().is_some());
+        });
+    }
+
+    #[test]
+    fn unlock_and_rekey() {
+        smol::block_on(async {
+            let dir = TestDir::new("KeyRing");
+            let path = dir.storage_path();
+            let storage = open(&path, UnlockSecret::passphrase("first passphrase"))
+                .await
+                .unwrap();
+            storage.set_solana_api_key(API_KEY).await.unwrap();
+            drop(storage);
+
+            assert_eq!(
+                open(&path, UnlockSecret::passphrase("wrong passphrase"))
+                    .await
+                    .err(),
+                Some(KrillError::InvalidUnlockSecret)
+            );
+
+            let locked = KrillStorage::init_db(path.clone()).await.unwrap();
+            assert!(!locked.is_unlocked());
+            assert_eq!(
+                locked.get_solana_api_key().await.err(),
+                Some(KrillError::StorageLocked)
+            );
+            drop(locked);
+
+            // Rekey to a threshold secret
+            let secret = *RandomBytes::<{ KrillCipher::KEY_LEN }>::generate().expose();
+            let storage = open(&path, UnlockSecret::passphrase("first passphrase"))
+                .await
+                .unwrap()
+                .rekey(UnlockSecret::secret(secret))
+                .await
+                .unwrap();
+            assert_eq!(
+                storage.get_solana_api_key().await.unwrap().as_deref(),
+                Some(API_KEY)
+            );
+            drop(storage);
+
+            assert_eq!(
+                open(&path, UnlockSecret::passphrase("first passphrase"))
+                    .await
+                    .err(),
+                Some(KrillError::InvalidUnlockSecret)
+            );
+
+            let storage = open(&path, UnlockSecret::secret(secret)).await.unwrap();
+            assert_eq!(
+                storage.get_solana_api_key().await.unwrap().as_deref(),
+                Some(API_KEY)
+            );
+        });
+    }
+
+    #[test]
+    fn values_are_bound_to_their_location() {
+        smol::block_on(async {
+            let storage = KrillStorage::init_memory()
+                .unlock(UnlockSecret::secret([4u8; KrillCipher::KEY_LEN]))
+                .await
+                .unwrap();
+            storage.set_solana_api_key(API_KEY).await.unwrap();
+
+            let backend = storage.backend();
+            let key = KrillStorage::SOLANA_API_KEY_INFO.as_bytes();
+            let sealed = backend
+                .get(KrillStorage::KEYSPACE_ORG_INFO, key)
+                .unwrap()
+                .unwrap();
+
+            // The same key in another keyspace
+            backend
+                .commit(vec![(
+                    KrillStorage::KEYSPACE_APP_STATE,
+                    key.to_vec(),
+                    Some(sealed.clone()),
+                )])
+                .unwrap();
+            assert_eq!(
+                storage
+                    .get(
+                        KrillStorage::KEYSPACE_APP_STATE,
+                        KrillStorage::SOLANA_API_KEY_INFO
+                    )
+                    .await
+                    .err(),
+                Some(KrillError::DecryptionFailed)
+            );
+
+            // Another key in the same keyspace
+            backend
+                .commit(vec![(
+                    KrillStorage::KEYSPACE_ORG_INFO,
+                    KrillStorage::ORG_DOMAIN_NAME.as_bytes().to_vec(),
+                    Some(sealed),
+                )])
+                .unwrap();
+            assert_eq!(
+                storage.get_fqdn().await.err(),
+                Some(KrillError::DecryptionFailed)
+            );
+        });
+    }
+}
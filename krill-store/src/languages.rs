@@ -1,11 +1,14 @@
 use countries_iso3166::BC47LanguageInfo;
 use krill_common::{KrillError, KrillResult};
 
-use crate::KrillStorage;
+use crate::{KrillStorage, Record};
 
 impl KrillStorage {
     pub const KEY_SERVER_LANGUAGE: &str = "ServerLanguage";
 
+    const SERVER_LANGUAGE: Record<String> =
+        Record::new(Self::KEYSPACE_ORG_INFO, Self::KEY_SERVER_LANGUAGE);
+
     pub async fn set_server_language(&self, bcp47_code: &str) -> KrillResult<()> {
         let parsed: BC47LanguageInfo = bcp47_code.into();
 
@@ -15,26 +18,16 @@ impl KrillStorage {
             ));
         }
 
-        let keyspace = self.org_info_keyspace();
-
-        // Use enum to u16 auto-conversion
-        self.set(keyspace, Self::KEY_SERVER_LANGUAGE, parsed.code())
+        Self::SERVER_LANGUAGE
+            .put(self, &parsed.code().to_string())
             .await
     }
 
     pub async fn get_server_language(&self) -> KrillResult<BC47LanguageInfo> {
-        let keyspace = self.org_info_keyspace();
-
-        let lang_bytes = self.get(keyspace, Self::KEY_SERVER_LANGUAGE).await?;
-
-        if let Some(bytes) = lang_bytes.as_ref() {
-            let parsed = bitcode::decode::<String>(bytes).or(Err(KrillError::Store(
-                "Unable to deserialize server language".to_string(),
-            )))?;
-
-            Ok(parsed.as_str().into())
-        } else {
-            Ok(BC47LanguageInfo::EN_US)
-        }
+        Ok(Self::SERVER_LANGUAGE
+            .get(self)
+            .await?
+            .map(|code| code.as_str().into())
+            .unwrap_or(BC47LanguageInfo::EN_US))
     }
 }
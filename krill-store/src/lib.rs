@@ -1,10 +1,53 @@
 mod storage;
 pub use storage::*;
 
+mod backend;
+pub use backend::*;
+
+mod schema;
+pub use schema::*;
+
+mod table;
+pub use table::*;
+
 mod org_info;
+pub use org_info::*;
+
+mod tenants;
+pub use tenants::*;
+
+mod config;
+pub use config::*;
+
+mod mail_queue;
+pub use mail_queue::*;
 
 mod app_state;
 
 mod languages;
 
 mod auth_tokens;
+pub use auth_tokens::AuthTokenType;
+
+mod siws;
+
+mod members;
+pub use members::*;
+
+mod authorities;
+pub use authorities::*;
+
+mod cipher;
+pub use cipher::*;
+
+mod keyring;
+pub use keyring::*;
+
+mod totp;
+pub use totp::*;
+
+mod audit;
+pub use audit::*;
+
+mod backup;
+pub use backup::*;
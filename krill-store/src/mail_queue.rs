@@ -0,0 +1,300 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use bitcode::{Decode, Encode};
+use krill_common::{KrillError, KrillResult, RandomBytes};
+
+use crate::{KrillStorage, Table};
+
+/// The timestamp in nanoseconds followed by random bytes so the queue scans oldest first
+pub type MailId = [u8; 16];
+
+/// The delivery state of a queued email
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Encode, Decode)]
+pub enum MailStatus {
+    Queued,
+    Sent,
+    /// Dead lettered after the last attempt failed
+    Failed,
+}
+
+impl MailStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Queued => "queued",
+            Self::Sent => "sent",
+            Self::Failed => "failed",
+        }
+    }
+}
+
+/// An email waiting for delivery. Times are seconds since the UNIX epoch.
+#[derive(PartialEq, Eq, Clone, Encode, Decode)]
+pub struct QueuedMail {
+    pub id: MailId,
+    /// What the email is about, like `invitation:jane@example.com`. The status
+    /// of the latest email with the reference is shown in the UI.
+    pub reference: String,
+    /// The encoded message. Removed once it is sent since it may hold a sign in link.
+    pub message: Vec<u8>,
+    pub status: MailStatus,
+    pub attempts: u32,
+    pub next_attempt: u64,
+    pub last_error: Option<String>,
+    pub created: u64,
+    pub updated: u64,
+}
+
+impl QueuedMail {
+    pub fn sent(&mut self, now: u64) -> &mut Self {
+        self.status = MailStatus::Sent;
+        self.attempts += 1;
+        self.message.clear();
+        self.last_error.take();
+        self.updated = now;
+
+        self
+    }
+
+    /// Retries at `retry_at` or dead letters the email when it is `None`
+    pub fn failed(&mut self, error: &str, retry_at: Option<u64>, now: u64) -> &mut Self {
+        self.attempts += 1;
+        self.last_error.replace(error.to_string());
+        self.updated = now;
+
+        match retry_at {
+            Some(retry_at) => self.next_attempt = retry_at,
+            None => self.status = MailStatus::Failed,
+        }
+
+        self
+    }
+
+    pub fn is_due(&self, now: u64) -> bool {
+        self.status == MailStatus::Queued && self.next_attempt <= now
+    }
+
+    /// Seconds since the UNIX epoch
+    pub fn unix_now() -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_secs())
+            .unwrap_or_default()
+    }
+
+    fn new_id() -> MailId {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_nanos() as u64)
+            .unwrap_or_default();
+
+        let mut id = [0u8; 16];
+        id[..8].copy_from_slice(&nanos.to_be_bytes());
+        id[8..].copy_from_slice(RandomBytes::<8>::generate().expose());
+
+        id
+    }
+}
+
+impl std::fmt::Debug for QueuedMail {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("QueuedMail")
+            .field("id", &self.id)
+            .field("reference", &self.reference)
+            .field("message", &"Redacted")
+            .field("status", &self.status)
+            .field("attempts", &self.attempts)
+            .field("next_attempt", &self.next_attempt)
+            .field("last_error", &self.last_error)
+            .field("created", &self.created)
+            .field("updated", &self.updated)
+            .finish()
+    }
+}
+
+impl KrillStorage {
+    pub(crate) const KEYSPACE_MAIL_QUEUE: &str = "MailQueue";
+
+    const MAIL_QUEUE: Table<MailId, QueuedMail> = Table::new(Self::KEYSPACE_MAIL_QUEUE);
+
+    /// Queues the encoded message for delivery from `now`
+    pub async fn enqueue_mail(
+        &self,
+        reference: &str,
+        message: Vec<u8>,
+        now: u64,
+    ) -> KrillResult<QueuedMail> {
+        let mail = QueuedMail {
+            id: QueuedMail::new_id(),
+            reference: reference.to_string(),
+            message,
+            status: MailStatus::Queued,
+            attempts: 0,
+            next_attempt: now,
+            last_error: None,
+            created: now,
+            updated: now,
+        };
+
+        Self::MAIL_QUEUE.put(self, &mail.id, &mail).await?;
+
+        Ok(mail)
+    }
+
+    /// The emails due at `now`, oldest first
+    pub async fn due_mail(&self, now: u64, limit: usize) -> KrillResult<Vec<QueuedMail>> {
+        Ok(Self::MAIL_QUEUE
+            .scan(self)
+            .await?
+            .into_iter()
+            .map(|(_, mail)| mail)
+            .filter(|mail| mail.is_due(now))
+            .take(limit)
+            .collect())
+    }
+
+    pub async fn update_queued_mail(&self, mail: &QueuedMail) -> KrillResult<()> {
+        Self::MAIL_QUEUE.put(self, &mail.id, mail).await
+    }
+
+    /// The latest email with the reference
+    pub async fn mail_status(&self, reference: &str) -> KrillResult<Option<QueuedMail>> {
+        Ok(Self::MAIL_QUEUE
+            .scan(self)
+            .await?
+            .into_iter()
+            .rev()
+            .map(|(_, mail)| mail)
+            .find(|mail| mail.reference == reference))
+    }
+
+    /// Queues the latest email with the reference again if it was dead lettered
+    pub async fn retry_mail(&self, reference: &str, now: u64) -> KrillResult<QueuedMail> {
+        let mut mail = self
+            .mail_status(reference)
+            .await?
+            .filter(|mail| mail.status == MailStatus::Failed)
+            .ok_or(KrillError::MailNotQueued(reference.to_string()))?;
+
+        mail.status = MailStatus::Queued;
+        mail.attempts = 0;
+        mail.next_attempt = now;
+        mail.updated = now;
+
+        self.update_queued_mail(&mail).await?;
+
+        Ok(mail)
+    }
+
+    /// Removes sent and dead lettered emails last updated before `before`
+    pub async fn prune_mail_queue(&self, before: u64) -> KrillResult<usize> {
+        let expired = Self::MAIL_QUEUE
+            .scan(self)
+            .await?
+            .into_iter()
+            .filter(|(_, mail)| mail.status != MailStatus::Queued && mail.updated < before)
+            .map(|(id, _)| id)
+            .collect::<Vec<MailId>>();
+
+        let mut transaction = self.transaction();
+        for id in &expired {
+            transaction.delete(&Self::MAIL_QUEUE, id)?;
+        }
+        transaction.commit().await?;
+
+        Ok(expired.len())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::test_storages;
+
+    #[test]
+    fn mail_queue_lifecycle() {
+        smol::block_on(async {
+            for storage in test_storages("MailQueue").await {
+                let first = storage
+                    .enqueue_mail("invitation:jane@example.com", vec![1, 2, 3], 100)
+                    .await
+                    .unwrap();
+                let second = storage
+                    .enqueue_mail("verification:support@example.com", vec![4], 100)
+                    .await
+                    .unwrap();
+                assert!(!format!("{first:?}").contains("[1, 2, 3]"));
+
+                assert_eq!(storage.due_mail(99, 10).await.unwrap(), vec![]);
+                assert_eq!(
+                    storage.due_mail(100, 10).await.unwrap(),
+                    vec![first.clone(), second.clone()]
+                );
+                assert_eq!(storage.due_mail(100, 1).await.unwrap(), vec![first.clone()]);
+
+                let mut retried = first.clone();
+                retried.failed("Connection reset", Some(160), 100);
+                storage.update_queued_mail(&retried).await.unwrap();
+                assert_eq!(
+                    storage.due_mail(159, 10).await.unwrap(),
+                    vec![second.clone()]
+                );
+                assert_eq!(storage.due_mail(160, 10).await.unwrap().len(), 2);
+
+                let mut sent = second.clone();
+                sent.sent(101);
+                storage.update_queued_mail(&sent).await.unwrap();
+
+                let status = storage
+                    .mail_status("verification:support@example.com")
+                    .await
+                    .unwrap()
+                    .unwrap();
+                assert_eq!(status.status, MailStatus::Sent);
+                assert!(status.message.is_empty());
+
+                assert_eq!(
+                    storage.retry_mail("invitation:jane@example.com", 200).await,
+                    Err(KrillError::MailNotQueued(
+                        "invitation:jane@example.com".to_string()
+                    ))
+                );
+
+                retried.failed("Mailbox unavailable", None, 160);
+                storage.update_queued_mail(&retried).await.unwrap();
+                assert_eq!(storage.due_mail(1_000, 10).await.unwrap(), vec![]);
+
+                let status = storage
+                    .mail_status("invitation:jane@example.com")
+                    .await
+                    .unwrap()
+                    .unwrap();
+                assert_eq!(status.status, MailStatus::Failed);
+                assert_eq!(status.attempts, 2);
+                assert_eq!(status.last_error.as_deref(), Some("Mailbox unavailable"));
+                assert_eq!(status.message, vec![1, 2, 3]);
+
+                let requeued = storage
+                    .retry_mail("invitation:jane@example.com", 200)
+                    .await
+                    .unwrap();
+                assert_eq!(requeued.status, MailStatus::Queued);
+                assert_eq!(storage.due_mail(200, 10).await.unwrap(), vec![requeued]);
+
+                assert_eq!(storage.prune_mail_queue(150).await.unwrap(), 1);
+                assert_eq!(
+                    storage
+                        .mail_status("verification:support@example.com")
+                        .await
+                        .unwrap(),
+                    None
+                );
+                assert!(storage
+                    .mail_status("invitation:jane@example.com")
+                    .await
+                    .unwrap()
+                    .is_some());
+            }
+        })
+    }
+}
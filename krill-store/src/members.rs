@@ -0,0 +1,790 @@
+use std::time::Duration;
+
+use bitcode::{Decode, Encode};
+use krill_common::{Holder, KrillError, KrillResult, RandomBytes, SiwsMessage, UserRole};
+use tai64::Tai64N;
+
+use crate::{KrillStorage, Table};
+
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy, Encode, Decode)]
+pub enum MemberStatus {
+    /// Invited by email and waiting for the invitation to be accepted
+    #[default]
+    Invited,
+    Active,
+    /// Sessions of suspended members are rejected until they are reactivated
+    Suspended,
+}
+
+impl MemberStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Invited => "invited",
+            Self::Active => "active",
+            Self::Suspended => "suspended",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "invited" => Some(Self::Invited),
+            "active" => Some(Self::Active),
+            "suspended" => Some(Self::Suspended),
+            _ => None,
+        }
+    }
+}
+
+/// An authority or community member of the organization, keyed by the email address
+#[derive(Debug, PartialEq, Eq, Clone, Encode, Decode)]
+pub struct MemberProfile {
+    pub email: String,
+    pub display_name: String,
+    pub role: UserRole,
+    pub wallets: Vec<String>,
+    pub frost_identifier: Option<Vec<u8>>,
+    pub status: MemberStatus,
+    /// The identity of the administrator who sent the invitation
+    pub invited_by: String,
+    pub created: [u8; Tai64N::BYTE_SIZE],
+}
+
+impl MemberProfile {
+    /// Length of a serialized FROST identifier of the Ed25519 ciphersuite
+    pub const FROST_IDENTIFIER_LEN: usize = 32;
+
+    /// The holder of sessions issued to this member
+    pub fn holder(&self) -> KrillResult<Holder> {
+        let holder = Holder::new_with_tld(&self.email)?.set_user_display(&self.display_name);
+
+        let holder = match self.wallets.first() {
+            Some(wallet) => holder.set_wallet(wallet),
+            None => holder,
+        };
+
+        Ok(match self.role {
+            UserRole::Superuser => holder.set_superuser(),
+            UserRole::Admin => holder.set_admin(),
+            UserRole::Member => holder.set_member(),
+        })
+    }
+
+    fn matches(&self, query: &MemberQuery) -> bool {
+        let search = query.search.trim().to_lowercase();
+
+        let matches_search = search.is_empty()
+            || self.email.contains(&search)
+            || self.display_name.to_lowercase().contains(&search)
+            || self
+                .wallets
+                .iter()
+                .any(|wallet| wallet.to_lowercase().contains(&search));
+
+        matches_search
+            && query.role.is_none_or(|role| role == self.role)
+            && query.status.is_none_or(|status| status == self.status)
+    }
+}
+
+/// Only the hash of the token is stored, the token itself is sent by email
+#[derive(Debug, PartialEq, Eq, Clone, Encode, Decode)]
+pub struct MemberInvitation {
+    pub email: String,
+    pub expires: [u8; Tai64N::BYTE_SIZE],
+}
+
+impl MemberInvitation {
+    pub const TOKEN_LEN: usize = 32;
+    pub const VALIDITY: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+    pub fn is_expired(&self) -> bool {
+        Tai64N::from_slice(&self.expires).is_ok_and(|expires| Tai64N::now() > expires)
+    }
+
+    /// The base58 form of the token used in the invitation link
+    pub fn encode_token(token: &[u8; Self::TOKEN_LEN]) -> String {
+        bs58::encode(token).into_string()
+    }
+
+    pub fn decode_token(token: &str) -> KrillResult<[u8; Self::TOKEN_LEN]> {
+        let mut buffer = [0u8; Self::TOKEN_LEN];
+
+        let decoded_len = bs58::decode(token.trim())
+            .onto(&mut buffer)
+            .or(Err(KrillError::InvalidMemberInvitation))?;
+
+        if decoded_len != buffer.len() {
+            return Err(KrillError::InvalidMemberInvitation);
+        }
+
+        Ok(buffer)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct MemberQuery {
+    /// Matched against the email address, the display name and the wallets
+    pub search: String,
+    pub role: Option<UserRole>,
+    pub status: Option<MemberStatus>,
+    /// Zero based
+    pub page: usize,
+    pub page_size: usize,
+}
+
+impl MemberQuery {
+    pub const DEFAULT_PAGE_SIZE: usize = 25;
+    pub const MAX_PAGE_SIZE: usize = 100;
+}
+
+impl Default for MemberQuery {
+    fn default() -> Self {
+        Self {
+            search: String::default(),
+            role: Option::default(),
+            status: Option::default(),
+            page: 0,
+            page_size: Self::DEFAULT_PAGE_SIZE,
+        }
+    }
+}
+
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
+pub struct MemberPage {
+    pub members: Vec<MemberProfile>,
+    /// The number of members matching the query on all pages
+    pub total: usize,
+}
+
+impl KrillStorage {
+    pub(crate) const KEYSPACE_MEMBERS: &str = "Members";
+    pub(crate) const KEYSPACE_MEMBER_INVITATIONS: &str = "MemberInvitations";
+
+    const MEMBERS: Table<String, MemberProfile> = Table::new(Self::KEYSPACE_MEMBERS);
+    /// Keyed by the hash of the invitation token
+    const MEMBER_INVITATIONS: Table<[u8; 32], MemberInvitation> =
+        Table::new(Self::KEYSPACE_MEMBER_INVITATIONS);
+
+    /// Adds an invited member or renews the invitation of a member that has not accepted yet.
+    /// Returns the invitation token to send to the member.
+    pub async fn invite_member(
+        &self,
+        email: &str,
+        display_name: &str,
+        role: UserRole,
+        invited_by: &Holder,
+    ) -> KrillResult<([u8; MemberInvitation::TOKEN_LEN], MemberProfile)> {
+        Holder::new_with_tld(email)?;
+
+        if role == UserRole::Superuser {
+            return Err(KrillError::Forbidden);
+        }
+
+        let email = Self::member_key(email);
+
+        let member = match Self::MEMBERS.get(self, &email).await? {
+            Some(existing) if existing.status != MemberStatus::Invited => {
+                return Err(KrillError::MemberAlreadyExists(email));
+            }
+            _ => MemberProfile {
+                email: email.clone(),
+                display_name: display_name.trim().to_string(),
+                role,
+                wallets: Vec::default(),
+                frost_identifier: Option::default(),
+                status: MemberStatus::Invited,
+                invited_by: invited_by.identity().to_string(),
+                created: Tai64N::now().to_bytes(),
+            },
+        };
+
+        let token = *RandomBytes::<{ MemberInvitation::TOKEN_LEN }>::generate().expose();
+        let invitation = MemberInvitation {
+            email: email.clone(),
+            expires: (Tai64N::now() + MemberInvitation::VALIDITY).to_bytes(),
+        };
+
+        let mut tx = self.transaction();
+        for (hash, _) in self.member_invitations(&email).await? {
+            tx.delete(&Self::MEMBER_INVITATIONS, &hash)?;
+        }
+        tx.put(&Self::MEMBERS, &email, &member)?.put(
+            &Self::MEMBER_INVITATIONS,
+            &Self::invitation_key(&token),
+            &invitation,
+        )?;
+        tx.commit().await?;
+
+        Ok((token, member))
+    }
+
+    /// Activates the invited member. Each invitation can only be accepted once.
+    pub async fn accept_member_invitation(
+        &self,
+        token: &[u8; MemberInvitation::TOKEN_LEN],
+    ) -> KrillResult<MemberProfile> {
+        let key = Self::invitation_key(token);
+
+        let invitation = Self::MEMBER_INVITATIONS
+            .get(self, &key)
+            .await?
+            .ok_or(KrillError::InvalidMemberInvitation)?;
+
+        if invitation.is_expired() {
+            Self::MEMBER_INVITATIONS.delete(self, &key).await?;
+
+            return Err(KrillError::MemberInvitationExpired);
+        }
+
+        let mut member = Self::MEMBERS
+            .get(self, &invitation.email)
+            .await?
+            .filter(|member| member.status == MemberStatus::Invited)
+            .ok_or(KrillError::InvalidMemberInvitation)?;
+        member.status = MemberStatus::Active;
+
+        let mut tx = self.transaction();
+        tx.put(&Self::MEMBERS, &member.email, &member)?
+            .delete(&Self::MEMBER_INVITATIONS, &key)?;
+        tx.commit().await?;
+
+        Ok(member)
+    }
+
+    pub async fn get_member(&self, email: &str) -> KrillResult<Option<MemberProfile>> {
+        Self::MEMBERS.get(self, &Self::member_key(email)).await
+    }
+
+    /// The members matching the query ordered by email address
+    pub async fn members(&self, query: &MemberQuery) -> KrillResult<MemberPage> {
+        let page_size = query.page_size.clamp(1, MemberQuery::MAX_PAGE_SIZE);
+
+        let matching = Self::MEMBERS
+            .scan(self)
+            .await?
+            .into_iter()
+            .map(|(_, member)| member)
+            .filter(|member| member.matches(query))
+            .collect::<Vec<_>>();
+
+        Ok(MemberPage {
+            total: matching.len(),
+            members: matching
+                .into_iter()
+                .skip(query.page.saturating_mul(page_size))
+                .take(page_size)
+                .collect(),
+        })
+    }
+
+    pub async fn update_member(
+        &self,
+        email: &str,
+        display_name: &str,
+        role: UserRole,
+    ) -> KrillResult<MemberProfile> {
+        if role == UserRole::Superuser {
+            return Err(KrillError::Forbidden);
+        }
+
+        let display_name = display_name.trim().to_string();
+
+        self.modify_member(email, move |member| {
+            member.display_name = display_name.clone();
+            member.role = role;
+
+            Ok(())
+        })
+        .await
+    }
+
+    /// Suspends an active member or reactivates a suspended one
+    pub async fn set_member_suspended(
+        &self,
+        email: &str,
+        suspended: bool,
+    ) -> KrillResult<MemberProfile> {
+        self.modify_member(email, move |member| {
+            member.status = match (member.status, suspended) {
+                (MemberStatus::Invited, _) => return Err(KrillError::InvalidMemberInvitation),
+                (_, true) => MemberStatus::Suspended,
+                (_, false) => MemberStatus::Active,
+            };
+
+            Ok(())
+        })
+        .await
+    }
+
+    /// Links the wallet so the member can sign in with it. A wallet is linked to one member only.
+    pub async fn link_member_wallet(
+        &self,
+        email: &str,
+        address: &str,
+    ) -> KrillResult<MemberProfile> {
+        let email = Self::member_key(email);
+        let address = address.trim().to_string();
+        SiwsMessage::decode_address(&address)?;
+
+        // Checked in the same update as the link so two members cannot link the same wallet
+        self.update_tables(move |tables| {
+            let mut linked = None;
+
+            for (key, member) in tables.scan(&Self::MEMBERS)? {
+                if key == email {
+                    linked.replace(member);
+                } else if member.wallets.contains(&address) {
+                    return Err(KrillError::WalletAlreadyLinked(address.clone()));
+                }
+            }
+
+            let mut member = linked.ok_or(KrillError::MemberNotFound(email.clone()))?;
+            if !member.wallets.contains(&address) {
+                member.wallets.push(address.clone());
+            }
+
+            tables.put(&Self::MEMBERS, &email, &member)?;
+
+            Ok(member)
+        })
+        .await
+    }
+
+    pub async fn unlink_member_wallet(
+        &self,
+        email: &str,
+        address: &str,
+    ) -> KrillResult<MemberProfile> {
+        let address = address.trim().to_string();
+
+        self.modify_member(email, move |member| {
+            member.wallets.retain(|wallet| *wallet != address);
+
+            Ok(())
+        })
+        .await
+    }
+
+    /// The identifier of the member in the FROST signing group, `None` removes it
+    pub async fn set_member_frost_identifier(
+        &self,
+        email: &str,
+        identifier: Option<Vec<u8>>,
+    ) -> KrillResult<MemberProfile> {
+        if let Some(identifier) = identifier.as_ref() {
+            if identifier.len() != MemberProfile::FROST_IDENTIFIER_LEN {
+                return Err(KrillError::InvalidRequestData(format!(
+                    "FROST identifier of {} bytes",
+                    identifier.len()
+                )));
+            }
+        }
+
+        self.modify_member(email, move |member| {
+            member.frost_identifier = identifier.clone();
+
+            Ok(())
+        })
+        .await
+    }
+
+    /// The member the wallet is linked to. Only active members sign in with a wallet.
+    pub async fn wallet_member(&self, address: &str) -> KrillResult<MemberProfile> {
+        let member = Self::MEMBERS
+            .scan(self)
+            .await?
+            .into_iter()
+            .map(|(_, member)| member)
+            .find(|member| member.wallets.iter().any(|wallet| wallet == address))
+            .ok_or(KrillError::SiwsWalletNotLinked)?;
+
+        match member.status {
+            MemberStatus::Active => Ok(member),
+            MemberStatus::Invited => Err(KrillError::MemberNotActive),
+            MemberStatus::Suspended => Err(KrillError::MemberSuspended),
+        }
+    }
+
+    /// Removes the member, any pending invitation and every session of the member
+    pub async fn remove_member(&self, email: &str) -> KrillResult<()> {
+        let email = Self::member_key(email);
+
+        if Self::MEMBERS.get(self, &email).await?.is_none() {
+            return Err(KrillError::MemberNotFound(email));
+        }
+
+        let mut tx = self.transaction();
+        for (hash, _) in self.member_invitations(&email).await? {
+            tx.delete(&Self::MEMBER_INVITATIONS, &hash)?;
+        }
+        tx.delete(&Self::MEMBERS, &email)?;
+        self.revoke_auth_tokens(&mut tx, &email).await?;
+
+        tx.commit().await
+    }
+
+    /// Rejects holders whose member profile is suspended or was removed. Holders without
+    /// an email address resolve to the member their wallet is linked to. Only the
+    /// superuser has no member profile.
+    pub async fn check_member_not_suspended(&self, holder: &Holder) -> KrillResult<()> {
+        if holder.role() == UserRole::Superuser {
+            return Ok(());
+        }
+
+        if holder.email_address().is_empty() {
+            let wallet = holder.wallet().ok_or(KrillError::InvalidAuthToken)?;
+
+            return self.wallet_member(wallet).await.map(|_| ());
+        }
+
+        match self.get_member(holder.email_address()).await? {
+            Some(member) if member.status == MemberStatus::Suspended => {
+                Err(KrillError::MemberSuspended)
+            }
+            Some(_) => Ok(()),
+            None => Err(KrillError::MemberNotFound(Self::member_key(
+                holder.email_address(),
+            ))),
+        }
+    }
+
+    /// Reads and writes the member in one update so concurrent changes are not lost
+    async fn modify_member(
+        &self,
+        email: &str,
+        mut modify: impl FnMut(&mut MemberProfile) -> KrillResult<()> + Send + 'static,
+    ) -> KrillResult<MemberProfile> {
+        let email = Self::member_key(email);
+
+        self.update_tables(move |tables| {
+            let mut member = tables
+                .get(&Self::MEMBERS, &email)?
+                .ok_or(KrillError::MemberNotFound(email.clone()))?;
+
+            modify(&mut member)?;
+
+            tables.put(&Self::MEMBERS, &email, &member)?;
+
+            Ok(member)
+        })
+        .await
+    }
+
+    async fn member_invitations(
+        &self,
+        email: &str,
+    ) -> KrillResult<Vec<([u8; 32], MemberInvitation)>> {
+        Ok(Self::MEMBER_INVITATIONS
+            .scan(self)
+            .await?
+            .into_iter()
+            .filter(|(_, invitation)| invitation.email == email)
+            .collect())
+    }
+
+    /// Email addresses are compared case insensitively
+    fn member_key(email: &str) -> String {
+        email.trim().to_lowercase()
+    }
+
+    fn invitation_key(token: &[u8; MemberInvitation::TOKEN_LEN]) -> [u8; 32] {
+        *blake3::hash(token).as_bytes()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use krill_common::AuthTokenDetails;
+
+    use crate::test_storages;
+
+    fn admin() -> Holder {
+        Holder::new_with_tld("admin@krill.example")
+            .unwrap()
+            .set_admin()
+    }
+
+    #[test]
+    fn invitation_flow() {
+        smol::block_on(async {
+            for storage in test_storages("Members").await {
+                let (first_token, member) = storage
+                    .invite_member("Ada@Krill.example", "Ada", UserRole::Member, &admin())
+                    .await
+                    .unwrap();
+                assert_eq!(member.email, "ada@krill.example");
+                assert_eq!(member.status, MemberStatus::Invited);
+
+                // Inviting again replaces the previous invitation
+                let (token, _) = storage
+                    .invite_member("ada@krill.example", "Ada", UserRole::Member, &admin())
+                    .await
+                    .unwrap();
+                assert_eq!(
+                    storage.accept_member_invitation(&first_token).await.err(),
+                    Some(KrillError::InvalidMemberInvitation)
+                );
+
+                let member = storage.accept_member_invitation(&token).await.unwrap();
+                assert_eq!(member.status, MemberStatus::Active);
+                assert_eq!(
+                    member.holder().unwrap().email_address(),
+                    "ada@krill.example"
+                );
+                assert_eq!(
+                    storage.accept_member_invitation(&token).await.err(),
+                    Some(KrillError::InvalidMemberInvitation)
+                );
+
+                assert!(matches!(
+                    storage
+                        .invite_member("ada@krill.example", "Ada", UserRole::Admin, &admin())
+                        .await,
+                    Err(KrillError::MemberAlreadyExists(_))
+                ));
+                assert_eq!(
+                    storage
+                        .invite_member("eve@krill.example", "Eve", UserRole::Superuser, &admin())
+                        .await
+                        .err(),
+                    Some(KrillError::Forbidden)
+                );
+            }
+        })
+    }
+
+    #[test]
+    fn search_pagination_and_suspension() {
+        smol::block_on(async {
+            for storage in test_storages("MemberSearch").await {
+                for (email, name, role) in [
+                    ("bob@krill.example", "Bob", UserRole::Member),
+                    ("carol@krill.example", "Carol", UserRole::Admin),
+                    ("dave@krill.example", "Dave", UserRole::Member),
+                ] {
+                    let (token, _) = storage
+                        .invite_member(email, name, role, &admin())
+                        .await
+                        .unwrap();
+                    storage.accept_member_invitation(&token).await.unwrap();
+                }
+
+                let page = storage
+                    .members(&MemberQuery {
+                        page: 1,
+                        page_size: 2,
+                        ..Default::default()
+                    })
+                    .await
+                    .unwrap();
+                assert_eq!(page.total, 3);
+                assert_eq!(page.members[0].email, "dave@krill.example");
+
+                let admins = storage
+                    .members(&MemberQuery {
+                        role: Some(UserRole::Admin),
+                        ..Default::default()
+                    })
+                    .await
+                    .unwrap();
+                assert_eq!(admins.total, 1);
+
+                let found = storage
+                    .members(&MemberQuery {
+                        search: "BO".to_string(),
+                        ..Default::default()
+                    })
+                    .await
+                    .unwrap();
+                assert_eq!(found.members[0].display_name, "Bob");
+
+                let bob = storage
+                    .set_member_suspended("bob@krill.example", true)
+                    .await
+                    .unwrap();
+                assert_eq!(
+                    storage
+                        .check_member_not_suspended(&bob.holder().unwrap())
+                        .await
+                        .err(),
+                    Some(KrillError::MemberSuspended)
+                );
+
+                let dave_wallet = bs58::encode([9u8; 32]).into_string();
+                storage
+                    .link_member_wallet("dave@krill.example", &dave_wallet)
+                    .await
+                    .unwrap();
+                let found = storage
+                    .members(&MemberQuery {
+                        search: dave_wallet.to_lowercase(),
+                        ..Default::default()
+                    })
+                    .await
+                    .unwrap();
+                assert_eq!(found.total, 1);
+
+                let dave = storage
+                    .get_member("dave@krill.example")
+                    .await
+                    .unwrap()
+                    .unwrap()
+                    .holder()
+                    .unwrap();
+                let session = storage
+                    .set_auth_token(
+                        AuthTokenDetails::generate_token(),
+                        AuthTokenDetails::new(dave.clone()),
+                    )
+                    .await
+                    .unwrap();
+                let other_session = storage
+                    .set_auth_token(
+                        AuthTokenDetails::generate_token(),
+                        AuthTokenDetails::new(admin()),
+                    )
+                    .await
+                    .unwrap();
+                storage.check_member_not_suspended(&dave).await.unwrap();
+
+                // Removed members are signed out and their remaining holders rejected
+                storage.remove_member("dave@krill.example").await.unwrap();
+                assert!(storage
+                    .get_member("dave@krill.example")
+                    .await
+                    .unwrap()
+                    .is_none());
+                assert!(storage.get_auth_token(session).await.unwrap().is_none());
+                assert!(storage
+                    .get_auth_token(other_session)
+                    .await
+                    .unwrap()
+                    .is_some());
+                assert!(matches!(
+                    storage.check_member_not_suspended(&dave).await,
+                    Err(KrillError::MemberNotFound(_))
+                ));
+                storage
+                    .check_member_not_suspended(
+                        &Holder::new_with_tld("admin@krill.example")
+                            .unwrap()
+                            .set_superuser(),
+                    )
+                    .await
+                    .unwrap();
+                assert!(matches!(
+                    storage.remove_member("dave@krill.example").await,
+                    Err(KrillError::MemberNotFound(_))
+                ));
+            }
+        })
+    }
+
+    #[test]
+    fn wallets_resolve_to_members() {
+        smol::block_on(async {
+            for storage in test_storages("MemberWallets").await {
+                let wallet = bs58::encode([7u8; 32]).into_string();
+                let wallet_holder = Holder::new_with_wallet(&wallet).set_member();
+
+                let (token, _) = storage
+                    .invite_member("ada@krill.example", "Ada", UserRole::Member, &admin())
+                    .await
+                    .unwrap();
+                storage
+                    .invite_member("bob@krill.example", "Bob", UserRole::Member, &admin())
+                    .await
+                    .unwrap();
+
+                assert_eq!(
+                    storage.wallet_member(&wallet).await.err(),
+                    Some(KrillError::SiwsWalletNotLinked)
+                );
+                assert!(storage
+                    .link_member_wallet("ada@krill.example", "NotAWallet")
+                    .await
+                    .is_err());
+
+                storage
+                    .link_member_wallet("ada@krill.example", &wallet)
+                    .await
+                    .unwrap();
+                assert_eq!(
+                    storage
+                        .link_member_wallet("bob@krill.example", &wallet)
+                        .await
+                        .err(),
+                    Some(KrillError::WalletAlreadyLinked(wallet.clone()))
+                );
+
+                // Invited members cannot sign in until they accept the invitation
+                assert_eq!(
+                    storage.wallet_member(&wallet).await.err(),
+                    Some(KrillError::MemberNotActive)
+                );
+                storage.accept_member_invitation(&token).await.unwrap();
+
+                let member = storage.wallet_member(&wallet).await.unwrap();
+                assert_eq!(member.email, "ada@krill.example");
+                storage
+                    .check_member_not_suspended(&wallet_holder)
+                    .await
+                    .unwrap();
+
+                storage
+                    .set_member_suspended("ada@krill.example", true)
+                    .await
+                    .unwrap();
+                assert_eq!(
+                    storage
+                        .check_member_not_suspended(&wallet_holder)
+                        .await
+                        .err(),
+                    Some(KrillError::MemberSuspended)
+                );
+                storage
+                    .set_member_suspended("ada@krill.example", false)
+                    .await
+                    .unwrap();
+
+                storage
+                    .unlink_member_wallet("ada@krill.example", &wallet)
+                    .await
+                    .unwrap();
+                assert_eq!(
+                    storage
+                        .check_member_not_suspended(&wallet_holder)
+                        .await
+                        .err(),
+                    Some(KrillError::SiwsWalletNotLinked)
+                );
+
+                // Holders without an email address or a wallet have no member profile
+                assert_eq!(
+                    storage
+                        .check_member_not_suspended(&Holder::default())
+                        .await
+                        .err(),
+                    Some(KrillError::InvalidAuthToken)
+                );
+
+                assert!(storage
+                    .set_member_frost_identifier("ada@krill.example", Some(vec![1u8; 3]))
+                    .await
+                    .is_err());
+                let member = storage
+                    .set_member_frost_identifier("ada@krill.example", Some(vec![1u8; 32]))
+                    .await
+                    .unwrap();
+                assert_eq!(member.frost_identifier, Some(vec![1u8; 32]));
+                let member = storage
+                    .set_member_frost_identifier("ada@krill.example", None)
+                    .await
+                    .unwrap();
+                assert!(member.frost_identifier.is_none());
+            }
+        })
+    }
+}
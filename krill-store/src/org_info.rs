@@ -1,125 +1,482 @@
-use krill_common::{KrillError, KrillResult, OrganizationInfo};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use bitcode::{Decode, Encode};
+use krill_common::{
+    Holder, KrillError, KrillResult, MailProvider, OrganizationInfo, TokenCategoryPreset,
+};
+use tai64::Tai64N;
+
+use crate::{KrillStorage, Record, Table};
+
+/// The organization details that can be changed after the organization was created
+#[derive(Debug, PartialEq, Eq, Clone, Encode, Decode)]
+pub struct OrgSettings {
+    pub org_info: OrganizationInfo,
+    pub mail: MailProvider,
+    pub api_key: String,
+    pub fqdn: String,
+}
+
+/// Settings that were replaced, kept so they can be rolled back
+#[derive(Debug, PartialEq, Eq, Clone, Encode, Decode)]
+pub struct OrgSettingsRevision {
+    pub version: u64,
+    pub settings: OrgSettings,
+    /// The identity of the administrator who replaced the settings
+    pub replaced_by: String,
+    pub replaced: [u8; Tai64N::BYTE_SIZE],
+}
+
+impl OrgSettingsRevision {
+    /// Seconds since the UNIX epoch
+    pub fn unix_timestamp(&self) -> u64 {
+        Tai64N::from_slice(&self.replaced)
+            .map(|timestamp| timestamp.to_system_time())
+            .unwrap_or(SystemTime::UNIX_EPOCH)
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_secs())
+            .unwrap_or_default()
+    }
+}
+
+/// The algorithm of a [DkimKey]
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Encode, Decode)]
+pub enum DkimAlgorithm {
+    Rsa,
+    Ed25519,
+}
 
-use crate::KrillStorage;
+impl DkimAlgorithm {
+    /// The key type in the DNS record, `k=` of `v=DKIM1`
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Rsa => "rsa",
+            Self::Ed25519 => "ed25519",
+        }
+    }
+}
+
+/// The key outgoing emails are signed with. The public key is published in DNS as
+/// `<selector>._domainkey.<domain>`.
+#[derive(PartialEq, Eq, Clone, Encode, Decode)]
+pub struct DkimKey {
+    pub algorithm: DkimAlgorithm,
+    pub selector: String,
+    pub domain: String,
+    /// A PKCS#1 PEM for RSA and the base64 encoded secret key for Ed25519
+    pub private_key: String,
+}
+
+impl std::fmt::Debug for DkimKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DkimKey")
+            .field("algorithm", &self.algorithm)
+            .field("selector", &self.selector)
+            .field("domain", &self.domain)
+            .field("private_key", &"Redacted")
+            .finish()
+    }
+}
 
 impl KrillStorage {
     pub const KEYSPACE_ORG_INFO: &str = "AllOrgDetails";
+    pub(crate) const KEYSPACE_ORG_SETTINGS_HISTORY: &str = "OrgSettingsHistory";
 
     pub const ORG_INFO_KEY: &str = "OrganizationInfo";
-    pub const SMTPS_KEY: &str = "SMTPs";
+    pub const MAIL_PROVIDER_KEY: &str = "MailProvider";
+    /// The baseline release stored the SMTP settings as a URI under this key
+    pub const LEGACY_SMTPS_KEY: &str = "SMTPs";
     pub const ORG_DOMAIN_NAME: &str = "FQDN";
     pub const SOLANA_API_KEY_INFO: &str = "API_KEY_SOLANA";
+    pub const DKIM_KEY_INFO: &str = "DKIM";
+    pub const TOKEN_CATEGORY_PRESETS_KEY: &str = "TokenCategoryPresets";
+
+    pub(crate) const ORG_INFO: Record<OrganizationInfo> =
+        Record::new(Self::KEYSPACE_ORG_INFO, Self::ORG_INFO_KEY);
+    const MAIL_PROVIDER: Record<MailProvider> =
+        Record::new(Self::KEYSPACE_ORG_INFO, Self::MAIL_PROVIDER_KEY);
+    const DOMAIN_NAME: Record<String> = Record::new(Self::KEYSPACE_ORG_INFO, Self::ORG_DOMAIN_NAME);
+    const SOLANA_API_KEY: Record<String> =
+        Record::new(Self::KEYSPACE_ORG_INFO, Self::SOLANA_API_KEY_INFO);
+    const DKIM_KEY: Record<DkimKey> = Record::new(Self::KEYSPACE_ORG_INFO, Self::DKIM_KEY_INFO);
+    const TOKEN_CATEGORY_PRESETS: Record<Vec<TokenCategoryPreset>> =
+        Record::new(Self::KEYSPACE_ORG_INFO, Self::TOKEN_CATEGORY_PRESETS_KEY);
+    const ORG_SETTINGS_HISTORY: Table<u64, OrgSettingsRevision> =
+        Table::new(Self::KEYSPACE_ORG_SETTINGS_HISTORY);
 
     pub async fn set_all_org_details(
         &self,
         org_info: OrganizationInfo,
-        smtps_uri: &str,
+        mail: &MailProvider,
         api_key: &str,
         fqdn: &str,
     ) -> KrillResult<()> {
-        let keyspace = self.org_info_keyspace();
-
-        let mut kvs = Vec::<(String, Vec<u8>)>::default();
-        kvs.push((Self::ORG_INFO_KEY.to_string(), bitcode::encode(&org_info)));
-        kvs.push((Self::SMTPS_KEY.to_string(), bitcode::encode(&smtps_uri)));
-        kvs.push((Self::ORG_DOMAIN_NAME.to_string(), bitcode::encode(&fqdn)));
-        kvs.push((
-            Self::SOLANA_API_KEY_INFO.to_string(),
-            bitcode::encode(&api_key),
-        ));
+        let mut tx = self.transaction();
+        tx.put_record(&Self::ORG_INFO, &org_info)?
+            .put_record(&Self::MAIL_PROVIDER, mail)?
+            .put_record(&Self::DOMAIN_NAME, &fqdn.to_string())?
+            .put_record(&Self::SOLANA_API_KEY, &api_key.to_string())?;
 
-        self.set_many_encoded(keyspace, kvs).await
+        tx.commit().await
     }
 
     pub async fn set_org_info(&self, org_info: OrganizationInfo) -> KrillResult<()> {
-        let keyspace = self.org_info_keyspace();
-
-        self.set(keyspace, Self::ORG_INFO_KEY, org_info).await
+        Self::ORG_INFO.put(self, &org_info).await
     }
 
+    /// Replaces the organization information once the organization was created
     pub async fn update_org_info(&self, org_info: OrganizationInfo) -> KrillResult<()> {
-        let keyspace = self.org_info_keyspace();
-        let org_info_exists = self.get_org_info().await?;
-
-        if !org_info_exists.name.is_empty() {
-            return Err(KrillError::Store(
-                "Organization Not Found exists".to_string(),
-            ));
+        if Self::ORG_INFO.get(self).await?.is_none() {
+            return Err(KrillError::Store("Organization not found".to_string()));
         }
 
-        self.set(keyspace, Self::ORG_INFO_KEY, org_info).await
+        Self::ORG_INFO.put(self, &org_info).await
     }
 
     pub async fn get_org_info(&self) -> KrillResult<OrganizationInfo> {
-        let bytes = self.get_org_info_bytes().await?;
-
-        bitcode::decode(&bytes).or(Err(KrillError::UnableToDeserializeBrandingData))
-    }
-
-    pub async fn get_org_info_bytes(&self) -> KrillResult<Vec<u8>> {
-        let keyspace = self.org_info_keyspace();
-
-        match self.get(keyspace, Self::ORG_INFO_KEY).await? {
-            Some(bytes) => Ok(bytes),
+        match Self::ORG_INFO.get(self).await? {
+            Some(org_info) => Ok(org_info),
             None => {
                 let info = OrganizationInfo::default();
 
                 self.set_org_info(info.clone()).await?;
 
-                Ok(bitcode::encode(&info))
+                Ok(info)
             }
         }
     }
 
-    pub async fn set_smtps_uri(&self, smtps_uri: &str) -> KrillResult<()> {
-        let keyspace = self.org_info_keyspace();
+    pub async fn get_org_info_bytes(&self) -> KrillResult<Vec<u8>> {
+        self.get_org_info()
+            .await
+            .map(|org_info| bitcode::encode(&org_info))
+    }
+
+    pub async fn set_mail_provider(&self, mail: &MailProvider) -> KrillResult<()> {
+        Self::MAIL_PROVIDER.put(self, mail).await
+    }
+
+    pub async fn get_mail_provider(&self) -> KrillResult<Option<MailProvider>> {
+        Self::MAIL_PROVIDER.get(self).await
+    }
+
+    pub async fn set_solana_api_key(&self, api_key: &str) -> KrillResult<()> {
+        Self::SOLANA_API_KEY.put(self, &api_key.to_string()).await
+    }
 
-        self.set(keyspace, Self::SMTPS_KEY, smtps_uri).await
+    pub async fn get_solana_api_key(&self) -> KrillResult<Option<String>> {
+        Self::SOLANA_API_KEY.get(self).await
     }
 
-    pub async fn get_smtps_uri(&self) -> KrillResult<Option<String>> {
-        let keyspace = self.org_info_keyspace();
+    pub async fn set_fqdn(&self, fqdn: &str) -> KrillResult<()> {
+        Self::DOMAIN_NAME.put(self, &fqdn.to_string()).await
+    }
 
-        let bytes = self.get(keyspace, Self::SMTPS_KEY).await?;
+    pub async fn get_fqdn(&self) -> KrillResult<Option<String>> {
+        Self::DOMAIN_NAME.get(self).await
+    }
 
-        bytes
-            .map(|bytes| bitcode::decode::<String>(&bytes).or(Err(KrillError::UnableToDecodeSmtps)))
-            .transpose()
+    pub async fn set_dkim_key(&self, key: &DkimKey) -> KrillResult<()> {
+        Self::DKIM_KEY.put(self, key).await
     }
 
-    pub async fn set_solana_api_key(&self, api_key: &str) -> KrillResult<()> {
-        let keyspace = self.org_info_keyspace();
+    pub async fn get_dkim_key(&self) -> KrillResult<Option<DkimKey>> {
+        Self::DKIM_KEY.get(self).await
+    }
 
-        self.set(keyspace, Self::SOLANA_API_KEY_INFO, api_key).await
+    /// Stops signing outgoing emails
+    pub async fn remove_dkim_key(&self) -> KrillResult<()> {
+        Self::DKIM_KEY.delete(self).await
     }
 
-    pub async fn get_solana_api_key(&self) -> KrillResult<Option<String>> {
-        let keyspace = self.org_info_keyspace();
+    pub async fn set_token_category_presets(
+        &self,
+        presets: &[TokenCategoryPreset],
+    ) -> KrillResult<()> {
+        TokenCategoryPreset::validate_all(presets)?;
 
-        let bytes = self.get(keyspace, Self::SOLANA_API_KEY_INFO).await?;
+        Self::TOKEN_CATEGORY_PRESETS
+            .put(self, &presets.to_vec())
+            .await
+    }
 
-        bytes
-            .map(|bytes| {
-                bitcode::decode::<String>(&bytes).or(Err(KrillError::UnableToDecodeSolanaApiKey))
-            })
-            .transpose()
+    /// Empty until the organization defines its categories
+    pub async fn token_category_presets(&self) -> KrillResult<Vec<TokenCategoryPreset>> {
+        Self::TOKEN_CATEGORY_PRESETS
+            .get(self)
+            .await
+            .map(Option::unwrap_or_default)
     }
 
-    pub async fn set_fqdn(&self, fqdn: &str) -> KrillResult<()> {
-        let keyspace = self.org_info_keyspace();
+    pub async fn org_settings(&self) -> KrillResult<OrgSettings> {
+        let missing = |name: &str| KrillError::Store(format!("{name} is not set"));
+
+        Ok(OrgSettings {
+            org_info: Self::ORG_INFO
+                .get(self)
+                .await?
+                .ok_or(missing("The organization information"))?,
+            mail: self
+                .get_mail_provider()
+                .await?
+                .ok_or(missing("The mail provider"))?,
+            api_key: self
+                .get_solana_api_key()
+                .await?
+                .ok_or(missing("The RPC API key"))?,
+            fqdn: self.get_fqdn().await?.ok_or(missing("The domain name"))?,
+        })
+    }
 
-        self.set(keyspace, Self::ORG_DOMAIN_NAME, fqdn).await
+    /// Replaces the settings and keeps the previous ones in the history.
+    /// Returns the version the previous settings were kept as.
+    pub async fn update_org_settings(
+        &self,
+        settings: &OrgSettings,
+        replaced_by: &Holder,
+    ) -> KrillResult<u64> {
+        let settings = settings.clone();
+        let replaced_by = replaced_by.identity().to_string();
+
+        // Concurrent updates would otherwise keep the same previous settings or version
+        self.update_tables(move |tables| {
+            let missing = |name: &str| KrillError::Store(format!("{name} is not set"));
+
+            let previous = OrgSettings {
+                org_info: tables
+                    .get_record(&Self::ORG_INFO)?
+                    .ok_or(missing("The organization information"))?,
+                mail: tables
+                    .get_record(&Self::MAIL_PROVIDER)?
+                    .ok_or(missing("The mail provider"))?,
+                api_key: tables
+                    .get_record(&Self::SOLANA_API_KEY)?
+                    .ok_or(missing("The RPC API key"))?,
+                fqdn: tables
+                    .get_record(&Self::DOMAIN_NAME)?
+                    .ok_or(missing("The domain name"))?,
+            };
+
+            let version = tables
+                .last(&Self::ORG_SETTINGS_HISTORY)?
+                .map(|(version, _)| version + 1)
+                .unwrap_or(1);
+
+            let revision = OrgSettingsRevision {
+                version,
+                settings: previous,
+                replaced_by: replaced_by.clone(),
+                replaced: Tai64N::now().to_bytes(),
+            };
+
+            tables
+                .put(&Self::ORG_SETTINGS_HISTORY, &version, &revision)?
+                .put_record(&Self::ORG_INFO, &settings.org_info)?
+                .put_record(&Self::MAIL_PROVIDER, &settings.mail)?
+                .put_record(&Self::DOMAIN_NAME, &settings.fqdn)?
+                .put_record(&Self::SOLANA_API_KEY, &settings.api_key)?;
+
+            Ok(version)
+        })
+        .await
     }
 
-    pub async fn get_fqdn(&self) -> KrillResult<Option<String>> {
-        let keyspace = self.org_info_keyspace();
+    /// The replaced settings, newest first
+    pub async fn org_settings_history(&self) -> KrillResult<Vec<OrgSettingsRevision>> {
+        Ok(Self::ORG_SETTINGS_HISTORY
+            .scan(self)
+            .await?
+            .into_iter()
+            .rev()
+            .map(|(_, revision)| revision)
+            .collect())
+    }
+
+    pub async fn org_settings_revision(&self, version: u64) -> KrillResult<OrgSettingsRevision> {
+        Self::ORG_SETTINGS_HISTORY
+            .get(self, &version)
+            .await?
+            .ok_or(KrillError::OrgSettingsRevisionNotFound(version))
+    }
+
+    /// Restores the settings of `version`. The settings being replaced are kept
+    /// in the history so the rollback can be undone.
+    pub async fn rollback_org_settings(
+        &self,
+        version: u64,
+        replaced_by: &Holder,
+    ) -> KrillResult<OrgSettings> {
+        let revision = self.org_settings_revision(version).await?;
 
-        let bytes = self.get(keyspace, Self::ORG_DOMAIN_NAME).await?;
+        self.update_org_settings(&revision.settings, replaced_by)
+            .await?;
 
-        bytes
-            .map(|bytes| {
-                bitcode::decode::<String>(&bytes).or(Err(KrillError::UnableToOrgDomainName))
-            })
-            .transpose()
+        Ok(revision.settings)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use krill_common::{MailApiConfig, MailApiProvider, SmtpConfig};
+
+    use crate::test_storages;
+
+    #[test]
+    fn org_details() {
+        smol::block_on(async {
+            for storage in test_storages("OrgInfo").await {
+                // Only an existing organization can be updated
+                assert!(storage
+                    .update_org_info(OrganizationInfo::default())
+                    .await
+                    .is_err());
+                assert!(storage.org_settings().await.is_err());
+
+                assert_eq!(
+                    storage.get_org_info().await.unwrap(),
+                    OrganizationInfo::default()
+                );
+                assert!(storage.get_mail_provider().await.unwrap().is_none());
+
+                let org_info = OrganizationInfo {
+                    name: "Krill Org".to_string(),
+                    support_mail: "support@krill.example".to_string(),
+                    ..Default::default()
+                };
+
+                let mail =
+                    MailProvider::Smtp(SmtpConfig::new("mail.krill.example", "krill", "password"));
+                storage
+                    .set_all_org_details(org_info.clone(), &mail, "solana-api-key", "krill.example")
+                    .await
+                    .unwrap();
+
+                assert_eq!(storage.get_org_info().await.unwrap(), org_info);
+                assert_eq!(storage.get_mail_provider().await.unwrap(), Some(mail));
+
+                let api = MailProvider::Api(MailApiConfig::new(
+                    MailApiProvider::Postmark,
+                    "server-token",
+                ));
+                storage.set_mail_provider(&api).await.unwrap();
+                assert_eq!(storage.get_mail_provider().await.unwrap(), Some(api));
+                assert_eq!(
+                    storage.get_solana_api_key().await.unwrap().as_deref(),
+                    Some("solana-api-key")
+                );
+                assert_eq!(
+                    storage.get_fqdn().await.unwrap().as_deref(),
+                    Some("krill.example")
+                );
+
+                let renamed = OrganizationInfo {
+                    name: "Krill Collective".to_string(),
+                    ..org_info.clone()
+                };
+                storage.update_org_info(renamed.clone()).await.unwrap();
+                assert_eq!(storage.get_org_info().await.unwrap(), renamed);
+
+                storage.set_fqdn("members.krill.example").await.unwrap();
+                assert_eq!(
+                    storage.get_fqdn().await.unwrap().as_deref(),
+                    Some("members.krill.example")
+                );
+
+                assert!(storage.get_dkim_key().await.unwrap().is_none());
+                let dkim_key = DkimKey {
+                    algorithm: DkimAlgorithm::Ed25519,
+                    selector: "krill".to_string(),
+                    domain: "krill.example".to_string(),
+                    private_key: "c2VjcmV0".to_string(),
+                };
+                storage.set_dkim_key(&dkim_key).await.unwrap();
+                assert_eq!(
+                    storage.get_dkim_key().await.unwrap(),
+                    Some(dkim_key.clone())
+                );
+                assert!(!format!("{dkim_key:?}").contains("c2VjcmV0"));
+                storage.remove_dkim_key().await.unwrap();
+                assert!(storage.get_dkim_key().await.unwrap().is_none());
+            }
+        })
+    }
+
+    #[test]
+    fn org_settings_history() {
+        smol::block_on(async {
+            for storage in test_storages("OrgSettings").await {
+                let original = OrgSettings {
+                    org_info: OrganizationInfo {
+                        name: "Krill Org".to_string(),
+                        support_mail: "support@krill.example".to_string(),
+                        ..Default::default()
+                    },
+                    mail: SmtpConfig::new("mail.krill.example", "krill", "password").into(),
+                    api_key: "solana-api-key".to_string(),
+                    fqdn: "krill.example".to_string(),
+                };
+                storage
+                    .set_all_org_details(
+                        original.org_info.clone(),
+                        &original.mail,
+                        &original.api_key,
+                        &original.fqdn,
+                    )
+                    .await
+                    .unwrap();
+                assert_eq!(storage.org_settings().await.unwrap(), original);
+
+                let admin = Holder::new_with_tld("admin@krill.example").unwrap();
+
+                let mut changed = original.clone();
+                changed.org_info.name = "Krill Collective".to_string();
+                changed.api_key = "rotated-api-key".to_string();
+                assert_eq!(
+                    storage.update_org_settings(&changed, &admin).await.unwrap(),
+                    1
+                );
+                assert_eq!(storage.org_settings().await.unwrap(), changed);
+
+                let history = storage.org_settings_history().await.unwrap();
+                assert_eq!(history.len(), 1);
+                assert_eq!(history[0].settings, original);
+                assert_eq!(history[0].replaced_by, "admin@krill.example");
+
+                assert_eq!(
+                    storage.rollback_org_settings(1, &admin).await.unwrap(),
+                    original
+                );
+                assert_eq!(storage.org_settings().await.unwrap(), original);
+
+                // The rollback itself is recorded so it can be undone
+                let history = storage.org_settings_history().await.unwrap();
+                assert_eq!(
+                    history
+                        .iter()
+                        .map(|revision| revision.version)
+                        .collect::<Vec<_>>(),
+                    [2, 1]
+                );
+                assert_eq!(history[0].settings, changed);
+
+                assert_eq!(
+                    storage.rollback_org_settings(7, &admin).await.err(),
+                    Some(KrillError::OrgSettingsRevisionNotFound(7))
+                );
+
+                // Concurrent updates each keep the settings they replaced under a new version
+                let (first, second) = smol::future::zip(
+                    storage.update_org_settings(&changed, &admin),
+                    storage.update_org_settings(&original, &admin),
+                )
+                .await;
+                let mut versions = [first.unwrap(), second.unwrap()];
+                versions.sort();
+                assert_eq!(versions, [3, 4]);
+                assert_eq!(storage.org_settings_history().await.unwrap().len(), 4);
+            }
+        })
     }
 }
@@ -0,0 +1,110 @@
+use krill_common::{KrillResult, SiwsChallenge};
+
+use crate::{KrillStorage, Table};
+
+impl KrillStorage {
+    pub(crate) const KEYSPACE_SIWS_CHALLENGES: &str = "SiwsChallenges";
+
+    /// Keyed by the nonce of the challenge
+    const SIWS_CHALLENGES: Table<String, SiwsChallenge> =
+        Table::new(Self::KEYSPACE_SIWS_CHALLENGES);
+
+    pub async fn set_siws_challenge(&self, challenge: SiwsChallenge) -> KrillResult<()> {
+        Self::SIWS_CHALLENGES
+            .put(self, &challenge.nonce, &challenge)
+            .await
+    }
+
+    /// Removes the challenge so that a nonce can only be used once.
+    /// Expired challenges are removed and `None` is returned.
+    pub async fn take_siws_challenge(&self, nonce: &str) -> KrillResult<Option<SiwsChallenge>> {
+        let nonce = nonce.to_string();
+
+        // Read and removed in one update so concurrent sign-ins cannot both take it
+        let challenge = self
+            .update_tables(move |tables| {
+                let challenge = tables.get(&Self::SIWS_CHALLENGES, &nonce)?;
+
+                if challenge.is_some() {
+                    tables.delete(&Self::SIWS_CHALLENGES, &nonce);
+                }
+
+                Ok(challenge)
+            })
+            .await?;
+
+        Ok(challenge.filter(|value| !value.is_expired()))
+    }
+
+    /// Removes the challenges that expired without being signed and returns how many
+    pub async fn prune_siws_challenges(&self) -> KrillResult<usize> {
+        let mut tx = self.transaction();
+        let mut pruned = 0;
+
+        for (nonce, challenge) in Self::SIWS_CHALLENGES.scan(self).await? {
+            if challenge.is_expired() {
+                tx.delete(&Self::SIWS_CHALLENGES, &nonce)?;
+                pruned += 1;
+            }
+        }
+
+        tx.commit().await?;
+
+        Ok(pruned)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use krill_common::SiwsMessage;
+    use tai64::Tai64N;
+
+    use super::*;
+
+    use crate::test_storages;
+
+    #[test]
+    fn expired_challenges_are_pruned() {
+        smol::block_on(async {
+            for storage in test_storages("SiwsChallenges").await {
+                let address = bs58::encode([7u8; 32]).into_string();
+                let message = SiwsMessage::new("krill.example", &address);
+
+                let pending = SiwsChallenge::new(&message, "pending-nonce");
+                let mut expired = SiwsChallenge::new(&message, "expired-nonce");
+                expired.timestamp = (Tai64N::now() - Duration::from_secs(10 * 60)).to_bytes();
+
+                storage.set_siws_challenge(pending.clone()).await.unwrap();
+                storage.set_siws_challenge(expired).await.unwrap();
+
+                assert_eq!(storage.prune_siws_challenges().await.unwrap(), 1);
+                assert_eq!(storage.prune_siws_challenges().await.unwrap(), 0);
+
+                assert_eq!(
+                    storage.take_siws_challenge("pending-nonce").await.unwrap(),
+                    Some(pending)
+                );
+                assert_eq!(
+                    storage.take_siws_challenge("pending-nonce").await.unwrap(),
+                    None
+                );
+                assert_eq!(
+                    storage.take_siws_challenge("expired-nonce").await.unwrap(),
+                    None
+                );
+
+                // Only one of concurrent sign-ins with the same nonce gets the challenge
+                let contested = SiwsChallenge::new(&message, "contested-nonce");
+                storage.set_siws_challenge(contested).await.unwrap();
+                let (first, second) = smol::future::zip(
+                    storage.take_siws_challenge("contested-nonce"),
+                    storage.take_siws_challenge("contested-nonce"),
+                )
+                .await;
+                assert!(first.unwrap().is_some() != second.unwrap().is_some());
+            }
+        })
+    }
+}
@@ -1,232 +1,444 @@
 use core::fmt;
+use std::sync::Arc;
 
 use bitcode::{Decode, Encode};
-use camino::Utf8PathBuf;
-use fjall::{
-    KeyspaceCreateOptions, PersistMode, Readable, SingleWriterTxDatabase, SingleWriterTxKeyspace,
-};
-use krill_common::{KrillResult, KrillUtils};
+use camino::{Utf8Path, Utf8PathBuf};
+use krill_common::{KrillError, KrillResult, KrillUtils};
+
+use crate::{FjallBackend, KeyDerivation, KrillCipher, MemoryBackend, StorageBackend};
+
+/// The engines [KrillStorage] can run on
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub enum StorageBackendKind {
+    /// On disk in the data directory
+    #[default]
+    Fjall,
+    /// Lost when the server stops
+    Memory,
+}
+
+impl StorageBackendKind {
+    pub fn parse(value: &str) -> KrillResult<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "fjall" => Ok(Self::Fjall),
+            "memory" => Ok(Self::Memory),
+            _ => Err(KrillError::InvalidStorageOptions(format!(
+                "Unknown storage backend `{value}`, expected `fjall` or `memory`"
+            ))),
+        }
+    }
+}
+
+/// Where and how [KrillStorage::init] opens the storage.
+/// Command line flags take precedence over environment variables.
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct StorageOptions {
+    pub backend: StorageBackendKind,
+    /// Defaults to the Krill directory in the home directory
+    pub data_dir: Option<Utf8PathBuf>,
+}
+
+impl StorageOptions {
+    pub const DATA_DIR_ENV: &str = "KRILL_DATA_DIR";
+    /// `--data-dir <dir>`
+    pub const DATA_DIR_FLAG: &str = "--data-dir";
+    pub const BACKEND_ENV: &str = "KRILL_STORAGE_BACKEND";
+    /// `--storage-backend <fjall|memory>`
+    pub const BACKEND_FLAG: &str = "--storage-backend";
+
+    /// Reads the options of the running process
+    pub fn from_env_and_args() -> KrillResult<Self> {
+        Self::parse(std::env::args(), |name| std::env::var(name).ok())
+    }
+
+    pub fn parse(
+        args: impl IntoIterator<Item = String>,
+        env: impl Fn(&str) -> Option<String>,
+    ) -> KrillResult<Self> {
+        let mut data_dir = env(Self::DATA_DIR_ENV);
+        let mut backend = env(Self::BACKEND_ENV);
+
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let target = match arg.as_str() {
+                Self::DATA_DIR_FLAG => &mut data_dir,
+                Self::BACKEND_FLAG => &mut backend,
+                _ => continue,
+            };
+
+            let value = args
+                .next()
+                .ok_or(KrillError::InvalidStorageOptions(format!(
+                    "`{arg}` requires a value"
+                )))?;
+
+            target.replace(value);
+        }
+
+        Ok(Self {
+            backend: backend
+                .as_deref()
+                .map(StorageBackendKind::parse)
+                .transpose()?
+                .unwrap_or_default(),
+            data_dir: data_dir
+                .filter(|dir| !dir.trim().is_empty())
+                .map(Utf8PathBuf::from),
+        })
+    }
+
+    /// The configured data directory or the Krill directory
+    pub async fn data_dir(&self) -> KrillResult<Utf8PathBuf> {
+        match self.data_dir.as_ref() {
+            Some(data_dir) => Ok(data_dir.clone()),
+            None => KrillUtils::krill_dir().await,
+        }
+    }
+}
 
 pub struct KrillStorage {
-    store: SingleWriterTxDatabase,
-    auth_tokens: SingleWriterTxKeyspace,
-    org_info: SingleWriterTxKeyspace,
-    app_state: SingleWriterTxKeyspace,
+    backend: Arc<dyn StorageBackend>,
+    /// The directory of the storage when it is stored on disk
+    pub(crate) path: Option<Utf8PathBuf>,
+    pub(crate) cipher: Option<KrillCipher>,
+    pub(crate) key_derivation: KeyDerivation,
 }
 
 impl KrillStorage {
+    /// Every keyspace of the storage, backends are opened with these
+    pub const KEYSPACES: [&str; 12] = [
+        Self::KEYSPACE_AUTH_TOKENS,
+        Self::KEYSPACE_ORG_INFO,
+        Self::KEYSPACE_APP_STATE,
+        Self::KEYSPACE_SIWS_CHALLENGES,
+        Self::KEYSPACE_TOTP,
+        Self::KEYSPACE_AUDIT_LOG,
+        Self::KEYSPACE_MEMBERS,
+        Self::KEYSPACE_MEMBER_INVITATIONS,
+        Self::KEYSPACE_ORG_SETTINGS_HISTORY,
+        Self::KEYSPACE_TENANTS,
+        Self::KEYSPACE_MAIL_QUEUE,
+        Self::KEYSPACE_META,
+    ];
+
+    /// Opens the storage with the options of the command line and the environment
     pub async fn init() -> KrillResult<Self> {
-        let mut path = KrillUtils::krill_dir().await?;
-        path.push("KrillAppStorage");
+        Self::open(&StorageOptions::from_env_and_args()?).await
+    }
+
+    pub async fn open(options: &StorageOptions) -> KrillResult<Self> {
+        match options.backend {
+            StorageBackendKind::Fjall => {
+                let path = options.data_dir().await?.join(Self::STORAGE_DIR);
 
-        KrillUtils::create_recursive_dir(&path).await?;
+                KrillUtils::create_recursive_dir(&path).await?;
 
-        Self::init_db(path).await
+                Self::init_db(path).await
+            }
+            StorageBackendKind::Memory => Ok(Self::init_memory()),
+        }
     }
 
+    /// The storage is opened locked, see [KrillStorage::unlock]
     pub async fn init_db(path: Utf8PathBuf) -> KrillResult<Self> {
         blocking::unblock(move || {
-            let store = SingleWriterTxDatabase::builder(path).open()?;
-
-            let auth_tokens = store.keyspace(Self::KEYSPACE_AUTH_TOKENS, || {
-                KeyspaceCreateOptions::default()
-            })?;
+            let backend = FjallBackend::open(&path, &Self::KEYSPACES)?;
 
-            #[allow(clippy::redundant_closure)]
-            let org_info =
-                store.keyspace(Self::KEYSPACE_ORG_INFO, || KeyspaceCreateOptions::default())?;
+            let mut storage = Self::with_backend(Arc::new(backend));
+            storage.path.replace(path);
 
-            let app_state = store.keyspace(Self::KEYSPACE_APP_STATE, || {
-                KeyspaceCreateOptions::default()
-            })?;
-
-            Ok(Self {
-                store,
-                auth_tokens,
-                org_info,
-                app_state,
-            })
+            Ok(storage)
         })
         .await
     }
 
-    pub fn db(&self) -> fjall::SingleWriterTxDatabase {
-        self.store.clone()
+    /// An empty storage that only lives in memory
+    pub fn init_memory() -> Self {
+        Self::with_backend(Arc::new(MemoryBackend::new(&Self::KEYSPACES)))
+    }
+
+    /// Opens the storage on any backend that has all of [Self::KEYSPACES]
+    pub fn with_backend(backend: Arc<dyn StorageBackend>) -> Self {
+        Self {
+            backend,
+            path: None,
+            cipher: None,
+            key_derivation: KeyDerivation::ARGON2ID_DEFAULT,
+        }
+    }
+
+    pub fn backend(&self) -> Arc<dyn StorageBackend> {
+        self.backend.clone()
+    }
+
+    /// The data directory holding the storage directory, `None` when it is not on disk
+    pub fn data_dir(&self) -> Option<&Utf8Path> {
+        self.path.as_deref().and_then(|path| path.parent())
     }
 
     pub async fn set(
         &self,
-        keyspace: fjall::SingleWriterTxKeyspace,
+        keyspace: &'static str,
         key: impl AsRef<[u8]> + Send + 'static,
         value: impl Encode + Decode<'_>,
     ) -> KrillResult<()> {
-        let db = self.db();
-
-        let value = bitcode::encode(&value);
+        let value = self.encode_value(keyspace, key.as_ref(), &bitcode::encode(&value))?;
 
-        blocking::unblock(move || {
-            let mut tx = db.write_tx();
-
-            tx.insert(&keyspace, key.as_ref(), &value);
-
-            tx.commit()?;
-
-            db.persist(PersistMode::SyncAll)?;
-
-            Ok(())
-        })
-        .await
+        self.commit(vec![(keyspace, key.as_ref().to_vec(), Some(value))])
+            .await
     }
 
     pub async fn set_many(
         &self,
-        keyspace: fjall::SingleWriterTxKeyspace,
+        keyspace: &'static str,
         kvs: Vec<(
             impl AsRef<[u8]> + Send + 'static,
             impl Encode + Decode<'_> + Send + 'static,
         )>,
     ) -> KrillResult<()> {
-        let db = self.db();
-
-        blocking::unblock(move || {
-            let mut tx = db.write_tx();
-
-            for (key, value) in kvs {
-                tx.insert(&keyspace, key.as_ref(), bitcode::encode(&value));
-            }
-
-            tx.commit()?;
+        let writes = kvs
+            .into_iter()
+            .map(|(key, value)| {
+                let value = self.encode_value(keyspace, key.as_ref(), &bitcode::encode(&value))?;
 
-            db.persist(PersistMode::SyncAll)?;
+                Ok((keyspace, key.as_ref().to_vec(), Some(value)))
+            })
+            .collect::<KrillResult<Vec<_>>>()?;
 
-            Ok(())
-        })
-        .await
+        self.commit(writes).await
     }
 
     pub async fn set_many_with_keyspaces_and_encoded(
         &self,
-        data: Vec<(
-            fjall::SingleWriterTxKeyspace,
-            impl AsRef<[u8]> + Send + 'static,
-            Vec<u8>,
-        )>,
+        data: Vec<(&'static str, impl AsRef<[u8]> + Send + 'static, Vec<u8>)>,
     ) -> KrillResult<()> {
-        let db = self.db();
-
-        blocking::unblock(move || {
-            let mut tx = db.write_tx();
-
-            for (keyspace, key, value) in data {
-                tx.insert(&keyspace, key.as_ref(), value);
-            }
-
-            tx.commit()?;
+        let writes = data
+            .into_iter()
+            .map(|(keyspace, key, value)| {
+                let value = self.encode_value(keyspace, key.as_ref(), &value)?;
 
-            db.persist(PersistMode::SyncAll)?;
+                Ok((keyspace, key.as_ref().to_vec(), Some(value)))
+            })
+            .collect::<KrillResult<Vec<_>>>()?;
 
-            Ok(())
-        })
-        .await
+        self.commit(writes).await
     }
 
     pub async fn set_many_encoded(
         &self,
-        keyspace: fjall::SingleWriterTxKeyspace,
+        keyspace: &'static str,
         kvs: Vec<(impl AsRef<[u8]> + Send + 'static, Vec<u8>)>,
     ) -> KrillResult<()> {
-        let db = self.db();
+        let writes = kvs
+            .into_iter()
+            .map(|(key, value)| {
+                let value = self.encode_value(keyspace, key.as_ref(), &value)?;
 
-        blocking::unblock(move || {
-            let mut tx = db.write_tx();
-
-            for (key, value) in kvs {
-                tx.insert(&keyspace, key.as_ref(), &value);
-            }
-
-            tx.commit()?;
-
-            db.persist(PersistMode::SyncAll)?;
+                Ok((keyspace, key.as_ref().to_vec(), Some(value)))
+            })
+            .collect::<KrillResult<Vec<_>>>()?;
 
-            Ok(())
-        })
-        .await
+        self.commit(writes).await
     }
 
     pub async fn remove(
         &self,
-        keyspace: fjall::SingleWriterTxKeyspace,
+        keyspace: &'static str,
         key: impl AsRef<[u8]> + Send + 'static,
     ) -> KrillResult<()> {
-        let db = self.db();
-
-        blocking::unblock(move || {
-            let mut tx = db.write_tx();
-
-            tx.remove(&keyspace, key.as_ref());
-            tx.commit()?;
-
-            db.persist(PersistMode::SyncAll)?;
-
-            Ok(())
-        })
-        .await
+        self.commit(vec![(keyspace, key.as_ref().to_vec(), None)])
+            .await
     }
 
     pub async fn remove_many(
         &self,
-        keyspace: fjall::SingleWriterTxKeyspace,
+        keyspace: &'static str,
         keys: Vec<impl AsRef<[u8]> + Send + 'static>,
     ) -> KrillResult<()> {
-        let db = self.db();
-
-        blocking::unblock(move || {
-            let mut tx = db.write_tx();
+        let writes = keys
+            .into_iter()
+            .map(|key| (keyspace, key.as_ref().to_vec(), None))
+            .collect();
 
-            for key in keys {
-                tx.remove(&keyspace, key.as_ref());
-            }
-
-            tx.commit()?;
-
-            db.persist(PersistMode::SyncAll)?;
-
-            Ok(())
-        })
-        .await
+        self.commit(writes).await
     }
 
     pub async fn get(
         &self,
-        keyspace: fjall::SingleWriterTxKeyspace,
+        keyspace: &'static str,
         key: impl AsRef<[u8]> + Send + 'static,
     ) -> KrillResult<Option<Vec<u8>>> {
-        let db = self.db();
+        let backend = self.backend();
 
-        Ok(blocking::unblock(move || {
-            let tx = db.read_tx();
+        // Fail early instead of reading values that cannot be decrypted
+        self.cipher()?;
 
-            tx.get(keyspace, key.as_ref())
+        let (key, value) = blocking::unblock(move || {
+            backend
+                .get(keyspace, key.as_ref())
+                .map(|value| (key, value))
         })
-        .await?
-        .map(|data| data.to_vec()))
+        .await?;
+
+        value
+            .map(|data| self.decode_value(keyspace, key.as_ref(), &data))
+            .transpose()
     }
 
-    pub fn org_info_keyspace(&self) -> SingleWriterTxKeyspace {
-        self.org_info.clone()
+    /// Commits writes that are already in the encrypted envelope
+    pub(crate) async fn commit(&self, writes: crate::StorageWrites) -> KrillResult<()> {
+        let backend = self.backend();
+
+        blocking::unblock(move || backend.commit(writes)).await
+    }
+
+    pub fn org_info_keyspace(&self) -> &'static str {
+        Self::KEYSPACE_ORG_INFO
     }
 
-    pub fn app_state_keyspace(&self) -> SingleWriterTxKeyspace {
-        self.app_state.clone()
+    pub fn app_state_keyspace(&self) -> &'static str {
+        Self::KEYSPACE_APP_STATE
     }
 
-    pub fn auth_tokens_namespace(&self) -> SingleWriterTxKeyspace {
-        self.auth_tokens.clone()
+    pub fn auth_tokens_namespace(&self) -> &'static str {
+        Self::KEYSPACE_AUTH_TOKENS
+    }
+
+    /// All keyspaces holding values in the versioned envelope, every keyspace but the metadata
+    pub(crate) fn data_keyspaces() -> impl Iterator<Item = &'static str> {
+        Self::KEYSPACES
+            .into_iter()
+            .filter(|keyspace| *keyspace != Self::KEYSPACE_META)
     }
 }
 
 impl fmt::Debug for KrillStorage {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "KrillStorage")
+        write!(f, "KrillStorage({})", self.backend.name())
+    }
+}
+
+/// A data directory of the tests, removed with its content when dropped
+#[cfg(test)]
+pub(crate) struct TestDir(tempfile::TempDir);
+
+#[cfg(test)]
+impl TestDir {
+    pub(crate) fn new(name: &str) -> Self {
+        Self(
+            tempfile::Builder::new()
+                .prefix(&format!("KrillStore{name}-"))
+                .tempdir()
+                .unwrap(),
+        )
+    }
+
+    pub(crate) fn path(&self) -> &Utf8Path {
+        Utf8Path::from_path(self.0.path()).unwrap()
+    }
+
+    /// The storage is nested like on a server so the tenants directory is unique too
+    pub(crate) fn storage_path(&self) -> Utf8PathBuf {
+        self.path().join(KrillStorage::STORAGE_DIR)
+    }
+}
+
+/// The storages of [test_storages]. The data directory is kept until they are all consumed.
+#[cfg(test)]
+pub(crate) struct TestStorages {
+    storages: std::vec::IntoIter<KrillStorage>,
+    _dir: TestDir,
+}
+
+#[cfg(test)]
+impl Iterator for TestStorages {
+    type Item = KrillStorage;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.storages.next()
+    }
+}
+
+/// Opens an unlocked storage on every backend, the tests of a module run against each
+#[cfg(test)]
+pub(crate) async fn test_storages(name: &str) -> TestStorages {
+    use crate::UnlockSecret;
+
+    let dir = TestDir::new(name);
+
+    let mut storages = Vec::default();
+    for storage in [
+        KrillStorage::init_db(dir.storage_path()).await.unwrap(),
+        KrillStorage::init_memory(),
+    ] {
+        storages.push(
+            storage
+                .unlock(UnlockSecret::secret([4u8; 32]))
+                .await
+                .unwrap(),
+        );
+    }
+
+    TestStorages {
+        storages: storages.into_iter(),
+        _dir: dir,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn parse(args: &[&str], env: &[(&str, &str)]) -> KrillResult<StorageOptions> {
+        StorageOptions::parse(args.iter().map(|arg| arg.to_string()), |name| {
+            env.iter()
+                .find(|(key, _)| *key == name)
+                .map(|(_, value)| value.to_string())
+        })
+    }
+
+    #[test]
+    fn storage_options() {
+        assert_eq!(
+            parse(&["krill-server"], &[]).unwrap(),
+            StorageOptions::default()
+        );
+
+        let options = parse(
+            &["krill-server", "--data-dir", "/srv/krill"],
+            &[
+                ("KRILL_DATA_DIR", "/var/lib/krill"),
+                ("KRILL_STORAGE_BACKEND", "Memory"),
+            ],
+        )
+        .unwrap();
+        assert_eq!(
+            options.data_dir.as_deref(),
+            Some(Utf8Path::new("/srv/krill"))
+        );
+        assert_eq!(options.backend, StorageBackendKind::Memory);
+
+        assert!(matches!(
+            parse(&["krill-server", "--storage-backend", "sled"], &[]),
+            Err(KrillError::InvalidStorageOptions(_))
+        ));
+        assert!(matches!(
+            parse(&["krill-server", "--data-dir"], &[]),
+            Err(KrillError::InvalidStorageOptions(_))
+        ));
+    }
+
+    #[test]
+    fn memory_backend_is_not_on_disk() {
+        smol::block_on(async {
+            let storage = KrillStorage::init_memory();
+
+            assert!(storage.data_dir().is_none());
+            assert_eq!(format!("{storage:?}"), "KrillStorage(memory)");
+            assert_eq!(
+                storage.get_app_state().await.err(),
+                Some(KrillError::StorageLocked)
+            );
+        })
     }
 }
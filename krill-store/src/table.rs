@@ -0,0 +1,545 @@
+use std::{
+    marker::PhantomData,
+    ops::{Bound, RangeBounds},
+};
+
+use bitcode::{Decode, Encode};
+use krill_common::{KrillError, KrillResult};
+
+use crate::{KrillCipher, KrillStorage, StorageBackend, StorageWrites};
+
+/// Keys of a [Table]. The encoding must sort like the keys so range scans return keys in order.
+pub trait TableKey: Sized {
+    fn encode_key(&self) -> Vec<u8>;
+
+    /// `None` when the bytes are not a key of this type
+    fn decode_key(bytes: &[u8]) -> Option<Self>;
+}
+
+impl TableKey for String {
+    fn encode_key(&self) -> Vec<u8> {
+        self.as_bytes().to_vec()
+    }
+
+    fn decode_key(bytes: &[u8]) -> Option<Self> {
+        String::from_utf8(bytes.to_vec()).ok()
+    }
+}
+
+impl TableKey for Vec<u8> {
+    fn encode_key(&self) -> Vec<u8> {
+        self.clone()
+    }
+
+    fn decode_key(bytes: &[u8]) -> Option<Self> {
+        Some(bytes.to_vec())
+    }
+}
+
+impl<const N: usize> TableKey for [u8; N] {
+    fn encode_key(&self) -> Vec<u8> {
+        self.to_vec()
+    }
+
+    fn decode_key(bytes: &[u8]) -> Option<Self> {
+        bytes.try_into().ok()
+    }
+}
+
+impl TableKey for u64 {
+    /// Big endian so the keys sort numerically
+    fn encode_key(&self) -> Vec<u8> {
+        self.to_be_bytes().to_vec()
+    }
+
+    fn decode_key(bytes: &[u8]) -> Option<Self> {
+        bytes.try_into().ok().map(u64::from_be_bytes)
+    }
+}
+
+/// Binds a keyspace to the type of its keys and values. Values are bitcode
+/// encoded and stored in the encrypted envelope like every other value.
+/// A table owns its keyspace, no record or other table is stored in it.
+pub struct Table<K, V> {
+    keyspace: &'static str,
+    _marker: PhantomData<fn() -> (K, V)>,
+}
+
+impl<K, V> Table<K, V>
+where
+    K: TableKey,
+    V: Encode + for<'a> Decode<'a>,
+{
+    pub const fn new(keyspace: &'static str) -> Self {
+        Self {
+            keyspace,
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.keyspace
+    }
+
+    pub async fn get(&self, storage: &KrillStorage, key: &K) -> KrillResult<Option<V>> {
+        storage
+            .get(self.keyspace, key.encode_key())
+            .await?
+            .map(|bytes| self.decode(&bytes))
+            .transpose()
+    }
+
+    pub async fn put(&self, storage: &KrillStorage, key: &K, value: &V) -> KrillResult<()> {
+        let mut tx = storage.transaction();
+        tx.put(self, key, value)?;
+
+        tx.commit().await
+    }
+
+    pub async fn delete(&self, storage: &KrillStorage, key: &K) -> KrillResult<()> {
+        storage.remove(self.keyspace, key.encode_key()).await
+    }
+
+    /// The entries with keys in `range` in key order
+    pub async fn range(
+        &self,
+        storage: &KrillStorage,
+        range: impl RangeBounds<K>,
+    ) -> KrillResult<Vec<(K, V)>> {
+        let bound = |bound: Bound<&K>| match bound {
+            Bound::Included(key) => Bound::Included(key.encode_key()),
+            Bound::Excluded(key) => Bound::Excluded(key.encode_key()),
+            Bound::Unbounded => Bound::Unbounded,
+        };
+        let range = (bound(range.start_bound()), bound(range.end_bound()));
+
+        let backend = storage.backend();
+        let keyspace = self.keyspace;
+
+        let entries = blocking::unblock(move || backend.range(keyspace, range)).await?;
+
+        self.decode_entries(storage, entries)
+    }
+
+    /// The entries whose encoded key starts with `prefix` in key order
+    pub async fn prefix(
+        &self,
+        storage: &KrillStorage,
+        prefix: impl AsRef<[u8]>,
+    ) -> KrillResult<Vec<(K, V)>> {
+        let backend = storage.backend();
+        let keyspace = self.keyspace;
+        let prefix = prefix.as_ref().to_vec();
+
+        let entries = blocking::unblock(move || backend.prefix(keyspace, &prefix)).await?;
+
+        self.decode_entries(storage, entries)
+    }
+
+    /// Every entry of the table in key order
+    pub async fn scan(&self, storage: &KrillStorage) -> KrillResult<Vec<(K, V)>> {
+        self.range(storage, ..).await
+    }
+
+    fn decode_entries(
+        &self,
+        storage: &KrillStorage,
+        entries: Vec<(Vec<u8>, Vec<u8>)>,
+    ) -> KrillResult<Vec<(K, V)>> {
+        let cipher = storage.cipher()?;
+
+        entries
+            .into_iter()
+            .map(|(key, bytes)| self.decode_entry(cipher, &key, &bytes))
+            .collect()
+    }
+
+    /// Any key of another type is corruption since the table owns its keyspace
+    fn decode_entry(&self, cipher: &KrillCipher, key: &[u8], bytes: &[u8]) -> KrillResult<(K, V)> {
+        let decoded =
+            K::decode_key(key).ok_or(KrillError::TableValueCorrupted(self.keyspace.to_string()))?;
+        let payload = KrillStorage::decode_value_with(cipher, self.keyspace, key, bytes)?;
+
+        Ok((decoded, self.decode(&payload)?))
+    }
+
+    fn decode(&self, bytes: &[u8]) -> KrillResult<V> {
+        bitcode::decode::<V>(bytes).or(Err(KrillError::TableValueCorrupted(
+            self.keyspace.to_string(),
+        )))
+    }
+}
+
+/// A single value stored under a fixed key, like the organization information
+pub struct Record<V> {
+    keyspace: &'static str,
+    key: &'static str,
+    _marker: PhantomData<fn() -> V>,
+}
+
+impl<V> Record<V>
+where
+    V: Encode + for<'a> Decode<'a>,
+{
+    pub const fn new(keyspace: &'static str, key: &'static str) -> Self {
+        Self {
+            keyspace,
+            key,
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn name(&self) -> String {
+        self.keyspace.to_string() + "/" + self.key
+    }
+
+    pub async fn get(&self, storage: &KrillStorage) -> KrillResult<Option<V>> {
+        storage
+            .get(self.keyspace, self.key)
+            .await?
+            .map(|bytes| self.decode(&bytes))
+            .transpose()
+    }
+
+    pub async fn put(&self, storage: &KrillStorage, value: &V) -> KrillResult<()> {
+        let mut tx = storage.transaction();
+        tx.put_record(self, value)?;
+
+        tx.commit().await
+    }
+
+    pub async fn delete(&self, storage: &KrillStorage) -> KrillResult<()> {
+        storage.remove(self.keyspace, self.key).await
+    }
+
+    fn decode(&self, bytes: &[u8]) -> KrillResult<V> {
+        bitcode::decode::<V>(bytes).or(Err(KrillError::TableValueCorrupted(self.name())))
+    }
+}
+
+/// Writes to any number of tables and records that are committed together or not at all
+pub struct TableTransaction<'a> {
+    storage: &'a KrillStorage,
+    writes: StorageWrites,
+}
+
+impl TableTransaction<'_> {
+    pub fn put<K, V>(&mut self, table: &Table<K, V>, key: &K, value: &V) -> KrillResult<&mut Self>
+    where
+        K: TableKey,
+        V: Encode + for<'a> Decode<'a>,
+    {
+        self.write(
+            table.keyspace,
+            key.encode_key(),
+            Some(bitcode::encode(value)),
+        )
+    }
+
+    pub fn delete<K, V>(&mut self, table: &Table<K, V>, key: &K) -> KrillResult<&mut Self>
+    where
+        K: TableKey,
+        V: Encode + for<'a> Decode<'a>,
+    {
+        self.write(table.keyspace, key.encode_key(), None)
+    }
+
+    pub fn put_record<V>(&mut self, record: &Record<V>, value: &V) -> KrillResult<&mut Self>
+    where
+        V: Encode + for<'a> Decode<'a>,
+    {
+        self.write(
+            record.keyspace,
+            record.key.as_bytes().to_vec(),
+            Some(bitcode::encode(value)),
+        )
+    }
+
+    pub fn delete_record<V>(&mut self, record: &Record<V>) -> KrillResult<&mut Self>
+    where
+        V: Encode + for<'a> Decode<'a>,
+    {
+        self.write(record.keyspace, record.key.as_bytes().to_vec(), None)
+    }
+
+    pub async fn commit(self) -> KrillResult<()> {
+        self.storage.commit(self.writes).await
+    }
+
+    fn write(
+        &mut self,
+        keyspace: &'static str,
+        key: Vec<u8>,
+        payload: Option<Vec<u8>>,
+    ) -> KrillResult<&mut Self> {
+        let value = payload
+            .map(|payload| self.storage.encode_value(keyspace, &key, &payload))
+            .transpose()?;
+
+        self.writes.push((keyspace, key, value));
+
+        Ok(self)
+    }
+}
+
+/// The tables inside [KrillStorage::update_tables]. Other writes wait until these
+/// writes are applied, so nothing read here changes before it is replaced.
+pub struct LockedTables<'a> {
+    backend: &'a dyn StorageBackend,
+    cipher: &'a KrillCipher,
+    writes: StorageWrites,
+}
+
+impl LockedTables<'_> {
+    /// The entry with the greatest key
+    pub fn last<K, V>(&self, table: &Table<K, V>) -> KrillResult<Option<(K, V)>>
+    where
+        K: TableKey,
+        V: Encode + for<'a> Decode<'a>,
+    {
+        self.backend
+            .last(table.keyspace)?
+            .map(|(key, bytes)| table.decode_entry(self.cipher, &key, &bytes))
+            .transpose()
+    }
+
+    pub fn get<K, V>(&self, table: &Table<K, V>, key: &K) -> KrillResult<Option<V>>
+    where
+        K: TableKey,
+        V: Encode + for<'a> Decode<'a>,
+    {
+        let key = key.encode_key();
+
+        self.backend
+            .get(table.keyspace, &key)?
+            .map(|bytes| {
+                table.decode(&KrillStorage::decode_value_with(
+                    self.cipher,
+                    table.keyspace,
+                    &key,
+                    &bytes,
+                )?)
+            })
+            .transpose()
+    }
+
+    /// Every entry of the table in key order
+    pub fn scan<K, V>(&self, table: &Table<K, V>) -> KrillResult<Vec<(K, V)>>
+    where
+        K: TableKey,
+        V: Encode + for<'a> Decode<'a>,
+    {
+        self.backend
+            .entries(table.keyspace)?
+            .into_iter()
+            .map(|(key, bytes)| table.decode_entry(self.cipher, &key, &bytes))
+            .collect()
+    }
+
+    pub fn get_record<V>(&self, record: &Record<V>) -> KrillResult<Option<V>>
+    where
+        V: Encode + for<'a> Decode<'a>,
+    {
+        let key = record.key.as_bytes();
+
+        self.backend
+            .get(record.keyspace, key)?
+            .map(|bytes| {
+                record.decode(&KrillStorage::decode_value_with(
+                    self.cipher,
+                    record.keyspace,
+                    key,
+                    &bytes,
+                )?)
+            })
+            .transpose()
+    }
+
+    pub fn put<K, V>(&mut self, table: &Table<K, V>, key: &K, value: &V) -> KrillResult<&mut Self>
+    where
+        K: TableKey,
+        V: Encode + for<'a> Decode<'a>,
+    {
+        self.write(table.keyspace, key.encode_key(), bitcode::encode(value))
+    }
+
+    pub fn put_record<V>(&mut self, record: &Record<V>, value: &V) -> KrillResult<&mut Self>
+    where
+        V: Encode + for<'a> Decode<'a>,
+    {
+        self.write(
+            record.keyspace,
+            record.key.as_bytes().to_vec(),
+            bitcode::encode(value),
+        )
+    }
+
+    pub fn delete<K, V>(&mut self, table: &Table<K, V>, key: &K) -> &mut Self
+    where
+        K: TableKey,
+        V: Encode + for<'a> Decode<'a>,
+    {
+        self.writes.push((table.keyspace, key.encode_key(), None));
+
+        self
+    }
+
+    fn write(
+        &mut self,
+        keyspace: &'static str,
+        key: Vec<u8>,
+        payload: Vec<u8>,
+    ) -> KrillResult<&mut Self> {
+        let value = KrillStorage::encode_value_with(self.cipher, keyspace, &key, &payload)?;
+
+        self.writes.push((keyspace, key, Some(value)));
+
+        Ok(self)
+    }
+}
+
+impl KrillStorage {
+    pub fn transaction(&self) -> TableTransaction<'_> {
+        TableTransaction {
+            storage: self,
+            writes: StorageWrites::new(),
+        }
+    }
+
+    /// Reads and writes the tables as one exclusive update, like assigning the next key
+    /// of a table. The writes are only applied when `update` succeeds.
+    pub async fn update_tables<T: Send + 'static>(
+        &self,
+        mut update: impl FnMut(&mut LockedTables<'_>) -> KrillResult<T> + Send + 'static,
+    ) -> KrillResult<T> {
+        let backend = self.backend();
+        let cipher = KrillCipher::new(*self.cipher()?.expose_key());
+
+        blocking::unblock(move || {
+            let mut outcome = None;
+
+            backend.update(&mut |backend| {
+                let mut tables = LockedTables {
+                    backend,
+                    cipher: &cipher,
+                    writes: StorageWrites::new(),
+                };
+
+                outcome.replace(update(&mut tables)?);
+
+                Ok(tables.writes)
+            })?;
+
+            outcome.ok_or(KrillError::Store("The tables were not updated".to_string()))
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::test_storages;
+
+    const SEQUENCES: Table<u64, String> = Table::new(KrillStorage::KEYSPACE_ORG_SETTINGS_HISTORY);
+    const NAMES: Table<String, u32> = Table::new(KrillStorage::KEYSPACE_TENANTS);
+    const MARKER: Record<bool> = Record::new(KrillStorage::KEYSPACE_APP_STATE, "Marker");
+
+    #[test]
+    fn get_put_delete_and_scans() {
+        smol::block_on(async {
+            for storage in test_storages("TableScans").await {
+                for sequence in [3u64, 1, 300, 2] {
+                    SEQUENCES
+                        .put(&storage, &sequence, &format!("entry-{sequence}"))
+                        .await
+                        .unwrap();
+                }
+
+                assert_eq!(
+                    SEQUENCES.get(&storage, &300).await.unwrap().as_deref(),
+                    Some("entry-300")
+                );
+
+                let range = SEQUENCES.range(&storage, 2..=300).await.unwrap();
+                assert_eq!(
+                    range.iter().map(|(key, _)| *key).collect::<Vec<_>>(),
+                    vec![2, 3, 300]
+                );
+
+                SEQUENCES.delete(&storage, &3).await.unwrap();
+                assert!(SEQUENCES.get(&storage, &3).await.unwrap().is_none());
+                assert_eq!(SEQUENCES.scan(&storage).await.unwrap().len(), 3);
+
+                for (name, value) in [("member:ada", 1u32), ("member:bob", 2), ("admin:eve", 3)] {
+                    NAMES
+                        .put(&storage, &name.to_string(), &value)
+                        .await
+                        .unwrap();
+                }
+
+                let members = NAMES.prefix(&storage, "member:").await.unwrap();
+                assert_eq!(
+                    members,
+                    vec![("member:ada".to_string(), 1), ("member:bob".to_string(), 2)]
+                );
+
+                let last = storage
+                    .update_tables(|tables| tables.last(&SEQUENCES))
+                    .await
+                    .unwrap();
+                assert_eq!(last, Some((300, "entry-300".to_string())));
+            }
+        })
+    }
+
+    #[test]
+    fn transactions_and_corruption() {
+        smol::block_on(async {
+            for storage in test_storages("TableTransactions").await {
+                let mut tx = storage.transaction();
+                tx.put(&SEQUENCES, &7, &"seven".to_string())
+                    .unwrap()
+                    .put_record(&MARKER, &true)
+                    .unwrap();
+
+                // Nothing is visible before the commit
+                assert!(MARKER.get(&storage).await.unwrap().is_none());
+                tx.commit().await.unwrap();
+
+                assert_eq!(MARKER.get(&storage).await.unwrap(), Some(true));
+                assert!(SEQUENCES.get(&storage, &7).await.unwrap().is_some());
+
+                // A value of another type under the same key is reported uniformly
+                let wrong_type: Record<String> =
+                    Record::new(KrillStorage::KEYSPACE_APP_STATE, "Marker");
+                assert!(matches!(
+                    wrong_type.get(&storage).await,
+                    Err(KrillError::TableValueCorrupted(name)) if name == "AppState/Marker"
+                ));
+
+                // A table owns its keyspace so a key of another type is corruption
+                let foreign: Record<bool> =
+                    Record::new(KrillStorage::KEYSPACE_ORG_SETTINGS_HISTORY, "Marker");
+                foreign.put(&storage, &true).await.unwrap();
+                assert!(matches!(
+                    SEQUENCES.scan(&storage).await,
+                    Err(KrillError::TableValueCorrupted(_))
+                ));
+                assert!(matches!(
+                    storage
+                        .update_tables(|tables| tables.last(&SEQUENCES))
+                        .await,
+                    Err(KrillError::TableValueCorrupted(_))
+                ));
+
+                let unknown: Record<bool> = Record::new("NoSuchKeyspace", "Marker");
+                assert!(matches!(
+                    unknown.get(&storage).await,
+                    Err(KrillError::UnknownKeyspace(_))
+                ));
+            }
+        })
+    }
+}
@@ -0,0 +1,455 @@
+use core::fmt;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use bitcode::{Decode, Encode};
+use krill_common::{KrillError, KrillResult, RandomBytes};
+use tai64::Tai64N;
+
+use crate::{KrillCipher, KrillStorage, Table, UnlockSecret};
+
+/// An organization hosted next to the main organization of the server.
+/// Every tenant has its own storage, unlocked by a secret that is only kept,
+/// encrypted, in the main storage.
+#[derive(PartialEq, Eq, Clone, Encode, Decode)]
+pub struct Tenant {
+    pub id: String,
+    /// The host name requests for the tenant are sent to
+    pub domain: String,
+    pub created: [u8; Tai64N::BYTE_SIZE],
+    secret: [u8; KrillCipher::KEY_LEN],
+}
+
+impl Tenant {
+    pub const MAX_ID_LEN: usize = 32;
+
+    /// Identifiers name the directory of the tenant storage, so only
+    /// lowercase letters, digits and inner dashes are allowed
+    pub fn validate_id(id: &str) -> KrillResult<()> {
+        let is_valid = !id.is_empty()
+            && id.len() <= Self::MAX_ID_LEN
+            && !id.starts_with('-')
+            && !id.ends_with('-')
+            && id
+                .chars()
+                .all(|char| char.is_ascii_lowercase() || char.is_ascii_digit() || char == '-');
+
+        if is_valid {
+            Ok(())
+        } else {
+            Err(KrillError::InvalidTenant(format!(
+                "The identifier `{id}` must have 1 to {} lowercase letters, digits or inner dashes",
+                Self::MAX_ID_LEN
+            )))
+        }
+    }
+
+    /// Lowercases the host of a `Host` header and removes the port and the trailing dot
+    pub fn normalize_domain(host: &str) -> String {
+        let host = host.trim();
+        let host = match host.rsplit_once(':') {
+            Some((name, port)) if port.chars().all(|char| char.is_ascii_digit()) => name,
+            _ => host,
+        };
+
+        host.trim_end_matches('.').to_ascii_lowercase()
+    }
+
+    /// Seconds since the UNIX epoch the tenant was created
+    pub fn unix_timestamp(&self) -> u64 {
+        Tai64N::from_slice(&self.created)
+            .map(|timestamp| timestamp.to_system_time())
+            .unwrap_or(SystemTime::UNIX_EPOCH)
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_secs())
+            .unwrap_or_default()
+    }
+
+    fn validate_domain(domain: &str) -> KrillResult<()> {
+        let is_valid = domain.contains('.')
+            && domain
+                .split('.')
+                .all(|label| !label.is_empty() && !label.starts_with('-'))
+            && domain
+                .chars()
+                .all(|char| char.is_ascii_alphanumeric() || char == '-' || char == '.');
+
+        if is_valid {
+            Ok(())
+        } else {
+            Err(KrillError::InvalidTenant(format!(
+                "`{domain}` is not a valid domain name"
+            )))
+        }
+    }
+}
+
+impl fmt::Debug for Tenant {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Tenant")
+            .field("id", &self.id)
+            .field("domain", &self.domain)
+            .field("created", &self.created)
+            .field("secret", &"Redacted")
+            .finish()
+    }
+}
+
+impl KrillStorage {
+    pub(crate) const KEYSPACE_TENANTS: &str = "Tenants";
+    /// The directory in the data directory holding the storage of every tenant.
+    /// Each tenant storage is backed up with the backup configuration of the tenant.
+    pub const TENANTS_DIR: &str = "Tenants";
+
+    const TENANTS: Table<String, Tenant> = Table::new(Self::KEYSPACE_TENANTS);
+
+    /// Registers a tenant, its storage is created the first time it is opened
+    pub async fn create_tenant(&self, id: &str, domain: &str) -> KrillResult<Tenant> {
+        Tenant::validate_id(id)?;
+
+        let domain = Tenant::normalize_domain(domain);
+        Tenant::validate_domain(&domain)?;
+
+        let tenant = Tenant {
+            id: id.to_string(),
+            domain,
+            created: Tai64N::now().to_bytes(),
+            secret: *RandomBytes::<{ KrillCipher::KEY_LEN }>::generate().expose(),
+        };
+
+        // Checked while holding the write lock so two requests cannot register the same tenant
+        self.update_tables(move |tables| {
+            if tables.get(&Self::TENANTS, &tenant.id)?.is_some() {
+                return Err(KrillError::TenantAlreadyExists(tenant.id.clone()));
+            }
+
+            if tables
+                .scan(&Self::TENANTS)?
+                .iter()
+                .any(|(_, existing)| existing.domain == tenant.domain)
+            {
+                return Err(KrillError::TenantAlreadyExists(tenant.domain.clone()));
+            }
+
+            tables.put(&Self::TENANTS, &tenant.id, &tenant)?;
+
+            Ok(tenant.clone())
+        })
+        .await
+    }
+
+    /// Unregisters the tenant and deletes its storage so a tenant whose creation
+    /// failed can be created again. The storage of the tenant must not be open.
+    pub async fn remove_tenant(&self, tenant: &Tenant) -> KrillResult<()> {
+        Tenant::validate_id(&tenant.id)?;
+
+        if let Some(data_dir) = self.data_dir() {
+            let path = data_dir.join(Self::TENANTS_DIR).join(&tenant.id);
+
+            blocking::unblock(move || match std::fs::remove_dir_all(path) {
+                Err(error) if error.kind() == std::io::ErrorKind::NotFound => Ok(()),
+                outcome => Ok::<_, KrillError>(outcome?),
+            })
+            .await?;
+        }
+
+        Self::TENANTS.delete(self, &tenant.id).await
+    }
+
+    pub async fn tenants(&self) -> KrillResult<Vec<Tenant>> {
+        Ok(Self::TENANTS
+            .scan(self)
+            .await?
+            .into_iter()
+            .map(|(_, tenant)| tenant)
+            .collect())
+    }
+
+    pub async fn tenant(&self, id: &str) -> KrillResult<Tenant> {
+        Self::TENANTS
+            .get(self, &id.to_string())
+            .await?
+            .ok_or(KrillError::TenantNotFound(id.to_string()))
+    }
+
+    /// The tenant serving `host`, see [Tenant::normalize_domain]
+    pub async fn tenant_by_domain(&self, host: &str) -> KrillResult<Option<Tenant>> {
+        let domain = Tenant::normalize_domain(host);
+
+        Ok(self
+            .tenants()
+            .await?
+            .into_iter()
+            .find(|tenant| tenant.domain == domain))
+    }
+
+    /// Opens and unlocks the storage of the tenant. It is stored in the tenants
+    /// directory of the data directory, or in memory when this storage is in memory,
+    /// in which case every call opens a new empty storage.
+    pub async fn open_tenant(&self, tenant: &Tenant) -> KrillResult<KrillStorage> {
+        Tenant::validate_id(&tenant.id)?;
+
+        let storage = match self.data_dir() {
+            Some(data_dir) => {
+                let path = data_dir
+                    .join(Self::TENANTS_DIR)
+                    .join(&tenant.id)
+                    .join(Self::STORAGE_DIR);
+
+                krill_common::KrillUtils::create_recursive_dir(&path).await?;
+
+                Self::init_db(path).await?
+            }
+            None => Self::init_memory(),
+        };
+
+        storage.unlock(UnlockSecret::secret(tenant.secret)).await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use krill_common::{AuthTokenDetails, Holder, OrganizationInfo, UserRole};
+
+    use crate::test_storages;
+
+    #[test]
+    fn tenant_registration() {
+        smol::block_on(async {
+            for storage in test_storages("TenantRegistration").await {
+                let tenant = storage
+                    .create_tenant("forex-bureau", "Forex.Example.com.")
+                    .await
+                    .unwrap();
+                assert_eq!(tenant.domain, "forex.example.com");
+                assert!(!format!("{tenant:?}").contains(&format!("{:?}", tenant.secret)));
+
+                assert_eq!(
+                    storage
+                        .tenant_by_domain("FOREX.example.com:8080")
+                        .await
+                        .unwrap(),
+                    Some(tenant.clone())
+                );
+                assert_eq!(storage.tenant_by_domain("example.com").await.unwrap(), None);
+                assert_eq!(storage.tenant("forex-bureau").await.unwrap(), tenant);
+                assert_eq!(
+                    storage.tenant("bank").await.err(),
+                    Some(KrillError::TenantNotFound("bank".to_string()))
+                );
+
+                assert_eq!(
+                    storage
+                        .create_tenant("forex-bureau", "other.example.com")
+                        .await
+                        .err(),
+                    Some(KrillError::TenantAlreadyExists("forex-bureau".to_string()))
+                );
+                assert_eq!(
+                    storage
+                        .create_tenant("bank", "forex.example.com")
+                        .await
+                        .err(),
+                    Some(KrillError::TenantAlreadyExists(
+                        "forex.example.com".to_string()
+                    ))
+                );
+
+                for (id, domain) in [
+                    ("../bank", "bank.example.com"),
+                    ("Bank", "bank.example.com"),
+                    ("-bank", "bank.example.com"),
+                    ("", "bank.example.com"),
+                    ("bank", "bank"),
+                    ("bank", "bank.example.com/login"),
+                    ("bank", "bank..example.com"),
+                ] {
+                    assert!(matches!(
+                        storage.create_tenant(id, domain).await,
+                        Err(KrillError::InvalidTenant(_))
+                    ));
+                }
+
+                assert_eq!(storage.tenants().await.unwrap(), vec![tenant]);
+            }
+        })
+    }
+
+    #[test]
+    fn concurrent_registration() {
+        smol::block_on(async {
+            for storage in test_storages("TenantConcurrentRegistration").await {
+                // Both the identifier and the domain are checked in the same update as the insert
+                let (first, second) = smol::future::zip(
+                    storage.create_tenant("bank", "bank.example.com"),
+                    storage.create_tenant("bank", "bureau.example.com"),
+                )
+                .await;
+                assert!(first.is_ok() != second.is_ok());
+
+                let (first, second) = smol::future::zip(
+                    storage.create_tenant("bureau", "forex.example.com"),
+                    storage.create_tenant("forex", "forex.example.com"),
+                )
+                .await;
+                assert!(first.is_ok() != second.is_ok());
+
+                assert_eq!(storage.tenants().await.unwrap().len(), 2);
+            }
+        })
+    }
+
+    #[test]
+    fn tenant_removal() {
+        smol::block_on(async {
+            for storage in test_storages("TenantRemoval").await {
+                let tenant = storage
+                    .create_tenant("bank", "bank.example.com")
+                    .await
+                    .unwrap();
+                drop(storage.open_tenant(&tenant).await.unwrap());
+
+                storage.remove_tenant(&tenant).await.unwrap();
+                assert_eq!(storage.tenants().await.unwrap(), Vec::default());
+                if let Some(data_dir) = storage.data_dir() {
+                    assert!(!data_dir
+                        .join(KrillStorage::TENANTS_DIR)
+                        .join(&tenant.id)
+                        .exists());
+                }
+
+                // The identifier and the domain can be used again
+                let tenant = storage
+                    .create_tenant("bank", "bank.example.com")
+                    .await
+                    .unwrap();
+                assert_eq!(storage.tenants().await.unwrap(), vec![tenant]);
+            }
+        })
+    }
+
+    #[test]
+    fn tenants_are_isolated() {
+        smol::block_on(async {
+            for storage in test_storages("TenantIsolation").await {
+                let bank = storage
+                    .create_tenant("bank", "bank.example.com")
+                    .await
+                    .unwrap();
+                let bureau = storage
+                    .create_tenant("bureau", "bureau.example.com")
+                    .await
+                    .unwrap();
+
+                let bank_storage = storage.open_tenant(&bank).await.unwrap();
+                let bureau_storage = storage.open_tenant(&bureau).await.unwrap();
+
+                let org_info = OrganizationInfo {
+                    name: "Bank".to_string(),
+                    ..Default::default()
+                };
+                bank_storage.set_org_info(org_info.clone()).await.unwrap();
+
+                let superuser = Holder::new_with_tld("superuser@bank.example.com")
+                    .unwrap()
+                    .set_superuser();
+                let (token, _) = bank_storage
+                    .invite_member("member@bank.example.com", "", UserRole::Member, &superuser)
+                    .await
+                    .unwrap();
+                bank_storage.accept_member_invitation(&token).await.unwrap();
+
+                let session = AuthTokenDetails::generate_token();
+                let store_key = bank_storage
+                    .set_auth_token(session, AuthTokenDetails::new(superuser))
+                    .await
+                    .unwrap();
+
+                assert_eq!(bank_storage.get_org_info().await.unwrap(), org_info);
+                assert!(bank_storage
+                    .get_member("member@bank.example.com")
+                    .await
+                    .unwrap()
+                    .is_some());
+                assert!(bank_storage
+                    .get_auth_token(store_key)
+                    .await
+                    .unwrap()
+                    .is_some());
+
+                // Neither the other tenant nor the main organization see the data
+                for other in [&bureau_storage, &storage] {
+                    assert_ne!(other.get_org_info().await.unwrap(), org_info);
+                    assert!(other
+                        .get_member("member@bank.example.com")
+                        .await
+                        .unwrap()
+                        .is_none());
+                    assert!(other.get_auth_token(store_key).await.unwrap().is_none());
+                }
+
+                // Values copied into the storage of another tenant cannot be decrypted
+                let raw = bank_storage
+                    .backend()
+                    .get(
+                        KrillStorage::KEYSPACE_ORG_INFO,
+                        KrillStorage::ORG_INFO_KEY.as_bytes(),
+                    )
+                    .unwrap()
+                    .unwrap();
+                bureau_storage
+                    .commit(vec![(
+                        KrillStorage::KEYSPACE_ORG_INFO,
+                        KrillStorage::ORG_INFO_KEY.as_bytes().to_vec(),
+                        Some(raw),
+                    )])
+                    .await
+                    .unwrap();
+                assert!(bureau_storage.get_org_info().await.is_err());
+            }
+        })
+    }
+
+    #[test]
+    fn tenant_storage_survives_rekey() {
+        smol::block_on(async {
+            let mut storages = test_storages("TenantRekey").await;
+            let storage = storages.next().unwrap();
+
+            let tenant = storage
+                .create_tenant("bank", "bank.example.com")
+                .await
+                .unwrap();
+
+            let org_info = OrganizationInfo {
+                name: "Bank".to_string(),
+                ..Default::default()
+            };
+            storage
+                .open_tenant(&tenant)
+                .await
+                .unwrap()
+                .set_org_info(org_info.clone())
+                .await
+                .unwrap();
+
+            let storage = storage
+                .rekey(UnlockSecret::secret([8u8; 32]))
+                .await
+                .unwrap();
+
+            let tenant = storage.tenant("bank").await.unwrap();
+            assert_eq!(
+                storage
+                    .open_tenant(&tenant)
+                    .await
+                    .unwrap()
+                    .get_org_info()
+                    .await
+                    .unwrap(),
+                org_info
+            );
+        })
+    }
+}
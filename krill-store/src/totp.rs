@@ -0,0 +1,224 @@
+use core::fmt;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use bitcode::{Decode, Encode};
+use krill_common::{KrillError, KrillResult, Totp, TotpPolicy, TotpRecoveryCodes};
+use subtle::ConstantTimeEq;
+
+use crate::{KrillStorage, Record, Table};
+
+/// The TOTP enrollment of a holder. The secret is protected by the value encryption
+/// of the storage and only the hashes of the recovery codes are stored.
+#[derive(PartialEq, Eq, Clone, Encode, Decode)]
+pub struct TotpEnrollment {
+    pub secret: Vec<u8>,
+    pub recovery_code_hashes: Vec<[u8; 32]>,
+    pub confirmed: bool,
+    pub last_used_step: u64,
+    /// Invalid codes entered since the last successful verification or lockout
+    pub failed_attempts: u8,
+    /// Seconds since the UNIX epoch until which every code is rejected
+    pub locked_until: u64,
+}
+
+impl TotpEnrollment {
+    /// Invalid codes accepted before verification is locked
+    pub const MAX_FAILED_ATTEMPTS: u8 = 5;
+    pub const LOCKOUT: Duration = Duration::from_secs(15 * 60);
+
+    pub fn totp(&self) -> Totp {
+        Totp::new(&self.secret)
+    }
+
+    pub fn is_locked(&self, now: SystemTime) -> bool {
+        Self::unix_secs(now) < self.locked_until
+    }
+
+    /// Counts an invalid code and locks verification once [Self::MAX_FAILED_ATTEMPTS]
+    /// is reached. Returns whether verification is now locked.
+    pub fn record_failed_attempt(&mut self, now: SystemTime) -> bool {
+        self.failed_attempts = self.failed_attempts.saturating_add(1);
+
+        if self.failed_attempts < Self::MAX_FAILED_ATTEMPTS {
+            return false;
+        }
+
+        self.failed_attempts = 0;
+        self.locked_until = Self::unix_secs(now) + Self::LOCKOUT.as_secs();
+
+        true
+    }
+
+    pub fn reset_failed_attempts(&mut self) {
+        self.failed_attempts = 0;
+        self.locked_until = 0;
+    }
+
+    fn unix_secs(now: SystemTime) -> u64 {
+        now.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs()
+    }
+
+    /// Removes the matching recovery code so that it can only be used once
+    pub fn use_recovery_code(&mut self, code: &str) -> bool {
+        let hash = TotpRecoveryCodes::hash(code);
+
+        let position = self
+            .recovery_code_hashes
+            .iter()
+            .position(|stored| bool::from(stored.ct_eq(&hash)));
+
+        match position {
+            Some(index) => {
+                self.recovery_code_hashes.remove(index);
+
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+impl fmt::Debug for TotpEnrollment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TotpEnrollment")
+            .field("secret", &"Redacted")
+            .field("recovery_codes", &self.recovery_code_hashes.len())
+            .field("confirmed", &self.confirmed)
+            .field("last_used_step", &self.last_used_step)
+            .field("failed_attempts", &self.failed_attempts)
+            .field("locked_until", &self.locked_until)
+            .finish()
+    }
+}
+
+impl KrillStorage {
+    pub(crate) const KEYSPACE_TOTP: &str = "Totp";
+    const KEY_TOTP_POLICY: &str = "TotpPolicy";
+
+    /// Keyed by [krill_common::Holder::identity]
+    const TOTP_ENROLLMENTS: Table<String, TotpEnrollment> = Table::new(Self::KEYSPACE_TOTP);
+    const TOTP_POLICY: Record<TotpPolicy> =
+        Record::new(Self::KEYSPACE_ORG_INFO, Self::KEY_TOTP_POLICY);
+
+    /// Stores a new unconfirmed enrollment, replacing any previous unconfirmed one
+    pub async fn begin_totp_enrollment(
+        &self,
+        identity: &str,
+        totp: &Totp,
+        recovery_codes: &TotpRecoveryCodes,
+    ) -> KrillResult<()> {
+        if let Some(existing) = self.get_totp_enrollment(identity).await? {
+            if existing.confirmed {
+                return Err(KrillError::TotpAlreadyEnrolled);
+            }
+        }
+
+        let enrollment = TotpEnrollment {
+            secret: totp.secret().to_vec(),
+            recovery_code_hashes: recovery_codes.hashes(),
+            confirmed: false,
+            last_used_step: 0,
+            failed_attempts: 0,
+            locked_until: 0,
+        };
+
+        self.set_totp_enrollment(identity, enrollment).await
+    }
+
+    pub async fn set_totp_enrollment(
+        &self,
+        identity: &str,
+        enrollment: TotpEnrollment,
+    ) -> KrillResult<()> {
+        Self::TOTP_ENROLLMENTS
+            .put(self, &identity.to_string(), &enrollment)
+            .await
+    }
+
+    pub async fn get_totp_enrollment(&self, identity: &str) -> KrillResult<Option<TotpEnrollment>> {
+        Self::TOTP_ENROLLMENTS
+            .get(self, &identity.to_string())
+            .await
+    }
+
+    pub async fn remove_totp_enrollment(&self, identity: &str) -> KrillResult<()> {
+        Self::TOTP_ENROLLMENTS
+            .delete(self, &identity.to_string())
+            .await
+    }
+
+    pub async fn is_totp_enrolled(&self, identity: &str) -> KrillResult<bool> {
+        Ok(self
+            .get_totp_enrollment(identity)
+            .await?
+            .is_some_and(|enrollment| enrollment.confirmed))
+    }
+
+    pub async fn set_totp_policy(&self, policy: TotpPolicy) -> KrillResult<()> {
+        Self::TOTP_POLICY.put(self, &policy).await
+    }
+
+    /// TOTP is optional for all roles unless an administrator changes the policy
+    pub async fn totp_policy(&self) -> KrillResult<TotpPolicy> {
+        Ok(Self::TOTP_POLICY.get(self).await?.unwrap_or_default())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::test_storages;
+
+    #[test]
+    fn enrollment_lockout() {
+        smol::block_on(async {
+            for storage in test_storages("TotpLockout").await {
+                let totp = Totp::generate();
+                let recovery_codes = TotpRecoveryCodes::generate();
+                storage
+                    .begin_totp_enrollment("ada@krill.example", &totp, &recovery_codes)
+                    .await
+                    .unwrap();
+
+                let mut enrollment = storage
+                    .get_totp_enrollment("ada@krill.example")
+                    .await
+                    .unwrap()
+                    .unwrap();
+                assert!(!enrollment.confirmed);
+                assert_eq!(enrollment.totp().secret(), totp.secret());
+
+                let now = SystemTime::now();
+                for _ in 1..TotpEnrollment::MAX_FAILED_ATTEMPTS {
+                    assert!(!enrollment.record_failed_attempt(now));
+                }
+                assert!(!enrollment.is_locked(now));
+                assert!(enrollment.record_failed_attempt(now));
+                assert!(enrollment.is_locked(now));
+                assert!(!enrollment.is_locked(now + TotpEnrollment::LOCKOUT));
+
+                // The lockout is kept with the enrollment so new sessions cannot reset it
+                storage
+                    .set_totp_enrollment("ada@krill.example", enrollment)
+                    .await
+                    .unwrap();
+                let mut enrollment = storage
+                    .get_totp_enrollment("ada@krill.example")
+                    .await
+                    .unwrap()
+                    .unwrap();
+                assert!(enrollment.is_locked(now));
+
+                enrollment.reset_failed_attempts();
+                assert!(!enrollment.is_locked(now));
+                assert_eq!(enrollment.failed_attempts, 0);
+
+                // Recovery codes are only accepted once
+                let code = recovery_codes.codes()[0].to_lowercase();
+                assert!(enrollment.use_recovery_code(&code));
+                assert!(!enrollment.use_recovery_code(&code));
+            }
+        })
+    }
+}
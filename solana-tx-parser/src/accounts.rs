@@ -0,0 +1,243 @@
+use base64ct::{Base64, Encoding};
+use krill_common::KrillResult;
+use serde::{de::Error, Deserialize, Deserializer};
+use serde_json::json;
+
+use crate::{Pubkey, SolanaTxParserUtils, WithContext};
+
+impl SolanaTxParserUtils {
+    /// The lamports of `pubkey`, zero when the account does not exist
+    pub async fn get_balance(&self, pubkey: &Pubkey) -> KrillResult<u64> {
+        self.call::<WithContext<u64>>(
+            "getBalance",
+            json!([pubkey, { "commitment": self.commitment() }]),
+        )
+        .await
+        .map(|result| result.value)
+    }
+
+    /// `None` when the account does not exist
+    pub async fn get_account_info(&self, pubkey: &Pubkey) -> KrillResult<Option<AccountInfo>> {
+        self.call::<WithContext<Option<AccountInfo>>>(
+            "getAccountInfo",
+            json!([pubkey, {
+                "encoding": "base64",
+                "commitment": self.commitment(),
+            }]),
+        )
+        .await
+        .map(|result| result.value)
+    }
+
+    /// The accounts in the order of `pubkeys`, at most 100 per request
+    pub async fn get_multiple_accounts(
+        &self,
+        pubkeys: &[Pubkey],
+    ) -> KrillResult<Vec<Option<AccountInfo>>> {
+        self.call::<WithContext<Vec<Option<AccountInfo>>>>(
+            "getMultipleAccounts",
+            json!([pubkeys, {
+                "encoding": "base64",
+                "commitment": self.commitment(),
+            }]),
+        )
+        .await
+        .map(|result| result.value)
+    }
+
+    pub async fn get_token_accounts_by_owner(
+        &self,
+        owner: &Pubkey,
+        filter: &TokenAccountsFilter,
+    ) -> KrillResult<Vec<KeyedAccount>> {
+        let filter = match filter {
+            TokenAccountsFilter::Mint(mint) => json!({ "mint": mint }),
+            TokenAccountsFilter::ProgramId(program_id) => json!({ "programId": program_id }),
+        };
+
+        self.call::<WithContext<Vec<KeyedAccount>>>(
+            "getTokenAccountsByOwner",
+            json!([owner, filter, {
+                "encoding": "base64",
+                "commitment": self.commitment(),
+            }]),
+        )
+        .await
+        .map(|result| result.value)
+    }
+
+    /// The lamports an account of `data_len` bytes needs to be exempt from rent
+    pub async fn get_minimum_balance_for_rent_exemption(
+        &self,
+        data_len: usize,
+    ) -> KrillResult<u64> {
+        self.call(
+            "getMinimumBalanceForRentExemption",
+            json!([data_len, { "commitment": self.commitment() }]),
+        )
+        .await
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AccountInfo {
+    pub lamports: u64,
+    /// The program the account belongs to
+    pub owner: Pubkey,
+    #[serde(deserialize_with = "deserialize_base64")]
+    pub data: Vec<u8>,
+    pub executable: bool,
+    pub rent_epoch: u64,
+    /// Missing from the responses of older nodes
+    pub space: Option<u64>,
+}
+
+/// An account returned with its address
+#[derive(Debug, PartialEq, Eq, Clone, Deserialize)]
+pub struct KeyedAccount {
+    pub pubkey: Pubkey,
+    pub account: AccountInfo,
+}
+
+/// Which token accounts of an owner `getTokenAccountsByOwner` returns
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum TokenAccountsFilter {
+    /// The accounts holding one mint
+    Mint(Pubkey),
+    /// All the accounts of the SPL Token or the Token-2022 program
+    ProgramId(Pubkey),
+}
+
+/// Binary data is requested as `[data, "base64"]`
+pub(crate) fn deserialize_base64<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Vec<u8>, D::Error> {
+    let (data, encoding) = <(String, String)>::deserialize(deserializer)?;
+
+    if encoding != "base64" {
+        return Err(D::Error::custom(format!(
+            "`{encoding}` is not the requested base64 encoding"
+        )));
+    }
+
+    Base64::decode_vec(&data).map_err(D::Error::custom)
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::Value;
+
+    use crate::{
+        mock_rpc::{MockReply, MockRpc},
+        Commitment, TokenInstruction,
+    };
+
+    use super::*;
+
+    const OWNER: &str = "8V1yfvQXQXfVhYRWiMtpcz1V6iRL9ftYyuZTYyn8o3ib";
+    const MINT: &str = "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v";
+    const TOKEN_ACCOUNT: &str = "HGBUKPxZQGjbKSkt33gngvqfUiPpXUXGhVAU8ddwEbdX";
+
+    fn pubkey(value: &str) -> Pubkey {
+        Pubkey::from_base58(value).unwrap()
+    }
+
+    fn token_account() -> Value {
+        json!({
+            "lamports": 2039280,
+            "owner": "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA",
+            "data": [Base64::encode_string(&[1, 2, 3]), "base64"],
+            "executable": false,
+            "rentEpoch": 18446744073709551615u64,
+            "space": 165,
+        })
+    }
+
+    fn with_context(value: Value) -> MockReply {
+        MockReply::Result(json!({ "context": { "slot": 42 }, "value": value }))
+    }
+
+    #[test]
+    fn account_methods() {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+
+        runtime.block_on(async {
+            let rpc = MockRpc::start(|method, params| match method {
+                "getBalance" => with_context(json!(5000)),
+                "getAccountInfo" if params[0] == TOKEN_ACCOUNT => with_context(token_account()),
+                "getAccountInfo" => with_context(Value::Null),
+                "getMultipleAccounts" => with_context(json!([null, token_account()])),
+                "getTokenAccountsByOwner" => with_context(json!([{
+                    "pubkey": TOKEN_ACCOUNT,
+                    "account": token_account(),
+                }])),
+                "getMinimumBalanceForRentExemption" => MockReply::Result(json!(2039280)),
+                _ => MockReply::Error {
+                    code: -32601,
+                    message: "Method not found",
+                },
+            })
+            .await;
+            let mut client = rpc.client();
+            client.set_commitment(Commitment::Confirmed);
+
+            let expected = AccountInfo {
+                lamports: 2039280,
+                owner: TokenInstruction::PROGRAM_ID,
+                data: vec![1, 2, 3],
+                executable: false,
+                rent_epoch: u64::MAX,
+                space: Some(165),
+            };
+
+            assert_eq!(client.get_balance(&pubkey(OWNER)).await, Ok(5000));
+            assert_eq!(
+                client.get_account_info(&pubkey(TOKEN_ACCOUNT)).await,
+                Ok(Some(expected.clone()))
+            );
+            assert_eq!(client.get_account_info(&pubkey(OWNER)).await, Ok(None));
+            assert_eq!(
+                client
+                    .get_multiple_accounts(&[pubkey(OWNER), pubkey(TOKEN_ACCOUNT)])
+                    .await,
+                Ok(vec![None, Some(expected.clone())])
+            );
+            assert_eq!(
+                client
+                    .get_token_accounts_by_owner(
+                        &pubkey(OWNER),
+                        &TokenAccountsFilter::Mint(pubkey(MINT))
+                    )
+                    .await,
+                Ok(vec![KeyedAccount {
+                    pubkey: pubkey(TOKEN_ACCOUNT),
+                    account: expected,
+                }])
+            );
+            assert_eq!(
+                client.get_minimum_balance_for_rent_exemption(165).await,
+                Ok(2039280)
+            );
+
+            let requests = rpc.requests();
+            assert_eq!(requests.len(), 6);
+            assert!(requests
+                .iter()
+                .all(|request| request["jsonrpc"] == "2.0" && request["id"] == 1));
+
+            assert_eq!(requests[0]["method"], "getBalance");
+            assert_eq!(
+                requests[0]["params"],
+                json!([OWNER, { "commitment": "confirmed" }])
+            );
+            assert_eq!(
+                requests[1]["params"],
+                json!([TOKEN_ACCOUNT, { "encoding": "base64", "commitment": "confirmed" }])
+            );
+            assert_eq!(requests[3]["params"][0], json!([OWNER, TOKEN_ACCOUNT]));
+            assert_eq!(requests[4]["params"][1], json!({ "mint": MINT }));
+            assert_eq!(requests[5]["params"][0], 165);
+        });
+    }
+}
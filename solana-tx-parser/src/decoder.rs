@@ -0,0 +1,312 @@
+use std::{collections::HashMap, sync::Arc};
+
+use krill_common::{KrillError, KrillResult};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    AccountAddress, AssociatedTokenDecoder, AssociatedTokenInstruction, ComputeBudgetDecoder,
+    ComputeBudgetInstruction, MemoDecoder, MemoInstruction, Pubkey, SystemDecoder,
+    SystemInstruction, TokenDecoder, TokenInstruction, TransactionAccount,
+};
+
+/// Turns the data of the instructions of some programs into a [DecodedInstruction]
+pub trait InstructionDecoder: Send + Sync {
+    /// The programs the decoder understands
+    fn program_ids(&self) -> Vec<Pubkey>;
+
+    /// Decodes an instruction of one of [Self::program_ids]. Errors are shown to
+    /// the signers with the raw data, see [DecodedInstruction::Invalid].
+    fn decode(
+        &self,
+        program_id: &Pubkey,
+        accounts: &[TransactionAccount],
+        data: &[u8],
+    ) -> KrillResult<DecodedInstruction>;
+}
+
+/// An instruction with named accounts and amounts
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+#[serde(tag = "program", content = "decoded", rename_all = "snake_case")]
+pub enum DecodedInstruction {
+    System(SystemInstruction),
+    Token(TokenInstruction),
+    Token2022(TokenInstruction),
+    AssociatedToken(AssociatedTokenInstruction),
+    Memo(MemoInstruction),
+    ComputeBudget(ComputeBudgetInstruction),
+    /// Decoded by a decoder registered with [InstructionDecoders::register]
+    Custom(CustomInstruction),
+    /// No decoder is registered for the program
+    Unknown {
+        data: Vec<u8>,
+    },
+    /// The decoder of the program rejected the data
+    Invalid {
+        reason: String,
+        data: Vec<u8>,
+    },
+}
+
+/// The instructions of programs Krill has no types for, for example the Solana Attestation Service
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct CustomInstruction {
+    /// The name of the program, `solana_attestation_service` for example
+    pub program: String,
+    pub instruction: String,
+    pub accounts: Vec<NamedAccount>,
+    pub fields: serde_json::Value,
+}
+
+impl CustomInstruction {
+    pub fn new(program: &str, instruction: &str) -> Self {
+        Self {
+            program: program.to_string(),
+            instruction: instruction.to_string(),
+            accounts: Vec::default(),
+            fields: serde_json::Value::Object(serde_json::Map::default()),
+        }
+    }
+
+    pub fn add_account(mut self, name: &str, address: AccountAddress) -> Self {
+        self.accounts.push(NamedAccount {
+            name: name.to_string(),
+            address,
+        });
+
+        self
+    }
+
+    pub fn set_fields(mut self, fields: serde_json::Value) -> Self {
+        self.fields = fields;
+
+        self
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+pub struct NamedAccount {
+    pub name: String,
+    pub address: AccountAddress,
+}
+
+/// The decoders of the programs, by program id. [Self::default] has the System,
+/// SPL Token, Token-2022, Associated Token Account, Memo and Compute Budget programs.
+#[derive(Clone)]
+pub struct InstructionDecoders {
+    decoders: HashMap<Pubkey, Arc<dyn InstructionDecoder>>,
+}
+
+impl Default for InstructionDecoders {
+    fn default() -> Self {
+        let mut decoders = Self::empty();
+
+        decoders
+            .register(SystemDecoder)
+            .register(TokenDecoder)
+            .register(AssociatedTokenDecoder)
+            .register(MemoDecoder)
+            .register(ComputeBudgetDecoder);
+
+        decoders
+    }
+}
+
+impl std::fmt::Debug for InstructionDecoders {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("InstructionDecoders")
+            .field("programs", &self.decoders.keys().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+impl InstructionDecoders {
+    /// Without the built-in decoders
+    pub fn empty() -> Self {
+        Self {
+            decoders: HashMap::default(),
+        }
+    }
+
+    /// Replaces the decoders already registered for the same programs
+    pub fn register(&mut self, decoder: impl InstructionDecoder + 'static) -> &mut Self {
+        let decoder: Arc<dyn InstructionDecoder> = Arc::new(decoder);
+
+        decoder.program_ids().into_iter().for_each(|program_id| {
+            self.decoders.insert(program_id, decoder.clone());
+        });
+
+        self
+    }
+
+    pub fn is_registered(&self, program_id: &Pubkey) -> bool {
+        self.decoders.contains_key(program_id)
+    }
+
+    /// Never fails, the raw data is kept when the program is unknown or the data is invalid
+    pub fn decode(
+        &self,
+        program_id: &Pubkey,
+        accounts: &[TransactionAccount],
+        data: &[u8],
+    ) -> DecodedInstruction {
+        let Some(decoder) = self.decoders.get(program_id) else {
+            return DecodedInstruction::Unknown {
+                data: data.to_vec(),
+            };
+        };
+
+        decoder
+            .decode(program_id, accounts, data)
+            .unwrap_or_else(|error| DecodedInstruction::Invalid {
+                reason: match error {
+                    KrillError::InvalidInstructionData(reason) => reason,
+                    error => error.to_string(),
+                },
+                data: data.to_vec(),
+            })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn accounts(len: u8) -> Vec<TransactionAccount> {
+        (0..len)
+            .map(|index| TransactionAccount {
+                address: AccountAddress::Static {
+                    pubkey: Pubkey::new([index + 1; 32]),
+                },
+                is_signer: index == 0,
+                is_writable: true,
+            })
+            .collect()
+    }
+
+    /// Decodes the `CreateAttestation` instruction of the Solana Attestation Service
+    struct AttestationDecoder;
+
+    impl AttestationDecoder {
+        const PROGRAM_ID: Pubkey = Pubkey::new([7u8; 32]);
+    }
+
+    impl InstructionDecoder for AttestationDecoder {
+        fn program_ids(&self) -> Vec<Pubkey> {
+            vec![Self::PROGRAM_ID]
+        }
+
+        fn decode(
+            &self,
+            _program_id: &Pubkey,
+            accounts: &[TransactionAccount],
+            data: &[u8],
+        ) -> KrillResult<DecodedInstruction> {
+            let accounts = crate::InstructionAccounts::new(accounts);
+            let mut data = crate::InstructionData::new(data);
+
+            if data.read_u8()? != 6 {
+                return Err(crate::InstructionData::invalid("not an attestation"));
+            }
+
+            Ok(DecodedInstruction::Custom(
+                CustomInstruction::new("solana_attestation_service", "create_attestation")
+                    .add_account("payer", accounts.get(0, "payer")?)
+                    .add_account("credential", accounts.get(1, "credential")?)
+                    .set_fields(serde_json::json!({ "expiry": data.read_i64()? })),
+            ))
+        }
+    }
+
+    #[test]
+    fn program_ids() {
+        for (program_id, expected) in [
+            (
+                SystemInstruction::PROGRAM_ID,
+                "11111111111111111111111111111111",
+            ),
+            (
+                TokenInstruction::PROGRAM_ID,
+                "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA",
+            ),
+            (
+                TokenInstruction::PROGRAM_ID_2022,
+                "TokenzQdBNbLqP5VEhdkAS6EPFLC1PHnBqCXEpPxuEb",
+            ),
+            (
+                AssociatedTokenInstruction::PROGRAM_ID,
+                "ATokenGPvbdGVxr1b2hvZbsiqW5xWH25efTNsLJA8knL",
+            ),
+            (
+                MemoInstruction::PROGRAM_ID,
+                "MemoSq4gqABAXKb96qnH8TysNcWxMyWCqXgDLGmfcHr",
+            ),
+            (
+                MemoInstruction::PROGRAM_ID_V1,
+                "Memo1UhkJRfHyvLMcVucJwxXeuD728EqVDDwQDxFMNo",
+            ),
+            (
+                ComputeBudgetInstruction::PROGRAM_ID,
+                "ComputeBudget111111111111111111111111111111",
+            ),
+        ] {
+            assert_eq!(program_id.to_string(), expected);
+            assert!(InstructionDecoders::default().is_registered(&program_id));
+        }
+    }
+
+    #[test]
+    fn fallbacks_and_custom_decoders() {
+        let mut decoders = InstructionDecoders::default();
+        let accounts = accounts(2);
+        let mut data = vec![6];
+        data.extend_from_slice(&1_800_000_000i64.to_le_bytes());
+
+        assert_eq!(
+            decoders.decode(&AttestationDecoder::PROGRAM_ID, &accounts, &data),
+            DecodedInstruction::Unknown { data: data.clone() }
+        );
+
+        decoders.register(AttestationDecoder);
+        let DecodedInstruction::Custom(attestation) =
+            decoders.decode(&AttestationDecoder::PROGRAM_ID, &accounts, &data)
+        else {
+            panic!("the attestation was not decoded");
+        };
+        assert_eq!(attestation.instruction, "create_attestation");
+        assert_eq!(attestation.accounts[1].name, "credential");
+        assert_eq!(attestation.accounts[1].address, accounts[1].address);
+        assert_eq!(attestation.fields["expiry"], 1_800_000_000i64);
+
+        assert_eq!(
+            decoders.decode(&AttestationDecoder::PROGRAM_ID, &accounts, &[1]),
+            DecodedInstruction::Invalid {
+                reason: "not an attestation".to_string(),
+                data: vec![1],
+            }
+        );
+
+        // Missing accounts and truncated data are shown with the raw data
+        let transfer = [2, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0];
+        assert!(matches!(
+            decoders.decode(&SystemInstruction::PROGRAM_ID, &accounts[..1], &transfer),
+            DecodedInstruction::Invalid { .. }
+        ));
+        assert!(matches!(
+            decoders.decode(&SystemInstruction::PROGRAM_ID, &accounts, &transfer[..8]),
+            DecodedInstruction::Invalid { .. }
+        ));
+
+        let memo = decoders.decode(&MemoInstruction::PROGRAM_ID, &accounts[..1], b"invoice 42");
+        assert_eq!(
+            memo,
+            DecodedInstruction::Memo(MemoInstruction {
+                memo: "invoice 42".to_string(),
+                signers: vec![accounts[0].address],
+            })
+        );
+
+        let json = serde_json::to_value(&memo).unwrap();
+        assert_eq!(json["program"], "memo");
+        assert_eq!(json["decoded"]["memo"], "invoice 42");
+    }
+}
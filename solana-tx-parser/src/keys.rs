@@ -0,0 +1,124 @@
+use core::fmt;
+use std::str::FromStr;
+
+use krill_common::{KrillError, KrillResult};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// Implements the base58 text form Solana uses for keys, signatures and hashes
+macro_rules! base58_bytes {
+    ($name:ident, $len:literal, $kind:literal) => {
+        impl $name {
+            pub const LEN: usize = $len;
+
+            pub const fn new(bytes: [u8; $len]) -> Self {
+                Self(bytes)
+            }
+
+            pub fn as_bytes(&self) -> &[u8; $len] {
+                &self.0
+            }
+
+            pub fn to_bytes(self) -> [u8; $len] {
+                self.0
+            }
+
+            pub fn from_base58(value: &str) -> KrillResult<Self> {
+                let mut buffer = [0u8; $len];
+
+                let decoded_len =
+                    bs58::decode(value.trim())
+                        .onto(&mut buffer)
+                        .map_err(|error| {
+                            KrillError::MalformedSolanaTransaction(format!(
+                                "`{value}` is not a base58 {}. Error: {error}",
+                                $kind
+                            ))
+                        })?;
+
+                if decoded_len != $len {
+                    return Err(KrillError::MalformedSolanaTransaction(format!(
+                        "`{value}` is not a {} of {} bytes",
+                        $kind, $len
+                    )));
+                }
+
+                Ok(Self(buffer))
+            }
+
+            pub fn to_base58(&self) -> String {
+                bs58::encode(&self.0).into_string()
+            }
+        }
+
+        impl Default for $name {
+            fn default() -> Self {
+                Self([0u8; $len])
+            }
+        }
+
+        impl From<[u8; $len]> for $name {
+            fn from(bytes: [u8; $len]) -> Self {
+                Self(bytes)
+            }
+        }
+
+        impl FromStr for $name {
+            type Err = KrillError;
+
+            fn from_str(value: &str) -> Result<Self, Self::Err> {
+                Self::from_base58(value)
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str(&self.to_base58())
+            }
+        }
+
+        impl fmt::Debug for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                write!(f, "{}({})", stringify!($name), self.to_base58())
+            }
+        }
+
+        impl Serialize for $name {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serializer.serialize_str(&self.to_base58())
+            }
+        }
+
+        impl<'de> Deserialize<'de> for $name {
+            fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                let value = String::deserialize(deserializer)?;
+
+                Self::from_base58(&value).map_err(serde::de::Error::custom)
+            }
+        }
+    };
+}
+
+/// The Ed25519 public key of an account
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy)]
+pub struct Pubkey([u8; 32]);
+
+base58_bytes!(Pubkey, 32, "public key");
+
+/// The Ed25519 signature of a transaction message
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy)]
+pub struct Signature([u8; 64]);
+
+base58_bytes!(Signature, 64, "signature");
+
+impl Signature {
+    /// Transactions that still wait for some signers carry zeroed signatures in their place
+    pub fn is_placeholder(&self) -> bool {
+        self.0.iter().all(|byte| *byte == 0)
+    }
+}
+
+/// The hash of a recent block, which limits how long a transaction can be submitted
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy)]
+pub struct Blockhash([u8; 32]);
+
+base58_bytes!(Blockhash, 32, "blockhash");
@@ -3,3 +3,36 @@ pub use tx_req::*;
 
 mod version;
 pub use version::*;
+
+mod accounts;
+pub use accounts::*;
+
+mod signatures;
+pub use signatures::*;
+
+mod submit;
+pub use submit::*;
+
+mod pubsub;
+pub use pubsub::*;
+
+mod wire;
+pub(crate) use wire::*;
+
+mod keys;
+pub use keys::*;
+
+mod message;
+pub use message::*;
+
+mod transaction;
+pub use transaction::*;
+
+mod decoder;
+pub use decoder::*;
+
+mod programs;
+pub use programs::*;
+
+#[cfg(test)]
+mod mock_rpc;
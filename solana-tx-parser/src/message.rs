@@ -0,0 +1,465 @@
+use std::collections::BTreeSet;
+
+use krill_common::KrillResult;
+use serde::{Deserialize, Serialize};
+
+use crate::{write_short_vec, write_short_vec_len, Blockhash, Pubkey, WireReader};
+
+/// The number of signers and read-only accounts at the start of the account keys.
+/// The keys are ordered as writable signers, read-only signers, writable accounts
+/// and read-only accounts.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
+pub struct MessageHeader {
+    pub num_required_signatures: u8,
+    pub num_readonly_signed_accounts: u8,
+    pub num_readonly_unsigned_accounts: u8,
+}
+
+impl MessageHeader {
+    fn parse(reader: &mut WireReader) -> KrillResult<Self> {
+        Ok(Self {
+            num_required_signatures: reader.read_u8()?,
+            num_readonly_signed_accounts: reader.read_u8()?,
+            num_readonly_unsigned_accounts: reader.read_u8()?,
+        })
+    }
+
+    fn serialize(&self, buffer: &mut Vec<u8>) {
+        buffer.extend_from_slice(&[
+            self.num_required_signatures,
+            self.num_readonly_signed_accounts,
+            self.num_readonly_unsigned_accounts,
+        ]);
+    }
+}
+
+/// An instruction whose program and accounts are indexes into the accounts of the message
+#[derive(Debug, Default, PartialEq, Eq, Clone, Serialize, Deserialize)]
+pub struct CompiledInstruction {
+    pub program_id_index: u8,
+    pub accounts: Vec<u8>,
+    pub data: Vec<u8>,
+}
+
+impl CompiledInstruction {
+    fn parse(reader: &mut WireReader) -> KrillResult<Self> {
+        Ok(Self {
+            program_id_index: reader.read_u8()?,
+            accounts: reader.read_short_vec()?.to_vec(),
+            data: reader.read_short_vec()?.to_vec(),
+        })
+    }
+
+    fn serialize(&self, buffer: &mut Vec<u8>) -> KrillResult<()> {
+        buffer.push(self.program_id_index);
+        write_short_vec(buffer, &self.accounts)?;
+        write_short_vec(buffer, &self.data)
+    }
+}
+
+/// The accounts a v0 message loads from an on-chain address lookup table
+#[derive(Debug, Default, PartialEq, Eq, Clone, Serialize, Deserialize)]
+pub struct MessageAddressTableLookup {
+    /// The address of the lookup table
+    pub account_key: Pubkey,
+    pub writable_indexes: Vec<u8>,
+    pub readonly_indexes: Vec<u8>,
+}
+
+impl MessageAddressTableLookup {
+    fn parse(reader: &mut WireReader) -> KrillResult<Self> {
+        Ok(Self {
+            account_key: Pubkey::new(reader.read_array()?),
+            writable_indexes: reader.read_short_vec()?.to_vec(),
+            readonly_indexes: reader.read_short_vec()?.to_vec(),
+        })
+    }
+
+    fn serialize(&self, buffer: &mut Vec<u8>) -> KrillResult<()> {
+        buffer.extend_from_slice(self.account_key.as_bytes());
+        write_short_vec(buffer, &self.writable_indexes)?;
+        write_short_vec(buffer, &self.readonly_indexes)
+    }
+}
+
+/// The message format from before versioned transactions, which has no version prefix
+#[derive(Debug, Default, PartialEq, Eq, Clone, Serialize, Deserialize)]
+pub struct LegacyMessage {
+    pub header: MessageHeader,
+    pub account_keys: Vec<Pubkey>,
+    pub recent_blockhash: Blockhash,
+    pub instructions: Vec<CompiledInstruction>,
+}
+
+/// A versioned message that can load more accounts from address lookup tables
+#[derive(Debug, Default, PartialEq, Eq, Clone, Serialize, Deserialize)]
+pub struct V0Message {
+    pub header: MessageHeader,
+    pub account_keys: Vec<Pubkey>,
+    pub recent_blockhash: Blockhash,
+    pub instructions: Vec<CompiledInstruction>,
+    pub address_table_lookups: Vec<MessageAddressTableLookup>,
+}
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TransactionVersion {
+    Legacy,
+    V0,
+}
+
+/// The part of a transaction the signers sign
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+#[serde(tag = "version", rename_all = "lowercase")]
+pub enum VersionedMessage {
+    Legacy(LegacyMessage),
+    V0(V0Message),
+}
+
+impl Default for VersionedMessage {
+    fn default() -> Self {
+        Self::Legacy(LegacyMessage::default())
+    }
+}
+
+impl VersionedMessage {
+    /// Versioned messages start with this bit set, followed by the version in the other 7 bits
+    pub const VERSION_PREFIX: u8 = 0x80;
+    /// Instructions index the accounts with a `u8`
+    pub const MAX_ACCOUNTS: usize = 256;
+
+    /// Parses a message on its own, for example one that a wallet was asked to sign
+    pub fn from_bytes(bytes: &[u8]) -> KrillResult<Self> {
+        let mut reader = WireReader::new(bytes);
+        let message = Self::parse(&mut reader)?;
+        reader.finish()?;
+
+        Ok(message)
+    }
+
+    pub(crate) fn parse(reader: &mut WireReader) -> KrillResult<Self> {
+        let prefix = reader.peek_u8()?;
+        let is_versioned = prefix & Self::VERSION_PREFIX != 0;
+
+        if is_versioned {
+            reader.read_u8()?;
+
+            let version = prefix & !Self::VERSION_PREFIX;
+            if version != 0 {
+                return Err(WireReader::malformed(&format!(
+                    "version `{version}` of the transaction message is not supported"
+                )));
+            }
+        }
+
+        let header = MessageHeader::parse(reader)?;
+        let account_keys = Self::parse_account_keys(reader)?;
+        let recent_blockhash = Blockhash::new(reader.read_array()?);
+        let instructions = Self::parse_instructions(reader)?;
+
+        let message = if is_versioned {
+            let lookups_len = reader.read_short_vec_len()?;
+            let address_table_lookups = (0..lookups_len)
+                .map(|_| MessageAddressTableLookup::parse(reader))
+                .collect::<KrillResult<Vec<_>>>()?;
+
+            Self::V0(V0Message {
+                header,
+                account_keys,
+                recent_blockhash,
+                instructions,
+                address_table_lookups,
+            })
+        } else {
+            Self::Legacy(LegacyMessage {
+                header,
+                account_keys,
+                recent_blockhash,
+                instructions,
+            })
+        };
+
+        message.sanitize()?;
+
+        Ok(message)
+    }
+
+    fn parse_account_keys(reader: &mut WireReader) -> KrillResult<Vec<Pubkey>> {
+        let len = reader.read_short_vec_len()?;
+
+        (0..len)
+            .map(|_| Ok(Pubkey::new(reader.read_array()?)))
+            .collect()
+    }
+
+    fn parse_instructions(reader: &mut WireReader) -> KrillResult<Vec<CompiledInstruction>> {
+        let len = reader.read_short_vec_len()?;
+
+        (0..len)
+            .map(|_| CompiledInstruction::parse(reader))
+            .collect()
+    }
+
+    /// The bytes the signers sign
+    pub fn to_bytes(&self) -> KrillResult<Vec<u8>> {
+        let mut buffer = Vec::new();
+
+        if let Self::V0(_) = self {
+            buffer.push(Self::VERSION_PREFIX);
+        }
+
+        self.header().serialize(&mut buffer);
+
+        write_short_vec_len(&mut buffer, self.static_account_keys().len())?;
+        self.static_account_keys()
+            .iter()
+            .for_each(|key| buffer.extend_from_slice(key.as_bytes()));
+
+        buffer.extend_from_slice(self.recent_blockhash().as_bytes());
+
+        write_short_vec_len(&mut buffer, self.instructions().len())?;
+        for instruction in self.instructions() {
+            instruction.serialize(&mut buffer)?;
+        }
+
+        if let Self::V0(message) = self {
+            write_short_vec_len(&mut buffer, message.address_table_lookups.len())?;
+            for lookup in &message.address_table_lookups {
+                lookup.serialize(&mut buffer)?;
+            }
+        }
+
+        Ok(buffer)
+    }
+
+    /// Rejects the messages the runtime would reject before running them
+    pub fn sanitize(&self) -> KrillResult<()> {
+        let header = self.header();
+        let static_len = self.static_account_keys().len();
+
+        if header.num_readonly_signed_accounts >= header.num_required_signatures {
+            return Err(WireReader::malformed(
+                "the fee payer must be a writable signer",
+            ));
+        }
+
+        if usize::from(header.num_required_signatures)
+            + usize::from(header.num_readonly_unsigned_accounts)
+            > static_len
+        {
+            return Err(WireReader::malformed(
+                "the header describes more accounts than the message has",
+            ));
+        }
+
+        let unique_keys = self.static_account_keys().iter().collect::<BTreeSet<_>>();
+        if unique_keys.len() != static_len {
+            return Err(WireReader::malformed("the account keys have duplicates"));
+        }
+
+        if self
+            .address_table_lookups()
+            .iter()
+            .any(|lookup| lookup.writable_indexes.is_empty() && lookup.readonly_indexes.is_empty())
+        {
+            return Err(WireReader::malformed(
+                "an address table lookup loads no accounts",
+            ));
+        }
+
+        let accounts_len = self.accounts_len();
+        if accounts_len > Self::MAX_ACCOUNTS {
+            return Err(WireReader::malformed(&format!(
+                "the message uses `{accounts_len}` accounts, at most `{}` are supported",
+                Self::MAX_ACCOUNTS
+            )));
+        }
+
+        for instruction in self.instructions() {
+            let program_id_index = usize::from(instruction.program_id_index);
+
+            // Programs cannot be loaded from lookup tables and cannot pay the fees
+            if program_id_index == 0 || program_id_index >= static_len {
+                return Err(WireReader::malformed(&format!(
+                    "the program id index `{program_id_index}` is invalid"
+                )));
+            }
+
+            if let Some(index) = instruction
+                .accounts
+                .iter()
+                .find(|index| usize::from(**index) >= accounts_len)
+            {
+                return Err(WireReader::malformed(&format!(
+                    "the account index `{index}` of an instruction is out of range"
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn version(&self) -> TransactionVersion {
+        match self {
+            Self::Legacy(_) => TransactionVersion::Legacy,
+            Self::V0(_) => TransactionVersion::V0,
+        }
+    }
+
+    pub fn header(&self) -> &MessageHeader {
+        match self {
+            Self::Legacy(message) => &message.header,
+            Self::V0(message) => &message.header,
+        }
+    }
+
+    /// The account keys stored in the message, without the ones loaded from lookup tables
+    pub fn static_account_keys(&self) -> &[Pubkey] {
+        match self {
+            Self::Legacy(message) => &message.account_keys,
+            Self::V0(message) => &message.account_keys,
+        }
+    }
+
+    pub fn recent_blockhash(&self) -> &Blockhash {
+        match self {
+            Self::Legacy(message) => &message.recent_blockhash,
+            Self::V0(message) => &message.recent_blockhash,
+        }
+    }
+
+    pub fn instructions(&self) -> &[CompiledInstruction] {
+        match self {
+            Self::Legacy(message) => &message.instructions,
+            Self::V0(message) => &message.instructions,
+        }
+    }
+
+    /// Empty for legacy messages
+    pub fn address_table_lookups(&self) -> &[MessageAddressTableLookup] {
+        match self {
+            Self::Legacy(_) => &[],
+            Self::V0(message) => &message.address_table_lookups,
+        }
+    }
+
+    /// The first account key, which pays the fees of the transaction
+    pub fn fee_payer(&self) -> Option<&Pubkey> {
+        self.static_account_keys().first()
+    }
+
+    /// The signers, in the order of the signatures of the transaction
+    pub fn signers(&self) -> &[Pubkey] {
+        let keys = self.static_account_keys();
+        let len = usize::from(self.header().num_required_signatures).min(keys.len());
+
+        &keys[..len]
+    }
+
+    /// The number of accounts instructions can index, including the ones loaded from lookup tables
+    pub fn accounts_len(&self) -> usize {
+        self.static_account_keys().len() + self.loaded_accounts_len()
+    }
+
+    fn loaded_accounts_len(&self) -> usize {
+        self.address_table_lookups()
+            .iter()
+            .map(|lookup| lookup.writable_indexes.len() + lookup.readonly_indexes.len())
+            .sum()
+    }
+
+    fn loaded_writable_len(&self) -> usize {
+        self.address_table_lookups()
+            .iter()
+            .map(|lookup| lookup.writable_indexes.len())
+            .sum()
+    }
+
+    pub fn is_signer(&self, index: usize) -> bool {
+        index < usize::from(self.header().num_required_signatures)
+    }
+
+    /// Whether the message asks to write to the account. The runtime still
+    /// demotes programs and sysvars to read-only.
+    pub fn is_writable(&self, index: usize) -> bool {
+        let header = self.header();
+        let static_len = self.static_account_keys().len();
+        let num_signed = usize::from(header.num_required_signatures);
+
+        if index < num_signed {
+            index < num_signed.saturating_sub(header.num_readonly_signed_accounts.into())
+        } else if index < static_len {
+            index < static_len.saturating_sub(header.num_readonly_unsigned_accounts.into())
+        } else {
+            index - static_len < self.loaded_writable_len()
+        }
+    }
+
+    /// Where the account at `index` comes from. Accounts loaded from lookup tables
+    /// are ordered as all the writable ones, then all the read-only ones.
+    pub fn account_address(&self, index: usize) -> Option<AccountAddress> {
+        if let Some(pubkey) = self.static_account_keys().get(index) {
+            return Some(AccountAddress::Static { pubkey: *pubkey });
+        }
+
+        let loaded_index = index - self.static_account_keys().len();
+        let lookups = self.address_table_lookups();
+
+        lookups
+            .iter()
+            .flat_map(|lookup| {
+                lookup
+                    .writable_indexes
+                    .iter()
+                    .map(|index| (lookup.account_key, *index))
+            })
+            .chain(lookups.iter().flat_map(|lookup| {
+                lookup
+                    .readonly_indexes
+                    .iter()
+                    .map(|index| (lookup.account_key, *index))
+            }))
+            .nth(loaded_index)
+            .map(|(table, index)| AccountAddress::Lookup { table, index })
+    }
+
+    pub fn account(&self, index: usize) -> Option<TransactionAccount> {
+        Some(TransactionAccount {
+            address: self.account_address(index)?,
+            is_signer: self.is_signer(index),
+            is_writable: self.is_writable(index),
+        })
+    }
+
+    /// All the accounts instructions can index, in order
+    pub fn accounts(&self) -> Vec<TransactionAccount> {
+        (0..self.accounts_len())
+            .filter_map(|index| self.account(index))
+            .collect()
+    }
+}
+
+/// The address of an account. Lookup table entries only become addresses once
+/// the table is fetched from the cluster.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
+#[serde(tag = "source", rename_all = "snake_case")]
+pub enum AccountAddress {
+    Static { pubkey: Pubkey },
+    Lookup { table: Pubkey, index: u8 },
+}
+
+impl AccountAddress {
+    pub fn pubkey(&self) -> Option<&Pubkey> {
+        match self {
+            Self::Static { pubkey } => Some(pubkey),
+            Self::Lookup { .. } => None,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
+pub struct TransactionAccount {
+    pub address: AccountAddress,
+    pub is_signer: bool,
+    pub is_writable: bool,
+}
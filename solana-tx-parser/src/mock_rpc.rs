@@ -0,0 +1,119 @@
+use std::sync::{Arc, Mutex};
+
+use serde_json::{json, Value};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+};
+
+use crate::{JsonRpcCluster, SolanaTxParserUtils};
+
+/// What [MockRpc] answers to a request
+pub(crate) enum MockReply {
+    Result(Value),
+    Error {
+        code: i64,
+        message: &'static str,
+    },
+    /// A response that is not JSON-RPC, like the ones of a rate limiter
+    Http {
+        status: u16,
+        body: &'static str,
+    },
+}
+
+type Handler = dyn Fn(&str, &Value) -> MockReply + Send + Sync;
+
+/// A local stand-in for a Solana JSON-RPC node. `handler` answers each request
+/// from its method and params, the requests are kept for the tests to assert on.
+pub(crate) struct MockRpc {
+    origin: String,
+    requests: Arc<Mutex<Vec<Value>>>,
+}
+
+impl MockRpc {
+    pub(crate) async fn start(
+        handler: impl Fn(&str, &Value) -> MockReply + Send + Sync + 'static,
+    ) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let origin = format!("http://{}", listener.local_addr().unwrap());
+        let requests = Arc::new(Mutex::new(Vec::<Value>::new()));
+        let handler: Arc<Handler> = Arc::new(handler);
+
+        let received = requests.clone();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let request = Self::read_request(&mut stream).await;
+                received.lock().unwrap().push(request.clone());
+
+                let method = request["method"].as_str().unwrap_or_default();
+                let (status, body) = match handler(method, &request["params"]) {
+                    MockReply::Result(result) => (
+                        200,
+                        json!({ "jsonrpc": "2.0", "result": result, "id": request["id"] })
+                            .to_string(),
+                    ),
+                    MockReply::Error { code, message } => (
+                        200,
+                        json!({
+                            "jsonrpc": "2.0",
+                            "error": { "code": code, "message": message },
+                            "id": request["id"],
+                        })
+                        .to_string(),
+                    ),
+                    MockReply::Http { status, body } => (status, body.to_string()),
+                };
+
+                let response = format!(
+                    "HTTP/1.1 {status} Mock\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{body}",
+                    body.len()
+                );
+                stream.write_all(response.as_bytes()).await.ok();
+                stream.shutdown().await.ok();
+            }
+        });
+
+        Self { origin, requests }
+    }
+
+    /// The JSON body of a request
+    pub(crate) async fn read_request(stream: &mut TcpStream) -> Value {
+        let mut buffer = Vec::<u8>::new();
+        let mut chunk = [0u8; 4096];
+
+        let head_end = loop {
+            if let Some(position) = buffer.windows(4).position(|window| window == b"\r\n\r\n") {
+                break position;
+            }
+
+            let read = stream.read(&mut chunk).await.unwrap();
+            assert!(read > 0, "The request ended before its headers");
+            buffer.extend_from_slice(&chunk[..read]);
+        };
+
+        let content_length = String::from_utf8_lossy(&buffer[..head_end])
+            .split("\r\n")
+            .filter_map(|line| line.split_once(':'))
+            .find(|(name, _)| name.trim().eq_ignore_ascii_case("content-length"))
+            .map(|(_, value)| value.trim().parse::<usize>().unwrap())
+            .unwrap_or_default();
+
+        let mut body = buffer[head_end + 4..].to_vec();
+        while body.len() < content_length {
+            let read = stream.read(&mut chunk).await.unwrap();
+            assert!(read > 0, "The request ended before its body");
+            body.extend_from_slice(&chunk[..read]);
+        }
+
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    pub(crate) fn client(&self) -> SolanaTxParserUtils {
+        SolanaTxParserUtils::new(JsonRpcCluster::Custom(self.origin.clone()))
+    }
+
+    pub(crate) fn requests(&self) -> Vec<Value> {
+        self.requests.lock().unwrap().clone()
+    }
+}
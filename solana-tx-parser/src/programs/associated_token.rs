@@ -0,0 +1,104 @@
+use krill_common::KrillResult;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    AccountAddress, DecodedInstruction, InstructionAccounts, InstructionData, InstructionDecoder,
+    Pubkey, TransactionAccount,
+};
+
+/// The instructions of the Associated Token Account program, which creates the
+/// token account a wallet holds a mint in at an address derived from both
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+#[serde(tag = "instruction", rename_all = "snake_case")]
+pub enum AssociatedTokenInstruction {
+    Create {
+        funding_account: AccountAddress,
+        associated_account: AccountAddress,
+        wallet: AccountAddress,
+        mint: AccountAddress,
+        token_program: AccountAddress,
+    },
+    /// Like [Self::Create] but does not fail when the account already exists
+    CreateIdempotent {
+        funding_account: AccountAddress,
+        associated_account: AccountAddress,
+        wallet: AccountAddress,
+        mint: AccountAddress,
+        token_program: AccountAddress,
+    },
+    /// Moves the tokens of an associated account owned by another associated account
+    /// back to the wallet, and closes it
+    RecoverNested {
+        nested_account: AccountAddress,
+        nested_mint: AccountAddress,
+        destination_account: AccountAddress,
+        owner_account: AccountAddress,
+        owner_mint: AccountAddress,
+        wallet: AccountAddress,
+        token_program: AccountAddress,
+    },
+}
+
+impl AssociatedTokenInstruction {
+    /// `ATokenGPvbdGVxr1b2hvZbsiqW5xWH25efTNsLJA8knL`
+    pub const PROGRAM_ID: Pubkey = Pubkey::new([
+        140, 151, 37, 143, 78, 36, 137, 241, 187, 61, 16, 41, 20, 142, 13, 131, 11, 90, 19, 153,
+        218, 255, 16, 132, 4, 142, 123, 216, 219, 233, 248, 89,
+    ]);
+
+    /// Empty data is the original [Self::Create] instruction
+    pub fn decode(accounts: &[TransactionAccount], data: &[u8]) -> KrillResult<Self> {
+        let accounts = InstructionAccounts::new(accounts);
+
+        let instruction = match data.first().copied().unwrap_or_default() {
+            0 => Self::Create {
+                funding_account: accounts.get(0, "funding account")?,
+                associated_account: accounts.get(1, "associated account")?,
+                wallet: accounts.get(2, "wallet")?,
+                mint: accounts.get(3, "mint")?,
+                token_program: accounts.get(5, "token program")?,
+            },
+            1 => Self::CreateIdempotent {
+                funding_account: accounts.get(0, "funding account")?,
+                associated_account: accounts.get(1, "associated account")?,
+                wallet: accounts.get(2, "wallet")?,
+                mint: accounts.get(3, "mint")?,
+                token_program: accounts.get(5, "token program")?,
+            },
+            2 => Self::RecoverNested {
+                nested_account: accounts.get(0, "nested account")?,
+                nested_mint: accounts.get(1, "nested mint")?,
+                destination_account: accounts.get(2, "destination account")?,
+                owner_account: accounts.get(3, "owner account")?,
+                owner_mint: accounts.get(4, "owner mint")?,
+                wallet: accounts.get(5, "wallet")?,
+                token_program: accounts.get(6, "token program")?,
+            },
+            unknown => {
+                return Err(InstructionData::invalid(&format!(
+                    "`{unknown}` is not an Associated Token Account program instruction"
+                )))
+            }
+        };
+
+        Ok(instruction)
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct AssociatedTokenDecoder;
+
+impl InstructionDecoder for AssociatedTokenDecoder {
+    fn program_ids(&self) -> Vec<Pubkey> {
+        vec![AssociatedTokenInstruction::PROGRAM_ID]
+    }
+
+    fn decode(
+        &self,
+        _program_id: &Pubkey,
+        accounts: &[TransactionAccount],
+        data: &[u8],
+    ) -> KrillResult<DecodedInstruction> {
+        AssociatedTokenInstruction::decode(accounts, data).map(DecodedInstruction::AssociatedToken)
+    }
+}
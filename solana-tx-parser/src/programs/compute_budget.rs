@@ -0,0 +1,86 @@
+use krill_common::KrillResult;
+use serde::{Deserialize, Serialize};
+
+use crate::{DecodedInstruction, InstructionData, InstructionDecoder, Pubkey, TransactionAccount};
+
+/// The instructions of the Compute Budget program, which set the limits and the
+/// priority fee of the whole transaction
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+#[serde(tag = "instruction", rename_all = "snake_case")]
+pub enum ComputeBudgetInstruction {
+    /// Deprecated, rejected by current validators
+    RequestUnits {
+        units: u32,
+        additional_fee: u32,
+    },
+    RequestHeapFrame {
+        bytes: u32,
+    },
+    SetComputeUnitLimit {
+        units: u32,
+    },
+    /// The priority fee in micro-lamports per compute unit
+    SetComputeUnitPrice {
+        micro_lamports: u64,
+    },
+    SetLoadedAccountsDataSizeLimit {
+        bytes: u32,
+    },
+}
+
+impl ComputeBudgetInstruction {
+    /// `ComputeBudget111111111111111111111111111111`
+    pub const PROGRAM_ID: Pubkey = Pubkey::new([
+        3, 6, 70, 111, 229, 33, 23, 50, 255, 236, 173, 186, 114, 195, 155, 231, 188, 140, 229, 187,
+        197, 247, 18, 107, 44, 67, 155, 58, 64, 0, 0, 0,
+    ]);
+
+    /// The instruction is a `u8` followed by the borsh serialized fields
+    pub fn decode(data: &[u8]) -> KrillResult<Self> {
+        let mut data = InstructionData::new(data);
+
+        let instruction = match data.read_u8()? {
+            0 => Self::RequestUnits {
+                units: data.read_u32()?,
+                additional_fee: data.read_u32()?,
+            },
+            1 => Self::RequestHeapFrame {
+                bytes: data.read_u32()?,
+            },
+            2 => Self::SetComputeUnitLimit {
+                units: data.read_u32()?,
+            },
+            3 => Self::SetComputeUnitPrice {
+                micro_lamports: data.read_u64()?,
+            },
+            4 => Self::SetLoadedAccountsDataSizeLimit {
+                bytes: data.read_u32()?,
+            },
+            unknown => {
+                return Err(InstructionData::invalid(&format!(
+                    "`{unknown}` is not a Compute Budget program instruction"
+                )))
+            }
+        };
+
+        Ok(instruction)
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ComputeBudgetDecoder;
+
+impl InstructionDecoder for ComputeBudgetDecoder {
+    fn program_ids(&self) -> Vec<Pubkey> {
+        vec![ComputeBudgetInstruction::PROGRAM_ID]
+    }
+
+    fn decode(
+        &self,
+        _program_id: &Pubkey,
+        _accounts: &[TransactionAccount],
+        data: &[u8],
+    ) -> KrillResult<DecodedInstruction> {
+        ComputeBudgetInstruction::decode(data).map(DecodedInstruction::ComputeBudget)
+    }
+}
@@ -0,0 +1,56 @@
+use krill_common::KrillResult;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    AccountAddress, DecodedInstruction, InstructionAccounts, InstructionData, InstructionDecoder,
+    Pubkey, TransactionAccount,
+};
+
+/// A memo recorded in the transaction logs. All the accounts of the instruction must sign it.
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+pub struct MemoInstruction {
+    pub memo: String,
+    pub signers: Vec<AccountAddress>,
+}
+
+impl MemoInstruction {
+    /// `MemoSq4gqABAXKb96qnH8TysNcWxMyWCqXgDLGmfcHr`
+    pub const PROGRAM_ID: Pubkey = Pubkey::new([
+        5, 74, 83, 90, 153, 41, 33, 6, 77, 36, 232, 113, 96, 218, 56, 124, 124, 53, 181, 221, 188,
+        146, 187, 129, 228, 31, 168, 64, 65, 5, 68, 141,
+    ]);
+    /// `Memo1UhkJRfHyvLMcVucJwxXeuD728EqVDDwQDxFMNo`, the first version which ignores the accounts
+    pub const PROGRAM_ID_V1: Pubkey = Pubkey::new([
+        5, 74, 83, 80, 248, 93, 200, 130, 214, 20, 165, 86, 114, 120, 138, 41, 109, 223, 30, 171,
+        171, 208, 166, 6, 120, 136, 73, 50, 244, 238, 246, 160,
+    ]);
+
+    /// The whole instruction data is the UTF-8 memo
+    pub fn decode(accounts: &[TransactionAccount], data: &[u8]) -> KrillResult<Self> {
+        let memo = String::from_utf8(data.to_vec())
+            .map_err(|_| InstructionData::invalid("the memo is not valid UTF-8"))?;
+
+        Ok(Self {
+            memo,
+            signers: InstructionAccounts::new(accounts).rest(0),
+        })
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct MemoDecoder;
+
+impl InstructionDecoder for MemoDecoder {
+    fn program_ids(&self) -> Vec<Pubkey> {
+        vec![MemoInstruction::PROGRAM_ID, MemoInstruction::PROGRAM_ID_V1]
+    }
+
+    fn decode(
+        &self,
+        _program_id: &Pubkey,
+        accounts: &[TransactionAccount],
+        data: &[u8],
+    ) -> KrillResult<DecodedInstruction> {
+        MemoInstruction::decode(accounts, data).map(DecodedInstruction::Memo)
+    }
+}
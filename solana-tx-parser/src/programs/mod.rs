@@ -0,0 +1,179 @@
+mod system;
+pub use system::*;
+
+mod compute_budget;
+pub use compute_budget::*;
+
+mod memo;
+pub use memo::*;
+
+mod associated_token;
+pub use associated_token::*;
+
+mod token;
+pub use token::*;
+
+mod token_extensions;
+pub use token_extensions::*;
+
+use krill_common::{KrillError, KrillResult};
+
+use crate::{AccountAddress, Pubkey, TransactionAccount};
+
+/// Reads the fields of instruction data. Native programs serialize with bincode
+/// and the SPL programs with their own packing, both little-endian.
+#[derive(Debug)]
+pub struct InstructionData<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> InstructionData<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        Self { data, position: 0 }
+    }
+
+    pub fn read_bytes(&mut self, len: usize) -> KrillResult<&'a [u8]> {
+        let end = self
+            .position
+            .checked_add(len)
+            .filter(|end| *end <= self.data.len())
+            .ok_or_else(|| Self::invalid("the instruction data is too short"))?;
+
+        let bytes = &self.data[self.position..end];
+        self.position = end;
+
+        Ok(bytes)
+    }
+
+    pub fn read_array<const N: usize>(&mut self) -> KrillResult<[u8; N]> {
+        let mut array = [0u8; N];
+        array.copy_from_slice(self.read_bytes(N)?);
+
+        Ok(array)
+    }
+
+    pub fn read_u8(&mut self) -> KrillResult<u8> {
+        Ok(self.read_array::<1>()?[0])
+    }
+
+    pub fn read_u16(&mut self) -> KrillResult<u16> {
+        Ok(u16::from_le_bytes(self.read_array()?))
+    }
+
+    pub fn read_i16(&mut self) -> KrillResult<i16> {
+        Ok(i16::from_le_bytes(self.read_array()?))
+    }
+
+    pub fn read_u32(&mut self) -> KrillResult<u32> {
+        Ok(u32::from_le_bytes(self.read_array()?))
+    }
+
+    pub fn read_u64(&mut self) -> KrillResult<u64> {
+        Ok(u64::from_le_bytes(self.read_array()?))
+    }
+
+    pub fn read_i64(&mut self) -> KrillResult<i64> {
+        Ok(i64::from_le_bytes(self.read_array()?))
+    }
+
+    pub fn read_f64(&mut self) -> KrillResult<f64> {
+        Ok(f64::from_le_bytes(self.read_array()?))
+    }
+
+    pub fn read_pubkey(&mut self) -> KrillResult<Pubkey> {
+        Ok(Pubkey::new(self.read_array()?))
+    }
+
+    /// A `COption<Pubkey>` packed as a one byte tag followed by the key when it is set
+    pub fn read_optional_pubkey(&mut self) -> KrillResult<Option<Pubkey>> {
+        match self.read_u8()? {
+            0 => Ok(None),
+            1 => Ok(Some(self.read_pubkey()?)),
+            tag => Err(Self::invalid(&format!(
+                "`{tag}` is not a valid tag for an optional public key"
+            ))),
+        }
+    }
+
+    /// An `OptionalNonZeroPubkey` of the Token-2022 extensions, all zeroes when it is not set
+    pub fn read_nonzero_pubkey(&mut self) -> KrillResult<Option<Pubkey>> {
+        let pubkey = self.read_pubkey()?;
+
+        Ok((pubkey != Pubkey::default()).then_some(pubkey))
+    }
+
+    /// A string prefixed with its length as a `u64`, as serialized by bincode
+    pub fn read_bincode_string(&mut self) -> KrillResult<String> {
+        let len = usize::try_from(self.read_u64()?)
+            .map_err(|_| Self::invalid("the length of a string is too large"))?;
+
+        self.read_utf8(len)
+    }
+
+    /// A string prefixed with its length as a `u32`, as serialized by borsh
+    pub fn read_borsh_string(&mut self) -> KrillResult<String> {
+        let len = usize::try_from(self.read_u32()?)
+            .map_err(|_| Self::invalid("the length of a string is too large"))?;
+
+        self.read_utf8(len)
+    }
+
+    fn read_utf8(&mut self, len: usize) -> KrillResult<String> {
+        let bytes = self.read_bytes(len)?;
+
+        String::from_utf8(bytes.to_vec()).map_err(|_| Self::invalid("a string is not valid UTF-8"))
+    }
+
+    /// The bytes that have not been read yet
+    pub fn rest(&mut self) -> &'a [u8] {
+        let rest = &self.data[self.position..];
+        self.position = self.data.len();
+
+        rest
+    }
+
+    pub fn invalid(reason: &str) -> KrillError {
+        KrillError::InvalidInstructionData(reason.to_string())
+    }
+}
+
+/// Names the accounts of an instruction by their position
+#[derive(Debug)]
+pub struct InstructionAccounts<'a> {
+    accounts: &'a [TransactionAccount],
+}
+
+impl<'a> InstructionAccounts<'a> {
+    pub fn new(accounts: &'a [TransactionAccount]) -> Self {
+        Self { accounts }
+    }
+
+    pub fn get(&self, index: usize, name: &str) -> KrillResult<AccountAddress> {
+        self.accounts
+            .get(index)
+            .map(|account| account.address)
+            .ok_or_else(|| InstructionData::invalid(&format!("the `{name}` account is missing")))
+    }
+
+    pub fn optional(&self, index: usize) -> Option<AccountAddress> {
+        self.accounts.get(index).map(|account| account.address)
+    }
+
+    /// The accounts from `index`, for example the signers of a multisig authority
+    pub fn rest(&self, index: usize) -> Vec<AccountAddress> {
+        self.accounts
+            .iter()
+            .skip(index)
+            .map(|account| account.address)
+            .collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.accounts.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.accounts.is_empty()
+    }
+}
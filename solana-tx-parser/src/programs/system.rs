@@ -0,0 +1,198 @@
+use krill_common::KrillResult;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    AccountAddress, DecodedInstruction, InstructionAccounts, InstructionData, InstructionDecoder,
+    Pubkey, TransactionAccount,
+};
+
+/// The instructions of the System program, which creates accounts and transfers SOL.
+/// Amounts are in lamports and sizes in bytes.
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+#[serde(tag = "instruction", rename_all = "snake_case")]
+pub enum SystemInstruction {
+    CreateAccount {
+        funding_account: AccountAddress,
+        new_account: AccountAddress,
+        lamports: u64,
+        space: u64,
+        owner: Pubkey,
+    },
+    Assign {
+        account: AccountAddress,
+        owner: Pubkey,
+    },
+    Transfer {
+        from: AccountAddress,
+        to: AccountAddress,
+        lamports: u64,
+    },
+    CreateAccountWithSeed {
+        funding_account: AccountAddress,
+        new_account: AccountAddress,
+        base: Pubkey,
+        seed: String,
+        lamports: u64,
+        space: u64,
+        owner: Pubkey,
+    },
+    AdvanceNonceAccount {
+        nonce_account: AccountAddress,
+        nonce_authority: AccountAddress,
+    },
+    WithdrawNonceAccount {
+        nonce_account: AccountAddress,
+        to: AccountAddress,
+        nonce_authority: AccountAddress,
+        lamports: u64,
+    },
+    InitializeNonceAccount {
+        nonce_account: AccountAddress,
+        nonce_authority: Pubkey,
+    },
+    AuthorizeNonceAccount {
+        nonce_account: AccountAddress,
+        nonce_authority: AccountAddress,
+        new_authority: Pubkey,
+    },
+    Allocate {
+        account: AccountAddress,
+        space: u64,
+    },
+    AllocateWithSeed {
+        account: AccountAddress,
+        base: Pubkey,
+        seed: String,
+        space: u64,
+        owner: Pubkey,
+    },
+    AssignWithSeed {
+        account: AccountAddress,
+        base: Pubkey,
+        seed: String,
+        owner: Pubkey,
+    },
+    TransferWithSeed {
+        from: AccountAddress,
+        from_base: AccountAddress,
+        to: AccountAddress,
+        lamports: u64,
+        from_seed: String,
+        from_owner: Pubkey,
+    },
+    UpgradeNonceAccount {
+        nonce_account: AccountAddress,
+    },
+}
+
+impl SystemInstruction {
+    /// `11111111111111111111111111111111`
+    pub const PROGRAM_ID: Pubkey = Pubkey::new([0u8; 32]);
+
+    /// The instruction is a `u32` followed by the bincode serialized fields
+    pub fn decode(accounts: &[TransactionAccount], data: &[u8]) -> KrillResult<Self> {
+        let accounts = InstructionAccounts::new(accounts);
+        let mut data = InstructionData::new(data);
+
+        let instruction = match data.read_u32()? {
+            0 => Self::CreateAccount {
+                funding_account: accounts.get(0, "funding account")?,
+                new_account: accounts.get(1, "new account")?,
+                lamports: data.read_u64()?,
+                space: data.read_u64()?,
+                owner: data.read_pubkey()?,
+            },
+            1 => Self::Assign {
+                account: accounts.get(0, "account")?,
+                owner: data.read_pubkey()?,
+            },
+            2 => Self::Transfer {
+                from: accounts.get(0, "from")?,
+                to: accounts.get(1, "to")?,
+                lamports: data.read_u64()?,
+            },
+            3 => Self::CreateAccountWithSeed {
+                funding_account: accounts.get(0, "funding account")?,
+                new_account: accounts.get(1, "new account")?,
+                base: data.read_pubkey()?,
+                seed: data.read_bincode_string()?,
+                lamports: data.read_u64()?,
+                space: data.read_u64()?,
+                owner: data.read_pubkey()?,
+            },
+            // The recent blockhashes and rent sysvars are left out
+            4 => Self::AdvanceNonceAccount {
+                nonce_account: accounts.get(0, "nonce account")?,
+                nonce_authority: accounts.get(2, "nonce authority")?,
+            },
+            5 => Self::WithdrawNonceAccount {
+                nonce_account: accounts.get(0, "nonce account")?,
+                to: accounts.get(1, "to")?,
+                nonce_authority: accounts.get(4, "nonce authority")?,
+                lamports: data.read_u64()?,
+            },
+            6 => Self::InitializeNonceAccount {
+                nonce_account: accounts.get(0, "nonce account")?,
+                nonce_authority: data.read_pubkey()?,
+            },
+            7 => Self::AuthorizeNonceAccount {
+                nonce_account: accounts.get(0, "nonce account")?,
+                nonce_authority: accounts.get(1, "nonce authority")?,
+                new_authority: data.read_pubkey()?,
+            },
+            8 => Self::Allocate {
+                account: accounts.get(0, "account")?,
+                space: data.read_u64()?,
+            },
+            9 => Self::AllocateWithSeed {
+                account: accounts.get(0, "account")?,
+                base: data.read_pubkey()?,
+                seed: data.read_bincode_string()?,
+                space: data.read_u64()?,
+                owner: data.read_pubkey()?,
+            },
+            10 => Self::AssignWithSeed {
+                account: accounts.get(0, "account")?,
+                base: data.read_pubkey()?,
+                seed: data.read_bincode_string()?,
+                owner: data.read_pubkey()?,
+            },
+            11 => Self::TransferWithSeed {
+                from: accounts.get(0, "from")?,
+                from_base: accounts.get(1, "from base")?,
+                to: accounts.get(2, "to")?,
+                lamports: data.read_u64()?,
+                from_seed: data.read_bincode_string()?,
+                from_owner: data.read_pubkey()?,
+            },
+            12 => Self::UpgradeNonceAccount {
+                nonce_account: accounts.get(0, "nonce account")?,
+            },
+            unknown => {
+                return Err(InstructionData::invalid(&format!(
+                    "`{unknown}` is not a System program instruction"
+                )))
+            }
+        };
+
+        Ok(instruction)
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SystemDecoder;
+
+impl InstructionDecoder for SystemDecoder {
+    fn program_ids(&self) -> Vec<Pubkey> {
+        vec![SystemInstruction::PROGRAM_ID]
+    }
+
+    fn decode(
+        &self,
+        _program_id: &Pubkey,
+        accounts: &[TransactionAccount],
+        data: &[u8],
+    ) -> KrillResult<DecodedInstruction> {
+        SystemInstruction::decode(accounts, data).map(DecodedInstruction::System)
+    }
+}
@@ -0,0 +1,907 @@
+use krill_common::KrillResult;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    AccountAddress, AccountState, ConfidentialExtension, DecodedInstruction, InstructionAccounts,
+    InstructionData, InstructionDecoder, Pubkey, TokenMetadataField, TransactionAccount,
+};
+
+/// The instructions of the SPL Token program and of Token-2022, which accepts the
+/// same instructions and adds its extensions. Amounts are in the smallest unit of
+/// the mint, `decimals` is the number of decimals of the mint.
+///
+/// `signers` are the signers of a multisig authority. Token-2022 transfers also
+/// pass the extra accounts of a transfer hook there.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+#[serde(tag = "instruction", rename_all = "snake_case")]
+pub enum TokenInstruction {
+    InitializeMint {
+        mint: AccountAddress,
+        decimals: u8,
+        mint_authority: Pubkey,
+        freeze_authority: Option<Pubkey>,
+    },
+    InitializeAccount {
+        account: AccountAddress,
+        mint: AccountAddress,
+        owner: AccountAddress,
+    },
+    InitializeMultisig {
+        multisig: AccountAddress,
+        signers: Vec<AccountAddress>,
+        /// The number of signers required
+        m: u8,
+    },
+    Transfer {
+        source: AccountAddress,
+        destination: AccountAddress,
+        authority: AccountAddress,
+        signers: Vec<AccountAddress>,
+        amount: u64,
+    },
+    Approve {
+        source: AccountAddress,
+        delegate: AccountAddress,
+        owner: AccountAddress,
+        signers: Vec<AccountAddress>,
+        amount: u64,
+    },
+    Revoke {
+        source: AccountAddress,
+        owner: AccountAddress,
+        signers: Vec<AccountAddress>,
+    },
+    SetAuthority {
+        /// The mint or the token account
+        account: AccountAddress,
+        current_authority: AccountAddress,
+        signers: Vec<AccountAddress>,
+        authority_type: AuthorityType,
+        new_authority: Option<Pubkey>,
+    },
+    MintTo {
+        mint: AccountAddress,
+        account: AccountAddress,
+        mint_authority: AccountAddress,
+        signers: Vec<AccountAddress>,
+        amount: u64,
+    },
+    Burn {
+        account: AccountAddress,
+        mint: AccountAddress,
+        authority: AccountAddress,
+        signers: Vec<AccountAddress>,
+        amount: u64,
+    },
+    CloseAccount {
+        account: AccountAddress,
+        destination: AccountAddress,
+        owner: AccountAddress,
+        signers: Vec<AccountAddress>,
+    },
+    FreezeAccount {
+        account: AccountAddress,
+        mint: AccountAddress,
+        freeze_authority: AccountAddress,
+        signers: Vec<AccountAddress>,
+    },
+    ThawAccount {
+        account: AccountAddress,
+        mint: AccountAddress,
+        freeze_authority: AccountAddress,
+        signers: Vec<AccountAddress>,
+    },
+    TransferChecked {
+        source: AccountAddress,
+        mint: AccountAddress,
+        destination: AccountAddress,
+        authority: AccountAddress,
+        signers: Vec<AccountAddress>,
+        amount: u64,
+        decimals: u8,
+    },
+    ApproveChecked {
+        source: AccountAddress,
+        mint: AccountAddress,
+        delegate: AccountAddress,
+        owner: AccountAddress,
+        signers: Vec<AccountAddress>,
+        amount: u64,
+        decimals: u8,
+    },
+    MintToChecked {
+        mint: AccountAddress,
+        account: AccountAddress,
+        mint_authority: AccountAddress,
+        signers: Vec<AccountAddress>,
+        amount: u64,
+        decimals: u8,
+    },
+    BurnChecked {
+        account: AccountAddress,
+        mint: AccountAddress,
+        authority: AccountAddress,
+        signers: Vec<AccountAddress>,
+        amount: u64,
+        decimals: u8,
+    },
+    InitializeAccount2 {
+        account: AccountAddress,
+        mint: AccountAddress,
+        owner: Pubkey,
+    },
+    /// Updates the amount of a wrapped SOL account to its lamports
+    SyncNative {
+        account: AccountAddress,
+    },
+    InitializeAccount3 {
+        account: AccountAddress,
+        mint: AccountAddress,
+        owner: Pubkey,
+    },
+    InitializeMultisig2 {
+        multisig: AccountAddress,
+        signers: Vec<AccountAddress>,
+        m: u8,
+    },
+    InitializeMint2 {
+        mint: AccountAddress,
+        decimals: u8,
+        mint_authority: Pubkey,
+        freeze_authority: Option<Pubkey>,
+    },
+    GetAccountDataSize {
+        mint: AccountAddress,
+        extension_types: Vec<u16>,
+    },
+    InitializeImmutableOwner {
+        account: AccountAddress,
+    },
+    AmountToUiAmount {
+        mint: AccountAddress,
+        amount: u64,
+    },
+    UiAmountToAmount {
+        mint: AccountAddress,
+        ui_amount: String,
+    },
+    InitializeMintCloseAuthority {
+        mint: AccountAddress,
+        close_authority: Option<Pubkey>,
+    },
+    Reallocate {
+        account: AccountAddress,
+        payer: AccountAddress,
+        owner: AccountAddress,
+        signers: Vec<AccountAddress>,
+        extension_types: Vec<u16>,
+    },
+    CreateNativeMint {
+        payer: AccountAddress,
+        native_mint: AccountAddress,
+    },
+    InitializeNonTransferableMint {
+        mint: AccountAddress,
+    },
+    /// The delegate can transfer and burn the tokens of every account of the mint
+    InitializePermanentDelegate {
+        mint: AccountAddress,
+        delegate: Pubkey,
+    },
+    WithdrawExcessLamports {
+        source: AccountAddress,
+        destination: AccountAddress,
+        authority: AccountAddress,
+        signers: Vec<AccountAddress>,
+    },
+    InitializeTransferFeeConfig {
+        mint: AccountAddress,
+        transfer_fee_config_authority: Option<Pubkey>,
+        withdraw_withheld_authority: Option<Pubkey>,
+        /// The fee in hundredths of a percent of the amount
+        transfer_fee_basis_points: u16,
+        maximum_fee: u64,
+    },
+    TransferCheckedWithFee {
+        source: AccountAddress,
+        mint: AccountAddress,
+        destination: AccountAddress,
+        authority: AccountAddress,
+        signers: Vec<AccountAddress>,
+        amount: u64,
+        decimals: u8,
+        fee: u64,
+    },
+    WithdrawWithheldTokensFromMint {
+        mint: AccountAddress,
+        destination: AccountAddress,
+        authority: AccountAddress,
+        signers: Vec<AccountAddress>,
+    },
+    WithdrawWithheldTokensFromAccounts {
+        mint: AccountAddress,
+        destination: AccountAddress,
+        authority: AccountAddress,
+        signers: Vec<AccountAddress>,
+        sources: Vec<AccountAddress>,
+    },
+    HarvestWithheldTokensToMint {
+        mint: AccountAddress,
+        sources: Vec<AccountAddress>,
+    },
+    SetTransferFee {
+        mint: AccountAddress,
+        authority: AccountAddress,
+        signers: Vec<AccountAddress>,
+        transfer_fee_basis_points: u16,
+        maximum_fee: u64,
+    },
+    InitializeDefaultAccountState {
+        mint: AccountAddress,
+        state: AccountState,
+    },
+    UpdateDefaultAccountState {
+        mint: AccountAddress,
+        freeze_authority: AccountAddress,
+        signers: Vec<AccountAddress>,
+        state: AccountState,
+    },
+    EnableRequiredMemoTransfers {
+        account: AccountAddress,
+        owner: AccountAddress,
+        signers: Vec<AccountAddress>,
+    },
+    DisableRequiredMemoTransfers {
+        account: AccountAddress,
+        owner: AccountAddress,
+        signers: Vec<AccountAddress>,
+    },
+    InitializeInterestBearingMint {
+        mint: AccountAddress,
+        rate_authority: Option<Pubkey>,
+        /// The yearly rate in basis points
+        rate: i16,
+    },
+    UpdateInterestRate {
+        mint: AccountAddress,
+        rate_authority: AccountAddress,
+        signers: Vec<AccountAddress>,
+        rate: i16,
+    },
+    EnableCpiGuard {
+        account: AccountAddress,
+        owner: AccountAddress,
+        signers: Vec<AccountAddress>,
+    },
+    DisableCpiGuard {
+        account: AccountAddress,
+        owner: AccountAddress,
+        signers: Vec<AccountAddress>,
+    },
+    InitializeTransferHook {
+        mint: AccountAddress,
+        authority: Option<Pubkey>,
+        /// The program every transfer of the mint calls
+        program_id: Option<Pubkey>,
+    },
+    UpdateTransferHook {
+        mint: AccountAddress,
+        authority: AccountAddress,
+        signers: Vec<AccountAddress>,
+        program_id: Option<Pubkey>,
+    },
+    InitializeMetadataPointer {
+        mint: AccountAddress,
+        authority: Option<Pubkey>,
+        metadata_address: Option<Pubkey>,
+    },
+    UpdateMetadataPointer {
+        mint: AccountAddress,
+        authority: AccountAddress,
+        signers: Vec<AccountAddress>,
+        metadata_address: Option<Pubkey>,
+    },
+    InitializeGroupPointer {
+        mint: AccountAddress,
+        authority: Option<Pubkey>,
+        group_address: Option<Pubkey>,
+    },
+    UpdateGroupPointer {
+        mint: AccountAddress,
+        authority: AccountAddress,
+        signers: Vec<AccountAddress>,
+        group_address: Option<Pubkey>,
+    },
+    InitializeGroupMemberPointer {
+        mint: AccountAddress,
+        authority: Option<Pubkey>,
+        member_address: Option<Pubkey>,
+    },
+    UpdateGroupMemberPointer {
+        mint: AccountAddress,
+        authority: AccountAddress,
+        signers: Vec<AccountAddress>,
+        member_address: Option<Pubkey>,
+    },
+    InitializeScaledUiAmount {
+        mint: AccountAddress,
+        authority: Option<Pubkey>,
+        multiplier: f64,
+    },
+    UpdateScaledUiAmountMultiplier {
+        mint: AccountAddress,
+        authority: AccountAddress,
+        signers: Vec<AccountAddress>,
+        multiplier: f64,
+        /// The unix timestamp the new multiplier applies from
+        effective_timestamp: i64,
+    },
+    InitializePausable {
+        mint: AccountAddress,
+        authority: Pubkey,
+    },
+    Pause {
+        mint: AccountAddress,
+        authority: AccountAddress,
+        signers: Vec<AccountAddress>,
+    },
+    Resume {
+        mint: AccountAddress,
+        authority: AccountAddress,
+        signers: Vec<AccountAddress>,
+    },
+    /// The confidential extensions carry zero-knowledge proofs, only the
+    /// extension and its instruction number are decoded
+    Confidential {
+        extension: ConfidentialExtension,
+        extension_instruction: u8,
+    },
+    InitializeTokenMetadata {
+        metadata: AccountAddress,
+        update_authority: AccountAddress,
+        mint: AccountAddress,
+        mint_authority: AccountAddress,
+        name: String,
+        symbol: String,
+        uri: String,
+    },
+    UpdateTokenMetadataField {
+        metadata: AccountAddress,
+        update_authority: AccountAddress,
+        field: TokenMetadataField,
+        value: String,
+    },
+    RemoveTokenMetadataKey {
+        metadata: AccountAddress,
+        update_authority: AccountAddress,
+        /// Does not fail when the key does not exist
+        idempotent: bool,
+        key: String,
+    },
+    UpdateTokenMetadataAuthority {
+        metadata: AccountAddress,
+        update_authority: AccountAddress,
+        new_authority: Option<Pubkey>,
+    },
+}
+
+impl TokenInstruction {
+    /// `TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA`
+    pub const PROGRAM_ID: Pubkey = Pubkey::new([
+        6, 221, 246, 225, 215, 101, 161, 147, 217, 203, 225, 70, 206, 235, 121, 172, 28, 180, 133,
+        237, 95, 91, 55, 145, 58, 140, 245, 133, 126, 255, 0, 169,
+    ]);
+    /// `TokenzQdBNbLqP5VEhdkAS6EPFLC1PHnBqCXEpPxuEb`
+    pub const PROGRAM_ID_2022: Pubkey = Pubkey::new([
+        6, 221, 246, 225, 238, 117, 143, 222, 24, 66, 93, 188, 228, 108, 205, 218, 182, 26, 252,
+        77, 131, 185, 13, 39, 254, 189, 249, 40, 216, 161, 139, 252,
+    ]);
+    /// The instructions the SPL Token program has, Token-2022 continues from there
+    const LAST_TOKEN_INSTRUCTION: u8 = 24;
+
+    /// The instruction is a `u8` followed by the packed fields. Token-2022 extensions
+    /// add a second `u8` for the instruction of the extension.
+    pub fn decode(
+        accounts: &[TransactionAccount],
+        data: &[u8],
+        is_token_2022: bool,
+    ) -> KrillResult<Self> {
+        if is_token_2022 {
+            if let Some(instruction) = Self::decode_token_metadata(accounts, data)? {
+                return Ok(instruction);
+            }
+        }
+
+        let accounts = InstructionAccounts::new(accounts);
+        let mut data = InstructionData::new(data);
+
+        let tag = data.read_u8()?;
+        if tag > Self::LAST_TOKEN_INSTRUCTION && !is_token_2022 {
+            return Err(InstructionData::invalid(&format!(
+                "`{tag}` is a Token-2022 instruction, not a Token program instruction"
+            )));
+        }
+
+        let instruction = match tag {
+            0 => Self::InitializeMint {
+                mint: accounts.get(0, "mint")?,
+                decimals: data.read_u8()?,
+                mint_authority: data.read_pubkey()?,
+                freeze_authority: data.read_optional_pubkey()?,
+            },
+            1 => Self::InitializeAccount {
+                account: accounts.get(0, "account")?,
+                mint: accounts.get(1, "mint")?,
+                owner: accounts.get(2, "owner")?,
+            },
+            2 => Self::InitializeMultisig {
+                multisig: accounts.get(0, "multisig")?,
+                signers: accounts.rest(2),
+                m: data.read_u8()?,
+            },
+            3 => Self::Transfer {
+                source: accounts.get(0, "source")?,
+                destination: accounts.get(1, "destination")?,
+                authority: accounts.get(2, "authority")?,
+                signers: accounts.rest(3),
+                amount: data.read_u64()?,
+            },
+            4 => Self::Approve {
+                source: accounts.get(0, "source")?,
+                delegate: accounts.get(1, "delegate")?,
+                owner: accounts.get(2, "owner")?,
+                signers: accounts.rest(3),
+                amount: data.read_u64()?,
+            },
+            5 => Self::Revoke {
+                source: accounts.get(0, "source")?,
+                owner: accounts.get(1, "owner")?,
+                signers: accounts.rest(2),
+            },
+            6 => Self::SetAuthority {
+                account: accounts.get(0, "account")?,
+                current_authority: accounts.get(1, "current authority")?,
+                signers: accounts.rest(2),
+                authority_type: AuthorityType::parse(data.read_u8()?)?,
+                new_authority: data.read_optional_pubkey()?,
+            },
+            7 => Self::MintTo {
+                mint: accounts.get(0, "mint")?,
+                account: accounts.get(1, "account")?,
+                mint_authority: accounts.get(2, "mint authority")?,
+                signers: accounts.rest(3),
+                amount: data.read_u64()?,
+            },
+            8 => Self::Burn {
+                account: accounts.get(0, "account")?,
+                mint: accounts.get(1, "mint")?,
+                authority: accounts.get(2, "authority")?,
+                signers: accounts.rest(3),
+                amount: data.read_u64()?,
+            },
+            9 => Self::CloseAccount {
+                account: accounts.get(0, "account")?,
+                destination: accounts.get(1, "destination")?,
+                owner: accounts.get(2, "owner")?,
+                signers: accounts.rest(3),
+            },
+            10 => Self::FreezeAccount {
+                account: accounts.get(0, "account")?,
+                mint: accounts.get(1, "mint")?,
+                freeze_authority: accounts.get(2, "freeze authority")?,
+                signers: accounts.rest(3),
+            },
+            11 => Self::ThawAccount {
+                account: accounts.get(0, "account")?,
+                mint: accounts.get(1, "mint")?,
+                freeze_authority: accounts.get(2, "freeze authority")?,
+                signers: accounts.rest(3),
+            },
+            12 => Self::TransferChecked {
+                source: accounts.get(0, "source")?,
+                mint: accounts.get(1, "mint")?,
+                destination: accounts.get(2, "destination")?,
+                authority: accounts.get(3, "authority")?,
+                signers: accounts.rest(4),
+                amount: data.read_u64()?,
+                decimals: data.read_u8()?,
+            },
+            13 => Self::ApproveChecked {
+                source: accounts.get(0, "source")?,
+                mint: accounts.get(1, "mint")?,
+                delegate: accounts.get(2, "delegate")?,
+                owner: accounts.get(3, "owner")?,
+                signers: accounts.rest(4),
+                amount: data.read_u64()?,
+                decimals: data.read_u8()?,
+            },
+            14 => Self::MintToChecked {
+                mint: accounts.get(0, "mint")?,
+                account: accounts.get(1, "account")?,
+                mint_authority: accounts.get(2, "mint authority")?,
+                signers: accounts.rest(3),
+                amount: data.read_u64()?,
+                decimals: data.read_u8()?,
+            },
+            15 => Self::BurnChecked {
+                account: accounts.get(0, "account")?,
+                mint: accounts.get(1, "mint")?,
+                authority: accounts.get(2, "authority")?,
+                signers: accounts.rest(3),
+                amount: data.read_u64()?,
+                decimals: data.read_u8()?,
+            },
+            16 => Self::InitializeAccount2 {
+                account: accounts.get(0, "account")?,
+                mint: accounts.get(1, "mint")?,
+                owner: data.read_pubkey()?,
+            },
+            17 => Self::SyncNative {
+                account: accounts.get(0, "account")?,
+            },
+            18 => Self::InitializeAccount3 {
+                account: accounts.get(0, "account")?,
+                mint: accounts.get(1, "mint")?,
+                owner: data.read_pubkey()?,
+            },
+            19 => Self::InitializeMultisig2 {
+                multisig: accounts.get(0, "multisig")?,
+                signers: accounts.rest(1),
+                m: data.read_u8()?,
+            },
+            20 => Self::InitializeMint2 {
+                mint: accounts.get(0, "mint")?,
+                decimals: data.read_u8()?,
+                mint_authority: data.read_pubkey()?,
+                freeze_authority: data.read_optional_pubkey()?,
+            },
+            21 => Self::GetAccountDataSize {
+                mint: accounts.get(0, "mint")?,
+                extension_types: Self::read_extension_types(&mut data)?,
+            },
+            22 => Self::InitializeImmutableOwner {
+                account: accounts.get(0, "account")?,
+            },
+            23 => Self::AmountToUiAmount {
+                mint: accounts.get(0, "mint")?,
+                amount: data.read_u64()?,
+            },
+            24 => Self::UiAmountToAmount {
+                mint: accounts.get(0, "mint")?,
+                ui_amount: String::from_utf8(data.rest().to_vec())
+                    .map_err(|_| InstructionData::invalid("the UI amount is not valid UTF-8"))?,
+            },
+            25 => Self::InitializeMintCloseAuthority {
+                mint: accounts.get(0, "mint")?,
+                close_authority: data.read_optional_pubkey()?,
+            },
+            29 => Self::Reallocate {
+                account: accounts.get(0, "account")?,
+                payer: accounts.get(1, "payer")?,
+                owner: accounts.get(3, "owner")?,
+                signers: accounts.rest(4),
+                extension_types: Self::read_extension_types(&mut data)?,
+            },
+            31 => Self::CreateNativeMint {
+                payer: accounts.get(0, "payer")?,
+                native_mint: accounts.get(1, "native mint")?,
+            },
+            32 => Self::InitializeNonTransferableMint {
+                mint: accounts.get(0, "mint")?,
+            },
+            35 => Self::InitializePermanentDelegate {
+                mint: accounts.get(0, "mint")?,
+                delegate: data.read_pubkey()?,
+            },
+            38 => Self::WithdrawExcessLamports {
+                source: accounts.get(0, "source")?,
+                destination: accounts.get(1, "destination")?,
+                authority: accounts.get(2, "authority")?,
+                signers: accounts.rest(3),
+            },
+            extension => Self::decode_extension(extension, &accounts, &mut data)?,
+        };
+
+        Ok(instruction)
+    }
+
+    /// The extension types are a list of `u16` that takes the rest of the data
+    fn read_extension_types(data: &mut InstructionData) -> KrillResult<Vec<u16>> {
+        let chunks = data.rest().chunks_exact(2);
+
+        if !chunks.remainder().is_empty() {
+            return Err(InstructionData::invalid(
+                "the extension types are not a list of `u16`",
+            ));
+        }
+
+        Ok(chunks
+            .map(|chunk| u16::from_le_bytes([chunk[0], chunk[1]]))
+            .collect())
+    }
+}
+
+/// The authority [TokenInstruction::SetAuthority] changes
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuthorityType {
+    MintTokens,
+    FreezeAccount,
+    AccountOwner,
+    CloseAccount,
+    TransferFeeConfig,
+    WithheldWithdraw,
+    CloseMint,
+    InterestRate,
+    PermanentDelegate,
+    ConfidentialTransferMint,
+    TransferHookProgramId,
+    ConfidentialTransferFeeConfig,
+    MetadataPointer,
+    GroupPointer,
+    GroupMemberPointer,
+    ScaledUiAmount,
+    Pause,
+}
+
+impl AuthorityType {
+    const ALL: [Self; 17] = [
+        Self::MintTokens,
+        Self::FreezeAccount,
+        Self::AccountOwner,
+        Self::CloseAccount,
+        Self::TransferFeeConfig,
+        Self::WithheldWithdraw,
+        Self::CloseMint,
+        Self::InterestRate,
+        Self::PermanentDelegate,
+        Self::ConfidentialTransferMint,
+        Self::TransferHookProgramId,
+        Self::ConfidentialTransferFeeConfig,
+        Self::MetadataPointer,
+        Self::GroupPointer,
+        Self::GroupMemberPointer,
+        Self::ScaledUiAmount,
+        Self::Pause,
+    ];
+
+    fn parse(value: u8) -> KrillResult<Self> {
+        Self::ALL.get(usize::from(value)).copied().ok_or_else(|| {
+            InstructionData::invalid(&format!("`{value}` is not a token authority type"))
+        })
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct TokenDecoder;
+
+impl InstructionDecoder for TokenDecoder {
+    fn program_ids(&self) -> Vec<Pubkey> {
+        vec![
+            TokenInstruction::PROGRAM_ID,
+            TokenInstruction::PROGRAM_ID_2022,
+        ]
+    }
+
+    fn decode(
+        &self,
+        program_id: &Pubkey,
+        accounts: &[TransactionAccount],
+        data: &[u8],
+    ) -> KrillResult<DecodedInstruction> {
+        if *program_id == TokenInstruction::PROGRAM_ID_2022 {
+            TokenInstruction::decode(accounts, data, true).map(DecodedInstruction::Token2022)
+        } else {
+            TokenInstruction::decode(accounts, data, false).map(DecodedInstruction::Token)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn accounts(len: u8) -> Vec<TransactionAccount> {
+        (0..len)
+            .map(|index| TransactionAccount {
+                address: AccountAddress::Static {
+                    pubkey: Pubkey::new([index + 1; 32]),
+                },
+                is_signer: false,
+                is_writable: false,
+            })
+            .collect()
+    }
+
+    fn address(index: u8) -> AccountAddress {
+        AccountAddress::Static {
+            pubkey: Pubkey::new([index + 1; 32]),
+        }
+    }
+
+    fn borsh_string(value: &str) -> Vec<u8> {
+        let mut bytes = (value.len() as u32).to_le_bytes().to_vec();
+        bytes.extend_from_slice(value.as_bytes());
+
+        bytes
+    }
+
+    #[test]
+    fn token_instructions() {
+        let mint_authority = Pubkey::new([9u8; 32]);
+
+        let mut data = vec![20, 6];
+        data.extend_from_slice(mint_authority.as_bytes());
+        data.push(0);
+        assert_eq!(
+            TokenInstruction::decode(&accounts(1), &data, false).unwrap(),
+            TokenInstruction::InitializeMint2 {
+                mint: address(0),
+                decimals: 6,
+                mint_authority,
+                freeze_authority: None,
+            }
+        );
+
+        // A multisig authority with two of its signers
+        let mut data = vec![7];
+        data.extend_from_slice(&42u64.to_le_bytes());
+        assert_eq!(
+            TokenInstruction::decode(&accounts(5), &data, false).unwrap(),
+            TokenInstruction::MintTo {
+                mint: address(0),
+                account: address(1),
+                mint_authority: address(2),
+                signers: vec![address(3), address(4)],
+                amount: 42,
+            }
+        );
+
+        let mut data = vec![6, 16, 1];
+        data.extend_from_slice(mint_authority.as_bytes());
+        assert_eq!(
+            TokenInstruction::decode(&accounts(2), &data, true).unwrap(),
+            TokenInstruction::SetAuthority {
+                account: address(0),
+                current_authority: address(1),
+                signers: Vec::default(),
+                authority_type: AuthorityType::Pause,
+                new_authority: Some(mint_authority),
+            }
+        );
+
+        // Token-2022 instructions are not Token program instructions
+        assert!(TokenInstruction::decode(&accounts(1), &[32], false).is_err());
+        assert_eq!(
+            TokenInstruction::decode(&accounts(1), &[32], true).unwrap(),
+            TokenInstruction::InitializeNonTransferableMint { mint: address(0) }
+        );
+        assert!(TokenInstruction::decode(&accounts(1), &[99], true).is_err());
+    }
+
+    #[test]
+    fn token_2022_extensions() {
+        let authority = Pubkey::new([9u8; 32]);
+
+        let mut data = vec![26, 0, 1];
+        data.extend_from_slice(authority.as_bytes());
+        data.push(0);
+        data.extend_from_slice(&50u16.to_le_bytes());
+        data.extend_from_slice(&5_000u64.to_le_bytes());
+        assert_eq!(
+            TokenInstruction::decode(&accounts(1), &data, true).unwrap(),
+            TokenInstruction::InitializeTransferFeeConfig {
+                mint: address(0),
+                transfer_fee_config_authority: Some(authority),
+                withdraw_withheld_authority: None,
+                transfer_fee_basis_points: 50,
+                maximum_fee: 5_000,
+            }
+        );
+
+        let mut data = vec![26, 1];
+        data.extend_from_slice(&1_000u64.to_le_bytes());
+        data.push(2);
+        data.extend_from_slice(&5u64.to_le_bytes());
+        assert!(matches!(
+            TokenInstruction::decode(&accounts(4), &data, true).unwrap(),
+            TokenInstruction::TransferCheckedWithFee {
+                amount: 1_000,
+                decimals: 2,
+                fee: 5,
+                ..
+            }
+        ));
+
+        // One multisig signer followed by the two accounts to withdraw from
+        assert_eq!(
+            TokenInstruction::decode(&accounts(6), &[26, 3, 2], true).unwrap(),
+            TokenInstruction::WithdrawWithheldTokensFromAccounts {
+                mint: address(0),
+                destination: address(1),
+                authority: address(2),
+                signers: vec![address(3)],
+                sources: vec![address(4), address(5)],
+            }
+        );
+        assert!(TokenInstruction::decode(&accounts(4), &[26, 3, 2], true).is_err());
+
+        assert_eq!(
+            TokenInstruction::decode(&accounts(2), &[28, 1, 2], true).unwrap(),
+            TokenInstruction::UpdateDefaultAccountState {
+                mint: address(0),
+                freeze_authority: address(1),
+                signers: Vec::default(),
+                state: AccountState::Frozen,
+            }
+        );
+
+        let mut data = vec![36, 0];
+        data.extend_from_slice(authority.as_bytes());
+        data.extend_from_slice(&[0u8; 32]);
+        assert_eq!(
+            TokenInstruction::decode(&accounts(1), &data, true).unwrap(),
+            TokenInstruction::InitializeTransferHook {
+                mint: address(0),
+                authority: Some(authority),
+                program_id: None,
+            }
+        );
+
+        let mut data = vec![43, 1];
+        data.extend_from_slice(&1.5f64.to_le_bytes());
+        data.extend_from_slice(&1_800_000_000i64.to_le_bytes());
+        assert_eq!(
+            TokenInstruction::decode(&accounts(2), &data, true).unwrap(),
+            TokenInstruction::UpdateScaledUiAmountMultiplier {
+                mint: address(0),
+                authority: address(1),
+                signers: Vec::default(),
+                multiplier: 1.5,
+                effective_timestamp: 1_800_000_000,
+            }
+        );
+
+        assert_eq!(
+            TokenInstruction::decode(&accounts(2), &[27, 7], true).unwrap(),
+            TokenInstruction::Confidential {
+                extension: ConfidentialExtension::ConfidentialTransfer,
+                extension_instruction: 7,
+            }
+        );
+    }
+
+    #[test]
+    fn token_metadata() {
+        let mut data = TokenInstruction::INITIALIZE_TOKEN_METADATA.to_vec();
+        data.extend(borsh_string("Krill Dollar"));
+        data.extend(borsh_string("KUSD"));
+        data.extend(borsh_string("https://krill.example/kusd.json"));
+        assert_eq!(
+            TokenInstruction::decode(&accounts(4), &data, true).unwrap(),
+            TokenInstruction::InitializeTokenMetadata {
+                metadata: address(0),
+                update_authority: address(1),
+                mint: address(2),
+                mint_authority: address(3),
+                name: "Krill Dollar".to_string(),
+                symbol: "KUSD".to_string(),
+                uri: "https://krill.example/kusd.json".to_string(),
+            }
+        );
+
+        let mut data = TokenInstruction::UPDATE_TOKEN_METADATA_FIELD.to_vec();
+        data.push(3);
+        data.extend(borsh_string("issuer"));
+        data.extend(borsh_string("Krill"));
+        assert_eq!(
+            TokenInstruction::decode(&accounts(2), &data, true).unwrap(),
+            TokenInstruction::UpdateTokenMetadataField {
+                metadata: address(0),
+                update_authority: address(1),
+                field: TokenMetadataField::Key("issuer".to_string()),
+                value: "Krill".to_string(),
+            }
+        );
+    }
+}
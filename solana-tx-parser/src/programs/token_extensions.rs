@@ -0,0 +1,317 @@
+use krill_common::KrillResult;
+use serde::{Deserialize, Serialize};
+
+use crate::{InstructionAccounts, InstructionData, TokenInstruction, TransactionAccount};
+
+impl TokenInstruction {
+    /// The instructions of the token metadata interface start with the first 8
+    /// bytes of the SHA-256 of `spl_token_metadata_interface:<name>`
+    pub(crate) const INITIALIZE_TOKEN_METADATA: [u8; 8] = [210, 225, 30, 162, 88, 184, 77, 141];
+    pub(crate) const UPDATE_TOKEN_METADATA_FIELD: [u8; 8] = [221, 233, 49, 45, 181, 202, 220, 200];
+    pub(crate) const REMOVE_TOKEN_METADATA_KEY: [u8; 8] = [234, 18, 32, 56, 89, 141, 37, 181];
+    pub(crate) const UPDATE_TOKEN_METADATA_AUTHORITY: [u8; 8] =
+        [215, 228, 166, 228, 84, 100, 86, 123];
+
+    /// The Token-2022 extensions with their own instructions in the second byte
+    pub(crate) fn decode_extension(
+        extension: u8,
+        accounts: &InstructionAccounts,
+        data: &mut InstructionData,
+    ) -> KrillResult<Self> {
+        if let Some(confidential) = ConfidentialExtension::parse(extension) {
+            return Ok(Self::Confidential {
+                extension: confidential,
+                extension_instruction: data.read_u8()?,
+            });
+        }
+
+        let instruction = data.read_u8()?;
+
+        let decoded = match (extension, instruction) {
+            (26, 0) => Self::InitializeTransferFeeConfig {
+                mint: accounts.get(0, "mint")?,
+                transfer_fee_config_authority: data.read_optional_pubkey()?,
+                withdraw_withheld_authority: data.read_optional_pubkey()?,
+                transfer_fee_basis_points: data.read_u16()?,
+                maximum_fee: data.read_u64()?,
+            },
+            (26, 1) => Self::TransferCheckedWithFee {
+                source: accounts.get(0, "source")?,
+                mint: accounts.get(1, "mint")?,
+                destination: accounts.get(2, "destination")?,
+                authority: accounts.get(3, "authority")?,
+                signers: accounts.rest(4),
+                amount: data.read_u64()?,
+                decimals: data.read_u8()?,
+                fee: data.read_u64()?,
+            },
+            (26, 2) => Self::WithdrawWithheldTokensFromMint {
+                mint: accounts.get(0, "mint")?,
+                destination: accounts.get(1, "destination")?,
+                authority: accounts.get(2, "authority")?,
+                signers: accounts.rest(3),
+            },
+            (26, 3) => {
+                // The token accounts to withdraw from come after the multisig signers
+                let num_token_accounts = usize::from(data.read_u8()?);
+                let others = accounts.rest(3);
+                let signers_len =
+                    others
+                        .len()
+                        .checked_sub(num_token_accounts)
+                        .ok_or_else(|| {
+                            InstructionData::invalid(
+                                "the token accounts to withdraw from are missing",
+                            )
+                        })?;
+
+                Self::WithdrawWithheldTokensFromAccounts {
+                    mint: accounts.get(0, "mint")?,
+                    destination: accounts.get(1, "destination")?,
+                    authority: accounts.get(2, "authority")?,
+                    signers: others[..signers_len].to_vec(),
+                    sources: others[signers_len..].to_vec(),
+                }
+            }
+            (26, 4) => Self::HarvestWithheldTokensToMint {
+                mint: accounts.get(0, "mint")?,
+                sources: accounts.rest(1),
+            },
+            (26, 5) => Self::SetTransferFee {
+                mint: accounts.get(0, "mint")?,
+                authority: accounts.get(1, "authority")?,
+                signers: accounts.rest(2),
+                transfer_fee_basis_points: data.read_u16()?,
+                maximum_fee: data.read_u64()?,
+            },
+            (28, 0) => Self::InitializeDefaultAccountState {
+                mint: accounts.get(0, "mint")?,
+                state: AccountState::parse(data.read_u8()?)?,
+            },
+            (28, 1) => Self::UpdateDefaultAccountState {
+                mint: accounts.get(0, "mint")?,
+                freeze_authority: accounts.get(1, "freeze authority")?,
+                signers: accounts.rest(2),
+                state: AccountState::parse(data.read_u8()?)?,
+            },
+            (30, 0) => Self::EnableRequiredMemoTransfers {
+                account: accounts.get(0, "account")?,
+                owner: accounts.get(1, "owner")?,
+                signers: accounts.rest(2),
+            },
+            (30, 1) => Self::DisableRequiredMemoTransfers {
+                account: accounts.get(0, "account")?,
+                owner: accounts.get(1, "owner")?,
+                signers: accounts.rest(2),
+            },
+            (33, 0) => Self::InitializeInterestBearingMint {
+                mint: accounts.get(0, "mint")?,
+                rate_authority: data.read_nonzero_pubkey()?,
+                rate: data.read_i16()?,
+            },
+            (33, 1) => Self::UpdateInterestRate {
+                mint: accounts.get(0, "mint")?,
+                rate_authority: accounts.get(1, "rate authority")?,
+                signers: accounts.rest(2),
+                rate: data.read_i16()?,
+            },
+            (34, 0) => Self::EnableCpiGuard {
+                account: accounts.get(0, "account")?,
+                owner: accounts.get(1, "owner")?,
+                signers: accounts.rest(2),
+            },
+            (34, 1) => Self::DisableCpiGuard {
+                account: accounts.get(0, "account")?,
+                owner: accounts.get(1, "owner")?,
+                signers: accounts.rest(2),
+            },
+            (36, 0) => Self::InitializeTransferHook {
+                mint: accounts.get(0, "mint")?,
+                authority: data.read_nonzero_pubkey()?,
+                program_id: data.read_nonzero_pubkey()?,
+            },
+            (36, 1) => Self::UpdateTransferHook {
+                mint: accounts.get(0, "mint")?,
+                authority: accounts.get(1, "authority")?,
+                signers: accounts.rest(2),
+                program_id: data.read_nonzero_pubkey()?,
+            },
+            (39, 0) => Self::InitializeMetadataPointer {
+                mint: accounts.get(0, "mint")?,
+                authority: data.read_nonzero_pubkey()?,
+                metadata_address: data.read_nonzero_pubkey()?,
+            },
+            (39, 1) => Self::UpdateMetadataPointer {
+                mint: accounts.get(0, "mint")?,
+                authority: accounts.get(1, "authority")?,
+                signers: accounts.rest(2),
+                metadata_address: data.read_nonzero_pubkey()?,
+            },
+            (40, 0) => Self::InitializeGroupPointer {
+                mint: accounts.get(0, "mint")?,
+                authority: data.read_nonzero_pubkey()?,
+                group_address: data.read_nonzero_pubkey()?,
+            },
+            (40, 1) => Self::UpdateGroupPointer {
+                mint: accounts.get(0, "mint")?,
+                authority: accounts.get(1, "authority")?,
+                signers: accounts.rest(2),
+                group_address: data.read_nonzero_pubkey()?,
+            },
+            (41, 0) => Self::InitializeGroupMemberPointer {
+                mint: accounts.get(0, "mint")?,
+                authority: data.read_nonzero_pubkey()?,
+                member_address: data.read_nonzero_pubkey()?,
+            },
+            (41, 1) => Self::UpdateGroupMemberPointer {
+                mint: accounts.get(0, "mint")?,
+                authority: accounts.get(1, "authority")?,
+                signers: accounts.rest(2),
+                member_address: data.read_nonzero_pubkey()?,
+            },
+            (43, 0) => Self::InitializeScaledUiAmount {
+                mint: accounts.get(0, "mint")?,
+                authority: data.read_nonzero_pubkey()?,
+                multiplier: data.read_f64()?,
+            },
+            (43, 1) => Self::UpdateScaledUiAmountMultiplier {
+                mint: accounts.get(0, "mint")?,
+                authority: accounts.get(1, "authority")?,
+                signers: accounts.rest(2),
+                multiplier: data.read_f64()?,
+                effective_timestamp: data.read_i64()?,
+            },
+            (44, 0) => Self::InitializePausable {
+                mint: accounts.get(0, "mint")?,
+                authority: data.read_pubkey()?,
+            },
+            (44, 1) => Self::Pause {
+                mint: accounts.get(0, "mint")?,
+                authority: accounts.get(1, "authority")?,
+                signers: accounts.rest(2),
+            },
+            (44, 2) => Self::Resume {
+                mint: accounts.get(0, "mint")?,
+                authority: accounts.get(1, "authority")?,
+                signers: accounts.rest(2),
+            },
+            (extension, instruction) => {
+                return Err(InstructionData::invalid(&format!(
+                    "`{extension}` `{instruction}` is not a Token-2022 instruction"
+                )))
+            }
+        };
+
+        Ok(decoded)
+    }
+
+    /// Token-2022 stores the metadata in the mint and implements the token metadata interface
+    pub(crate) fn decode_token_metadata(
+        accounts: &[TransactionAccount],
+        data: &[u8],
+    ) -> KrillResult<Option<Self>> {
+        let Some((discriminator, fields)) = data.split_first_chunk::<8>() else {
+            return Ok(None);
+        };
+
+        let accounts = InstructionAccounts::new(accounts);
+        let mut fields = InstructionData::new(fields);
+
+        let instruction = match *discriminator {
+            Self::INITIALIZE_TOKEN_METADATA => Self::InitializeTokenMetadata {
+                metadata: accounts.get(0, "metadata")?,
+                update_authority: accounts.get(1, "update authority")?,
+                mint: accounts.get(2, "mint")?,
+                mint_authority: accounts.get(3, "mint authority")?,
+                name: fields.read_borsh_string()?,
+                symbol: fields.read_borsh_string()?,
+                uri: fields.read_borsh_string()?,
+            },
+            Self::UPDATE_TOKEN_METADATA_FIELD => Self::UpdateTokenMetadataField {
+                metadata: accounts.get(0, "metadata")?,
+                update_authority: accounts.get(1, "update authority")?,
+                field: TokenMetadataField::read(&mut fields)?,
+                value: fields.read_borsh_string()?,
+            },
+            Self::REMOVE_TOKEN_METADATA_KEY => Self::RemoveTokenMetadataKey {
+                metadata: accounts.get(0, "metadata")?,
+                update_authority: accounts.get(1, "update authority")?,
+                idempotent: fields.read_u8()? != 0,
+                key: fields.read_borsh_string()?,
+            },
+            Self::UPDATE_TOKEN_METADATA_AUTHORITY => Self::UpdateTokenMetadataAuthority {
+                metadata: accounts.get(0, "metadata")?,
+                update_authority: accounts.get(1, "update authority")?,
+                new_authority: fields.read_nonzero_pubkey()?,
+            },
+            _ => return Ok(None),
+        };
+
+        Ok(Some(instruction))
+    }
+}
+
+/// The state new token accounts of a mint start in
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AccountState {
+    Uninitialized,
+    Initialized,
+    Frozen,
+}
+
+impl AccountState {
+    fn parse(value: u8) -> KrillResult<Self> {
+        match value {
+            0 => Ok(Self::Uninitialized),
+            1 => Ok(Self::Initialized),
+            2 => Ok(Self::Frozen),
+            _ => Err(InstructionData::invalid(&format!(
+                "`{value}` is not a token account state"
+            ))),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConfidentialExtension {
+    ConfidentialTransfer,
+    ConfidentialTransferFee,
+    ConfidentialMintBurn,
+}
+
+impl ConfidentialExtension {
+    fn parse(extension: u8) -> Option<Self> {
+        match extension {
+            27 => Some(Self::ConfidentialTransfer),
+            37 => Some(Self::ConfidentialTransferFee),
+            42 => Some(Self::ConfidentialMintBurn),
+            _ => None,
+        }
+    }
+}
+
+/// A field of the token metadata, custom fields are a key
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TokenMetadataField {
+    Name,
+    Symbol,
+    Uri,
+    Key(String),
+}
+
+impl TokenMetadataField {
+    fn read(data: &mut InstructionData) -> KrillResult<Self> {
+        match data.read_u8()? {
+            0 => Ok(Self::Name),
+            1 => Ok(Self::Symbol),
+            2 => Ok(Self::Uri),
+            3 => Ok(Self::Key(data.read_borsh_string()?)),
+            value => Err(InstructionData::invalid(&format!(
+                "`{value}` is not a token metadata field"
+            ))),
+        }
+    }
+}
@@ -0,0 +1,1276 @@
+use std::{
+    collections::HashMap,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc,
+    },
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+
+use async_channel::{Receiver, Sender, TrySendError};
+use base64ct::{Base64, Encoding};
+use futures_util::{SinkExt, Stream, StreamExt};
+use krill_common::{KrillError, KrillResult};
+use serde::{de::DeserializeOwned, Deserialize, Deserializer};
+use serde_json::{json, Value};
+use tokio::net::TcpStream;
+use tokio_tungstenite::{
+    tungstenite::{self, Message},
+    MaybeTlsStream, WebSocketStream,
+};
+
+use crate::{
+    AccountInfo, Commitment, JsonRpcCluster, KeyedAccount, Pubkey, RpcError, Signature,
+    SolanaTxParserUtils,
+};
+
+type Socket = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+/// Parses the `result` of a notification and sends it to its [Subscription],
+/// `false` once the subscription is dropped
+type Forwarder = Box<dyn FnMut(Value) -> bool + Send>;
+
+/// The `result` of a notification with the id [SolanaPubSub] gave the subscription
+type Delivery = (u64, Value);
+
+/// Pushes the changes of accounts, signatures and logs over the WebSocket of a
+/// node. A dropped connection is reopened and the subscriptions are sent again,
+/// so a [Subscription] outlives the connections.
+pub struct SolanaPubSub {
+    commands: Sender<Command>,
+    next_id: AtomicU64,
+}
+
+impl std::fmt::Debug for SolanaPubSub {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SolanaPubSub").finish_non_exhaustive()
+    }
+}
+
+impl SolanaPubSub {
+    /// Subscriptions and unsubscriptions waiting for the connection
+    const COMMAND_CAPACITY: usize = 64;
+
+    pub fn connect(cluster: &JsonRpcCluster) -> KrillResult<Self> {
+        Self::connect_with(cluster, ReconnectPolicy::default())
+    }
+
+    /// The connection runs in its own task of the current Tokio runtime until
+    /// the client and all its subscriptions are dropped. Fails outside a Tokio runtime.
+    pub fn connect_with(cluster: &JsonRpcCluster, policy: ReconnectPolicy) -> KrillResult<Self> {
+        let runtime = tokio::runtime::Handle::try_current().or(Err(KrillError::HttpClient(
+            "The Solana PubSub connection must be opened in a Tokio runtime".to_string(),
+        )))?;
+        let (commands, receiver) = async_channel::bounded(Self::COMMAND_CAPACITY);
+
+        runtime.spawn(PubSubWorker::new(cluster, policy, receiver).run());
+
+        Ok(Self {
+            commands,
+            next_id: AtomicU64::new(0),
+        })
+    }
+
+    /// The lamports, owner and data of an account each time they change
+    pub async fn account_subscribe(
+        &self,
+        pubkey: &Pubkey,
+        commitment: Commitment,
+    ) -> KrillResult<Subscription<Notification<AccountInfo>>> {
+        self.subscribe(SubscribeRequest {
+            method: "accountSubscribe",
+            unsubscribe: "accountUnsubscribe",
+            params: json!([pubkey, {
+                "encoding": "base64",
+                "commitment": commitment,
+            }]),
+            single: false,
+            status_check: None,
+        })
+        .await
+    }
+
+    /// The accounts of a program each time one of them changes, only the
+    /// accounts matching all the `filters` when there are some
+    pub async fn program_subscribe(
+        &self,
+        program_id: &Pubkey,
+        filters: &[ProgramFilter],
+        commitment: Commitment,
+    ) -> KrillResult<Subscription<Notification<KeyedAccount>>> {
+        let mut config = json!({
+            "encoding": "base64",
+            "commitment": commitment,
+        });
+
+        if !filters.is_empty() {
+            config["filters"] = filters.iter().map(ProgramFilter::to_json).collect();
+        }
+
+        self.subscribe(SubscribeRequest {
+            method: "programSubscribe",
+            unsubscribe: "programUnsubscribe",
+            params: json!([program_id, config]),
+            single: false,
+            status_check: None,
+        })
+        .await
+    }
+
+    /// A single notification once the transaction reaches `commitment`, then the
+    /// stream ends. A transaction that landed before the subscription is not
+    /// notified, check [crate::SolanaTxParserUtils::get_signature_statuses] after subscribing.
+    /// The status is checked by the client itself after a reconnection.
+    pub async fn signature_subscribe(
+        &self,
+        signature: &Signature,
+        commitment: Commitment,
+    ) -> KrillResult<Subscription<Notification<SignatureResult>>> {
+        self.subscribe(SubscribeRequest {
+            method: "signatureSubscribe",
+            unsubscribe: "signatureUnsubscribe",
+            params: json!([signature, {
+                "commitment": commitment,
+                "enableReceivedNotification": false,
+            }]),
+            single: true,
+            status_check: Some((*signature, commitment)),
+        })
+        .await
+    }
+
+    /// The logs of the transactions matching `filter`
+    pub async fn logs_subscribe(
+        &self,
+        filter: &LogsFilter,
+        commitment: Commitment,
+    ) -> KrillResult<Subscription<Notification<Logs>>> {
+        self.subscribe(SubscribeRequest {
+            method: "logsSubscribe",
+            unsubscribe: "logsUnsubscribe",
+            params: json!([filter.to_json(), { "commitment": commitment }]),
+            single: false,
+            status_check: None,
+        })
+        .await
+    }
+
+    /// Waits for the node to confirm the subscription, which includes waiting
+    /// for the connection when the node cannot be reached
+    async fn subscribe<T: DeserializeOwned + Send + 'static>(
+        &self,
+        request: SubscribeRequest,
+    ) -> KrillResult<Subscription<T>> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let method = request.method;
+        let (sender, receiver) =
+            async_channel::bounded::<KrillResult<T>>(Subscription::<T>::CAPACITY);
+        let (confirmed, confirmation) = async_channel::bounded(1);
+        let lagged = Arc::new(AtomicBool::new(false));
+
+        let lagging = lagged.clone();
+        let forward: Forwarder = Box::new(move |result| {
+            let notification = serde_json::from_value(result).map_err(|error| {
+                KrillError::HttpClient(format!(
+                    "Unable to parse the notification of `{method}`. Error: `{error}`"
+                ))
+            });
+
+            match sender.try_send(notification) {
+                Ok(()) => true,
+                // The worker never waits for a slow stream, the stream ends instead
+                Err(TrySendError::Full(_)) => {
+                    lagging.store(true, Ordering::Relaxed);
+                    sender.close();
+
+                    false
+                }
+                Err(TrySendError::Closed(_)) => false,
+            }
+        });
+
+        self.commands
+            .send(Command::Subscribe {
+                id,
+                subscription: ActiveSubscription {
+                    request,
+                    forward,
+                    confirmed: Some(confirmed),
+                    server_id: None,
+                },
+            })
+            .await
+            .map_err(|_| Self::closed())?;
+
+        // Created first so the node is unsubscribed if this future is dropped
+        let subscription = Subscription {
+            id,
+            receiver: Box::pin(receiver),
+            lagged,
+            commands: self.commands.clone(),
+        };
+
+        confirmation.recv().await.map_err(|_| Self::closed())??;
+
+        Ok(subscription)
+    }
+
+    fn closed() -> KrillError {
+        KrillError::HttpClient("The Solana PubSub connection is closed".to_string())
+    }
+}
+
+/// How long [SolanaPubSub] waits before reopening a dropped connection, the
+/// delay doubles after each failed attempt up to `max_delay`
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct ReconnectPolicy {
+    pub initial_delay: Duration,
+    pub max_delay: Duration,
+}
+
+impl Default for ReconnectPolicy {
+    fn default() -> Self {
+        Self {
+            initial_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(30),
+        }
+    }
+}
+
+/// The notifications of a subscription as a stream, unsubscribed when dropped.
+/// Notifications that cannot be parsed are returned as errors. The stream ends
+/// when the node rejects a resubscription after a reconnection, or with an error
+/// when it falls [Subscription::CAPACITY] notifications behind.
+pub struct Subscription<T> {
+    id: u64,
+    receiver: Pin<Box<Receiver<KrillResult<T>>>>,
+    lagged: Arc<AtomicBool>,
+    commands: Sender<Command>,
+}
+
+impl<T> Subscription<T> {
+    /// Notifications kept until the stream is polled
+    pub const CAPACITY: usize = 1024;
+}
+
+impl<T> Stream for Subscription<T> {
+    type Item = KrillResult<T>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        match self.receiver.as_mut().poll_next(cx) {
+            Poll::Ready(None) if self.lagged.swap(false, Ordering::Relaxed) => {
+                Poll::Ready(Some(Err(KrillError::HttpClient(format!(
+                    "The subscription fell more than {} notifications behind",
+                    Self::CAPACITY
+                )))))
+            }
+            poll => poll,
+        }
+    }
+}
+
+impl<T> Drop for Subscription<T> {
+    /// With a full command queue the node is unsubscribed at the next notification instead
+    fn drop(&mut self) {
+        self.commands.try_send(Command::Unsubscribe(self.id)).ok();
+    }
+}
+
+/// The value of a notification with the slot it was observed at
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Notification<T> {
+    pub slot: u64,
+    pub value: T,
+}
+
+#[derive(Deserialize)]
+struct NotificationContext {
+    slot: u64,
+}
+
+#[derive(Deserialize)]
+struct NotificationResult<T> {
+    context: NotificationContext,
+    value: T,
+}
+
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for Notification<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let result = NotificationResult::<T>::deserialize(deserializer)?;
+
+        Ok(Self {
+            slot: result.context.slot,
+            value: result.value,
+        })
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Deserialize)]
+pub struct SignatureResult {
+    /// Why the transaction failed, it still paid its fee
+    pub err: Option<Value>,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Deserialize)]
+pub struct Logs {
+    pub signature: Signature,
+    pub err: Option<Value>,
+    pub logs: Vec<String>,
+}
+
+/// Which transactions `logsSubscribe` notifies
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum LogsFilter {
+    /// All the transactions except the votes
+    All,
+    AllWithVotes,
+    /// The transactions with the account or program in their accounts
+    Mentions(Pubkey),
+}
+
+impl LogsFilter {
+    fn to_json(self) -> Value {
+        match self {
+            Self::All => json!("all"),
+            Self::AllWithVotes => json!("allWithVotes"),
+            Self::Mentions(pubkey) => json!({ "mentions": [pubkey] }),
+        }
+    }
+}
+
+/// Narrows the accounts of `programSubscribe`
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum ProgramFilter {
+    DataSize(u64),
+    /// The data has `bytes` at `offset`, the owner of a token account is at 32 for example
+    Memcmp {
+        offset: usize,
+        bytes: Vec<u8>,
+    },
+}
+
+impl ProgramFilter {
+    fn to_json(&self) -> Value {
+        match self {
+            Self::DataSize(data_size) => json!({ "dataSize": data_size }),
+            Self::Memcmp { offset, bytes } => json!({
+                "memcmp": {
+                    "offset": offset,
+                    "bytes": Base64::encode_string(bytes),
+                    "encoding": "base64",
+                },
+            }),
+        }
+    }
+}
+
+enum Command {
+    Subscribe {
+        id: u64,
+        subscription: ActiveSubscription,
+    },
+    Unsubscribe(u64),
+}
+
+struct SubscribeRequest {
+    method: &'static str,
+    unsubscribe: &'static str,
+    params: Value,
+    /// The node cancels the subscription after its first notification
+    single: bool,
+    /// The transaction and commitment checked with `getSignatureStatuses` after a
+    /// resubscription, the node does not notify a transaction that landed while
+    /// the connection was down
+    status_check: Option<(Signature, Commitment)>,
+}
+
+struct ActiveSubscription {
+    request: SubscribeRequest,
+    forward: Forwarder,
+    /// Answered once, by the first confirmation of the node
+    confirmed: Option<Sender<KrillResult<()>>>,
+    /// The id the node gave the subscription on the current connection
+    server_id: Option<u64>,
+}
+
+enum PendingRequest {
+    Subscribe { id: u64, unsubscribe: &'static str },
+    Unsubscribe,
+}
+
+enum Closed {
+    Client,
+    Connection,
+}
+
+/// Owns the connection, the subscriptions are keyed by the id [SolanaPubSub]
+/// gave them since the ids of the node change with each connection
+struct PubSubWorker {
+    cluster: JsonRpcCluster,
+    policy: ReconnectPolicy,
+    commands: Receiver<Command>,
+    /// The results of the signature status checks as `(id, result)` notifications
+    statuses: (Sender<Delivery>, Receiver<Delivery>),
+    subscriptions: HashMap<u64, ActiveSubscription>,
+    server_ids: HashMap<u64, u64>,
+    pending: HashMap<u64, PendingRequest>,
+    next_request_id: u64,
+}
+
+impl PubSubWorker {
+    /// Nodes and load balancers close idle connections
+    const PING_INTERVAL: Duration = Duration::from_secs(30);
+
+    fn new(cluster: &JsonRpcCluster, policy: ReconnectPolicy, commands: Receiver<Command>) -> Self {
+        Self {
+            cluster: cluster.clone(),
+            policy,
+            commands,
+            statuses: async_channel::bounded(SolanaPubSub::COMMAND_CAPACITY),
+            subscriptions: HashMap::default(),
+            server_ids: HashMap::default(),
+            pending: HashMap::default(),
+            next_request_id: 0,
+        }
+    }
+
+    async fn run(mut self) {
+        let mut delay = self.policy.initial_delay;
+        let uri = self.cluster.websocket_uri();
+
+        loop {
+            if let Ok((socket, _)) = tokio_tungstenite::connect_async(uri.as_str()).await {
+                delay = self.policy.initial_delay;
+
+                if let Closed::Client = self.serve(socket).await {
+                    return;
+                }
+            }
+
+            let reconnect = tokio::time::sleep(delay);
+            tokio::pin!(reconnect);
+
+            loop {
+                tokio::select! {
+                    _ = &mut reconnect => break,
+                    command = self.commands.recv() => match command {
+                        Ok(command) => self.handle_offline(command),
+                        Err(_) => return,
+                    },
+                }
+            }
+
+            delay = (delay * 2).min(self.policy.max_delay);
+        }
+    }
+
+    async fn serve(&mut self, mut socket: Socket) -> Closed {
+        self.server_ids.clear();
+        self.pending.clear();
+
+        let ids = self.subscriptions.keys().copied().collect::<Vec<u64>>();
+        for id in ids {
+            if self.send_subscribe(&mut socket, id).await.is_err() {
+                return Closed::Connection;
+            }
+        }
+
+        let mut ping = tokio::time::interval(Self::PING_INTERVAL);
+        ping.tick().await;
+        let mut last_seen = Instant::now();
+
+        loop {
+            tokio::select! {
+                command = self.commands.recv() => {
+                    let Ok(command) = command else {
+                        socket.close(None).await.ok();
+
+                        return Closed::Client;
+                    };
+
+                    if self.handle_command(&mut socket, command).await.is_err() {
+                        return Closed::Connection;
+                    }
+                }
+                Ok((id, result)) = self.statuses.1.recv() => {
+                    if self.forward(&mut socket, id, result, false).await.is_err() {
+                        return Closed::Connection;
+                    }
+                }
+                message = socket.next() => {
+                    last_seen = Instant::now();
+
+                    match message {
+                        Some(Ok(Message::Text(text))) => {
+                            if self.handle_message(&mut socket, text.as_str()).await.is_err() {
+                                return Closed::Connection;
+                            }
+                        }
+                        Some(Ok(Message::Close(_))) | Some(Err(_)) | None => {
+                            return Closed::Connection;
+                        }
+                        Some(Ok(_)) => {}
+                    }
+                }
+                _ = ping.tick() => {
+                    // Not even a pong since the last ping, the connection is dead
+                    if last_seen.elapsed() > Self::PING_INTERVAL * 2
+                        || socket.send(Message::Ping(Default::default())).await.is_err()
+                    {
+                        return Closed::Connection;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Keeps the subscriptions made while reconnecting for the next connection
+    fn handle_offline(&mut self, command: Command) {
+        match command {
+            Command::Subscribe { id, subscription } => {
+                self.subscriptions.insert(id, subscription);
+            }
+            Command::Unsubscribe(id) => {
+                self.subscriptions.remove(&id);
+            }
+        }
+    }
+
+    async fn handle_command(
+        &mut self,
+        socket: &mut Socket,
+        command: Command,
+    ) -> Result<(), tungstenite::Error> {
+        match command {
+            Command::Subscribe { id, subscription } => {
+                self.subscriptions.insert(id, subscription);
+
+                self.send_subscribe(socket, id).await
+            }
+            Command::Unsubscribe(id) => match self.subscriptions.remove(&id) {
+                Some(ActiveSubscription {
+                    request,
+                    server_id: Some(server_id),
+                    ..
+                }) => {
+                    self.server_ids.remove(&server_id);
+
+                    self.send_unsubscribe(socket, request.unsubscribe, server_id)
+                        .await
+                }
+                _ => Ok(()),
+            },
+        }
+    }
+
+    async fn handle_message(
+        &mut self,
+        socket: &mut Socket,
+        text: &str,
+    ) -> Result<(), tungstenite::Error> {
+        let Ok(mut message) = serde_json::from_str::<Value>(text) else {
+            return Ok(());
+        };
+
+        if let Some(server_id) = message["params"]["subscription"].as_u64() {
+            let result = message["params"]["result"].take();
+
+            return self.notify(socket, server_id, result).await;
+        }
+
+        let Some(request_id) = message["id"].as_u64() else {
+            return Ok(());
+        };
+
+        match self.pending.remove(&request_id) {
+            Some(PendingRequest::Subscribe { id, unsubscribe }) => {
+                self.confirm(socket, id, unsubscribe, message).await
+            }
+            _ => Ok(()),
+        }
+    }
+
+    async fn confirm(
+        &mut self,
+        socket: &mut Socket,
+        id: u64,
+        unsubscribe: &'static str,
+        mut message: Value,
+    ) -> Result<(), tungstenite::Error> {
+        let Some(subscription) = self.subscriptions.get_mut(&id) else {
+            // The subscription was dropped before the node confirmed it
+            return match message["result"].as_u64() {
+                Some(server_id) => self.send_unsubscribe(socket, unsubscribe, server_id).await,
+                None => Ok(()),
+            };
+        };
+
+        match message["result"].as_u64() {
+            Some(server_id) => {
+                subscription.server_id = Some(server_id);
+
+                let status_check = match subscription.confirmed.take() {
+                    Some(confirmed) => {
+                        confirmed.try_send(Ok(())).ok();
+
+                        None
+                    }
+                    None => subscription.request.status_check,
+                };
+
+                self.server_ids.insert(server_id, id);
+
+                if let Some((signature, commitment)) = status_check {
+                    self.check_signature_status(id, signature, commitment);
+                }
+            }
+            None => {
+                let method = subscription.request.method;
+                let error = serde_json::from_value::<RpcError>(message["error"].take())
+                    .map(|error| error.into_error(method))
+                    .unwrap_or_else(|error| {
+                        KrillError::HttpClient(format!(
+                            "Unable to parse the response for `{method}`. Error: `{error}`"
+                        ))
+                    });
+
+                if let Some(confirmed) = subscription.confirmed.take() {
+                    confirmed.try_send(Err(error)).ok();
+                }
+
+                // Ends the stream
+                self.subscriptions.remove(&id);
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn notify(
+        &mut self,
+        socket: &mut Socket,
+        server_id: u64,
+        result: Value,
+    ) -> Result<(), tungstenite::Error> {
+        let Some(id) = self.server_ids.get(&server_id).copied() else {
+            return Ok(());
+        };
+
+        self.forward(socket, id, result, true).await
+    }
+
+    /// Sends the `result` to the subscription and drops the subscriptions that
+    /// are done. The node cancels single subscriptions it notified itself.
+    async fn forward(
+        &mut self,
+        socket: &mut Socket,
+        id: u64,
+        result: Value,
+        from_node: bool,
+    ) -> Result<(), tungstenite::Error> {
+        let Some(subscription) = self.subscriptions.get_mut(&id) else {
+            return Ok(());
+        };
+
+        let delivered = (subscription.forward)(result);
+        let single = subscription.request.single;
+        let unsubscribe = subscription.request.unsubscribe;
+        let server_id = subscription.server_id;
+
+        if !single && delivered {
+            return Ok(());
+        }
+
+        self.subscriptions.remove(&id);
+
+        match server_id {
+            Some(server_id) => {
+                self.server_ids.remove(&server_id);
+
+                if single && from_node {
+                    return Ok(());
+                }
+
+                self.send_unsubscribe(socket, unsubscribe, server_id).await
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// Notifies the subscription `id` when the transaction already reached `commitment`.
+    /// A failed check is not retried, the subscription keeps waiting for the node.
+    fn check_signature_status(&self, id: u64, signature: Signature, commitment: Commitment) {
+        let rpc = SolanaTxParserUtils::new(self.cluster.clone());
+        let statuses = self.statuses.0.clone();
+
+        tokio::spawn(async move {
+            let Ok(status) = rpc.get_signature_statuses(&[signature], false).await else {
+                return;
+            };
+
+            if let Some(status) = status
+                .into_iter()
+                .flatten()
+                .find(|status| status.is_at(commitment))
+            {
+                let result = json!({
+                    "context": { "slot": status.slot },
+                    "value": { "err": status.err },
+                });
+
+                statuses.send((id, result)).await.ok();
+            }
+        });
+    }
+
+    async fn send_subscribe(
+        &mut self,
+        socket: &mut Socket,
+        id: u64,
+    ) -> Result<(), tungstenite::Error> {
+        let request_id = self.request_id();
+        let Some(subscription) = self.subscriptions.get(&id) else {
+            return Ok(());
+        };
+
+        let body = json!({
+            "jsonrpc": "2.0",
+            "id": request_id,
+            "method": subscription.request.method,
+            "params": subscription.request.params,
+        });
+        self.pending.insert(
+            request_id,
+            PendingRequest::Subscribe {
+                id,
+                unsubscribe: subscription.request.unsubscribe,
+            },
+        );
+
+        socket.send(Message::text(body.to_string())).await
+    }
+
+    async fn send_unsubscribe(
+        &mut self,
+        socket: &mut Socket,
+        method: &str,
+        server_id: u64,
+    ) -> Result<(), tungstenite::Error> {
+        let request_id = self.request_id();
+        self.pending.insert(request_id, PendingRequest::Unsubscribe);
+
+        let body = json!({
+            "jsonrpc": "2.0",
+            "id": request_id,
+            "method": method,
+            "params": [server_id],
+        });
+
+        socket.send(Message::text(body.to_string())).await
+    }
+
+    fn request_id(&mut self) -> u64 {
+        self.next_request_id += 1;
+
+        self.next_request_id
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Mutex;
+
+    use tokio::{
+        io::AsyncWriteExt,
+        net::{TcpListener, TcpStream},
+    };
+
+    use crate::{mock_rpc::MockRpc, TokenInstruction};
+
+    use super::*;
+
+    const ACCOUNT: &str = "HGBUKPxZQGjbKSkt33gngvqfUiPpXUXGhVAU8ddwEbdX";
+    const HOOK_PROGRAM: &str = "8V1yfvQXQXfVhYRWiMtpcz1V6iRL9ftYyuZTYyn8o3ib";
+    const REJECTED: &str = "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v";
+    const TIMEOUT: Duration = Duration::from_secs(5);
+
+    fn pubkey(value: &str) -> Pubkey {
+        Pubkey::from_base58(value).unwrap()
+    }
+
+    /// A request received by [MockPubSub] with the subscription id it answered
+    #[derive(Debug, Clone)]
+    struct MockRequest {
+        method: String,
+        params: Value,
+        subscription: u64,
+    }
+
+    #[derive(Debug)]
+    enum Control {
+        Notify {
+            method: &'static str,
+            subscription: u64,
+            result: Value,
+        },
+        Disconnect,
+    }
+
+    /// A local stand-in for the WebSocket of a node. It confirms each subscription
+    /// with a new id, rejects the accounts `REJECTED`, pushes the notifications
+    /// of the test and drops the connection on demand. HTTP requests on the same
+    /// port are answered with the `getSignatureStatuses` result of the test.
+    struct MockPubSub {
+        cluster: JsonRpcCluster,
+        requests: Arc<Mutex<Vec<MockRequest>>>,
+        signature_statuses: Arc<Mutex<Value>>,
+        control: Sender<Control>,
+    }
+
+    impl MockPubSub {
+        async fn start() -> Self {
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let cluster =
+                JsonRpcCluster::Custom(format!("http://{}", listener.local_addr().unwrap()));
+            let requests = Arc::new(Mutex::new(Vec::<MockRequest>::new()));
+            let signature_statuses = Arc::new(Mutex::new(json!([null])));
+            let (control, controls) = async_channel::unbounded::<Control>();
+
+            let received = requests.clone();
+            let statuses = signature_statuses.clone();
+            tokio::spawn(async move {
+                let next_subscription = Arc::new(Mutex::new(0u64));
+
+                while let Ok((stream, _)) = listener.accept().await {
+                    let mut method = [0u8; 4];
+                    stream.peek(&mut method).await.unwrap();
+
+                    if &method == b"POST" {
+                        let result = statuses.lock().unwrap().clone();
+                        tokio::spawn(Self::answer_http(stream, result, received.clone()));
+
+                        continue;
+                    }
+
+                    let received = received.clone();
+                    let next_subscription = next_subscription.clone();
+                    let controls = controls.clone();
+                    tokio::spawn(async move {
+                        let mut socket = tokio_tungstenite::accept_async(stream).await.unwrap();
+
+                        loop {
+                            let reply = tokio::select! {
+                                message = socket.next() => match message {
+                                    Some(Ok(Message::Text(text))) => Self::answer(
+                                        text.as_str(),
+                                        &mut next_subscription.lock().unwrap(),
+                                        &received,
+                                    ),
+                                    Some(Ok(_)) => continue,
+                                    _ => break,
+                                },
+                                control = controls.recv() => match control {
+                                    Ok(Control::Notify { method, subscription, result }) => json!({
+                                        "jsonrpc": "2.0",
+                                        "method": method,
+                                        "params": { "result": result, "subscription": subscription },
+                                    }),
+                                    Ok(Control::Disconnect) | Err(_) => break,
+                                },
+                            };
+
+                            if socket.send(Message::text(reply.to_string())).await.is_err() {
+                                break;
+                            }
+                        }
+                    });
+                }
+            });
+
+            Self {
+                cluster,
+                requests,
+                signature_statuses,
+                control,
+            }
+        }
+
+        async fn answer_http(
+            mut stream: TcpStream,
+            statuses: Value,
+            received: Arc<Mutex<Vec<MockRequest>>>,
+        ) {
+            let request = MockRpc::read_request(&mut stream).await;
+            received.lock().unwrap().push(MockRequest {
+                method: request["method"].as_str().unwrap_or_default().to_string(),
+                params: request["params"].clone(),
+                subscription: 0,
+            });
+
+            let body = json!({
+                "jsonrpc": "2.0",
+                "result": { "context": { "slot": 9 }, "value": statuses },
+                "id": request["id"],
+            })
+            .to_string();
+            let response = format!(
+                "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{body}",
+                body.len()
+            );
+            stream.write_all(response.as_bytes()).await.ok();
+            stream.shutdown().await.ok();
+        }
+
+        fn answer(
+            text: &str,
+            next_subscription: &mut u64,
+            received: &Mutex<Vec<MockRequest>>,
+        ) -> Value {
+            let request = serde_json::from_str::<Value>(text).unwrap();
+            let method = request["method"].as_str().unwrap().to_string();
+            let id = request["id"].clone();
+
+            let (reply, subscription) = if method.ends_with("Unsubscribe") {
+                (json!({ "jsonrpc": "2.0", "result": true, "id": id }), 0)
+            } else if request["params"][0] == REJECTED {
+                let error = json!({ "code": -32602, "message": "Invalid param" });
+
+                (json!({ "jsonrpc": "2.0", "error": error, "id": id }), 0)
+            } else {
+                *next_subscription += 1;
+
+                (
+                    json!({ "jsonrpc": "2.0", "result": *next_subscription, "id": id }),
+                    *next_subscription,
+                )
+            };
+
+            received.lock().unwrap().push(MockRequest {
+                method,
+                params: request["params"].clone(),
+                subscription,
+            });
+
+            reply
+        }
+
+        async fn notify(&self, method: &'static str, subscription: u64, result: Value) {
+            self.control
+                .send(Control::Notify {
+                    method,
+                    subscription,
+                    result,
+                })
+                .await
+                .unwrap();
+        }
+
+        async fn disconnect(&self) {
+            self.control.send(Control::Disconnect).await.unwrap();
+        }
+
+        /// The requests once there are at least `len` of them
+        async fn requests(&self, len: usize) -> Vec<MockRequest> {
+            tokio::time::timeout(TIMEOUT, async {
+                loop {
+                    let requests = self.requests.lock().unwrap().clone();
+                    if requests.len() >= len {
+                        return requests;
+                    }
+
+                    tokio::time::sleep(Duration::from_millis(10)).await;
+                }
+            })
+            .await
+            .unwrap()
+        }
+    }
+
+    async fn next<T>(subscription: &mut Subscription<T>) -> Option<KrillResult<T>> {
+        tokio::time::timeout(TIMEOUT, subscription.next())
+            .await
+            .unwrap()
+    }
+
+    #[test]
+    fn subscriptions() {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+
+        runtime.block_on(async {
+            let node = MockPubSub::start().await;
+            let pubsub = SolanaPubSub::connect_with(
+                &node.cluster,
+                ReconnectPolicy {
+                    initial_delay: Duration::from_millis(10),
+                    max_delay: Duration::from_millis(100),
+                },
+            )
+            .unwrap();
+
+            let mut account = pubsub
+                .account_subscribe(&pubkey(ACCOUNT), Commitment::Confirmed)
+                .await
+                .unwrap();
+            let mut logs = pubsub
+                .logs_subscribe(
+                    &LogsFilter::Mentions(pubkey(HOOK_PROGRAM)),
+                    Commitment::Processed,
+                )
+                .await
+                .unwrap();
+            let mut signature = pubsub
+                .signature_subscribe(&Signature::default(), Commitment::Finalized)
+                .await
+                .unwrap();
+            let _tokens = pubsub
+                .program_subscribe(
+                    &TokenInstruction::PROGRAM_ID,
+                    &[
+                        ProgramFilter::DataSize(165),
+                        ProgramFilter::Memcmp {
+                            offset: 32,
+                            bytes: pubkey(ACCOUNT).to_bytes().to_vec(),
+                        },
+                    ],
+                    Commitment::Confirmed,
+                )
+                .await
+                .unwrap();
+
+            let requests = node.requests(4).await;
+            assert_eq!(requests[0].method, "accountSubscribe");
+            assert_eq!(
+                requests[0].params,
+                json!([ACCOUNT, { "encoding": "base64", "commitment": "confirmed" }])
+            );
+            assert_eq!(
+                requests[1].params,
+                json!([{ "mentions": [HOOK_PROGRAM] }, { "commitment": "processed" }])
+            );
+            assert_eq!(
+                requests[2].params[1],
+                json!({ "commitment": "finalized", "enableReceivedNotification": false })
+            );
+            assert_eq!(
+                requests[3].params[1]["filters"],
+                json!([
+                    { "dataSize": 165 },
+                    {
+                        "memcmp": {
+                            "offset": 32,
+                            "bytes": Base64::encode_string(pubkey(ACCOUNT).as_bytes()),
+                            "encoding": "base64",
+                        },
+                    },
+                ])
+            );
+
+            node.notify(
+                "accountNotification",
+                requests[0].subscription,
+                json!({
+                    "context": { "slot": 5 },
+                    "value": {
+                        "lamports": 890880,
+                        "owner": "11111111111111111111111111111111",
+                        "data": ["", "base64"],
+                        "executable": false,
+                        "rentEpoch": 18446744073709551615u64,
+                        "space": 0,
+                    },
+                }),
+            )
+            .await;
+            let notification = next(&mut account).await.unwrap().unwrap();
+            assert_eq!(notification.slot, 5);
+            assert_eq!(notification.value.lamports, 890880);
+            assert_eq!(notification.value.owner, Pubkey::default());
+
+            // The node cancels signature subscriptions after their notification
+            node.notify(
+                "signatureNotification",
+                requests[2].subscription,
+                json!({ "context": { "slot": 6 }, "value": { "err": null } }),
+            )
+            .await;
+            assert_eq!(
+                next(&mut signature).await,
+                Some(Ok(Notification {
+                    slot: 6,
+                    value: SignatureResult { err: None },
+                }))
+            );
+            assert_eq!(next(&mut signature).await, None);
+
+            // The account, logs and program subscriptions are sent again on the new connection
+            node.disconnect().await;
+            let requests = node.requests(7).await;
+            let mut resubscribed = requests[4..]
+                .iter()
+                .map(|request| request.method.as_str())
+                .collect::<Vec<&str>>();
+            resubscribed.sort();
+            assert_eq!(
+                resubscribed,
+                ["accountSubscribe", "logsSubscribe", "programSubscribe"]
+            );
+
+            let logs_subscription = requests[4..]
+                .iter()
+                .find(|request| request.method == "logsSubscribe")
+                .unwrap()
+                .subscription;
+            assert!(logs_subscription > requests[3].subscription);
+
+            node.notify(
+                "logsNotification",
+                logs_subscription,
+                json!({
+                    "context": { "slot": 7 },
+                    "value": {
+                        "signature": Signature::default(),
+                        "err": null,
+                        "logs": ["Program log: Instruction: Execute"],
+                    },
+                }),
+            )
+            .await;
+            let notification = next(&mut logs).await.unwrap().unwrap();
+            assert_eq!(notification.slot, 7);
+            assert_eq!(
+                notification.value.logs,
+                ["Program log: Instruction: Execute"]
+            );
+
+            assert!(matches!(
+                pubsub
+                    .account_subscribe(&pubkey(REJECTED), Commitment::Confirmed)
+                    .await,
+                Err(KrillError::SolanaRpc { code: -32602, .. })
+            ));
+
+            // Dropping a stream unsubscribes with the id of the current connection
+            let account_subscription = requests[4..]
+                .iter()
+                .find(|request| request.method == "accountSubscribe")
+                .unwrap()
+                .subscription;
+            drop(account);
+            let requests = node.requests(9).await;
+            assert_eq!(requests[8].method, "accountUnsubscribe");
+            assert_eq!(requests[8].params, json!([account_subscription]));
+        });
+    }
+
+    fn policy() -> ReconnectPolicy {
+        ReconnectPolicy {
+            initial_delay: Duration::from_millis(10),
+            max_delay: Duration::from_millis(100),
+        }
+    }
+
+    #[test]
+    fn connect_requires_runtime() {
+        assert!(matches!(
+            SolanaPubSub::connect(&JsonRpcCluster::Devnet),
+            Err(KrillError::HttpClient(_))
+        ));
+    }
+
+    #[test]
+    fn unparsable_and_lagging_notifications() {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+
+        runtime.block_on(async {
+            let node = MockPubSub::start().await;
+            let pubsub = SolanaPubSub::connect_with(&node.cluster, policy()).unwrap();
+
+            let mut account = pubsub
+                .account_subscribe(&pubkey(ACCOUNT), Commitment::Confirmed)
+                .await
+                .unwrap();
+            let subscription = node.requests(1).await[0].subscription;
+
+            node.notify(
+                "accountNotification",
+                subscription,
+                json!({ "context": { "slot": 5 }, "value": "unexpected" }),
+            )
+            .await;
+            assert!(matches!(
+                next(&mut account).await,
+                Some(Err(KrillError::HttpClient(_)))
+            ));
+
+            // A stream that is not polled ends once it falls too far behind
+            for _ in 0..=Subscription::<Notification<AccountInfo>>::CAPACITY {
+                node.notify(
+                    "accountNotification",
+                    subscription,
+                    json!({ "context": { "slot": 5 }, "value": "unexpected" }),
+                )
+                .await;
+            }
+            let requests = node.requests(2).await;
+            assert_eq!(requests[1].method, "accountUnsubscribe");
+            assert_eq!(requests[1].params, json!([subscription]));
+
+            let mut received = 0;
+            let lagged = loop {
+                match next(&mut account).await {
+                    Some(Err(error)) if error.to_string().contains("behind") => break error,
+                    Some(_) => received += 1,
+                    None => panic!("The stream ended without the lagging error"),
+                }
+            };
+            assert_eq!(
+                received,
+                Subscription::<Notification<AccountInfo>>::CAPACITY
+            );
+            assert!(matches!(lagged, KrillError::HttpClient(_)));
+            assert!(next(&mut account).await.is_none());
+        });
+    }
+
+    #[test]
+    fn signature_confirmed_while_disconnected() {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+
+        runtime.block_on(async {
+            let node = MockPubSub::start().await;
+            let pubsub = SolanaPubSub::connect_with(&node.cluster, policy()).unwrap();
+
+            let mut signature = pubsub
+                .signature_subscribe(&Signature::default(), Commitment::Confirmed)
+                .await
+                .unwrap();
+            node.requests(1).await;
+
+            // The notification is missed while the connection is down
+            *node.signature_statuses.lock().unwrap() = json!([{
+                "slot": 8,
+                "confirmations": null,
+                "err": null,
+                "status": { "Ok": null },
+                "confirmationStatus": "finalized",
+            }]);
+            node.disconnect().await;
+
+            let requests = node.requests(3).await;
+            assert!(requests
+                .iter()
+                .any(|request| request.method == "getSignatureStatuses"));
+
+            assert_eq!(
+                next(&mut signature).await,
+                Some(Ok(Notification {
+                    slot: 8,
+                    value: SignatureResult { err: None },
+                }))
+            );
+            assert_eq!(next(&mut signature).await, None);
+        });
+    }
+}
@@ -0,0 +1,282 @@
+use krill_common::KrillResult;
+use serde::{de::Error, Deserialize, Deserializer};
+use serde_json::json;
+
+use crate::{
+    deserialize_base64, Commitment, Pubkey, Signature, SolanaTxParserUtils, VersionedTransaction,
+    WithContext,
+};
+
+impl SolanaTxParserUtils {
+    /// The statuses in the order of `signatures`, `None` for the signatures the
+    /// node does not know. Without `search_transaction_history` only the
+    /// recent signatures are known.
+    pub async fn get_signature_statuses(
+        &self,
+        signatures: &[Signature],
+        search_transaction_history: bool,
+    ) -> KrillResult<Vec<Option<SignatureStatus>>> {
+        self.call::<WithContext<Vec<Option<SignatureStatus>>>>(
+            "getSignatureStatuses",
+            json!([signatures, {
+                "searchTransactionHistory": search_transaction_history,
+            }]),
+        )
+        .await
+        .map(|result| result.value)
+    }
+
+    /// `None` when the transaction is unknown or not yet at the commitment of
+    /// the client, which cannot be [Commitment::Processed] for this method
+    pub async fn get_transaction(
+        &self,
+        signature: &Signature,
+    ) -> KrillResult<Option<ConfirmedTransaction>> {
+        self.call(
+            "getTransaction",
+            json!([signature, {
+                "encoding": "base64",
+                "commitment": self.commitment(),
+                "maxSupportedTransactionVersion": 0,
+            }]),
+        )
+        .await
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SignatureStatus {
+    pub slot: u64,
+    /// `None` once the block is rooted
+    pub confirmations: Option<u64>,
+    /// Why the transaction failed, it still paid its fee
+    pub err: Option<serde_json::Value>,
+    pub confirmation_status: Option<Commitment>,
+}
+
+impl SignatureStatus {
+    /// Whether the transaction reached `commitment`, failed or not
+    pub fn is_at(&self, commitment: Commitment) -> bool {
+        self.confirmation_status
+            .is_some_and(|confirmation_status| confirmation_status >= commitment)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConfirmedTransaction {
+    pub slot: u64,
+    /// Unix timestamp estimated by the validators
+    pub block_time: Option<i64>,
+    #[serde(deserialize_with = "deserialize_transaction")]
+    pub transaction: VersionedTransaction,
+    pub meta: Option<TransactionStatusMeta>,
+}
+
+impl ConfirmedTransaction {
+    /// The static keys followed by the addresses loaded from lookup tables,
+    /// the order the balances of [TransactionStatusMeta] refer to
+    pub fn account_keys(&self) -> Vec<Pubkey> {
+        let mut account_keys = self.transaction.message.static_account_keys().to_vec();
+
+        if let Some(loaded_addresses) = self
+            .meta
+            .as_ref()
+            .and_then(|meta| meta.loaded_addresses.as_ref())
+        {
+            account_keys.extend_from_slice(&loaded_addresses.writable);
+            account_keys.extend_from_slice(&loaded_addresses.readonly);
+        }
+
+        account_keys
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TransactionStatusMeta {
+    pub err: Option<serde_json::Value>,
+    pub fee: u64,
+    pub pre_balances: Vec<u64>,
+    pub post_balances: Vec<u64>,
+    pub pre_token_balances: Option<Vec<TokenBalance>>,
+    pub post_token_balances: Option<Vec<TokenBalance>>,
+    pub log_messages: Option<Vec<String>>,
+    pub loaded_addresses: Option<LoadedAddresses>,
+    pub compute_units_consumed: Option<u64>,
+}
+
+/// The addresses a v0 transaction loaded from its address lookup tables
+#[derive(Debug, PartialEq, Eq, Clone, Deserialize)]
+pub struct LoadedAddresses {
+    pub writable: Vec<Pubkey>,
+    pub readonly: Vec<Pubkey>,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TokenBalance {
+    /// The index in [ConfirmedTransaction::account_keys]
+    pub account_index: u8,
+    pub mint: Pubkey,
+    pub owner: Option<Pubkey>,
+    pub program_id: Option<Pubkey>,
+    pub ui_token_amount: UiTokenAmount,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UiTokenAmount {
+    /// The amount in base units, a string since it can exceed JSON numbers
+    pub amount: String,
+    pub decimals: u8,
+    pub ui_amount_string: String,
+}
+
+fn deserialize_transaction<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<VersionedTransaction, D::Error> {
+    VersionedTransaction::from_bytes(&deserialize_base64(deserializer)?).map_err(D::Error::custom)
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::Value;
+
+    use crate::mock_rpc::{MockReply, MockRpc};
+
+    use super::*;
+
+    const V0_TOKEN_TRANSFER: &str = include_str!("../fixtures/v0_token_transfer.b64");
+    const LOADED_WRITABLE: &str = "HGBUKPxZQGjbKSkt33gngvqfUiPpXUXGhVAU8ddwEbdX";
+    const LOADED_READONLY: &str = "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v";
+
+    fn confirmed_transaction() -> Value {
+        json!({
+            "slot": 310,
+            "blockTime": 1760000000,
+            "transaction": [V0_TOKEN_TRANSFER.trim(), "base64"],
+            "meta": {
+                "err": null,
+                "fee": 5000,
+                "preBalances": [1000000, 0],
+                "postBalances": [995000, 0],
+                "preTokenBalances": [],
+                "postTokenBalances": [{
+                    "accountIndex": 3,
+                    "mint": LOADED_READONLY,
+                    "owner": LOADED_WRITABLE,
+                    "programId": "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA",
+                    "uiTokenAmount": {
+                        "amount": "1500000",
+                        "decimals": 6,
+                        "uiAmount": 1.5,
+                        "uiAmountString": "1.5",
+                    },
+                }],
+                "logMessages": ["Program log: Instruction: TransferChecked"],
+                "loadedAddresses": {
+                    "writable": [LOADED_WRITABLE],
+                    "readonly": [LOADED_READONLY],
+                },
+                "computeUnitsConsumed": 6200,
+            },
+            "version": 0,
+        })
+    }
+
+    #[test]
+    fn signature_methods() {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let transaction = VersionedTransaction::from_base64(V0_TOKEN_TRANSFER).unwrap();
+        let signature = *transaction.id().unwrap();
+
+        runtime.block_on(async {
+            let known = signature.to_string();
+            let rpc = MockRpc::start(move |method, params| match method {
+                "getSignatureStatuses" => MockReply::Result(json!({
+                    "context": { "slot": 320 },
+                    "value": [
+                        {
+                            "slot": 310,
+                            "confirmations": 10,
+                            "err": null,
+                            "status": { "Ok": null },
+                            "confirmationStatus": "confirmed",
+                        },
+                        null,
+                    ],
+                })),
+                "getTransaction" if params[0] == known.as_str() => {
+                    MockReply::Result(confirmed_transaction())
+                }
+                "getTransaction" => MockReply::Result(Value::Null),
+                _ => MockReply::Error {
+                    code: -32601,
+                    message: "Method not found",
+                },
+            })
+            .await;
+            let client = rpc.client();
+
+            let statuses = client
+                .get_signature_statuses(&[signature, Signature::default()], true)
+                .await
+                .unwrap();
+            let status = statuses[0].as_ref().unwrap();
+            assert_eq!(status.slot, 310);
+            assert_eq!(status.confirmations, Some(10));
+            assert!(status.is_at(Commitment::Processed));
+            assert!(status.is_at(Commitment::Confirmed));
+            assert!(!status.is_at(Commitment::Finalized));
+            assert_eq!(statuses[1], None);
+
+            let confirmed = client.get_transaction(&signature).await.unwrap().unwrap();
+            assert_eq!(confirmed.slot, 310);
+            assert_eq!(confirmed.block_time, Some(1760000000));
+            assert_eq!(confirmed.transaction, transaction);
+
+            let meta = confirmed.meta.as_ref().unwrap();
+            assert_eq!(meta.fee, 5000);
+            assert_eq!(meta.compute_units_consumed, Some(6200));
+            let balance = &meta.post_token_balances.as_ref().unwrap()[0];
+            assert_eq!(balance.ui_token_amount.amount, "1500000");
+            assert_eq!(balance.ui_token_amount.decimals, 6);
+
+            let static_keys = transaction.message.static_account_keys();
+            let account_keys = confirmed.account_keys();
+            assert_eq!(account_keys.len(), static_keys.len() + 2);
+            assert_eq!(account_keys[..static_keys.len()], *static_keys);
+            assert_eq!(
+                account_keys[static_keys.len()..],
+                [
+                    Pubkey::from_base58(LOADED_WRITABLE).unwrap(),
+                    Pubkey::from_base58(LOADED_READONLY).unwrap(),
+                ]
+            );
+
+            assert_eq!(
+                client.get_transaction(&Signature::default()).await,
+                Ok(None)
+            );
+
+            let requests = rpc.requests();
+            assert_eq!(
+                requests[0]["params"],
+                json!([
+                    [signature.to_string(), Signature::default().to_string()],
+                    { "searchTransactionHistory": true },
+                ])
+            );
+            assert_eq!(
+                requests[1]["params"],
+                json!([signature.to_string(), {
+                    "encoding": "base64",
+                    "commitment": "finalized",
+                    "maxSupportedTransactionVersion": 0,
+                }])
+            );
+        });
+    }
+}
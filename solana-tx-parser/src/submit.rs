@@ -0,0 +1,225 @@
+use krill_common::KrillResult;
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::{Blockhash, Signature, SolanaTxParserUtils, VersionedTransaction, WithContext};
+
+impl SolanaTxParserUtils {
+    pub async fn get_latest_blockhash(&self) -> KrillResult<LatestBlockhash> {
+        self.call::<WithContext<LatestBlockhash>>(
+            "getLatestBlockhash",
+            json!([{ "commitment": self.commitment() }]),
+        )
+        .await
+        .map(|result| result.value)
+    }
+
+    /// Sends a signed transaction to the leaders, the commitment of the client
+    /// is the one of the preflight simulation. The signature is returned before
+    /// the transaction lands, see [Self::get_signature_statuses].
+    pub async fn send_transaction(
+        &self,
+        transaction: &VersionedTransaction,
+        config: &SendTransactionConfig,
+    ) -> KrillResult<Signature> {
+        self.call(
+            "sendTransaction",
+            json!([transaction.to_base64()?, {
+                "encoding": "base64",
+                "skipPreflight": config.skip_preflight,
+                "preflightCommitment": self.commitment(),
+                "maxRetries": config.max_retries,
+                "minContextSlot": config.min_context_slot,
+            }]),
+        )
+        .await
+    }
+
+    /// Runs a transaction without sending it, it does not need to be signed
+    /// unless `sig_verify` is set
+    pub async fn simulate_transaction(
+        &self,
+        transaction: &VersionedTransaction,
+        config: &SimulateTransactionConfig,
+    ) -> KrillResult<SimulationResult> {
+        self.call::<WithContext<SimulationResult>>(
+            "simulateTransaction",
+            json!([transaction.to_base64()?, {
+                "encoding": "base64",
+                "commitment": self.commitment(),
+                "sigVerify": config.sig_verify,
+                "replaceRecentBlockhash": config.replace_recent_blockhash,
+            }]),
+        )
+        .await
+        .map(|result| result.value)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LatestBlockhash {
+    pub blockhash: Blockhash,
+    /// The transaction expires once the cluster is past this block height
+    pub last_valid_block_height: u64,
+}
+
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub struct SendTransactionConfig {
+    pub skip_preflight: bool,
+    /// How many times the node forwards the transaction, until it expires when `None`
+    pub max_retries: Option<usize>,
+    /// Rejects the request when the node is behind this slot
+    pub min_context_slot: Option<u64>,
+}
+
+/// `sig_verify` and `replace_recent_blockhash` cannot be both set
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub struct SimulateTransactionConfig {
+    pub sig_verify: bool,
+    /// Simulates with the latest blockhash, for transactions that are not signed yet
+    pub replace_recent_blockhash: bool,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SimulationResult {
+    pub err: Option<serde_json::Value>,
+    pub logs: Option<Vec<String>>,
+    pub units_consumed: Option<u64>,
+    /// The blockhash used when `replace_recent_blockhash` is set
+    pub replacement_blockhash: Option<LatestBlockhash>,
+}
+
+#[cfg(test)]
+mod test {
+    use krill_common::KrillError;
+
+    use crate::{
+        mock_rpc::{MockReply, MockRpc},
+        Commitment,
+    };
+
+    use super::*;
+
+    const LEGACY_TRANSFER: &str = include_str!("../fixtures/legacy_transfer.b64");
+    const BLOCKHASH: &str = "EkSnNWid2cvwEVnVx9aBqawnmiCNiDgp3gUdkDPTKN1N";
+
+    #[test]
+    fn submit_methods() {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let transaction = VersionedTransaction::from_base64(LEGACY_TRANSFER).unwrap();
+        let signature = *transaction.id().unwrap();
+
+        runtime.block_on(async {
+            let rpc = MockRpc::start(move |method, params| match method {
+                "getLatestBlockhash" => MockReply::Result(json!({
+                    "context": { "slot": 2792 },
+                    "value": { "blockhash": BLOCKHASH, "lastValidBlockHeight": 3090 },
+                })),
+                "sendTransaction" if params[1]["skipPreflight"] == true => {
+                    MockReply::Result(json!(signature.to_string()))
+                }
+                "sendTransaction" => MockReply::Error {
+                    code: -32002,
+                    message: "Transaction simulation failed: Blockhash not found",
+                },
+                "simulateTransaction" => MockReply::Result(json!({
+                    "context": { "slot": 2792 },
+                    "value": {
+                        "err": null,
+                        "logs": ["Program 11111111111111111111111111111111 success"],
+                        "accounts": null,
+                        "unitsConsumed": 150,
+                        "returnData": null,
+                        "replacementBlockhash": {
+                            "blockhash": BLOCKHASH,
+                            "lastValidBlockHeight": 3090,
+                        },
+                    },
+                })),
+                _ => MockReply::Http {
+                    status: 429,
+                    body: "Too many requests",
+                },
+            })
+            .await;
+            let mut client = rpc.client();
+            client.set_commitment(Commitment::Confirmed);
+
+            let latest = LatestBlockhash {
+                blockhash: Blockhash::from_base58(BLOCKHASH).unwrap(),
+                last_valid_block_height: 3090,
+            };
+            assert_eq!(client.get_latest_blockhash().await, Ok(latest));
+
+            let config = SendTransactionConfig {
+                skip_preflight: true,
+                max_retries: Some(3),
+                min_context_slot: None,
+            };
+            assert_eq!(
+                client.send_transaction(&transaction, &config).await,
+                Ok(signature)
+            );
+            assert_eq!(
+                client
+                    .send_transaction(&transaction, &SendTransactionConfig::default())
+                    .await,
+                Err(KrillError::SolanaRpc {
+                    method: "sendTransaction".to_string(),
+                    code: -32002,
+                    message: "Transaction simulation failed: Blockhash not found".to_string(),
+                    data: None,
+                })
+            );
+
+            let simulation = client
+                .simulate_transaction(
+                    &transaction,
+                    &SimulateTransactionConfig {
+                        sig_verify: false,
+                        replace_recent_blockhash: true,
+                    },
+                )
+                .await
+                .unwrap();
+            assert_eq!(simulation.err, None);
+            assert_eq!(simulation.units_consumed, Some(150));
+            assert_eq!(simulation.logs.unwrap().len(), 1);
+            assert_eq!(simulation.replacement_blockhash, Some(latest));
+
+            let Err(KrillError::HttpClient(rate_limited)) =
+                client.get_minimum_balance_for_rent_exemption(0).await
+            else {
+                panic!("the rate limited response was parsed");
+            };
+            assert!(rate_limited.contains("`429`"));
+
+            let requests = rpc.requests();
+            assert_eq!(
+                requests[0]["params"],
+                json!([{ "commitment": "confirmed" }])
+            );
+            assert_eq!(
+                requests[1]["params"],
+                json!([LEGACY_TRANSFER.trim(), {
+                    "encoding": "base64",
+                    "skipPreflight": true,
+                    "preflightCommitment": "confirmed",
+                    "maxRetries": 3,
+                    "minContextSlot": null,
+                }])
+            );
+            assert_eq!(
+                requests[3]["params"][1],
+                json!({
+                    "encoding": "base64",
+                    "commitment": "confirmed",
+                    "sigVerify": false,
+                    "replaceRecentBlockhash": true,
+                })
+            );
+        });
+    }
+}
@@ -0,0 +1,497 @@
+use base64ct::{Base64, Encoding};
+use ed25519_dalek::VerifyingKey;
+use krill_common::{KrillError, KrillResult};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    write_short_vec_len, Blockhash, DecodedInstruction, InstructionDecoders, Pubkey, Signature,
+    TransactionAccount, TransactionVersion, VersionedMessage, WireReader,
+};
+
+/// A legacy or v0 transaction as sent to `sendTransaction` and returned by
+/// `getTransaction` with the `base64` encoding
+#[derive(Debug, Default, PartialEq, Eq, Clone, Serialize, Deserialize)]
+pub struct VersionedTransaction {
+    /// One per signer of the message, zeroed for the signers that have not signed yet
+    pub signatures: Vec<Signature>,
+    pub message: VersionedMessage,
+}
+
+impl VersionedTransaction {
+    /// The IPv6 MTU minus the headers, the largest transaction a validator accepts
+    pub const PACKET_DATA_SIZE: usize = 1232;
+
+    pub fn new(message: VersionedMessage) -> Self {
+        Self {
+            signatures: vec![Signature::default(); message.signers().len()],
+            message,
+        }
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> KrillResult<Self> {
+        if bytes.len() > Self::PACKET_DATA_SIZE {
+            return Err(WireReader::malformed(&format!(
+                "the transaction is `{}` bytes, at most `{}` bytes are accepted",
+                bytes.len(),
+                Self::PACKET_DATA_SIZE
+            )));
+        }
+
+        let mut reader = WireReader::new(bytes);
+
+        let signatures_len = reader.read_short_vec_len()?;
+        let signatures = (0..signatures_len)
+            .map(|_| Ok(Signature::new(reader.read_array()?)))
+            .collect::<KrillResult<Vec<_>>>()?;
+
+        let message = VersionedMessage::parse(&mut reader)?;
+        reader.finish()?;
+
+        let transaction = Self {
+            signatures,
+            message,
+        };
+        transaction.sanitize()?;
+
+        Ok(transaction)
+    }
+
+    pub fn from_base64(encoded: &str) -> KrillResult<Self> {
+        let bytes = Base64::decode_vec(encoded.trim()).map_err(|error| {
+            WireReader::malformed(&format!("the transaction is not base64. Error: {error}"))
+        })?;
+
+        Self::from_bytes(&bytes)
+    }
+
+    /// The deprecated encoding some wallets and older RPC methods still use
+    pub fn from_base58(encoded: &str) -> KrillResult<Self> {
+        let bytes = bs58::decode(encoded.trim()).into_vec().map_err(|error| {
+            WireReader::malformed(&format!("the transaction is not base58. Error: {error}"))
+        })?;
+
+        Self::from_bytes(&bytes)
+    }
+
+    pub fn to_bytes(&self) -> KrillResult<Vec<u8>> {
+        let mut buffer = Vec::new();
+
+        write_short_vec_len(&mut buffer, self.signatures.len())?;
+        self.signatures
+            .iter()
+            .for_each(|signature| buffer.extend_from_slice(signature.as_bytes()));
+
+        buffer.extend_from_slice(&self.message.to_bytes()?);
+
+        Ok(buffer)
+    }
+
+    pub fn to_base64(&self) -> KrillResult<String> {
+        Ok(Base64::encode_string(&self.to_bytes()?))
+    }
+
+    pub fn to_base58(&self) -> KrillResult<String> {
+        Ok(bs58::encode(self.to_bytes()?).into_string())
+    }
+
+    /// Checks the message and that there is a signature for each signer
+    pub fn sanitize(&self) -> KrillResult<()> {
+        self.message.sanitize()?;
+
+        let signers_len = self.message.signers().len();
+        if self.signatures.len() != signers_len {
+            return Err(WireReader::malformed(&format!(
+                "the transaction has `{}` signatures but the message requires `{signers_len}`",
+                self.signatures.len()
+            )));
+        }
+
+        Ok(())
+    }
+
+    pub fn version(&self) -> TransactionVersion {
+        self.message.version()
+    }
+
+    /// The first signature, which identifies the transaction on the cluster
+    pub fn id(&self) -> Option<&Signature> {
+        self.signatures
+            .first()
+            .filter(|signature| !signature.is_placeholder())
+    }
+
+    /// Each signer with its signature, `None` for the signers that have not signed yet
+    pub fn signers(&self) -> Vec<TransactionSigner> {
+        self.message
+            .signers()
+            .iter()
+            .zip(&self.signatures)
+            .map(|(pubkey, signature)| TransactionSigner {
+                pubkey: *pubkey,
+                signature: (!signature.is_placeholder()).then_some(*signature),
+            })
+            .collect()
+    }
+
+    pub fn missing_signers(&self) -> Vec<Pubkey> {
+        self.signers()
+            .into_iter()
+            .filter(|signer| signer.signature.is_none())
+            .map(|signer| signer.pubkey)
+            .collect()
+    }
+
+    /// Verifies the signatures that are present. A partially signed transaction
+    /// is valid, see [Self::missing_signers] for the signers it still waits for.
+    pub fn verify_signatures(&self) -> KrillResult<()> {
+        let message = self.message.to_bytes()?;
+
+        for signer in self.signers() {
+            let Some(signature) = signer.signature else {
+                continue;
+            };
+
+            let invalid =
+                || KrillError::InvalidSolanaTransactionSignature(signer.pubkey.to_base58());
+
+            VerifyingKey::from_bytes(signer.pubkey.as_bytes())
+                .map_err(|_| invalid())?
+                .verify_strict(
+                    &message,
+                    &ed25519_dalek::Signature::from_bytes(signature.as_bytes()),
+                )
+                .map_err(|_| invalid())?;
+        }
+
+        Ok(())
+    }
+
+    /// What the transaction does, for the signers to review before they sign,
+    /// with the instructions of the built-in [InstructionDecoders]
+    pub fn explain(&self) -> KrillResult<ExplainedTransaction> {
+        self.explain_with(&InstructionDecoders::default())
+    }
+
+    pub fn explain_with(
+        &self,
+        decoders: &InstructionDecoders,
+    ) -> KrillResult<ExplainedTransaction> {
+        self.sanitize()?;
+
+        let message = &self.message;
+
+        let instructions = message
+            .instructions()
+            .iter()
+            .map(|instruction| {
+                let program_id =
+                    message.static_account_keys()[usize::from(instruction.program_id_index)];
+                let accounts = instruction
+                    .accounts
+                    .iter()
+                    .filter_map(|index| message.account(usize::from(*index)))
+                    .collect::<Vec<_>>();
+
+                ExplainedInstruction {
+                    decoded: decoders.decode(&program_id, &accounts, &instruction.data),
+                    program_id,
+                    accounts,
+                    data: instruction.data.clone(),
+                }
+            })
+            .collect();
+
+        Ok(ExplainedTransaction {
+            version: message.version(),
+            fee_payer: message.static_account_keys()[0],
+            recent_blockhash: *message.recent_blockhash(),
+            signers: self.signers(),
+            accounts: message.accounts(),
+            instructions,
+        })
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
+pub struct TransactionSigner {
+    pub pubkey: Pubkey,
+    pub signature: Option<Signature>,
+}
+
+/// A transaction with the account indexes of its instructions resolved
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct ExplainedTransaction {
+    pub version: TransactionVersion,
+    pub fee_payer: Pubkey,
+    pub recent_blockhash: Blockhash,
+    pub signers: Vec<TransactionSigner>,
+    pub accounts: Vec<TransactionAccount>,
+    pub instructions: Vec<ExplainedInstruction>,
+}
+
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct ExplainedInstruction {
+    pub program_id: Pubkey,
+    pub accounts: Vec<TransactionAccount>,
+    pub data: Vec<u8>,
+    pub decoded: DecodedInstruction,
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        AccountAddress, ComputeBudgetInstruction, MessageHeader, SystemInstruction,
+        TokenInstruction,
+    };
+
+    use super::*;
+
+    const LEGACY_TRANSFER: &str = include_str!("../fixtures/legacy_transfer.b64");
+    const V0_TOKEN_TRANSFER: &str = include_str!("../fixtures/v0_token_transfer.b64");
+
+    fn pubkey(value: &str) -> Pubkey {
+        Pubkey::from_base58(value).unwrap()
+    }
+
+    #[test]
+    fn legacy_transfer() {
+        let transaction = VersionedTransaction::from_base64(LEGACY_TRANSFER).unwrap();
+        let payer = pubkey("8V1yfvQXQXfVhYRWiMtpcz1V6iRL9ftYyuZTYyn8o3ib");
+        let recipient = pubkey("HGBUKPxZQGjbKSkt33gngvqfUiPpXUXGhVAU8ddwEbdX");
+        let system_program = pubkey("11111111111111111111111111111111");
+
+        assert_eq!(transaction.version(), TransactionVersion::Legacy);
+        assert_eq!(
+            transaction.message.header(),
+            &MessageHeader {
+                num_required_signatures: 1,
+                num_readonly_signed_accounts: 0,
+                num_readonly_unsigned_accounts: 1,
+            }
+        );
+        assert_eq!(
+            transaction.message.static_account_keys(),
+            &[payer, recipient, system_program]
+        );
+        assert_eq!(
+            transaction.message.recent_blockhash().to_string(),
+            "7F5zm1g2k89kZGs8QUYQhuStq6XB3X7Wc4TWTUWurPhg"
+        );
+        assert_eq!(transaction.id().unwrap().to_string(), "1mrfs1A4tAjva6PVzCMRN24evbwhjGJ3TFs7fTQ9aBorGyzifuoMut4N79tfrmCR5AcvU1uShHPhwxbVng18cLJ");
+        assert!(transaction.message.address_table_lookups().is_empty());
+        transaction.verify_signatures().unwrap();
+        assert!(transaction.missing_signers().is_empty());
+
+        let explained = transaction.explain().unwrap();
+        assert_eq!(explained.fee_payer, payer);
+        assert_eq!(explained.instructions.len(), 1);
+
+        let transfer = &explained.instructions[0];
+        assert_eq!(transfer.program_id, system_program);
+        assert_eq!(
+            transfer.accounts,
+            vec![
+                TransactionAccount {
+                    address: AccountAddress::Static { pubkey: payer },
+                    is_signer: true,
+                    is_writable: true,
+                },
+                TransactionAccount {
+                    address: AccountAddress::Static { pubkey: recipient },
+                    is_signer: false,
+                    is_writable: true,
+                },
+            ]
+        );
+        assert_eq!(
+            transfer.decoded,
+            DecodedInstruction::System(SystemInstruction::Transfer {
+                from: AccountAddress::Static { pubkey: payer },
+                to: AccountAddress::Static { pubkey: recipient },
+                lamports: 1_000_000,
+            })
+        );
+
+        assert_eq!(transaction.to_base64().unwrap(), LEGACY_TRANSFER.trim());
+        assert_eq!(
+            VersionedTransaction::from_base58(&transaction.to_base58().unwrap()).unwrap(),
+            transaction
+        );
+    }
+
+    #[test]
+    fn v0_token_transfer() {
+        let transaction = VersionedTransaction::from_base64(V0_TOKEN_TRANSFER).unwrap();
+        let payer = pubkey("8V1yfvQXQXfVhYRWiMtpcz1V6iRL9ftYyuZTYyn8o3ib");
+        let authority = pubkey("tczvFU2CTm6KWDcjKFshpLKgBRfDRAWV38SkFRzGC1U");
+        let destination = pubkey("3hyBQRWmcwyjSVfaUk7ETkMukYfoBK8k8QuLQPw6Cgio");
+        let table = pubkey("DBMgBFwz17URnheXwJu4qiV9hcBCv9g21SioJ2h3VbUd");
+
+        assert_eq!(transaction.version(), TransactionVersion::V0);
+        assert_eq!(transaction.signatures.len(), 2);
+        assert_eq!(transaction.message.signers(), &[payer, authority]);
+        assert_eq!(transaction.message.accounts_len(), 7);
+        transaction.verify_signatures().unwrap();
+
+        let lookups = transaction.message.address_table_lookups();
+        assert_eq!(lookups.len(), 1);
+        assert_eq!(lookups[0].account_key, table);
+        assert_eq!(lookups[0].writable_indexes, vec![3]);
+        assert_eq!(lookups[0].readonly_indexes, vec![7]);
+
+        let explained = transaction.explain().unwrap();
+        assert_eq!(explained.instructions.len(), 3);
+        assert_eq!(
+            explained.instructions[0].program_id,
+            pubkey("ComputeBudget111111111111111111111111111111")
+        );
+        assert!(explained.instructions[0].accounts.is_empty());
+        assert_eq!(
+            explained.instructions[0].decoded,
+            DecodedInstruction::ComputeBudget(ComputeBudgetInstruction::SetComputeUnitLimit {
+                units: 200_000
+            })
+        );
+        assert_eq!(
+            explained.instructions[1].decoded,
+            DecodedInstruction::ComputeBudget(ComputeBudgetInstruction::SetComputeUnitPrice {
+                micro_lamports: 5_000
+            })
+        );
+
+        let transfer = &explained.instructions[2];
+        assert_eq!(
+            transfer.program_id,
+            pubkey("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA")
+        );
+        assert_eq!(
+            transfer.accounts,
+            vec![
+                TransactionAccount {
+                    address: AccountAddress::Lookup { table, index: 3 },
+                    is_signer: false,
+                    is_writable: true,
+                },
+                TransactionAccount {
+                    address: AccountAddress::Lookup { table, index: 7 },
+                    is_signer: false,
+                    is_writable: false,
+                },
+                TransactionAccount {
+                    address: AccountAddress::Static {
+                        pubkey: destination
+                    },
+                    is_signer: false,
+                    is_writable: true,
+                },
+                TransactionAccount {
+                    address: AccountAddress::Static { pubkey: authority },
+                    is_signer: true,
+                    is_writable: false,
+                },
+            ]
+        );
+
+        assert_eq!(
+            transfer.decoded,
+            DecodedInstruction::Token(TokenInstruction::TransferChecked {
+                source: AccountAddress::Lookup { table, index: 3 },
+                mint: AccountAddress::Lookup { table, index: 7 },
+                destination: AccountAddress::Static {
+                    pubkey: destination
+                },
+                authority: AccountAddress::Static { pubkey: authority },
+                signers: Vec::default(),
+                amount: 2_500_000,
+                decimals: 6,
+            })
+        );
+
+        assert_eq!(transaction.to_base64().unwrap(), V0_TOKEN_TRANSFER.trim());
+
+        let message = transaction.message.to_bytes().unwrap();
+        assert_eq!(message[0], VersionedMessage::VERSION_PREFIX);
+        assert_eq!(
+            VersionedMessage::from_bytes(&message).unwrap(),
+            transaction.message
+        );
+
+        let json = serde_json::to_string(&transaction).unwrap();
+        assert_eq!(
+            serde_json::from_str::<VersionedTransaction>(&json).unwrap(),
+            transaction
+        );
+    }
+
+    #[test]
+    fn partially_signed() {
+        let mut transaction = VersionedTransaction::from_base64(V0_TOKEN_TRANSFER).unwrap();
+        let authority = transaction.message.signers()[1];
+
+        transaction.signatures[1] = Signature::default();
+        assert!(transaction.id().is_some());
+        assert_eq!(transaction.missing_signers(), vec![authority]);
+        transaction.verify_signatures().unwrap();
+
+        let unsigned = VersionedTransaction::new(transaction.message.clone());
+        assert!(unsigned.id().is_none());
+        assert_eq!(unsigned.missing_signers().len(), 2);
+        assert_eq!(
+            VersionedTransaction::from_bytes(&unsigned.to_bytes().unwrap()).unwrap(),
+            unsigned
+        );
+
+        // The fee payer signature over a message that was changed after signing
+        let mut tampered = transaction.clone();
+        if let VersionedMessage::V0(message) = &mut tampered.message {
+            message.instructions[2].data[1] ^= 1;
+        }
+        assert_eq!(
+            tampered.verify_signatures(),
+            Err(KrillError::InvalidSolanaTransactionSignature(
+                transaction.message.signers()[0].to_base58()
+            ))
+        );
+    }
+
+    #[test]
+    fn malformed_transactions() {
+        let bytes = Base64::decode_vec(LEGACY_TRANSFER.trim()).unwrap();
+
+        // Truncated, with trailing bytes and over the packet size
+        assert!(VersionedTransaction::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+        assert!(VersionedTransaction::from_bytes(&[bytes.as_slice(), &[0]].concat()).is_err());
+        assert!(VersionedTransaction::from_bytes(&[0u8; 1233]).is_err());
+
+        // A signature count that does not match the header
+        let mut extra_signature = vec![2];
+        extra_signature.extend_from_slice(&[0u8; 64]);
+        extra_signature.extend_from_slice(&bytes[1..]);
+        assert!(VersionedTransaction::from_bytes(&extra_signature).is_err());
+
+        // Version 1 messages do not exist yet
+        let mut message = Base64::decode_vec(V0_TOKEN_TRANSFER.trim()).unwrap();
+        message[1 + 2 * Signature::LEN] = VersionedMessage::VERSION_PREFIX | 1;
+        assert!(VersionedTransaction::from_bytes(&message).is_err());
+
+        // The program of the transfer pointing at the fee payer
+        let transaction = VersionedTransaction::from_bytes(&bytes).unwrap();
+        let mut invalid = transaction.message.clone();
+        if let VersionedMessage::Legacy(message) = &mut invalid {
+            message.instructions[0].program_id_index = 0;
+        }
+        assert!(invalid.sanitize().is_err());
+
+        let mut invalid = transaction.message.clone();
+        if let VersionedMessage::Legacy(message) = &mut invalid {
+            message.instructions[0].accounts.push(3);
+        }
+        assert!(invalid.sanitize().is_err());
+
+        let mut invalid = transaction.message;
+        if let VersionedMessage::Legacy(message) = &mut invalid {
+            message.account_keys[1] = message.account_keys[0];
+        }
+        assert!(invalid.sanitize().is_err());
+    }
+}
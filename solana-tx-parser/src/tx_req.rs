@@ -2,7 +2,7 @@ use std::sync::LazyLock;
 
 use krill_common::{KrillError, KrillResult};
 use reqwest::RequestBuilder;
-use serde::Deserialize;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 
 #[allow(clippy::redundant_closure)]
 static SOLANA_REQWEST_CLIENT: LazyLock<reqwest::Client> = LazyLock::new(|| reqwest::Client::new());
@@ -12,6 +12,7 @@ pub struct SolanaTxParserUtils {
     uri: JsonRpcCluster,
     method: String,
     body: jzon::JsonValue,
+    commitment: Commitment,
 }
 
 impl SolanaTxParserUtils {
@@ -20,9 +21,21 @@ impl SolanaTxParserUtils {
             uri,
             method: "getVersion".to_string(),
             body: jzon::array![],
+            commitment: Commitment::default(),
         }
     }
 
+    /// The commitment of the typed methods, [Commitment::Finalized] by default
+    pub fn set_commitment(&mut self, commitment: Commitment) -> &mut Self {
+        self.commitment = commitment;
+
+        self
+    }
+
+    pub fn commitment(&self) -> Commitment {
+        self.commitment
+    }
+
     pub fn set_method(&mut self, method: &str) -> &mut Self {
         self.method = method.to_string();
 
@@ -86,28 +99,112 @@ impl SolanaTxParserUtils {
                 KrillError::HttpClient(format!("HTTP ERROR: The result of `{}` Solana JSONRPC method is not a JSON string. Error: {}", self.method(),error))
             })
     }
+
+    /// Sends `method` with `params` and parses the `result` of the response.
+    /// The `error` of the response is returned as [KrillError::SolanaRpc].
+    pub async fn call<T: DeserializeOwned>(
+        &self,
+        method: &str,
+        params: serde_json::Value,
+    ) -> KrillResult<T> {
+        let body = serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": method,
+            "params": params,
+        });
+
+        let response = self
+            .get_client()
+            .body(body.to_string())
+            .send()
+            .await
+            .map_err(|error| KrillError::HttpClient(error.to_string()))?;
+        let status = response.status().as_u16();
+        let response_text = response.text().await.map_err(|error| {
+            KrillError::HttpClient(format!(
+                "The response for `{method}` is not a string. Error: `{error}`"
+            ))
+        })?;
+
+        RpcResponse::<T>::parse(method, status, &response_text)
+    }
 }
 
-#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Deserialize)]
+/// How much of the cluster has voted on the state a method reads
+#[derive(Debug, Default, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Commitment {
+    /// The latest block of the node, it can still be skipped
+    Processed,
+    /// Voted on by a supermajority of the cluster
+    Confirmed,
+    /// Rooted, it can no longer be rolled back
+    #[default]
+    Finalized,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Deserialize)]
 pub struct RpcResponse<T> {
     pub jsonrpc: String,
     pub result: Option<T>,
-    pub id: u8,
+    /// `null` when the node could not parse the request
+    pub id: Option<u8>,
     pub error: Option<RpcError>,
 }
 
-#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Deserialize)]
+impl<T: DeserializeOwned> RpcResponse<T> {
+    /// Parses the body of a response to `method`. Rate limits and outages
+    /// usually come with a body that is not JSON-RPC, hence the HTTP `status`.
+    pub fn parse(method: &str, status: u16, response_text: &str) -> KrillResult<T> {
+        serde_json::from_str::<Self>(response_text)
+            .map_err(|error| {
+                KrillError::HttpClient(format!(
+                    "Unable to parse the response for `{method}` with the HTTP status `{status}`. Error: `{error}`"
+                ))
+            })?
+            .into_result(method)
+    }
+
+    pub fn into_result(self, method: &str) -> KrillResult<T> {
+        if let Some(error) = self.error {
+            return Err(error.into_error(method));
+        }
+
+        match self.result {
+            Some(result) => Ok(result),
+            // `null` is the result of `getTransaction` for an unknown signature
+            None => serde_json::from_value(serde_json::Value::Null).map_err(|_| {
+                KrillError::HttpClient(format!("The response for `{method}` has no result"))
+            }),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Deserialize)]
 pub struct RpcError {
     pub code: i64,
     pub message: String,
-    pub data: Option<String>,
+    /// The simulation of a transaction that fails its preflight checks, for example
+    #[serde(default)]
+    pub data: Option<serde_json::Value>,
 }
 
-#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Deserialize)]
-#[serde(rename_all = "kebab-case")]
-pub struct RpcVersionInfo {
-    pub solana_core: String,
-    pub feature_set: Option<u32>,
+impl RpcError {
+    pub fn into_error(self, method: &str) -> KrillError {
+        KrillError::SolanaRpc {
+            method: method.to_string(),
+            code: self.code,
+            message: self.message,
+            data: self.data.map(|data| data.to_string()),
+        }
+    }
+}
+
+/// The `{ context, value }` result of the methods that read the state at a slot
+#[derive(Debug, PartialEq, Eq, Clone, Deserialize)]
+pub(crate) struct WithContext<T> {
+    pub(crate) value: T,
 }
 
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone)]
@@ -125,4 +222,103 @@ impl JsonRpcCluster {
             Self::Custom(uri) => uri.as_str(),
         }
     }
+
+    /// The PubSub endpoint, the same host with the `ws` or `wss` scheme
+    pub fn websocket_uri(&self) -> String {
+        let uri = self.as_str();
+
+        if let Some(rest) = uri.strip_prefix("https://") {
+            format!("wss://{rest}")
+        } else if let Some(rest) = uri.strip_prefix("http://") {
+            format!("ws://{rest}")
+        } else {
+            uri.to_string()
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn rpc_errors() {
+        assert_eq!(
+            RpcResponse::<WithContext<u64>>::parse(
+                "getBalance",
+                200,
+                r#"{"jsonrpc":"2.0","result":{"context":{"slot":1},"value":5000},"id":1}"#
+            )
+            .map(|result| result.value),
+            Ok(5000)
+        );
+
+        // Preflight failures carry the simulation in `data`
+        let preflight = r#"{"jsonrpc":"2.0","error":{"code":-32002,"message":"Transaction simulation failed: Blockhash not found","data":{"err":"BlockhashNotFound","logs":[]}},"id":1}"#;
+        assert_eq!(
+            RpcResponse::<String>::parse("sendTransaction", 200, preflight),
+            Err(KrillError::SolanaRpc {
+                method: "sendTransaction".to_string(),
+                code: -32002,
+                message: "Transaction simulation failed: Blockhash not found".to_string(),
+                data: Some(r#"{"err":"BlockhashNotFound","logs":[]}"#.to_string()),
+            })
+        );
+
+        let parse_error =
+            r#"{"jsonrpc":"2.0","error":{"code":-32700,"message":"Parse error"},"id":null}"#;
+        assert!(matches!(
+            RpcResponse::<u64>::parse("getBalance", 200, parse_error),
+            Err(KrillError::SolanaRpc { code: -32700, .. })
+        ));
+
+        let Err(KrillError::HttpClient(rate_limited)) =
+            RpcResponse::<u64>::parse("getBalance", 429, "Too many requests")
+        else {
+            panic!("a body that is not JSON-RPC was parsed");
+        };
+        assert!(rate_limited.contains("`429`"));
+
+        // A `null` result is only valid when it is expected
+        let null = r#"{"jsonrpc":"2.0","result":null,"id":1}"#;
+        assert_eq!(
+            RpcResponse::<Option<u64>>::parse("getTransaction", 200, null),
+            Ok(None)
+        );
+        assert!(matches!(
+            RpcResponse::<u64>::parse("getBalance", 200, null),
+            Err(KrillError::HttpClient(_))
+        ));
+    }
+
+    #[test]
+    fn commitment() {
+        assert_eq!(
+            serde_json::to_value(Commitment::Confirmed).unwrap(),
+            "confirmed"
+        );
+        assert!(Commitment::Finalized > Commitment::Confirmed);
+        assert!(Commitment::Confirmed > Commitment::Processed);
+
+        let mut client = SolanaTxParserUtils::new(JsonRpcCluster::Devnet);
+        assert_eq!(client.commitment(), Commitment::Finalized);
+        client.set_commitment(Commitment::Processed);
+        assert_eq!(client.commitment(), Commitment::Processed);
+    }
+
+    #[test]
+    fn websocket_uri() {
+        assert_eq!(
+            JsonRpcCluster::Devnet.websocket_uri(),
+            "wss://api.devnet.solana.com"
+        );
+        assert_eq!(
+            JsonRpcCluster::Custom("http://127.0.0.1:8899".to_string()).websocket_uri(),
+            "ws://127.0.0.1:8899"
+        );
+        assert_eq!(
+            JsonRpcCluster::Custom("wss://rpc.example/?api-key=key".to_string()).websocket_uri(),
+            "wss://rpc.example/?api-key=key"
+        );
+    }
 }
@@ -0,0 +1,162 @@
+use krill_common::{KrillError, KrillResult};
+
+/// Reads the fields of a transaction in the order they are serialized on the wire
+#[derive(Debug)]
+pub(crate) struct WireReader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> WireReader<'a> {
+    pub(crate) fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes, position: 0 }
+    }
+
+    pub(crate) fn peek_u8(&self) -> KrillResult<u8> {
+        self.bytes
+            .get(self.position)
+            .copied()
+            .ok_or_else(|| Self::malformed("unexpected end of the transaction"))
+    }
+
+    pub(crate) fn read_u8(&mut self) -> KrillResult<u8> {
+        let byte = self.peek_u8()?;
+        self.position += 1;
+
+        Ok(byte)
+    }
+
+    pub(crate) fn read_bytes(&mut self, len: usize) -> KrillResult<&'a [u8]> {
+        let end = self
+            .position
+            .checked_add(len)
+            .filter(|end| *end <= self.bytes.len())
+            .ok_or_else(|| Self::malformed("unexpected end of the transaction"))?;
+
+        let bytes = &self.bytes[self.position..end];
+        self.position = end;
+
+        Ok(bytes)
+    }
+
+    pub(crate) fn read_array<const N: usize>(&mut self) -> KrillResult<[u8; N]> {
+        let mut array = [0u8; N];
+        array.copy_from_slice(self.read_bytes(N)?);
+
+        Ok(array)
+    }
+
+    /// Reads the compact-u16 length prefix of a list, which Solana calls a `short_vec`.
+    /// Each byte holds 7 bits of the length and the high bit marks that another byte follows.
+    pub(crate) fn read_short_vec_len(&mut self) -> KrillResult<usize> {
+        let mut len = 0usize;
+
+        for nth in 0..SHORT_VEC_MAX_BYTES {
+            let byte = self.read_u8()?;
+            len |= usize::from(byte & 0x7f) << (nth * 7);
+
+            if byte & 0x80 == 0 {
+                // A zero after a continuation byte means the length fits in fewer bytes
+                if nth > 0 && byte == 0 {
+                    return Err(Self::malformed(
+                        "the length of a list is not encoded canonically",
+                    ));
+                }
+
+                if len > usize::from(u16::MAX) {
+                    return Err(Self::malformed("the length of a list overflows a u16"));
+                }
+
+                return Ok(len);
+            }
+        }
+
+        Err(Self::malformed("the length of a list overflows a u16"))
+    }
+
+    pub(crate) fn read_short_vec(&mut self) -> KrillResult<&'a [u8]> {
+        let len = self.read_short_vec_len()?;
+
+        self.read_bytes(len)
+    }
+
+    pub(crate) fn finish(&self) -> KrillResult<()> {
+        if self.position != self.bytes.len() {
+            return Err(Self::malformed(&format!(
+                "`{}` unexpected bytes after the transaction",
+                self.bytes.len() - self.position
+            )));
+        }
+
+        Ok(())
+    }
+
+    pub(crate) fn malformed(reason: &str) -> KrillError {
+        KrillError::MalformedSolanaTransaction(reason.to_string())
+    }
+}
+
+/// A `u16` takes at most 3 bytes of 7 bits
+const SHORT_VEC_MAX_BYTES: usize = 3;
+
+pub(crate) fn write_short_vec_len(buffer: &mut Vec<u8>, len: usize) -> KrillResult<()> {
+    let mut len = u16::try_from(len)
+        .map_err(|_| WireReader::malformed("a list is longer than `u16::MAX` items"))?;
+
+    loop {
+        let byte = (len & 0x7f) as u8;
+        len >>= 7;
+
+        if len == 0 {
+            buffer.push(byte);
+
+            return Ok(());
+        }
+
+        buffer.push(byte | 0x80);
+    }
+}
+
+pub(crate) fn write_short_vec(buffer: &mut Vec<u8>, bytes: &[u8]) -> KrillResult<()> {
+    write_short_vec_len(buffer, bytes.len())?;
+    buffer.extend_from_slice(bytes);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn short_vec_lengths() {
+        for (len, encoded) in [
+            (0usize, vec![0x00]),
+            (0x7f, vec![0x7f]),
+            (0x80, vec![0x80, 0x01]),
+            (0x3fff, vec![0xff, 0x7f]),
+            (0x4000, vec![0x80, 0x80, 0x01]),
+            (0xffff, vec![0xff, 0xff, 0x03]),
+        ] {
+            let mut buffer = Vec::new();
+            write_short_vec_len(&mut buffer, len).unwrap();
+            assert_eq!(buffer, encoded);
+
+            let mut reader = WireReader::new(&encoded);
+            assert_eq!(reader.read_short_vec_len().unwrap(), len);
+            reader.finish().unwrap();
+        }
+
+        assert!(write_short_vec_len(&mut Vec::new(), 0x10000).is_err());
+
+        for invalid in [
+            vec![0x80, 0x00],
+            vec![0x80, 0x80, 0x00],
+            vec![0x80, 0x80, 0x04],
+            vec![0x80, 0x80, 0x80, 0x01],
+            vec![0x80],
+        ] {
+            assert!(WireReader::new(&invalid).read_short_vec_len().is_err());
+        }
+    }
+}